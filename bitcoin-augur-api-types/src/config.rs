@@ -0,0 +1,146 @@
+use bitcoin_augur::{FeeEstimator, BUCKET_MAX, BUCKET_SCALE};
+use serde::{Deserialize, Serialize};
+
+/// Response of GET /config/estimator: what the server estimates and how, so clients can
+/// discover its targets and confidence levels instead of assuming the defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EstimatorConfigResponse {
+    /// Version of the server
+    pub server_version: String,
+
+    /// Confidence levels estimated for each target, ascending
+    pub probabilities: Vec<f64>,
+
+    /// Block targets `/fees` returns, ascending, including fast targets
+    pub block_targets: Vec<f64>,
+
+    /// Set when the 1 and 2 block targets are estimated
+    pub fast_targets: bool,
+
+    /// Short-term inflow window of targets without target windows, in seconds
+    pub short_term_window_secs: i64,
+
+    /// Long-term inflow window of targets without target windows, in seconds
+    pub long_term_window_secs: i64,
+
+    /// Inflow windows overriding the default pair for ranges of targets
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_windows: Vec<TargetWindowsResponse>,
+
+    /// Highest fee rate any estimate may ask for, in sat/vB, when capped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_rate: Option<f64>,
+
+    /// Smallest relative step between the fee rates of adjacent confidence levels, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_spread: Option<f64>,
+
+    /// Block arrival sequences sampled per target, when estimates come from the Monte Carlo
+    /// simulation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monte_carlo_samples: Option<usize>,
+
+    /// How fee rates are grouped into buckets
+    pub buckets: BucketConfigResponse,
+}
+
+/// Inflow windows of the block targets from `min_target` to `max_target`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetWindowsResponse {
+    pub min_target: f64,
+    pub max_target: f64,
+    pub short_term_window_secs: i64,
+    pub long_term_window_secs: i64,
+}
+
+/// Fee rate bucketing: a fee rate falls in bucket `round(ln(fee_rate) * scale)`, capped at
+/// `max_index`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketConfigResponse {
+    pub scale: f64,
+    pub max_index: i32,
+}
+
+impl EstimatorConfigResponse {
+    /// Describes `estimator` as run by a server of version `server_version`
+    pub fn new(estimator: &FeeEstimator, server_version: &str) -> Self {
+        let mut block_targets = estimator.block_targets().to_vec();
+        if estimator.has_fast_targets() {
+            block_targets.extend(
+                FeeEstimator::FAST_BLOCK_TARGETS
+                    .iter()
+                    .filter(|target| !estimator.block_targets().contains(target)),
+            );
+        }
+        block_targets.sort_by(f64::total_cmp);
+        let mut probabilities = estimator.probabilities().to_vec();
+        probabilities.sort_by(f64::total_cmp);
+
+        Self {
+            server_version: server_version.to_string(),
+            probabilities,
+            block_targets,
+            fast_targets: estimator.has_fast_targets(),
+            short_term_window_secs: estimator.short_term_window().num_seconds(),
+            long_term_window_secs: estimator.long_term_window().num_seconds(),
+            target_windows: estimator
+                .target_windows()
+                .iter()
+                .map(|windows| TargetWindowsResponse {
+                    min_target: windows.min_target(),
+                    max_target: windows.max_target(),
+                    short_term_window_secs: windows.short_term().num_seconds(),
+                    long_term_window_secs: windows.long_term().num_seconds(),
+                })
+                .collect(),
+            max_fee_rate: estimator.max_fee_rate(),
+            min_spread: estimator.min_spread(),
+            monte_carlo_samples: estimator
+                .monte_carlo()
+                .map(|monte_carlo| monte_carlo.samples()),
+            buckets: BucketConfigResponse {
+                scale: BUCKET_SCALE,
+                max_index: BUCKET_MAX,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::MonteCarlo;
+
+    #[test]
+    fn test_estimator_config() {
+        let config = EstimatorConfigResponse::new(&FeeEstimator::new(), "1.2.3");
+        assert_eq!(config.server_version, "1.2.3");
+        assert_eq!(config.probabilities, FeeEstimator::DEFAULT_PROBABILITIES);
+        assert_eq!(config.block_targets, FeeEstimator::DEFAULT_BLOCK_TARGETS);
+        assert!(!config.fast_targets);
+        assert_eq!(config.short_term_window_secs, 30 * 60);
+        assert_eq!(config.long_term_window_secs, 24 * 60 * 60);
+        assert_eq!(config.buckets.max_index, 10000);
+
+        // Fast targets are sorted in with the others
+        let config =
+            EstimatorConfigResponse::new(&FeeEstimator::new().with_fast_targets(), "1.2.3");
+        assert_eq!(config.block_targets[..3], [1.0, 2.0, 3.0]);
+        assert_eq!(
+            config.block_targets.len(),
+            FeeEstimator::DEFAULT_BLOCK_TARGETS.len() + 2
+        );
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert!(json.get("target_windows").is_none());
+        assert!(json.get("max_fee_rate").is_none());
+        assert!(json.get("monte_carlo_samples").is_none());
+
+        let monte_carlo = MonteCarlo::new(200, 1).unwrap();
+        let config = EstimatorConfigResponse::new(
+            &FeeEstimator::new().with_monte_carlo(monte_carlo),
+            "1.2.3",
+        );
+        assert_eq!(config.monte_carlo_samples, Some(200));
+    }
+}
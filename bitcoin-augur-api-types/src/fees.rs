@@ -0,0 +1,450 @@
+use bitcoin_augur::{BlockTarget, BlockTargetKey, FeeEstimate};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Virtual size of the typical transaction fiat costs are quoted for: one input and two outputs,
+/// all P2WPKH
+pub const STANDARD_TX_VSIZE: u64 = 140;
+
+/// Satoshis per bitcoin
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// Response format for fee estimation API matching Kotlin implementation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeEstimateResponse {
+    /// ISO 8601 formatted timestamp of when the mempool was last updated
+    #[serde(rename = "mempool_update_time")]
+    pub mempool_update_time: String,
+
+    /// Map of block targets to their probability estimates
+    pub estimates: BTreeMap<String, BlockTargetResponse>,
+
+    /// Until when the whole response can be cached: the earliest expiry of any block target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
+
+    /// Set while the estimation window has gaps in its snapshot history
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub degraded: bool,
+
+    /// Seconds since the latest mempool snapshot was taken, on current estimates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_age_seconds: Option<u64>,
+
+    /// Price the fiat costs were computed with, when the request asked for a currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatQuote>,
+}
+
+/// Bitcoin price and transaction size behind the fiat costs of a response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FiatQuote {
+    /// Currency code such as `USD`
+    pub currency: String,
+    /// Price of one bitcoin in the currency
+    pub btc_price: f64,
+    /// Virtual size of the transaction each fiat cost is for
+    pub tx_vsize: u64,
+}
+
+impl FeeEstimateResponse {
+    /// Adds the expiry time of every block target in `valid_until`, and of the response
+    pub fn with_valid_until(
+        mut self,
+        valid_until: &BTreeMap<BlockTargetKey, DateTime<Utc>>,
+    ) -> Self {
+        for (target_key, &time) in valid_until {
+            if let Some(target) = self.estimates.get_mut(&target_key.to_string()) {
+                target.valid_until = Some(format_timestamp(time));
+            }
+        }
+        self.valid_until = valid_until
+            .values()
+            .min()
+            .map(|&time| format_timestamp(time));
+        self
+    }
+
+    /// Adds the approximate cost of a [`STANDARD_TX_VSIZE`] transaction at every fee rate, in
+    /// `currency` at `btc_price`
+    pub fn with_fiat(mut self, currency: &str, btc_price: f64) -> Self {
+        for target in self.estimates.values_mut() {
+            for probability in target.probabilities.values_mut() {
+                let sats = probability.fee_rate * STANDARD_TX_VSIZE as f64;
+                probability.fiat_cost = Some(format_fiat(sats / SATS_PER_BTC * btc_price));
+            }
+        }
+        self.fiat = Some(FiatQuote {
+            currency: currency.to_uppercase(),
+            btc_price,
+            tx_vsize: STANDARD_TX_VSIZE,
+        });
+        self
+    }
+
+    /// Whether the response has any block target
+    pub fn has_estimates(&self) -> bool {
+        !self.estimates.is_empty()
+    }
+
+    /// Estimates for a block target, keyed like the response
+    pub fn get_target(&self, blocks: usize) -> Option<&BlockTargetResponse> {
+        self.estimates.get(&blocks.to_string())
+    }
+}
+
+impl From<FeeEstimate> for FeeEstimateResponse {
+    fn from(estimate: FeeEstimate) -> Self {
+        transform_fee_estimate(estimate)
+    }
+}
+
+/// Response of GET /fees/compare: the augur estimate next to the baseline strategies' estimates
+/// from the same snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeComparisonResponse {
+    /// The estimate served at GET /fees
+    pub augur: FeeEstimateResponse,
+
+    /// Estimates of each baseline strategy, keyed by its name such as `top-vmb` or `core`
+    pub baselines: BTreeMap<String, FeeEstimateResponse>,
+}
+
+/// Response of GET /fees/sources: estimates made from each snapshot source's snapshots alone,
+/// next to the median across sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceComparisonResponse {
+    /// Median of the sources' fee rates at each block target and confidence level
+    pub combined: FeeEstimateResponse,
+
+    /// Estimates of each source, keyed by its label such as `node-a` or `unlabeled`
+    pub sources: BTreeMap<String, FeeEstimateResponse>,
+
+    /// Largest relative difference between each source's fee rates and the combined ones
+    pub deviations: BTreeMap<String, f64>,
+}
+
+/// Block target with probability-based fee estimates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockTargetResponse {
+    /// Map of probability percentages to fee rates
+    pub probabilities: BTreeMap<String, ProbabilityResponse>,
+
+    /// Until when this target's estimate can be cached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
+
+    /// Set on 1 and 2 block targets, whose estimates are far less reliable than longer ones
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub high_variance: bool,
+}
+
+impl BlockTargetResponse {
+    /// Fee rate for `probability`, looked up with the two decimal places it is keyed by
+    pub fn get_fee_rate(&self, probability: f64) -> Option<f64> {
+        self.probabilities
+            .get(&format!("{probability:.2}"))
+            .map(|p| p.fee_rate)
+    }
+}
+
+/// Fee rate for a specific probability
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProbabilityResponse {
+    /// Fee rate in satoshis per virtual byte
+    #[serde(rename = "fee_rate")]
+    pub fee_rate: f64,
+
+    /// Set when the fee rate was clamped to the configured maximum fee rate
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub capped: bool,
+
+    /// Set when the fee rate was raised to keep the configured minimum spread above the next
+    /// lower confidence level
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub widened: bool,
+
+    /// Approximate cost of a [`STANDARD_TX_VSIZE`] transaction at this fee rate, in the
+    /// currency of the response's [`FiatQuote`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiat_cost: Option<f64>,
+}
+
+/// Transform internal FeeEstimate to API response format
+pub fn transform_fee_estimate(estimate: FeeEstimate) -> FeeEstimateResponse {
+    let estimates = estimate
+        .estimates
+        .into_iter()
+        .map(|(blocks, target)| (BlockTargetKey::from(blocks), target))
+        .chain(estimate.fractional_estimates)
+        .map(|(block_num, target)| {
+            let block_key = block_num.to_string();
+            let high_variance = target.is_high_variance();
+            let probabilities = transform_block_target(target);
+            let target = BlockTargetResponse {
+                probabilities,
+                valid_until: None,
+                high_variance,
+            };
+            (block_key, target)
+        })
+        .collect();
+
+    FeeEstimateResponse {
+        mempool_update_time: format_timestamp(estimate.timestamp),
+        estimates,
+        valid_until: None,
+        degraded: false,
+        data_age_seconds: None,
+        fiat: None,
+    }
+}
+
+/// Transform internal BlockTarget to API format
+fn transform_block_target(target: BlockTarget) -> BTreeMap<String, ProbabilityResponse> {
+    target
+        .probabilities
+        .iter()
+        .map(|(&prob, &fee_rate)| {
+            // Format probability with 2 decimal places (e.g., "0.95")
+            let prob_key = format!("{:.2}", prob.0);
+
+            (
+                prob_key,
+                ProbabilityResponse {
+                    fee_rate: format_fee_rate(fee_rate),
+                    capped: target.capped.contains(&prob),
+                    widened: target.widened.contains(&prob),
+                    fiat_cost: None,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Format fee rate with 4 decimal places, matching Kotlin
+pub fn format_fee_rate(fee_rate: f64) -> f64 {
+    format!("{fee_rate:.4}").parse::<f64>().unwrap_or(fee_rate)
+}
+
+/// Format a fiat amount with 2 decimal places
+pub fn format_fiat(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// Format timestamp to ISO 8601 with milliseconds and UTC timezone
+pub fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    // Format: "2025-01-20T12:00:00.000Z"
+    timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Create an empty response when no estimates are available
+pub fn empty_response(timestamp: DateTime<Utc>) -> FeeEstimateResponse {
+    FeeEstimateResponse {
+        mempool_update_time: format_timestamp(timestamp),
+        estimates: BTreeMap::new(),
+        valid_until: None,
+        degraded: false,
+        data_age_seconds: None,
+        fiat: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::OrderedFloat;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_transform_fee_estimate() {
+        let mut probabilities = BTreeMap::new();
+        probabilities.insert(OrderedFloat(0.05), 2.0916);
+        probabilities.insert(OrderedFloat(0.50), 3.4846);
+        probabilities.insert(OrderedFloat(0.95), 5.0531);
+
+        let block_target = BlockTarget::new(6, probabilities);
+
+        let mut estimates = BTreeMap::new();
+        estimates.insert(6, block_target);
+
+        let fee_estimate = FeeEstimate {
+            estimates,
+            fractional_estimates: BTreeMap::new(),
+            timestamp: Utc::now(),
+        };
+
+        let response = transform_fee_estimate(fee_estimate);
+
+        assert!(response.estimates.contains_key("6"));
+        let target = &response.estimates["6"];
+        assert!(target.probabilities.contains_key("0.05"));
+        assert!(target.probabilities.contains_key("0.50"));
+        assert!(target.probabilities.contains_key("0.95"));
+    }
+
+    #[test]
+    fn test_valid_until() {
+        let timestamp = DateTime::parse_from_rfc3339("2025-01-20T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let estimates = [3, 12]
+            .into_iter()
+            .map(|blocks| {
+                let probabilities = BTreeMap::from([(OrderedFloat(0.5), 2.0)]);
+                (blocks, BlockTarget::new(blocks, probabilities))
+            })
+            .collect();
+        let response = transform_fee_estimate(FeeEstimate::new(estimates, timestamp));
+
+        // Absent unless requested, so the format matches the reference implementation
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("valid_until").is_none());
+        assert!(json["estimates"]["3"].get("valid_until").is_none());
+
+        let valid_until = BTreeMap::from([
+            (
+                BlockTargetKey::from(3),
+                timestamp + chrono::Duration::minutes(3),
+            ),
+            (
+                BlockTargetKey::from(12),
+                timestamp + chrono::Duration::minutes(12),
+            ),
+        ]);
+        let json = serde_json::to_value(response.with_valid_until(&valid_until)).unwrap();
+        assert_eq!(json["valid_until"], "2025-01-20T12:03:00.000Z");
+        assert_eq!(
+            json["estimates"]["3"]["valid_until"],
+            "2025-01-20T12:03:00.000Z"
+        );
+        assert_eq!(
+            json["estimates"]["12"]["valid_until"],
+            "2025-01-20T12:12:00.000Z"
+        );
+    }
+
+    #[test]
+    fn test_capped_flag() {
+        let probabilities =
+            BTreeMap::from([(OrderedFloat(0.5), 80.0), (OrderedFloat(0.95), 100.0)]);
+        let mut block_target = BlockTarget::new(3, probabilities);
+        block_target.capped.insert(OrderedFloat(0.95));
+        let estimate = FeeEstimate::new(BTreeMap::from([(3, block_target)]), Utc::now());
+
+        let json = serde_json::to_value(transform_fee_estimate(estimate)).unwrap();
+        let probabilities = &json["estimates"]["3"]["probabilities"];
+        assert_eq!(probabilities["0.95"]["capped"], true);
+        assert_eq!(probabilities["0.95"]["fee_rate"], 100.0);
+        assert!(probabilities["0.50"].get("capped").is_none());
+    }
+
+    #[test]
+    fn test_widened_flag() {
+        let probabilities = BTreeMap::from([(OrderedFloat(0.5), 10.0), (OrderedFloat(0.95), 10.2)]);
+        let mut block_target = BlockTarget::new(3, probabilities);
+        block_target.widened.insert(OrderedFloat(0.95));
+        let estimate = FeeEstimate::new(BTreeMap::from([(3, block_target)]), Utc::now());
+
+        let json = serde_json::to_value(transform_fee_estimate(estimate)).unwrap();
+        let probabilities = &json["estimates"]["3"]["probabilities"];
+        assert_eq!(probabilities["0.95"]["widened"], true);
+        assert!(probabilities["0.50"].get("widened").is_none());
+    }
+
+    #[test]
+    fn test_high_variance_flag() {
+        let estimates = [1, 3]
+            .into_iter()
+            .map(|blocks| {
+                let probabilities = BTreeMap::from([(OrderedFloat(0.5), 2.0)]);
+                (blocks, BlockTarget::new(blocks, probabilities))
+            })
+            .collect();
+        let response = transform_fee_estimate(FeeEstimate::new(estimates, Utc::now()));
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["estimates"]["1"]["high_variance"], true);
+        assert!(json["estimates"]["3"].get("high_variance").is_none());
+    }
+
+    #[test]
+    fn test_with_fiat() {
+        let probabilities = BTreeMap::from([(OrderedFloat(0.5), 10.0), (OrderedFloat(0.95), 0.1)]);
+        let estimate = FeeEstimate::new(
+            BTreeMap::from([(6, BlockTarget::new(6, probabilities))]),
+            Utc::now(),
+        );
+        let response = transform_fee_estimate(estimate).with_fiat("usd", 100_000.0);
+
+        let target = response.get_target(6).unwrap();
+        // 10 sat/vB * 140 vB = 1400 sats = 0.000014 BTC
+        assert_eq!(target.probabilities["0.50"].fiat_cost, Some(1.4));
+        assert_eq!(target.probabilities["0.95"].fiat_cost, Some(0.01));
+        assert_eq!(
+            response.fiat,
+            Some(FiatQuote {
+                currency: "USD".to_string(),
+                btc_price: 100_000.0,
+                tx_vsize: STANDARD_TX_VSIZE,
+            })
+        );
+
+        // The quote is only serialized when there is one
+        let json = serde_json::to_value(response.clone()).unwrap();
+        assert_eq!(json["fiat"]["currency"], "USD");
+        let plain = FeeEstimateResponse {
+            fiat: None,
+            ..response
+        };
+        let json = serde_json::to_value(&plain).unwrap();
+        assert!(json.get("fiat").is_none());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let timestamp = DateTime::parse_from_rfc3339("2025-01-20T12:00:00.123Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let formatted = format_timestamp(timestamp);
+        assert_eq!(formatted, "2025-01-20T12:00:00.123Z");
+    }
+
+    #[test]
+    fn test_probability_formatting() {
+        let mut probabilities = BTreeMap::new();
+        probabilities.insert(OrderedFloat(0.05123), 2.091678);
+        probabilities.insert(OrderedFloat(0.95456), 5.053189);
+
+        let block_target = BlockTarget::new(3, probabilities);
+
+        let transformed = transform_block_target(block_target);
+
+        // Check that probabilities are formatted with 2 decimal places
+        assert!(transformed.contains_key("0.05"));
+        assert!(transformed.contains_key("0.95"));
+
+        // Check that fee rates are formatted with 4 decimal places
+        assert_eq!(transformed["0.05"].fee_rate, 2.0917);
+        assert_eq!(transformed["0.95"].fee_rate, 5.0532);
+    }
+
+    #[test]
+    fn test_lookups() {
+        let probabilities = BTreeMap::from([(OrderedFloat(0.5), 3.0)]);
+        let estimate = FeeEstimate::new(
+            BTreeMap::from([(6, BlockTarget::new(6, probabilities))]),
+            Utc::now(),
+        );
+        let response = FeeEstimateResponse::from(estimate);
+
+        assert!(response.has_estimates());
+        let target = response.get_target(6).unwrap();
+        assert_eq!(target.get_fee_rate(0.5), Some(3.0));
+        assert_eq!(target.get_fee_rate(0.95), None);
+        assert!(response.get_target(3).is_none());
+        assert!(!empty_response(Utc::now()).has_estimates());
+    }
+}
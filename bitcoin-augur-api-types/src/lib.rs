@@ -0,0 +1,18 @@
+//! Bitcoin Augur API types - Serde models of the fee estimation HTTP API
+//!
+//! The server serializes these models, and the client, the server's integration tests and the
+//! regression tests decode responses with them, so every side agrees on the format. Responses
+//! are built from the core library's [`FeeEstimate`](bitcoin_augur::FeeEstimate) with
+//! [`transform_fee_estimate`] or `From`.
+
+mod config;
+mod fees;
+mod problem;
+
+pub use config::{BucketConfigResponse, EstimatorConfigResponse, TargetWindowsResponse};
+pub use fees::{
+    empty_response, format_fee_rate, format_fiat, format_timestamp, transform_fee_estimate,
+    BlockTargetResponse, FeeComparisonResponse, FeeEstimateResponse, FiatQuote,
+    ProbabilityResponse, SourceComparisonResponse, STANDARD_TX_VSIZE,
+};
+pub use problem::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE};
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// Media type of every error body, from RFC 7807
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Machine-readable reason a request failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// No snapshots or estimates exist for what was requested
+    NoData,
+    /// The server has not computed its first estimate yet
+    WarmingUp,
+    /// The block target is not a number of blocks the server estimates for
+    InvalidTarget,
+    /// More distinct block targets were requested than the server estimates per minute
+    TooManyTargets,
+    /// Bitcoin Core cannot be reached or returns errors
+    UpstreamRpcDown,
+    /// The latest mempool data is older than the server's maximum staleness
+    StaleData,
+    /// The price feed fiat costs are computed with cannot be reached
+    UpstreamPriceFeedDown,
+    /// Any other malformed request
+    InvalidRequest,
+    Unauthorized,
+    NotFound,
+    MethodNotAllowed,
+    ShuttingDown,
+    InternalError,
+}
+
+/// RFC 7807 problem details body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    /// Always `about:blank`: the title is the HTTP status and `code` tells problems apart
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: ErrorCode,
+}
+
+impl ErrorCode {
+    /// Code for an error response that carries none, such as an extractor rejection
+    pub fn for_status(status: u16) -> Self {
+        match status {
+            401 => Self::Unauthorized,
+            404 => Self::NotFound,
+            405 => Self::MethodNotAllowed,
+            400..=499 => Self::InvalidRequest,
+            _ => Self::InternalError,
+        }
+    }
+}
+
+impl Problem {
+    /// Creates a problem for `status`, titled with its reason phrase
+    pub fn new(
+        status: u16,
+        title: impl Into<String>,
+        code: ErrorCode,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            problem_type: "about:blank".to_string(),
+            title: title.into(),
+            status,
+            detail: detail.into(),
+            code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problem_format() {
+        let problem = Problem::new(503, "Service Unavailable", ErrorCode::WarmingUp, "Starting");
+        let json = serde_json::to_value(&problem).unwrap();
+        assert_eq!(json["type"], "about:blank");
+        assert_eq!(json["code"], "WARMING_UP");
+        assert_eq!(serde_json::from_value::<Problem>(json).unwrap(), problem);
+
+        assert_eq!(ErrorCode::for_status(401), ErrorCode::Unauthorized);
+        assert_eq!(ErrorCode::for_status(422), ErrorCode::InvalidRequest);
+        assert_eq!(ErrorCode::for_status(502), ErrorCode::InternalError);
+    }
+}
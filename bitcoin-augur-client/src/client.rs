@@ -0,0 +1,342 @@
+use bitcoin_augur_api_types::{
+    EstimatorConfigResponse, FeeComparisonResponse, FeeEstimateResponse, Problem,
+    SourceComparisonResponse,
+};
+use chrono::{DateTime, Utc};
+use reqwest::{header, Response, Url};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::error::ClientError;
+use crate::retry::RetryPolicy;
+use crate::watch::FeeWatch;
+
+/// Default time a single request may take
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Async client for a Bitcoin Augur server
+///
+/// Cloning is cheap: clones share the underlying connection pool.
+#[derive(Debug, Clone)]
+pub struct AugurClient {
+    http: reqwest::Client,
+    base_url: Url,
+    retry: RetryPolicy,
+    timeout: Duration,
+}
+
+impl AugurClient {
+    /// Creates a client for the server at `base_url`, such as `http://localhost:8080`
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        let mut base_url = Url::parse(base_url)
+            .map_err(|e| ClientError::InvalidUrl(format!("{base_url}: {e}")))?;
+        if base_url.cannot_be_a_base() || base_url.host().is_none() {
+            return Err(ClientError::InvalidUrl(format!(
+                "{base_url} is not an HTTP server URL"
+            )));
+        }
+        // Endpoints are joined onto the base, so it must end with a slash to keep its path
+        if !base_url.path().ends_with('/') {
+            let path = format!("{path}/", path = base_url.path());
+            base_url.set_path(&path);
+        }
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url,
+            retry: RetryPolicy::default(),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Sends requests with `http`, such as a client configured with a proxy or extra headers
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Retries failed requests according to `retry`
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Gives up on a single request after `timeout`, 30 seconds by default
+    ///
+    /// The stream followed by [`watch`](Self::watch) has no timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retry policy of the client
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// GET /fees - Current estimates for every block target
+    pub async fn get_fees(&self) -> Result<FeeEstimateResponse, ClientError> {
+        self.get_json("fees", &[]).await
+    }
+
+    /// GET /fees/target/{num_blocks} - Current estimates for one block target
+    ///
+    /// `num_blocks` may be fractional; the response is keyed by the target the server used.
+    pub async fn get_fee_for_target(
+        &self,
+        num_blocks: f64,
+    ) -> Result<FeeEstimateResponse, ClientError> {
+        self.get_json(&format!("fees/target/{num_blocks}"), &[])
+            .await
+    }
+
+    /// GET /historical_fee - Estimates as they were at `timestamp`, to the second
+    pub async fn get_historical(
+        &self,
+        timestamp: DateTime<Utc>,
+    ) -> Result<FeeEstimateResponse, ClientError> {
+        self.get_json(
+            "historical_fee",
+            &[("timestamp", timestamp.timestamp().to_string())],
+        )
+        .await
+    }
+
+    /// GET /fees/compare - Current estimates next to the baseline strategies the server runs
+    pub async fn get_comparison(&self) -> Result<FeeComparisonResponse, ClientError> {
+        self.get_json("fees/compare", &[]).await
+    }
+
+    /// GET /fees/sources - Estimates from each snapshot source next to their median
+    pub async fn get_source_comparison(&self) -> Result<SourceComparisonResponse, ClientError> {
+        self.get_json("fees/sources", &[]).await
+    }
+
+    /// GET /config/estimator - Block targets, confidence levels and windows the server uses
+    pub async fn get_estimator_config(&self) -> Result<EstimatorConfigResponse, ClientError> {
+        self.get_json("config/estimator", &[]).await
+    }
+
+    /// Follows GET /fees/stream, yielding the current estimate and every new one
+    pub fn watch(&self) -> FeeWatch {
+        FeeWatch::new(self.clone())
+    }
+
+    /// Resolves an endpoint path against the base URL
+    fn url(&self, path: &str) -> Result<Url, ClientError> {
+        self.base_url
+            .join(path)
+            .map_err(|e| ClientError::InvalidUrl(format!("{path}: {e}")))
+    }
+
+    /// GETs `path` and decodes its JSON body, retrying temporary failures
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, ClientError> {
+        let url = self.url(path)?;
+        let mut retry = 0;
+        loop {
+            match self.try_get_json(url.clone(), query).await {
+                Err(e) if e.is_retryable() && retry < self.retry.max_retries => {
+                    let backoff = self.retry.backoff(retry);
+                    debug!("GET {url} failed, retrying in {backoff:?}: {e}");
+                    tokio::time::sleep(backoff).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn try_get_json<T: DeserializeOwned>(
+        &self,
+        url: Url,
+        query: &[(&str, String)],
+    ) -> Result<T, ClientError> {
+        let response = self
+            .http
+            .get(url)
+            .query(query)
+            .header(header::ACCEPT, "application/json")
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        let body = check_status(response).await?.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Opens the fee stream, telling the server which estimate was seen last
+    pub(crate) async fn open_stream(
+        &self,
+        last_event_id: Option<&str>,
+    ) -> Result<Response, ClientError> {
+        let mut request = self
+            .http
+            .get(self.url("fees/stream")?)
+            .header(header::ACCEPT, "text/event-stream");
+        if let Some(id) = last_event_id {
+            request = request.header("Last-Event-ID", id);
+        }
+        check_status(request.send().await?).await
+    }
+}
+
+/// Turns an error status into a [`ClientError`], decoding its problem details if it has them
+async fn check_status(response: Response) -> Result<Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await?;
+    Err(match serde_json::from_str::<Problem>(&body) {
+        Ok(problem) => ClientError::Api(problem),
+        Err(_) => ClientError::Status { status, body },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur_api_types::ErrorCode;
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn fees_body() -> serde_json::Value {
+        json!({
+            "mempool_update_time": "2025-01-20T12:00:00.000Z",
+            "estimates": {
+                "6": {
+                    "probabilities": {
+                        "0.50": { "fee_rate": 3.4846 },
+                        "0.95": { "fee_rate": 5.0531, "capped": true }
+                    }
+                }
+            }
+        })
+    }
+
+    fn problem(status: u16, code: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_json(json!({
+            "type": "about:blank",
+            "title": "Error",
+            "status": status,
+            "detail": "details",
+            "code": code
+        }))
+    }
+
+    fn quick_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_base_url_validation() {
+        let client = AugurClient::new("http://localhost:8080/augur").unwrap();
+        assert_eq!(
+            client.url("fees").unwrap().as_str(),
+            "http://localhost:8080/augur/fees"
+        );
+        assert!(matches!(
+            AugurClient::new("localhost:8080"),
+            Err(ClientError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            AugurClient::new("not a url"),
+            Err(ClientError::InvalidUrl(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_typed_endpoints() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fees/target/6"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fees_body()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/historical_fee"))
+            .and(query_param("timestamp", "1737374400"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fees_body()))
+            .mount(&server)
+            .await;
+
+        let client = AugurClient::new(&server.uri()).unwrap();
+        let fees = client.get_fee_for_target(6.0).await.unwrap();
+        let target = &fees.estimates["6"];
+        assert_eq!(target.probabilities["0.50"].fee_rate, 3.4846);
+        assert!(target.probabilities["0.95"].capped);
+        assert!(!fees.degraded);
+
+        let timestamp = DateTime::from_timestamp(1_737_374_400, 0).unwrap();
+        let historical = client.get_historical(timestamp).await.unwrap();
+        assert_eq!(historical.mempool_update_time, "2025-01-20T12:00:00.000Z");
+    }
+
+    #[tokio::test]
+    async fn test_problems_are_typed_and_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(path("/fees/target/0"))
+            .respond_with(problem(400, "INVALID_TARGET"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = AugurClient::new(&server.uri())
+            .unwrap()
+            .with_retry(quick_retries());
+        let error = client.get_fee_for_target(0.0).await.unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::InvalidTarget));
+        assert!(!error.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_is_retried() {
+        let server = MockServer::start().await;
+        Mock::given(path("/fees"))
+            .respond_with(problem(503, "WARMING_UP"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(path("/fees"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fees_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = AugurClient::new(&server.uri())
+            .unwrap()
+            .with_retry(quick_retries());
+        assert!(client.get_fees().await.is_ok());
+
+        // Without retries the first failure is returned
+        let server = MockServer::start().await;
+        Mock::given(path("/fees"))
+            .respond_with(ResponseTemplate::new(502).set_body_string("Bad gateway"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AugurClient::new(&server.uri())
+            .unwrap()
+            .with_retry(RetryPolicy::none());
+        let error = client.get_fees().await.unwrap_err();
+        assert!(matches!(
+            error,
+            ClientError::Status {
+                status: StatusCode::BAD_GATEWAY,
+                ..
+            }
+        ));
+        assert!(error.is_retryable());
+    }
+}
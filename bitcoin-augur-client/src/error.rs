@@ -0,0 +1,64 @@
+use bitcoin_augur_api_types::{ErrorCode, Problem};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+/// Errors returned by [`AugurClient`](crate::AugurClient)
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The base URL cannot be parsed or has no host
+    #[error("Invalid server URL: {0}")]
+    InvalidUrl(String),
+
+    /// The request could not be sent or its response not read
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with a problem details body
+    #[error("Server returned {status} {code:?}: {detail}", status = .0.status, code = .0.code, detail = .0.detail)]
+    Api(Problem),
+
+    /// The server answered with an error status and a body that is not a problem
+    #[error("Server returned {status}: {body}")]
+    Status { status: StatusCode, body: String },
+
+    /// A successful response could not be decoded
+    #[error("Invalid response body: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+impl ClientError {
+    /// Code of the problem the server reported, if it reported one
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Api(problem) => Some(problem.code),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed later
+    ///
+    /// Connection failures, timeouts and the server's temporary problems are retried:
+    /// warming up, too many distinct targets, an unreachable Bitcoin node, stale data, shutting
+    /// down and gateway errors.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Http(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            Self::Api(problem) => matches!(
+                problem.code,
+                ErrorCode::WarmingUp
+                    | ErrorCode::TooManyTargets
+                    | ErrorCode::UpstreamRpcDown
+                    | ErrorCode::StaleData
+                    | ErrorCode::UpstreamPriceFeedDown
+                    | ErrorCode::ShuttingDown
+            ),
+            Self::Status { status, .. } => matches!(
+                *status,
+                StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            Self::InvalidUrl(_) | Self::Decode(_) => false,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Bitcoin Augur client - A typed async client for the fee estimation API
+//!
+//! The client wraps the HTTP API of `bitcoin-augur-server`, decoding its responses into the
+//! models of `bitcoin-augur-api-types` the server serializes, retrying requests while the server warms up or its
+//! Bitcoin node is unreachable, and following new estimates as they are published.
+//!
+//! # Example
+//! ```no_run
+//! use bitcoin_augur_client::AugurClient;
+//!
+//! # async fn example() -> Result<(), bitcoin_augur_client::ClientError> {
+//! let client = AugurClient::new("http://localhost:8080")?;
+//!
+//! let fees = client.get_fee_for_target(6.0).await?;
+//! if let Some(rate) = fees.estimates["6"].probabilities.get("0.95") {
+//!     println!("6 blocks at 95% confidence: {:.2} sat/vB", rate.fee_rate);
+//! }
+//!
+//! // Follow every new estimate
+//! let mut watch = client.watch();
+//! loop {
+//!     let fees = watch.next().await?;
+//!     println!("Mempool updated at {}", fees.mempool_update_time);
+//! }
+//! # }
+//! ```
+
+mod client;
+mod error;
+mod retry;
+mod watch;
+
+pub use bitcoin_augur_api_types::{
+    BlockTargetResponse, ErrorCode, FeeComparisonResponse, FeeEstimateResponse,
+    ProbabilityResponse, Problem, SourceComparisonResponse,
+};
+pub use client::AugurClient;
+pub use error::ClientError;
+pub use retry::RetryPolicy;
+pub use watch::FeeWatch;
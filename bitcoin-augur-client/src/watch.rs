@@ -0,0 +1,235 @@
+use bitcoin_augur_api_types::FeeEstimateResponse;
+use reqwest::Response;
+use tracing::debug;
+
+use crate::client::AugurClient;
+use crate::error::ClientError;
+
+/// Name of the server-sent events carrying estimates
+const FEES_EVENT: &str = "fees";
+
+/// Stream of estimates from GET /fees/stream, created by [`AugurClient::watch`]
+///
+/// The stream is reconnected whenever it drops, with the client's retry policy. An estimate
+/// seen before the reconnection is not yielded twice.
+pub struct FeeWatch {
+    client: AugurClient,
+    response: Option<Response>,
+    parser: EventParser,
+    /// Id of the last estimate yielded, its `mempool_update_time`
+    last_id: Option<String>,
+    /// Whether a stream was open before, so reconnecting waits first
+    connected: bool,
+}
+
+impl FeeWatch {
+    pub(crate) fn new(client: AugurClient) -> Self {
+        Self {
+            client,
+            response: None,
+            parser: EventParser::default(),
+            last_id: None,
+            connected: false,
+        }
+    }
+
+    /// Waits for the next estimate
+    ///
+    /// Fails when the stream cannot be reopened within the retry policy, or when an event
+    /// cannot be decoded. Calling `next` again after an error keeps following the stream.
+    pub async fn next(&mut self) -> Result<FeeEstimateResponse, ClientError> {
+        loop {
+            while let Some(event) = self.parser.next_event() {
+                if event
+                    .event
+                    .as_deref()
+                    .is_some_and(|name| name != FEES_EVENT)
+                {
+                    continue;
+                }
+                if event.id.is_some() && event.id == self.last_id {
+                    continue;
+                }
+                let estimate = serde_json::from_str(&event.data)?;
+                self.last_id = event.id;
+                return Ok(estimate);
+            }
+
+            let chunk = match self.response.as_mut() {
+                Some(response) => response.chunk().await,
+                None => Ok(None),
+            };
+            match chunk {
+                Ok(Some(bytes)) => self.parser.push(&bytes),
+                Ok(None) | Err(_) => {
+                    if let Err(e) = chunk {
+                        debug!("Fee stream failed: {e}");
+                    }
+                    self.response = None;
+                    self.parser = EventParser::default();
+                    self.connect().await?;
+                }
+            }
+        }
+    }
+
+    /// Opens the stream, retrying temporary failures
+    async fn connect(&mut self) -> Result<(), ClientError> {
+        let retry = self.client.retry_policy();
+        if self.connected {
+            // A server closing every stream at once must not be reconnected to in a busy loop
+            tokio::time::sleep(retry.backoff(0)).await;
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.client.open_stream(self.last_id.as_deref()).await {
+                Ok(response) => {
+                    self.response = Some(response);
+                    self.connected = true;
+                    return Ok(());
+                }
+                Err(e) if e.is_retryable() && attempt < retry.max_retries => {
+                    let backoff = retry.backoff(attempt);
+                    debug!("Failed to open fee stream, retrying in {backoff:?}: {e}");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// One server-sent event
+#[derive(Debug, PartialEq)]
+struct Event {
+    event: Option<String>,
+    id: Option<String>,
+    data: String,
+}
+
+/// Splits a `text/event-stream` body into events as its chunks arrive
+#[derive(Debug, Default)]
+struct EventParser {
+    buffer: Vec<u8>,
+}
+
+impl EventParser {
+    fn push(&mut self, chunk: &[u8]) {
+        // Lines may end with CRLF; events are then separated by an empty line either way
+        self.buffer
+            .extend(chunk.iter().copied().filter(|&byte| byte != b'\r'));
+    }
+
+    /// Next complete event with data, skipping comments such as keep-alives
+    fn next_event(&mut self) -> Option<Event> {
+        loop {
+            let end = self.buffer.windows(2).position(|pair| pair == b"\n\n")?;
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block);
+
+            let mut event = None;
+            let mut id = None;
+            let mut data: Option<String> = None;
+            for line in block.lines() {
+                let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                match field {
+                    "event" => event = Some(value.to_string()),
+                    "id" => id = Some(value.to_string()),
+                    "data" => match &mut data {
+                        Some(data) => {
+                            data.push('\n');
+                            data.push_str(value);
+                        }
+                        None => data = Some(value.to_string()),
+                    },
+                    _ => {}
+                }
+            }
+            if let Some(data) = data {
+                return Some(Event { event, id, data });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use std::time::Duration;
+    use wiremock::matchers::{header, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_event_parser() {
+        let mut parser = EventParser::default();
+        parser.push(b":keep-alive\n\nevent: fees\r\nid: 1\r\ndata: {\"a\":");
+        assert_eq!(parser.next_event(), None);
+
+        parser.push(b"1}\r\n\r\ndata: line one\ndata:line two\n\n");
+        assert_eq!(
+            parser.next_event(),
+            Some(Event {
+                event: Some("fees".to_string()),
+                id: Some("1".to_string()),
+                data: "{\"a\":1}".to_string(),
+            })
+        );
+        assert_eq!(
+            parser.next_event(),
+            Some(Event {
+                event: None,
+                id: None,
+                data: "line one\nline two".to_string(),
+            })
+        );
+        assert_eq!(parser.next_event(), None);
+    }
+
+    fn fees_event(time: &str) -> String {
+        format!(
+            "event: fees\nid: {time}\ndata: {{\"mempool_update_time\":\"{time}\",\"estimates\":{{}}}}\n\n"
+        )
+    }
+
+    #[tokio::test]
+    async fn test_watch_reconnects_without_repeating_estimates() {
+        let first = "2025-01-20T12:00:00.000Z";
+        let second = "2025-01-20T12:00:30.000Z";
+
+        // The first connection ends after one estimate; the reconnection repeats it
+        let server = MockServer::start().await;
+        Mock::given(path("/fees/stream"))
+            .and(header("Last-Event-ID", first))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(format!(":\n\n{}{}", fees_event(first), fees_event(second))),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(path("/fees/stream"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(fees_event(first)),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+
+        let client = AugurClient::new(&server.uri())
+            .unwrap()
+            .with_retry(RetryPolicy {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+            });
+        let mut watch = client.watch();
+        assert_eq!(watch.next().await.unwrap().mempool_update_time, first);
+        assert_eq!(watch.next().await.unwrap().mempool_update_time, second);
+    }
+}
@@ -1,14 +1,19 @@
 #![allow(dead_code)]
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
+use bitcoin_augur::MempoolSnapshot;
+use bitcoin_augur_api_types::{empty_response, BlockTargetResponse, EstimatorConfigResponse};
 use chrono::{DateTime, Utc};
 use reqwest::{Client, StatusCode};
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, trace};
 
+use crate::comparison::ToleranceModel;
+
+/// Responses of both the Rust and the Kotlin servers decode into the shared models
+pub use bitcoin_augur_api_types::FeeEstimateResponse;
+
 /// API client for bitcoin-augur-server
 pub struct ApiClient {
     client: Client,
@@ -39,7 +44,7 @@ impl ApiClient {
             .context("Failed to send request")?;
 
         if response.status() == StatusCode::SERVICE_UNAVAILABLE {
-            return Ok(FeeEstimateResponse::empty());
+            return Ok(empty_response(Utc::now()));
         }
 
         response
@@ -64,7 +69,7 @@ impl ApiClient {
             .context("Failed to send request")?;
 
         if response.status() == StatusCode::SERVICE_UNAVAILABLE {
-            return Ok(FeeEstimateResponse::empty());
+            return Ok(empty_response(Utc::now()));
         }
 
         response
@@ -73,6 +78,30 @@ impl ApiClient {
             .context("Failed to parse fee estimates")
     }
 
+    /// Get the estimator configuration, or `None` from servers without the endpoint such as the
+    /// Kotlin reference
+    pub async fn get_estimator_config(&self) -> Result<Option<EstimatorConfigResponse>> {
+        let url = format!("{base_url}/config/estimator", base_url = self.base_url);
+        debug!("Getting estimator configuration from {url}");
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("Failed to send request")?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        response
+            .json()
+            .await
+            .map(Some)
+            .context("Failed to parse estimator configuration")
+    }
+
     /// Get raw response as JSON value (for compatibility testing)
     pub async fn get_raw(&self, path: &str) -> Result<(StatusCode, Value)> {
         let url = format!("{base_url}{path}", base_url = self.base_url);
@@ -104,61 +133,109 @@ impl ApiClient {
         Ok((status, body))
     }
 
-    /// Check server health
-    pub async fn health_check(&self) -> Result<bool> {
-        let url = format!("{base_url}/health", base_url = self.base_url);
-        let response = self.client.get(&url).send().await?;
-        Ok(response.status().is_success())
-    }
-}
+    /// Get a JSON response with field names in the casing `profile` asks for
+    pub async fn get_with_profile(&self, path: &str, profile: &str) -> Result<Value> {
+        let url = format!("{base_url}{path}", base_url = self.base_url);
+        trace!("Getting {profile} response from {url}");
 
-/// Fee estimate response structure (matches both Rust and Kotlin implementations)
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct FeeEstimateResponse {
-    #[serde(rename = "mempool_update_time")]
-    pub mempool_update_time: String,
-    pub estimates: HashMap<String, BlockTarget>,
-}
+        let response = self
+            .client
+            .get(&url)
+            .header(
+                reqwest::header::ACCEPT,
+                format!("application/json; profile=\"{profile}\""),
+            )
+            .send()
+            .await
+            .context("Failed to send request")?;
+        ensure!(
+            response.status().is_success(),
+            "Request failed with status {status}",
+            status = response.status()
+        );
 
-impl FeeEstimateResponse {
-    /// Create an empty response
-    pub fn empty() -> Self {
-        Self {
-            mempool_update_time: Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-            estimates: HashMap::new(),
-        }
+        response.json().await.context("Failed to parse response")
     }
 
-    /// Check if response has estimates
-    pub fn has_estimates(&self) -> bool {
-        !self.estimates.is_empty()
+    /// Get an error response, with its content type and body parsed as JSON if possible
+    pub async fn get_problem(&self, path: &str) -> Result<(StatusCode, Option<String>, Value)> {
+        self.request_problem(reqwest::Method::GET, path, None, None)
+            .await
     }
 
-    /// Get estimate for specific block target
-    pub fn get_target(&self, blocks: usize) -> Option<&BlockTarget> {
-        self.estimates.get(&blocks.to_string())
+    /// Send a request expected to fail, authenticated with `token` and with a JSON `body` if
+    /// given, returning the response like `get_problem`
+    pub async fn request_problem(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        token: Option<&str>,
+        body: Option<&Value>,
+    ) -> Result<(StatusCode, Option<String>, Value)> {
+        let url = format!("{base_url}{path}", base_url = self.base_url);
+        trace!("Sending {method} {url}, expecting an error response");
+
+        let mut request = self.client.request(method, &url);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send().await.context("Failed to send request")?;
+
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response.json::<Value>().await.unwrap_or(Value::Null);
+
+        Ok((status, content_type, body))
     }
-}
 
-/// Block target with probability-based fee estimates
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct BlockTarget {
-    pub probabilities: HashMap<String, Probability>,
-}
+    /// Inject snapshots through the test-only `POST /test/snapshots` endpoint
+    pub async fn inject_snapshots(
+        &self,
+        token: &str,
+        snapshots: &[MempoolSnapshot],
+        replace: bool,
+    ) -> Result<Value> {
+        let url = format!("{base_url}/test/snapshots", base_url = self.base_url);
+        debug!(
+            "Injecting {count} snapshots into {url}",
+            count = snapshots.len()
+        );
 
-impl BlockTarget {
-    /// Get fee rate for specific probability
-    pub fn get_fee_rate(&self, probability: f64) -> Option<f64> {
-        let key = format!("{probability:.2}");
-        self.probabilities.get(&key).map(|p| p.fee_rate)
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "snapshots": snapshots, "replace": replace }))
+            .send()
+            .await
+            .context("Failed to send injection request")?;
+
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .context("Failed to parse injection response")?;
+        ensure!(
+            status.is_success(),
+            "Snapshot injection failed with {status}: {body}"
+        );
+
+        Ok(body)
     }
-}
 
-/// Probability with fee rate
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct Probability {
-    #[serde(rename = "fee_rate")]
-    pub fee_rate: f64,
+    /// Check server health
+    pub async fn health_check(&self) -> Result<bool> {
+        let url = format!("{base_url}/health", base_url = self.base_url);
+        let response = self.client.get(&url).send().await?;
+        Ok(response.status().is_success())
+    }
 }
 
 /// Helper for comparing API responses
@@ -203,8 +280,8 @@ impl ResponseComparator {
     /// Compare block targets
     fn compare_block_targets(
         block_num: &str,
-        target1: &BlockTarget,
-        target2: &BlockTarget,
+        target1: &BlockTargetResponse,
+        target2: &BlockTargetResponse,
         differences: &mut Vec<String>,
     ) {
         let probs1: Vec<_> = target1.probabilities.keys().cloned().collect();
@@ -219,10 +296,7 @@ impl ResponseComparator {
         // Compare fee rates with tolerance
         for (prob_str, prob1) in &target1.probabilities {
             if let Some(prob2) = target2.probabilities.get(prob_str) {
-                let diff = (prob1.fee_rate - prob2.fee_rate).abs();
-                let tolerance = 0.0001; // Allow small floating point differences
-
-                if diff > tolerance {
+                if !ToleranceModel::default().accepts(prob1.fee_rate, prob2.fee_rate) {
                     differences.push(format!(
                         "Block {block_num} probability {prob_str} fee rate mismatch: {fee1:.4} vs {fee2:.4}",
                         fee1 = prob1.fee_rate,
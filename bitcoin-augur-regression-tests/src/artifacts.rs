@@ -0,0 +1,174 @@
+//! Bundles of everything needed to reproduce a failed run
+//!
+//! When a suite fails, the runner gathers the output of its servers, their data directories,
+//! the state of the mock node and the responses behind each failed comparison into one
+//! directory under `artifacts` in the data directory, named after the time and the suite. CI
+//! can upload the directory as it is, and starting a server on the bundled data directory
+//! brings back the estimate that failed.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::compatibility::TestResults;
+use crate::mock_rpc::MockBitcoinRpc;
+use crate::persistence::copy_dir;
+
+/// A directory collecting the artifacts of one failed suite
+#[derive(Debug)]
+pub struct FailureBundle {
+    dir: PathBuf,
+}
+
+impl FailureBundle {
+    /// Creates `artifacts/<time>-<suite>` below `data_dir`
+    pub fn create(data_dir: &Path, suite: &str) -> Result<Self> {
+        let time = Utc::now().format("%Y%m%dT%H%M%SZ");
+        let dir = data_dir
+            .join("artifacts")
+            .join(format!("{time}-{suite}", suite = file_name(suite)));
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {dir:?}"))?;
+        Ok(Self { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copies the server log at `path` into `logs`, skipping a server that never wrote one
+    pub fn add_log(&self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let logs = self.dir.join("logs");
+        fs::create_dir_all(&logs).with_context(|| format!("Failed to create {logs:?}"))?;
+        let target = logs.join(path.file_name().context("Log path without a file name")?);
+        fs::copy(path, &target).with_context(|| format!("Failed to copy {path:?}"))?;
+        Ok(())
+    }
+
+    /// Copies the data directory at `path` into `data/<name>`
+    pub fn add_data_dir(&self, name: &str, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        copy_dir(path, &self.dir.join("data").join(file_name(name)))
+    }
+
+    /// Writes the current state of the mock node to `mock-rpc.json`
+    pub fn add_scenario(&self, rpc: &MockBitcoinRpc) -> Result<()> {
+        self.write_json(&self.dir.join("mock-rpc.json"), &rpc.scenario())
+    }
+
+    /// Writes the failures in `results` to `failures.txt` and each set of recorded responses
+    /// to its own file in `responses`
+    pub fn add_results(&self, results: &TestResults) -> Result<()> {
+        self.add_failures(&results.failures())?;
+        for (index, recorded) in results.responses().iter().enumerate() {
+            let path = self.dir.join("responses").join(format!(
+                "{index:02}-{test}.json",
+                test = file_name(&recorded.test)
+            ));
+            self.write_json(&path, recorded)?;
+        }
+        Ok(())
+    }
+
+    /// Writes one failure per line to `failures.txt`
+    pub fn add_failures(&self, failures: &[String]) -> Result<()> {
+        let path = self.dir.join("failures.txt");
+        let mut text = failures.join("\n");
+        text.push('\n');
+        fs::write(&path, text).with_context(|| format!("Failed to write {path:?}"))
+    }
+
+    fn write_json(&self, path: &Path, value: &impl serde::Serialize) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(value)?)
+            .with_context(|| format!("Failed to write {path:?}"))
+    }
+}
+
+/// `name` with everything but ASCII letters, digits, `-` and `_` replaced by `-`, so test names
+/// such as `Cross-impl: /fees/target/3` make portable file names
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    name.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bundle_collects_artifacts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log = temp_dir.path().join("logs/matrix-rust-server.log");
+        fs::create_dir_all(log.parent().unwrap()).unwrap();
+        fs::write(&log, "server output\n").unwrap();
+        let data = temp_dir.path().join("rust-server");
+        fs::create_dir_all(data.join("2025-01-20")).unwrap();
+        fs::write(data.join("2025-01-20/850000_0.json"), "{}").unwrap();
+
+        let mut results = TestResults::new();
+        results.add_fail("Cross-impl: /fees/target/3", "2 differences");
+        results.record_responses(
+            "Cross-impl: /fees/target/3",
+            [
+                ("rust", json!({"fee": 1})),
+                ("reference", json!({"fee": 2})),
+            ],
+        );
+
+        let bundle = FailureBundle::create(temp_dir.path(), "matrix default").unwrap();
+        bundle.add_log(&log).unwrap();
+        bundle
+            .add_log(&temp_dir.path().join("missing.log"))
+            .unwrap();
+        bundle.add_data_dir("rust-server", &data).unwrap();
+        bundle.add_scenario(&MockBitcoinRpc::new(0)).unwrap();
+        bundle.add_results(&results).unwrap();
+
+        let dir = bundle.path();
+        assert!(dir.starts_with(temp_dir.path().join("artifacts")));
+        assert!(
+            dir.to_string_lossy().ends_with("-matrix-default"),
+            "{dir:?}"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("logs/matrix-rust-server.log")).unwrap(),
+            "server output\n"
+        );
+        assert!(dir
+            .join("data/rust-server/2025-01-20/850000_0.json")
+            .exists());
+        let scenario: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("mock-rpc.json")).unwrap()).unwrap();
+        assert_eq!(scenario["block_height"], 850000);
+        assert_eq!(
+            fs::read_to_string(dir.join("failures.txt")).unwrap(),
+            "Cross-impl: /fees/target/3: 2 differences\n"
+        );
+        let responses: serde_json::Value = serde_json::from_slice(
+            &fs::read(dir.join("responses/00-Cross-impl-fees-target-3.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(responses["responses"]["reference"]["fee"], 2);
+    }
+}
@@ -0,0 +1,320 @@
+//! Stage-by-stage bisection of estimates that differ between two code paths
+//!
+//! A stage trace records what every stage of the estimator (bucketing, inflows, expected blocks,
+//! simulation, weighting and monotonicity) produced for each test vector. Each code path, such
+//! as the current build and the previous commit, or the library built with and without a
+//! feature, records a trace of the same vectors. Comparing the traces names, for every vector
+//! whose values differ, the first stage that differs and the first value within it. Later
+//! stages only inherit the difference.
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "platform": "x86_64-linux",
+//!   "generator": "bitcoin-augur-rust 0.1.0",
+//!   "vectors": "bitcoin-augur-rust 0.1.0",
+//!   "traces": { "steady_state": { "snapshots": 12, "mempool": { "250": 4000.0 }, ... } }
+//! }
+//! ```
+
+use anyhow::{ensure, Context, Result};
+use bitcoin_augur::{AugurError, EstimateTrace, FeeEstimator, Stage};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::reproducibility::{platform, ulp_distance};
+use crate::test_vectors::VectorFile;
+
+/// Current stage trace schema version
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What every estimator stage of one build produced for every vector of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StageTraceFile {
+    pub schema_version: u32,
+    /// Architecture and operating system the traces were recorded on
+    pub platform: String,
+    /// Implementation and version that recorded the traces
+    pub generator: String,
+    /// Generator of the vector file
+    pub vectors: String,
+    /// Traces keyed by vector name
+    pub traces: BTreeMap<String, EstimateTrace>,
+}
+
+impl StageTraceFile {
+    /// Traces every vector of `file`, skipping vectors without enough data to estimate
+    pub fn record(file: &VectorFile, filter: Option<&str>) -> Result<Self> {
+        let estimator = FeeEstimator::new();
+        let mut traces = BTreeMap::new();
+        for vector in &file.vectors {
+            if filter.is_some_and(|f| !vector.name.contains(f)) {
+                continue;
+            }
+            match estimator.trace(&vector.snapshots, vector.num_blocks) {
+                Ok(trace) => {
+                    traces.insert(vector.name.clone(), trace);
+                }
+                Err(AugurError::InsufficientData(_)) => {}
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!("Failed to trace vector {name}", name = vector.name)
+                    })
+                }
+            }
+        }
+
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            platform: platform(),
+            generator: format!(
+                "bitcoin-augur-rust {version}",
+                version = env!("CARGO_PKG_VERSION")
+            ),
+            vectors: file.generator.clone(),
+            traces,
+        })
+    }
+
+    /// Loads a stage trace file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read stage trace {path:?}"))?;
+        let file: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid stage trace {path:?}"))?;
+        ensure!(
+            file.schema_version == SCHEMA_VERSION,
+            "Unsupported schema version {version} in {path:?} (expected {SCHEMA_VERSION})",
+            version = file.schema_version
+        );
+        Ok(file)
+    }
+
+    /// Saves the traces as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write stage trace {path:?}"))
+    }
+
+    /// The first diverging stage of every vector traced by both files, and the vectors traced
+    /// by only one of them
+    pub fn bisect(&self, reference: &Self, max_ulps: u64) -> Bisection {
+        let mut bisection = Bisection::default();
+        for (name, expected) in &reference.traces {
+            match self.traces.get(name) {
+                Some(actual) => {
+                    if let Some(divergence) = first_divergence(name, expected, actual, max_ulps) {
+                        bisection.divergences.push(divergence);
+                    } else {
+                        bisection.identical += 1;
+                    }
+                }
+                None => bisection.missing.push(name.clone()),
+            }
+        }
+        bisection.missing.extend(
+            self.traces
+                .keys()
+                .filter(|name| !reference.traces.contains_key(*name))
+                .cloned(),
+        );
+        bisection
+    }
+}
+
+/// Outcome of comparing two stage trace files
+#[derive(Debug, Default)]
+pub struct Bisection {
+    /// Vectors whose traces agree at every stage
+    pub identical: usize,
+    /// First divergence of every vector whose traces differ
+    pub divergences: Vec<StageDivergence>,
+    /// Vectors traced on one side only
+    pub missing: Vec<String>,
+}
+
+impl Bisection {
+    pub fn passed(&self) -> bool {
+        self.divergences.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// The first stage at which two traces of one vector differ
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageDivergence {
+    pub vector: String,
+    pub stage: Stage,
+    /// Label of the first differing value, such as `short-term bucket 250`
+    pub location: String,
+    pub expected: Option<f64>,
+    pub actual: Option<f64>,
+    /// Values of the stage that differ
+    pub count: usize,
+}
+
+/// The first stage of `actual` with a value more than `max_ulps` from `expected`'s
+fn first_divergence(
+    vector: &str,
+    expected: &EstimateTrace,
+    actual: &EstimateTrace,
+    max_ulps: u64,
+) -> Option<StageDivergence> {
+    Stage::ALL.into_iter().find_map(|stage| {
+        let expected_values = expected.values(stage);
+        let actual_values: HashMap<String, f64> = actual.values(stage).into_iter().collect();
+
+        // Values of `expected` in order, then those only `actual` has
+        let mut pairs: Vec<(String, Option<f64>, Option<f64>)> = expected_values
+            .iter()
+            .map(|(location, value)| {
+                (
+                    location.clone(),
+                    Some(*value),
+                    actual_values.get(location).copied(),
+                )
+            })
+            .collect();
+        let known: HashSet<&String> = expected_values
+            .iter()
+            .map(|(location, _)| location)
+            .collect();
+        pairs.extend(
+            actual
+                .values(stage)
+                .into_iter()
+                .filter(|(location, _)| !known.contains(location))
+                .map(|(location, value)| (location, None, Some(value))),
+        );
+
+        let differing: Vec<_> = pairs
+            .into_iter()
+            .filter(|(_, expected, actual)| match (expected, actual) {
+                (Some(expected), Some(actual)) => ulp_distance(*expected, *actual) > max_ulps,
+                _ => true,
+            })
+            .collect();
+        let (location, expected, actual) = differing.first().cloned()?;
+        Some(StageDivergence {
+            vector: vector.to_string(),
+            stage,
+            location,
+            expected,
+            actual,
+            count: differing.len(),
+        })
+    })
+}
+
+/// Prints where each vector's traces first diverge from `reference`
+pub fn print_bisection(reference: &StageTraceFile, bisection: &Bisection) {
+    let status = if bisection.passed() {
+        "PASSED".green()
+    } else {
+        "FAILED".red()
+    };
+    println!(
+        "\nStages against {generator} on {platform} [{status}]",
+        generator = reference.generator,
+        platform = reference.platform
+    );
+    println!("{separator}", separator = "-".repeat(60));
+
+    let format_value = |value: Option<f64>| {
+        value
+            .map(|v| format!("{v:?}"))
+            .unwrap_or_else(|| "N/A".to_string())
+    };
+    for divergence in &bisection.divergences {
+        println!(
+            "{symbol} {vector}: first diverges at {stage}, {location}: expected {expected}, actual {actual} ({count} values differ in the stage)",
+            symbol = "✗".red(),
+            vector = divergence.vector,
+            stage = divergence.stage,
+            location = divergence.location,
+            expected = format_value(divergence.expected),
+            actual = format_value(divergence.actual),
+            count = divergence.count
+        );
+    }
+    for vector in &bisection.missing {
+        println!(
+            "{symbol} {vector}: traced on one side only",
+            symbol = "✗".red()
+        );
+    }
+    println!(
+        "{symbol} {identical} vectors identical at every stage",
+        symbol = "✓".green(),
+        identical = bisection.identical
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::TestVectorRunner;
+
+    fn recorded() -> StageTraceFile {
+        let vectors = TestVectorRunner::bundled_vectors().unwrap();
+        StageTraceFile::record(&vectors, Some("steady_state")).unwrap()
+    }
+
+    #[test]
+    fn test_identical_traces_pass() {
+        let trace = recorded();
+        assert_eq!(trace.traces.len(), 1);
+
+        let bisection = trace.bisect(&trace, 0);
+        assert!(bisection.passed());
+        assert_eq!(bisection.identical, 1);
+    }
+
+    #[test]
+    fn test_bisect_reports_the_first_diverging_stage() {
+        let reference = recorded();
+        let mut changed = reference.clone();
+        let trace = changed.traces.get_mut("steady_state").unwrap();
+        // An inflow change carries through to the fee rates
+        let (&bucket, inflow) = trace.long_term_inflows.iter_mut().next().unwrap();
+        *inflow *= 2.0;
+        trace.fee_rates[0][0] *= 1.5;
+
+        let bisection = changed.bisect(&reference, 0);
+        assert_eq!(bisection.divergences.len(), 1);
+        let divergence = &bisection.divergences[0];
+        assert_eq!(divergence.stage, Stage::Inflows);
+        assert_eq!(divergence.location, format!("long-term bucket {bucket}"));
+        assert_eq!(divergence.count, 1);
+
+        // A bucket present on one side only differs too
+        let mut changed = reference.clone();
+        let trace = changed.traces.get_mut("steady_state").unwrap();
+        trace.mempool.insert(-1, 400.0);
+        let divergence = &changed.bisect(&reference, 0).divergences[0];
+        assert_eq!(divergence.stage, Stage::Bucketing);
+        assert_eq!(
+            (divergence.expected, divergence.actual),
+            (None, Some(400.0))
+        );
+
+        // Only the last stage differs within a tolerance of one ulp
+        let mut changed = reference.clone();
+        let trace = changed.traces.get_mut("steady_state").unwrap();
+        let rate = &mut trace.fee_rates[2][1];
+        *rate = f64::from_bits(rate.to_bits() + 1);
+        assert_eq!(
+            changed.bisect(&reference, 0).divergences[0].stage,
+            Stage::Monotonicity
+        );
+        assert!(changed.bisect(&reference, 1).passed());
+
+        changed.traces.clear();
+        assert_eq!(changed.bisect(&reference, 0).missing, ["steady_state"]);
+    }
+}
@@ -0,0 +1,369 @@
+//! Chaos testing of servers polling a misbehaving Bitcoin node
+//!
+//! The servers poll a mock node that is repeatedly broken in a seeded random order: killed and
+//! restarted, answering HTTP 500, hanging, or returning malformed JSON. While a fault lasts,
+//! `/fees` must keep serving the last valid estimate. Once the fault is cleared, the server must
+//! publish an estimate from a snapshot taken after the node came back.
+//!
+//! The Rust server is held to these expectations. The Kotlin reference, when available, runs
+//! against the same node and any different behavior is reported as a warning, so divergences are
+//! documented without failing the run.
+
+use anyhow::{ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info};
+
+use crate::api_client::{ApiClient, FeeEstimateResponse};
+use crate::compatibility::TestResults;
+use crate::mock_rpc::{Fault, MockBitcoinRpc, MockRpcTask, TestDataGenerator};
+
+/// How often `/fees` is requested while a fault lasts or a server recovers
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Transactions in the mock mempool, refilled after every fault so each recovery has new data
+const MEMPOOL_SIZE: usize = 300;
+
+/// Chaos run settings
+#[derive(Debug, Clone)]
+pub struct ChaosConfig {
+    pub seed: u64,
+    pub rounds: usize,
+    /// How long each fault lasts
+    pub fault_duration: Duration,
+    /// How long a server may take to publish a fresh estimate after a fault is cleared
+    pub recovery_timeout: Duration,
+}
+
+/// A way of breaking the mock node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChaosEvent {
+    /// Stop the node entirely, then start it again on the same port
+    KillRpc,
+    ServerErrors,
+    /// Hold every RPC response for twice the fault duration
+    Timeouts,
+    MalformedJson,
+}
+
+impl ChaosEvent {
+    const ALL: [ChaosEvent; 4] = [
+        ChaosEvent::KillRpc,
+        ChaosEvent::ServerErrors,
+        ChaosEvent::Timeouts,
+        ChaosEvent::MalformedJson,
+    ];
+
+    /// Fault injected into a running node, or `None` if the node is stopped instead
+    fn fault(self, fault_duration: Duration) -> Option<Fault> {
+        match self {
+            Self::KillRpc => None,
+            Self::ServerErrors => Some(Fault::ServerError),
+            Self::Timeouts => Some(Fault::Delay {
+                seconds: fault_duration.as_secs().max(1) * 2,
+            }),
+            Self::MalformedJson => Some(Fault::MalformedJson),
+        }
+    }
+}
+
+impl fmt::Display for ChaosEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::KillRpc => "node killed",
+            Self::ServerErrors => "HTTP 500",
+            Self::Timeouts => "timeouts",
+            Self::MalformedJson => "malformed JSON",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Seeded order of events, covering every kind before repeating any
+pub fn schedule(seed: u64, rounds: usize) -> Vec<ChaosEvent> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut events = Vec::with_capacity(rounds);
+    while events.len() < rounds {
+        let mut cycle = ChaosEvent::ALL;
+        for i in (1..cycle.len()).rev() {
+            cycle.swap(i, rng.random_range(0..=i));
+        }
+        events.extend(cycle.into_iter().take(rounds - events.len()));
+    }
+    events
+}
+
+/// A server under test
+pub struct ChaosTarget {
+    pub name: String,
+    pub client: ApiClient,
+    /// Whether a misbehavior fails the run, rather than being reported as a divergence
+    pub strict: bool,
+}
+
+impl ChaosTarget {
+    fn report(&self, results: &mut TestResults, test: &str, failure: &str) {
+        if self.strict {
+            results.add_fail(test, failure);
+        } else {
+            results.add_warning(test, &format!("Diverges from the Rust server: {failure}"));
+        }
+    }
+}
+
+/// What `/fees` served at one point in time
+fn check_response(response: &FeeEstimateResponse) -> Result<DateTime<Utc>> {
+    ensure!(response.has_estimates(), "No estimates served");
+    for (target, block_target) in &response.estimates {
+        for (probability, rate) in &block_target.probabilities {
+            ensure!(
+                rate.fee_rate.is_finite() && rate.fee_rate > 0.0,
+                "Invalid fee rate {rate} for {target} blocks at {probability}",
+                rate = rate.fee_rate
+            );
+        }
+    }
+    let time = DateTime::parse_from_rfc3339(&response.mempool_update_time).with_context(|| {
+        format!(
+            "Invalid mempool_update_time {time}",
+            time = response.mempool_update_time
+        )
+    })?;
+    Ok(time.with_timezone(&Utc))
+}
+
+/// Requests `/fees` and returns the update time of a valid estimate
+async fn served_update_time(client: &ApiClient) -> Result<DateTime<Utc>> {
+    let response = client.get_fees().await?;
+    check_response(&response)
+}
+
+/// Polls until `client` serves a valid estimate updated after `after`
+async fn wait_for_fresh_estimate(
+    client: &ApiClient,
+    after: DateTime<Utc>,
+    timeout: Duration,
+) -> Result<Duration> {
+    let started = Instant::now();
+    loop {
+        match served_update_time(client).await {
+            Ok(time) if time > after => return Ok(started.elapsed()),
+            Ok(time) => debug!("Estimate from {time} is not newer than {after}"),
+            Err(e) => debug!("No valid estimate yet: {e}"),
+        }
+        ensure!(
+            started.elapsed() < timeout,
+            "No estimate newer than {after} within {timeout:?}"
+        );
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Refills the mock mempool and mines a block, so the next snapshot differs from the last
+fn change_mempool(rpc: &MockBitcoinRpc) {
+    rpc.set_mempool(TestDataGenerator::uniform_distribution(
+        MEMPOOL_SIZE,
+        1.0,
+        100.0,
+    ));
+    rpc.mine_blocks(1, 1_000_000);
+}
+
+/// The mock node and its background task, which is absent while the node is killed
+pub struct ChaosNode {
+    rpc: Arc<MockBitcoinRpc>,
+    task: Option<MockRpcTask>,
+}
+
+impl ChaosNode {
+    /// Starts a mock node on `port` with a populated mempool
+    pub async fn start(port: u16) -> Result<Self> {
+        let rpc = Arc::new(MockBitcoinRpc::new(port));
+        change_mempool(&rpc);
+        let task = rpc.spawn().await?;
+        Ok(Self {
+            rpc,
+            task: Some(task),
+        })
+    }
+
+    pub fn url(&self) -> String {
+        self.rpc.url()
+    }
+
+    pub fn rpc(&self) -> &MockBitcoinRpc {
+        &self.rpc
+    }
+
+    async fn apply(&mut self, event: ChaosEvent, fault_duration: Duration) -> Result<()> {
+        match event.fault(fault_duration) {
+            Some(fault) => self.rpc.set_fault(fault),
+            None => {
+                if let Some(task) = self.task.take() {
+                    task.stop().await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn clear(&mut self) -> Result<()> {
+        self.rpc.set_fault(Fault::None);
+        change_mempool(&self.rpc);
+        if self.task.is_none() {
+            self.task = Some(self.rpc.spawn().await?);
+        }
+        Ok(())
+    }
+
+    pub async fn stop(mut self) -> Result<()> {
+        match self.task.take() {
+            Some(task) => task.stop().await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Runs `config.rounds` faults against `node` and checks how every target behaves
+pub async fn run_chaos(
+    node: &mut ChaosNode,
+    targets: &[ChaosTarget],
+    config: &ChaosConfig,
+) -> Result<TestResults> {
+    ensure!(!targets.is_empty(), "No servers to test");
+    let mut results = TestResults::new();
+
+    // Every target must serve an estimate before the node misbehaves
+    for target in targets {
+        let elapsed = wait_for_fresh_estimate(
+            &target.client,
+            DateTime::<Utc>::MIN_UTC,
+            config.recovery_timeout,
+        )
+        .await
+        .with_context(|| format!("{name} never served an estimate", name = target.name))?;
+        debug!(
+            "{name} served its first estimate after {elapsed:?}",
+            name = target.name
+        );
+    }
+
+    let events = schedule(config.seed, config.rounds);
+    info!(
+        "Running {count} chaos rounds with seed {seed}",
+        count = events.len(),
+        seed = config.seed
+    );
+
+    for (round, event) in events.into_iter().enumerate() {
+        let round = round + 1;
+        info!("Round {round}: {event}");
+        node.apply(event, config.fault_duration).await?;
+
+        // Stale estimates are expected, missing or invalid ones are not
+        let mut failures: Vec<Option<String>> = vec![None; targets.len()];
+        let fault_end = Instant::now() + config.fault_duration;
+        while Instant::now() < fault_end {
+            for (target, failure) in targets.iter().zip(failures.iter_mut()) {
+                if failure.is_some() {
+                    continue;
+                }
+                if let Err(e) = served_update_time(&target.client).await {
+                    *failure = Some(format!("{e:#}"));
+                }
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+        for (target, failure) in targets.iter().zip(failures) {
+            let test = format!(
+                "{name} round {round} ({event}): serves during fault",
+                name = target.name
+            );
+            match failure {
+                None => results.add_pass(&test, "Kept serving a valid estimate"),
+                Some(failure) => target.report(&mut results, &test, &failure),
+            }
+        }
+
+        let cleared_at = Utc::now();
+        node.clear().await?;
+
+        for target in targets {
+            let test = format!(
+                "{name} round {round} ({event}): recovers",
+                name = target.name
+            );
+            match wait_for_fresh_estimate(&target.client, cleared_at, config.recovery_timeout).await
+            {
+                Ok(elapsed) => results.add_pass(
+                    &test,
+                    &format!(
+                        "Fresh estimate {secs:.1}s after the fault cleared",
+                        secs = elapsed.as_secs_f64()
+                    ),
+                ),
+                Err(e) => target.report(&mut results, &test, &format!("{e:#}")),
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur_api_types::{empty_response, BlockTargetResponse, ProbabilityResponse};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_schedule_is_seeded_and_covers_every_event() {
+        let events = schedule(7, 8);
+        assert_eq!(events, schedule(7, 8));
+        assert_eq!(events.len(), 8);
+        for cycle in events.chunks(ChaosEvent::ALL.len()) {
+            for event in ChaosEvent::ALL {
+                assert!(cycle.contains(&event), "{event} missing from {cycle:?}");
+            }
+        }
+        assert_eq!(schedule(7, 3), events[..3]);
+    }
+
+    fn response(fee_rate: f64) -> FeeEstimateResponse {
+        let probabilities = BTreeMap::from([(
+            "0.50".to_string(),
+            ProbabilityResponse {
+                fee_rate,
+                ..Default::default()
+            },
+        )]);
+        FeeEstimateResponse {
+            mempool_update_time: "2025-01-20T12:00:00.000Z".to_string(),
+            estimates: BTreeMap::from([(
+                "3".to_string(),
+                BlockTargetResponse {
+                    probabilities,
+                    ..Default::default()
+                },
+            )]),
+            valid_until: None,
+            degraded: false,
+            data_age_seconds: None,
+            fiat: None,
+        }
+    }
+
+    #[test]
+    fn test_check_response() {
+        let time = check_response(&response(2.5)).unwrap();
+        assert_eq!(time.to_rfc3339(), "2025-01-20T12:00:00+00:00");
+
+        assert!(check_response(&response(0.0)).is_err());
+        assert!(check_response(&response(f64::NAN)).is_err());
+        assert!(check_response(&empty_response(Utc::now())).is_err());
+    }
+}
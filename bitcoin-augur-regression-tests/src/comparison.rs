@@ -0,0 +1,209 @@
+//! Tolerance for comparing fee rates of different magnitudes
+//!
+//! A single relative tolerance is too strict for fee rates near the 1 sat/vB minimum, where
+//! 1.0 and 1.01 differ by one percent, and too lax for rates in the hundreds. A
+//! [`ToleranceModel`] allows an absolute difference plus a relative one, like numpy's `isclose`,
+//! where the relative tolerance changes at configurable fee rate breakpoints:
+//!
+//! ```json
+//! { "absolute": 0.01, "relative": 1e-3, "breakpoints": [{ "from": 100.0, "relative": 1e-4 }] }
+//! ```
+//!
+//! The default model is the 1e-4 sat/vB the Kotlin and Rust servers have always been held to,
+//! since both serve fee rates with four decimal places.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Allowed difference between an expected and an actual fee rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToleranceModel {
+    /// Difference always allowed, in sat/vB
+    pub absolute: f64,
+    /// Relative tolerance below the first breakpoint
+    pub relative: f64,
+    /// Relative tolerances from a fee rate upwards
+    #[serde(default)]
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+/// Relative tolerance for fee rates of at least `from` sat/vB
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Breakpoint {
+    pub from: f64,
+    pub relative: f64,
+}
+
+impl Default for ToleranceModel {
+    /// Four decimal places at every fee rate
+    fn default() -> Self {
+        Self {
+            absolute: 1e-4,
+            relative: 0.0,
+            breakpoints: Vec::new(),
+        }
+    }
+}
+
+impl ToleranceModel {
+    /// A relative tolerance with an absolute term of the same size, as if every fee rate were
+    /// 1 sat/vB higher
+    pub fn relative(relative: f64) -> Self {
+        Self {
+            absolute: relative,
+            relative,
+            breakpoints: Vec::new(),
+        }
+    }
+
+    /// Checks that every tolerance is finite and non-negative and that breakpoints are sorted
+    pub fn validate(&self) -> Result<(), String> {
+        let valid = |t: f64| t.is_finite() && t >= 0.0;
+        if !valid(self.absolute) {
+            return Err(format!("Invalid absolute tolerance {}", self.absolute));
+        }
+        if !valid(self.relative) {
+            return Err(format!("Invalid relative tolerance {}", self.relative));
+        }
+        for breakpoint in &self.breakpoints {
+            if !valid(breakpoint.from) || !valid(breakpoint.relative) {
+                return Err(format!("Invalid breakpoint {breakpoint}"));
+            }
+        }
+        if self.breakpoints.windows(2).any(|w| w[0].from >= w[1].from) {
+            return Err("Breakpoints must be in increasing fee rate order".to_string());
+        }
+        Ok(())
+    }
+
+    /// Relative tolerance applying to `fee_rate`
+    pub fn relative_at(&self, fee_rate: f64) -> f64 {
+        self.breakpoints
+            .iter()
+            .rev()
+            .find(|b| fee_rate.abs() >= b.from)
+            .map_or(self.relative, |b| b.relative)
+    }
+
+    /// Largest difference allowed from `expected`
+    pub fn allowed(&self, expected: f64) -> f64 {
+        self.absolute + self.relative_at(expected) * expected.abs()
+    }
+
+    /// Whether `actual` is within the tolerance of `expected`
+    pub fn accepts(&self, expected: f64, actual: f64) -> bool {
+        (actual - expected).abs() <= self.allowed(expected)
+    }
+}
+
+impl fmt::Display for ToleranceModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "±{absolute:e} sat/vB + {relative:e}",
+            absolute = self.absolute,
+            relative = self.relative
+        )?;
+        for breakpoint in &self.breakpoints {
+            write!(f, ", {breakpoint}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{relative:e} from {from} sat/vB",
+            relative = self.relative,
+            from = self.from
+        )
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    /// Parses `FROM=RELATIVE`, such as `100=1e-4`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, relative) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Breakpoint {s:?} is not FROM=RELATIVE"))?;
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("Breakpoint {s:?}: {e}"))
+        };
+        Ok(Self {
+            from: parse(from)?,
+            relative: parse(relative)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_model_allows_four_decimal_places() {
+        let model = ToleranceModel::default();
+        assert!(model.accepts(1.0, 1.0001));
+        assert!(!model.accepts(1.0, 1.001));
+        assert!(!model.accepts(500.0, 500.001));
+    }
+
+    #[test]
+    fn test_breakpoints_scale_with_magnitude() {
+        let model = ToleranceModel {
+            absolute: 0.01,
+            relative: 1e-3,
+            breakpoints: vec![Breakpoint {
+                from: 100.0,
+                relative: 1e-4,
+            }],
+        };
+        assert!(model.accepts(1.0, 1.01));
+        assert!(!model.accepts(1.0, 1.02));
+        assert!(model.accepts(50.0, 50.05));
+        assert!(!model.accepts(50.0, 50.1));
+        assert!(model.accepts(500.0, 500.05));
+        assert!(!model.accepts(500.0, 500.5));
+    }
+
+    #[test]
+    fn test_relative_model_has_an_absolute_term() {
+        let model = ToleranceModel::relative(1e-3);
+        assert!(model.accepts(10.0, 10.005));
+        assert!(!model.accepts(10.0, 10.5));
+        assert!(model.accepts(0.0, 0.0005));
+        assert!(!model.accepts(1.0, 1.01));
+    }
+
+    #[test]
+    fn test_validate_and_parse_breakpoints() {
+        assert!(ToleranceModel::default().validate().is_ok());
+        let mut model = ToleranceModel::default();
+        model.breakpoints.push("100=1e-4".parse().unwrap());
+        model.breakpoints.push("10=1e-2".parse().unwrap());
+        assert!(model.validate().is_err());
+        model.breakpoints.clear();
+        model.absolute = -1.0;
+        assert!(model.validate().is_err());
+
+        assert_eq!(
+            "1000=1e-5".parse::<Breakpoint>(),
+            Ok(Breakpoint {
+                from: 1000.0,
+                relative: 1e-5
+            })
+        );
+        assert!("1000".parse::<Breakpoint>().is_err());
+        assert!("a=1".parse::<Breakpoint>().is_err());
+    }
+}
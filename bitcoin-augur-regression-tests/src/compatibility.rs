@@ -1,13 +1,32 @@
-use crate::api_client::{ApiClient, ResponseComparator};
-use anyhow::Result;
+use crate::api_client::{ApiClient, FeeEstimateResponse, ResponseComparator};
+use crate::differential::{read_recorded_snapshots, wait_for_polls, POLLS_PER_SNAPSHOT};
+use crate::mock_rpc::MockBitcoinRpc;
+use anyhow::{ensure, Context, Result};
+use bitcoin_augur::{BlockTarget, FeeEstimate, FeeEstimator, MempoolSnapshot, MempoolTransaction};
+use chrono::{DateTime, Duration, Utc};
 use colored::Colorize;
+use reqwest::StatusCode;
+use serde::Serialize;
 use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::{debug, info};
 
+/// Relative tolerance when comparing served fee rates with in-process estimates
+const INJECTED_FEE_TOLERANCE: f64 = 1e-3;
+
 /// API compatibility test suite
 pub struct CompatibilityTests {
     rust_client: ApiClient,
     reference_client: Option<ApiClient>,
+    injection_token: Option<String>,
+    reference_node: Option<ReferenceNode>,
+}
+
+/// The mock node the reference server polls, and where it records snapshots
+pub struct ReferenceNode {
+    pub rpc: Arc<MockBitcoinRpc>,
+    pub snapshot_dir: PathBuf,
 }
 
 impl CompatibilityTests {
@@ -16,9 +35,23 @@ impl CompatibilityTests {
         Self {
             rust_client: ApiClient::new(rust_url),
             reference_client: reference_url.map(ApiClient::new),
+            injection_token: None,
+            reference_node: None,
         }
     }
 
+    /// Enable tests that inject snapshots through the server's test API
+    pub fn with_injection_token(mut self, token: impl Into<String>) -> Self {
+        self.injection_token = Some(token.into());
+        self
+    }
+
+    /// Feed the injected snapshots to the reference server through the mock node it polls
+    pub fn with_reference_node(mut self, node: ReferenceNode) -> Self {
+        self.reference_node = Some(node);
+        self
+    }
+
     /// Run all compatibility tests
     pub async fn run_all(&self) -> Result<TestResults> {
         let mut results = TestResults::new();
@@ -35,11 +68,26 @@ impl CompatibilityTests {
         // Test response format compatibility
         self.test_response_format(&mut results).await?;
 
+        // Test cache expiry metadata
+        self.test_valid_until(&mut results).await?;
+
         // If reference server available, run cross-implementation tests
         if self.reference_client.is_some() {
             self.test_cross_implementation(&mut results).await?;
         }
 
+        // Runs last since it replaces the server's stored snapshots. Both servers get the same
+        // mempool contents, the reference server one snapshot per collection cycle.
+        let snapshots = injection_history(Utc::now());
+        if let Some(token) = &self.injection_token {
+            self.test_injected_estimates(token, &snapshots, &mut results)
+                .await?;
+        }
+        if let (Some(client), Some(node)) = (&self.reference_client, &self.reference_node) {
+            self.test_reference_injected_estimates(client, node, &snapshots, &mut results)
+                .await?;
+        }
+
         results.print_summary();
         Ok(results)
     }
@@ -74,8 +122,19 @@ impl CompatibilityTests {
     async fn test_fee_targets(&self, results: &mut TestResults) -> Result<()> {
         info!("Testing /fees/target/num_blocks endpoint");
 
-        // Skip 1.0 as it's rarely used and may not be supported
-        let test_targets = vec![3.0, 6.0, 12.0, 24.0, 144.0];
+        // Every target the server reports estimating, or a sample of the defaults from servers
+        // that cannot tell
+        let test_targets = match self.rust_client.get_estimator_config().await {
+            Ok(Some(config)) => {
+                results.add_pass("GET /config/estimator", "Configuration discovered");
+                config.block_targets
+            }
+            Ok(None) => vec![3.0, 6.0, 12.0, 24.0, 144.0],
+            Err(e) => {
+                results.add_fail("GET /config/estimator", &format!("Request failed: {e}"));
+                vec![3.0, 6.0, 12.0, 24.0, 144.0]
+            }
+        };
 
         for target in test_targets {
             let test_name = format!("GET /fees/target/{target}");
@@ -97,32 +156,54 @@ impl CompatibilityTests {
         Ok(())
     }
 
-    /// Test error handling
+    /// Test that errors are RFC 7807 problem details with the expected codes
     async fn test_error_handling(&self, results: &mut TestResults) -> Result<()> {
         info!("Testing error handling");
 
-        // Test invalid block targets
-        let invalid_targets = vec![
-            ("negative", "-1"),
-            ("zero", "0"),
-            ("non-numeric", "abc"),
-            ("too_large", "10000"),
+        let cases = [
+            (
+                "Invalid target: negative",
+                "/fees/target/-1",
+                400,
+                "INVALID_TARGET",
+            ),
+            (
+                "Invalid target: zero",
+                "/fees/target/0",
+                400,
+                "INVALID_TARGET",
+            ),
+            (
+                "Invalid target: non-numeric",
+                "/fees/target/abc",
+                400,
+                "INVALID_TARGET",
+            ),
+            (
+                "Invalid target: too_large",
+                "/fees/target/10000",
+                400,
+                "INVALID_TARGET",
+            ),
+            (
+                "Missing timestamp",
+                "/historical_fee",
+                400,
+                "INVALID_REQUEST",
+            ),
+            ("Unknown endpoint", "/no/such/endpoint", 404, "NOT_FOUND"),
         ];
 
-        for (test_case, value) in invalid_targets {
-            let test_name = format!("Invalid target: {test_case}");
-            let path = format!("/fees/target/{value}");
-
-            match self.rust_client.get_raw(&path).await {
-                Ok((status, _body)) => {
-                    if status.as_u16() == 400 || status.as_u16() == 404 {
-                        results.add_pass(&test_name, &format!("Correctly returned {status}"));
-                    } else {
-                        results.add_fail(&test_name, &format!("Unexpected status: {status}"));
+        for (test_name, path, status, code) in cases {
+            match self.rust_client.get_problem(path).await {
+                Ok((actual, content_type, body)) => {
+                    match check_problem(actual, content_type.as_deref(), &body, status, &[code]) {
+                        Ok(()) => results.add_pass(test_name, &format!("Returned {status} {code}")),
+                        Err(e) => results.add_fail(test_name, &format!("{e:#}")),
                     }
                 }
                 Err(e) => {
-                    results.add_fail(&test_name, &format!("Request error: {e}"));
+                    results.add_fail(test_name, &format!("Request error: {e}"));
                 }
             }
         }
@@ -136,11 +217,17 @@ impl CompatibilityTests {
 
         let test_name = "Response format validation";
 
-        match self.rust_client.get_raw("/fees").await {
-            Ok((status, body)) => {
+        match self.rust_client.get_problem("/fees").await {
+            Ok((status, content_type, body)) => {
                 if status.as_u16() == 503 {
-                    // No data available is OK
-                    results.add_pass(test_name, "Service unavailable handled correctly");
+                    // No data available is OK, as long as the problem says why
+                    let codes = ["WARMING_UP", "UPSTREAM_RPC_DOWN"];
+                    match check_problem(status, content_type.as_deref(), &body, 503, &codes) {
+                        Ok(()) => {
+                            results.add_pass(test_name, "Service unavailable handled correctly")
+                        }
+                        Err(e) => results.add_fail(test_name, &format!("{e:#}")),
+                    }
                 } else if status.is_success() {
                     // Validate JSON structure matches expected format
                     if self.validate_json_format(&body) {
@@ -160,6 +247,40 @@ impl CompatibilityTests {
         Ok(())
     }
 
+    /// Test the `valid_until` cache expiry times the Rust server adds to fee responses
+    async fn test_valid_until(&self, results: &mut TestResults) -> Result<()> {
+        info!("Testing valid_until metadata");
+
+        for target in [None, Some(6.0)] {
+            let (test_name, response) = match target {
+                None => (
+                    "valid_until: /fees".to_string(),
+                    self.rust_client.get_fees().await,
+                ),
+                Some(blocks) => (
+                    format!("valid_until: /fees/target/{blocks}"),
+                    self.rust_client.get_fees_for_target(blocks).await,
+                ),
+            };
+
+            match response {
+                Ok(resp) if !resp.has_estimates() => {
+                    results.add_pass(&test_name, "No estimates to expire");
+                }
+                Ok(resp) if resp.valid_until.is_none() => {
+                    results.add_warning(&test_name, "No valid_until metadata (disabled?)");
+                }
+                Ok(resp) => match check_valid_until(&resp) {
+                    Ok(()) => results.add_pass(&test_name, "Expiry times consistent"),
+                    Err(e) => results.add_fail(&test_name, &format!("{e:#}")),
+                },
+                Err(e) => results.add_fail(&test_name, &format!("Request failed: {e}")),
+            }
+        }
+
+        Ok(())
+    }
+
     /// Test cross-implementation compatibility
     async fn test_cross_implementation(&self, results: &mut TestResults) -> Result<()> {
         if let Some(ref_client) = &self.reference_client {
@@ -171,10 +292,14 @@ impl CompatibilityTests {
                 .compare_endpoints(&self.rust_client, ref_client, "/fees")
                 .await
             {
-                Ok(differences) => {
+                Ok((differences, rust, reference)) => {
                     if differences.is_empty() {
                         results.add_pass(test_name, "Responses match");
                     } else {
+                        results.record_responses(
+                            test_name,
+                            [("rust", rust), ("reference", reference)],
+                        );
                         let msg = format!("{count} differences found", count = differences.len());
                         results.add_warning(test_name, &msg);
                         for diff in &differences {
@@ -196,10 +321,14 @@ impl CompatibilityTests {
                     .compare_endpoints(&self.rust_client, ref_client, &path)
                     .await
                 {
-                    Ok(differences) => {
+                    Ok((differences, rust, reference)) => {
                         if differences.is_empty() {
                             results.add_pass(&test_name, "Responses match");
                         } else {
+                            results.record_responses(
+                                &test_name,
+                                [("rust", rust), ("reference", reference)],
+                            );
                             results.add_warning(
                                 &test_name,
                                 &format!("{count} differences", count = differences.len()),
@@ -216,13 +345,158 @@ impl CompatibilityTests {
         Ok(())
     }
 
-    /// Compare responses from two endpoints
+    /// A library estimator with the confidence levels, block targets and inflow windows the
+    /// server reports, or the defaults from servers that cannot tell
+    async fn local_estimator(client: &ApiClient) -> Result<FeeEstimator> {
+        let Some(config) = client.get_estimator_config().await? else {
+            return Ok(FeeEstimator::new());
+        };
+        // Fast targets are flagged separately and not compared
+        let min_target = f64::from(BlockTarget::MIN_SIMULATED_BLOCKS);
+        let block_targets = config
+            .block_targets
+            .into_iter()
+            .filter(|&target| target >= min_target)
+            .collect();
+        FeeEstimator::with_config(
+            config.probabilities,
+            block_targets,
+            Duration::seconds(config.short_term_window_secs),
+            Duration::seconds(config.long_term_window_secs),
+        )
+        .context("Server reported an invalid estimator configuration")
+    }
+
+    /// Inject a known snapshot history and check the served estimates match the library
+    async fn test_injected_estimates(
+        &self,
+        token: &str,
+        snapshots: &[MempoolSnapshot],
+        results: &mut TestResults,
+    ) -> Result<()> {
+        info!("Testing estimates from injected snapshots");
+
+        let test_name = "Injected snapshots";
+        let estimator = match Self::local_estimator(&self.rust_client).await {
+            Ok(estimator) => estimator,
+            Err(e) => {
+                results.add_fail(test_name, &format!("Estimator setup failed: {e:#}"));
+                return Ok(());
+            }
+        };
+        let expected = match estimator.calculate_estimates(snapshots, None) {
+            Ok(estimate) => estimate,
+            Err(e) => {
+                results.add_fail(test_name, &format!("Local estimation failed: {e}"));
+                return Ok(());
+            }
+        };
+
+        let injected = match self
+            .rust_client
+            .inject_snapshots(token, snapshots, true)
+            .await
+        {
+            Ok(body) => body,
+            Err(e) => {
+                results.add_fail(test_name, &format!("Injection failed: {e}"));
+                return Ok(());
+            }
+        };
+
+        let served = match self.rust_client.get_fees().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                results.add_fail(test_name, &format!("Request failed: {e}"));
+                return Ok(());
+            }
+        };
+
+        // A collection cycle between injection and the request replaces the estimate
+        if injected.get("mempool_update_time").and_then(Value::as_str)
+            != Some(served.mempool_update_time.as_str())
+        {
+            results.add_warning(test_name, "Estimate was refreshed before it could be read");
+            return Ok(());
+        }
+
+        record_library_comparison(results, test_name, "rust", &served, &expected)?;
+        Ok(())
+    }
+
+    /// Load the injected snapshots into the mock node one collection cycle at a time and check
+    /// the reference server's estimates match the library
+    ///
+    /// The reference server timestamps what it records itself, so the library estimates from
+    /// the snapshots it recorded up to the served estimate.
+    async fn test_reference_injected_estimates(
+        &self,
+        client: &ApiClient,
+        node: &ReferenceNode,
+        snapshots: &[MempoolSnapshot],
+        results: &mut TestResults,
+    ) -> Result<()> {
+        info!("Testing reference estimates from the injected mempool");
+
+        let test_name = "Cross-impl: injected snapshots";
+        for snapshot in snapshots {
+            node.rpc.load_snapshot(snapshot);
+            if let Err(e) = wait_for_polls(&node.rpc, POLLS_PER_SNAPSHOT).await {
+                results.add_fail(test_name, &format!("Loading snapshots failed: {e}"));
+                return Ok(());
+            }
+        }
+
+        let served = match client.get_fees().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                results.add_fail(test_name, &format!("Request failed: {e}"));
+                return Ok(());
+            }
+        };
+        let expected = match Self::reference_library_estimate(client, node, &served).await {
+            Ok(estimate) => estimate,
+            Err(e) => {
+                results.add_fail(test_name, &format!("Local estimation failed: {e:#}"));
+                return Ok(());
+            }
+        };
+
+        record_library_comparison(results, test_name, "reference", &served, &expected)
+    }
+
+    /// The library's estimate from the snapshots the reference server recorded up to `served`
+    async fn reference_library_estimate(
+        client: &ApiClient,
+        node: &ReferenceNode,
+        served: &FeeEstimateResponse,
+    ) -> Result<FeeEstimate> {
+        let update_time = DateTime::parse_from_rfc3339(&served.mempool_update_time)
+            .with_context(|| {
+                format!(
+                    "Invalid mempool_update_time {time}",
+                    time = served.mempool_update_time
+                )
+            })?
+            .with_timezone(&Utc);
+        let mut recorded = read_recorded_snapshots(&node.snapshot_dir)?;
+        recorded.retain(|s| s.timestamp <= update_time);
+        ensure!(
+            !recorded.is_empty(),
+            "Reference server recorded no snapshots in {dir:?}",
+            dir = node.snapshot_dir
+        );
+        let estimator = Self::local_estimator(client).await?;
+        Ok(estimator.calculate_estimates(&recorded, None)?)
+    }
+
+    /// Compare responses from two endpoints, returning the differences and both bodies
     async fn compare_endpoints(
         &self,
         client1: &ApiClient,
         client2: &ApiClient,
         path: &str,
-    ) -> Result<Vec<String>> {
+    ) -> Result<(Vec<String>, Value, Value)> {
         let (status1, body1) = client1.get_raw(path).await?;
         let (status2, body2) = client2.get_raw(path).await?;
 
@@ -231,14 +505,17 @@ impl CompatibilityTests {
         // Compare status codes
         if status1 != status2 {
             differences.push(format!("Status code mismatch: {status1} vs {status2}"));
-            return Ok(differences);
+            return Ok((differences, body1, body2));
         }
 
-        // Compare JSON bodies
+        // Compare JSON bodies, ignoring metadata only the Rust server publishes
+        let (mut body1, mut body2) = (body1, body2);
+        strip_rust_metadata(&mut body1);
+        strip_rust_metadata(&mut body2);
         let json_diffs = ResponseComparator::compare_json(&body1, &body2, "");
         differences.extend(json_diffs);
 
-        Ok(differences)
+        Ok((differences, body1, body2))
     }
 
     /// Validate fee response structure
@@ -328,14 +605,197 @@ impl CompatibilityTests {
     }
 }
 
+/// Checks that every block target expires after the estimate was made, that longer targets
+/// never expire earlier, and that the response expires with its earliest target
+pub fn check_valid_until(response: &FeeEstimateResponse) -> Result<()> {
+    let parse = |field: &str, value: &str| -> Result<DateTime<Utc>> {
+        let time = DateTime::parse_from_rfc3339(value)
+            .with_context(|| format!("Invalid {field} {value}"))?;
+        Ok(time.with_timezone(&Utc))
+    };
+
+    let updated = parse("mempool_update_time", &response.mempool_update_time)?;
+    let valid_until = response
+        .valid_until
+        .as_deref()
+        .context("Response has no valid_until")?;
+    let valid_until = parse("valid_until", valid_until)?;
+
+    let mut targets = Vec::new();
+    for (blocks, target) in &response.estimates {
+        let blocks: u32 = blocks
+            .parse()
+            .with_context(|| format!("Invalid block target {blocks}"))?;
+        let expiry = target
+            .valid_until
+            .as_deref()
+            .with_context(|| format!("Target {blocks} has no valid_until"))?;
+        let expiry = parse("valid_until", expiry)?;
+        ensure!(
+            expiry > updated,
+            "Target {blocks} expires at {expiry}, not after its update at {updated}"
+        );
+        targets.push((blocks, expiry));
+    }
+    targets.sort();
+
+    for pair in targets.windows(2) {
+        let ((short, short_expiry), (long, long_expiry)) = (pair[0], pair[1]);
+        ensure!(
+            long_expiry >= short_expiry,
+            "Target {long} expires at {long_expiry}, before target {short} at {short_expiry}"
+        );
+    }
+    let earliest = targets.iter().map(|&(_, expiry)| expiry).min();
+    ensure!(
+        earliest == Some(valid_until),
+        "Response valid_until {valid_until} differs from its earliest target {earliest:?}"
+    );
+
+    Ok(())
+}
+
+/// Checks that an error response is an RFC 7807 problem with `status` and one of `codes`
+pub fn check_problem(
+    status: StatusCode,
+    content_type: Option<&str>,
+    body: &Value,
+    expected_status: u16,
+    codes: &[&str],
+) -> Result<()> {
+    ensure!(
+        status.as_u16() == expected_status,
+        "Expected status {expected_status}, got {status}"
+    );
+    ensure!(
+        content_type == Some("application/problem+json"),
+        "Expected application/problem+json, got {content_type:?}"
+    );
+    ensure!(
+        body["status"] == expected_status,
+        "Problem status {actual} differs from the response status",
+        actual = body["status"]
+    );
+    for field in ["type", "title", "detail"] {
+        ensure!(body[field].is_string(), "Problem has no {field}");
+    }
+    let code = body["code"].as_str().context("Problem has no code")?;
+    ensure!(codes.contains(&code), "Expected code {codes:?}, got {code}");
+    Ok(())
+}
+
+/// Removes `valid_until` from a fee response and its block targets, and its `data_age_seconds`
+fn strip_rust_metadata(body: &mut Value) {
+    let Some(response) = body.as_object_mut() else {
+        return;
+    };
+    response.remove("valid_until");
+    response.remove("data_age_seconds");
+    if let Some(estimates) = response.get_mut("estimates").and_then(Value::as_object_mut) {
+        for target in estimates.values_mut().filter_map(Value::as_object_mut) {
+            target.remove("valid_until");
+        }
+    }
+}
+
+/// Deterministic three-hour snapshot history ending at `end`, one block every 30 minutes
+pub fn injection_history(end: DateTime<Utc>) -> Vec<MempoolSnapshot> {
+    const SNAPSHOTS: u32 = 19;
+    const START_HEIGHT: u32 = 850_000;
+
+    (0..SNAPSHOTS)
+        .map(|i| {
+            let timestamp = end - Duration::minutes(i64::from((SNAPSHOTS - 1 - i) * 10));
+            let height = START_HEIGHT + i / 3;
+            // Backlog grows between blocks and is partially cleared when one is found
+            let backlog = 1 + (i % 3) as u64;
+            let transactions = (1..=60u64)
+                .map(|n| {
+                    let weight = 2_000 + (n * 137 + u64::from(i) * 53) % 4_000;
+                    let fee_rate = 1.0 + (n as f64).powf(1.3) * 0.5;
+                    let fee = (fee_rate * weight as f64 / 4.0).round() as u64;
+                    MempoolTransaction::new(weight * backlog * 10, fee * backlog * 10)
+                })
+                .collect();
+            MempoolSnapshot::from_transactions(transactions, height, timestamp)
+        })
+        .collect()
+}
+
+/// Records whether every fee rate of `expected` was served within tolerance by `implementation`
+fn record_library_comparison(
+    results: &mut TestResults,
+    test_name: &str,
+    implementation: &str,
+    served: &FeeEstimateResponse,
+    expected: &FeeEstimate,
+) -> Result<()> {
+    let mut mismatches = Vec::new();
+    let mut compared = 0;
+    for (blocks, target) in expected.targets() {
+        for (probability, &expected_rate) in &target.probabilities {
+            let probability = probability.0;
+            let actual = served
+                .estimates
+                .get(&blocks.to_string())
+                .and_then(|t| t.get_fee_rate(probability));
+            match actual {
+                Some(actual) if within_tolerance(actual, expected_rate) => compared += 1,
+                Some(actual) => mismatches.push(format!(
+                    "{blocks} blocks @ {probability}: expected {expected_rate:.4}, got {actual:.4}"
+                )),
+                None => mismatches.push(format!("{blocks} blocks @ {probability}: missing")),
+            }
+        }
+    }
+
+    if compared == 0 && mismatches.is_empty() {
+        results.add_fail(test_name, "Library produced no estimates to compare");
+    } else if mismatches.is_empty() {
+        results.add_pass(
+            test_name,
+            &format!("{compared} fee rates match the library"),
+        );
+    } else {
+        for mismatch in &mismatches {
+            debug!("  - {mismatch}");
+        }
+        results.record_responses(
+            test_name,
+            [
+                (implementation, serde_json::to_value(served)?),
+                ("library", serde_json::to_value(expected)?),
+            ],
+        );
+        results.add_fail(
+            test_name,
+            &format!("{count} fee rates differ", count = mismatches.len()),
+        );
+    }
+    Ok(())
+}
+
+fn within_tolerance(actual: f64, expected: f64) -> bool {
+    (actual - expected).abs() <= expected.abs().max(1.0) * INJECTED_FEE_TOLERANCE
+}
+
 /// Test results tracker
 pub struct TestResults {
     passed: Vec<TestResult>,
     failed: Vec<TestResult>,
     warnings: Vec<TestResult>,
+    responses: Vec<RecordedResponses>,
     start_time: std::time::Instant,
 }
 
+/// The responses a test compared, kept so a failure can be inspected after the servers stop
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedResponses {
+    pub test: String,
+    /// Each response under the name of the side that produced it
+    pub responses: serde_json::Map<String, Value>,
+}
+
 struct TestResult {
     name: String,
     message: String,
@@ -347,6 +807,7 @@ impl TestResults {
             passed: Vec::new(),
             failed: Vec::new(),
             warnings: Vec::new(),
+            responses: Vec::new(),
             start_time: std::time::Instant::now(),
         }
     }
@@ -387,6 +848,26 @@ impl TestResults {
         );
     }
 
+    /// Keeps the responses `name` compared, such as the Rust and reference bodies
+    pub fn record_responses<'a>(
+        &mut self,
+        name: &str,
+        responses: impl IntoIterator<Item = (&'a str, Value)>,
+    ) {
+        self.responses.push(RecordedResponses {
+            test: name.to_string(),
+            responses: responses
+                .into_iter()
+                .map(|(side, body)| (side.to_string(), body))
+                .collect(),
+        });
+    }
+
+    /// Responses recorded by tests that compared them
+    pub fn responses(&self) -> &[RecordedResponses] {
+        &self.responses
+    }
+
     pub fn print_summary(&self) {
         let duration = self.start_time.elapsed();
 
@@ -439,8 +920,144 @@ impl TestResults {
         self.failed.is_empty()
     }
 
+    /// Name and message of every failed test
+    pub fn failures(&self) -> Vec<String> {
+        self.failed
+            .iter()
+            .map(|test| {
+                format!(
+                    "{name}: {message}",
+                    name = test.name,
+                    message = test.message
+                )
+            })
+            .collect()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.warnings.len()
+    }
+
     #[allow(dead_code)]
     pub fn total_tests(&self) -> usize {
         self.passed.len() + self.failed.len() + self.warnings.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_injection_history_produces_estimates() {
+        let end = Utc::now();
+        let snapshots = injection_history(end);
+
+        assert_eq!(snapshots.len(), 19);
+        assert_eq!(snapshots.last().map(|s| s.timestamp), Some(end));
+        assert!(snapshots
+            .windows(2)
+            .all(|w| w[0].block_height <= w[1].block_height));
+
+        let estimate = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+        assert!(estimate.get_fee_rate(6, 0.5).is_some());
+    }
+
+    fn response(targets: &[(&str, &str)], valid_until: Option<&str>) -> FeeEstimateResponse {
+        let estimates = targets
+            .iter()
+            .map(|&(blocks, expiry)| {
+                let target = bitcoin_augur_api_types::BlockTargetResponse {
+                    valid_until: Some(expiry.to_string()),
+                    ..Default::default()
+                };
+                (blocks.to_string(), target)
+            })
+            .collect();
+        FeeEstimateResponse {
+            mempool_update_time: "2025-01-20T12:00:00.000Z".to_string(),
+            estimates,
+            valid_until: valid_until.map(str::to_string),
+            degraded: false,
+            data_age_seconds: None,
+            fiat: None,
+        }
+    }
+
+    #[test]
+    fn test_check_valid_until() {
+        let targets = [
+            ("3", "2025-01-20T12:03:00.000Z"),
+            ("12", "2025-01-20T12:12:00.000Z"),
+        ];
+        assert!(check_valid_until(&response(&targets, Some("2025-01-20T12:03:00.000Z"))).is_ok());
+
+        // Response must expire with its earliest target
+        assert!(check_valid_until(&response(&targets, Some("2025-01-20T12:12:00.000Z"))).is_err());
+        assert!(check_valid_until(&response(&targets, None)).is_err());
+
+        // Longer targets must not expire first, and nothing may expire before its update
+        let inverted = [
+            ("3", "2025-01-20T12:12:00.000Z"),
+            ("12", "2025-01-20T12:03:00.000Z"),
+        ];
+        assert!(check_valid_until(&response(&inverted, Some("2025-01-20T12:03:00.000Z"))).is_err());
+        let expired = [("3", "2025-01-20T11:59:00.000Z")];
+        assert!(check_valid_until(&response(&expired, Some("2025-01-20T11:59:00.000Z"))).is_err());
+    }
+
+    #[test]
+    fn test_check_problem() {
+        let body = serde_json::json!({
+            "type": "about:blank",
+            "title": "Bad Request",
+            "status": 400,
+            "detail": "Invalid number of blocks: must be between 1 and 1000",
+            "code": "INVALID_TARGET"
+        });
+        let problem = Some("application/problem+json");
+        let bad_request = StatusCode::BAD_REQUEST;
+        assert!(check_problem(bad_request, problem, &body, 400, &["INVALID_TARGET"]).is_ok());
+
+        assert!(check_problem(bad_request, problem, &body, 400, &["NO_DATA"]).is_err());
+        assert!(check_problem(
+            StatusCode::NOT_FOUND,
+            problem,
+            &body,
+            404,
+            &["INVALID_TARGET"]
+        )
+        .is_err());
+        let json = Some("application/json");
+        assert!(check_problem(bad_request, json, &body, 400, &["INVALID_TARGET"]).is_err());
+        let bare = serde_json::json!({"status": 400, "code": "INVALID_TARGET"});
+        assert!(check_problem(bad_request, problem, &bare, 400, &["INVALID_TARGET"]).is_err());
+    }
+
+    #[test]
+    fn test_strip_rust_metadata() {
+        let mut body = serde_json::json!({
+            "mempool_update_time": "2025-01-20T12:00:00.000Z",
+            "valid_until": "2025-01-20T12:03:00.000Z",
+            "data_age_seconds": 12,
+            "estimates": {"3": {"probabilities": {}, "valid_until": "2025-01-20T12:03:00.000Z"}}
+        });
+        strip_rust_metadata(&mut body);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "mempool_update_time": "2025-01-20T12:00:00.000Z",
+                "estimates": {"3": {"probabilities": {}}}
+            })
+        );
+    }
+
+    #[test]
+    fn test_within_tolerance() {
+        assert!(within_tolerance(10.0, 10.005));
+        assert!(!within_tolerance(10.0, 10.5));
+        assert!(within_tolerance(0.0, 0.0005));
+    }
+}
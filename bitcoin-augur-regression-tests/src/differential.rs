@@ -0,0 +1,567 @@
+//! Differential fuzzing of the estimator against a second implementation
+//!
+//! Every case is a random snapshot sequence with explicit timestamps, derived from a seed so
+//! it can be replayed. The in-process library provides the expected estimate and the target
+//! is either the Kotlin reference, which polls each snapshot from the mock RPC, or a Rust
+//! server fed through the test injection API. Divergent cases are shrunk by dropping
+//! snapshots and buckets while the divergence persists, then saved as regression vector files
+//! that `vectors --vectors-file` replays.
+
+use anyhow::{bail, ensure, Context, Result};
+use bitcoin_augur::MempoolSnapshot;
+use chrono::{DateTime, Duration, Utc};
+use colored::Colorize;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info, warn};
+
+use crate::api_client::{ApiClient, FeeEstimateResponse};
+use crate::comparison::ToleranceModel;
+use crate::mock_rpc::MockBitcoinRpc;
+use crate::server::ReferenceServerManager;
+use crate::test_vectors::{
+    compare, estimate, ExpectedEstimates, ProbabilityValidation, TestVector, TestVectorResult,
+    TestVectorRunner, VectorFile, SCHEMA_VERSION,
+};
+use crate::traces::{trace_window, TraceModel};
+
+/// Mempool polls the reference server must make after each snapshot is loaded, so that one
+/// complete collection cycle observed it
+pub(crate) const POLLS_PER_SNAPSHOT: u64 = 2;
+
+/// How long to wait for the reference server to poll the mock node
+const POLL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Attempts at reading an injected estimate before a collection cycle replaces it
+const INJECTION_ATTEMPTS: usize = 3;
+
+/// Differential fuzzing settings
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    /// Seed of the first case; case `i` uses `seed + i`
+    pub seed: u64,
+    pub cases: usize,
+    pub max_snapshots: usize,
+    /// Allowed fee rate difference
+    pub tolerance: ToleranceModel,
+    /// Target runs spent shrinking each divergent case
+    pub max_shrink_runs: usize,
+    /// Directory for minimized divergent cases
+    pub output: PathBuf,
+    pub generator: CaseGenerator,
+}
+
+/// Source of the snapshot sequence of each case
+#[derive(Debug, Clone)]
+pub enum CaseGenerator {
+    /// Random buckets at random intervals, independent between snapshots
+    Uniform,
+    /// Simulated mempool dynamics
+    Realistic(TraceModel),
+    /// Windows of recorded traces, one trace picked per case
+    Trace(Vec<Vec<MempoolSnapshot>>),
+}
+
+impl CaseGenerator {
+    /// Generates the snapshot sequence for `seed`, ending at `end`
+    pub fn generate(
+        &self,
+        seed: u64,
+        max_snapshots: usize,
+        end: DateTime<Utc>,
+    ) -> Vec<MempoolSnapshot> {
+        match self {
+            Self::Uniform => generate_case(seed, max_snapshots, end),
+            Self::Realistic(model) => {
+                let count = StdRng::seed_from_u64(seed).random_range(1..=max_snapshots.max(1));
+                model.generate(seed, count, end)
+            }
+            Self::Trace(traces) => {
+                let trace = &traces[seed as usize % traces.len().max(1)];
+                trace_window(trace, seed, max_snapshots, end)
+            }
+        }
+    }
+}
+
+/// Implementation compared against the in-process library
+pub enum DifferentialTarget {
+    /// Kotlin reference server polling a mock node
+    Reference {
+        manager: Box<ReferenceServerManager>,
+        rpc: Arc<MockBitcoinRpc>,
+        rpc_task: JoinHandle<()>,
+        client: ApiClient,
+    },
+    /// Rust server with the test injection API enabled
+    Injection { client: ApiClient, token: String },
+}
+
+/// Result of running one snapshot sequence through the library and the target
+struct Outcome {
+    /// Snapshots the expected estimate was computed from
+    inputs: Vec<MempoolSnapshot>,
+    expected: ExpectedEstimates,
+    mismatches: Vec<ProbabilityValidation>,
+}
+
+/// Generates the uniform snapshot sequence for `seed`, ending at `end`
+pub fn generate_case(seed: u64, max_snapshots: usize, end: DateTime<Utc>) -> Vec<MempoolSnapshot> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let count = rng.random_range(1..=max_snapshots.max(1));
+
+    let mut offsets_minutes = Vec::with_capacity(count);
+    let mut elapsed = 0i64;
+    for index in 0..count {
+        if index > 0 {
+            elapsed += rng.random_range(1..=20);
+        }
+        offsets_minutes.push(elapsed);
+    }
+    let start = end - Duration::minutes(elapsed);
+
+    let mut height = 850_000u32;
+    offsets_minutes
+        .into_iter()
+        .map(|offset| {
+            if rng.random_bool(0.3) {
+                height += rng.random_range(1..=2);
+            }
+
+            // Occasionally model a fully drained mempool
+            let buckets = if rng.random_bool(0.1) {
+                0
+            } else {
+                rng.random_range(1..=40)
+            };
+            let mut bucketed_weights = BTreeMap::new();
+            for _ in 0..buckets {
+                let bucket = rng.random_range(0..=1_000);
+                let weight = rng.random_range(1..=2_000_000u64);
+                *bucketed_weights.entry(bucket).or_insert(0) += weight;
+            }
+
+            MempoolSnapshot::new(height, start + Duration::minutes(offset), bucketed_weights)
+        })
+        .collect()
+}
+
+/// Smaller variants of `snapshots`: each without one snapshot, then each without one bucket
+fn shrink_candidates(snapshots: &[MempoolSnapshot]) -> Vec<Vec<MempoolSnapshot>> {
+    let mut candidates = Vec::new();
+
+    if snapshots.len() > 1 {
+        for index in 0..snapshots.len() {
+            let mut candidate = snapshots.to_vec();
+            candidate.remove(index);
+            candidates.push(candidate);
+        }
+    }
+
+    for (index, snapshot) in snapshots.iter().enumerate() {
+        for bucket in snapshot.bucketed_weights.keys() {
+            let mut candidate = snapshots.to_vec();
+            candidate[index].bucketed_weights.remove(bucket);
+            candidates.push(candidate);
+        }
+    }
+
+    candidates
+}
+
+/// Converts an API response into the regression vector form
+pub(crate) fn served_estimates(response: &FeeEstimateResponse) -> ExpectedEstimates {
+    response
+        .estimates
+        .iter()
+        .map(|(blocks, target)| {
+            let probabilities = target
+                .probabilities
+                .iter()
+                .map(|(probability, rate)| (probability.clone(), Some(rate.fee_rate)))
+                .collect();
+            (blocks.clone(), probabilities)
+        })
+        .collect()
+}
+
+impl DifferentialTarget {
+    /// Starts a mock node on `rpc_port` and the reference server polling it every second
+    pub fn reference(jar_path: PathBuf, port: u16, rpc_port: u16, data_dir: PathBuf) -> Self {
+        let rpc = Arc::new(MockBitcoinRpc::new(rpc_port));
+        let rpc_task = tokio::spawn({
+            let rpc = rpc.clone();
+            async move {
+                if let Err(e) = rpc.start().await {
+                    warn!("Mock RPC server stopped: {e}");
+                }
+            }
+        });
+
+        let manager = ReferenceServerManager::new(jar_path, port, data_dir)
+            .with_rpc_url(rpc.url())
+            .with_refresh_interval(1);
+        let client = ApiClient::new(manager.url());
+
+        Self::Reference {
+            manager: Box::new(manager),
+            rpc,
+            rpc_task,
+            client,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Reference { .. } => "Kotlin reference",
+            Self::Injection { .. } => "Rust server",
+        }
+    }
+
+    /// Feeds `snapshots` to the target and compares its estimate with the library's
+    async fn run(
+        &mut self,
+        snapshots: &[MempoolSnapshot],
+        tolerance: &ToleranceModel,
+    ) -> Result<Outcome> {
+        let mut sorted = snapshots.to_vec();
+        sorted.sort_by_key(|s| s.timestamp);
+
+        let (inputs, served) = match self {
+            Self::Reference {
+                manager,
+                rpc,
+                client,
+                ..
+            } => {
+                // Restart on an empty data directory so earlier cases do not leak in
+                manager.stop().await?;
+                let snapshot_dir = manager.snapshot_dir();
+                if snapshot_dir.exists() {
+                    tokio::fs::remove_dir_all(&snapshot_dir)
+                        .await
+                        .with_context(|| format!("Failed to clear {snapshot_dir:?}"))?;
+                }
+                manager.start().await?;
+
+                for snapshot in &sorted {
+                    rpc.load_snapshot(snapshot);
+                    wait_for_polls(rpc, POLLS_PER_SNAPSHOT).await?;
+                }
+
+                let served = client.get_fees().await?;
+                let update_time = DateTime::parse_from_rfc3339(&served.mempool_update_time)
+                    .with_context(|| {
+                        format!(
+                            "Invalid mempool_update_time {time}",
+                            time = served.mempool_update_time
+                        )
+                    })?
+                    .with_timezone(&Utc);
+
+                // The reference timestamps snapshots itself, so the library must estimate
+                // from what it recorded up to the served estimate
+                let mut recorded = read_recorded_snapshots(&snapshot_dir)?;
+                recorded.retain(|s| s.timestamp <= update_time);
+                ensure!(
+                    !recorded.is_empty(),
+                    "Reference server recorded no snapshots in {snapshot_dir:?}"
+                );
+                (recorded, served)
+            }
+            Self::Injection { client, token } => {
+                let mut attempt = 0;
+                loop {
+                    attempt += 1;
+                    let injected = client.inject_snapshots(token, &sorted, true).await?;
+                    let served = client.get_fees().await?;
+
+                    // A collection cycle between injection and the request replaces the estimate
+                    let fresh = injected
+                        .get("mempool_update_time")
+                        .and_then(|v| v.as_str())
+                        .is_none_or(|time| time == served.mempool_update_time);
+                    if fresh {
+                        break (sorted, served);
+                    }
+                    ensure!(
+                        attempt < INJECTION_ATTEMPTS,
+                        "Injected estimate was replaced before it could be read"
+                    );
+                    debug!("Injected estimate was refreshed, retrying");
+                }
+            }
+        };
+
+        let expected = estimate(&inputs, None)?;
+        let mismatches = compare(&expected, &served_estimates(&served), tolerance)
+            .into_iter()
+            .filter(|v| !v.passed)
+            .collect();
+
+        Ok(Outcome {
+            inputs,
+            expected,
+            mismatches,
+        })
+    }
+
+    /// Shrinks a divergent case, returning the smallest divergent outcome found
+    async fn minimize(
+        &mut self,
+        mut outcome: Outcome,
+        tolerance: &ToleranceModel,
+        max_runs: usize,
+    ) -> Result<Outcome> {
+        let mut runs = 0;
+
+        'shrink: while runs < max_runs {
+            for candidate in shrink_candidates(&outcome.inputs) {
+                if runs >= max_runs {
+                    break 'shrink;
+                }
+                runs += 1;
+
+                let smaller = self.run(&candidate, tolerance).await?;
+                if !smaller.mismatches.is_empty() {
+                    debug!("Shrunk to {count} snapshots", count = smaller.inputs.len());
+                    outcome = smaller;
+                    continue 'shrink;
+                }
+            }
+            // No smaller variant still diverges
+            break;
+        }
+
+        info!(
+            "Minimized case to {count} snapshots after {runs} runs",
+            count = outcome.inputs.len()
+        );
+        Ok(outcome)
+    }
+
+    async fn shutdown(self) -> Result<()> {
+        if let Self::Reference {
+            mut manager,
+            rpc_task,
+            ..
+        } = self
+        {
+            manager.stop().await?;
+            rpc_task.abort();
+        }
+        Ok(())
+    }
+}
+
+/// Waits until the mock node has served `count` more mempool polls
+pub(crate) async fn wait_for_polls(rpc: &MockBitcoinRpc, count: u64) -> Result<()> {
+    let target = rpc.mempool_polls() + count;
+    let deadline = Instant::now() + POLL_TIMEOUT;
+    while rpc.mempool_polls() < target {
+        ensure!(
+            Instant::now() < deadline,
+            "Reference server stopped polling the mock node"
+        );
+        sleep(std::time::Duration::from_millis(100)).await;
+    }
+    Ok(())
+}
+
+/// Reads every snapshot file below `dir`
+pub(crate) fn read_recorded_snapshots(dir: &Path) -> Result<Vec<MempoolSnapshot>> {
+    let mut snapshots = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {dir:?}"))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {path:?}"))?;
+                match serde_json::from_str(&content) {
+                    Ok(snapshot) => snapshots.push(snapshot),
+                    Err(e) => warn!("Skipping unreadable snapshot {path:?}: {e}"),
+                }
+            }
+        }
+    }
+
+    snapshots.sort_by_key(|s: &MempoolSnapshot| s.timestamp);
+    Ok(snapshots)
+}
+
+/// Runs `config.cases` random cases against `target`, saving each minimized divergence
+pub async fn run_differential(mut target: DifferentialTarget, config: &FuzzConfig) -> Result<()> {
+    config.tolerance.validate().map_err(anyhow::Error::msg)?;
+
+    info!(
+        "Differential fuzzing {cases} cases against the {name} (seed {seed})",
+        cases = config.cases,
+        name = target.name(),
+        seed = config.seed
+    );
+
+    let mut failures = Vec::new();
+    for case in 0..config.cases {
+        let seed = config.seed.wrapping_add(case as u64);
+        let snapshots = config
+            .generator
+            .generate(seed, config.max_snapshots, Utc::now());
+
+        let outcome = match target.run(&snapshots, &config.tolerance).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                target.shutdown().await?;
+                return Err(e).with_context(|| format!("Case {seed} could not be run"));
+            }
+        };
+        if outcome.mismatches.is_empty() {
+            debug!("Case {seed}: estimates agree");
+            continue;
+        }
+
+        warn!(
+            "Case {seed}: {count} fee rates diverge, minimizing",
+            count = outcome.mismatches.len()
+        );
+        let outcome = target
+            .minimize(outcome, &config.tolerance, config.max_shrink_runs)
+            .await?;
+        let path = save_failure(&config.output, seed, &outcome, &config.tolerance).await?;
+
+        TestVectorResult {
+            name: format!("case-{seed}"),
+            passed: false,
+            validations: outcome.mismatches,
+        }
+        .print_summary();
+        println!("  Saved to {path:?}");
+        failures.push(seed);
+    }
+
+    target.shutdown().await?;
+
+    if failures.is_empty() {
+        let message = format!("All {cases} cases agree", cases = config.cases)
+            .green()
+            .bold();
+        println!("\n{message}");
+        Ok(())
+    } else {
+        bail!(
+            "{count} of {cases} cases diverged (seeds: {failures:?})",
+            count = failures.len(),
+            cases = config.cases
+        )
+    }
+}
+
+/// Writes a minimized divergence as a single-vector regression file
+async fn save_failure(
+    output: &Path,
+    seed: u64,
+    outcome: &Outcome,
+    tolerance: &ToleranceModel,
+) -> Result<PathBuf> {
+    let file = VectorFile {
+        schema_version: SCHEMA_VERSION,
+        generator: format!(
+            "bitcoin-augur-rust {version}",
+            version = env!("CARGO_PKG_VERSION")
+        ),
+        tolerance: tolerance.relative,
+        tolerance_model: Some(tolerance.clone()),
+        vectors: vec![TestVector {
+            name: format!("differential-{seed}"),
+            description: format!(
+                "Minimized differential fuzzing case for seed {seed}; expected values are the library's"
+            ),
+            snapshots: outcome.inputs.clone(),
+            num_blocks: None,
+            expected: outcome.expected.clone(),
+        }],
+    };
+
+    let path = output.join(format!("case-{seed}.json"));
+    TestVectorRunner::save_vectors(&file, &path).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cases_are_reproducible() {
+        let end = Utc::now();
+        for seed in 0..20 {
+            let first = generate_case(seed, 12, end);
+            let second = generate_case(seed, 12, end);
+            assert_eq!(
+                serde_json::to_value(&first).unwrap(),
+                serde_json::to_value(&second).unwrap()
+            );
+            assert!((1..=12).contains(&first.len()));
+            assert!(first.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+            assert_eq!(first.last().map(|s| s.timestamp), Some(end));
+        }
+        assert_ne!(
+            serde_json::to_value(generate_case(1, 12, end)).unwrap(),
+            serde_json::to_value(generate_case(2, 12, end)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_shrink_candidates_remove_one_element() {
+        let snapshots = generate_case(7, 4, Utc::now());
+        let bucket_count: usize = snapshots.iter().map(|s| s.bucketed_weights.len()).sum();
+        let removable_snapshots = if snapshots.len() > 1 {
+            snapshots.len()
+        } else {
+            0
+        };
+
+        let candidates = shrink_candidates(&snapshots);
+        assert_eq!(candidates.len(), removable_snapshots + bucket_count);
+
+        let total = |s: &[MempoolSnapshot]| -> usize {
+            s.iter().map(|s| s.bucketed_weights.len() + 1).sum()
+        };
+        for candidate in &candidates {
+            assert!(total(candidate) < total(&snapshots));
+        }
+    }
+
+    #[test]
+    fn test_served_estimates_compare_with_library() {
+        let snapshots = generate_case(3, 10, Utc::now());
+        let expected = estimate(&snapshots, None).unwrap();
+
+        // Serve the library's own estimate rounded the way the API rounds it
+        let served: FeeEstimateResponse = serde_json::from_value(serde_json::json!({
+            "mempool_update_time": "2025-01-20T12:00:00.000Z",
+            "estimates": expected.iter().map(|(blocks, target)| {
+                let probabilities: serde_json::Map<_, _> = target.iter().map(|(p, rate)| {
+                    let rounded = rate.map(|r| (r * 10_000.0).round() / 10_000.0);
+                    (p.clone(), serde_json::json!({ "fee_rate": rounded }))
+                }).collect();
+                (blocks.clone(), serde_json::json!({ "probabilities": probabilities }))
+            }).collect::<serde_json::Map<_, _>>()
+        }))
+        .unwrap();
+
+        let validations = compare(
+            &expected,
+            &served_estimates(&served),
+            &ToleranceModel::default(),
+        );
+        assert!(validations.iter().all(|v| v.passed));
+    }
+}
@@ -1,17 +1,29 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 use tracing_subscriber::EnvFilter;
 
 mod api_client;
+mod artifacts;
+mod bisect;
+mod chaos;
+mod comparison;
 mod compatibility;
+mod differential;
+mod matrix;
 mod mock_rpc;
+mod persistence;
+mod report;
+mod reproducibility;
 mod runner;
+mod scenario;
 mod server;
 mod snapshots;
 mod stress;
 mod test_cases;
 mod test_vectors;
+mod traces;
+mod upgrade;
 
 use runner::TestRunner;
 
@@ -49,7 +61,8 @@ struct Cli {
     #[arg(long, short = 'f')]
     filter: Option<String>,
 
-    /// Number of parallel tests
+    /// Test cases run at once; servers run at most one per CPU core, and one at a time with
+    /// --port
     #[arg(long, short = 'j', default_value = "4")]
     jobs: usize,
 
@@ -85,6 +98,15 @@ enum Commands {
         with_reference: bool,
     },
 
+    /// Run the compatibility suite against the Rust server in every configuration of a matrix
+    ///
+    /// `--filter` selects configurations by name.
+    Matrix {
+        /// JSON file listing the server configurations (default: the bundled matrix)
+        #[arg(long)]
+        matrix_file: Option<PathBuf>,
+    },
+
     /// Run snapshot tests only
     Snapshots {
         /// Force update all snapshots
@@ -99,6 +121,60 @@ enum Commands {
         vectors_file: Option<PathBuf>,
     },
 
+    /// Replay the estimator stage by stage and report the first stage where this build, or a
+    /// recorded trace, diverges from another code path's trace of the same inputs
+    ///
+    /// Record a trace with `--output` on each code path, such as the previous commit, then
+    /// compare: one `--compare` file is checked against this build, two against each other.
+    Bisect {
+        /// Path to test vectors JSON file (default: the bundled vectors)
+        #[arg(long)]
+        vectors_file: Option<PathBuf>,
+
+        /// Trace the snapshots of a server data directory instead of test vectors
+        #[arg(long, conflicts_with = "vectors_file")]
+        snapshot_dir: Option<PathBuf>,
+
+        /// Write this build's stage trace to a file
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Stage traces recorded by other code paths, at most two
+        #[arg(long)]
+        compare: Vec<PathBuf>,
+
+        /// Units in the last place a value may differ by
+        #[arg(long, default_value = "0")]
+        max_ulps: u64,
+    },
+
+    /// Compare this build's exact estimates for the regression vectors with fingerprints recorded on
+    /// other platforms, detecting floating-point nondeterminism
+    ///
+    /// Compares with the bundled fingerprint when neither `--compare` nor `--vectors-file` is
+    /// given.
+    Reproducibility {
+        /// Path to test vectors JSON file (default: the bundled vectors)
+        #[arg(long)]
+        vectors_file: Option<PathBuf>,
+
+        /// Write this platform's fingerprint to a file
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Fingerprint files to compare with, such as one recorded on another architecture
+        #[arg(long)]
+        compare: Vec<PathBuf>,
+
+        /// Units in the last place an estimate may differ by
+        #[arg(long, default_value = "0")]
+        max_ulps: u64,
+
+        /// Times each vector is estimated, all of which must agree
+        #[arg(long, default_value = "3")]
+        repeat: usize,
+    },
+
     /// Generate test data for regression testing
     Generate {
         /// Output directory for generated data
@@ -110,6 +186,154 @@ enum Commands {
         count: usize,
     },
 
+    /// Drive a fixed request rate against the server and report latency percentiles
+    Stress {
+        /// Server URL to test (default: start the server binary)
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Requests per second
+        #[arg(long, default_value = "50")]
+        rps: f64,
+
+        /// Test duration in seconds
+        #[arg(long, default_value = "10")]
+        duration_secs: u64,
+
+        /// Endpoints to request in round-robin order
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "/fees,/fees/target/3,/fees/target/6,/fees/target/144"
+        )]
+        endpoints: Vec<String>,
+
+        /// JSON file with latency and error rate thresholds; exceeding any fails the run
+        #[arg(long)]
+        thresholds: Option<PathBuf>,
+    },
+
+    /// Compare estimates for random snapshot sequences with the library, shrinking divergent cases
+    ///
+    /// Targets the reference implementation when `--reference-jar` is set, otherwise the Rust
+    /// server through its test injection API.
+    Differential {
+        /// Number of random cases
+        #[arg(long, default_value = "50")]
+        cases: usize,
+
+        /// Seed of the first case (default: random)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Maximum snapshots per case
+        #[arg(long, default_value = "24")]
+        max_snapshots: usize,
+
+        #[command(flatten)]
+        tolerance: ToleranceArgs,
+
+        /// Target runs spent minimizing each divergent case
+        #[arg(long, default_value = "100")]
+        max_shrink_runs: usize,
+
+        /// Directory for minimized divergent cases
+        #[arg(long, default_value = "differential-failures")]
+        output: PathBuf,
+
+        /// How the snapshot sequence of each case is produced
+        #[arg(long, value_enum, default_value_t = Generator::Uniform)]
+        generator: Generator,
+
+        /// JSON file with parameters of the realistic mempool model
+        #[arg(long)]
+        model: Option<PathBuf>,
+
+        /// Trace file of one JSON snapshot per line, or a directory of `.jsonl` traces such as
+        /// `traces/`, for the trace generator
+        #[arg(long, required_if_eq("generator", "trace"))]
+        trace: Option<PathBuf>,
+    },
+
+    /// Kill, restart and corrupt the Bitcoin node the servers poll, checking that estimates stay
+    /// available and recover
+    ///
+    /// Runs the Kotlin reference against the same node when `--reference-jar` is set and
+    /// reports where its behavior differs.
+    Chaos {
+        /// Number of faults to inject
+        #[arg(long, default_value = "8")]
+        rounds: usize,
+
+        /// Seed of the fault order (default: random)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// How long each fault lasts, in seconds
+        #[arg(long, default_value = "5")]
+        fault_secs: u64,
+
+        /// Seconds a server may take to publish a fresh estimate after a fault is cleared
+        #[arg(long, default_value = "60")]
+        recovery_timeout_secs: u64,
+    },
+
+    /// Exchange data directories between the servers, checking that each reads the others'
+    /// snapshot files and serves matching estimates from them
+    ///
+    /// Includes the Kotlin reference when `--reference-jar` is set, otherwise checks that the
+    /// Rust server reads its own data directory.
+    Persistence {
+        /// Snapshots each server records
+        #[arg(long, default_value = "10")]
+        snapshots: usize,
+
+        #[command(flatten)]
+        tolerance: ToleranceArgs,
+
+        /// Seconds a server may take to serve an estimate from a copied data directory
+        #[arg(long, default_value = "60")]
+        estimate_timeout_secs: u64,
+
+        /// Directory keeping the data directory recorded by each server
+        #[arg(long, default_value = "persistence-parity")]
+        output: PathBuf,
+    },
+
+    /// Change the mempool of the node the servers poll while they run, checking that each
+    /// serves the library's estimate after every change
+    ///
+    /// Runs the Kotlin reference against the same node when `--reference-jar` is set and checks
+    /// that its fee rates move the same way as the Rust server's.
+    Scenario {
+        #[command(flatten)]
+        tolerance: ToleranceArgs,
+    },
+
+    /// Record a data directory with an earlier release of the server, then run the current
+    /// build on the same directory and the release on the result, checking that snapshots stay
+    /// readable and estimates consistent across the switch
+    Upgrade {
+        /// Released bitcoin-augur-server binary to upgrade from
+        #[arg(long, env = "BITCOIN_AUGUR_RELEASED_SERVER_PATH")]
+        released_server: PathBuf,
+
+        /// Snapshots each build records
+        #[arg(long, default_value = "10")]
+        snapshots: usize,
+
+        #[command(flatten)]
+        tolerance: ToleranceArgs,
+
+        /// Seconds a server may take to serve an estimate from the data directory
+        #[arg(long, default_value = "60")]
+        estimate_timeout_secs: u64,
+
+        /// Directory keeping the data directory before and after the upgrade
+        #[arg(long, default_value = "upgrade-compatibility")]
+        output: PathBuf,
+    },
+
     /// Compare two API responses for compatibility
     Compare {
         /// First API endpoint URL
@@ -118,12 +342,62 @@ enum Commands {
         /// Second API endpoint URL
         endpoint2: String,
 
-        /// Request path to test
+        /// Request paths to test
         #[arg(default_value = "/fees")]
-        path: String,
+        paths: Vec<String>,
+
+        #[command(flatten)]
+        tolerance: ToleranceArgs,
+
+        /// Write an HTML report with both responses side by side, a heatmap of fee rate
+        /// differences and the history of earlier runs
+        #[arg(long)]
+        html: Option<PathBuf>,
+
+        /// History of runs shown in the report (default: the report's path with a
+        /// .history.jsonl extension)
+        #[arg(long, requires = "html")]
+        history: Option<PathBuf>,
     },
 }
 
+/// Allowed difference between compared fee rates
+#[derive(Args, Debug)]
+struct ToleranceArgs {
+    /// Relative fee rate tolerance below the first breakpoint
+    #[arg(long, default_value = "1e-3")]
+    tolerance: f64,
+
+    /// Fee rate difference always allowed, in sat/vB
+    #[arg(long, default_value = "0.01")]
+    absolute_tolerance: f64,
+
+    /// Relative tolerance from a fee rate upwards, as FROM=RELATIVE
+    #[arg(long, value_delimiter = ',', default_value = "100=1e-4")]
+    tolerance_breakpoints: Vec<comparison::Breakpoint>,
+}
+
+impl ToleranceArgs {
+    fn into_model(self) -> comparison::ToleranceModel {
+        comparison::ToleranceModel {
+            absolute: self.absolute_tolerance,
+            relative: self.tolerance,
+            breakpoints: self.tolerance_breakpoints,
+        }
+    }
+}
+
+/// Snapshot sequence generators for differential fuzzing
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Generator {
+    /// Random buckets at random intervals
+    Uniform,
+    /// Simulated mempool with a daily cycle, fee spikes and block bursts
+    Realistic,
+    /// Windows of a recorded trace file
+    Trace,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -164,22 +438,153 @@ async fn main() -> Result<()> {
         Commands::Compatibility { with_reference } => {
             runner.run_compatibility_tests(with_reference).await?;
         }
+        Commands::Matrix { matrix_file } => {
+            runner.run_matrix_tests(matrix_file).await?;
+        }
         Commands::Snapshots { force_update } => {
             runner.run_snapshot_tests(force_update).await?;
         }
         Commands::Vectors { vectors_file } => {
             runner.run_vector_tests(vectors_file).await?;
         }
+        Commands::Bisect {
+            vectors_file,
+            snapshot_dir,
+            output,
+            compare,
+            max_ulps,
+        } => {
+            runner
+                .run_bisect(vectors_file, snapshot_dir, output, compare, max_ulps)
+                .await?;
+        }
+        Commands::Reproducibility {
+            vectors_file,
+            output,
+            compare,
+            max_ulps,
+            repeat,
+        } => {
+            runner
+                .run_reproducibility_tests(vectors_file, output, compare, max_ulps, repeat)
+                .await?;
+        }
         Commands::Generate { output, count } => {
             runner.generate_test_data(output, count).await?;
         }
+        Commands::Stress {
+            url,
+            rps,
+            duration_secs,
+            endpoints,
+            thresholds,
+        } => {
+            let config = stress::LoadTestConfig {
+                requests_per_second: rps,
+                duration: std::time::Duration::from_secs(duration_secs),
+                endpoints,
+            };
+            runner.run_stress_test(url, config, thresholds).await?;
+        }
+        Commands::Differential {
+            cases,
+            seed,
+            max_snapshots,
+            tolerance,
+            max_shrink_runs,
+            output,
+            generator,
+            model,
+            trace,
+        } => {
+            let generator = match (generator, trace) {
+                (Generator::Uniform, _) => differential::CaseGenerator::Uniform,
+                (Generator::Realistic, _) => {
+                    let model = match model {
+                        Some(path) => traces::TraceModel::from_file(&path)?,
+                        None => traces::TraceModel::default(),
+                    };
+                    differential::CaseGenerator::Realistic(model)
+                }
+                (Generator::Trace, Some(path)) => {
+                    differential::CaseGenerator::Trace(traces::load_corpus(&path)?)
+                }
+                (Generator::Trace, None) => anyhow::bail!("--generator trace requires --trace"),
+            };
+            let config = differential::FuzzConfig {
+                seed: seed.unwrap_or_else(rand::random),
+                cases,
+                max_snapshots,
+                tolerance: tolerance.into_model(),
+                max_shrink_runs,
+                output,
+                generator,
+            };
+            runner.run_differential_fuzz(config).await?;
+        }
+        Commands::Chaos {
+            rounds,
+            seed,
+            fault_secs,
+            recovery_timeout_secs,
+        } => {
+            let config = chaos::ChaosConfig {
+                seed: seed.unwrap_or_else(rand::random),
+                rounds,
+                fault_duration: std::time::Duration::from_secs(fault_secs),
+                recovery_timeout: std::time::Duration::from_secs(recovery_timeout_secs),
+            };
+            runner.run_chaos_tests(config).await?;
+        }
+        Commands::Persistence {
+            snapshots,
+            tolerance,
+            estimate_timeout_secs,
+            output,
+        } => {
+            let config = persistence::PersistenceConfig {
+                snapshots,
+                tolerance: tolerance.into_model(),
+                estimate_timeout: std::time::Duration::from_secs(estimate_timeout_secs),
+                output,
+            };
+            runner.run_persistence_tests(config).await?;
+        }
+        Commands::Scenario { tolerance } => {
+            runner.run_scenario_tests(tolerance.into_model()).await?;
+        }
+        Commands::Upgrade {
+            released_server,
+            snapshots,
+            tolerance,
+            estimate_timeout_secs,
+            output,
+        } => {
+            let config = persistence::PersistenceConfig {
+                snapshots,
+                tolerance: tolerance.into_model(),
+                estimate_timeout: std::time::Duration::from_secs(estimate_timeout_secs),
+                output,
+            };
+            runner.run_upgrade_tests(released_server, config).await?;
+        }
         Commands::Compare {
             endpoint1,
             endpoint2,
-            path,
+            paths,
+            tolerance,
+            html,
+            history,
         } => {
             runner
-                .compare_endpoints(&endpoint1, &endpoint2, &path)
+                .compare_endpoints(
+                    &endpoint1,
+                    &endpoint2,
+                    &paths,
+                    tolerance.into_model(),
+                    html,
+                    history,
+                )
                 .await?;
         }
     }
@@ -0,0 +1,226 @@
+//! Server configurations the compatibility suite is repeated for
+//!
+//! A matrix file lists named configurations of the Rust server. Each one is started on a fresh
+//! data directory with its command line arguments, configuration file and polling interval, and
+//! the whole compatibility suite runs against it. Configurations that change the published fee
+//! rates, such as rounding or a fee ceiling, opt out of the comparison with the library.
+
+use anyhow::{ensure, Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::compatibility::TestResults;
+
+/// Matrix used when no file is given
+const BUNDLED_MATRIX: &str = include_str!("../config/server-matrix.json");
+
+/// A list of server configurations
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerMatrix {
+    pub configurations: Vec<ServerConfiguration>,
+}
+
+/// One way of running the server
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfiguration {
+    /// Unique name, also used for the data directory and log file
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Command line arguments passed to the server
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Contents of a configuration file passed with `--config`, in the server's config layout
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
+    /// Mempool polling interval in seconds (default: the server's)
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    /// Whether served estimates must match the library's for injected snapshots
+    #[serde(default = "default_library_estimates")]
+    pub library_estimates: bool,
+}
+
+fn default_library_estimates() -> bool {
+    true
+}
+
+impl ServerMatrix {
+    /// Loads a matrix from a JSON file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read matrix file {path:?}"))?;
+        Self::parse(&content).with_context(|| format!("Invalid matrix file {path:?}"))
+    }
+
+    /// The matrix shipped in `config/server-matrix.json`
+    pub fn bundled() -> Result<Self> {
+        Self::parse(BUNDLED_MATRIX).context("Bundled server matrix is invalid")
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let matrix: Self = serde_json::from_str(content)?;
+        ensure!(!matrix.configurations.is_empty(), "No configurations");
+
+        let mut names = HashSet::new();
+        for configuration in &matrix.configurations {
+            let name = &configuration.name;
+            ensure!(
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+                "Configuration name {name:?} must be letters, digits, '-' or '_'"
+            );
+            ensure!(names.insert(name), "Duplicate configuration {name:?}");
+            ensure!(
+                configuration.config.as_ref().is_none_or(|c| c.is_object()),
+                "Configuration {name:?} has a config that is not an object"
+            );
+        }
+        Ok(matrix)
+    }
+}
+
+impl ServerConfiguration {
+    /// Server arguments for this configuration, writing its config file into `dir`
+    pub fn server_args(&self, dir: &Path) -> Result<Vec<String>> {
+        let mut args = Vec::new();
+        if let Some(ref config) = self.config {
+            let path = dir.join("augur.json");
+            std::fs::write(&path, serde_json::to_vec_pretty(config)?)
+                .with_context(|| format!("Failed to write {path:?}"))?;
+            args.push("--config".to_string());
+            args.push(path.to_string_lossy().into_owned());
+        }
+        args.extend(self.args.iter().cloned());
+        Ok(args)
+    }
+}
+
+/// Outcome of the compatibility suite for one configuration
+pub struct MatrixResult {
+    pub name: String,
+    /// Failed tests, or the error that kept the suite from running
+    pub failures: Vec<String>,
+    pub warnings: usize,
+    /// Server output, for investigating failures
+    pub log_path: PathBuf,
+    /// Bundle of the artifacts of a failed configuration
+    pub artifacts: Option<PathBuf>,
+}
+
+impl MatrixResult {
+    pub fn from_results(name: &str, results: &TestResults, log_path: PathBuf) -> Self {
+        Self {
+            name: name.to_string(),
+            failures: results.failures(),
+            warnings: results.warning_count(),
+            log_path,
+            artifacts: None,
+        }
+    }
+
+    pub fn from_error(name: &str, error: &anyhow::Error, log_path: PathBuf) -> Self {
+        Self {
+            name: name.to_string(),
+            failures: vec![format!("{error:#}")],
+            warnings: 0,
+            log_path,
+            artifacts: None,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Prints one line per configuration, with the failures of those that failed
+pub fn print_matrix_summary(results: &[MatrixResult]) {
+    println!("\n{separator}", separator = "=".repeat(60));
+    println!("Server Matrix Summary");
+    println!("{separator}", separator = "=".repeat(60));
+
+    for result in results {
+        let status = if result.passed() {
+            "PASSED".green()
+        } else {
+            "FAILED".red()
+        };
+        let warnings = match result.warnings {
+            0 => String::new(),
+            count => format!(" (warnings: {count})").yellow().to_string(),
+        };
+        println!("{status} {name}{warnings}", name = result.name);
+        for failure in &result.failures {
+            println!("    {symbol} {failure}", symbol = "✗".red());
+        }
+        if !result.passed() {
+            println!("    Server output: {path:?}", path = result.log_path);
+        }
+        if let Some(ref path) = result.artifacts {
+            println!("    Artifacts: {path:?}");
+        }
+    }
+
+    let passed = results.iter().filter(|r| r.passed()).count();
+    println!(
+        "\n{passed}/{total} configurations passed",
+        total = results.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_matrix_is_valid() {
+        let matrix = ServerMatrix::bundled().unwrap();
+        assert!(matrix.configurations.iter().any(|c| c.name == "default"));
+        assert!(matrix
+            .configurations
+            .iter()
+            .all(|c| !c.description.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_matrices() {
+        let duplicate = r#"{"configurations": [{"name": "a"}, {"name": "a"}]}"#;
+        assert!(ServerMatrix::parse(duplicate).is_err());
+        assert!(ServerMatrix::parse(r#"{"configurations": []}"#).is_err());
+        assert!(ServerMatrix::parse(r#"{"configurations": [{"name": "a/b"}]}"#).is_err());
+        assert!(
+            ServerMatrix::parse(r#"{"configurations": [{"name": "a", "config": 1}]}"#).is_err()
+        );
+        assert!(ServerMatrix::parse(r#"{"configurations": [{"name": "a", "port": 1}]}"#).is_err());
+
+        let matrix = ServerMatrix::parse(r#"{"configurations": [{"name": "a"}]}"#).unwrap();
+        assert!(matrix.configurations[0].library_estimates);
+    }
+
+    #[test]
+    fn test_server_args_write_the_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let matrix = ServerMatrix::parse(
+            r#"{"configurations": [{
+                "name": "a",
+                "args": ["--fast-targets"],
+                "config": {"estimator": {"max_fee_rate": 20.0}}
+            }]}"#,
+        )
+        .unwrap();
+
+        let args = matrix.configurations[0].server_args(dir.path()).unwrap();
+        let path = dir.path().join("augur.json");
+        assert_eq!(args, ["--config", path.to_str().unwrap(), "--fast-targets"]);
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(written["estimator"]["max_fee_rate"], 20.0);
+    }
+}
@@ -2,15 +2,33 @@
 //!
 //! Provides a controllable Bitcoin Core RPC mock that can simulate
 //! various mempool states for testing fee estimation algorithms.
+//!
+//! Besides JSON-RPC on `/`, the server exposes a control surface so a running test can
+//! change what every polling server observes:
+//!
+//! - `POST /control/mempool` replaces (or appends to) the mempool
+//! - `POST /control/mine-block` mines blocks, removing the highest fee rate transactions
+//! - `POST /control/advance-time` moves the node's reported time forward
+//! - `POST /control/fault` makes JSON-RPC calls fail until the fault is cleared
 
 #![allow(dead_code)]
 
-use anyhow::Result;
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use anyhow::{ensure, Context, Result};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use bitcoin_augur::MempoolSnapshot;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
-use tracing::{debug, info};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 
 /// A mock transaction in the mempool
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,19 +51,72 @@ impl MockTransaction {
     }
 }
 
+/// Maximum weight of a block, used when mining without an explicit limit
+const MAX_BLOCK_WEIGHT: u64 = 4_000_000;
+
+/// State of a mock node at one point of a test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockScenario {
+    pub block_height: u64,
+    /// How far the node's clock was moved ahead of the real time
+    pub time_offset_secs: i64,
+    pub fault: Fault,
+    /// Verbose `getrawmempool` calls served so far
+    pub mempool_polls: u64,
+    pub mempool: Vec<MockTransaction>,
+}
+
 /// Mock Bitcoin RPC server state
 pub struct MockBitcoinRpc {
     mempool: Arc<RwLock<Vec<MockTransaction>>>,
     block_height: Arc<RwLock<u64>>,
+    time_offset_secs: Arc<RwLock<i64>>,
+    mempool_polls: Arc<AtomicU64>,
+    fault: Arc<RwLock<Fault>>,
     port: u16,
 }
 
+/// A mock RPC server running in the background
+pub struct MockRpcTask {
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl MockRpcTask {
+    /// Stops listening and closes idle connections, so pollers see a node that went away
+    ///
+    /// Requests already in progress, such as ones held by a delay fault, are still answered.
+    pub async fn stop(self) -> Result<()> {
+        // The receiver is gone only if the server already stopped on its own
+        self.shutdown.send(()).ok();
+        self.task.await.context("Mock RPC server task failed")
+    }
+}
+
+/// Failure mode applied to every JSON-RPC call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Fault {
+    /// Answer normally
+    #[default]
+    None,
+    /// Respond with HTTP 500
+    ServerError,
+    /// Hold each response for `seconds` before answering normally
+    Delay { seconds: u64 },
+    /// Respond with HTTP 200 and a body that is not valid JSON
+    MalformedJson,
+}
+
 impl MockBitcoinRpc {
     /// Create a new mock RPC server
     pub fn new(port: u16) -> Self {
         Self {
             mempool: Arc::new(RwLock::new(Vec::new())),
             block_height: Arc::new(RwLock::new(850000)),
+            time_offset_secs: Arc::new(RwLock::new(0)),
+            mempool_polls: Arc::new(AtomicU64::new(0)),
+            fault: Arc::new(RwLock::new(Fault::None)),
             port,
         }
     }
@@ -70,24 +141,102 @@ impl MockBitcoinRpc {
         *self.block_height.write().unwrap() = height;
     }
 
-    /// Start the mock RPC server
-    pub async fn start(self: Arc<Self>) -> Result<()> {
-        let state = MockRpcState {
+    /// Mirror a generated snapshot so a polling server observes the same mempool state
+    ///
+    /// This is how injected test data reaches servers without an injection API (such as the
+    /// Kotlin reference): each fee bucket becomes transactions paying that bucket's fee rate.
+    pub fn load_snapshot(&self, snapshot: &MempoolSnapshot) {
+        self.set_mempool(TestDataGenerator::from_snapshot(snapshot));
+        self.set_block_height(snapshot.block_height as u64);
+    }
+
+    /// Mine `count` blocks of at most `max_weight` each, highest fee rate first
+    pub fn mine_blocks(&self, count: u64, max_weight: u64) -> MineBlocksResponse {
+        self.state().mine_blocks(count, max_weight)
+    }
+
+    /// Move the node's reported time forward
+    pub fn advance_time(&self, seconds: i64) {
+        *self.time_offset_secs.write().unwrap() += seconds;
+    }
+
+    /// Apply `fault` to every JSON-RPC call until replaced by `Fault::None`
+    pub fn set_fault(&self, fault: Fault) {
+        *self.fault.write().unwrap() = fault;
+    }
+
+    /// Number of verbose `getrawmempool` calls served so far
+    pub fn mempool_polls(&self) -> u64 {
+        self.mempool_polls.load(Ordering::SeqCst)
+    }
+
+    /// The node's current mempool, height, time and fault, for reproducing a failure
+    pub fn scenario(&self) -> MockScenario {
+        MockScenario {
+            block_height: *self.block_height.read().unwrap(),
+            time_offset_secs: *self.time_offset_secs.read().unwrap(),
+            fault: *self.fault.read().unwrap(),
+            mempool_polls: self.mempool_polls(),
+            mempool: self.mempool.read().unwrap().clone(),
+        }
+    }
+
+    fn state(&self) -> MockRpcState {
+        MockRpcState {
             mempool: self.mempool.clone(),
             block_height: self.block_height.clone(),
-        };
+            time_offset_secs: self.time_offset_secs.clone(),
+            mempool_polls: self.mempool_polls.clone(),
+            fault: self.fault.clone(),
+        }
+    }
 
-        let app = Router::new().route("/", post(handle_rpc)).with_state(state);
+    fn router(&self) -> Router {
+        Router::new()
+            .route("/", post(handle_rpc))
+            .route("/control/mempool", post(control_mempool))
+            .route("/control/mine-block", post(control_mine_block))
+            .route("/control/advance-time", post(control_advance_time))
+            .route("/control/fault", post(control_fault))
+            .with_state(self.state())
+    }
 
+    /// Start the mock RPC server
+    pub async fn start(self: Arc<Self>) -> Result<()> {
         let addr = format!("127.0.0.1:{}", self.port);
         info!("Mock Bitcoin RPC server listening on {addr}");
 
         let listener = tokio::net::TcpListener::bind(&addr).await?;
-        axum::serve(listener, app).await?;
+        axum::serve(listener, self.router()).await?;
 
         Ok(())
     }
 
+    /// Bind the port and serve in a background task until the returned handle is stopped
+    ///
+    /// Spawning again on the same port after a stop brings the node back with the mempool,
+    /// height and fault it had before.
+    pub async fn spawn(self: &Arc<Self>) -> Result<MockRpcTask> {
+        let addr = format!("127.0.0.1:{}", self.port);
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .with_context(|| format!("Failed to bind mock RPC server to {addr}"))?;
+        debug!("Mock Bitcoin RPC server listening on {addr}");
+
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let app = self.router();
+        let task = tokio::spawn(async move {
+            let serve = axum::serve(listener, app).with_graceful_shutdown(async {
+                signal.await.ok();
+            });
+            if let Err(e) = serve.await {
+                warn!("Mock RPC server stopped: {e}");
+            }
+        });
+
+        Ok(MockRpcTask { shutdown, task })
+    }
+
     /// Get the RPC URL for this mock server
     pub fn url(&self) -> String {
         format!("http://127.0.0.1:{}", self.port)
@@ -98,6 +247,252 @@ impl MockBitcoinRpc {
 struct MockRpcState {
     mempool: Arc<RwLock<Vec<MockTransaction>>>,
     block_height: Arc<RwLock<u64>>,
+    time_offset_secs: Arc<RwLock<i64>>,
+    mempool_polls: Arc<AtomicU64>,
+    fault: Arc<RwLock<Fault>>,
+}
+
+impl MockRpcState {
+    /// Current node time, including any simulated advance
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp() + *self.time_offset_secs.read().unwrap()
+    }
+
+    fn mine_blocks(&self, count: u64, max_weight: u64) -> MineBlocksResponse {
+        let mut mempool = self.mempool.write().unwrap();
+        let mut height = self.block_height.write().unwrap();
+
+        // Miners pick the best paying transactions first
+        mempool.sort_by(|a, b| b.fee_rate.total_cmp(&a.fee_rate));
+
+        let mut mined = 0;
+        for _ in 0..count {
+            let mut block_weight = 0u64;
+            let mut included = 0;
+            for tx in mempool.iter() {
+                if block_weight + u64::from(tx.weight) > max_weight {
+                    break;
+                }
+                block_weight += u64::from(tx.weight);
+                included += 1;
+            }
+            mempool.drain(..included);
+            mined += included;
+            *height += 1;
+        }
+
+        MineBlocksResponse {
+            height: *height,
+            mined_transactions: mined,
+            remaining_transactions: mempool.len(),
+        }
+    }
+}
+
+/// Body of `POST /control/mempool`
+#[derive(Debug, Deserialize)]
+pub struct SetMempoolRequest {
+    /// Groups of identical transactions to place in the mempool
+    pub transactions: Vec<ControlTransaction>,
+    /// Add to the current mempool instead of replacing it
+    #[serde(default)]
+    pub append: bool,
+}
+
+/// A group of identical transactions in a control request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlTransaction {
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+    /// Transaction weight in weight units
+    pub weight: u32,
+    /// Number of copies
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+fn default_count() -> usize {
+    1
+}
+
+/// Body of `POST /control/mine-block`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MineBlocksRequest {
+    /// Number of blocks to mine (default: 1)
+    #[serde(default)]
+    pub count: Option<u64>,
+    /// Weight limit per block (default: 4,000,000 WU)
+    #[serde(default)]
+    pub max_weight: Option<u64>,
+}
+
+/// Result of mining blocks
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MineBlocksResponse {
+    pub height: u64,
+    pub mined_transactions: usize,
+    pub remaining_transactions: usize,
+}
+
+/// Body of `POST /control/advance-time`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdvanceTimeRequest {
+    pub seconds: i64,
+}
+
+/// Summary of the mock node after a control request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ControlStateResponse {
+    pub height: u64,
+    pub mempool_size: usize,
+    pub time: i64,
+}
+
+impl From<&MockRpcState> for ControlStateResponse {
+    fn from(state: &MockRpcState) -> Self {
+        Self {
+            height: *state.block_height.read().unwrap(),
+            mempool_size: state.mempool.read().unwrap().len(),
+            time: state.now(),
+        }
+    }
+}
+
+async fn control_mempool(
+    State(state): State<MockRpcState>,
+    Json(request): Json<SetMempoolRequest>,
+) -> Result<Json<ControlStateResponse>, (StatusCode, String)> {
+    let mut transactions = Vec::new();
+    for group in &request.transactions {
+        if group.weight == 0 || !group.fee_rate.is_finite() || group.fee_rate < 0.0 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Invalid transaction group: weight {weight}, fee rate {rate}",
+                    weight = group.weight,
+                    rate = group.fee_rate
+                ),
+            ));
+        }
+        let fee = (group.fee_rate * f64::from(group.weight) / 4.0).round() as u64;
+        transactions.extend((0..group.count).map(|_| MockTransaction::new(group.weight, fee)));
+    }
+
+    {
+        let mut mempool = state.mempool.write().unwrap();
+        if !request.append {
+            mempool.clear();
+        }
+        mempool.extend(transactions);
+    }
+    debug!(
+        "Mock mempool now holds {count} transactions",
+        count = state.mempool.read().unwrap().len()
+    );
+
+    Ok(Json(ControlStateResponse::from(&state)))
+}
+
+async fn control_mine_block(
+    State(state): State<MockRpcState>,
+    request: Option<Json<MineBlocksRequest>>,
+) -> Json<MineBlocksResponse> {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    let response = state.mine_blocks(
+        request.count.unwrap_or(1),
+        request.max_weight.unwrap_or(MAX_BLOCK_WEIGHT),
+    );
+    debug!(
+        "Mock node mined up to height {height}",
+        height = response.height
+    );
+    Json(response)
+}
+
+async fn control_advance_time(
+    State(state): State<MockRpcState>,
+    Json(request): Json<AdvanceTimeRequest>,
+) -> Json<ControlStateResponse> {
+    *state.time_offset_secs.write().unwrap() += request.seconds;
+    Json(ControlStateResponse::from(&state))
+}
+
+async fn control_fault(
+    State(state): State<MockRpcState>,
+    Json(fault): Json<Fault>,
+) -> Json<ControlStateResponse> {
+    *state.fault.write().unwrap() = fault;
+    debug!("Mock RPC fault set to {fault:?}");
+    Json(ControlStateResponse::from(&state))
+}
+
+/// Client for the control surface of a running mock RPC server
+pub struct MockRpcControl {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl MockRpcControl {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into(),
+        }
+    }
+
+    /// Replace (or with `append`, extend) the mock mempool
+    pub async fn set_mempool(
+        &self,
+        transactions: Vec<ControlTransaction>,
+        append: bool,
+    ) -> Result<ControlStateResponse> {
+        self.post(
+            "/control/mempool",
+            json!({ "transactions": transactions, "append": append }),
+        )
+        .await
+    }
+
+    /// Mine `count` full blocks
+    pub async fn mine_blocks(&self, count: u64) -> Result<MineBlocksResponse> {
+        self.post("/control/mine-block", json!({ "count": count }))
+            .await
+    }
+
+    /// Advance the node's reported time
+    pub async fn advance_time(&self, seconds: i64) -> Result<ControlStateResponse> {
+        self.post("/control/advance-time", json!({ "seconds": seconds }))
+            .await
+    }
+
+    /// Make JSON-RPC calls fail with `fault`, or recover with `Fault::None`
+    pub async fn set_fault(&self, fault: Fault) -> Result<ControlStateResponse> {
+        self.post("/control/fault", serde_json::to_value(fault)?)
+            .await
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(&self, path: &str, body: Value) -> Result<T> {
+        let url = format!("{base_url}{path}", base_url = self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach mock RPC control endpoint {url}"))?;
+
+        let status = response.status();
+        ensure!(
+            status.is_success(),
+            "Mock RPC control request {path} failed with {status}: {body}",
+            body = response.text().await.unwrap_or_default()
+        );
+
+        response
+            .json()
+            .await
+            .with_context(|| format!("Invalid response from {url}"))
+    }
 }
 
 #[derive(Deserialize)]
@@ -114,10 +509,53 @@ struct RpcResponse {
     id: Value,
 }
 
-async fn handle_rpc(
-    State(state): State<MockRpcState>,
-    Json(request): Json<RpcRequest>,
-) -> Result<Json<RpcResponse>, StatusCode> {
+/// Handles a single JSON-RPC request or a batch of them, subject to the active fault
+async fn handle_rpc(State(state): State<MockRpcState>, Json(body): Json<Value>) -> Response {
+    let fault = *state.fault.read().unwrap();
+    match fault {
+        Fault::None => {}
+        Fault::ServerError => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Injected failure").into_response();
+        }
+        Fault::Delay { seconds } => {
+            tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+        }
+        Fault::MalformedJson => {
+            return (StatusCode::OK, r#"{"result": [truncated"#).into_response();
+        }
+    }
+
+    match answer_rpc(&state, body) {
+        Ok(response) => Json(response).into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+fn answer_rpc(state: &MockRpcState, body: Value) -> Result<Value, StatusCode> {
+    let response = match body {
+        Value::Array(requests) => {
+            let responses = requests
+                .into_iter()
+                .map(|request| {
+                    let request: RpcRequest =
+                        serde_json::from_value(request).map_err(|_| StatusCode::BAD_REQUEST)?;
+                    serde_json::to_value(dispatch_rpc(state, request))
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Value::Array(responses)
+        }
+        request => {
+            let request: RpcRequest =
+                serde_json::from_value(request).map_err(|_| StatusCode::BAD_REQUEST)?;
+            serde_json::to_value(dispatch_rpc(state, request))
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        }
+    };
+    Ok(response)
+}
+
+fn dispatch_rpc(state: &MockRpcState, request: RpcRequest) -> RpcResponse {
     debug!("Mock RPC received method: {}", request.method);
 
     let result = match request.method.as_str() {
@@ -129,8 +567,8 @@ async fn handle_rpc(
                 "headers": height,
                 "bestblockhash": format!("{:064x}", height),
                 "difficulty": 1.0,
-                "time": chrono::Utc::now().timestamp(),
-                "mediantime": chrono::Utc::now().timestamp() - 600,
+                "time": state.now(),
+                "mediantime": state.now() - 600,
                 "verificationprogress": 0.999999,
                 "initialblockdownload": false,
                 "chainwork": format!("{:064x}", height * 1000),
@@ -138,6 +576,7 @@ async fn handle_rpc(
                 "pruned": false
             }))
         }
+        "getblockcount" => Some(json!(*state.block_height.read().unwrap())),
         "getrawmempool" => {
             let mempool = state.mempool.read().unwrap();
             let txids: Vec<String> = mempool.iter().map(|tx| tx.txid.clone()).collect();
@@ -150,6 +589,7 @@ async fn handle_rpc(
                     .and_then(|v| v.as_bool())
                 {
                     if verbose {
+                        state.mempool_polls.fetch_add(1, Ordering::SeqCst);
                         // Return verbose mempool info
                         let mut verbose_mempool = serde_json::Map::new();
                         for tx in mempool.iter() {
@@ -160,7 +600,7 @@ async fn handle_rpc(
                                     "weight": tx.weight,
                                     "fee": tx.fee as f64 / 100_000_000.0, // Convert to BTC
                                     "modifiedfee": tx.fee as f64 / 100_000_000.0,
-                                    "time": chrono::Utc::now().timestamp() - 300,
+                                    "time": state.now() - 300,
                                     "height": *state.block_height.read().unwrap(),
                                     "descendantcount": 1,
                                     "descendantsize": tx.weight / 4,
@@ -177,17 +617,27 @@ async fn handle_rpc(
                                 }),
                             );
                         }
-                        return Ok(Json(RpcResponse {
+                        return RpcResponse {
                             result: Some(Value::Object(verbose_mempool)),
                             error: None,
                             id: request.id,
-                        }));
+                        };
                     }
                 }
             }
 
             Some(json!(txids))
         }
+        "getmempoolinfo" => {
+            let mempool = state.mempool.read().unwrap();
+            Some(json!({
+                "loaded": true,
+                "size": mempool.len(),
+                "bytes": mempool.iter().map(|tx| u64::from(tx.weight / 4)).sum::<u64>(),
+                "mempoolminfee": 0.00001,
+                "minrelaytxfee": 0.00001
+            }))
+        }
         "getmempoolentry" => {
             if let Some(params) = request.params {
                 if let Some(txid) = params
@@ -197,13 +647,13 @@ async fn handle_rpc(
                 {
                     let mempool = state.mempool.read().unwrap();
                     if let Some(tx) = mempool.iter().find(|t| t.txid == txid) {
-                        return Ok(Json(RpcResponse {
+                        return RpcResponse {
                             result: Some(json!({
                                 "vsize": tx.weight / 4,
                                 "weight": tx.weight,
                                 "fee": tx.fee as f64 / 100_000_000.0,
                                 "modifiedfee": tx.fee as f64 / 100_000_000.0,
-                                "time": chrono::Utc::now().timestamp() - 300,
+                                "time": state.now() - 300,
                                 "height": *state.block_height.read().unwrap(),
                                 "descendantcount": 1,
                                 "descendantsize": tx.weight / 4,
@@ -214,29 +664,29 @@ async fn handle_rpc(
                             })),
                             error: None,
                             id: request.id,
-                        }));
+                        };
                     }
                 }
             }
             None
         }
         _ => {
-            return Ok(Json(RpcResponse {
+            return RpcResponse {
                 result: None,
                 error: Some(json!({
                     "code": -32601,
                     "message": "Method not found"
                 })),
                 id: request.id,
-            }));
+            };
         }
     };
 
-    Ok(Json(RpcResponse {
+    RpcResponse {
         result,
         error: None,
         id: request.id,
-    }))
+    }
 }
 
 /// Test data generator for creating various mempool scenarios
@@ -319,6 +769,25 @@ impl TestDataGenerator {
         txs
     }
 
+    /// Convert a bucketed snapshot back into transactions at each bucket's fee rate
+    pub fn from_snapshot(snapshot: &MempoolSnapshot) -> Vec<MockTransaction> {
+        // Keep individual transactions within a standard block's weight
+        const MAX_TX_WEIGHT: u64 = 400_000;
+
+        let mut txs = Vec::new();
+        for (&bucket, &total_weight) in &snapshot.bucketed_weights {
+            let fee_rate = (bucket as f64 / 100.0).exp();
+            let mut remaining = total_weight;
+            while remaining > 0 {
+                let weight = remaining.min(MAX_TX_WEIGHT);
+                let fee = (fee_rate * weight as f64 / 4.0).round() as u64;
+                txs.push(MockTransaction::new(weight as u32, fee));
+                remaining -= weight;
+            }
+        }
+        txs
+    }
+
     /// Generate graduated fees (steadily increasing)
     pub fn graduated_fees(count: usize) -> Vec<MockTransaction> {
         let mut txs = Vec::new();
@@ -377,4 +846,216 @@ mod tests {
 
         Ok(())
     }
+
+    fn state_with(transactions: Vec<MockTransaction>) -> MockRpcState {
+        let rpc = MockBitcoinRpc::new(0);
+        rpc.set_mempool(transactions);
+        rpc.state()
+    }
+
+    #[tokio::test]
+    async fn test_batch_requests_answered_in_order() -> Result<()> {
+        let state = state_with(TestDataGenerator::single_transaction());
+        let batch = json!([
+            { "jsonrpc": "1.0", "id": "blockchain-info", "method": "getblockchaininfo", "params": [] },
+            { "jsonrpc": "1.0", "id": "mempool", "method": "getrawmempool", "params": [true] }
+        ]);
+
+        let response = answer_rpc(&state, batch).map_err(|status| anyhow::anyhow!("{status}"))?;
+
+        let responses = response.as_array().context("expected a batch response")?;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], "blockchain-info");
+        assert_eq!(responses[0]["result"]["blocks"], 850000);
+        assert_eq!(responses[1]["id"], "mempool");
+        assert_eq!(responses[1]["result"].as_object().map(|m| m.len()), Some(1));
+        assert_eq!(state.mempool_polls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    async fn call(state: &MockRpcState, method: &str) -> Result<(StatusCode, String)> {
+        let request = json!({ "jsonrpc": "1.0", "id": 1, "method": method, "params": [] });
+        let response = handle_rpc(State(state.clone()), Json(request)).await;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        Ok((status, String::from_utf8(body.to_vec())?))
+    }
+
+    #[tokio::test]
+    async fn test_faults_until_cleared() -> Result<()> {
+        let state = state_with(Vec::new());
+
+        let Json(response) = control_fault(State(state.clone()), Json(Fault::ServerError)).await;
+        assert_eq!(response.height, 850000);
+        let (status, _) = call(&state, "getblockcount").await?;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        let _ = control_fault(State(state.clone()), Json(Fault::MalformedJson)).await;
+        let (status, body) = call(&state, "getblockcount").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert!(serde_json::from_str::<Value>(&body).is_err());
+
+        let _ = control_fault(State(state.clone()), Json(Fault::None)).await;
+        let (status, body) = call(&state, "getblockcount").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(serde_json::from_str::<Value>(&body)?["result"], 850000);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delay_fault_still_answers() -> Result<()> {
+        let state = state_with(Vec::new());
+        *state.fault.write().unwrap() = Fault::Delay { seconds: 1 };
+
+        let started = std::time::Instant::now();
+        let (status, _) = call(&state, "getblockcount").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restart_keeps_state() -> Result<()> {
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await?
+            .local_addr()?
+            .port();
+        let rpc = Arc::new(MockBitcoinRpc::new(port));
+        let client = reqwest::Client::new();
+        let block_count = || async {
+            let request = json!({ "jsonrpc": "1.0", "id": 1, "method": "getblockcount" });
+            let response: Value = client
+                .post(rpc.url())
+                .json(&request)
+                .send()
+                .await?
+                .json()
+                .await?;
+            anyhow::Ok(response["result"].clone())
+        };
+
+        let task = rpc.spawn().await?;
+        rpc.mine_blocks(2, MAX_BLOCK_WEIGHT);
+        assert_eq!(block_count().await?, 850002);
+
+        task.stop().await?;
+        assert!(block_count().await.is_err());
+
+        let task = rpc.spawn().await?;
+        assert_eq!(block_count().await?, 850002);
+        task.stop().await
+    }
+
+    #[test]
+    fn test_fault_wire_format() -> Result<()> {
+        let fault: Fault = serde_json::from_value(json!({ "kind": "delay", "seconds": 5 }))?;
+        assert_eq!(fault, Fault::Delay { seconds: 5 });
+        assert_eq!(
+            serde_json::to_value(Fault::MalformedJson)?,
+            json!({ "kind": "malformed_json" })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_control_mempool_replaces_or_appends() -> Result<()> {
+        let state = state_with(TestDataGenerator::single_transaction());
+        let request = |append| SetMempoolRequest {
+            transactions: vec![ControlTransaction {
+                fee_rate: 10.0,
+                weight: 400,
+                count: 3,
+            }],
+            append,
+        };
+
+        let Json(replaced) = control_mempool(State(state.clone()), Json(request(false)))
+            .await
+            .map_err(|(_, msg)| anyhow::anyhow!(msg))?;
+        assert_eq!(replaced.mempool_size, 3);
+        assert_eq!(state.mempool.read().unwrap()[0].fee, 1000);
+
+        let Json(appended) = control_mempool(State(state.clone()), Json(request(true)))
+            .await
+            .map_err(|(_, msg)| anyhow::anyhow!(msg))?;
+        assert_eq!(appended.mempool_size, 6);
+
+        let invalid = SetMempoolRequest {
+            transactions: vec![ControlTransaction {
+                fee_rate: 1.0,
+                weight: 0,
+                count: 1,
+            }],
+            append: false,
+        };
+        assert!(control_mempool(State(state), Json(invalid)).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mining_removes_highest_fee_rates_first() {
+        let rpc = MockBitcoinRpc::new(0);
+        rpc.set_mempool(vec![
+            MockTransaction::new(2_000_000, 500_000),   // 1 sat/vB
+            MockTransaction::new(2_000_000, 5_000_000), // 10 sat/vB
+            MockTransaction::new(2_000_000, 2_500_000), // 5 sat/vB
+        ]);
+
+        let result = rpc.mine_blocks(1, MAX_BLOCK_WEIGHT);
+        assert_eq!(result.height, 850001);
+        assert_eq!(result.mined_transactions, 2);
+        assert_eq!(result.remaining_transactions, 1);
+        assert_eq!(rpc.mempool.read().unwrap()[0].fee, 500_000);
+
+        // Empty blocks still advance the chain
+        let result = rpc.mine_blocks(2, 1000);
+        assert_eq!(result.height, 850003);
+        assert_eq!(result.remaining_transactions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_advance_time_shifts_reported_time() {
+        let state = state_with(Vec::new());
+        let before = state.now();
+
+        let Json(response) = control_advance_time(
+            State(state.clone()),
+            Json(AdvanceTimeRequest { seconds: 3600 }),
+        )
+        .await;
+        assert!(response.time >= before + 3600);
+
+        let info = dispatch_rpc(
+            &state,
+            RpcRequest {
+                method: "getblockchaininfo".to_string(),
+                params: None,
+                id: json!(1),
+            },
+        );
+        let reported = info.result.and_then(|r| r["time"].as_i64()).unwrap_or(0);
+        assert!(reported >= before + 3600);
+    }
+
+    #[test]
+    fn test_from_snapshot_round_trips_buckets() -> Result<()> {
+        let transactions = vec![
+            bitcoin_augur::MempoolTransaction::new(1_000_000, 2_500_000),
+            bitcoin_augur::MempoolTransaction::new(4000, 4000),
+        ];
+        let snapshot =
+            MempoolSnapshot::from_transactions(transactions, 850_123, chrono::Utc::now());
+
+        let mock = TestDataGenerator::from_snapshot(&snapshot);
+        assert_eq!(mock.len(), 4); // 1M WU split into three transactions plus one small one
+
+        let rebuilt: Vec<_> = mock
+            .iter()
+            .map(|tx| bitcoin_augur::MempoolTransaction::new(tx.weight as u64, tx.fee))
+            .collect();
+        let rebuilt = MempoolSnapshot::from_transactions(rebuilt, 850_123, snapshot.timestamp);
+        assert_eq!(rebuilt.bucketed_weights, snapshot.bucketed_weights);
+
+        Ok(())
+    }
 }
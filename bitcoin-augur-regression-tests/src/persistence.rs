@@ -0,0 +1,721 @@
+//! Round-trip parity of the data directories written by each implementation
+//!
+//! Every server first records snapshots from a shared mock node into an empty data directory.
+//! Each recorded directory is then copied into the data directory of every server, which is
+//! started with the node failing every call, so it can only estimate from the files on disk. The
+//! estimate it serves must match the library's estimate for the snapshots in the directory, and
+//! every snapshot file must parse as a [`MempoolSnapshot`].
+//!
+//! A reader failing on its own directory shows the implementation does not restore estimates
+//! from disk at all. Only the Rust server is required to, so for the Kotlin reference such a
+//! failure turns its results for the other directories into warnings.
+//!
+//! Finally every server is started on the same recorded directory and asked for
+//! `/historical_fee` before any snapshot, between two snapshots and exactly on one. Each answer
+//! must match the library's estimate for the snapshots up to that time, and the first server's
+//! answer within the tolerance. Only the Rust server is required to serve historical estimates.
+
+use anyhow::{bail, ensure, Context, Result};
+use bitcoin_augur::{AugurError, MempoolSnapshot};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info};
+
+use crate::api_client::{ApiClient, FeeEstimateResponse};
+use crate::comparison::ToleranceModel;
+use crate::compatibility::TestResults;
+use crate::differential::served_estimates;
+use crate::mock_rpc::{Fault, MockBitcoinRpc, TestDataGenerator};
+use crate::server::{ReferenceServerManager, ServerManager};
+use crate::test_vectors::ExpectedEstimates;
+use crate::test_vectors::{compare, estimate};
+
+/// Mempool polls a writer must make after each mempool change, so one complete collection
+/// cycle recorded it
+const POLLS_PER_SNAPSHOT: u64 = 2;
+
+/// How long to wait for a writer to poll the mock node
+const POLL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often `/fees` is requested while waiting for a reader's estimate
+const ESTIMATE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Persistence parity settings
+#[derive(Debug, Clone)]
+pub struct PersistenceConfig {
+    /// Snapshots each writer records
+    pub snapshots: usize,
+    /// Allowed fee rate difference
+    pub tolerance: ToleranceModel,
+    /// How long a reader may take to serve an estimate from its data directory
+    pub estimate_timeout: Duration,
+    /// Where every recorded data directory is kept for inspection
+    pub output: PathBuf,
+}
+
+/// A server whose data directory is exchanged with the others
+pub enum ParityServer {
+    Rust(Box<ServerManager>),
+    /// An earlier release of the Rust server, checked by the upgrade tests
+    Released(Box<ServerManager>),
+    Kotlin(Box<ReferenceServerManager>),
+}
+
+impl ParityServer {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Rust(_) => "Rust server",
+            Self::Released(_) => "released Rust server",
+            Self::Kotlin(_) => "Kotlin reference",
+        }
+    }
+
+    /// Short name of the directories this server recorded
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Self::Rust(_) => "rust",
+            Self::Released(_) => "released",
+            Self::Kotlin(_) => "kotlin",
+        }
+    }
+
+    /// Whether a failure to read a directory fails the run, rather than being reported
+    fn strict(&self) -> bool {
+        matches!(self, Self::Rust(_))
+    }
+
+    pub(crate) fn snapshot_dir(&self) -> PathBuf {
+        match self {
+            Self::Rust(manager) | Self::Released(manager) => manager.snapshot_dir(),
+            Self::Kotlin(manager) => manager.snapshot_dir(),
+        }
+    }
+
+    pub(crate) fn client(&self) -> ApiClient {
+        match self {
+            Self::Rust(manager) | Self::Released(manager) => ApiClient::new(manager.url()),
+            Self::Kotlin(manager) => ApiClient::new(manager.url()),
+        }
+    }
+
+    pub(crate) async fn start(&mut self) -> Result<()> {
+        match self {
+            Self::Rust(manager) | Self::Released(manager) => manager.start().await,
+            Self::Kotlin(manager) => manager.start().await,
+        }
+    }
+
+    pub(crate) async fn stop(&mut self) -> Result<()> {
+        match self {
+            Self::Rust(manager) | Self::Released(manager) => manager.stop().await,
+            Self::Kotlin(manager) => manager.stop().await,
+        }
+    }
+
+    /// Replaces the server's data directory with a copy of `source`, or an empty one
+    pub(crate) fn reset_data(&self, source: Option<&Path>) -> Result<()> {
+        let dir = self.snapshot_dir();
+        if dir.exists() {
+            fs::remove_dir_all(&dir).with_context(|| format!("Failed to clear {dir:?}"))?;
+        }
+        match source {
+            Some(source) => copy_dir(source, &dir),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for ParityServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Copies the files below `from` into `to`, creating directories as needed
+pub fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to).with_context(|| format!("Failed to create {to:?}"))?;
+    for entry in fs::read_dir(from).with_context(|| format!("Failed to read {from:?}"))? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().context("Entry without a file name")?);
+        if path.is_dir() {
+            copy_dir(&path, &target)?;
+        } else {
+            fs::copy(&path, &target)
+                .with_context(|| format!("Failed to copy {path:?} to {target:?}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads every JSON snapshot file in the subdirectories of `dir`, failing on any that does not
+/// parse
+///
+/// Files directly in `dir`, such as the Rust server's estimate cache, are not snapshots.
+pub fn read_snapshot_dir(dir: &Path) -> Result<Vec<MempoolSnapshot>> {
+    let mut snapshots = Vec::new();
+    let mut pending = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {dir:?}"))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            pending.push(path);
+        }
+    }
+
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {dir:?}"))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {path:?}"))?;
+                let snapshot = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse {path:?}"))?;
+                snapshots.push(snapshot);
+            }
+        }
+    }
+
+    snapshots.sort_by_key(|s: &MempoolSnapshot| s.timestamp);
+    Ok(snapshots)
+}
+
+/// Waits until `rpc` has answered `count` more mempool polls
+async fn wait_for_polls(rpc: &MockBitcoinRpc, count: u64) -> Result<()> {
+    let target = rpc.mempool_polls() + count;
+    let deadline = Instant::now() + POLL_TIMEOUT;
+    while rpc.mempool_polls() < target {
+        ensure!(
+            Instant::now() < deadline,
+            "Server stopped polling the mock node"
+        );
+        sleep(Duration::from_millis(100)).await;
+    }
+    Ok(())
+}
+
+/// Polls `/fees` until `client` serves an estimate
+async fn wait_for_estimate(client: &ApiClient, timeout: Duration) -> Result<FeeEstimateResponse> {
+    let started = Instant::now();
+    loop {
+        match client.get_fees().await {
+            Ok(response) if response.has_estimates() => return Ok(response),
+            Ok(_) => debug!("No estimate served yet"),
+            Err(e) => debug!("Failed to request estimates: {e}"),
+        }
+        ensure!(
+            started.elapsed() < timeout,
+            "No estimate served within {timeout:?}"
+        );
+        sleep(ESTIMATE_POLL_INTERVAL).await;
+    }
+}
+
+/// Has `server` record `count` snapshots of a changing mempool into its data directory
+pub(crate) async fn record(
+    server: &mut ParityServer,
+    rpc: &MockBitcoinRpc,
+    count: usize,
+) -> Result<()> {
+    server.start().await?;
+    for i in 0..count {
+        rpc.set_mempool(TestDataGenerator::uniform_distribution(
+            200 + 50 * i,
+            1.0,
+            50.0 + 10.0 * i as f64,
+        ));
+        rpc.mine_blocks(1, 1_000_000);
+        wait_for_polls(rpc, POLLS_PER_SNAPSHOT).await?;
+    }
+    server.stop().await
+}
+
+/// Copies the data directory of `server` to `dir`, checking that every snapshot file in it
+/// parses
+pub(crate) fn keep_recorded(
+    server: &ParityServer,
+    dir: &Path,
+    results: &mut TestResults,
+) -> Result<()> {
+    copy_dir(&server.snapshot_dir(), dir)?;
+    let test = format!("{server} snapshot files parse");
+    match read_snapshot_dir(dir) {
+        Ok(snapshots) if snapshots.is_empty() => {
+            results.add_fail(&test, &format!("No snapshot files in {dir:?}"))
+        }
+        Ok(snapshots) => results.add_pass(
+            &test,
+            &format!("{count} snapshots", count = snapshots.len()),
+        ),
+        Err(e) => results.add_fail(&test, &format!("{e:#}")),
+    }
+    Ok(())
+}
+
+/// Starts `server` on a copy of `source` while the node fails, and compares its estimate with
+/// the library's for the snapshots in `source`
+///
+/// When they differ, both estimates are recorded in `results` under `test`.
+pub(crate) async fn read(
+    server: &mut ParityServer,
+    rpc: &MockBitcoinRpc,
+    source: &Path,
+    config: &PersistenceConfig,
+    results: &mut TestResults,
+    test: &str,
+) -> Result<()> {
+    server.reset_data(Some(source))?;
+    rpc.set_fault(Fault::ServerError);
+    let served = async {
+        server.start().await?;
+        wait_for_estimate(&server.client(), config.estimate_timeout).await
+    }
+    .await;
+    rpc.set_fault(Fault::None);
+    server.stop().await?;
+    let served = served?;
+
+    let update_time = DateTime::parse_from_rfc3339(&served.mempool_update_time)
+        .with_context(|| {
+            format!(
+                "Invalid mempool_update_time {time}",
+                time = served.mempool_update_time
+            )
+        })?
+        .with_timezone(&Utc);
+    // The served time is truncated to milliseconds
+    let mut snapshots = read_snapshot_dir(source)?;
+    snapshots.retain(|s| s.timestamp < update_time + chrono::Duration::milliseconds(1));
+    ensure!(
+        !snapshots.is_empty(),
+        "Served an estimate from {update_time}, before every stored snapshot"
+    );
+
+    let expected = estimate(&snapshots, None)?;
+    let mismatches: Vec<_> = compare(&expected, &served_estimates(&served), &config.tolerance)
+        .into_iter()
+        .filter(|v| !v.passed)
+        .collect();
+    if !mismatches.is_empty() {
+        results.record_responses(
+            test,
+            [
+                (server.label(), serde_json::to_value(&served)?),
+                ("library", serde_json::to_value(&expected)?),
+            ],
+        );
+    }
+    ensure!(
+        mismatches.is_empty(),
+        "{count} fee rates differ from the library, first: {first}",
+        count = mismatches.len(),
+        first = mismatches[0].message
+    );
+    Ok(())
+}
+
+/// A time `/historical_fee` is requested for
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HistoricalPoint {
+    name: &'static str,
+    timestamp: i64,
+}
+
+/// How long before the first snapshot the request without any data is made
+const BEFORE_DATA_SECS: i64 = 3600;
+
+/// Times before every snapshot in `snapshots`, between the last two at least two seconds apart,
+/// and on the last one
+///
+/// Snapshot files are named by the second they were taken in, so a time between two snapshots
+/// needs a whole second after the first one.
+fn historical_points(snapshots: &[MempoolSnapshot]) -> Vec<HistoricalPoint> {
+    let seconds: Vec<i64> = snapshots.iter().map(|s| s.timestamp.timestamp()).collect();
+    let (Some(&first), Some(&last)) = (seconds.first(), seconds.last()) else {
+        return Vec::new();
+    };
+
+    let mut points = vec![HistoricalPoint {
+        name: "before any snapshot",
+        timestamp: first - BEFORE_DATA_SECS,
+    }];
+    if let Some(pair) = seconds.windows(2).rev().find(|pair| pair[1] - pair[0] >= 2) {
+        points.push(HistoricalPoint {
+            name: "between snapshots",
+            timestamp: pair[0] + 1,
+        });
+    }
+    points.push(HistoricalPoint {
+        name: "on a snapshot",
+        timestamp: last,
+    });
+    points
+}
+
+/// The library's estimate from the snapshots taken up to `timestamp`, or `None` where a server
+/// has no data to estimate from
+fn historical_estimate(
+    snapshots: &[MempoolSnapshot],
+    timestamp: i64,
+) -> Result<Option<ExpectedEstimates>> {
+    let snapshots: Vec<MempoolSnapshot> = snapshots
+        .iter()
+        .filter(|s| s.timestamp.timestamp() <= timestamp)
+        .cloned()
+        .collect();
+    if snapshots.is_empty() {
+        return Ok(None);
+    }
+    match estimate(&snapshots, None) {
+        Ok(expected) if expected.is_empty() => Ok(None),
+        Ok(expected) => Ok(Some(expected)),
+        Err(e)
+            if matches!(
+                e.downcast_ref::<AugurError>(),
+                Some(AugurError::InsufficientData(_))
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Requests `/historical_fee` for `point`, returning the estimate, or `None` if the server had
+/// no data
+async fn request_historical(
+    client: &ApiClient,
+    point: HistoricalPoint,
+) -> Result<Option<FeeEstimateResponse>> {
+    let path = format!("/historical_fee?timestamp={time}", time = point.timestamp);
+    let (status, body) = client.get_raw(&path).await?;
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    ensure!(
+        status.is_success(),
+        "GET {path} failed with {status}: {body}"
+    );
+    let response = serde_json::from_value(body)
+        .with_context(|| format!("GET {path} returned an invalid estimate"))?;
+    Ok(Some(response))
+}
+
+/// Checks the estimate `served` for `point` against the library's `expected` one
+fn check_historical(
+    expected: Option<&ExpectedEstimates>,
+    served: Option<&FeeEstimateResponse>,
+    tolerance: &ToleranceModel,
+) -> Result<()> {
+    match (expected, served) {
+        (None, None) => Ok(()),
+        (None, Some(_)) => bail!("Served an estimate where the library has none"),
+        (Some(_), None) => bail!("Served no estimate where the library has one"),
+        (Some(expected), Some(served)) => {
+            let mismatches: Vec<_> = compare(expected, &served_estimates(served), tolerance)
+                .into_iter()
+                .filter(|v| !v.passed)
+                .collect();
+            ensure!(
+                mismatches.is_empty(),
+                "{count} fee rates differ, first: {blocks} blocks @ {probability}: {message}",
+                count = mismatches.len(),
+                blocks = mismatches[0].blocks,
+                probability = mismatches[0].probability,
+                message = mismatches[0].message
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Starts `server` on a copy of `source` while the node fails, and requests `/historical_fee`
+/// for every one of `points`
+async fn read_historical(
+    server: &mut ParityServer,
+    rpc: &MockBitcoinRpc,
+    source: &Path,
+    points: &[HistoricalPoint],
+) -> Result<Vec<Option<FeeEstimateResponse>>> {
+    server.reset_data(Some(source))?;
+    rpc.set_fault(Fault::ServerError);
+    let served = async {
+        server.start().await?;
+        let client = server.client();
+        let mut served = Vec::with_capacity(points.len());
+        for &point in points {
+            served.push(request_historical(&client, point).await?);
+        }
+        Ok::<_, anyhow::Error>(served)
+    }
+    .await;
+    rpc.set_fault(Fault::None);
+    server.stop().await?;
+    served
+}
+
+/// Has every server serve historical estimates from the same recorded directory, checking them
+/// against the library and against the first server's
+async fn run_historical(
+    rpc: &MockBitcoinRpc,
+    servers: &mut [ParityServer],
+    (writer, dir): (&str, &Path),
+    config: &PersistenceConfig,
+    results: &mut TestResults,
+) -> Result<()> {
+    let snapshots = read_snapshot_dir(dir)?;
+    let points = historical_points(&snapshots);
+    let expected = points
+        .iter()
+        .map(|point| historical_estimate(&snapshots, point.timestamp))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Name, label and answers of the server the others are compared with
+    let mut first: Option<(&str, &str, Vec<Option<FeeEstimateResponse>>)> = None;
+    for server in servers.iter_mut() {
+        info!("Requesting historical estimates from the {server} on the {writer} data directory");
+        let strict = server.strict();
+
+        let served = match read_historical(server, rpc, dir, &points).await {
+            Ok(served) => served,
+            Err(e) => {
+                let test = format!("{server} serves historical estimates");
+                report(results, strict, &test, &format!("{e:#}"));
+                continue;
+            }
+        };
+
+        for (index, point) in points.iter().enumerate() {
+            let test = format!("{server} historical estimate {name}", name = point.name);
+            match check_historical(
+                expected[index].as_ref(),
+                served[index].as_ref(),
+                &config.tolerance,
+            ) {
+                Ok(()) if served[index].is_some() => {
+                    results.add_pass(&test, "Matches the library's estimate")
+                }
+                Ok(()) => results.add_pass(&test, "No data, as expected"),
+                Err(e) => {
+                    results.record_responses(
+                        &test,
+                        [
+                            (server.label(), serde_json::to_value(&served[index])?),
+                            ("library", serde_json::to_value(&expected[index])?),
+                        ],
+                    );
+                    report(results, strict, &test, &format!("{e:#}"));
+                }
+            }
+
+            if let Some((name, label, ref first)) = first {
+                let test = format!(
+                    "{server} historical estimate {point} matches the {name}",
+                    point = point.name
+                );
+                let first_estimate = first[index].as_ref().map(served_estimates);
+                match check_historical(
+                    first_estimate.as_ref(),
+                    served[index].as_ref(),
+                    &config.tolerance,
+                ) {
+                    Ok(()) => results.add_pass(&test, "Within the tolerance"),
+                    Err(e) => {
+                        results.record_responses(
+                            &test,
+                            [
+                                (server.label(), serde_json::to_value(&served[index])?),
+                                (label, serde_json::to_value(&first[index])?),
+                            ],
+                        );
+                        report(results, strict, &test, &format!("{e:#}"));
+                    }
+                }
+            }
+        }
+
+        if first.is_none() {
+            first = Some((server.name(), server.label(), served));
+        }
+    }
+    Ok(())
+}
+
+/// Fails `test` for a strict server, and only warns for the others
+fn report(results: &mut TestResults, strict: bool, test: &str, failure: &str) {
+    if strict {
+        results.add_fail(test, failure);
+    } else {
+        results.add_warning(test, failure);
+    }
+}
+
+/// Records a data directory with every server and has every server read each of them
+pub async fn run_persistence(
+    rpc: &MockBitcoinRpc,
+    servers: &mut [ParityServer],
+    config: &PersistenceConfig,
+) -> Result<TestResults> {
+    ensure!(!servers.is_empty(), "No servers to test");
+    ensure!(config.snapshots > 0, "Every writer must record a snapshot");
+    config.tolerance.validate().map_err(anyhow::Error::msg)?;
+    let mut results = TestResults::new();
+
+    if config.output.exists() {
+        fs::remove_dir_all(&config.output)
+            .with_context(|| format!("Failed to clear {output:?}", output = config.output))?;
+    }
+
+    // Writers run one at a time, so each directory holds only its own writer's snapshots
+    let mut recorded = Vec::with_capacity(servers.len());
+    for server in servers.iter_mut() {
+        info!(
+            "Recording {count} snapshots with the {server}",
+            count = config.snapshots
+        );
+        server.reset_data(None)?;
+        record(server, rpc, config.snapshots)
+            .await
+            .with_context(|| format!("{server} failed to record snapshots"))?;
+
+        let dir = config.output.join(server.label());
+        keep_recorded(server, &dir, &mut results)?;
+        recorded.push((server.name(), dir));
+    }
+
+    for (reader_index, reader) in servers.iter_mut().enumerate() {
+        // A reader's own directory comes first, telling whether it reads stored snapshots at all
+        let mut order: Vec<usize> = (0..recorded.len()).collect();
+        order.swap(0, reader_index);
+
+        let mut reads_own = true;
+        for index in order {
+            let (writer, dir) = &recorded[index];
+            let test = format!("{reader} reads the {writer} data directory");
+            info!("Checking that the {reader} reads the {writer} data directory");
+            match read(reader, rpc, dir, config, &mut results, &test).await {
+                Ok(()) => results.add_pass(&test, "Served the library's estimate"),
+                Err(e) if index == reader_index => {
+                    reads_own = false;
+                    if reader.strict() {
+                        results.add_fail(&test, &format!("{e:#}"));
+                    } else {
+                        results.add_warning(
+                            &test,
+                            &format!("Does not restore estimates from disk: {e:#}"),
+                        );
+                    }
+                }
+                Err(e) if reader.strict() || reads_own => {
+                    results.add_fail(&test, &format!("{e:#}"))
+                }
+                Err(e) => results.add_warning(
+                    &test,
+                    &format!("Skipped, the reader cannot read its own directory: {e:#}"),
+                ),
+            }
+        }
+    }
+
+    // Every server answers from a copy of the first writer's directory
+    let (writer, dir) = &recorded[0];
+    run_historical(rpc, servers, (writer, dir), config, &mut results).await?;
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::MempoolTransaction;
+
+    #[test]
+    fn test_read_snapshot_dir_copies_and_parses_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let day = source.join("2025-01-20");
+        fs::create_dir_all(&day).unwrap();
+
+        let time = DateTime::parse_from_rfc3339("2025-01-20T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for (i, height) in [(0, 850_001), (1, 850_000)] {
+            let snapshot = MempoolSnapshot::from_transactions(
+                vec![MempoolTransaction::new(400, 1_000)],
+                height,
+                time + chrono::Duration::seconds(30 * (1 - i)),
+            );
+            fs::write(
+                day.join(format!("{height}_{i}.json")),
+                serde_json::to_string(&snapshot).unwrap(),
+            )
+            .unwrap();
+        }
+        // Files outside the date directories are not snapshots
+        fs::write(source.join("estimate_cache.json"), "{}").unwrap();
+        fs::write(source.join("FORMAT_VERSION"), "3").unwrap();
+
+        let copy = dir.path().join("copy");
+        copy_dir(&source, &copy).unwrap();
+        assert!(copy.join("FORMAT_VERSION").exists());
+
+        let snapshots = read_snapshot_dir(&copy).unwrap();
+        let heights: Vec<u32> = snapshots.iter().map(|s| s.block_height).collect();
+        assert_eq!(heights, [850_000, 850_001]);
+
+        fs::write(copy.join("2025-01-20").join("850002_2.json"), "{").unwrap();
+        let error = read_snapshot_dir(&copy).unwrap_err();
+        assert!(format!("{error:#}").contains("850002_2.json"), "{error:#}");
+    }
+
+    #[test]
+    fn test_historical_points_and_estimates() {
+        let start = DateTime::parse_from_rfc3339("2025-01-20T12:00:00.500Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        // One second apart, then three
+        let snapshots: Vec<MempoolSnapshot> = [0, 1, 4]
+            .into_iter()
+            .map(|offset| {
+                let transactions = (0..200)
+                    .map(|i| MempoolTransaction::new(400 + i, 1_000 + 100 * i))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850_000 + offset as u32,
+                    start + chrono::Duration::seconds(offset),
+                )
+            })
+            .collect();
+
+        let first = start.timestamp();
+        assert_eq!(
+            historical_points(&snapshots),
+            [
+                HistoricalPoint {
+                    name: "before any snapshot",
+                    timestamp: first - BEFORE_DATA_SECS,
+                },
+                HistoricalPoint {
+                    name: "between snapshots",
+                    timestamp: first + 2,
+                },
+                HistoricalPoint {
+                    name: "on a snapshot",
+                    timestamp: first + 4,
+                },
+            ]
+        );
+        assert!(historical_points(&[]).is_empty());
+
+        assert_eq!(historical_estimate(&snapshots, first - 1).unwrap(), None);
+        let between = historical_estimate(&snapshots, first + 2).unwrap();
+        assert_eq!(between, Some(estimate(&snapshots[..2], None).unwrap()));
+        let on = historical_estimate(&snapshots, first + 4).unwrap();
+        assert_eq!(on, Some(estimate(&snapshots, None).unwrap()));
+    }
+}
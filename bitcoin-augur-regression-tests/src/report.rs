@@ -0,0 +1,590 @@
+//! HTML report of a comparison between two servers
+//!
+//! `compare --html` writes a self-contained page for reviewing parity without the command line.
+//! Each compared path shows both responses side by side with the differing lines highlighted,
+//! and a heatmap of the relative difference of every fee rate by block target and confidence,
+//! colored by how it compares with the tolerance. A summary of every run is appended to a JSON
+//! lines history file, and the page charts it, so slow drift between implementations shows up.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+
+use crate::api_client::FeeEstimateResponse;
+use crate::comparison::ToleranceModel;
+use crate::differential::served_estimates;
+use crate::test_vectors::ExpectedEstimates;
+
+/// Responses of both servers for one request path
+#[derive(Debug, Clone)]
+pub struct PathComparison {
+    pub path: String,
+    pub left_status: u16,
+    pub right_status: u16,
+    pub left: Value,
+    pub right: Value,
+    /// Differences found by [`crate::api_client::ResponseComparator`]
+    pub differences: Vec<String>,
+}
+
+impl PathComparison {
+    /// Fee rates of both responses by block target and confidence, when both are fee estimates
+    pub fn fee_rate_differences(&self) -> Option<Vec<FeeRateDifference>> {
+        let left = fee_rates(&self.left)?;
+        let right = fee_rates(&self.right)?;
+
+        let mut targets: Vec<&String> = left.keys().chain(right.keys()).collect();
+        targets.sort_by(|a, b| numeric(a).total_cmp(&numeric(b)));
+        targets.dedup();
+
+        let mut differences = Vec::new();
+        for target in targets {
+            let probabilities: BTreeSet<&String> = left
+                .get(target)
+                .into_iter()
+                .chain(right.get(target))
+                .flat_map(|rates| rates.keys())
+                .collect();
+            let mut probabilities: Vec<&String> = probabilities.into_iter().collect();
+            probabilities.sort_by(|a, b| numeric(a).total_cmp(&numeric(b)));
+            for probability in probabilities {
+                let rate = |estimates: &ExpectedEstimates| {
+                    estimates
+                        .get(target)
+                        .and_then(|rates| rates.get(probability))
+                        .copied()
+                        .flatten()
+                };
+                differences.push(FeeRateDifference {
+                    target: target.clone(),
+                    probability: probability.clone(),
+                    left: rate(&left),
+                    right: rate(&right),
+                });
+            }
+        }
+        Some(differences)
+    }
+}
+
+/// A fee rate as served by both servers
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeRateDifference {
+    pub target: String,
+    pub probability: String,
+    pub left: Option<f64>,
+    pub right: Option<f64>,
+}
+
+impl FeeRateDifference {
+    /// Difference relative to the larger fee rate, or `None` if one server has no fee rate
+    pub fn relative(&self) -> Option<f64> {
+        let (left, right) = (self.left?, self.right?);
+        let scale = left.abs().max(right.abs());
+        Some(if scale == 0.0 {
+            0.0
+        } else {
+            (left - right).abs() / scale
+        })
+    }
+
+    /// Difference as a multiple of what `tolerance` allows, so 1 is the limit
+    fn tolerance_ratio(&self, tolerance: &ToleranceModel) -> Option<f64> {
+        let (left, right) = (self.left?, self.right?);
+        let allowed = tolerance.allowed(left);
+        let difference = (left - right).abs();
+        Some(if difference == 0.0 {
+            0.0
+        } else if allowed == 0.0 {
+            f64::INFINITY
+        } else {
+            difference / allowed
+        })
+    }
+}
+
+/// Everything one `compare` run found
+#[derive(Debug, Clone)]
+pub struct ComparisonReport {
+    pub time: DateTime<Utc>,
+    pub left: String,
+    pub right: String,
+    pub tolerance: ToleranceModel,
+    pub paths: Vec<PathComparison>,
+}
+
+/// One run in the history file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub time: DateTime<Utc>,
+    pub left: String,
+    pub right: String,
+    pub paths: usize,
+    /// Differences found in all responses
+    pub differences: usize,
+    /// Fee rates outside the tolerance or served by one server only
+    pub outside_tolerance: usize,
+    pub max_relative_difference: Option<f64>,
+}
+
+impl ComparisonReport {
+    /// Counts of this run for the history
+    pub fn summary(&self) -> RunSummary {
+        let rates: Vec<FeeRateDifference> = self
+            .paths
+            .iter()
+            .filter_map(PathComparison::fee_rate_differences)
+            .flatten()
+            .collect();
+        RunSummary {
+            time: self.time,
+            left: self.left.clone(),
+            right: self.right.clone(),
+            paths: self.paths.len(),
+            differences: self.paths.iter().map(|p| p.differences.len()).sum(),
+            outside_tolerance: rates
+                .iter()
+                .filter(|rate| {
+                    rate.tolerance_ratio(&self.tolerance)
+                        .is_none_or(|r| r > 1.0)
+                })
+                .count(),
+            max_relative_difference: rates
+                .iter()
+                .filter_map(FeeRateDifference::relative)
+                .reduce(f64::max),
+        }
+    }
+
+    /// The report as a standalone HTML page, with `history` ordered from the oldest run
+    pub fn to_html(&self, history: &[RunSummary]) -> String {
+        let mut html = String::new();
+        let title = format!(
+            "{left} vs {right}",
+            left = escape(&self.left),
+            right = escape(&self.right)
+        );
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Comparison: {title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n<p>Compared at {time} with a tolerance of {tolerance}.</p>\n",
+            time = self.time.to_rfc3339(),
+            tolerance = escape(&self.tolerance.to_string())
+        );
+
+        html.push_str("<h2>History</h2>\n");
+        html.push_str(&history_chart(history));
+        html.push_str(&history_table(history));
+
+        for comparison in &self.paths {
+            let _ = write!(
+                html,
+                "<h2><code>{path}</code></h2>\n<p>Status {left_status} vs {right_status}, \
+                 {count} differences.</p>\n",
+                path = escape(&comparison.path),
+                left_status = comparison.left_status,
+                right_status = comparison.right_status,
+                count = comparison.differences.len()
+            );
+            if !comparison.differences.is_empty() {
+                html.push_str("<ul class=\"differences\">\n");
+                for difference in &comparison.differences {
+                    let _ = writeln!(
+                        html,
+                        "<li>{difference}</li>",
+                        difference = escape(difference)
+                    );
+                }
+                html.push_str("</ul>\n");
+            }
+            if let Some(rates) = comparison.fee_rate_differences() {
+                html.push_str(&heatmap(&rates, &self.tolerance));
+            }
+            html.push_str(&side_by_side(
+                &self.left,
+                &comparison.left,
+                &self.right,
+                &comparison.right,
+            ));
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+/// Reads the runs in the history file at `path`, of which there are none before the first run
+pub fn load_history(path: &Path) -> Result<Vec<RunSummary>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read history {path:?}"))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Invalid run {number} in history {path:?}",
+                    number = index + 1
+                )
+            })
+        })
+        .collect()
+}
+
+/// Appends `summary` to the history file at `path`
+pub fn append_history(path: &Path, summary: &RunSummary) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history {path:?}"))?;
+    writeln!(file, "{line}", line = serde_json::to_string(summary)?)
+        .with_context(|| format!("Failed to write history {path:?}"))
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:1em 0}\
+th,td{border:1px solid #ccc;padding:2px 6px;text-align:right}\
+.diff td{text-align:left;font-family:monospace;white-space:pre;vertical-align:top}\
+.diff td.changed{background:#fff3bf}.diff td.removed{background:#ffc9c9}\
+.diff td.added{background:#c3fae8}.heatmap td{min-width:5em}\
+.heatmap td.missing{background:#dee2e6}.differences{font-family:monospace}\
+svg{border:1px solid #ccc}";
+
+/// Heatmap cell of a fee rate only one server serves
+const MISSING: &str = "missing";
+
+/// Relative differences by block target (rows) and confidence (columns)
+fn heatmap(rates: &[FeeRateDifference], tolerance: &ToleranceModel) -> String {
+    let mut targets: Vec<&str> = Vec::new();
+    let mut probabilities: Vec<&str> = Vec::new();
+    for rate in rates {
+        if !targets.contains(&rate.target.as_str()) {
+            targets.push(&rate.target);
+        }
+        if !probabilities.contains(&rate.probability.as_str()) {
+            probabilities.push(&rate.probability);
+        }
+    }
+    probabilities.sort_by(|a, b| numeric(a).total_cmp(&numeric(b)));
+
+    let mut html = String::from("<table class=\"heatmap\">\n<tr><th>Blocks</th>");
+    for probability in &probabilities {
+        let _ = write!(
+            html,
+            "<th>{probability}</th>",
+            probability = escape(probability)
+        );
+    }
+    html.push_str("</tr>\n");
+    for target in targets {
+        let _ = write!(html, "<tr><th>{target}</th>", target = escape(target));
+        for probability in &probabilities {
+            let rate = rates
+                .iter()
+                .find(|r| r.target == target && r.probability == *probability);
+            match rate {
+                Some(rate) => html.push_str(&heatmap_cell(rate, tolerance)),
+                None => html.push_str("<td></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn heatmap_cell(rate: &FeeRateDifference, tolerance: &ToleranceModel) -> String {
+    let title = format!(
+        "{left} vs {right} sat/vB",
+        left = format_rate(rate.left),
+        right = format_rate(rate.right)
+    );
+    match (rate.relative(), rate.tolerance_ratio(tolerance)) {
+        (Some(relative), Some(ratio)) => format!(
+            "<td style=\"background:{color}\" title=\"{title}\">{relative}</td>",
+            color = heat_color(ratio),
+            relative = if relative == 0.0 {
+                "0".to_string()
+            } else {
+                format!("{relative:.1e}")
+            }
+        ),
+        _ => format!("<td class=\"missing\" title=\"{title}\">{MISSING}</td>"),
+    }
+}
+
+/// Green for equal fee rates, yellow at the tolerance and red at twice the tolerance or more
+fn heat_color(ratio: f64) -> String {
+    let hue = 120.0 * (1.0 - ratio.clamp(0.0, 2.0) / 2.0);
+    format!("hsl({hue:.0},70%,75%)")
+}
+
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "none".to_string(), |rate| format!("{rate:.4}"))
+}
+
+/// Both responses, pretty printed, in two columns aligned on their common lines
+fn side_by_side(left_name: &str, left: &Value, right_name: &str, right: &Value) -> String {
+    let pretty = |value: &Value| serde_json::to_string_pretty(value).unwrap_or_default();
+    let (left, right) = (pretty(left), pretty(right));
+    let left: Vec<&str> = left.lines().collect();
+    let right: Vec<&str> = right.lines().collect();
+
+    let mut html = format!(
+        "<table class=\"diff\">\n<tr><th>{left_name}</th><th>{right_name}</th></tr>\n",
+        left_name = escape(left_name),
+        right_name = escape(right_name)
+    );
+    for row in align_lines(&left, &right) {
+        let (left_class, right_class) = match row {
+            (Some(_), Some(_)) if row.0 == row.1 => ("", ""),
+            (Some(_), Some(_)) => ("changed", "changed"),
+            (Some(_), None) => ("removed", ""),
+            (None, _) => ("", "added"),
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td class=\"{left_class}\">{left}</td><td class=\"{right_class}\">{right}</td></tr>",
+            left = escape(row.0.unwrap_or_default()),
+            right = escape(row.1.unwrap_or_default())
+        );
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Pairs the lines of `left` and `right` along their longest common subsequence, putting a
+/// line only one side has next to a line only the other has where possible
+fn align_lines<'a>(left: &[&'a str], right: &[&'a str]) -> Vec<(Option<&'a str>, Option<&'a str>)> {
+    // common[i][j] is the length of the longest common subsequence of left[i..] and right[j..]
+    let mut common = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            common[i][j] = if left[i] == right[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let flush = |rows: &mut Vec<_>, removed: &mut Vec<&'a str>, added: &mut Vec<&'a str>| {
+        let count = removed.len().max(added.len());
+        for k in 0..count {
+            rows.push((removed.get(k).copied(), added.get(k).copied()));
+        }
+        removed.clear();
+        added.clear();
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            flush(&mut rows, &mut removed, &mut added);
+            rows.push((Some(left[i]), Some(right[j])));
+            i += 1;
+            j += 1;
+        } else if j == right.len() || (i < left.len() && common[i + 1][j] >= common[i][j + 1]) {
+            removed.push(left[i]);
+            i += 1;
+        } else {
+            added.push(right[j]);
+            j += 1;
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// Runs as a line chart of the fee rates outside the tolerance, oldest first
+fn history_chart(history: &[RunSummary]) -> String {
+    const WIDTH: f64 = 600.0;
+    const HEIGHT: f64 = 120.0;
+    if history.len() < 2 {
+        return String::new();
+    }
+    let max = history
+        .iter()
+        .map(|run| run.outside_tolerance)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let step = WIDTH / (history.len() - 1) as f64;
+    let points: Vec<String> = history
+        .iter()
+        .enumerate()
+        .map(|(index, run)| {
+            let x = index as f64 * step;
+            let y = HEIGHT - run.outside_tolerance as f64 / max * HEIGHT;
+            format!("{x:.1},{y:.1}")
+        })
+        .collect();
+    format!(
+        "<svg width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"-4 -4 {view_width} {view_height}\">\
+         <title>Fee rates outside the tolerance per run, at most {max}</title>\
+         <polyline fill=\"none\" stroke=\"#c92a2a\" stroke-width=\"2\" points=\"{points}\"/></svg>\n",
+        view_width = WIDTH + 8.0,
+        view_height = HEIGHT + 8.0,
+        points = points.join(" ")
+    )
+}
+
+/// Runs newest first
+fn history_table(history: &[RunSummary]) -> String {
+    let mut html = String::from(
+        "<table>\n<tr><th>Time</th><th>Servers</th><th>Paths</th><th>Differences</th>\
+         <th>Outside tolerance</th><th>Max relative difference</th></tr>\n",
+    );
+    for run in history.iter().rev() {
+        let _ = writeln!(
+            html,
+            "<tr><td>{time}</td><td>{left} vs {right}</td><td>{paths}</td><td>{differences}</td>\
+             <td>{outside}</td><td>{max}</td></tr>",
+            time = run.time.to_rfc3339(),
+            left = escape(&run.left),
+            right = escape(&run.right),
+            paths = run.paths,
+            differences = run.differences,
+            outside = run.outside_tolerance,
+            max = run
+                .max_relative_difference
+                .map_or_else(|| "-".to_string(), |max| format!("{max:.2e}"))
+        );
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Fee rates by block target and confidence, if `body` is a fee estimate response
+fn fee_rates(body: &Value) -> Option<ExpectedEstimates> {
+    let response: FeeEstimateResponse = serde_json::from_value(body.clone()).ok()?;
+    Some(served_estimates(&response))
+}
+
+/// Sort key of a block target or confidence level
+fn numeric(key: &str) -> f64 {
+    key.parse().unwrap_or(f64::INFINITY)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fees(rate: f64) -> Value {
+        json!({
+            "mempool_update_time": "2025-01-20T12:00:00.000Z",
+            "estimates": {
+                "3": {"probabilities": {"0.05": {"fee_rate": 2.0}, "0.95": {"fee_rate": rate}}},
+                "12": {"probabilities": {"0.05": {"fee_rate": 1.0}}}
+            }
+        })
+    }
+
+    fn report(left: f64, right: f64) -> ComparisonReport {
+        ComparisonReport {
+            time: DateTime::parse_from_rfc3339("2025-01-20T12:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            left: "http://rust".to_string(),
+            right: "http://kotlin".to_string(),
+            tolerance: ToleranceModel::default(),
+            paths: vec![PathComparison {
+                path: "/fees".to_string(),
+                left_status: 200,
+                right_status: 200,
+                left: fees(left),
+                right: fees(right),
+                differences: vec!["estimates.3 <differs>".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_fee_rate_differences_cover_both_responses() {
+        let report = report(10.0, 11.0);
+        let rates = report.paths[0].fee_rate_differences().unwrap();
+        let cells: Vec<(&str, &str)> = rates
+            .iter()
+            .map(|r| (r.target.as_str(), r.probability.as_str()))
+            .collect();
+        assert_eq!(cells, [("3", "0.05"), ("3", "0.95"), ("12", "0.05")]);
+        let relative = rates[1].relative().unwrap();
+        assert!((relative - 1.0 / 11.0).abs() < 1e-12);
+
+        let summary = report.summary();
+        assert_eq!(summary.outside_tolerance, 1);
+        assert_eq!(summary.differences, 1);
+        assert_eq!(summary.max_relative_difference, Some(relative));
+
+        let mut text = report.paths[0].clone();
+        text.right = json!("Not found");
+        assert_eq!(text.fee_rate_differences(), None);
+    }
+
+    #[test]
+    fn test_align_lines_pairs_changed_lines() {
+        let left = ["{", "  \"a\": 1,", "  \"b\": 2", "}"];
+        let right = ["{", "  \"a\": 3,", "  \"b\": 2", "  \"c\": 4", "}"];
+        assert_eq!(
+            align_lines(&left, &right),
+            [
+                (Some("{"), Some("{")),
+                (Some("  \"a\": 1,"), Some("  \"a\": 3,")),
+                (Some("  \"b\": 2"), Some("  \"b\": 2")),
+                (None, Some("  \"c\": 4")),
+                (Some("}"), Some("}")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_escapes_and_shows_history() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let history_path = temp_dir.path().join("report.history.jsonl");
+        assert!(load_history(&history_path).unwrap().is_empty());
+
+        let first = report(10.0, 10.0);
+        append_history(&history_path, &first.summary()).unwrap();
+        let second = report(10.0, 11.0);
+        append_history(&history_path, &second.summary()).unwrap();
+        let history = load_history(&history_path).unwrap();
+        assert_eq!(history, [first.summary(), second.summary()]);
+
+        let html = second.to_html(&history);
+        assert!(html.contains("estimates.3 &lt;differs&gt;"));
+        assert!(!html.contains("<differs>"));
+        assert!(html.contains("<polyline"));
+        assert!(html.contains("class=\"changed\""));
+        // The heatmap cell for 3 blocks at 95% is far beyond the tolerance
+        assert!(html.contains("hsl(0,70%,75%)"));
+    }
+}
@@ -0,0 +1,347 @@
+//! Bit-for-bit reproducibility of estimates across platforms
+//!
+//! A fingerprint records the exact estimates this build produces for a regression vector file,
+//! together with the platform it ran on. Fingerprints recorded on different architectures, or
+//! by an earlier build, must agree to the last bit: regression vectors tolerate tiny differences,
+//! while a fingerprint catches the floating-point drift that a vectorized sum, a fused
+//! multiply-add or a different `exp` implementation would introduce.
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "platform": "x86_64-linux",
+//!   "generator": "bitcoin-augur-rust 0.1.0",
+//!   "vectors": "bitcoin-augur-rust 0.1.0",
+//!   "estimates": { "steady_state": { "3": { "0.05": 2.0138, "0.95": null } } }
+//! }
+//! ```
+//!
+//! `vectors` is the generator of the vector file the estimates are for. Fee rates are written
+//! as JSON numbers, which round-trip exactly.
+
+use anyhow::{ensure, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::test_vectors::{estimate, ExpectedEstimates, VectorFile};
+
+/// Current fingerprint schema version
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Fingerprint recorded from the bundled vectors on x86_64 Linux
+const BUNDLED_FINGERPRINT: &str = include_str!("../vectors/reproducibility_v1.json");
+
+/// Exact estimates of one build for every vector of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fingerprint {
+    pub schema_version: u32,
+    /// Architecture and operating system the estimates were computed on
+    pub platform: String,
+    /// Implementation and version that computed the estimates
+    pub generator: String,
+    /// Generator of the vector file
+    pub vectors: String,
+    /// Estimates keyed by vector name
+    pub estimates: BTreeMap<String, ExpectedEstimates>,
+}
+
+impl Fingerprint {
+    /// Estimates every vector `repeat` times, failing if any run differs from the first
+    pub fn record(file: &VectorFile, filter: Option<&str>, repeat: usize) -> Result<Self> {
+        let mut estimates = BTreeMap::new();
+        for vector in &file.vectors {
+            if filter.is_some_and(|f| !vector.name.contains(f)) {
+                continue;
+            }
+
+            let first = estimate(&vector.snapshots, vector.num_blocks)?;
+            for run in 1..repeat {
+                let again = estimate(&vector.snapshots, vector.num_blocks)?;
+                let divergences = compare_estimates(&vector.name, &first, &again, 0);
+                ensure!(
+                    divergences.is_empty(),
+                    "Vector {name} is not deterministic: run {run} differs from the first at {divergences}",
+                    name = vector.name,
+                    divergences = divergences
+                        .iter()
+                        .map(Divergence::location)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            estimates.insert(vector.name.clone(), first);
+        }
+
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            platform: platform(),
+            generator: format!(
+                "bitcoin-augur-rust {version}",
+                version = env!("CARGO_PKG_VERSION")
+            ),
+            vectors: file.generator.clone(),
+            estimates,
+        })
+    }
+
+    /// Loads a fingerprint file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fingerprint {path:?}"))?;
+        Self::parse(&content).with_context(|| format!("Invalid fingerprint {path:?}"))
+    }
+
+    /// Fingerprint shipped in `vectors/reproducibility_v1.json`
+    pub fn bundled() -> Result<Self> {
+        Self::parse(BUNDLED_FINGERPRINT).context("Bundled fingerprint is invalid")
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let fingerprint: Self = serde_json::from_str(content)?;
+        ensure!(
+            fingerprint.schema_version == SCHEMA_VERSION,
+            "Unsupported schema version {version} (expected {SCHEMA_VERSION})",
+            version = fingerprint.schema_version
+        );
+        Ok(fingerprint)
+    }
+
+    /// Saves the fingerprint as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write fingerprint {path:?}"))
+    }
+
+    /// Every estimate that differs from `reference` by more than `max_ulps`
+    ///
+    /// With a `filter`, only the vectors whose names contain it are compared.
+    pub fn compare(
+        &self,
+        reference: &Fingerprint,
+        filter: Option<&str>,
+        max_ulps: u64,
+    ) -> Vec<Divergence> {
+        let mut names: Vec<&String> = self
+            .estimates
+            .keys()
+            .chain(reference.estimates.keys())
+            .filter(|name| filter.is_none_or(|f| name.contains(f)))
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let empty = ExpectedEstimates::new();
+        names
+            .into_iter()
+            .flat_map(|name| {
+                match (reference.estimates.get(name), self.estimates.get(name)) {
+                    (Some(expected), Some(actual)) => {
+                        compare_estimates(name, expected, actual, max_ulps)
+                    }
+                    // A vector present on one side only differs in every estimate
+                    (Some(expected), None) => compare_estimates(name, expected, &empty, max_ulps),
+                    (None, Some(actual)) => compare_estimates(name, &empty, actual, max_ulps),
+                    (None, None) => Vec::new(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// One estimate that differs between two fingerprints
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub vector: String,
+    pub blocks: String,
+    pub probability: String,
+    pub expected: Option<f64>,
+    pub actual: Option<f64>,
+}
+
+impl Divergence {
+    /// Distance in units in the last place, when both sides have an estimate
+    pub fn ulps(&self) -> Option<u64> {
+        Some(ulp_distance(self.expected?, self.actual?))
+    }
+
+    fn location(&self) -> String {
+        format!(
+            "{vector} {blocks} blocks {probability}",
+            vector = self.vector,
+            blocks = self.blocks,
+            probability = self.probability
+        )
+    }
+}
+
+/// Compares two fingerprints' estimates for one vector
+fn compare_estimates(
+    vector: &str,
+    expected: &ExpectedEstimates,
+    actual: &ExpectedEstimates,
+    max_ulps: u64,
+) -> Vec<Divergence> {
+    let mut keys: Vec<(&String, &String)> = expected
+        .iter()
+        .chain(actual)
+        .flat_map(|(blocks, probabilities)| probabilities.keys().map(move |p| (blocks, p)))
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|(blocks, probability)| {
+            let lookup = |estimates: &ExpectedEstimates| {
+                estimates
+                    .get(blocks)
+                    .and_then(|target| target.get(probability))
+                    .copied()
+                    .flatten()
+            };
+            let divergence = Divergence {
+                vector: vector.to_string(),
+                blocks: blocks.clone(),
+                probability: probability.clone(),
+                expected: lookup(expected),
+                actual: lookup(actual),
+            };
+            let agrees = match (divergence.expected, divergence.actual) {
+                (None, None) => true,
+                _ => divergence.ulps().is_some_and(|ulps| ulps <= max_ulps),
+            };
+            (!agrees).then_some(divergence)
+        })
+        .collect()
+}
+
+/// Number of representable doubles between `a` and `b`
+pub(crate) fn ulp_distance(a: f64, b: f64) -> u64 {
+    if a.is_nan() || b.is_nan() {
+        return if a.to_bits() == b.to_bits() {
+            0
+        } else {
+            u64::MAX
+        };
+    }
+    // Maps the bit patterns onto a line where adjacent doubles are adjacent integers
+    let ordered = |x: f64| {
+        let bits = x.to_bits() as i64;
+        if bits < 0 {
+            i64::MIN - bits
+        } else {
+            bits
+        }
+    };
+    (i128::from(ordered(a)) - i128::from(ordered(b))).unsigned_abs() as u64
+}
+
+/// Architecture and operating system of this build, such as `aarch64-macos`
+pub fn platform() -> String {
+    format!(
+        "{arch}-{os}",
+        arch = std::env::consts::ARCH,
+        os = std::env::consts::OS
+    )
+}
+
+/// Prints the divergences from one reference fingerprint
+pub fn print_divergences(reference: &Fingerprint, divergences: &[Divergence]) {
+    let status = if divergences.is_empty() {
+        "PASSED".green()
+    } else {
+        "FAILED".red()
+    };
+    println!(
+        "\nReproducibility against {platform} ({generator}) [{status}]",
+        platform = reference.platform,
+        generator = reference.generator
+    );
+    println!("{separator}", separator = "-".repeat(60));
+
+    let format_rate = |rate: Option<f64>| {
+        rate.map(|v| format!("{v:?}"))
+            .unwrap_or_else(|| "N/A".to_string())
+    };
+    for divergence in divergences {
+        let ulps = match divergence.ulps() {
+            Some(ulps) => format!("{ulps} ulps"),
+            None => "missing estimate".to_string(),
+        };
+        println!(
+            "{symbol} {location}: expected {expected}, actual {actual} ({ulps})",
+            symbol = "✗".red(),
+            location = divergence.location(),
+            expected = format_rate(divergence.expected),
+            actual = format_rate(divergence.actual)
+        );
+    }
+    if divergences.is_empty() {
+        println!("{symbol} All estimates identical", symbol = "✓".green());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::TestVectorRunner;
+
+    #[test]
+    fn test_bundled_vectors_match_bundled_fingerprint() {
+        let vectors = TestVectorRunner::bundled_vectors().unwrap();
+        let fingerprint = Fingerprint::record(&vectors, None, 2).unwrap();
+        let bundled = Fingerprint::bundled().unwrap();
+
+        assert_eq!(bundled.vectors, vectors.generator);
+        // Other architectures are compared in CI, where a mismatch is what is being looked for
+        if bundled.platform == platform() {
+            assert_eq!(fingerprint.compare(&bundled, None, 0), Vec::new());
+        }
+    }
+
+    #[test]
+    fn test_compare_reports_drift_beyond_max_ulps() {
+        let mut reference = Fingerprint::bundled().unwrap();
+        reference.estimates.retain(|name, _| name == "steady_state");
+        let rate = reference.estimates["steady_state"]["3"]["0.95"].unwrap();
+
+        let mut drifted = reference.clone();
+        let next = f64::from_bits(rate.to_bits() + 1);
+        drifted
+            .estimates
+            .get_mut("steady_state")
+            .unwrap()
+            .get_mut("3")
+            .unwrap()
+            .insert("0.95".to_string(), Some(next));
+
+        let divergences = drifted.compare(&reference, None, 0);
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].ulps(), Some(1));
+        assert!(drifted.compare(&reference, None, 1).is_empty());
+
+        // A vector recorded on one side only is reported, unless filtered out
+        drifted.estimates.insert(
+            "extra".to_string(),
+            reference.estimates["steady_state"].clone(),
+        );
+        assert!(!drifted.compare(&reference, None, 1).is_empty());
+        assert!(drifted.compare(&reference, Some("steady"), 1).is_empty());
+    }
+
+    #[test]
+    fn test_ulp_distance() {
+        assert_eq!(ulp_distance(1.0, 1.0), 0);
+        assert_eq!(ulp_distance(0.0, -0.0), 0);
+        assert_eq!(ulp_distance(1.0, f64::from_bits(1.0f64.to_bits() + 3)), 3);
+        assert_eq!(
+            ulp_distance(-f64::MIN_POSITIVE, f64::MIN_POSITIVE),
+            2 * ulp_distance(0.0, f64::MIN_POSITIVE)
+        );
+    }
+}
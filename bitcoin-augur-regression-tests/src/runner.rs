@@ -1,20 +1,82 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use colored::Colorize;
-use futures::future::join_all;
-use std::path::PathBuf;
+use futures::future::{join_all, FutureExt, LocalBoxFuture};
+use futures::stream::{self, StreamExt};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 use tracing::{error, info, warn};
 
 use crate::{
     api_client::ApiClient,
-    compatibility::CompatibilityTests,
+    artifacts::FailureBundle,
+    bisect::{print_bisection, StageTraceFile},
+    chaos::{run_chaos, ChaosConfig, ChaosNode, ChaosTarget},
+    comparison::ToleranceModel,
+    compatibility::{CompatibilityTests, ReferenceNode, TestResults},
+    differential::{run_differential, DifferentialTarget, FuzzConfig},
+    matrix::{print_matrix_summary, MatrixResult, ServerConfiguration, ServerMatrix},
+    mock_rpc::{Fault, MockBitcoinRpc, MockRpcControl},
+    persistence::{read_snapshot_dir, run_persistence, ParityServer, PersistenceConfig},
+    report::{append_history, load_history, ComparisonReport, PathComparison},
+    reproducibility::{print_divergences, Fingerprint},
+    scenario::run_scenario,
     server::{ReferenceServerManager, ServerManager},
-    snapshots::SnapshotTester,
+    snapshots::{ErrorCase, SnapshotTestResults, SnapshotTester},
+    stress::{run_load_test, LoadTestConfig, StressThresholds},
     test_cases::TestCaseGenerator,
-    test_vectors::TestVectorRunner,
+    test_vectors::{self, TestVector, TestVectorRunner, VectorFile},
+    upgrade::run_upgrade,
 };
 
+/// Waits until the server reports at `GET /health/ready` that it serves an estimate
+async fn wait_for_readiness(url: &str) -> Result<()> {
+    let client = ApiClient::new(url.to_string());
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(30);
+    loop {
+        let (status, body) = client.get_raw("/health/ready").await?;
+        if status.is_success() {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() > deadline {
+            bail!("Server did not become ready within 30 seconds, last body: {body}");
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+}
+
+/// Waits until `GET /fees` fails with the problem `code`
+async fn wait_for_problem(url: &str, code: &str) -> Result<()> {
+    let client = ApiClient::new(url.to_string());
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(30);
+    loop {
+        let (_, _, body) = client.get_problem("/fees").await?;
+        if body["code"] == code {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() > deadline {
+            bail!("GET /fees did not fail with {code} within 30 seconds, last body: {body}");
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+}
+
+/// Runs `cases` concurrently, at most `jobs` at a time, returning their outputs in order
+async fn run_bounded<T>(jobs: usize, cases: Vec<LocalBoxFuture<'_, T>>) -> Vec<T> {
+    stream::iter(cases).buffered(jobs.max(1)).collect().await
+}
+
+/// Where to find the artifacts of a failure, for the end of its error message
+fn artifacts_note(artifacts: &Option<PathBuf>) -> String {
+    match artifacts {
+        Some(path) => format!(", artifacts in {path:?}"),
+        None => String::new(),
+    }
+}
+
+/// Bearer token for the test API of servers started by the runner
+const TEST_API_TOKEN: &str = "regression-test-token";
+
 /// Main test runner
 pub struct TestRunner {
     server_path: Option<PathBuf>,
@@ -92,6 +154,22 @@ impl TestRunner {
         Ok(port)
     }
 
+    /// Port for a Rust server: the one given, or 0 so the server picks a free port itself
+    /// instead of racing other processes for one found beforehand
+    fn server_port(&self) -> u16 {
+        self.port.unwrap_or(0)
+    }
+
+    /// Servers that may run at once: `jobs`, capped at the available cores since estimating
+    /// keeps each server busy, or one when `--port` fixes the port they all bind
+    fn server_jobs(&self) -> usize {
+        if self.port.is_some() {
+            return 1;
+        }
+        let cores = std::thread::available_parallelism().map_or(1, usize::from);
+        self.jobs.clamp(1, cores)
+    }
+
     /// Run all tests
     pub async fn run_all(
         &mut self,
@@ -105,8 +183,8 @@ impl TestRunner {
 
         // Start server if available
         if let Some(ref server_path) = self.server_path {
-            let port = self.get_available_port().await?;
-            self.start_server(server_path.clone(), port).await?;
+            let port = self.server_port();
+            self.start_server(server_path.clone(), port, "run").await?;
 
             if run_snapshots {
                 info!("Running snapshot tests");
@@ -161,8 +239,9 @@ impl TestRunner {
         // Ensure server is running
         if self.server_manager.is_none() {
             if let Some(ref server_path) = self.server_path {
-                let port = self.get_available_port().await?;
-                self.start_server(server_path.clone(), port).await?;
+                let port = self.server_port();
+                self.start_server(server_path.clone(), port, "compatibility")
+                    .await?;
             } else {
                 bail!("No server binary available");
             }
@@ -174,31 +253,518 @@ impl TestRunner {
             .map(|m| m.url())
             .context("Rust server not running")?;
 
-        let reference_url = if with_reference {
+        // The reference server polls a mock node, so the injected snapshots can reach it too
+        let mut reference_url = None;
+        let mut reference_node = None;
+        let mut rpc_task = None;
+        if with_reference {
             if let Some(ref jar_path) = self.reference_jar {
+                let rpc_port = self.get_available_port().await?;
+                let rpc = Arc::new(MockBitcoinRpc::new(rpc_port));
+                let task = rpc.spawn().await?;
                 let port = self.get_available_port().await?;
-                self.start_reference_server(jar_path.clone(), port).await?;
-                Some(
-                    self.reference_manager
-                        .as_ref()
-                        .map(|m| m.url())
-                        .context("Reference server not running")?,
-                )
+                self.start_reference_server(jar_path.clone(), port, Some(&rpc), "compatibility")
+                    .await?;
+                let manager = self
+                    .reference_manager
+                    .as_ref()
+                    .context("Reference server not running")?;
+                reference_url = Some(manager.url());
+                reference_node = Some(ReferenceNode {
+                    rpc,
+                    snapshot_dir: manager.snapshot_dir(),
+                });
+                rpc_task = Some(task);
             } else {
                 warn!("Reference JAR not provided, skipping cross-implementation tests");
-                None
             }
+        }
+
+        let mut compat_tests =
+            CompatibilityTests::new(rust_url, reference_url).with_injection_token(TEST_API_TOKEN);
+        if let Some(node) = reference_node {
+            compat_tests = compat_tests.with_reference_node(node);
+        }
+        let results = compat_tests.run_all().await;
+        if let Some(task) = rpc_task {
+            task.stop().await?;
+        }
+        let results = results?;
+
+        if !results.all_passed() {
+            let artifacts = self.bundle_failure("compatibility", |bundle| {
+                bundle.add_results(&results)?;
+                self.add_servers(bundle)
+            });
+            bail!(
+                "Compatibility tests failed{at}",
+                at = artifacts_note(&artifacts)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Run a fixed-rate load test, failing if any configured threshold is exceeded
+    pub async fn run_stress_test(
+        &mut self,
+        url: Option<String>,
+        config: LoadTestConfig,
+        thresholds: Option<PathBuf>,
+    ) -> Result<()> {
+        // Load thresholds first so a bad file fails before generating load
+        let thresholds = thresholds
+            .map(StressThresholds::from_file)
+            .transpose()?
+            .unwrap_or_default();
+
+        let url = match url {
+            Some(url) => url,
+            None => {
+                if self.server_manager.is_none() {
+                    let server_path = self
+                        .server_path
+                        .clone()
+                        .context("No server binary available")?;
+                    let port = self.server_port();
+                    self.start_server(server_path, port, "stress").await?;
+                }
+                self.server_manager
+                    .as_ref()
+                    .map(|m| m.url())
+                    .context("Rust server not running")?
+            }
+        };
+
+        let report = run_load_test(url, config).await?;
+        report.print_summary();
+
+        let violations = thresholds.violations(&report);
+        if !violations.is_empty() {
+            println!("\n{title}", title = "Threshold violations:".red().bold());
+            for violation in &violations {
+                println!("  {symbol} {violation}", symbol = "✗".red());
+            }
+            bail!(
+                "Stress test exceeded {count} thresholds",
+                count = violations.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Run the compatibility suite against the Rust server in every configuration of the
+    /// matrix file, or of the bundled matrix
+    pub async fn run_matrix_tests(&mut self, matrix_file: Option<PathBuf>) -> Result<()> {
+        let server_path = self
+            .server_path
+            .clone()
+            .context("Matrix tests require the server binary")?;
+        let matrix = match matrix_file {
+            Some(path) => {
+                info!("Loading server matrix from {path:?}");
+                ServerMatrix::from_file(&path)?
+            }
+            None => {
+                info!("Using the bundled server matrix");
+                ServerMatrix::bundled()?
+            }
+        };
+
+        let data_dir = self.data_dir.join("matrix");
+        if data_dir.exists() {
+            tokio::fs::remove_dir_all(&data_dir)
+                .await
+                .with_context(|| format!("Failed to clear {data_dir:?}"))?;
+        }
+
+        // Each configuration runs its own server on its own port, so they run side by side
+        let runner = &*self;
+        let mut cases = Vec::new();
+        for configuration in &matrix.configurations {
+            if let Some(ref filter) = runner.filter {
+                if !configuration.name.contains(filter.as_str()) {
+                    continue;
+                }
+            }
+
+            let name = &configuration.name;
+            let log_path = runner.log_path(&format!("matrix-{name}"), "rust-server");
+            let data_dir = data_dir.join(name);
+            let server_path = &server_path;
+            cases.push(
+                async move {
+                    info!(
+                        "Configuration {name}: {description}",
+                        description = configuration.description
+                    );
+                    let outcome = runner
+                        .run_configuration(server_path, configuration, &data_dir, &log_path)
+                        .await;
+                    let mut result = match outcome {
+                        Ok(ref suite) => MatrixResult::from_results(name, suite, log_path.clone()),
+                        Err(ref e) => MatrixResult::from_error(name, e, log_path.clone()),
+                    };
+                    if !result.passed() {
+                        result.artifacts =
+                            runner.bundle_failure(&format!("matrix-{name}"), |bundle| {
+                                match outcome {
+                                    Ok(ref suite) => bundle.add_results(suite)?,
+                                    Err(_) => bundle.add_failures(&result.failures)?,
+                                }
+                                bundle.add_log(&log_path)?;
+                                bundle.add_data_dir("rust-server", &data_dir)
+                            });
+                    }
+                    result
+                }
+                .boxed_local(),
+            );
+        }
+        let jobs = runner.server_jobs();
+        info!(
+            "Running {count} configurations, {jobs} at a time",
+            count = cases.len()
+        );
+        let results = run_bounded(jobs, cases).await;
+
+        print_matrix_summary(&results);
+        if !results.iter().all(MatrixResult::passed) {
+            bail!("Matrix tests failed");
+        }
+        Ok(())
+    }
+
+    /// Start the Rust server in `configuration` on `data_dir`, logging to `log_path`, run the
+    /// compatibility suite and stop the server
+    async fn run_configuration(
+        &self,
+        server_path: &Path,
+        configuration: &ServerConfiguration,
+        data_dir: &Path,
+        log_path: &Path,
+    ) -> Result<TestResults> {
+        tokio::fs::create_dir_all(data_dir).await?;
+        let port = self.server_port();
+
+        let mut manager =
+            ServerManager::new(server_path.to_path_buf(), port, data_dir.to_path_buf())
+                .with_args(configuration.server_args(data_dir)?)
+                .with_log_path(log_path);
+        if let Some(secs) = configuration.interval_secs {
+            manager = manager.with_interval(secs);
+        }
+        if configuration.library_estimates {
+            manager = manager.with_test_api_token(TEST_API_TOKEN);
+        }
+        info!("Server output: {path:?}", path = manager.log_path());
+        manager.start().await?;
+
+        let mut suite = CompatibilityTests::new(manager.url(), None);
+        if configuration.library_estimates {
+            suite = suite.with_injection_token(TEST_API_TOKEN);
+        }
+        let results = suite.run_all().await;
+        manager.stop().await?;
+        results
+    }
+
+    /// Run differential fuzzing against the reference implementation, or the Rust server
+    /// when no reference JAR is configured
+    pub async fn run_differential_fuzz(&mut self, config: FuzzConfig) -> Result<()> {
+        let target = if let Some(jar_path) = self.reference_jar.clone() {
+            let port = self.get_available_port().await?;
+            let rpc_port = self.get_available_port().await?;
+            let data_dir = self.data_dir.join("differential");
+            tokio::fs::create_dir_all(&data_dir).await?;
+            DifferentialTarget::reference(jar_path, port, rpc_port, data_dir)
         } else {
-            None
+            if self.server_manager.is_none() {
+                let server_path = self
+                    .server_path
+                    .clone()
+                    .context("Neither a reference JAR nor a server binary is available")?;
+                let port = self.server_port();
+                self.start_server(server_path, port, "differential").await?;
+            }
+            let url = self
+                .server_manager
+                .as_ref()
+                .map(|m| m.url())
+                .context("Rust server not running")?;
+            DifferentialTarget::Injection {
+                client: ApiClient::new(url),
+                token: TEST_API_TOKEN.to_string(),
+            }
         };
 
-        let compat_tests = CompatibilityTests::new(rust_url, reference_url);
-        let results = compat_tests.run_all().await?;
+        run_differential(target, &config).await
+    }
+
+    /// Break the node polled by the Rust server, and the reference server when a JAR is
+    /// configured, checking that estimates stay available and recover
+    pub async fn run_chaos_tests(&mut self, config: ChaosConfig) -> Result<()> {
+        let server_path = self
+            .server_path
+            .clone()
+            .context("Chaos tests require the server binary")?;
+        let rpc_port = self.get_available_port().await?;
+        let mut node = ChaosNode::start(rpc_port).await?;
+
+        // Fresh data directories keep snapshots from other runs out of the estimates
+        let data_dir = self.data_dir.join("chaos");
+        if data_dir.exists() {
+            tokio::fs::remove_dir_all(&data_dir)
+                .await
+                .with_context(|| format!("Failed to clear {data_dir:?}"))?;
+        }
+
+        let port = self.server_port();
+        let rust_dir = data_dir.join("rust-server");
+        tokio::fs::create_dir_all(&rust_dir).await?;
+        let mut manager = ServerManager::new(server_path, port, rust_dir)
+            .with_rpc_url(node.url())
+            .with_interval(1)
+            .with_log_path(self.log_path("chaos", "rust-server"));
+        info!("Server output: {path:?}", path = manager.log_path());
+        manager.start().await?;
+        let mut targets = vec![ChaosTarget {
+            name: "Rust server".to_string(),
+            client: ApiClient::new(manager.url()),
+            strict: true,
+        }];
+        self.server_manager = Some(manager);
+
+        if let Some(jar_path) = self.reference_jar.clone() {
+            let port = self.get_available_port().await?;
+            let reference_dir = data_dir.join("reference-server");
+            tokio::fs::create_dir_all(&reference_dir).await?;
+            let mut manager = ReferenceServerManager::new(jar_path, port, reference_dir)
+                .with_rpc_url(node.url())
+                .with_refresh_interval(1)
+                .with_log_path(self.log_path("chaos", "reference-server"));
+            info!(
+                "Reference server output: {path:?}",
+                path = manager.log_path()
+            );
+            manager.start().await?;
+            targets.push(ChaosTarget {
+                name: "Kotlin reference".to_string(),
+                client: ApiClient::new(manager.url()),
+                strict: false,
+            });
+            self.reference_manager = Some(manager);
+        } else {
+            info!("Reference JAR not provided, testing the Rust server only");
+        }
+
+        let results = run_chaos(&mut node, &targets, &config).await;
+        self.stop_servers().await?;
+
+        let artifacts = match results {
+            Ok(ref results) if !results.all_passed() => self.bundle_failure("chaos", |bundle| {
+                bundle.add_results(results)?;
+                bundle.add_scenario(node.rpc())?;
+                bundle.add_log(&self.log_path("chaos", "rust-server"))?;
+                bundle.add_log(&self.log_path("chaos", "reference-server"))?;
+                bundle.add_data_dir("chaos", &data_dir)
+            }),
+            _ => None,
+        };
+        node.stop().await?;
+
+        let results = results?;
+        results.print_summary();
+        if !results.all_passed() {
+            bail!("Chaos tests failed{at}", at = artifacts_note(&artifacts));
+        }
+        Ok(())
+    }
+
+    /// Exchange data directories between the Rust server and, when a JAR is configured, the
+    /// reference server, checking that each serves matching estimates from the others' files
+    pub async fn run_persistence_tests(&mut self, config: PersistenceConfig) -> Result<()> {
+        let server_path = self
+            .server_path
+            .clone()
+            .context("Persistence tests require the server binary")?;
+        let rpc_port = self.get_available_port().await?;
+        let rpc = Arc::new(MockBitcoinRpc::new(rpc_port));
+        let rpc_task = rpc.spawn().await?;
+
+        let data_dir = self.data_dir.join("persistence");
+        let port = self.server_port();
+        let rust_dir = data_dir.join("rust-server");
+        tokio::fs::create_dir_all(&rust_dir).await?;
+        let manager = ServerManager::new(server_path, port, rust_dir)
+            .with_rpc_url(rpc.url())
+            .with_interval(1)
+            .with_init_from_store()
+            .with_log_path(self.log_path("persistence", "rust-server"));
+        info!("Server output: {path:?}", path = manager.log_path());
+        let mut servers = vec![ParityServer::Rust(Box::new(manager))];
+
+        if let Some(jar_path) = self.reference_jar.clone() {
+            let port = self.get_available_port().await?;
+            let reference_dir = data_dir.join("reference-server");
+            tokio::fs::create_dir_all(&reference_dir).await?;
+            let manager = ReferenceServerManager::new(jar_path, port, reference_dir)
+                .with_rpc_url(rpc.url())
+                .with_refresh_interval(1)
+                .with_log_path(self.log_path("persistence", "reference-server"));
+            info!(
+                "Reference server output: {path:?}",
+                path = manager.log_path()
+            );
+            servers.push(ParityServer::Kotlin(Box::new(manager)));
+        } else {
+            info!("Reference JAR not provided, testing the Rust server only");
+        }
+
+        let results = run_persistence(&rpc, &mut servers, &config).await;
+        drop(servers);
+        rpc_task.stop().await?;
+
+        let results = results?;
+        results.print_summary();
+        if !results.all_passed() {
+            let artifacts = self.bundle_failure("persistence", |bundle| {
+                bundle.add_results(&results)?;
+                bundle.add_scenario(&rpc)?;
+                bundle.add_log(&self.log_path("persistence", "rust-server"))?;
+                bundle.add_log(&self.log_path("persistence", "reference-server"))?;
+                bundle.add_data_dir("persistence", &data_dir)?;
+                bundle.add_data_dir("recorded", &config.output)
+            });
+            bail!(
+                "Persistence parity tests failed{at}",
+                at = artifacts_note(&artifacts)
+            );
+        }
+        Ok(())
+    }
+
+    /// Change the mock node's mempool through its control endpoints while the Rust server,
+    /// and the reference server when a JAR is configured, poll it
+    pub async fn run_scenario_tests(&mut self, tolerance: ToleranceModel) -> Result<()> {
+        tolerance.validate().map_err(anyhow::Error::msg)?;
+        let server_path = self
+            .server_path
+            .clone()
+            .context("Scenario tests require the server binary")?;
+        let rpc_port = self.get_available_port().await?;
+        let rpc = Arc::new(MockBitcoinRpc::new(rpc_port));
+        let rpc_task = rpc.spawn().await?;
+
+        let data_dir = self.data_dir.join("scenario");
+        let port = self.server_port();
+        let rust_dir = data_dir.join("rust-server");
+        tokio::fs::create_dir_all(&rust_dir).await?;
+        let manager = ServerManager::new(server_path, port, rust_dir)
+            .with_rpc_url(rpc.url())
+            .with_interval(1)
+            .with_log_path(self.log_path("scenario", "rust-server"));
+        info!("Server output: {path:?}", path = manager.log_path());
+        let mut servers = vec![ParityServer::Rust(Box::new(manager))];
+
+        if let Some(jar_path) = self.reference_jar.clone() {
+            let port = self.get_available_port().await?;
+            let reference_dir = data_dir.join("reference-server");
+            tokio::fs::create_dir_all(&reference_dir).await?;
+            let manager = ReferenceServerManager::new(jar_path, port, reference_dir)
+                .with_rpc_url(rpc.url())
+                .with_refresh_interval(1)
+                .with_log_path(self.log_path("scenario", "reference-server"));
+            info!(
+                "Reference server output: {path:?}",
+                path = manager.log_path()
+            );
+            servers.push(ParityServer::Kotlin(Box::new(manager)));
+        } else {
+            info!("Reference JAR not provided, testing the Rust server only");
+        }
 
+        let control = MockRpcControl::new(rpc.url());
+        let results = run_scenario(&control, &mut servers, &tolerance).await;
+        drop(servers);
+        rpc_task.stop().await?;
+
+        let results = results?;
+        results.print_summary();
         if !results.all_passed() {
-            bail!("Compatibility tests failed");
+            let artifacts = self.bundle_failure("scenario", |bundle| {
+                bundle.add_results(&results)?;
+                bundle.add_scenario(&rpc)?;
+                bundle.add_log(&self.log_path("scenario", "rust-server"))?;
+                bundle.add_log(&self.log_path("scenario", "reference-server"))?;
+                bundle.add_data_dir("scenario", &data_dir)
+            });
+            bail!("Scenario tests failed{at}", at = artifacts_note(&artifacts));
         }
+        Ok(())
+    }
+
+    /// Record a data directory with the `released` server binary, switch to the current build
+    /// on it and back, checking that every build serves the estimate of the stored snapshots
+    pub async fn run_upgrade_tests(
+        &mut self,
+        released: PathBuf,
+        config: PersistenceConfig,
+    ) -> Result<()> {
+        let server_path = self
+            .server_path
+            .clone()
+            .context("Upgrade tests require the server binary")?;
+        let rpc_port = self.get_available_port().await?;
+        let rpc = Arc::new(MockBitcoinRpc::new(rpc_port));
+        let rpc_task = rpc.spawn().await?;
+
+        let data_dir = self.data_dir.join("upgrade");
+        let released_dir = data_dir.join("released-server");
+        let rust_dir = data_dir.join("rust-server");
+        tokio::fs::create_dir_all(&released_dir).await?;
+        tokio::fs::create_dir_all(&rust_dir).await?;
 
+        // Releases before `--ready-file` are only seen to be ready on a known port
+        let port = self.get_available_port().await?;
+        let released = ServerManager::new(released, port, released_dir)
+            .with_rpc_url(rpc.url())
+            .with_interval(1)
+            .with_init_from_store()
+            .without_ready_file()
+            .with_log_path(self.log_path("upgrade", "released-server"));
+        info!(
+            "Released server output: {path:?}",
+            path = released.log_path()
+        );
+
+        let current = ServerManager::new(server_path, self.server_port(), rust_dir)
+            .with_rpc_url(rpc.url())
+            .with_interval(1)
+            .with_init_from_store()
+            .with_log_path(self.log_path("upgrade", "rust-server"));
+        info!("Server output: {path:?}", path = current.log_path());
+
+        let results = run_upgrade(&rpc, released, current, &config).await;
+        rpc_task.stop().await?;
+
+        let results = results?;
+        results.print_summary();
+        if !results.all_passed() {
+            let artifacts = self.bundle_failure("upgrade", |bundle| {
+                bundle.add_results(&results)?;
+                bundle.add_scenario(&rpc)?;
+                bundle.add_log(&self.log_path("upgrade", "released-server"))?;
+                bundle.add_log(&self.log_path("upgrade", "rust-server"))?;
+                bundle.add_data_dir("upgrade", &data_dir)?;
+                bundle.add_data_dir("recorded", &config.output)
+            });
+            bail!(
+                "Upgrade compatibility tests failed{at}",
+                at = artifacts_note(&artifacts)
+            );
+        }
         Ok(())
     }
 
@@ -207,8 +773,9 @@ impl TestRunner {
         // Ensure server is running
         if self.server_manager.is_none() {
             if let Some(ref server_path) = self.server_path {
-                let port = self.get_available_port().await?;
-                self.start_server(server_path.clone(), port).await?;
+                let port = self.server_port();
+                self.start_server(server_path.clone(), port, "snapshots")
+                    .await?;
             } else {
                 bail!("No server binary available");
             }
@@ -220,8 +787,13 @@ impl TestRunner {
             .map(|m| m.url())
             .context("Server not running")?;
 
+        // Snapshots of a server still warming up would not show its estimates
+        wait_for_readiness(&server_url).await?;
+
         let tester = SnapshotTester::new(self.update_snapshots || force_update);
-        let results = tester.run_tests(&server_url).await?;
+        let mut results = tester.run_tests(&server_url).await?;
+        results.extend(self.run_error_snapshot_tests(&tester).await?);
+        results.print_summary();
 
         if !results.all_passed() {
             bail!("Snapshot tests failed");
@@ -230,24 +802,122 @@ impl TestRunner {
         Ok(())
     }
 
+    /// Snapshot error responses from servers brought into the states that produce them: one
+    /// estimating from mock data with every token-protected endpoint enabled, one warming up
+    /// without a Bitcoin node and one whose node fails every call
+    async fn run_error_snapshot_tests(
+        &self,
+        tester: &SnapshotTester,
+    ) -> Result<SnapshotTestResults> {
+        let server_path = self
+            .server_path
+            .clone()
+            .context("Error snapshot tests require the server binary")?;
+        let data_dir = self.data_dir.join("snapshot-errors");
+        if data_dir.exists() {
+            tokio::fs::remove_dir_all(&data_dir).await?;
+        }
+
+        let admin_args = ["--ingest-token", TEST_API_TOKEN, "--enable-debug-api"];
+
+        // Each state has its own server, and node where it needs one, so they run side by side
+        let requests = async {
+            let port = self.server_port();
+            let mut manager =
+                ServerManager::new(server_path.clone(), port, data_dir.join("requests"))
+                    .with_test_api_token(TEST_API_TOKEN)
+                    .with_args(admin_args)
+                    .with_log_path(self.log_path("snapshot-errors", "requests"));
+            manager.start().await?;
+            let tested = tester
+                .run_error_tests(&manager.url(), TEST_API_TOKEN, &ErrorCase::request_errors())
+                .await;
+            manager.stop().await?;
+            tested
+        };
+
+        // Without a node to poll, no estimate is computed until snapshots are ingested
+        let warm_up = async {
+            let port = self.server_port();
+            let mut manager =
+                ServerManager::new(server_path.clone(), port, data_dir.join("warm-up"))
+                    .with_args(admin_args)
+                    .with_args(["--ingest-only"])
+                    .with_log_path(self.log_path("snapshot-errors", "warm-up"));
+            manager.start().await?;
+            let tested = tester
+                .run_error_tests(&manager.url(), TEST_API_TOKEN, &ErrorCase::warm_up())
+                .await;
+            manager.stop().await?;
+            tested
+        };
+
+        let upstream_down = async {
+            let rpc_port = self.get_available_port().await?;
+            let rpc = Arc::new(MockBitcoinRpc::new(rpc_port));
+            rpc.set_fault(Fault::ServerError);
+            let rpc_task = rpc.spawn().await?;
+            let port = self.server_port();
+            let mut manager =
+                ServerManager::new(server_path.clone(), port, data_dir.join("upstream-down"))
+                    .with_rpc_url(rpc.url())
+                    .with_interval(1)
+                    .with_log_path(self.log_path("snapshot-errors", "upstream-down"));
+            manager.start().await?;
+            let tested = match wait_for_problem(&manager.url(), "UPSTREAM_RPC_DOWN").await {
+                Ok(()) => {
+                    tester
+                        .run_error_tests(
+                            &manager.url(),
+                            TEST_API_TOKEN,
+                            &ErrorCase::upstream_down(),
+                        )
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            manager.stop().await?;
+            rpc_task.stop().await?;
+            tested
+        };
+
+        let cases = vec![
+            requests.boxed_local(),
+            warm_up.boxed_local(),
+            upstream_down.boxed_local(),
+        ];
+        let mut results = SnapshotTestResults::new();
+        for tested in run_bounded(self.server_jobs(), cases).await {
+            results.extend(tested?);
+        }
+
+        Ok(results)
+    }
+
     /// Run test vector validation
     pub async fn run_vector_tests(&mut self, vectors_file: Option<PathBuf>) -> Result<()> {
-        let vectors = if let Some(path) = vectors_file {
+        let file = if let Some(path) = vectors_file {
             info!("Loading test vectors from {path:?}");
             TestVectorRunner::load_vectors(&path).await?
         } else {
-            info!("Using default test vectors");
-            TestVectorRunner::generate_default_vectors()
+            info!("Using bundled test vectors");
+            TestVectorRunner::bundled_vectors()?
         };
 
-        info!("Running {count} test vectors", count = vectors.len());
+        let tolerance = Arc::new(file.tolerance());
+        info!(
+            "Running {count} test vectors generated by {generator} (tolerance: {tolerance})",
+            count = file.vectors.len(),
+            generator = file.generator
+        );
 
         let semaphore = Arc::new(Semaphore::new(self.jobs));
         let mut tasks = Vec::new();
 
-        for vector in vectors {
+        for vector in file.vectors {
             let sem = semaphore.clone();
             let filter = self.filter.clone();
+            let tolerance = tolerance.clone();
 
             // Apply filter if provided
             if let Some(ref f) = filter {
@@ -258,7 +928,7 @@ impl TestRunner {
 
             tasks.push(tokio::spawn(async move {
                 let _permit = sem.acquire().await.unwrap();
-                TestVectorRunner::run_vector(&vector)
+                TestVectorRunner::run_vector(&vector, &tolerance)
             }));
         }
 
@@ -291,6 +961,146 @@ impl TestRunner {
         Ok(())
     }
 
+    /// Compare this build's exact estimates for a vector file with fingerprints recorded on
+    /// other platforms or by earlier builds
+    ///
+    /// Without fingerprints to compare, the bundled vectors are compared with the bundled
+    /// fingerprint.
+    pub async fn run_reproducibility_tests(
+        &mut self,
+        vectors_file: Option<PathBuf>,
+        output: Option<PathBuf>,
+        compare: Vec<PathBuf>,
+        max_ulps: u64,
+        repeat: usize,
+    ) -> Result<()> {
+        let mut references = compare
+            .iter()
+            .map(|path| Fingerprint::from_file(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let file = if let Some(ref path) = vectors_file {
+            TestVectorRunner::load_vectors(path).await?
+        } else {
+            if references.is_empty() {
+                references.push(Fingerprint::bundled()?);
+            }
+            TestVectorRunner::bundled_vectors()?
+        };
+
+        info!(
+            "Estimating {count} test vectors {repeat} times on {platform}",
+            count = file.vectors.len(),
+            platform = crate::reproducibility::platform()
+        );
+        let fingerprint = Fingerprint::record(&file, self.filter.as_deref(), repeat.max(1))?;
+
+        if let Some(path) = output {
+            fingerprint.save(&path)?;
+            info!("Saved fingerprint to {path:?}");
+        }
+
+        let mut all_passed = true;
+        for reference in &references {
+            if reference.vectors != fingerprint.vectors {
+                warn!(
+                    "Fingerprint from {platform} is for vectors by {vectors}, not {expected}",
+                    platform = reference.platform,
+                    vectors = reference.vectors,
+                    expected = fingerprint.vectors
+                );
+            }
+            let divergences = fingerprint.compare(reference, self.filter.as_deref(), max_ulps);
+            print_divergences(reference, &divergences);
+            all_passed &= divergences.is_empty();
+        }
+
+        if !all_passed {
+            bail!("Estimates differ from a recorded fingerprint");
+        }
+
+        Ok(())
+    }
+
+    /// Trace every estimator stage for the test vectors, or the snapshots in `snapshot_dir`,
+    /// and report where traces recorded by other code paths first diverge
+    pub async fn run_bisect(
+        &mut self,
+        vectors_file: Option<PathBuf>,
+        snapshot_dir: Option<PathBuf>,
+        output: Option<PathBuf>,
+        compare: Vec<PathBuf>,
+        max_ulps: u64,
+    ) -> Result<()> {
+        ensure!(
+            compare.len() <= 2,
+            "At most two stage traces can be compared, got {count}",
+            count = compare.len()
+        );
+        let mut references = compare
+            .iter()
+            .map(|path| StageTraceFile::from_file(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Two recorded traces are compared with each other without replaying anything
+        let current = if references.len() == 2 {
+            references.remove(1)
+        } else {
+            let file = match (vectors_file, snapshot_dir) {
+                (Some(path), _) => TestVectorRunner::load_vectors(&path).await?,
+                (None, Some(dir)) => VectorFile {
+                    schema_version: test_vectors::SCHEMA_VERSION,
+                    generator: dir.display().to_string(),
+                    tolerance: 0.0,
+                    tolerance_model: None,
+                    vectors: vec![TestVector {
+                        name: dir.file_name().map_or_else(
+                            || "snapshots".to_string(),
+                            |name| name.to_string_lossy().into_owned(),
+                        ),
+                        description: String::new(),
+                        snapshots: read_snapshot_dir(&dir)?,
+                        num_blocks: None,
+                        expected: Default::default(),
+                    }],
+                },
+                (None, None) => TestVectorRunner::bundled_vectors()?,
+            };
+            info!(
+                "Tracing {count} inputs on {platform}",
+                count = file.vectors.len(),
+                platform = crate::reproducibility::platform()
+            );
+            StageTraceFile::record(&file, self.filter.as_deref())?
+        };
+
+        if let Some(path) = output {
+            current.save(&path)?;
+            info!("Saved stage trace to {path:?}");
+        }
+
+        let mut all_passed = true;
+        for reference in &references {
+            if reference.vectors != current.vectors {
+                warn!(
+                    "Stage trace by {generator} is for {vectors}, not {expected}",
+                    generator = reference.generator,
+                    vectors = reference.vectors,
+                    expected = current.vectors
+                );
+            }
+            let bisection = current.bisect(reference, max_ulps);
+            print_bisection(reference, &bisection);
+            all_passed &= bisection.passed();
+        }
+
+        if !all_passed {
+            bail!("Estimator stages diverge from a recorded trace");
+        }
+
+        Ok(())
+    }
+
     /// Generate test data
     pub async fn generate_test_data(&mut self, output: PathBuf, count: usize) -> Result<()> {
         info!("Generating {count} test cases");
@@ -306,14 +1116,14 @@ impl TestRunner {
         info!("Saved test cases to {test_cases_path:?}");
 
         // Generate test vectors
-        let vectors = TestVectorRunner::generate_default_vectors();
+        let vectors = TestVectorRunner::generate_vectors()?;
         let vectors_path = output.join("test_vectors.json");
         TestVectorRunner::save_vectors(&vectors, &vectors_path).await?;
         info!("Saved test vectors to {vectors_path:?}");
 
         let message = format!(
             "Generated {count} test cases and {vec_count} test vectors",
-            vec_count = vectors.len()
+            vec_count = vectors.vectors.len()
         )
         .green()
         .bold();
@@ -322,70 +1132,187 @@ impl TestRunner {
         Ok(())
     }
 
-    /// Compare two endpoints
+    /// Compare the responses of two endpoints for each of `paths`, writing an HTML report to
+    /// `html` and adding the run to the `history` file next to it
     pub async fn compare_endpoints(
         &mut self,
         endpoint1: &str,
         endpoint2: &str,
-        path: &str,
+        paths: &[String],
+        tolerance: ToleranceModel,
+        html: Option<PathBuf>,
+        history: Option<PathBuf>,
     ) -> Result<()> {
         info!("Comparing endpoints: {endpoint1} vs {endpoint2}");
+        tolerance.validate().map_err(anyhow::Error::msg)?;
 
         let client1 = ApiClient::new(endpoint1.to_string());
         let client2 = ApiClient::new(endpoint2.to_string());
 
-        let (status1, body1) = client1.get_raw(path).await?;
-        let (status2, body2) = client2.get_raw(path).await?;
+        let mut comparisons = Vec::new();
+        for path in paths {
+            let (status1, body1) = client1.get_raw(path).await?;
+            let (status2, body2) = client2.get_raw(path).await?;
 
-        if status1 != status2 {
-            println!(
-                "{symbol} Status codes differ: {status1} vs {status2}",
-                symbol = "✗".red()
-            );
-            bail!("Status codes differ");
-        }
+            let differences = if status1 != status2 {
+                println!(
+                    "{symbol} {path}: status codes differ: {status1} vs {status2}",
+                    symbol = "✗".red()
+                );
+                vec![format!("Status code mismatch: {status1} vs {status2}")]
+            } else {
+                let differences =
+                    crate::api_client::ResponseComparator::compare_json(&body1, &body2, "");
+                if differences.is_empty() {
+                    let check = "✓".green();
+                    println!("{check} {path}: responses are identical");
+                } else {
+                    let warning = "⚠".yellow();
+                    println!(
+                        "{warning} {path}: found {count} differences:",
+                        count = differences.len()
+                    );
+                    for diff in &differences {
+                        println!("  - {diff}");
+                    }
+                }
+                differences
+            };
 
-        let differences = crate::api_client::ResponseComparator::compare_json(&body1, &body2, "");
+            comparisons.push(PathComparison {
+                path: path.clone(),
+                left_status: status1.as_u16(),
+                right_status: status2.as_u16(),
+                left: body1,
+                right: body2,
+                differences,
+            });
+        }
 
-        if differences.is_empty() {
-            let check = "✓".green();
-            println!("{check} Responses are identical");
-        } else {
-            let warning = "⚠".yellow();
-            println!(
-                "{warning} Found {count} differences:",
-                count = differences.len()
+        if let Some(html) = html {
+            let report = ComparisonReport {
+                time: chrono::Utc::now(),
+                left: endpoint1.to_string(),
+                right: endpoint2.to_string(),
+                tolerance,
+                paths: comparisons.clone(),
+            };
+            let history = history.unwrap_or_else(|| html.with_extension("history.jsonl"));
+            append_history(&history, &report.summary())?;
+            let runs = load_history(&history)?;
+            tokio::fs::write(&html, report.to_html(&runs))
+                .await
+                .with_context(|| format!("Failed to write report {html:?}"))?;
+            info!(
+                "Wrote the report to {html:?}, with {count} runs from {history:?}",
+                count = runs.len()
             );
-            for diff in &differences {
-                println!("  - {diff}");
-            }
+        }
+
+        if comparisons
+            .iter()
+            .any(|comparison| comparison.left_status != comparison.right_status)
+        {
+            bail!("Status codes differ");
         }
 
         Ok(())
     }
 
-    /// Start the server
-    async fn start_server(&mut self, binary_path: PathBuf, port: u16) -> Result<()> {
+    /// Start the server, logging its output to a file named after `test`
+    async fn start_server(&mut self, binary_path: PathBuf, port: u16, test: &str) -> Result<()> {
         let data_dir = self.data_dir.join("rust-server");
         tokio::fs::create_dir_all(&data_dir).await?;
 
-        let mut manager = ServerManager::new(binary_path, port, data_dir);
+        let mut manager = ServerManager::new(binary_path, port, data_dir)
+            .with_test_api_token(TEST_API_TOKEN)
+            .with_log_path(self.log_path(test, "rust-server"));
+        info!("Server output: {path:?}", path = manager.log_path());
         manager.start().await?;
         self.server_manager = Some(manager);
         Ok(())
     }
 
-    /// Start the reference server
-    async fn start_reference_server(&mut self, jar_path: PathBuf, port: u16) -> Result<()> {
+    /// Start the reference server, polling `rpc` every second when given, logging its output
+    /// to a file named after `test`
+    async fn start_reference_server(
+        &mut self,
+        jar_path: PathBuf,
+        port: u16,
+        rpc: Option<&MockBitcoinRpc>,
+        test: &str,
+    ) -> Result<()> {
         let data_dir = self.data_dir.join("reference-server");
         tokio::fs::create_dir_all(&data_dir).await?;
 
-        let mut manager = ReferenceServerManager::new(jar_path, port, data_dir);
+        let mut manager = ReferenceServerManager::new(jar_path, port, data_dir)
+            .with_log_path(self.log_path(test, "reference-server"));
+        if let Some(rpc) = rpc {
+            // Snapshots from earlier runs would otherwise enter the estimates
+            let snapshot_dir = manager.snapshot_dir();
+            if snapshot_dir.exists() {
+                tokio::fs::remove_dir_all(&snapshot_dir)
+                    .await
+                    .with_context(|| format!("Failed to clear {snapshot_dir:?}"))?;
+            }
+            manager = manager.with_rpc_url(rpc.url()).with_refresh_interval(1);
+        }
+        info!(
+            "Reference server output: {path:?}",
+            path = manager.log_path()
+        );
         manager.start().await?;
         self.reference_manager = Some(manager);
         Ok(())
     }
 
+    /// Collects the artifacts of a failed `suite` into a new bundle, returning where it is
+    ///
+    /// Failing to collect them is only logged, so it never hides the failure itself.
+    fn bundle_failure(
+        &self,
+        suite: &str,
+        collect: impl FnOnce(&FailureBundle) -> Result<()>,
+    ) -> Option<PathBuf> {
+        let bundled = FailureBundle::create(&self.data_dir, suite).and_then(|bundle| {
+            collect(&bundle)?;
+            Ok(bundle)
+        });
+        match bundled {
+            Ok(bundle) => {
+                info!(
+                    "Artifacts of the failed {suite} run: {path:?}",
+                    path = bundle.path()
+                );
+                Some(bundle.path().to_path_buf())
+            }
+            Err(e) => {
+                warn!("Failed to collect the artifacts of the {suite} run: {e:#}");
+                None
+            }
+        }
+    }
+
+    /// Adds the logs and data directories of the running servers to `bundle`
+    fn add_servers(&self, bundle: &FailureBundle) -> Result<()> {
+        if let Some(ref manager) = self.server_manager {
+            bundle.add_log(manager.log_path())?;
+            bundle.add_data_dir("rust-server", manager.data_dir())?;
+        }
+        if let Some(ref manager) = self.reference_manager {
+            bundle.add_log(manager.log_path())?;
+            bundle.add_data_dir("reference-server", manager.data_dir())?;
+        }
+        Ok(())
+    }
+
+    /// Log file for `server` started by `test`
+    fn log_path(&self, test: &str, server: &str) -> PathBuf {
+        self.data_dir
+            .join("logs")
+            .join(format!("{test}-{server}.log"))
+    }
+
     /// Stop all servers
     async fn stop_servers(&mut self) -> Result<()> {
         if let Some(mut manager) = self.server_manager.take() {
@@ -408,3 +1335,33 @@ impl Drop for TestRunner {
         let _ = self.reference_manager.take();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_run_bounded_keeps_order_and_limit() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let cases = (0..6u64)
+            .map(|case| {
+                let (running, peak) = (&running, &peak);
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    // Later cases finish first
+                    tokio::time::sleep(Duration::from_millis(60 - case * 10)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    case
+                }
+                .boxed_local()
+            })
+            .collect();
+
+        assert_eq!(run_bounded(3, cases).await, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,333 @@
+//! Servers reacting to a mempool that changes while they run
+//!
+//! Every server starts on an empty data directory and polls the same mock node. The test then
+//! changes the node through its control endpoints, as an external driver would: it fills the
+//! mempool, adds a fee spike, mines a block and replaces the backlog with cheaper transactions.
+//! After each step every server must serve an estimate collected after the change, matching the
+//! library's estimate for the snapshots it recorded. Fee rates that move by more than the
+//! tolerance must move the same way on every server.
+
+use anyhow::{ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info};
+
+use crate::api_client::FeeEstimateResponse;
+use crate::comparison::ToleranceModel;
+use crate::compatibility::TestResults;
+use crate::differential::served_estimates;
+use crate::mock_rpc::{ControlTransaction, MockRpcControl};
+use crate::persistence::{read_snapshot_dir, ParityServer};
+use crate::test_vectors::{compare, estimate};
+
+/// How long a server may take to serve an estimate collected after a change
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often `/fees` is requested while waiting for a fresh estimate
+const UPDATE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A change made to the mock node through its control endpoints
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    /// Replace the mempool with a spread of fee rates
+    Fill,
+    /// Add a burst of high fee rate transactions
+    FeeSpike,
+    /// Mine a block, removing the highest fee rate transactions
+    MineBlock,
+    /// Replace the mempool with a smaller, cheaper backlog
+    Drain,
+}
+
+impl Step {
+    const ALL: [Step; 4] = [Step::Fill, Step::FeeSpike, Step::MineBlock, Step::Drain];
+
+    fn name(self) -> &'static str {
+        match self {
+            Step::Fill => "fill",
+            Step::FeeSpike => "fee spike",
+            Step::MineBlock => "mine block",
+            Step::Drain => "drain",
+        }
+    }
+
+    async fn apply(self, control: &MockRpcControl) -> Result<()> {
+        match self {
+            Step::Fill => {
+                control.set_mempool(spread(2.0, 40.0, 400), false).await?;
+            }
+            Step::FeeSpike => {
+                control.set_mempool(spread(80.0, 150.0, 150), true).await?;
+            }
+            Step::MineBlock => {
+                control.mine_blocks(1).await?;
+            }
+            Step::Drain => {
+                control.set_mempool(spread(1.0, 8.0, 100), false).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `count` transactions of 4,000 WU with fee rates spread evenly from `min` to `max` sat/vB
+fn spread(min: f64, max: f64, count: usize) -> Vec<ControlTransaction> {
+    (0..count)
+        .map(|i| ControlTransaction {
+            fee_rate: min + (max - min) * i as f64 / count as f64,
+            weight: 4_000,
+            count: 1,
+        })
+        .collect()
+}
+
+/// Fee rates served for each block target and confidence level
+type ServedRates = BTreeMap<(String, String), f64>;
+
+fn served_rates(response: &FeeEstimateResponse) -> ServedRates {
+    response
+        .estimates
+        .iter()
+        .flat_map(|(blocks, target)| {
+            target.probabilities.iter().map(move |(probability, rate)| {
+                ((blocks.clone(), probability.clone()), rate.fee_rate)
+            })
+        })
+        .collect()
+}
+
+/// Direction of each fee rate's change between two estimates, ignoring changes within the
+/// tolerance
+fn directions(
+    before: &ServedRates,
+    after: &ServedRates,
+    tolerance: &ToleranceModel,
+) -> BTreeMap<(String, String), std::cmp::Ordering> {
+    after
+        .iter()
+        .filter_map(|(key, &rate)| {
+            let previous = *before.get(key)?;
+            let direction = if tolerance.accepts(previous, rate) {
+                std::cmp::Ordering::Equal
+            } else {
+                rate.total_cmp(&previous)
+            };
+            Some((key.clone(), direction))
+        })
+        .collect()
+}
+
+/// Polls `/fees` on `server` until it serves an estimate collected after `changed_at`
+async fn wait_for_update(
+    server: &ParityServer,
+    changed_at: DateTime<Utc>,
+) -> Result<FeeEstimateResponse> {
+    let client = server.client();
+    let deadline = Instant::now() + UPDATE_TIMEOUT;
+    loop {
+        match client.get_fees().await {
+            Ok(response) if response.has_estimates() => {
+                let update_time = update_time(&response)?;
+                if update_time > changed_at {
+                    return Ok(response);
+                }
+                debug!("{server} still serves the estimate from {update_time}");
+            }
+            Ok(_) => debug!("{server} serves no estimate yet"),
+            Err(e) => debug!("Failed to request estimates from {server}: {e}"),
+        }
+        ensure!(
+            Instant::now() < deadline,
+            "{server} served no estimate collected after {changed_at} within {UPDATE_TIMEOUT:?}"
+        );
+        sleep(UPDATE_POLL_INTERVAL).await;
+    }
+}
+
+fn update_time(response: &FeeEstimateResponse) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(&response.mempool_update_time)
+        .with_context(|| {
+            format!(
+                "Invalid mempool_update_time {time}",
+                time = response.mempool_update_time
+            )
+        })?
+        .with_timezone(&Utc))
+}
+
+/// Compares `served` with the library's estimate for the snapshots `server` recorded up to it
+fn check_library(
+    server: &ParityServer,
+    served: &FeeEstimateResponse,
+    tolerance: &ToleranceModel,
+    results: &mut TestResults,
+    test: &str,
+) -> Result<()> {
+    // The served time is truncated to milliseconds
+    let update_time = update_time(served)?;
+    let mut snapshots = read_snapshot_dir(&server.snapshot_dir())?;
+    snapshots.retain(|s| s.timestamp < update_time + chrono::Duration::milliseconds(1));
+    ensure!(
+        !snapshots.is_empty(),
+        "Served an estimate from {update_time}, before every stored snapshot"
+    );
+
+    let expected = estimate(&snapshots, None)?;
+    let mismatches: Vec<_> = compare(&expected, &served_estimates(served), tolerance)
+        .into_iter()
+        .filter(|v| !v.passed)
+        .collect();
+    if !mismatches.is_empty() {
+        results.record_responses(
+            test,
+            [
+                (server.label(), serde_json::to_value(served)?),
+                ("library", serde_json::to_value(&expected)?),
+            ],
+        );
+    }
+    ensure!(
+        mismatches.is_empty(),
+        "{count} fee rates differ from the library, first: {first}",
+        count = mismatches.len(),
+        first = mismatches[0].message
+    );
+    Ok(())
+}
+
+/// Starts every server against the node behind `control`, changes the node step by step and
+/// checks how each server reacts
+pub async fn run_scenario(
+    control: &MockRpcControl,
+    servers: &mut [ParityServer],
+    tolerance: &ToleranceModel,
+) -> Result<TestResults> {
+    let mut results = TestResults::new();
+    for server in servers.iter_mut() {
+        server.reset_data(None)?;
+        server.start().await?;
+    }
+
+    let outcome = run_steps(control, servers, tolerance, &mut results).await;
+    for server in servers.iter_mut() {
+        server.stop().await?;
+    }
+    outcome?;
+    Ok(results)
+}
+
+async fn run_steps(
+    control: &MockRpcControl,
+    servers: &[ParityServer],
+    tolerance: &ToleranceModel,
+    results: &mut TestResults,
+) -> Result<()> {
+    let mut previous: Vec<Option<ServedRates>> = vec![None; servers.len()];
+
+    for step in Step::ALL {
+        info!("Scenario step: {name}", name = step.name());
+        step.apply(control).await?;
+        let changed_at = Utc::now();
+
+        let mut moves = Vec::new();
+        for (server, previous) in servers.iter().zip(previous.iter_mut()) {
+            let test = format!("{server} after {name}", name = step.name());
+            let served = match wait_for_update(server, changed_at).await {
+                Ok(served) => served,
+                Err(e) => {
+                    results.add_fail(&test, &format!("{e:#}"));
+                    *previous = None;
+                    continue;
+                }
+            };
+            match check_library(server, &served, tolerance, results, &test) {
+                Ok(()) => results.add_pass(&test, "Estimate matches the library"),
+                Err(e) => results.add_fail(&test, &format!("{e:#}")),
+            }
+
+            let rates = served_rates(&served);
+            if let Some(before) = previous.as_ref() {
+                moves.push((server, directions(before, &rates, tolerance)));
+            }
+            *previous = Some(rates);
+        }
+
+        // Every server must move its fee rates the same way as the first one
+        let Some(((first, expected), others)) = moves.split_first() else {
+            continue;
+        };
+        for (server, actual) in others {
+            let test = format!("{server} reacts like {first} to {name}", name = step.name());
+            let differing: Vec<String> = expected
+                .iter()
+                .filter(|&(key, direction)| actual.get(key).is_some_and(|d| d != direction))
+                .map(|((blocks, probability), direction)| {
+                    format!("{blocks} blocks @ {probability}: {first} {direction:?}")
+                })
+                .collect();
+            if differing.is_empty() {
+                results.add_pass(&test, "Fee rates moved the same way");
+            } else {
+                for difference in &differing {
+                    debug!("  - {difference}");
+                }
+                results.add_fail(
+                    &test,
+                    &format!(
+                        "{count} fee rates moved differently, first: {first_difference}",
+                        count = differing.len(),
+                        first_difference = differing[0]
+                    ),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    fn rates(values: &[(&str, &str, f64)]) -> ServedRates {
+        values
+            .iter()
+            .map(|&(blocks, probability, rate)| {
+                ((blocks.to_string(), probability.to_string()), rate)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_directions_ignore_changes_within_tolerance() {
+        let tolerance = ToleranceModel::default();
+        let before = rates(&[("3", "0.50", 10.0), ("6", "0.50", 5.0), ("9", "0.50", 2.0)]);
+        let after = rates(&[
+            ("3", "0.50", 12.0),
+            ("6", "0.50", 5.00001),
+            ("12", "0.50", 1.0),
+        ]);
+
+        let moved = directions(&before, &after, &tolerance);
+        assert_eq!(moved.len(), 2);
+        assert_eq!(
+            moved[&("3".to_string(), "0.50".to_string())],
+            Ordering::Greater
+        );
+        assert_eq!(
+            moved[&("6".to_string(), "0.50".to_string())],
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_spread_covers_the_range() {
+        let transactions = spread(2.0, 40.0, 400);
+        assert_eq!(transactions.len(), 400);
+        assert_eq!(transactions[0].fee_rate, 2.0);
+        assert!(transactions.iter().all(|tx| tx.fee_rate < 40.0));
+    }
+}
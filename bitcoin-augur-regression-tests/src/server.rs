@@ -1,30 +1,118 @@
 use anyhow::{bail, ensure, Context, Result};
-use std::path::PathBuf;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::Duration;
 use tokio::process::{Child, Command};
 use tokio::time::{sleep, timeout};
 use tracing::{debug, info};
 
+/// Log lines included when a server exits during startup
+const LOG_TAIL_LINES: usize = 20;
+
 /// Manages a bitcoin-augur-server process for testing
 pub struct ServerManager {
     process: Option<Child>,
     port: u16,
     binary_path: PathBuf,
     data_dir: PathBuf,
+    log_path: PathBuf,
+    test_api_token: Option<String>,
+    rpc_url: Option<String>,
+    interval_secs: Option<u64>,
+    init_from_store: bool,
+    ready_file: bool,
+    extra_args: Vec<String>,
 }
 
 impl ServerManager {
-    /// Create a new server manager
+    /// Create a new server manager; port 0 lets the server pick a free port once started
     pub fn new(binary_path: PathBuf, port: u16, data_dir: PathBuf) -> Self {
         Self {
             process: None,
             port,
             binary_path,
+            log_path: data_dir.join("server.log"),
             data_dir,
+            test_api_token: None,
+            rpc_url: None,
+            interval_secs: None,
+            init_from_store: false,
+            ready_file: true,
+            extra_args: Vec::new(),
         }
     }
 
+    /// Write the server's stdout and stderr to `path` instead of `server.log` in the data directory
+    pub fn with_log_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log_path = path.into();
+        self
+    }
+
+    /// File receiving the server's output
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Enable the server's test-only injection API, authenticated with `token`
+    pub fn with_test_api_token(mut self, token: impl Into<String>) -> Self {
+        self.test_api_token = Some(token.into());
+        self
+    }
+
+    /// Poll the Bitcoin RPC at `url` (such as a mock node) instead of the built-in mock data
+    #[allow(dead_code)]
+    pub fn with_rpc_url(mut self, url: impl Into<String>) -> Self {
+        self.rpc_url = Some(url.into());
+        self
+    }
+
+    /// Set how often the server polls the mempool
+    #[allow(dead_code)]
+    pub fn with_interval(mut self, secs: u64) -> Self {
+        self.interval_secs = Some(secs);
+        self
+    }
+
+    /// Compute the first estimate from the snapshots already in the data directory
+    pub fn with_init_from_store(mut self) -> Self {
+        self.init_from_store = true;
+        self
+    }
+
+    /// Wait for `/health` to answer instead of passing `--ready-file`, for releases that
+    /// predate it; the server then needs a fixed port
+    pub fn without_ready_file(mut self) -> Self {
+        self.ready_file = false;
+        self
+    }
+
+    /// Pass `args` to the server after the options set by the manager
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extra_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Directory the server was started on
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Server binary the manager starts
+    pub fn binary_path(&self) -> &Path {
+        &self.binary_path
+    }
+
+    /// Directory where the server persists its snapshots
+    pub fn snapshot_dir(&self) -> PathBuf {
+        self.data_dir.join("mempool")
+    }
+
     /// Start the server process
     pub async fn start(&mut self) -> Result<()> {
         ensure!(self.process.is_none(), "Server is already running");
@@ -41,14 +129,59 @@ impl ServerManager {
             .arg("--host")
             .arg("127.0.0.1")
             .arg("--data-dir")
-            .arg(self.data_dir.join("mempool"))
-            .arg("--test-mode")
-            .arg("--use-mock-data")
+            .arg(self.snapshot_dir())
             .arg("--log-filter")
-            .arg("bitcoin_augur_server=info,bitcoin_augur=info")
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .kill_on_drop(true);
+            .arg("bitcoin_augur_server=info,bitcoin_augur=info");
+
+        match self.rpc_url {
+            Some(ref url) => {
+                cmd.arg("--rpc-url")
+                    .arg(url)
+                    .arg("--rpc-username")
+                    .arg("test")
+                    .arg("--rpc-password")
+                    .arg("test");
+            }
+            None => {
+                cmd.arg("--test-mode").arg("--use-mock-data");
+            }
+        }
+
+        if let Some(secs) = self.interval_secs {
+            cmd.arg("--interval-secs").arg(secs.to_string());
+        }
+
+        if self.init_from_store {
+            cmd.arg("--init-from-store");
+        }
+
+        cmd.args(&self.extra_args);
+
+        if let Some(ref token) = self.test_api_token {
+            cmd.arg("--enable-test-api")
+                .arg("--test-api-token")
+                .arg(token);
+        }
+
+        // The server writes the address it bound to the ready file once it accepts requests
+        let ready_file = self.data_dir.join("server.ready");
+        if self.ready_file {
+            match std::fs::remove_file(&ready_file) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(e).with_context(|| format!("Failed to remove {ready_file:?}"));
+                }
+                _ => {}
+            }
+            cmd.arg("--ready-file").arg(&ready_file);
+        } else {
+            ensure!(
+                self.port != 0,
+                "A server started without a ready file needs a fixed port"
+            );
+        }
+
+        let (stdout, stderr) = log_stdio(&self.log_path, "bitcoin-augur-server")?;
+        cmd.stdout(stdout).stderr(stderr).kill_on_drop(true);
 
         let child = cmd.spawn().with_context(|| {
             format!(
@@ -59,8 +192,12 @@ impl ServerManager {
 
         self.process = Some(child);
 
-        // Wait for server to be ready
-        self.wait_for_ready().await?;
+        // Restarts keep the port a server started on port 0 picked
+        if self.ready_file {
+            self.port = self.wait_for_ready(&ready_file).await?;
+        } else {
+            self.wait_for_health().await?;
+        }
 
         Ok(())
     }
@@ -79,6 +216,13 @@ impl ServerManager {
         Ok(())
     }
 
+    /// Restart the server on the same port and data directory
+    #[allow(dead_code)]
+    pub async fn restart(&mut self) -> Result<()> {
+        self.stop().await?;
+        self.start().await
+    }
+
     /// Check if server is running
     #[allow(dead_code)]
     pub async fn is_running(&self) -> bool {
@@ -87,30 +231,52 @@ impl ServerManager {
         reqwest::get(&url).await.is_ok()
     }
 
-    /// Wait for server to be ready
-    async fn wait_for_ready(&self) -> Result<()> {
-        let url = format!("http://127.0.0.1:{port}/health", port = self.port);
+    /// Wait for the server to write the address it bound to `ready_file`, returning its port
+    /// and failing early if the process exits
+    async fn wait_for_ready(&mut self, ready_file: &Path) -> Result<u16> {
         let max_wait = Duration::from_secs(30);
-        let check_interval = Duration::from_millis(500);
-
-        info!("Waiting for server to be ready at {url}");
+        info!("Waiting for server to write {ready_file:?}");
 
         let start = std::time::Instant::now();
         loop {
+            ensure_running(&mut self.process, "Server", &self.log_path)?;
+            if let Ok(contents) = tokio::fs::read_to_string(ready_file).await {
+                let address: SocketAddr = contents
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .with_context(|| format!("Malformed ready file {ready_file:?}"))?;
+                info!("Server is ready at {address}");
+                return Ok(address.port());
+            }
             if start.elapsed() > max_wait {
                 bail!("Server failed to start within {max_wait:?}");
             }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
 
+    /// Wait for `/health` to answer, failing early if the process exits
+    async fn wait_for_health(&mut self) -> Result<()> {
+        let url = format!("http://127.0.0.1:{port}/health", port = self.port);
+        let max_wait = Duration::from_secs(30);
+        info!("Waiting for server to be ready at {url}");
+
+        let start = std::time::Instant::now();
+        loop {
+            ensure_running(&mut self.process, "Server", &self.log_path)?;
             match timeout(Duration::from_secs(1), reqwest::get(&url)).await {
                 Ok(Ok(response)) if response.status().is_success() => {
                     info!("Server is ready");
                     return Ok(());
                 }
-                _ => {
-                    debug!("Server not ready yet, retrying...");
-                    sleep(check_interval).await;
-                }
+                _ => debug!("Server not ready yet, retrying..."),
+            }
+            if start.elapsed() > max_wait {
+                bail!("Server failed to start within {max_wait:?}");
             }
+            sleep(Duration::from_millis(500)).await;
         }
     }
 
@@ -135,6 +301,9 @@ pub struct ReferenceServerManager {
     port: u16,
     jar_path: PathBuf,
     data_dir: PathBuf,
+    log_path: PathBuf,
+    rpc_url: String,
+    refresh_interval_secs: u64,
 }
 
 impl ReferenceServerManager {
@@ -144,10 +313,47 @@ impl ReferenceServerManager {
             process: None,
             port,
             jar_path,
+            log_path: data_dir.join("reference-server.log"),
             data_dir,
+            rpc_url: "http://localhost:38332".to_string(),
+            refresh_interval_secs: 5,
         }
     }
 
+    /// Poll the Bitcoin RPC at `url` (such as a mock node) instead of the default
+    pub fn with_rpc_url(mut self, url: impl Into<String>) -> Self {
+        self.rpc_url = url.into();
+        self
+    }
+
+    /// Set how often the reference server polls the mempool
+    pub fn with_refresh_interval(mut self, secs: u64) -> Self {
+        self.refresh_interval_secs = secs;
+        self
+    }
+
+    /// Write the server's stdout and stderr to `path` instead of `reference-server.log` in the
+    /// data directory
+    pub fn with_log_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log_path = path.into();
+        self
+    }
+
+    /// File receiving the server's output
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Directory the reference server was started on
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Directory where the reference server persists its snapshots
+    pub fn snapshot_dir(&self) -> PathBuf {
+        self.data_dir.join("mempool-ref")
+    }
+
     /// Start the reference server
     pub async fn start(&mut self) -> Result<()> {
         ensure!(
@@ -167,10 +373,11 @@ impl ReferenceServerManager {
             .arg(&self.jar_path)
             .env("APP_CONFIG", &config_path)
             .env("SERVER_PORT", self.port.to_string())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
             .kill_on_drop(true);
 
+        let (stdout, stderr) = log_stdio(&self.log_path, "reference server")?;
+        cmd.stdout(stdout).stderr(stderr);
+
         let child = cmd.spawn().with_context(|| {
             format!(
                 "Failed to start reference server from {path:?}",
@@ -196,8 +403,15 @@ impl ReferenceServerManager {
         Ok(())
     }
 
-    /// Wait for server to be ready
-    async fn wait_for_ready(&self) -> Result<()> {
+    /// Restart the reference server on the same port and data directory
+    #[allow(dead_code)]
+    pub async fn restart(&mut self) -> Result<()> {
+        self.stop().await?;
+        self.start().await
+    }
+
+    /// Wait for server to be ready, failing early if the process exits
+    async fn wait_for_ready(&mut self) -> Result<()> {
         let url = format!("http://127.0.0.1:{port}/fees", port = self.port);
         let max_wait = Duration::from_secs(60); // Java server may take longer
         let check_interval = Duration::from_secs(1);
@@ -206,6 +420,7 @@ impl ReferenceServerManager {
 
         let start = std::time::Instant::now();
         loop {
+            ensure_running(&mut self.process, "Reference server", &self.log_path)?;
             if start.elapsed() > max_wait {
                 bail!("Reference server failed to start within {max_wait:?}");
             }
@@ -236,12 +451,12 @@ server:
   host: "127.0.0.1"
 
 mempool:
-  refreshIntervalSecs: 5
+  refreshIntervalSecs: {refresh_interval_secs}
   dataPath: "{data_path}"
   maxSnapshots: 100
 
 bitcoin:
-  rpcUrl: "http://localhost:38332"
+  rpcUrl: "{rpc_url}"
   rpcUser: "test"
   rpcPassword: "test"
 
@@ -249,7 +464,9 @@ logging:
   level: "INFO"
 "#,
             port = self.port,
-            data_path = self.data_dir.join("mempool-ref").display()
+            refresh_interval_secs = self.refresh_interval_secs,
+            data_path = self.snapshot_dir().display(),
+            rpc_url = self.rpc_url
         );
 
         tokio::fs::create_dir_all(path.parent().unwrap()).await?;
@@ -270,3 +487,113 @@ impl Drop for ReferenceServerManager {
         }
     }
 }
+
+/// Opens `path` for appending, marks the start of a run, and returns it as stdout and stderr
+fn log_stdio(path: &Path, name: &str) -> Result<(Stdio, Stdio)> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create log directory {parent:?}"))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {path:?}"))?;
+
+    // Restarts append to the same file, so separate each run
+    writeln!(
+        file,
+        "==> Starting {name} at {time}",
+        time = chrono::Utc::now().to_rfc3339()
+    )?;
+
+    let stderr = file
+        .try_clone()
+        .context("Failed to duplicate log file handle")?;
+    Ok((Stdio::from(file), Stdio::from(stderr)))
+}
+
+/// Fails with the end of the log if the process has exited
+fn ensure_running(process: &mut Option<Child>, name: &str, log_path: &Path) -> Result<()> {
+    let Some(child) = process.as_mut() else {
+        bail!("{name} is not running");
+    };
+
+    if let Some(status) = child.try_wait()? {
+        process.take();
+        bail!(
+            "{name} exited with {status} before becoming ready. Last lines of {log_path:?}:\n{tail}",
+            tail = log_tail(log_path, LOG_TAIL_LINES)
+        );
+    }
+    Ok(())
+}
+
+/// Last `lines` lines of a log file, or an empty string if it cannot be read
+fn log_tail(path: &Path, lines: usize) -> String {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let all: Vec<&str> = content.lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exited_server_fails_fast_with_log() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // `echo` prints the server arguments to the log and exits immediately
+        let mut manager = ServerManager::new(PathBuf::from("echo"), 1, temp_dir.path().into());
+
+        let started = std::time::Instant::now();
+        let error = manager.start().await.unwrap_err().to_string();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(error.contains("exited"), "{error}");
+        assert!(error.contains("--test-mode"), "{error}");
+        assert!(manager.process.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_server_without_ready_file_gets_no_ready_file_argument() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut manager = ServerManager::new(PathBuf::from("echo"), 0, temp_dir.path().into())
+            .without_ready_file();
+        let error = manager.start().await.unwrap_err().to_string();
+        assert!(error.contains("fixed port"), "{error}");
+
+        let mut manager = ServerManager::new(PathBuf::from("echo"), 1, temp_dir.path().into())
+            .without_ready_file();
+        let error = manager.start().await.unwrap_err().to_string();
+        assert!(error.contains("exited"), "{error}");
+        assert!(error.contains("--data-dir"), "{error}");
+        assert!(!error.contains("--ready-file"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_restarts_append_to_the_same_log() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("logs/custom.log");
+        let mut manager = ServerManager::new(PathBuf::from("echo"), 1, temp_dir.path().into())
+            .with_log_path(&log_path);
+
+        assert!(manager.start().await.is_err());
+        assert!(manager.restart().await.is_err());
+
+        let log = std::fs::read_to_string(manager.log_path()).unwrap();
+        assert_eq!(log.matches("==> Starting bitcoin-augur-server").count(), 2);
+        assert_eq!(log.matches("--data-dir").count(), 2);
+    }
+
+    #[test]
+    fn test_log_tail_keeps_last_lines() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("server.log");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        assert_eq!(log_tail(&path, 2), "two\nthree");
+        assert_eq!(log_tail(&path, 10), "one\ntwo\nthree");
+        assert_eq!(log_tail(&temp_dir.path().join("missing.log"), 2), "");
+    }
+}
@@ -2,6 +2,7 @@
 
 use anyhow::{Context, Result};
 use insta::{assert_json_snapshot, Settings};
+use reqwest::Method;
 use serde_json::Value;
 use std::path::Path;
 use tracing::{debug, info};
@@ -21,15 +22,9 @@ impl SnapshotTester {
     pub async fn run_tests(&self, api_url: &str) -> Result<SnapshotTestResults> {
         let mut results = SnapshotTestResults::new();
 
-        // Configure insta settings
-        let mut settings = Settings::clone_current();
-        settings.set_snapshot_path("snapshots");
-
-        if self.update_snapshots {
-            // Insta uses environment variables for controlling snapshot updates.
-            // We programmatically set it here to give users control via CLI flags.
-            std::env::set_var("INSTA_UPDATE", "always");
-        }
+        // Insta uses environment variables for controlling snapshot updates, which the
+        // settings set to give users control via CLI flags
+        let settings = self.settings();
 
         // Run the async tests directly without creating a new runtime
         let client = crate::api_client::ApiClient::new(api_url.to_string());
@@ -42,6 +37,8 @@ impl SnapshotTester {
             Ok(response) => {
                 // Redact timestamp for consistent snapshots
                 let mut value = serde_json::to_value(&response)?;
+                Self::remove_key(&mut value, "valid_until");
+                Self::remove_key(&mut value, "data_age_seconds");
                 Self::redact_timestamps(&mut value);
 
                 // We need to use settings.bind for insta snapshots
@@ -65,6 +62,8 @@ impl SnapshotTester {
             match client.get_fees_for_target(target).await {
                 Ok(response) => {
                     let mut value = serde_json::to_value(&response)?;
+                    Self::remove_key(&mut value, "valid_until");
+                    Self::remove_key(&mut value, "data_age_seconds");
                     Self::redact_timestamps(&mut value);
 
                     settings.bind(|| {
@@ -81,16 +80,118 @@ impl SnapshotTester {
             }
         }
 
-        results.print_summary();
+        // The same responses with camelCase field names, as a client asking for them sees them
+        let paths = [("fee_estimates".to_string(), "/fees".to_string())]
+            .into_iter()
+            .chain([3.0, 6.0, 12.0, 24.0, 144.0].map(|target| {
+                (
+                    format!("fee_estimates_target_{target}"),
+                    format!("/fees/target/{target}"),
+                )
+            }));
+        for (name, path) in paths {
+            let test_name = format!("{name}_camel_case");
+
+            match client.get_with_profile(&path, "camelCase").await {
+                Ok(mut value) => {
+                    Self::remove_key(&mut value, "validUntil");
+                    Self::remove_key(&mut value, "dataAgeSeconds");
+                    Self::redact_timestamps(&mut value);
+
+                    settings.bind(|| {
+                        assert_json_snapshot!(test_name.as_str(), value);
+                    });
+
+                    results.add_pass(&test_name);
+                }
+                Err(e) => {
+                    results.add_fail(&test_name, &format!("Failed: {e}"));
+                }
+            }
+        }
+
         Ok(results)
     }
 
+    /// Snapshot the status, content type and body of each case's error response
+    ///
+    /// Authorized cases carry `token` as a bearer token.
+    pub async fn run_error_tests(
+        &self,
+        api_url: &str,
+        token: &str,
+        cases: &[ErrorCase],
+    ) -> Result<SnapshotTestResults> {
+        let mut results = SnapshotTestResults::new();
+        let settings = self.settings();
+        let client = crate::api_client::ApiClient::new(api_url.to_string());
+
+        for case in cases {
+            info!("Testing {name} error snapshot", name = case.name);
+            let token = case.authorized.then_some(token);
+
+            match client
+                .request_problem(case.method.clone(), case.path, token, case.body.as_ref())
+                .await
+            {
+                Ok((status, content_type, mut body)) => {
+                    Self::redact_timestamps(&mut body);
+                    let value = serde_json::json!({
+                        "request": format!("{method} {path}", method = case.method, path = case.path),
+                        "status": status.as_u16(),
+                        "content_type": content_type,
+                        "body": body,
+                    });
+
+                    settings.bind(|| {
+                        assert_json_snapshot!(case.name, value);
+                    });
+
+                    results.add_pass(case.name);
+                }
+                Err(e) => {
+                    results.add_fail(case.name, &format!("Failed: {e}"));
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Insta settings storing snapshots in the `snapshots` directory
+    fn settings(&self) -> Settings {
+        let mut settings = Settings::clone_current();
+        settings.set_snapshot_path("snapshots");
+        if self.update_snapshots {
+            std::env::set_var("INSTA_UPDATE", "always");
+        }
+        settings
+    }
+
+    /// Removes `key` from every object in `value`, for fields not every server publishes
+    fn remove_key(value: &mut Value, key: &str) {
+        match value {
+            Value::Object(map) => {
+                map.remove(key);
+                for val in map.values_mut() {
+                    Self::remove_key(val, key);
+                }
+            }
+            Value::Array(arr) => {
+                for item in arr {
+                    Self::remove_key(item, key);
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// Redact timestamps for consistent snapshots
     fn redact_timestamps(value: &mut Value) {
         match value {
             Value::Object(map) => {
                 for (key, val) in map.iter_mut() {
-                    if key.contains("time") || key.contains("timestamp") {
+                    if key.to_lowercase().contains("time") {
                         *val = Value::String("[timestamp]".to_string());
                     } else {
                         Self::redact_timestamps(val);
@@ -212,6 +313,119 @@ impl SnapshotTester {
     }
 }
 
+/// A request whose error response is snapshotted
+pub struct ErrorCase {
+    /// Snapshot name
+    pub name: &'static str,
+    pub method: Method,
+    pub path: &'static str,
+    /// Whether the request carries the server's bearer token
+    pub authorized: bool,
+    pub body: Option<Value>,
+}
+
+impl ErrorCase {
+    pub fn get(name: &'static str, path: &'static str) -> Self {
+        Self {
+            name,
+            method: Method::GET,
+            path,
+            authorized: false,
+            body: None,
+        }
+    }
+
+    pub fn post(name: &'static str, path: &'static str, body: Value) -> Self {
+        Self {
+            name,
+            method: Method::POST,
+            path,
+            authorized: false,
+            body: Some(body),
+        }
+    }
+
+    /// Send the server's bearer token with the request
+    pub fn authorized(mut self) -> Self {
+        self.authorized = true;
+        self
+    }
+
+    /// Malformed requests and the token-protected endpoints of a server estimating from mock
+    /// data, with the test, ingestion and debug endpoints enabled
+    pub fn request_errors() -> Vec<Self> {
+        let snapshot = serde_json::json!({
+            "block_height": 850000,
+            "timestamp": "2025-01-20T12:00:00Z",
+            "bucketed_weights": {"100": 4000}
+        });
+        vec![
+            Self::get("error_target_not_a_number", "/fees/target/abc"),
+            Self::get("error_target_zero", "/fees/target/0"),
+            Self::get("error_target_too_large", "/fees/target/1001"),
+            Self::get("error_historical_missing_timestamp", "/historical_fee"),
+            Self::get("error_historical_no_data", "/historical_fee?timestamp=1"),
+            Self::get("error_unknown_path", "/unknown"),
+            Self::post("error_method_not_allowed", "/fees", Value::Null),
+            Self::post(
+                "admin_test_snapshots_unauthorized",
+                "/test/snapshots",
+                serde_json::json!({ "snapshots": [snapshot] }),
+            ),
+            Self::post(
+                "admin_test_snapshots_invalid_body",
+                "/test/snapshots",
+                serde_json::json!({ "snapshots": "none" }),
+            )
+            .authorized(),
+            Self::post(
+                "admin_ingest_unauthorized",
+                "/snapshots",
+                serde_json::json!({ "snapshots": [snapshot] }),
+            ),
+            Self::post(
+                "admin_ingest_empty",
+                "/snapshots",
+                serde_json::json!({ "snapshots": [] }),
+            )
+            .authorized(),
+            Self::post(
+                "admin_ingest_bucket_too_high",
+                "/snapshots",
+                serde_json::json!({ "snapshots": [{
+                    "block_height": 850000,
+                    "timestamp": "2025-01-20T12:00:00Z",
+                    "bucketed_weights": {"10001": 4000}
+                }] }),
+            )
+            .authorized(),
+            Self::get("admin_debug_missing_query", "/debug/simulation"),
+        ]
+    }
+
+    /// A server that has not computed an estimate, estimating only from ingested snapshots
+    pub fn warm_up() -> Vec<Self> {
+        vec![
+            Self::get("warmup_fees", "/fees"),
+            Self::get("warmup_fees_target", "/fees/target/6"),
+            Self::get("warmup_readiness", "/health/ready"),
+            Self::get(
+                "warmup_debug_simulation",
+                "/debug/simulation?target=6&confidence=0.5",
+            ),
+        ]
+    }
+
+    /// A server whose Bitcoin node answers every call with HTTP 500
+    pub fn upstream_down() -> Vec<Self> {
+        vec![
+            Self::get("upstream_down_fees", "/fees"),
+            Self::get("upstream_down_fees_target", "/fees/target/6"),
+            Self::get("upstream_down_readiness", "/health/ready"),
+        ]
+    }
+}
+
 /// Snapshot test results
 pub struct SnapshotTestResults {
     passed: Vec<String>,
@@ -269,6 +483,12 @@ impl SnapshotTestResults {
     pub fn all_passed(&self) -> bool {
         self.failed.is_empty()
     }
+
+    /// Adds the results of another run
+    pub fn extend(&mut self, other: SnapshotTestResults) {
+        self.passed.extend(other.passed);
+        self.failed.extend(other.failed);
+    }
 }
 
 /// Snapshot difference
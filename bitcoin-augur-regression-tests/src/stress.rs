@@ -1,15 +1,21 @@
 //! Stress and concurrent testing module
 //!
-//! Tests server behavior under concurrent load and stress conditions
+//! Tests server behavior under concurrent load and stress conditions. The
+//! `stress` subcommand drives a fixed request rate with [`run_load_test`] and
+//! checks the resulting latency percentiles against [`StressThresholds`].
 
 #![allow(dead_code)]
 
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
 use colored::*;
 use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tracing::info;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info};
 
 use crate::api_client::ApiClient;
 
@@ -184,6 +190,289 @@ async fn make_request(client: &ApiClient, endpoint: &str) -> Result<()> {
     Ok(())
 }
 
+/// Fixed-rate load test configuration
+#[derive(Debug, Clone)]
+pub struct LoadTestConfig {
+    /// Requests started per second across all endpoints
+    pub requests_per_second: f64,
+    /// How long to keep generating load
+    pub duration: Duration,
+    /// Endpoints requested in round-robin order
+    pub endpoints: Vec<String>,
+}
+
+impl Default for LoadTestConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 50.0,
+            duration: Duration::from_secs(10),
+            endpoints: StressTestConfig::default().endpoints,
+        }
+    }
+}
+
+/// Latency percentiles in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// Computes nearest-rank percentiles over the given samples
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+
+        let percentile = |p: f64| -> f64 {
+            if sorted.is_empty() {
+                return 0.0;
+            }
+            let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1].as_secs_f64() * 1000.0
+        };
+
+        Self {
+            p50_ms: percentile(50.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            max_ms: percentile(100.0),
+        }
+    }
+}
+
+/// Outcome of load against a single endpoint
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointReport {
+    pub requests: usize,
+    pub errors: usize,
+    pub latency: LatencyStats,
+}
+
+impl EndpointReport {
+    /// Fraction of requests that failed or returned a non-success status
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+/// Outcome of a fixed-rate load test
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadTestReport {
+    pub target_rps: f64,
+    pub achieved_rps: f64,
+    pub overall: EndpointReport,
+    pub endpoints: BTreeMap<String, EndpointReport>,
+}
+
+impl LoadTestReport {
+    pub fn print_summary(&self) {
+        println!("\n{title}", title = "Load Test Results".bold());
+        println!("{line}", line = "=================".dimmed());
+        println!(
+            "Target rate:   {rps:.1} req/s (achieved {achieved:.1} req/s)",
+            rps = self.target_rps,
+            achieved = self.achieved_rps
+        );
+
+        println!(
+            "\n{endpoint:<24} {requests:>8} {errors:>8} {p50:>9} {p95:>9} {p99:>9} {max:>9}",
+            endpoint = "Endpoint",
+            requests = "Requests",
+            errors = "Errors",
+            p50 = "p50 ms",
+            p95 = "p95 ms",
+            p99 = "p99 ms",
+            max = "max ms"
+        );
+        println!("{line:-<82}", line = "");
+
+        let rows = self
+            .endpoints
+            .iter()
+            .map(|(name, report)| (name.as_str(), report))
+            .chain(std::iter::once(("all", &self.overall)));
+        for (name, report) in rows {
+            let errors = format!("{rate:.1}%", rate = report.error_rate() * 100.0);
+            let errors = if report.errors > 0 {
+                errors.red()
+            } else {
+                errors.green()
+            };
+            println!(
+                "{name:<24} {requests:>8} {errors:>8} {p50:>9.2} {p95:>9.2} {p99:>9.2} {max:>9.2}",
+                requests = report.requests,
+                p50 = report.latency.p50_ms,
+                p95 = report.latency.p95_ms,
+                p99 = report.latency.p99_ms,
+                max = report.latency.max_ms
+            );
+        }
+    }
+}
+
+/// Limits a load test must stay within, loaded from a JSON file
+///
+/// ```json
+/// { "max_p50_ms": 20, "max_p95_ms": 100, "max_p99_ms": 250, "max_error_rate": 0.001 }
+/// ```
+///
+/// Unset limits are not checked. Limits apply to every endpoint as well as the overall totals.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StressThresholds {
+    pub max_p50_ms: Option<f64>,
+    pub max_p95_ms: Option<f64>,
+    pub max_p99_ms: Option<f64>,
+    /// Maximum fraction of failed requests (0.0 - 1.0)
+    pub max_error_rate: Option<f64>,
+    /// Minimum achieved request rate, to catch a load generator that fell behind
+    pub min_achieved_rps: Option<f64>,
+}
+
+impl StressThresholds {
+    /// Loads thresholds from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "Failed to read thresholds file {path}",
+                path = path.display()
+            )
+        })?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid thresholds file {path}", path = path.display()))
+    }
+
+    /// Returns a description of every exceeded limit
+    pub fn violations(&self, report: &LoadTestReport) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        if let Some(min) = self.min_achieved_rps {
+            if report.achieved_rps < min {
+                violations.push(format!(
+                    "achieved {achieved:.1} req/s, below minimum {min:.1}",
+                    achieved = report.achieved_rps
+                ));
+            }
+        }
+
+        let reports = std::iter::once(("all", &report.overall))
+            .chain(report.endpoints.iter().map(|(n, r)| (n.as_str(), r)));
+        for (name, endpoint) in reports {
+            let latency = &endpoint.latency;
+            for (label, actual, limit) in [
+                ("p50", latency.p50_ms, self.max_p50_ms),
+                ("p95", latency.p95_ms, self.max_p95_ms),
+                ("p99", latency.p99_ms, self.max_p99_ms),
+            ] {
+                if let Some(limit) = limit {
+                    if actual > limit {
+                        violations.push(format!(
+                            "{name}: {label} latency {actual:.2} ms exceeds {limit:.2} ms"
+                        ));
+                    }
+                }
+            }
+            if let Some(limit) = self.max_error_rate {
+                if endpoint.error_rate() > limit {
+                    violations.push(format!(
+                        "{name}: error rate {rate:.4} exceeds {limit:.4}",
+                        rate = endpoint.error_rate()
+                    ));
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Drive a fixed request rate against a server and record per-endpoint latency
+///
+/// Requests are started on schedule regardless of how long earlier ones take, so a slow server
+/// shows up as growing latency rather than as a lower request rate.
+pub async fn run_load_test(base_url: String, config: LoadTestConfig) -> Result<LoadTestReport> {
+    ensure!(
+        config.requests_per_second > 0.0,
+        "Request rate must be positive"
+    );
+    ensure!(
+        !config.endpoints.is_empty(),
+        "At least one endpoint is required"
+    );
+
+    info!(
+        "Starting load test: {rps} req/s for {secs}s",
+        rps = config.requests_per_second,
+        secs = config.duration.as_secs_f64()
+    );
+
+    let client = Arc::new(ApiClient::new(base_url));
+    let mut interval =
+        tokio::time::interval(Duration::from_secs_f64(1.0 / config.requests_per_second));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    let start = Instant::now();
+    let mut handles = Vec::new();
+    let mut sent = 0usize;
+    while start.elapsed() < config.duration {
+        interval.tick().await;
+        let endpoint = config.endpoints[sent % config.endpoints.len()].clone();
+        let client = client.clone();
+        handles.push(tokio::spawn(async move {
+            let request_start = Instant::now();
+            let ok = match client.get_raw(&endpoint).await {
+                Ok((status, _)) => status.is_success(),
+                Err(e) => {
+                    debug!("Request to {endpoint} failed: {e}");
+                    false
+                }
+            };
+            (endpoint, request_start.elapsed(), ok)
+        }));
+        sent += 1;
+    }
+    let elapsed = start.elapsed();
+
+    let mut samples: BTreeMap<String, (Vec<Duration>, usize)> = BTreeMap::new();
+    for result in join_all(handles).await {
+        let (endpoint, latency, ok) = result.context("Load test request task failed")?;
+        let entry = samples.entry(endpoint).or_default();
+        entry.0.push(latency);
+        if !ok {
+            entry.1 += 1;
+        }
+    }
+
+    let summarize = |latencies: &[Duration], errors: usize| EndpointReport {
+        requests: latencies.len(),
+        errors,
+        latency: LatencyStats::from_samples(latencies),
+    };
+
+    let all: Vec<Duration> = samples.values().flat_map(|(l, _)| l.clone()).collect();
+    let total_errors = samples.values().map(|(_, e)| e).sum();
+
+    Ok(LoadTestReport {
+        target_rps: config.requests_per_second,
+        achieved_rps: sent as f64 / elapsed.as_secs_f64(),
+        overall: summarize(&all, total_errors),
+        endpoints: samples
+            .iter()
+            .map(|(endpoint, (latencies, errors))| {
+                (endpoint.clone(), summarize(latencies, *errors))
+            })
+            .collect(),
+    })
+}
+
 /// Performance comparison between two servers
 pub async fn compare_performance(
     server1_url: String,
@@ -278,4 +567,49 @@ mod tests {
         assert_eq!(config.iterations, 5);
         assert_eq!(config.endpoints.len(), 4);
     }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        let stats = LatencyStats::from_samples(&samples);
+        assert_eq!(stats.p50_ms, 50.0);
+        assert_eq!(stats.p95_ms, 95.0);
+        assert_eq!(stats.p99_ms, 99.0);
+        assert_eq!(stats.max_ms, 100.0);
+
+        assert_eq!(LatencyStats::from_samples(&[]), LatencyStats::default());
+    }
+
+    #[test]
+    fn test_threshold_violations() {
+        let endpoint = EndpointReport {
+            requests: 100,
+            errors: 2,
+            latency: LatencyStats {
+                p50_ms: 5.0,
+                p95_ms: 40.0,
+                p99_ms: 120.0,
+                max_ms: 300.0,
+            },
+        };
+        let report = LoadTestReport {
+            target_rps: 50.0,
+            achieved_rps: 49.5,
+            overall: endpoint.clone(),
+            endpoints: BTreeMap::from([("/fees".to_string(), endpoint)]),
+        };
+
+        let relaxed: StressThresholds =
+            serde_json::from_str(r#"{"max_p99_ms": 200, "max_error_rate": 0.05}"#).unwrap();
+        assert!(relaxed.violations(&report).is_empty());
+
+        let strict: StressThresholds = serde_json::from_str(
+            r#"{"max_p95_ms": 20, "max_error_rate": 0.01, "min_achieved_rps": 50}"#,
+        )
+        .unwrap();
+        // p95 and error rate fail for both the endpoint and the totals, plus the rate check
+        assert_eq!(strict.violations(&report).len(), 5);
+
+        assert!(serde_json::from_str::<StressThresholds>(r#"{"max_p42_ms": 1}"#).is_err());
+    }
 }
@@ -1,364 +1,375 @@
-use anyhow::{Context, Result};
-use bitcoin_augur::{MempoolSnapshot, MempoolTransaction};
-use chrono::{DateTime, Utc};
+//! Regression test vectors recording the estimates of this implementation
+//!
+//! A vector file is versioned JSON holding estimator inputs and the estimates they produced:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "generator": "bitcoin-augur-rust 0.1.0",
+//!   "tolerance": 1e-9,
+//!   "vectors": [
+//!     {
+//!       "name": "steady_state",
+//!       "description": "Three hours of steady inflow",
+//!       "snapshots": [
+//!         { "block_height": 850000, "timestamp": "2025-01-20T12:00:00Z",
+//!           "bucketed_weights": { "69": 40000, "230": 12000 } }
+//!       ],
+//!       "num_blocks": null,
+//!       "expected": { "3": { "0.05": 2.0138, "0.95": null } }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Snapshots use the `MempoolSnapshot` JSON form with explicit timestamps, so no
+//! implementation depends on the wall clock. `expected` maps each block target and
+//! confidence level (formatted as in the API) to the exact fee rate, or `null` where no
+//! estimate is produced. A vector passes only if the produced estimates have exactly the
+//! expected targets and confidence levels, each within `tolerance` (relative, plus the same
+//! amount in sat/vB). A file may instead give a `tolerance_model` with an absolute tolerance and
+//! relative ones per fee rate magnitude, as described in `comparison.rs`.
+//!
+//! The expected values come from this implementation, so a vector catches changes to the
+//! estimates, not divergence from the Kotlin reference.
+
+use anyhow::{ensure, Context, Result};
+use bitcoin_augur::{FeeEstimator, MempoolSnapshot, MempoolTransaction};
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
 use tracing::info;
 
-/// Test vector for fee estimation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TestVector {
-    pub name: String,
-    pub description: String,
-    pub mempool_snapshots: Vec<MempoolSnapshotData>,
-    pub expected_estimates: ExpectedEstimates,
-}
+use crate::comparison::ToleranceModel;
 
-/// Mempool snapshot data for test vectors
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MempoolSnapshotData {
-    pub block_height: u64,
-    pub timestamp: String,
-    pub transactions: Vec<TransactionData>,
-}
+/// Current vector file schema version
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Vectors bundled with the test suite
+const BUNDLED_VECTORS: &str = include_str!("../vectors/regression_v1.json");
 
-/// Transaction data for test vectors
+/// Expected fee rates keyed by block target, then confidence level
+pub type ExpectedEstimates = BTreeMap<String, BTreeMap<String, Option<f64>>>;
+
+/// A versioned file of regression test vectors
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TransactionData {
-    pub weight: u32,
-    pub fee: u64,
-    pub fee_rate: Option<f64>, // Optional, calculated if not provided
+pub struct VectorFile {
+    pub schema_version: u32,
+    /// Implementation and version that produced the expected values
+    pub generator: String,
+    /// Relative tolerance applied to every comparison
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+    /// Tolerance per fee rate magnitude, replacing `tolerance`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance_model: Option<ToleranceModel>,
+    pub vectors: Vec<TestVector>,
 }
 
-/// Expected estimates for validation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExpectedEstimates {
-    pub block_targets: Vec<ExpectedBlockTarget>,
+fn default_tolerance() -> f64 {
+    1e-9
 }
 
-/// Expected estimates for a specific block target
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExpectedBlockTarget {
-    pub blocks: usize,
-    pub probabilities: Vec<ExpectedProbability>,
+impl VectorFile {
+    /// Tolerance applied to every comparison
+    pub fn tolerance(&self) -> ToleranceModel {
+        self.tolerance_model
+            .clone()
+            .unwrap_or_else(|| ToleranceModel::relative(self.tolerance))
+    }
 }
 
-/// Expected probability and fee rate
+/// Inputs and exact expected outputs for one estimation
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExpectedProbability {
-    pub probability: f64,
-    pub fee_rate: f64,
-    pub tolerance: Option<f64>, // Optional tolerance for comparison
+pub struct TestVector {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Snapshot history, in any order
+    pub snapshots: Vec<MempoolSnapshot>,
+    /// Custom block target, as for `GET /fees/target/{num_blocks}`
+    #[serde(default)]
+    pub num_blocks: Option<f64>,
+    pub expected: ExpectedEstimates,
 }
 
+/// Name, description, snapshots and custom target of a generated vector
+type VectorInput = (
+    &'static str,
+    &'static str,
+    Vec<MempoolSnapshot>,
+    Option<f64>,
+);
+
 /// Test vector runner
 pub struct TestVectorRunner;
 
 impl TestVectorRunner {
-    /// Load test vectors from file
-    pub async fn load_vectors(path: &Path) -> Result<Vec<TestVector>> {
+    /// Load and validate a vector file
+    pub async fn load_vectors(path: &Path) -> Result<VectorFile> {
         let content = tokio::fs::read_to_string(path)
             .await
             .with_context(|| format!("Failed to read test vectors from {path:?}"))?;
 
-        serde_json::from_str(&content)
+        Self::parse_vectors(&content)
             .with_context(|| format!("Failed to parse test vectors from {path:?}"))
     }
 
-    /// Generate default test vectors
-    pub fn generate_default_vectors() -> Vec<TestVector> {
-        vec![
-            Self::generate_simple_vector(),
-            Self::generate_congestion_vector(),
-            Self::generate_empty_mempool_vector(),
-            Self::generate_high_variance_vector(),
-            Self::generate_reference_compatibility_vector(),
-        ]
+    /// Vectors bundled with the test suite
+    pub fn bundled_vectors() -> Result<VectorFile> {
+        Self::parse_vectors(BUNDLED_VECTORS).context("Bundled test vectors are invalid")
     }
 
-    /// Generate a simple test vector
-    fn generate_simple_vector() -> TestVector {
-        TestVector {
-            name: "simple_mempool".to_string(),
-            description: "Basic mempool with uniform fee distribution".to_string(),
-            mempool_snapshots: vec![MempoolSnapshotData {
-                block_height: 850000,
-                timestamp: "2025-01-20T12:00:00Z".to_string(),
-                transactions: (1..=100)
-                    .map(|i| TransactionData {
-                        weight: 2000,
-                        fee: (i * 1000) as u64,
-                        fee_rate: Some(i as f64 * 2.0),
-                    })
-                    .collect(),
-            }],
-            expected_estimates: ExpectedEstimates {
-                block_targets: vec![ExpectedBlockTarget {
-                    blocks: 3,
-                    probabilities: vec![
-                        ExpectedProbability {
-                            probability: 0.05,
-                            fee_rate: 1.0, // Minimum fee rate
-                            tolerance: Some(0.5),
-                        },
-                        ExpectedProbability {
-                            probability: 0.50,
-                            fee_rate: 1.0, // Minimum fee rate
-                            tolerance: Some(0.5),
-                        },
-                        ExpectedProbability {
-                            probability: 0.95,
-                            fee_rate: 1.0, // Minimum fee rate
-                            tolerance: Some(0.5),
-                        },
-                    ],
-                }],
-            },
-        }
-    }
-
-    /// Generate a congestion test vector
-    fn generate_congestion_vector() -> TestVector {
-        TestVector {
-            name: "mempool_congestion".to_string(),
-            description: "Congested mempool with high fees".to_string(),
-            mempool_snapshots: vec![MempoolSnapshotData {
-                block_height: 850100,
-                timestamp: "2025-01-20T13:00:00Z".to_string(),
-                transactions: (1..=500)
-                    .map(|i| TransactionData {
-                        weight: 4000,
-                        fee: (i * 5000) as u64,
-                        fee_rate: Some(i as f64 * 5.0),
-                    })
-                    .collect(),
-            }],
-            expected_estimates: ExpectedEstimates {
-                block_targets: vec![ExpectedBlockTarget {
-                    blocks: 6,
-                    probabilities: vec![ExpectedProbability {
-                        probability: 0.50,
-                        fee_rate: 1.0, // Minimum fee without sufficient data
-                        tolerance: Some(0.5),
-                    }],
-                }],
-            },
+    fn parse_vectors(content: &str) -> Result<VectorFile> {
+        let file: VectorFile = serde_json::from_str(content)?;
+        ensure!(
+            file.schema_version == SCHEMA_VERSION,
+            "Unsupported schema version {version} (expected {SCHEMA_VERSION})",
+            version = file.schema_version
+        );
+        ensure!(
+            file.tolerance.is_finite() && file.tolerance >= 0.0,
+            "Invalid tolerance {tolerance}",
+            tolerance = file.tolerance
+        );
+        if let Some(ref model) = file.tolerance_model {
+            model.validate().map_err(anyhow::Error::msg)?;
         }
+        Ok(file)
     }
 
-    /// Generate empty mempool test vector
-    fn generate_empty_mempool_vector() -> TestVector {
-        TestVector {
-            name: "empty_mempool".to_string(),
-            description: "Empty mempool should return minimum fees".to_string(),
-            mempool_snapshots: vec![MempoolSnapshotData {
-                block_height: 850200,
-                timestamp: "2025-01-20T14:00:00Z".to_string(),
-                transactions: vec![],
-            }],
-            expected_estimates: ExpectedEstimates {
-                block_targets: vec![], // No estimates for empty mempool
-            },
-        }
+    /// Generate a vector file whose expected values come from this implementation
+    pub fn generate_vectors() -> Result<VectorFile> {
+        let vectors = Self::vector_inputs()?
+            .into_iter()
+            .map(|(name, description, snapshots, num_blocks)| {
+                let expected = estimate(&snapshots, num_blocks)?;
+                Ok(TestVector {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    snapshots,
+                    num_blocks,
+                    expected,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(VectorFile {
+            schema_version: SCHEMA_VERSION,
+            generator: format!(
+                "bitcoin-augur-rust {version}",
+                version = env!("CARGO_PKG_VERSION")
+            ),
+            tolerance: default_tolerance(),
+            tolerance_model: None,
+            vectors,
+        })
     }
 
-    /// Generate high variance test vector
-    fn generate_high_variance_vector() -> TestVector {
-        let mut transactions = Vec::new();
-
-        // Low fee transactions
-        for _ in 0..100 {
-            transactions.push(TransactionData {
-                weight: 2000,
-                fee: 2000,
-                fee_rate: Some(1.0),
-            });
-        }
-
-        // High fee transactions
-        for _ in 0..50 {
-            transactions.push(TransactionData {
-                weight: 2000,
-                fee: 200000,
-                fee_rate: Some(100.0),
-            });
-        }
-
-        TestVector {
-            name: "high_variance".to_string(),
-            description: "Mempool with high fee variance".to_string(),
-            mempool_snapshots: vec![MempoolSnapshotData {
-                block_height: 850300,
-                timestamp: "2025-01-20T15:00:00Z".to_string(),
-                transactions,
-            }],
-            expected_estimates: ExpectedEstimates {
-                block_targets: vec![ExpectedBlockTarget {
-                    blocks: 3,
-                    probabilities: vec![
-                        ExpectedProbability {
-                            probability: 0.05,
-                            fee_rate: 1.0,
-                            tolerance: Some(0.5),
-                        },
-                        ExpectedProbability {
-                            probability: 0.95,
-                            fee_rate: 1.0, // Returns minimum without sufficient history
-                            tolerance: Some(0.5),
-                        },
-                    ],
-                }],
-            },
-        }
+    /// Deterministic inputs for the generated vectors
+    fn vector_inputs() -> Result<Vec<VectorInput>> {
+        let start = Utc
+            .with_ymd_and_hms(2025, 1, 20, 12, 0, 0)
+            .single()
+            .context("Invalid vector start time")?;
+
+        Ok(vec![
+            (
+                "empty_mempool",
+                "Empty mempool produces no estimates",
+                history(start, 6, |_| Vec::new()),
+                None,
+            ),
+            (
+                "single_snapshot",
+                "One snapshot without inflow history",
+                history(start, 1, |_| uniform(40, 1.0, 200.0)),
+                None,
+            ),
+            (
+                "steady_state",
+                "Three hours of steady inflow across fee rates",
+                history(start, 19, |i| scaled(uniform(60, 1.0, 100.0), 1 + i % 3)),
+                None,
+            ),
+            (
+                "congestion",
+                "Backlog growing faster than blocks clear it",
+                history(start, 19, |i| scaled(uniform(80, 5.0, 150.0), 4 + i)),
+                None,
+            ),
+            (
+                "high_variance",
+                "Bimodal mempool at 1 and 100 sat/vB",
+                history(start, 19, |i| {
+                    let mut txs = scaled(uniform(20, 1.0, 1.5), 10 + i % 3);
+                    txs.extend(scaled(uniform(20, 90.0, 110.0), 1 + i % 3));
+                    txs
+                }),
+                None,
+            ),
+            (
+                "fee_spike",
+                "Final hour dominated by high fee transactions",
+                history(start, 19, |i| {
+                    if i >= 13 {
+                        scaled(uniform(60, 50.0, 300.0), 3 + i % 3)
+                    } else {
+                        scaled(uniform(60, 1.0, 20.0), 1 + i % 3)
+                    }
+                }),
+                None,
+            ),
+            (
+                "custom_target",
+                "Steady inflow estimated for a 10 block target",
+                history(start, 19, |i| scaled(uniform(60, 1.0, 100.0), 1 + i % 3)),
+                Some(10.0),
+            ),
+        ])
     }
 
-    /// Generate test vector matching reference implementation test data
-    fn generate_reference_compatibility_vector() -> TestVector {
-        // Based on test data from FeeEstimateEndpointTest.kt
-        TestVector {
-            name: "reference_compatibility".to_string(),
-            description: "Test vector matching Kotlin reference implementation".to_string(),
-            mempool_snapshots: vec![MempoolSnapshotData {
-                block_height: 850000,
-                timestamp: "2025-01-15T10:00:00.123Z".to_string(),
-                transactions: vec![
-                    // Block 1 transactions
-                    TransactionData {
-                        weight: 4000,
-                        fee: 42000,
-                        fee_rate: Some(10.5),
-                    },
-                    TransactionData {
-                        weight: 4000,
-                        fee: 61000,
-                        fee_rate: Some(15.25),
-                    },
-                    // Block 6 transactions
-                    TransactionData {
-                        weight: 4000,
-                        fee: 23000,
-                        fee_rate: Some(5.75),
-                    },
-                    TransactionData {
-                        weight: 4000,
-                        fee: 32494,
-                        fee_rate: Some(8.1234),
-                    },
-                ],
-            }],
-            expected_estimates: ExpectedEstimates {
-                block_targets: vec![ExpectedBlockTarget {
-                    blocks: 6,
-                    probabilities: vec![ExpectedProbability {
-                        probability: 0.50,
-                        fee_rate: 1.0, // Minimum without sufficient history
-                        tolerance: Some(0.5),
-                    }],
-                }],
-            },
+    /// Save a vector file
+    pub async fn save_vectors(file: &VectorFile, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(file)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
         }
+        tokio::fs::write(path, json).await?;
+        Ok(())
     }
 
-    /// Run test vector
-    pub fn run_vector(vector: &TestVector) -> Result<TestVectorResult> {
+    /// Run a test vector against the in-process estimator
+    pub fn run_vector(vector: &TestVector, tolerance: &ToleranceModel) -> Result<TestVectorResult> {
         info!("Running test vector: {name}", name = vector.name);
 
-        let estimator = bitcoin_augur::FeeEstimator::new();
+        let actual = estimate(&vector.snapshots, vector.num_blocks)?;
+        let validations = compare(&vector.expected, &actual, tolerance);
+        let passed = validations.iter().all(|v| v.passed);
+
+        Ok(TestVectorResult {
+            name: vector.name.clone(),
+            passed,
+            validations,
+        })
+    }
+}
 
-        // Convert test vector data to mempool snapshots
-        let mut snapshots = Vec::new();
-        for snapshot_data in &vector.mempool_snapshots {
-            let transactions: Vec<MempoolTransaction> = snapshot_data
-                .transactions
+/// Estimates for `snapshots` in the vector `expected` form
+pub(crate) fn estimate(
+    snapshots: &[MempoolSnapshot],
+    num_blocks: Option<f64>,
+) -> Result<ExpectedEstimates> {
+    let estimate = FeeEstimator::new().calculate_estimates(snapshots, num_blocks)?;
+
+    Ok(estimate
+        .estimates
+        .iter()
+        .map(|(blocks, target)| {
+            let probabilities = target
+                .probabilities
                 .iter()
-                .map(|tx| MempoolTransaction::new(tx.weight as u64, tx.fee))
+                .map(|(probability, &fee_rate)| {
+                    (format!("{p:.2}", p = probability.0), Some(fee_rate))
+                })
                 .collect();
+            (blocks.to_string(), probabilities)
+        })
+        .collect())
+}
 
-            let timestamp = DateTime::parse_from_rfc3339(&snapshot_data.timestamp)
-                .unwrap()
-                .with_timezone(&Utc);
-
-            snapshots.push(MempoolSnapshot::from_transactions(
-                transactions,
-                snapshot_data.block_height as u32,
-                timestamp,
-            ));
-        }
-
-        // Calculate estimates
-        let estimates = estimator.calculate_estimates(&snapshots, None)?;
-
-        // Validate against expected estimates
-        let mut validations = Vec::new();
-        for expected_target in &vector.expected_estimates.block_targets {
-            let actual_target = estimates.estimates.get(&(expected_target.blocks as u32));
-
-            if let Some(actual) = actual_target {
-                for expected_prob in &expected_target.probabilities {
-                    let actual_fee = actual.get_fee_rate(expected_prob.probability);
-
-                    let validation = if let Some(fee) = actual_fee {
-                        let tolerance = expected_prob.tolerance.unwrap_or(0.01);
-                        let diff = (fee - expected_prob.fee_rate).abs();
-
-                        ProbabilityValidation {
-                            blocks: expected_target.blocks,
-                            probability: expected_prob.probability,
-                            expected: expected_prob.fee_rate,
-                            actual: Some(fee),
-                            passed: diff <= tolerance,
-                            message: if diff <= tolerance {
-                                format!("Within tolerance (diff: {diff:.4})")
-                            } else {
-                                format!("Outside tolerance (diff: {diff:.4} > {tolerance:.4})")
-                            },
-                        }
+/// Compares every expected and every produced estimate
+pub(crate) fn compare(
+    expected: &ExpectedEstimates,
+    actual: &ExpectedEstimates,
+    tolerance: &ToleranceModel,
+) -> Vec<ProbabilityValidation> {
+    let mut keys: Vec<(&String, &String)> = expected
+        .iter()
+        .chain(actual)
+        .flat_map(|(blocks, probabilities)| probabilities.keys().map(move |p| (blocks, p)))
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .map(|(blocks, probability)| {
+            let lookup = |estimates: &ExpectedEstimates| {
+                estimates
+                    .get(blocks)
+                    .and_then(|target| target.get(probability))
+                    .copied()
+                    .flatten()
+            };
+            let expected = lookup(expected);
+            let actual = lookup(actual);
+
+            let (passed, message) = match (expected, actual) {
+                (Some(e), Some(a)) => {
+                    let diff = (a - e).abs();
+                    let allowed = tolerance.allowed(e);
+                    if diff <= allowed {
+                        (true, format!("Match (diff: {diff:.2e})"))
                     } else {
-                        ProbabilityValidation {
-                            blocks: expected_target.blocks,
-                            probability: expected_prob.probability,
-                            expected: expected_prob.fee_rate,
-                            actual: None,
-                            passed: false,
-                            message: "No fee rate calculated".to_string(),
-                        }
-                    };
-
-                    validations.push(validation);
-                }
-            } else {
-                for expected_prob in &expected_target.probabilities {
-                    validations.push(ProbabilityValidation {
-                        blocks: expected_target.blocks,
-                        probability: expected_prob.probability,
-                        expected: expected_prob.fee_rate,
-                        actual: None,
-                        passed: false,
-                        message: format!(
-                            "No estimates for {blocks} blocks",
-                            blocks = expected_target.blocks
-                        ),
-                    });
+                        (
+                            false,
+                            format!("Mismatch (diff: {diff:.2e} > {allowed:.2e})"),
+                        )
+                    }
                 }
+                (None, None) => (true, "No estimate, as expected".to_string()),
+                (Some(_), None) => (false, "No fee rate calculated".to_string()),
+                (None, Some(_)) => (false, "Unexpected estimate".to_string()),
+            };
+
+            ProbabilityValidation {
+                blocks: blocks.clone(),
+                probability: probability.clone(),
+                expected,
+                actual,
+                passed,
+                message,
             }
-        }
+        })
+        .collect()
+}
 
-        let all_passed = validations.iter().all(|v| v.passed);
+/// Snapshots every 10 minutes starting at `start`, with a block every third snapshot
+fn history(
+    start: DateTime<Utc>,
+    count: u32,
+    transactions: impl Fn(u32) -> Vec<MempoolTransaction>,
+) -> Vec<MempoolSnapshot> {
+    (0..count)
+        .map(|i| {
+            MempoolSnapshot::from_transactions(
+                transactions(i),
+                850_000 + i / 3,
+                start + Duration::minutes(i64::from(i) * 10),
+            )
+        })
+        .collect()
+}
 
-        Ok(TestVectorResult {
-            name: vector.name.clone(),
-            passed: all_passed,
-            validations,
+/// `count` transactions with fee rates spread evenly over `[min_rate, max_rate]`
+fn uniform(count: u32, min_rate: f64, max_rate: f64) -> Vec<MempoolTransaction> {
+    (0..count)
+        .map(|i| {
+            let fee_rate = min_rate + (max_rate - min_rate) * f64::from(i) / f64::from(count);
+            let weight = 1_000 + u64::from(i % 7) * 400;
+            MempoolTransaction::new(weight, (fee_rate * weight as f64 / 4.0).round() as u64)
         })
-    }
+        .collect()
+}
 
-    /// Save test vectors to file
-    pub async fn save_vectors(vectors: &[TestVector], path: &Path) -> Result<()> {
-        let json = serde_json::to_string_pretty(vectors)?;
-        tokio::fs::create_dir_all(path.parent().unwrap()).await?;
-        tokio::fs::write(path, json).await?;
-        Ok(())
-    }
+/// Multiplies every transaction's weight and fee, keeping its fee rate
+fn scaled(transactions: Vec<MempoolTransaction>, factor: u32) -> Vec<MempoolTransaction> {
+    let factor = u64::from(factor) * 10;
+    transactions
+        .into_iter()
+        .map(|tx| MempoolTransaction::new(tx.weight * factor, tx.fee * factor))
+        .collect()
 }
 
 /// Test vector validation result
@@ -372,9 +383,9 @@ pub struct TestVectorResult {
 /// Individual probability validation
 #[derive(Debug)]
 pub struct ProbabilityValidation {
-    pub blocks: usize,
-    pub probability: f64,
-    pub expected: f64,
+    pub blocks: String,
+    pub probability: String,
+    pub expected: Option<f64>,
     pub actual: Option<f64>,
     pub passed: bool,
     pub message: String,
@@ -397,6 +408,11 @@ impl TestVectorResult {
         );
         println!("{separator}", separator = "-".repeat(60));
 
+        let format_rate = |rate: Option<f64>| {
+            rate.map(|v| format!("{v:.4}"))
+                .unwrap_or_else(|| "N/A".to_string())
+        };
+
         for validation in &self.validations {
             let symbol = if validation.passed {
                 "✓".green()
@@ -404,20 +420,79 @@ impl TestVectorResult {
                 "✗".red()
             };
 
-            let actual_str = validation
-                .actual
-                .map(|v| format!("{v:.4}"))
-                .unwrap_or_else(|| "N/A".to_string());
-
             println!(
-                "{symbol} Blocks: {blocks}, Prob: {prob:.2}, Expected: {expected:.4}, Actual: {actual}, {message}",
+                "{symbol} Blocks: {blocks}, Prob: {prob}, Expected: {expected}, Actual: {actual}, {message}",
                 symbol = symbol,
                 blocks = validation.blocks,
                 prob = validation.probability,
-                expected = validation.expected,
-                actual = actual_str,
+                expected = format_rate(validation.expected),
+                actual = format_rate(validation.actual),
                 message = validation.message
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_vectors_pass() {
+        let file = TestVectorRunner::bundled_vectors().unwrap();
+        assert_eq!(file.schema_version, SCHEMA_VERSION);
+        assert!(!file.vectors.is_empty());
+
+        for vector in &file.vectors {
+            let result = TestVectorRunner::run_vector(vector, &file.tolerance()).unwrap();
+            assert!(result.passed, "vector {name} failed", name = vector.name);
+        }
+    }
+
+    #[test]
+    fn test_generated_vectors_match_bundled_inputs() {
+        let bundled = TestVectorRunner::bundled_vectors().unwrap();
+        let generated = TestVectorRunner::generate_vectors().unwrap();
+
+        let names = |file: &VectorFile| -> Vec<String> {
+            file.vectors.iter().map(|v| v.name.clone()).collect()
+        };
+        assert_eq!(names(&bundled), names(&generated));
+        for (b, g) in bundled.vectors.iter().zip(&generated.vectors) {
+            assert_eq!(b.snapshots.len(), g.snapshots.len());
+            assert_eq!(b.num_blocks, g.num_blocks);
+        }
+    }
+
+    #[test]
+    fn test_compare_is_exact_both_ways() {
+        let expected: ExpectedEstimates =
+            serde_json::from_str(r#"{"3": {"0.50": 10.0, "0.95": null}, "6": {"0.50": 5.0}}"#)
+                .unwrap();
+
+        let same = compare(&expected, &expected, &ToleranceModel::relative(1e-9));
+        assert!(same.iter().all(|v| v.passed));
+
+        let mut drifted = expected.clone();
+        drifted
+            .get_mut("6")
+            .unwrap()
+            .insert("0.50".to_string(), Some(5.001));
+        drifted
+            .get_mut("3")
+            .unwrap()
+            .insert("0.95".to_string(), Some(12.0));
+        let results = compare(&expected, &drifted, &ToleranceModel::relative(1e-9));
+        assert_eq!(results.iter().filter(|v| !v.passed).count(), 2);
+
+        // An estimate missing from the output fails as well
+        let results = compare(&expected, &BTreeMap::new(), &ToleranceModel::relative(1e-9));
+        assert_eq!(results.iter().filter(|v| !v.passed).count(), 2);
+    }
+
+    #[test]
+    fn test_unsupported_schema_version_rejected() {
+        let content = r#"{"schema_version": 2, "generator": "x", "vectors": []}"#;
+        assert!(TestVectorRunner::parse_vectors(content).is_err());
+    }
+}
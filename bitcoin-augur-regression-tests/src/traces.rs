@@ -0,0 +1,478 @@
+//! Snapshot sequences with the dynamics of a real mempool
+//!
+//! [`TraceModel`] simulates a mempool filled by a steady stream of transactions and emptied by
+//! blocks. Inflow and fee rates follow a daily cycle, fee spikes occasionally flood the mempool
+//! with high fee rates, and blocks arrive at exponentially distributed intervals, with a burst of
+//! quick blocks after an unusually long one.
+//!
+//! A trace file replays recorded dynamics instead. It holds one JSON [`MempoolSnapshot`] per
+//! line, so it carries only block heights, timestamps and weights per fee rate bucket, never
+//! transaction ids. Cases are windows of the trace, shifted in time to end at the requested time.
+//! The server's `--record-trace` option writes such files, and `traces/` holds a corpus of them.
+
+use anyhow::{ensure, Context, Result};
+use bitcoin_augur::{MempoolSnapshot, MempoolTransaction};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+use std::f64::consts::PI;
+use std::path::Path;
+
+/// Weight a block can hold
+const BLOCK_WEIGHT: f64 = 4_000_000.0;
+
+/// Mean seconds between the quick blocks of a burst
+const BURST_BLOCK_INTERVAL_SECS: f64 = 60.0;
+
+/// Parameters of the simulated mempool
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TraceModel {
+    /// Seconds between snapshots
+    pub snapshot_interval_secs: u64,
+    /// Mean seconds between blocks
+    pub block_interval_secs: f64,
+    /// Inflow as a fraction of block capacity, averaged over the day
+    pub load: f64,
+    /// Relative swing of inflow and fee rates over the day
+    pub diurnal_amplitude: f64,
+    /// UTC hour at which inflow and fee rates peak
+    pub peak_hour: f64,
+    /// Median fee rate of new transactions, averaged over the day, in sat/vB
+    pub median_fee_rate: f64,
+    /// Standard deviation of the natural log of new transactions' fee rates
+    pub fee_rate_spread: f64,
+    /// Chance that a fee spike starts between two snapshots
+    pub spike_probability: f64,
+    /// How long a fee spike lasts, in seconds
+    pub spike_secs: u64,
+    /// Factor applied to inflow during a fee spike
+    pub spike_inflow: f64,
+    /// Factor applied to new transactions' fee rates during a fee spike
+    pub spike_fee_multiplier: f64,
+    /// Block intervals longer than this, in seconds, are followed by a burst
+    pub burst_after_secs: f64,
+    /// Blocks found in quick succession after a long block interval
+    pub burst_blocks: u32,
+}
+
+impl Default for TraceModel {
+    fn default() -> Self {
+        Self {
+            snapshot_interval_secs: 300,
+            block_interval_secs: 600.0,
+            load: 1.0,
+            diurnal_amplitude: 0.4,
+            peak_hour: 15.0,
+            median_fee_rate: 5.0,
+            fee_rate_spread: 0.9,
+            spike_probability: 0.05,
+            spike_secs: 1800,
+            spike_inflow: 2.0,
+            spike_fee_multiplier: 8.0,
+            burst_after_secs: 1800.0,
+            burst_blocks: 2,
+        }
+    }
+}
+
+/// Mempool state while a sequence is simulated
+struct Simulation<'a> {
+    model: &'a TraceModel,
+    rng: StdRng,
+    /// Waiting transactions as (weight, fee rate)
+    mempool: Vec<(u64, f64)>,
+    height: u32,
+    now: DateTime<Utc>,
+    next_block: DateTime<Utc>,
+    last_block: DateTime<Utc>,
+    burst_remaining: u32,
+    spike_until: Option<DateTime<Utc>>,
+}
+
+impl Simulation<'_> {
+    /// Multiplier of the daily cycle at `time`, averaging 1
+    fn diurnal(&self, time: DateTime<Utc>) -> f64 {
+        let hour = time.num_seconds_from_midnight() as f64 / 3600.0;
+        let phase = 2.0 * PI * (hour - self.model.peak_hour) / 24.0;
+        (1.0 + self.model.diurnal_amplitude * phase.cos()).max(0.0)
+    }
+
+    fn in_spike(&self, time: DateTime<Utc>) -> bool {
+        self.spike_until.is_some_and(|until| time < until)
+    }
+
+    /// Standard normal sample
+    fn normal(&mut self) -> f64 {
+        let u1: f64 = self.rng.random_range(f64::EPSILON..1.0);
+        let u2: f64 = self.rng.random();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+    }
+
+    /// Exponential sample with mean `mean_secs`
+    fn interval(&mut self, mean_secs: f64) -> Duration {
+        let u: f64 = self.rng.random_range(f64::EPSILON..1.0);
+        Duration::milliseconds((-u.ln() * mean_secs * 1000.0) as i64)
+    }
+
+    fn schedule_block(&mut self) {
+        let mean = if self.burst_remaining > 0 {
+            self.burst_remaining -= 1;
+            BURST_BLOCK_INTERVAL_SECS
+        } else {
+            self.model.block_interval_secs
+        };
+        self.next_block = self.now + self.interval(mean);
+    }
+
+    /// Adds the transactions arriving between `now` and `until`
+    fn receive(&mut self, until: DateTime<Utc>) {
+        let secs = (until - self.now).as_seconds_f64();
+        if secs <= 0.0 {
+            return;
+        }
+        let middle = self.now + (until - self.now) / 2;
+        let spike = self.in_spike(middle);
+        let diurnal = self.diurnal(middle);
+
+        let mut inflow =
+            BLOCK_WEIGHT / self.model.block_interval_secs * self.model.load * diurnal * secs;
+        let mut median = self.model.median_fee_rate * diurnal.max(0.1);
+        if spike {
+            inflow *= self.model.spike_inflow;
+            median *= self.model.spike_fee_multiplier;
+        }
+
+        let mut remaining = inflow as u64;
+        while remaining > 0 {
+            let weight = self.rng.random_range(2_000..=40_000u64).min(remaining);
+            let fee_rate = (median.ln() + self.model.fee_rate_spread * self.normal())
+                .exp()
+                .max(1.0);
+            self.mempool.push((weight, fee_rate));
+            remaining -= weight;
+        }
+        self.now = until;
+    }
+
+    /// Confirms the highest fee rate transactions that fit in a block
+    fn mine(&mut self) {
+        self.mempool.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut used = 0.0;
+        let mut confirmed = 0;
+        for &(weight, _) in &self.mempool {
+            if used + weight as f64 > BLOCK_WEIGHT {
+                break;
+            }
+            used += weight as f64;
+            confirmed += 1;
+        }
+        self.mempool.drain(..confirmed);
+        self.height += 1;
+
+        if (self.now - self.last_block).as_seconds_f64() > self.model.burst_after_secs {
+            self.burst_remaining = self.model.burst_blocks;
+        }
+        self.last_block = self.now;
+        self.schedule_block();
+    }
+
+    /// Advances to `time`, mining every block found on the way, and snapshots the mempool
+    fn snapshot_at(&mut self, time: DateTime<Utc>) -> MempoolSnapshot {
+        while self.next_block <= time {
+            let block = self.next_block;
+            self.receive(block);
+            self.mine();
+        }
+        self.receive(time);
+
+        let transactions = self
+            .mempool
+            .iter()
+            .map(|&(weight, fee_rate)| {
+                MempoolTransaction::new(weight, (fee_rate * weight as f64 / 4.0).round() as u64)
+            })
+            .collect();
+        MempoolSnapshot::from_transactions(transactions, self.height, time)
+    }
+}
+
+impl TraceModel {
+    /// Loads a model from a JSON file, taking defaults for missing fields
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read model file {path:?}"))?;
+        let model: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid model file {path:?}"))?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.snapshot_interval_secs > 0,
+            "snapshot_interval_secs must be positive"
+        );
+        ensure!(
+            self.block_interval_secs > 0.0,
+            "block_interval_secs must be positive"
+        );
+        ensure!(
+            self.median_fee_rate >= 1.0,
+            "median_fee_rate must be at least 1"
+        );
+        ensure!(
+            (0.0..=1.0).contains(&self.spike_probability),
+            "spike_probability must be between 0 and 1"
+        );
+        ensure!(
+            self.load >= 0.0 && self.spike_inflow >= 0.0 && self.spike_fee_multiplier > 0.0,
+            "load and spike factors must not be negative"
+        );
+        Ok(())
+    }
+
+    /// Simulates `count` snapshots ending at `end`, from a mempool warmed up for an hour
+    ///
+    /// The time of day the sequence starts at is drawn from `seed`, so different seeds cover
+    /// different parts of the daily cycle.
+    pub fn generate(&self, seed: u64, count: usize, end: DateTime<Utc>) -> Vec<MempoolSnapshot> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let interval = Duration::seconds(self.snapshot_interval_secs as i64);
+        let span = interval * count.saturating_sub(1) as i32;
+
+        // Simulated wall time, so the daily cycle does not depend on when the case runs
+        let day_offset = Duration::seconds(rng.random_range(0..86_400));
+        let sim_end = end.date_naive().and_time(Default::default()).and_utc() + day_offset;
+        let first = sim_end - span;
+        let warm_up_start = first - Duration::hours(1);
+
+        let mut simulation = Simulation {
+            model: self,
+            rng,
+            mempool: Vec::new(),
+            height: 850_000,
+            now: warm_up_start,
+            next_block: warm_up_start,
+            last_block: warm_up_start,
+            burst_remaining: 0,
+            spike_until: None,
+        };
+        simulation.schedule_block();
+        simulation.snapshot_at(first);
+
+        let shift = end - sim_end;
+        (0..count)
+            .map(|index| {
+                let time = first + interval * index as i32;
+                if index > 0 && simulation.rng.random_bool(self.spike_probability) {
+                    let previous = time - interval;
+                    simulation.spike_until =
+                        Some(previous + Duration::seconds(self.spike_secs as i64));
+                }
+                let mut snapshot = simulation.snapshot_at(time);
+                snapshot.timestamp += shift;
+                snapshot
+            })
+            .collect()
+    }
+}
+
+/// Reads a trace file of one JSON snapshot per line, sorted by time
+///
+/// Blank lines are skipped.
+pub fn load_trace(path: &Path) -> Result<Vec<MempoolSnapshot>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read trace {path:?}"))?;
+    let mut snapshots = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<MempoolSnapshot>(line).with_context(|| {
+                format!(
+                    "Invalid snapshot on line {line} of {path:?}",
+                    line = index + 1
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(!snapshots.is_empty(), "Trace {path:?} holds no snapshots");
+    snapshots.sort_by_key(|s| s.timestamp);
+    Ok(snapshots)
+}
+
+/// Reads a trace file, or every `.jsonl` trace in a directory in file name order
+pub fn load_corpus(path: &Path) -> Result<Vec<Vec<MempoolSnapshot>>> {
+    if !path.is_dir() {
+        return Ok(vec![load_trace(path)?]);
+    }
+    let mut files = std::fs::read_dir(path)
+        .with_context(|| format!("Failed to read trace corpus {path:?}"))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    files.retain(|file| file.extension().is_some_and(|ext| ext == "jsonl"));
+    files.sort();
+    ensure!(
+        !files.is_empty(),
+        "Trace corpus {path:?} holds no .jsonl files"
+    );
+    files.iter().map(|file| load_trace(file)).collect()
+}
+
+/// A seeded window of up to `max_snapshots` consecutive snapshots of `trace`, shifted in time
+/// so the last one is taken at `end`
+pub fn trace_window(
+    trace: &[MempoolSnapshot],
+    seed: u64,
+    max_snapshots: usize,
+    end: DateTime<Utc>,
+) -> Vec<MempoolSnapshot> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let len = rng.random_range(1..=max_snapshots.clamp(1, trace.len().max(1)));
+    let start = rng.random_range(0..=trace.len().saturating_sub(len));
+    let window = &trace[start..(start + len).min(trace.len())];
+
+    let Some(last) = window.last() else {
+        return Vec::new();
+    };
+    let shift = end - last.timestamp;
+    window
+        .iter()
+        .map(|snapshot| {
+            let mut snapshot = snapshot.clone();
+            snapshot.timestamp += shift;
+            snapshot
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn end() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-01-20T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_generate_is_seeded_and_ends_at_end() {
+        let model = TraceModel::default();
+        let snapshots = model.generate(5, 24, end());
+        assert_eq!(snapshots.len(), 24);
+        assert_eq!(snapshots.last().unwrap().timestamp, end());
+        assert!(snapshots
+            .windows(2)
+            .all(|pair| pair[1].timestamp - pair[0].timestamp == Duration::minutes(5)));
+        assert_eq!(
+            serde_json::to_value(&snapshots).unwrap(),
+            serde_json::to_value(model.generate(5, 24, end())).unwrap()
+        );
+
+        // Two hours of mostly 10 minute blocks
+        let blocks = snapshots.last().unwrap().block_height - snapshots[0].block_height;
+        assert!((2..=40).contains(&blocks), "{blocks} blocks");
+        assert!(snapshots.iter().all(|s| s.total_weight() > 0));
+    }
+
+    #[test]
+    fn test_spikes_raise_fee_rates() {
+        let calm = TraceModel {
+            spike_probability: 0.0,
+            ..TraceModel::default()
+        };
+        let spiking = TraceModel {
+            spike_probability: 1.0,
+            ..TraceModel::default()
+        };
+
+        // Weight above 20 sat/vB, bucket round(ln(20) * 100) = 300
+        let high = |snapshots: &[MempoolSnapshot]| -> u64 {
+            snapshots
+                .iter()
+                .flat_map(|s| s.bucketed_weights.range(300..))
+                .map(|(_, weight)| weight)
+                .sum()
+        };
+        let calm = calm.generate(9, 12, end());
+        let spiking = spiking.generate(9, 12, end());
+        assert!(high(&spiking) > 2 * high(&calm).max(1));
+    }
+
+    #[test]
+    fn test_long_block_intervals_are_followed_by_bursts() {
+        let model = TraceModel {
+            block_interval_secs: 3600.0,
+            burst_after_secs: 0.0,
+            burst_blocks: 1_000,
+            ..TraceModel::default()
+        };
+        // After the first block every interval is a burst block
+        let snapshots = model.generate(1, 24, end());
+        let blocks = snapshots.last().unwrap().block_height - snapshots[0].block_height;
+        assert!(blocks > 50, "{blocks} blocks");
+    }
+
+    #[test]
+    fn test_model_file_defaults_and_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.json");
+        std::fs::write(&path, r#"{"diurnal_amplitude": 0.8}"#).unwrap();
+        let model = TraceModel::from_file(&path).unwrap();
+        assert_eq!(model.diurnal_amplitude, 0.8);
+        assert_eq!(model.snapshot_interval_secs, 300);
+
+        std::fs::write(&path, r#"{"snapshot_interval_secs": 0}"#).unwrap();
+        assert!(TraceModel::from_file(&path).is_err());
+        std::fs::write(&path, r#"{"unknown": 1}"#).unwrap();
+        assert!(TraceModel::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_trace_windows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let recorded = TraceModel::default().generate(3, 30, end() - Duration::days(90));
+        let mut file = std::fs::File::create(&path).unwrap();
+        for snapshot in recorded.iter().rev() {
+            writeln!(file, "{}", serde_json::to_string(snapshot).unwrap()).unwrap();
+            writeln!(file).unwrap();
+        }
+
+        let trace = load_trace(&path).unwrap();
+        assert_eq!(trace.len(), 30);
+        assert!(trace.windows(2).all(|p| p[0].timestamp < p[1].timestamp));
+
+        for seed in 0..20 {
+            let window = trace_window(&trace, seed, 8, end());
+            assert!((1..=8).contains(&window.len()));
+            assert_eq!(window.last().unwrap().timestamp, end());
+            let first = trace
+                .iter()
+                .position(|s| s.bucketed_weights == window[0].bucketed_weights)
+                .unwrap();
+            for (offset, snapshot) in window.iter().enumerate() {
+                assert_eq!(snapshot.block_height, trace[first + offset].block_height);
+            }
+        }
+
+        std::fs::write(&path, "{}\n").unwrap();
+        let error = load_trace(&path).unwrap_err();
+        assert!(format!("{error:#}").contains("line 1"), "{error:#}");
+    }
+
+    #[test]
+    fn test_load_bundled_corpus() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("traces");
+        let traces = load_corpus(&corpus).unwrap();
+        assert!(!traces.is_empty());
+        assert!(traces.iter().all(|trace| trace.len() > 1));
+
+        let single = load_corpus(&corpus.join("synthetic_diurnal.jsonl")).unwrap();
+        assert_eq!(single.len(), 1);
+        assert!(load_corpus(tempfile::tempdir().unwrap().path()).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Upgrade and downgrade compatibility of the data directory between releases
+//!
+//! An earlier release of the server records snapshots from the mock node into an empty data
+//! directory. The current build is then started on a copy of that directory while the node fails
+//! every call, so it must serve the library's estimate for the released snapshots, and its `fsck`
+//! must find every stored file readable once the startup migrations ran. The current build then
+//! records more snapshots on the same directory and must serve the estimate for all of them.
+//!
+//! Finally the release is started on the directory the current build left behind. A release
+//! refuses a data directory format newer than its own, so a failed downgrade is reported as a
+//! warning rather than failing the run.
+
+use anyhow::{ensure, Context, Result};
+use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+use tracing::info;
+
+use crate::compatibility::TestResults;
+use crate::mock_rpc::MockBitcoinRpc;
+use crate::persistence::{keep_recorded, read, record, ParityServer, PersistenceConfig};
+use crate::server::ServerManager;
+
+/// Runs `fsck` of the server at `binary` on `dir`, failing with its output if it finds a
+/// problem
+async fn fsck(binary: &Path, dir: &Path) -> Result<String> {
+    let output = Command::new(binary)
+        .arg("--data-dir")
+        .arg(dir)
+        .arg("fsck")
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {binary:?} fsck"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    ensure!(
+        output.status.success(),
+        "fsck exited with {status}: {stdout}{stderr}",
+        status = output.status,
+        stderr = String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(stdout.lines().last().unwrap_or_default().to_string())
+}
+
+/// Has the `released` server record a data directory, upgrades it with the `current` build and
+/// downgrades it again, checking the estimates served at every step
+pub async fn run_upgrade(
+    rpc: &MockBitcoinRpc,
+    released: ServerManager,
+    current: ServerManager,
+    config: &PersistenceConfig,
+) -> Result<TestResults> {
+    ensure!(config.snapshots > 0, "Every writer must record a snapshot");
+    config.tolerance.validate().map_err(anyhow::Error::msg)?;
+    let mut results = TestResults::new();
+
+    if config.output.exists() {
+        fs::remove_dir_all(&config.output)
+            .with_context(|| format!("Failed to clear {output:?}", output = config.output))?;
+    }
+
+    let binary = current.binary_path().to_path_buf();
+    let mut released = ParityServer::Released(Box::new(released));
+    let mut current = ParityServer::Rust(Box::new(current));
+
+    info!(
+        "Recording {count} snapshots with the {released}",
+        count = config.snapshots
+    );
+    released.reset_data(None)?;
+    record(&mut released, rpc, config.snapshots)
+        .await
+        .with_context(|| format!("{released} failed to record snapshots"))?;
+    let released_dir = config.output.join("released");
+    keep_recorded(&released, &released_dir, &mut results)?;
+
+    // Reading leaves the migrated copy in the data directory of the current build
+    let test = format!("{current} reads the {released} data directory");
+    info!("Checking that the {current} reads the {released} data directory");
+    match read(
+        &mut current,
+        rpc,
+        &released_dir,
+        config,
+        &mut results,
+        &test,
+    )
+    .await
+    {
+        Ok(()) => results.add_pass(&test, "Served the library's estimate"),
+        Err(e) => results.add_fail(&test, &format!("{e:#}")),
+    }
+
+    let test = format!("{current} finds the upgraded data directory intact");
+    match fsck(&binary, &current.snapshot_dir()).await {
+        Ok(summary) => results.add_pass(&test, &summary),
+        Err(e) => results.add_fail(&test, &format!("{e:#}")),
+    }
+
+    info!(
+        "Recording {count} more snapshots with the {current}",
+        count = config.snapshots
+    );
+    record(&mut current, rpc, config.snapshots)
+        .await
+        .with_context(|| format!("{current} failed to record on the upgraded data directory"))?;
+    let upgraded_dir = config.output.join("upgraded");
+    keep_recorded(&current, &upgraded_dir, &mut results)?;
+
+    let test = format!("{current} reads the upgraded data directory");
+    match read(
+        &mut current,
+        rpc,
+        &upgraded_dir,
+        config,
+        &mut results,
+        &test,
+    )
+    .await
+    {
+        Ok(()) => results.add_pass(&test, "Served the library's estimate"),
+        Err(e) => results.add_fail(&test, &format!("{e:#}")),
+    }
+
+    let test = format!("{released} reads the upgraded data directory");
+    info!("Checking that the {released} reads the upgraded data directory");
+    match read(
+        &mut released,
+        rpc,
+        &upgraded_dir,
+        config,
+        &mut results,
+        &test,
+    )
+    .await
+    {
+        Ok(()) => results.add_pass(&test, "Served the library's estimate"),
+        Err(e) => results.add_warning(&test, &format!("Cannot downgrade: {e:#}")),
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fsck_reports_the_failing_output() {
+        let dir = tempfile::tempdir().unwrap();
+        // `echo` prints the arguments and succeeds, `false` fails without output
+        let summary = fsck(Path::new("echo"), dir.path()).await.unwrap();
+        assert!(summary.ends_with("fsck"), "{summary}");
+
+        let error = fsck(Path::new("false"), dir.path()).await.unwrap_err();
+        assert!(error.to_string().starts_with("fsck exited with"), "{error}");
+    }
+}
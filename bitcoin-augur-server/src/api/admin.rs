@@ -0,0 +1,55 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::auth::require_bearer_token;
+use super::error::ApiError;
+use crate::server::AppState;
+use crate::service::CycleRecord;
+
+/// Cycles returned when the request does not ask for a number
+const DEFAULT_CYCLE_LIMIT: usize = 100;
+
+/// Query parameters for the cycle log endpoint
+#[derive(Debug, Deserialize)]
+pub struct CyclesQuery {
+    /// Most cycles to return, newest first
+    limit: Option<usize>,
+}
+
+/// Response of `GET /admin/cycles`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CyclesResponse {
+    /// Latest collection cycles, newest first
+    pub cycles: Vec<CycleRecord>,
+}
+
+/// GET /admin/cycles?limit={n} - Returns how long each step of the latest collection cycles took
+pub async fn get_cycles(
+    Query(CyclesQuery { limit }): Query<CyclesQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<CyclesResponse>, ApiError> {
+    require_bearer_token(&headers, state.api.admin_token.as_deref())?;
+
+    let limit = limit.unwrap_or(DEFAULT_CYCLE_LIMIT);
+    if limit == 0 {
+        return Err(ApiError::BadRequest(
+            "Invalid limit: must be at least 1".to_string(),
+        ));
+    }
+    info!("Received request for the latest {limit} collection cycles");
+
+    if !state.collector.has_cycle_log() {
+        return Err(ApiError::NotFound(
+            "Collection cycles are not logged (collector.cycle_log_size is 0)".to_string(),
+        ));
+    }
+    Ok(Json(CyclesResponse {
+        cycles: state.collector.recent_cycles(limit),
+    }))
+}
@@ -0,0 +1,65 @@
+use axum::http::{header, HeaderMap};
+
+use super::error::ApiError;
+
+/// Checks that the request carries `Authorization: Bearer <expected>`
+///
+/// A missing expected token means the endpoint has no credentials configured, so every
+/// request is rejected rather than silently allowed.
+pub fn require_bearer_token(headers: &HeaderMap, expected: Option<&str>) -> Result<(), ApiError> {
+    let expected = expected
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| ApiError::Unauthorized("No API token configured".to_string()))?;
+
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("Missing bearer token".to_string()))?;
+
+    if constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized("Invalid bearer token".to_string()))
+    }
+}
+
+/// Compares two byte strings without short-circuiting on the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers_with(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_valid_token_accepted() {
+        let headers = headers_with("Bearer secret");
+        assert!(require_bearer_token(&headers, Some("secret")).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_or_missing_token_rejected() {
+        assert!(require_bearer_token(&headers_with("Bearer wrong"), Some("secret")).is_err());
+        assert!(require_bearer_token(&headers_with("secret"), Some("secret")).is_err());
+        assert!(require_bearer_token(&HeaderMap::new(), Some("secret")).is_err());
+    }
+
+    #[test]
+    fn test_unconfigured_token_rejects_everything() {
+        let headers = headers_with("Bearer ");
+        assert!(require_bearer_token(&headers, None).is_err());
+        assert!(require_bearer_token(&headers, Some("")).is_err());
+    }
+}
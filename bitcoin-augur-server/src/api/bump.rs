@@ -0,0 +1,234 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bitcoin_augur::{cpfp_child_fee, rbf_replacement_fee, Package};
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::error::ApiError;
+use super::suggest::{default_confidence, suggest};
+use crate::service::MempoolCollector;
+
+/// Size of a child spending one P2WPKH output to one P2WPKH output
+const DEFAULT_CHILD_VSIZE: u64 = 110;
+
+/// Query parameters for the CPFP bump endpoint
+#[derive(Debug, Deserialize)]
+pub struct CpfpQuery {
+    /// The stuck transaction the child spends from
+    parent_txid: String,
+    /// Blocks within which the package should confirm
+    target: u32,
+    /// Minimum confidence of confirming within the target
+    #[serde(default = "default_confidence")]
+    confidence: f64,
+    /// Size of the child in virtual bytes
+    #[serde(default = "default_child_vsize")]
+    child_vsize: u64,
+}
+
+fn default_child_vsize() -> u64 {
+    DEFAULT_CHILD_VSIZE
+}
+
+/// A group of mempool transactions in a bump response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageResponse {
+    pub count: u64,
+    pub vsize: u64,
+    /// Total fee in satoshis
+    pub fee: u64,
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+}
+
+impl From<Package> for PackageResponse {
+    fn from(package: Package) -> Self {
+        Self {
+            count: package.count,
+            vsize: package.vsize,
+            fee: package.fee,
+            fee_rate: format_fee_rate(package.fee_rate()),
+        }
+    }
+}
+
+/// Response of `GET /bump/cpfp`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CpfpResponse {
+    pub mempool_update_time: String,
+    pub parent_txid: String,
+    /// Block target the fee rate was taken from
+    pub target_blocks: u32,
+    /// Confidence level the fee rate was taken from
+    pub confidence: f64,
+    /// Package fee rate to reach, in sat/vB
+    pub fee_rate: f64,
+    /// The parent and its unconfirmed ancestors as they are now
+    pub ancestors: PackageResponse,
+    pub child_vsize: u64,
+    /// Fee the child must pay, in satoshis
+    pub child_fee: u64,
+    /// The ancestors together with the child
+    pub package: PackageResponse,
+}
+
+/// Query parameters for the RBF bump endpoint
+#[derive(Debug, Deserialize)]
+pub struct RbfQuery {
+    /// The transaction to replace
+    txid: String,
+    /// Blocks within which the replacement should confirm
+    target: u32,
+    /// Minimum confidence of confirming within the target
+    #[serde(default = "default_confidence")]
+    confidence: f64,
+    /// Size of the replacement in virtual bytes, the original's size if omitted
+    #[serde(default)]
+    vsize: Option<u64>,
+}
+
+/// Response of `GET /bump/rbf`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RbfResponse {
+    pub mempool_update_time: String,
+    pub txid: String,
+    /// Block target the fee rate was taken from
+    pub target_blocks: u32,
+    /// Confidence level the fee rate was taken from
+    pub confidence: f64,
+    /// Fee rate the replacement should pay, in sat/vB
+    pub fee_rate: f64,
+    /// The transaction being replaced
+    pub original: PackageResponse,
+    /// The transaction and its descendants, all evicted by the replacement
+    pub evicted: PackageResponse,
+    /// The node's incremental relay fee, in sat/vB
+    pub incremental_relay_fee: f64,
+    pub replacement_vsize: u64,
+    /// Fee the fee rate asks for at the replacement's size
+    pub target_fee: u64,
+    /// Lowest fee the BIP125 replacement rules accept
+    pub relay_minimum_fee: u64,
+    /// Fee the replacement must pay, in satoshis
+    pub fee: u64,
+    /// The replacement's resulting fee rate, in sat/vB
+    pub replacement_fee_rate: f64,
+}
+
+/// Rejects anything but a 32 byte hex transaction id
+fn validate_txid(txid: &str) -> Result<(), ApiError> {
+    if txid.len() != 64 || !txid.bytes().all(|b| b.is_ascii_hexdigit()) {
+        warn!("Invalid txid parameter: {txid}");
+        return Err(ApiError::BadRequest(
+            "Invalid txid: must be 64 hex characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /bump/cpfp?parent_txid={txid}&target={blocks}&confidence={p} - Returns the fee a child
+/// must pay for a stuck transaction and its ancestors to confirm within the target
+pub async fn get_cpfp_bump(
+    Query(params): Query<CpfpQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<CpfpResponse>, ApiError> {
+    let CpfpQuery {
+        parent_txid,
+        target,
+        confidence,
+        child_vsize,
+    } = params;
+    validate_txid(&parent_txid)?;
+
+    info!("Received CPFP bump request for {parent_txid} within {target} blocks");
+
+    let (timestamp, suggestion) = suggest(&collector, child_vsize, target, confidence).await?;
+    let entry = collector
+        .get_mempool_entry(&parent_txid)
+        .await?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Transaction {parent_txid} is not in the mempool"))
+        })?;
+
+    let bump = cpfp_child_fee(entry.ancestors, child_vsize, suggestion.fee_rate);
+
+    Ok(Json(CpfpResponse {
+        mempool_update_time: format_timestamp(timestamp),
+        parent_txid,
+        target_blocks: suggestion.target_blocks,
+        confidence: suggestion.confidence,
+        fee_rate: format_fee_rate(suggestion.fee_rate),
+        ancestors: entry.ancestors.into(),
+        child_vsize,
+        child_fee: bump.child_fee,
+        package: bump.package.into(),
+    }))
+}
+
+/// GET /bump/rbf?txid={txid}&target={blocks}&confidence={p} - Returns the lowest fee a
+/// replacement must pay to confirm within the target and be relayed under BIP125
+pub async fn get_rbf_bump(
+    Query(params): Query<RbfQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<RbfResponse>, ApiError> {
+    let RbfQuery {
+        txid,
+        target,
+        confidence,
+        vsize,
+    } = params;
+    validate_txid(&txid)?;
+
+    info!("Received RBF bump request for {txid} within {target} blocks");
+
+    let entry = collector
+        .get_mempool_entry(&txid)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Transaction {txid} is not in the mempool")))?;
+    let replacement_vsize = vsize.unwrap_or(entry.tx.vsize);
+
+    let (timestamp, suggestion) =
+        suggest(&collector, replacement_vsize, target, confidence).await?;
+    let incremental_relay_fee = collector.get_incremental_relay_fee().await?;
+
+    let bump = rbf_replacement_fee(
+        entry.tx,
+        entry.descendants,
+        replacement_vsize,
+        suggestion.fee_rate,
+        incremental_relay_fee,
+    );
+
+    Ok(Json(RbfResponse {
+        mempool_update_time: format_timestamp(timestamp),
+        txid,
+        target_blocks: suggestion.target_blocks,
+        confidence: suggestion.confidence,
+        fee_rate: format_fee_rate(suggestion.fee_rate),
+        original: entry.tx.into(),
+        evicted: entry.descendants.into(),
+        incremental_relay_fee,
+        replacement_vsize,
+        target_fee: bump.target_fee,
+        relay_minimum_fee: bump.relay_minimum_fee,
+        fee: bump.fee,
+        replacement_fee_rate: format_fee_rate(bump.fee as f64 / replacement_vsize as f64),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_txid() {
+        assert!(validate_txid(&"ab".repeat(32)).is_ok());
+        assert!(validate_txid(&"AB".repeat(32)).is_ok());
+        assert!(validate_txid("abcd").is_err());
+        assert!(validate_txid(&"zz".repeat(32)).is_err());
+    }
+}
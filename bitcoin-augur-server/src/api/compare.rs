@@ -0,0 +1,71 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bitcoin_augur_api_types::{
+    transform_fee_estimate, FeeComparisonResponse, SourceComparisonResponse,
+};
+use std::sync::Arc;
+use tracing::info;
+
+use super::error::ApiError;
+use super::fee_endpoint::current_response;
+use crate::service::MempoolCollector;
+
+/// GET /fees/compare - Returns the current estimates next to each baseline strategy's
+pub async fn get_fee_comparison(
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Response, ApiError> {
+    info!("Received request for fee estimate comparison");
+
+    let Some(estimate) = collector.get_latest_estimate().await else {
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No fee estimates available yet".to_string()),
+        });
+    };
+
+    let baselines = collector
+        .baseline_estimates()
+        .await
+        .into_iter()
+        .map(|(name, estimate)| (name, transform_fee_estimate(estimate)))
+        .collect();
+    let response = FeeComparisonResponse {
+        augur: current_response(&collector, estimate).await,
+        baselines,
+    };
+    Ok(Json(response).into_response())
+}
+
+/// GET /fees/sources - Returns an estimate from each snapshot source's snapshots alone, next to
+/// their median, to spot a node whose mempool policy skews the results
+pub async fn get_source_comparison(
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<SourceComparisonResponse>, ApiError> {
+    info!("Received request for snapshot source comparison");
+
+    let estimates = collector.get_source_estimates().await?;
+    if estimates.by_source.is_empty() {
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No snapshots available yet".to_string()),
+        });
+    }
+
+    let deviations = estimates
+        .by_source
+        .keys()
+        .filter_map(|source| Some((source.clone(), estimates.deviation(source)?)))
+        .collect();
+    Ok(Json(SourceComparisonResponse {
+        combined: transform_fee_estimate(estimates.combined),
+        sources: estimates
+            .by_source
+            .into_iter()
+            .map(|(source, estimate)| (source, transform_fee_estimate(estimate)))
+            .collect(),
+        deviations,
+    }))
+}
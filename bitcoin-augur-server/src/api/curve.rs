@@ -0,0 +1,195 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bitcoin_augur::{AugurError, BucketWeighting};
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::error::ApiError;
+use super::fee_endpoint::MAX_TARGET_BLOCKS;
+use crate::service::{CollectorError, MempoolCollector};
+
+/// Confidence levels the curve is sampled at: every 5% from 5% to 95%, then 99%
+fn confidence_grid() -> Vec<f64> {
+    // Whole percentages divide exactly, so each level matches its key in the estimate
+    (1..=19)
+        .map(|step| f64::from(step * 5))
+        .chain([99.0])
+        .map(|percent| percent / 100.0)
+        .collect()
+}
+
+/// Block targets the target curve is sampled at, every block from the shortest simulated target
+/// to a day
+const CURVE_TARGETS: RangeInclusive<u32> = 3..=144;
+
+/// Query parameters for the confidence curve endpoint
+#[derive(Debug, Deserialize)]
+pub struct ConfidenceCurveQuery {
+    /// Block target in blocks
+    target: f64,
+    /// What each fee rate bucket's share of the mempool is measured in
+    #[serde(default)]
+    weighting: BucketWeighting,
+}
+
+/// Fee rate needed at one confidence level
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfidencePoint {
+    pub confidence: f64,
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+}
+
+/// Response of `GET /fees/curve`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfidenceCurveResponse {
+    pub mempool_update_time: String,
+    /// Block target as requested, which may be a fractional number of blocks
+    pub target_blocks: f64,
+    pub weighting: BucketWeighting,
+    /// Fee rate at each sampled confidence level, lowest confidence first
+    pub curve: Vec<ConfidencePoint>,
+}
+
+/// GET /fees/curve?target={num_blocks}&weighting={weight|value} - Returns fee rate as a function
+/// of confidence for one block target, so clients can pick their own risk tolerance
+pub async fn get_confidence_curve(
+    Query(ConfidenceCurveQuery { target, weighting }): Query<ConfidenceCurveQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<ConfidenceCurveResponse>, ApiError> {
+    info!("Received confidence curve request for {target} blocks by {weighting}");
+
+    if !target.is_finite() || target <= 0.0 || target > MAX_TARGET_BLOCKS {
+        warn!("Invalid target parameter: {target}");
+        return Err(ApiError::InvalidTarget(format!(
+            "Invalid target: must be a number of blocks up to {MAX_TARGET_BLOCKS}"
+        )));
+    }
+
+    let estimate = collector
+        .get_confidence_curve(target, confidence_grid(), weighting)
+        .await
+        .map_err(|err| match err {
+            CollectorError::EstimationError(AugurError::InvalidParameter(msg)) => {
+                ApiError::InvalidTarget(msg)
+            }
+            err => err.into(),
+        })?;
+    let Some((target_blocks, block_target)) = estimate.targets().next() else {
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No fee estimates available yet".to_string()),
+        });
+    };
+
+    let curve = block_target
+        .probabilities
+        .iter()
+        .map(|(confidence, &fee_rate)| ConfidencePoint {
+            confidence: confidence.0,
+            fee_rate: format_fee_rate(fee_rate),
+        })
+        .collect();
+
+    Ok(Json(ConfidenceCurveResponse {
+        mempool_update_time: format_timestamp(estimate.timestamp),
+        target_blocks: target_blocks.target(),
+        weighting,
+        curve,
+    }))
+}
+
+/// Query parameters for the target curve endpoint
+#[derive(Debug, Deserialize)]
+pub struct TargetCurveQuery {
+    /// Confidence level between 0 and 1
+    confidence: f64,
+    /// What each fee rate bucket's share of the mempool is measured in
+    #[serde(default)]
+    weighting: BucketWeighting,
+}
+
+/// Fee rate needed at one block target
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TargetPoint {
+    pub blocks: f64,
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+}
+
+/// Response of `GET /fees/targets`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TargetCurveResponse {
+    pub mempool_update_time: String,
+    pub confidence: f64,
+    pub weighting: BucketWeighting,
+    /// Fee rate at each block target, shortest first
+    pub curve: Vec<TargetPoint>,
+}
+
+/// GET /fees/targets?confidence={p}&weighting={weight|value} - Returns fee rate as a function of
+/// the block target at one confidence level, for every target from 3 to 144 blocks
+pub async fn get_target_curve(
+    Query(TargetCurveQuery {
+        confidence,
+        weighting,
+    }): Query<TargetCurveQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<TargetCurveResponse>, ApiError> {
+    info!("Received target curve request at {confidence} confidence by {weighting}");
+
+    if !(0.0..=1.0).contains(&confidence) {
+        warn!("Invalid confidence parameter: {confidence}");
+        return Err(ApiError::BadRequest(
+            "Invalid confidence: must be between 0 and 1".to_string(),
+        ));
+    }
+
+    let block_targets = CURVE_TARGETS.map(f64::from).collect();
+    let estimate = collector
+        .get_target_curve(confidence, block_targets, weighting)
+        .await?;
+    if estimate.is_empty() {
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No fee estimates available yet".to_string()),
+        });
+    }
+
+    let curve = estimate
+        .targets()
+        .filter_map(|(blocks, target)| {
+            target.get_fee_rate(confidence).map(|fee_rate| TargetPoint {
+                blocks: blocks.target(),
+                fee_rate: format_fee_rate(fee_rate),
+            })
+        })
+        .collect();
+
+    Ok(Json(TargetCurveResponse {
+        mempool_update_time: format_timestamp(estimate.timestamp),
+        confidence,
+        weighting,
+        curve,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confidence_grid() {
+        let grid = confidence_grid();
+        assert_eq!(grid.len(), 20);
+        assert_eq!(grid.first(), Some(&0.05));
+        assert_eq!(grid.last(), Some(&0.99));
+        assert!(grid.contains(&0.2));
+        assert!(grid.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
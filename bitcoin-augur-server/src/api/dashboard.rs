@@ -0,0 +1,10 @@
+use axum::response::Html;
+
+/// The dashboard page, with its styles and scripts inline so the binary serves it on its own
+const DASHBOARD_PAGE: &str = include_str!("../../assets/dashboard.html");
+
+/// GET /ui - Serves a page showing the current fee estimates, the latest mempool by projected
+/// block and the median fee rates over the past day, all read from the public endpoints
+pub async fn get_dashboard() -> Html<&'static str> {
+    Html(DASHBOARD_PAGE)
+}
@@ -0,0 +1,219 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bitcoin_augur::{BucketResidual, SimulationRun};
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::info;
+
+use super::error::ApiError;
+use super::suggest::default_confidence;
+use crate::evaluation::CalibrationCell;
+use crate::service::{MempoolCollector, RealizedBlock};
+
+/// Realized blocks returned when the request does not ask for a number
+const DEFAULT_BLOCK_LIMIT: usize = 10;
+
+/// Query parameters for the simulation debug endpoint
+#[derive(Debug, Deserialize)]
+pub struct SimulationQuery {
+    /// Block target to simulate
+    target: u32,
+    /// Confidence level to simulate
+    #[serde(default = "default_confidence")]
+    confidence: f64,
+}
+
+/// Weight of one fee bucket before and after mining, in weight units
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BucketResponse {
+    /// Fee rate bucket, 100 × ln(sat/vB)
+    pub bucket: i32,
+    /// Lowest fee rate of the bucket in sat/vB
+    pub fee_rate: f64,
+    /// Weight waiting when mining starts
+    pub initial_weight: u64,
+    /// Weight arriving while the blocks are mined
+    pub added_weight: u64,
+    /// Weight left after the blocks are mined
+    pub remaining_weight: u64,
+}
+
+impl From<&BucketResidual> for BucketResponse {
+    fn from(residual: &BucketResidual) -> Self {
+        Self {
+            bucket: residual.bucket,
+            fee_rate: format_fee_rate(residual.fee_rate),
+            initial_weight: residual.initial.round() as u64,
+            added_weight: residual.added.round() as u64,
+            remaining_weight: residual.remaining.round() as u64,
+        }
+    }
+}
+
+/// One simulated mining run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulationRunResponse {
+    /// Lowest fee rate fully mined, in sat/vB
+    pub fee_rate: Option<f64>,
+    /// Buckets holding any weight, highest fee rate first
+    pub buckets: Vec<BucketResponse>,
+}
+
+impl From<&SimulationRun> for SimulationRunResponse {
+    fn from(run: &SimulationRun) -> Self {
+        Self {
+            fee_rate: run.fee_rate.map(format_fee_rate),
+            buckets: run.buckets.iter().map(BucketResponse::from).collect(),
+        }
+    }
+}
+
+/// Response of `GET /debug/simulation`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulationResponse {
+    pub mempool_update_time: String,
+    pub target_blocks: u32,
+    pub confidence: f64,
+    /// Blocks mined in each run
+    pub expected_blocks: usize,
+    /// Weight of the long-term run in the blended fee rate
+    pub long_term_weight: f64,
+    /// Blended fee rate in sat/vB, before monotonicity, rounding and capping
+    pub fee_rate: Option<f64>,
+    pub short_term: SimulationRunResponse,
+    pub long_term: SimulationRunResponse,
+}
+
+/// GET /debug/simulation?target={blocks}&confidence={p} - Returns the simulated weight left in
+/// each fee bucket behind the estimate for a target and confidence level
+pub async fn get_simulation(
+    Query(params): Query<SimulationQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<SimulationResponse>, ApiError> {
+    let SimulationQuery { target, confidence } = params;
+    info!("Received simulation request for {target} blocks at confidence {confidence}");
+
+    let simulation = collector.simulate(f64::from(target), confidence).await?;
+
+    Ok(Json(SimulationResponse {
+        mempool_update_time: format_timestamp(simulation.timestamp),
+        target_blocks: target,
+        confidence,
+        expected_blocks: simulation.expected_blocks,
+        long_term_weight: simulation.long_term_weight,
+        fee_rate: simulation.fee_rate.map(format_fee_rate),
+        short_term: (&simulation.short_term).into(),
+        long_term: (&simulation.long_term).into(),
+    }))
+}
+
+/// Query parameters for the realized blocks debug endpoint
+#[derive(Debug, Deserialize)]
+pub struct BlocksQuery {
+    /// Most blocks to return, newest first
+    limit: Option<usize>,
+}
+
+/// What one mined block confirmed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RealizedBlockResponse {
+    pub height: u32,
+    pub time: String,
+    /// Lowest fee rate confirmed in sat/vB, leaving out the cheapest tail of the block
+    pub threshold: Option<f64>,
+    pub transactions: usize,
+    /// Weight confirmed, in weight units
+    pub weight: u64,
+    /// Weight confirmed in each fee rate bucket, 100 × ln(sat/vB)
+    pub buckets: BTreeMap<i32, u64>,
+}
+
+impl From<RealizedBlock> for RealizedBlockResponse {
+    fn from(block: RealizedBlock) -> Self {
+        Self {
+            height: block.height,
+            time: format_timestamp(block.time),
+            threshold: block.threshold.map(format_fee_rate),
+            transactions: block.transactions,
+            weight: block.weight(),
+            buckets: block.bucketed_weights,
+        }
+    }
+}
+
+/// How estimates for one target and confidence level fared against realized blocks
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CalibrationResponse {
+    /// Estimates whose target has been realized
+    pub samples: usize,
+    /// Estimates whose fee rate a realized block confirmed within the target
+    pub confirmed: usize,
+    pub hit_rate: f64,
+    /// Mean estimated fee rate in sat/vB
+    pub mean_fee_rate: f64,
+}
+
+impl From<&CalibrationCell> for CalibrationResponse {
+    fn from(cell: &CalibrationCell) -> Self {
+        Self {
+            samples: cell.samples,
+            confirmed: cell.confirmed,
+            hit_rate: cell.hit_rate(),
+            mean_fee_rate: format_fee_rate(cell.mean_fee_rate()),
+        }
+    }
+}
+
+/// Response of `GET /debug/blocks`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlocksResponse {
+    /// Latest realized blocks, newest first
+    pub blocks: Vec<RealizedBlockResponse>,
+    /// Estimates scored since startup, by confidence level and block target
+    pub calibration: BTreeMap<String, BTreeMap<u32, CalibrationResponse>>,
+}
+
+/// GET /debug/blocks?limit={n} - Returns the fee rates the latest blocks actually confirmed and
+/// how the estimates made before them fared
+pub async fn get_realized_blocks(
+    Query(BlocksQuery { limit }): Query<BlocksQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<BlocksResponse>, ApiError> {
+    let limit = limit.unwrap_or(DEFAULT_BLOCK_LIMIT);
+    if limit == 0 {
+        return Err(ApiError::BadRequest(
+            "Invalid limit: must be at least 1".to_string(),
+        ));
+    }
+    info!("Received request for the latest {limit} realized blocks");
+
+    let Some(feedback) = collector.block_feedback() else {
+        return Err(ApiError::NotFound(
+            "Blocks are not realized (collector.block_feedback is off)".to_string(),
+        ));
+    };
+    let calibration = feedback
+        .calibration()
+        .iter()
+        .map(|(confidence, cells)| {
+            let cells = cells
+                .iter()
+                .map(|(&target, cell)| (target, cell.into()))
+                .collect();
+            (confidence.clone(), cells)
+        })
+        .collect();
+
+    Ok(Json(BlocksResponse {
+        blocks: feedback
+            .recent(limit)
+            .into_iter()
+            .map(RealizedBlockResponse::from)
+            .collect(),
+        calibration,
+    }))
+}
@@ -0,0 +1,55 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bitcoin_augur::WU_PER_BYTE;
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use super::error::ApiError;
+use crate::service::MempoolCollector;
+
+/// Query parameters for the mempool depth endpoint
+#[derive(Debug, Deserialize)]
+pub struct DepthQuery {
+    /// Depth into the mempool in virtual bytes, counted from the highest fee rate down
+    vsize: u64,
+}
+
+/// Response of `GET /mempool/depth`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MempoolDepthResponse {
+    pub mempool_update_time: String,
+    pub block_height: u32,
+    pub vsize: u64,
+    /// Virtual size of the whole mempool
+    pub mempool_vsize: u64,
+    /// Fee rate in sat/vB `vsize` deep, or null when the mempool is not that deep
+    pub fee_rate: Option<f64>,
+}
+
+/// GET /mempool/depth?vsize={vbytes} - Returns the fee rate of the transactions `vsize` deep in
+/// the latest mempool snapshot, such as what it takes to be in the next block at 1000000
+pub async fn get_mempool_depth(
+    Query(DepthQuery { vsize }): Query<DepthQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<MempoolDepthResponse>, ApiError> {
+    info!("Received mempool depth request for {vsize} vB");
+
+    let Some(snapshot) = collector.get_latest_snapshot().await else {
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No mempool snapshot available yet".to_string()),
+        });
+    };
+
+    Ok(Json(MempoolDepthResponse {
+        mempool_update_time: format_timestamp(snapshot.timestamp),
+        block_height: snapshot.block_height,
+        vsize,
+        mempool_vsize: (snapshot.total_weight() as f64 / WU_PER_BYTE).ceil() as u64,
+        fee_rate: snapshot.fee_rate_at_depth(vsize).map(format_fee_rate),
+    }))
+}
@@ -1,16 +1,28 @@
 use axum::{
-    http::StatusCode,
+    body::to_bytes,
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
-use serde::{Deserialize, Serialize};
+use bitcoin_augur_api_types::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE};
 use thiserror::Error;
+use tracing::warn;
 
-/// JSON error response structure
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub message: String,
+/// Largest error body the problem middleware reads to use as the detail
+const MAX_DETAIL_BYTES: usize = 16 * 1024;
+
+/// Problem details response for `status`, titled with its reason phrase
+fn problem_response(status: StatusCode, code: ErrorCode, detail: impl Into<String>) -> Response {
+    let title = status.canonical_reason().unwrap_or("Error");
+    let problem = Problem::new(status.as_u16(), title, code, detail);
+    let mut response = (status, Json(problem)).into_response();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(PROBLEM_CONTENT_TYPE),
+    );
+    response
 }
 
 /// API-specific error types with proper HTTP status code mapping
@@ -20,38 +32,110 @@ pub enum ApiError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
-    /// Service unavailable - temporary issue (503)
-    #[error("Service unavailable: {0}")]
-    ServiceUnavailable(String),
+    /// Block target outside what the server estimates for (400)
+    #[error("Invalid target: {0}")]
+    InvalidTarget(String),
+
+    /// Missing or invalid credentials (401)
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// Requested resource does not exist (404)
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// No data for the requested time or target (404)
+    #[error("No data: {0}")]
+    NoData(String),
+
+    /// More distinct block targets requested than the server estimates per minute (429)
+    #[error("Too many targets: {0}")]
+    TooManyTargets(String),
+
+    /// No estimate computed yet (503)
+    #[error("Warming up: {0}")]
+    WarmingUp(String),
+
+    /// Bitcoin Core is unreachable (503)
+    #[error("Bitcoin RPC unavailable: {0}")]
+    UpstreamRpcDown(String),
+
+    /// The latest snapshot is older than the maximum staleness (503)
+    #[error("Stale data: {0}")]
+    StaleData(String),
+
+    /// The price feed is unreachable and no price is cached (503)
+    #[error("Price feed unavailable: {0}")]
+    UpstreamPriceFeedDown(String),
+
+    /// The server is stopping (503)
+    #[error("Shutting down: {0}")]
+    ShuttingDown(String),
 
     /// Internal server error - unexpected failure (500)
     #[error("Internal server error: {0}")]
     InternalError(String),
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, error_type, message) = match self {
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg),
-            ApiError::ServiceUnavailable(msg) => {
-                (StatusCode::SERVICE_UNAVAILABLE, "service_unavailable", msg)
+impl ApiError {
+    /// HTTP status and error code of the response
+    fn status_and_code(&self) -> (StatusCode, ErrorCode) {
+        match self {
+            ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest),
+            ApiError::InvalidTarget(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidTarget),
+            ApiError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized),
+            ApiError::NotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::NotFound),
+            ApiError::NoData(_) => (StatusCode::NOT_FOUND, ErrorCode::NoData),
+            ApiError::TooManyTargets(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, ErrorCode::TooManyTargets)
             }
-            ApiError::InternalError(msg) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", msg)
+            ApiError::WarmingUp(_) => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::WarmingUp),
+            ApiError::UpstreamRpcDown(_) => {
+                (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::UpstreamRpcDown)
             }
-        };
+            ApiError::StaleData(_) => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::StaleData),
+            ApiError::UpstreamPriceFeedDown(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorCode::UpstreamPriceFeedDown,
+            ),
+            ApiError::ShuttingDown(_) => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::ShuttingDown),
+            ApiError::InternalError(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::InternalError)
+            }
+        }
+    }
+}
 
-        let body = Json(ErrorResponse {
-            error: error_type.to_string(),
-            message,
-        });
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, code) = self.status_and_code();
+        let detail = match self {
+            ApiError::BadRequest(msg)
+            | ApiError::InvalidTarget(msg)
+            | ApiError::Unauthorized(msg)
+            | ApiError::NotFound(msg)
+            | ApiError::NoData(msg)
+            | ApiError::TooManyTargets(msg)
+            | ApiError::WarmingUp(msg)
+            | ApiError::UpstreamRpcDown(msg)
+            | ApiError::StaleData(msg)
+            | ApiError::UpstreamPriceFeedDown(msg)
+            | ApiError::ShuttingDown(msg)
+            | ApiError::InternalError(msg) => msg,
+        };
 
-        (status, body).into_response()
+        problem_response(status, code, detail)
     }
 }
 
 impl From<crate::service::CollectorError> for ApiError {
     fn from(err: crate::service::CollectorError) -> Self {
+        ApiError::from(&err)
+    }
+}
+
+impl From<&crate::service::CollectorError> for ApiError {
+    fn from(err: &crate::service::CollectorError) -> Self {
         use crate::service::CollectorError;
 
         match err {
@@ -59,10 +143,12 @@ impl From<crate::service::CollectorError> for ApiError {
             CollectorError::EstimationError(augur_err) => {
                 match augur_err {
                     // Invalid parameters are client errors (400)
-                    bitcoin_augur::AugurError::InvalidParameter(msg) => ApiError::BadRequest(msg),
-                    // Insufficient data is a temporary issue (503)
+                    bitcoin_augur::AugurError::InvalidParameter(msg) => {
+                        ApiError::BadRequest(msg.clone())
+                    }
+                    // Too few snapshots to estimate from yet (503)
                     bitcoin_augur::AugurError::InsufficientData(msg) => {
-                        ApiError::ServiceUnavailable(msg)
+                        ApiError::WarmingUp(msg.clone())
                     }
                     // Other errors are internal server errors (500)
                     _ => ApiError::InternalError(format!("Estimation error: {augur_err}")),
@@ -70,16 +156,153 @@ impl From<crate::service::CollectorError> for ApiError {
             }
             // RPC errors are usually temporary issues
             CollectorError::RpcError(err) => {
-                ApiError::ServiceUnavailable(format!("Bitcoin RPC error: {err}"))
+                ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {err}"))
             }
             // Persistence errors are internal issues
             CollectorError::PersistenceError(err) => {
                 ApiError::InternalError(format!("Storage error: {err}"))
             }
+            CollectorError::TaskFailed(err) => {
+                ApiError::InternalError(format!("Estimation task failed: {err}"))
+            }
+            CollectorError::TooManyTargets(msg) => ApiError::TooManyTargets(msg.clone()),
             // Shutdown is a service unavailable issue
             CollectorError::Shutdown => {
-                ApiError::ServiceUnavailable("Service is shutting down".to_string())
+                ApiError::ShuttingDown("Service is shutting down".to_string())
             }
+            // Requests sharing a computation get the error it failed with
+            CollectorError::Coalesced(err) => ApiError::from(&**err),
+        }
+    }
+}
+
+/// Route whose 503 responses carry the warm-up progress rather than a problem
+const READINESS_PATH: &str = "/health/ready";
+
+/// Middleware turning error responses that are not problem details yet, such as extractor
+/// rejections and unknown routes, into problem details with the same status
+///
+/// Only the readiness payload of `/health/ready` is served as plain JSON while unavailable.
+pub async fn problem_details(request: Request, next: Next) -> Response {
+    let is_readiness = request.uri().path() == READINESS_PATH;
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_problem = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes() == PROBLEM_CONTENT_TYPE.as_bytes());
+    if !(status.is_client_error() || status.is_server_error()) || is_problem || is_readiness {
+        return response;
+    }
+
+    let detail = match to_bytes(response.into_body(), MAX_DETAIL_BYTES).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).into_owned(),
+        Ok(_) => status.canonical_reason().unwrap_or("Error").to_string(),
+        Err(e) => {
+            warn!("Failed to read error response body: {e}");
+            status.canonical_reason().unwrap_or("Error").to_string()
+        }
+    };
+
+    problem_response(status, ErrorCode::for_status(status.as_u16()), detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Json, Router};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    async fn read_problem(response: Response) -> Problem {
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            PROBLEM_CONTENT_TYPE
+        );
+        let body = to_bytes(response.into_body(), MAX_DETAIL_BYTES)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_problem() {
+        let response =
+            ApiError::InvalidTarget("Target must be positive".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let problem = read_problem(response).await;
+        assert_eq!(problem.problem_type, "about:blank");
+        assert_eq!(problem.title, "Bad Request");
+        assert_eq!(problem.status, 400);
+        assert_eq!(problem.detail, "Target must be positive");
+        assert_eq!(problem.code, ErrorCode::InvalidTarget);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_converts_plain_errors() {
+        let app = Router::new()
+            .route(
+                "/plain",
+                get(|| async { (StatusCode::BAD_REQUEST, "bad input") }),
+            )
+            .route("/ok", get(|| async { "fine" }))
+            .route(
+                "/json",
+                get(|| async { (StatusCode::BAD_REQUEST, Json(json!({ "error": "bad" }))) }),
+            )
+            .route(
+                "/health/ready",
+                get(|| async {
+                    (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        Json(json!({ "ready": false })),
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(problem_details));
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let problem = read_problem(app.clone().oneshot(get("/plain")).await.unwrap()).await;
+        assert_eq!(problem.code, ErrorCode::InvalidRequest);
+        assert_eq!(problem.detail, "bad input");
+
+        // Other JSON error bodies become problems too
+        let problem = read_problem(app.clone().oneshot(get("/json")).await.unwrap()).await;
+        assert_eq!(problem.code, ErrorCode::InvalidRequest);
+        assert_eq!(problem.detail, r#"{"error":"bad"}"#);
+
+        // The readiness payload is left as it is
+        let response = app.clone().oneshot(get("/health/ready")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let problem = read_problem(app.clone().oneshot(get("/missing")).await.unwrap()).await;
+        assert_eq!(problem.status, 404);
+        assert_eq!(problem.code, ErrorCode::NotFound);
+        assert_eq!(problem.detail, "Not Found");
+
+        let response = app.oneshot(get("/ok")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn test_codes_serialize_in_screaming_snake_case() {
+        let codes = [
+            (ErrorCode::NoData, "NO_DATA"),
+            (ErrorCode::WarmingUp, "WARMING_UP"),
+            (ErrorCode::InvalidTarget, "INVALID_TARGET"),
+            (ErrorCode::TooManyTargets, "TOO_MANY_TARGETS"),
+            (ErrorCode::UpstreamRpcDown, "UPSTREAM_RPC_DOWN"),
+            (ErrorCode::StaleData, "STALE_DATA"),
+            (ErrorCode::UpstreamPriceFeedDown, "UPSTREAM_PRICE_FEED_DOWN"),
+        ];
+        for (code, name) in codes {
+            assert_eq!(serde_json::to_value(code).unwrap(), name);
         }
     }
 }
@@ -0,0 +1,20 @@
+use axum::{extract::State, Json};
+use bitcoin_augur_api_types::EstimatorConfigResponse;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::service::MempoolCollector;
+
+/// GET /config/estimator - Returns the block targets, confidence levels, inflow windows and
+/// bucketing of the estimator in use, with the server version
+pub async fn get_estimator_config(
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Json<EstimatorConfigResponse> {
+    info!("Received estimator configuration request");
+
+    let estimator = collector.fee_estimator().await;
+    Json(EstimatorConfigResponse::new(
+        &estimator,
+        env!("CARGO_PKG_VERSION"),
+    ))
+}
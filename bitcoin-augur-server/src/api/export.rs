@@ -0,0 +1,400 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use bitcoin_augur::FeeEstimate;
+use chrono::{DateTime, SecondsFormat};
+use futures_util::stream::{self, StreamExt};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::auth::require_bearer_token;
+use super::error::ApiError;
+use super::format::round_to;
+use crate::server::AppState;
+use crate::service::CollectorError;
+
+/// Spacing of the exported estimates when the request does not ask for one, in seconds
+const DEFAULT_STEP_SECS: i64 = 600;
+
+/// Closest spacing of exported estimates, in seconds
+const MIN_STEP_SECS: i64 = 60;
+
+/// How much one export may recompute
+#[derive(Debug, Clone, Copy)]
+struct ExportLimits {
+    /// Longest range, in days
+    max_days: i64,
+    /// Most estimates
+    max_points: i64,
+}
+
+/// Limits of exports without a token: two days every five minutes
+const PUBLIC_LIMITS: ExportLimits = ExportLimits {
+    max_days: 2,
+    max_points: 576,
+};
+
+/// Limits of exports authenticated with the admin token
+const ADMIN_LIMITS: ExportLimits = ExportLimits {
+    max_days: 31,
+    max_points: 10_000,
+};
+
+/// Columns of an export, one row per estimated fee rate
+const CSV_HEADER: &str = "timestamp,block_target,probability,fee_rate\n";
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+/// Query parameters for the historical fee export endpoint
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// Unix timestamp in seconds of the first estimate
+    from: i64,
+    /// Unix timestamp in seconds no estimate is later than
+    to: i64,
+    /// Seconds between estimates
+    step: Option<i64>,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+/// GET /historical_fees/export?from={unix_ts}&to={unix_ts}&step={secs}&format=csv|parquet -
+/// Recomputes the estimate every `step` seconds and returns the series as a table with one row
+/// per block target and confidence level
+///
+/// Both formats are streamed as the estimates are computed. Parquet is only available when the
+/// server is built with the `parquet` feature. Requests carrying the admin token may export
+/// longer ranges.
+pub async fn export_historical_fees(
+    Query(params): Query<ExportQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let limits = if headers.contains_key(header::AUTHORIZATION) {
+        require_bearer_token(&headers, state.api.admin_token.as_deref())?;
+        ADMIN_LIMITS
+    } else {
+        PUBLIC_LIMITS
+    };
+    let collector = state.collector;
+    let decimal_places = state.response.decimal_places;
+    let step = params.step.unwrap_or(DEFAULT_STEP_SECS);
+    validate_range(
+        params.from,
+        params.to,
+        step,
+        collector.now().timestamp(),
+        limits,
+    )?;
+    if params.format == ExportFormat::Parquet && !cfg!(feature = "parquet") {
+        return Err(ApiError::BadRequest(
+            "Parquet export requires the server to be built with the parquet feature".to_string(),
+        ));
+    }
+    info!(
+        "Exporting historical fee estimates from {from} to {to} every {step}s as {format:?}",
+        from = params.from,
+        to = params.to,
+        format = params.format
+    );
+
+    let from = DateTime::from_timestamp(params.from, 0)
+        .ok_or_else(|| ApiError::BadRequest("Invalid from timestamp".to_string()))?;
+    let to = DateTime::from_timestamp(params.to, 0)
+        .ok_or_else(|| ApiError::BadRequest("Invalid to timestamp".to_string()))?;
+    let mut series = collector
+        .estimate_series(from, to, chrono::Duration::seconds(step))
+        .await?;
+    let filename = format!(
+        "historical_fees_{from}_{to}",
+        from = params.from,
+        to = params.to
+    );
+
+    #[cfg(feature = "parquet")]
+    if params.format == ExportFormat::Parquet {
+        let writer = parquet_export::StreamWriter::new(decimal_places).map_err(|e| {
+            warn!("Failed to write Parquet export: {e}");
+            ApiError::InternalError("Failed to write Parquet export".to_string())
+        })?;
+        // Row groups follow as they fill up; an error cuts the file short, without a footer
+        let chunks = stream::unfold(Some((series, writer)), |state| async move {
+            let (mut series, mut writer) = state?;
+            loop {
+                let chunk: Result<Vec<u8>, axum::BoxError> = match series.recv().await {
+                    Some(Ok(estimate)) => writer.push(&estimate).map_err(Into::into),
+                    Some(Err(e)) => Err(e.into()),
+                    None => return Some((writer.finish().map_err(Into::into), None)),
+                };
+                match chunk {
+                    Ok(bytes) if bytes.is_empty() => continue,
+                    Ok(bytes) => return Some((Ok(bytes), Some((series, writer)))),
+                    Err(e) => {
+                        warn!("Historical fee export failed: {e}");
+                        return Some((Err(e), None));
+                    }
+                }
+            }
+        });
+        return Ok((
+            [
+                (
+                    header::CONTENT_TYPE,
+                    "application/vnd.apache.parquet".to_string(),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{filename}.parquet\""),
+                ),
+            ],
+            Body::from_stream(chunks),
+        )
+            .into_response());
+    }
+
+    // Rows follow as each estimate is computed; an error cuts the body short
+    let rows = stream::poll_fn(move |cx| series.poll_recv(cx)).map(move |estimate| {
+        estimate
+            .map(|estimate| csv_rows(&estimate, decimal_places))
+            .inspect_err(|e| {
+                warn!("Historical fee export failed: {e}");
+            })
+    });
+    let body = stream::once(async { Ok::<_, CollectorError>(CSV_HEADER.to_string()) }).chain(rows);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}.csv\""),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Checks that an export from `from` to `to` every `step` seconds is within `limits` and in the
+/// past
+fn validate_range(
+    from: i64,
+    to: i64,
+    step: i64,
+    now: i64,
+    limits: ExportLimits,
+) -> Result<(), ApiError> {
+    let ExportLimits {
+        max_days,
+        max_points,
+    } = limits;
+    if from > to {
+        return Err(ApiError::BadRequest(
+            "Invalid range: from must not be after to".to_string(),
+        ));
+    }
+    if to > now {
+        return Err(ApiError::BadRequest(
+            "Invalid range: to cannot be in the future".to_string(),
+        ));
+    }
+    if to - from > max_days * 24 * 60 * 60 {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid range: at most {max_days} days can be exported at once"
+        )));
+    }
+    if step < MIN_STEP_SECS {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid step: must be at least {MIN_STEP_SECS} seconds"
+        )));
+    }
+    if (to - from) / step + 1 > max_points {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid step: at most {max_points} estimates can be exported at once"
+        )));
+    }
+    Ok(())
+}
+
+/// CSV rows of `estimate`, one per block target and confidence level, with fee rates rounded to
+/// `decimal_places`
+fn csv_rows(estimate: &FeeEstimate, decimal_places: u32) -> String {
+    let timestamp = estimate
+        .timestamp
+        .to_rfc3339_opts(SecondsFormat::Secs, true);
+    let mut rows = String::new();
+    for (blocks, target) in estimate.targets() {
+        for (probability, fee_rate) in &target.probabilities {
+            rows.push_str(&format!(
+                "{timestamp},{blocks},{probability},{fee_rate}\n",
+                probability = probability.0,
+                fee_rate = round_to(*fee_rate, decimal_places)
+            ));
+        }
+    }
+    rows
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_export {
+    use arrow_array::{ArrayRef, Float64Array, RecordBatch, TimestampSecondArray};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+    use bitcoin_augur::FeeEstimate;
+    use parquet::arrow::ArrowWriter;
+    use parquet::errors::ParquetError;
+    use std::sync::Arc;
+
+    use super::round_to;
+
+    /// Estimates buffered in one row group, a day every ten minutes
+    const ROW_GROUP_ESTIMATES: usize = 144;
+
+    /// Writes estimates as a Parquet file with the same columns as the CSV export, handing out
+    /// each row group as soon as it is complete
+    pub struct StreamWriter {
+        writer: ArrowWriter<Vec<u8>>,
+        schema: SchemaRef,
+        decimal_places: u32,
+        buffered: usize,
+    }
+
+    impl StreamWriter {
+        pub fn new(decimal_places: u32) -> Result<Self, ParquetError> {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new(
+                    "timestamp",
+                    DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+                    false,
+                ),
+                Field::new("block_target", DataType::Float64, false),
+                Field::new("probability", DataType::Float64, false),
+                Field::new("fee_rate", DataType::Float64, false),
+            ]));
+            Ok(Self {
+                writer: ArrowWriter::try_new(Vec::new(), schema.clone(), None)?,
+                schema,
+                decimal_places,
+                buffered: 0,
+            })
+        }
+
+        /// Adds the rows of `estimate`, returning the bytes written since the last call, which
+        /// are empty until a row group is complete
+        pub fn push(&mut self, estimate: &FeeEstimate) -> Result<Vec<u8>, ParquetError> {
+            let mut timestamps = Vec::new();
+            let mut block_targets = Vec::new();
+            let mut probabilities = Vec::new();
+            let mut fee_rates = Vec::new();
+            for (blocks, target) in estimate.targets() {
+                for (probability, fee_rate) in target.fee_rates() {
+                    timestamps.push(estimate.timestamp.timestamp());
+                    block_targets.push(blocks.target());
+                    probabilities.push(probability);
+                    fee_rates.push(round_to(fee_rate, self.decimal_places));
+                }
+            }
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(TimestampSecondArray::from(timestamps).with_timezone("UTC")),
+                Arc::new(Float64Array::from(block_targets)),
+                Arc::new(Float64Array::from(probabilities)),
+                Arc::new(Float64Array::from(fee_rates)),
+            ];
+            self.writer
+                .write(&RecordBatch::try_new(self.schema.clone(), columns)?)?;
+
+            self.buffered += 1;
+            if self.buffered == ROW_GROUP_ESTIMATES {
+                self.buffered = 0;
+                self.writer.flush()?;
+            }
+            // The writer tracks offsets itself, so written bytes can be handed out
+            Ok(std::mem::take(self.writer.inner_mut()))
+        }
+
+        /// Writes the remaining rows and the footer, returning the bytes not yet handed out
+        pub fn finish(self) -> Result<Vec<u8>, ParquetError> {
+            self.writer.into_inner()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_range() {
+        let now = 1_750_000_000;
+        let day = 24 * 60 * 60;
+        assert!(validate_range(now - day, now, 600, now, PUBLIC_LIMITS).is_ok());
+        assert!(validate_range(now, now, 600, now, PUBLIC_LIMITS).is_ok());
+        assert!(validate_range(now - 30 * day, now, 600, now, ADMIN_LIMITS).is_ok());
+
+        assert!(validate_range(now, now - day, 600, now, ADMIN_LIMITS).is_err());
+        assert!(validate_range(now - day, now + 1, 600, now, ADMIN_LIMITS).is_err());
+        assert!(validate_range(now - 40 * day, now, 600, now, ADMIN_LIMITS).is_err());
+        assert!(validate_range(now - day, now, 30, now, ADMIN_LIMITS).is_err());
+        assert!(validate_range(now - 30 * day, now, 60, now, ADMIN_LIMITS).is_err());
+
+        // Without a token, a week is too long and a day every minute too many estimates
+        assert!(validate_range(now - 7 * day, now, 600, now, PUBLIC_LIMITS).is_err());
+        assert!(validate_range(now - day, now, 60, now, PUBLIC_LIMITS).is_err());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_export_round_trips() {
+        use bitcoin_augur::{BlockTarget, OrderedFloat};
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use std::collections::BTreeMap;
+
+        let start = chrono::Utc::now();
+        let estimate = |i: i32| {
+            let targets = [3, 6].map(|blocks| {
+                let fee_rate = f64::from(i) / f64::from(blocks);
+                let probabilities = BTreeMap::from([
+                    (OrderedFloat(0.5), fee_rate),
+                    (OrderedFloat(0.95), 2.0 * fee_rate),
+                ]);
+                (blocks, BlockTarget::new(blocks, probabilities))
+            });
+            let timestamp = start + chrono::Duration::minutes(i64::from(i));
+            FeeEstimate::new(BTreeMap::from(targets), timestamp)
+        };
+
+        // Complete row groups are handed out before the export finishes
+        let mut writer = parquet_export::StreamWriter::new(4).unwrap();
+        let mut bytes = Vec::new();
+        for i in 0..1000 {
+            bytes.extend(writer.push(&estimate(i)).unwrap());
+        }
+        assert!(!bytes.is_empty());
+        bytes.extend(writer.finish().unwrap());
+        let file = tempfile::tempfile().unwrap();
+        std::io::Write::write_all(&mut &file, &bytes).unwrap();
+        let reader = SerializedFileReader::new(file).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 4000);
+        assert_eq!(reader.metadata().num_row_groups(), 7);
+        let columns: Vec<_> = reader
+            .metadata()
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        assert_eq!(
+            columns,
+            ["timestamp", "block_target", "probability", "fee_rate"]
+        );
+    }
+}
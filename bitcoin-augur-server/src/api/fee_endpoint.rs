@@ -1,64 +1,178 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
     response::{IntoResponse, Response},
     Json,
 };
+use bitcoin_augur_api_types::{transform_fee_estimate, FeeEstimateResponse};
+use serde::Deserialize;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 
-use super::error::{ApiError, ErrorResponse};
-use super::models::transform_fee_estimate;
-use crate::service::MempoolCollector;
+use super::error::ApiError;
+use crate::service::{CollectorError, MempoolCollector, PriceFeed};
+use bitcoin_augur::{AugurError, FeeEstimate};
 
-/// GET /fees - Returns current fee estimates for all block targets
-pub async fn get_fees(State(collector): State<Arc<MempoolCollector>>) -> Response {
+/// Query parameters of the fee endpoints
+#[derive(Debug, Deserialize)]
+pub struct FeeQuery {
+    /// Currency to quote the cost of a standard transaction in, such as `usd`
+    fiat: Option<String>,
+}
+
+/// Builds the response for a current estimate, with its validity if enabled, whether the
+/// history it comes from has gaps and how old the latest snapshot is
+pub(super) async fn current_response(
+    collector: &MempoolCollector,
+    estimate: FeeEstimate,
+) -> FeeEstimateResponse {
+    let valid_until = collector.valid_until(&estimate).await;
+    let mut response = transform_fee_estimate(estimate);
+    response.degraded = collector.is_degraded().await;
+    response.data_age_seconds = collector
+        .data_age()
+        .await
+        .map(|age| age.num_seconds().unsigned_abs());
+    match valid_until {
+        Some(valid_until) => response.with_valid_until(&valid_until),
+        None => response,
+    }
+}
+
+/// Adds the cost of a standard transaction in `currency` to `response`, if one was asked for
+async fn with_fiat(
+    response: FeeEstimateResponse,
+    currency: Option<&str>,
+    prices: Option<&PriceFeed>,
+) -> Result<FeeEstimateResponse, ApiError> {
+    let Some(currency) = currency else {
+        return Ok(response);
+    };
+    let Some(prices) = prices else {
+        return Err(ApiError::BadRequest(
+            "Fiat costs are unavailable: no price feed is configured".to_string(),
+        ));
+    };
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid currency {currency:?}: expected a three letter code such as usd"
+        )));
+    }
+    let price = prices
+        .price(currency)
+        .await
+        .map_err(|e| ApiError::UpstreamPriceFeedDown(e.to_string()))?
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!("The price feed has no price in {currency:?}"))
+        })?;
+    Ok(response.with_fiat(currency, price))
+}
+
+/// GET /fees?fiat={currency} - Returns current fee estimates for all block targets, with the
+/// cost of a standard transaction in `currency` if given
+pub async fn get_fees(
+    Query(query): Query<FeeQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+    State(prices): State<Option<Arc<PriceFeed>>>,
+) -> Result<Response, ApiError> {
     info!("Received request for fee estimates");
 
-    match collector.get_latest_estimate().await {
-        Some(estimate) => {
-            let response = transform_fee_estimate(estimate);
-            debug!(
-                "Returning fee estimates with {} targets",
-                response.estimates.len()
-            );
-            Json(response).into_response()
-        }
-        None => {
-            warn!("No fee estimates available yet");
-            let error_response = ErrorResponse {
-                error: "service_unavailable".to_string(),
-                message: "No fee estimates available yet".to_string(),
-            };
-            (StatusCode::SERVICE_UNAVAILABLE, Json(error_response)).into_response()
-        }
+    let Some(estimate) = collector.get_latest_estimate().await else {
+        warn!("No fee estimates available yet");
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No fee estimates available yet".to_string()),
+        });
+    };
+
+    let response = current_response(&collector, estimate).await;
+    let response = with_fiat(response, query.fiat.as_deref(), prices.as_deref()).await?;
+    debug!(
+        "Returning fee estimates with {} targets",
+        response.estimates.len()
+    );
+    Ok(Json(response).into_response())
+}
+
+/// Largest block target the API estimates for
+pub(super) const MAX_TARGET_BLOCKS: f64 = 1000.0;
+
+/// Parses a block target path segment, accepting whole and decimal numbers such as `6` and
+/// `6.0` like the library does
+fn parse_target(raw: &str) -> Result<f64, ApiError> {
+    let num_blocks = raw
+        .parse::<f64>()
+        .ok()
+        .filter(|blocks| blocks.is_finite())
+        .ok_or_else(|| {
+            ApiError::InvalidTarget(format!("Invalid number of blocks: {raw:?} is not a number"))
+        })?;
+    if num_blocks <= 0.0 {
+        return Err(ApiError::InvalidTarget(format!(
+            "Invalid number of blocks: {num_blocks} is not positive"
+        )));
+    }
+    if num_blocks > MAX_TARGET_BLOCKS {
+        return Err(ApiError::InvalidTarget(format!(
+            "Invalid number of blocks: {num_blocks} is above the maximum of {MAX_TARGET_BLOCKS}"
+        )));
     }
+    Ok(num_blocks)
 }
 
-/// GET /fees/target/{num_blocks} - Returns fee estimates for a specific block target
+/// GET /fees/target/{num_blocks}?fiat={currency} - Returns fee estimates for a specific block
+/// target, with the cost of a standard transaction in `currency` if given
 pub async fn get_fee_for_target(
-    Path(num_blocks): Path<f64>,
+    Path(raw_target): Path<String>,
+    Query(query): Query<FeeQuery>,
     State(collector): State<Arc<MempoolCollector>>,
+    State(prices): State<Option<Arc<PriceFeed>>>,
 ) -> Result<Response, ApiError> {
-    // Validate num_blocks parameter
-    if num_blocks <= 0.0 || num_blocks > 1000.0 || !num_blocks.is_finite() {
-        warn!("Invalid num_blocks parameter: {num_blocks}");
-        return Err(ApiError::BadRequest(
-            "Invalid number of blocks: must be between 1 and 1000".to_string(),
-        ));
-    }
+    let num_blocks = parse_target(&raw_target).inspect_err(|_| {
+        warn!("Invalid num_blocks parameter: {raw_target}");
+    })?;
 
     info!(
         "Received request for fee estimates targeting {} blocks",
         num_blocks
     );
 
-    // Get estimate for specific block target
-    let estimate = collector.get_estimate_for_blocks(num_blocks).await?;
-    let response = transform_fee_estimate(estimate);
+    // Get estimate for specific block target, whose bounds the estimator checks as well
+    let estimate = collector
+        .get_estimate_for_blocks(num_blocks)
+        .await
+        .map_err(|err| match err.cause() {
+            CollectorError::EstimationError(AugurError::InvalidParameter(msg)) => {
+                ApiError::InvalidTarget(msg.clone())
+            }
+            _ => err.into(),
+        })?;
+    let response = current_response(&collector, estimate).await;
+    let response = with_fiat(response, query.fiat.as_deref(), prices.as_deref()).await?;
     debug!(
         "Returning fee estimates with {} targets",
         response.estimates.len()
     );
     Ok(Json(response).into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(parse_target("6").unwrap(), 6.0);
+        assert_eq!(parse_target("6.0").unwrap(), 6.0);
+        assert_eq!(parse_target("2.5").unwrap(), 2.5);
+        assert_eq!(parse_target("1000").unwrap(), MAX_TARGET_BLOCKS);
+
+        for raw in [
+            "abc", "", "6blocks", "NaN", "inf", "-inf", "0", "-1", "-0.5", "1000.5",
+        ] {
+            assert!(
+                matches!(parse_target(raw), Err(ApiError::InvalidTarget(_))),
+                "{raw}"
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use tracing::warn;
+
+use super::error::ApiError;
+use crate::config::ResponseConfig;
+
+/// Largest JSON body the formatting middlewares rewrite
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Rounds `number` to `decimal_places`
+///
+/// Rounding goes through Rust's own formatting, which never depends on the locale, so the
+/// result is the same on every host.
+pub fn round_to(number: f64, decimal_places: u32) -> f64 {
+    format!("{number:.places$}", places = decimal_places as usize)
+        .parse()
+        .unwrap_or(number)
+}
+
+/// Rounds every fractional number in `value` to `decimal_places`, leaving integers as they are
+pub fn round_numbers(value: Value, decimal_places: u32) -> Value {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            number.as_f64().map_or(Value::Number(number), |number| {
+                Value::from(round_to(number, decimal_places))
+            })
+        }
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, round_numbers(value, decimal_places)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| round_numbers(value, decimal_places))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Whether `response` has a JSON body the formatting middlewares rewrite
+pub(super) fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes() == b"application/json")
+}
+
+/// Replaces the JSON body of `response` with `rewrite` of it, keeping the body as it was if it
+/// cannot be parsed and answering with an internal error if it cannot be read
+pub(super) async fn rewrite_json(
+    response: Response,
+    purpose: &str,
+    rewrite: impl FnOnce(Value) -> Value,
+) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read response body for {purpose}: {e}");
+            return ApiError::InternalError(format!(
+                "Failed to read the response body for {purpose}"
+            ))
+            .into_response();
+        }
+    };
+    let rewritten = serde_json::from_slice(&bytes)
+        .map(rewrite)
+        .and_then(|value| serde_json::to_vec(&value));
+    match rewritten {
+        Ok(rewritten) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(rewritten))
+        }
+        Err(e) => {
+            warn!("Failed to rewrite response body for {purpose}: {e}");
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+/// Middleware giving every JSON response the same numeric precision, whichever endpoint built it
+pub async fn response_format(
+    State(format): State<ResponseConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if !is_json(&response) {
+        return response;
+    }
+    rewrite_json(response, "rounding", |value| {
+        round_numbers(value, format.decimal_places)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_unreadable_body_is_an_internal_error() {
+        let mut response = Response::new(Body::from(vec![b' '; MAX_BODY_BYTES + 1]));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        );
+
+        let response = rewrite_json(response, "testing", |value| value).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            bitcoin_augur_api_types::PROBLEM_CONTENT_TYPE
+        );
+    }
+
+    #[test]
+    fn test_round_to() {
+        assert_eq!(round_to(5.123456, 4), 5.1235);
+        assert_eq!(round_to(5.123456, 0), 5.0);
+        assert_eq!(round_to(1234567.891, 2), 1234567.89);
+        assert_eq!(round_to(0.1 + 0.2, 4), 0.3);
+    }
+
+    #[test]
+    fn test_round_numbers_is_locale_independent() {
+        let body = json!({
+            "fee_rate": 12345.678912,
+            "block_height": 850000,
+            "probabilities": {"0.50": {"fee_rate": 1.00004}},
+            "datapoints": [[0.123456, 1750000000000_i64]],
+            "label": "1,234.5",
+        });
+        let rounded = round_numbers(body, 4);
+        assert_eq!(
+            rounded,
+            json!({
+                "fee_rate": 12345.6789,
+                "block_height": 850000,
+                "probabilities": {"0.50": {"fee_rate": 1.0}},
+                "datapoints": [[0.1235, 1750000000000_i64]],
+                "label": "1,234.5",
+            })
+        );
+        // No thousands separators or locale decimal commas, whatever the host's locale
+        let text = serde_json::to_string(&rounded).unwrap();
+        assert!(text.contains("\"fee_rate\":12345.6789"));
+        assert!(text.contains("\"block_height\":850000"));
+    }
+}
@@ -0,0 +1,273 @@
+//! Grafana JSON datasource endpoints, so dashboards can chart estimates and mempool statistics
+//! without an intermediate exporter
+//!
+//! Pointing a JSON datasource at `/grafana` makes Grafana test it with `GET /grafana`, list
+//! metrics with `POST /grafana/search` and fetch them with `POST /grafana/query`.
+
+use axum::{extract::State, Json};
+use bitcoin_augur::{MempoolSnapshot, WU_PER_BYTE};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use super::error::ApiError;
+use crate::service::MempoolCollector;
+
+/// Closest spacing of recomputed estimates, in seconds
+const MIN_STEP_SECS: i64 = 60;
+
+/// Longest range one query may cover, in days
+const MAX_QUERY_DAYS: i64 = 31;
+
+/// Most estimates one query may recompute, whatever Grafana asks for
+const MAX_ESTIMATE_POINTS: i64 = 500;
+
+/// Points Grafana gets per series when the query does not say
+const DEFAULT_MAX_DATA_POINTS: i64 = 1000;
+
+/// Prefix of the fee rate metrics, named `fee_rate:{blocks}:{probability}`
+const FEE_RATE_PREFIX: &str = "fee_rate:";
+
+/// Mempool statistics charted straight from the stored snapshots
+const SNAPSHOT_METRICS: [&str; 3] = ["mempool_vsize", "mempool_total_fees", "block_height"];
+
+/// A metric Grafana can query
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    /// Estimated fee rate in sat/vB to confirm within `blocks` with `probability`
+    FeeRate { blocks: u32, probability: f64 },
+    /// Virtual size of the mempool
+    MempoolVsize,
+    /// Total fees of the mempool in sats
+    MempoolTotalFees,
+    /// Chain tip height the snapshot was taken at
+    BlockHeight,
+}
+
+impl Metric {
+    fn parse(name: &str) -> Option<Self> {
+        if let Some(rest) = name.strip_prefix(FEE_RATE_PREFIX) {
+            let (blocks, probability) = rest.split_once(':')?;
+            return Some(Self::FeeRate {
+                blocks: blocks.parse().ok()?,
+                probability: probability.parse().ok()?,
+            });
+        }
+        match name {
+            "mempool_vsize" => Some(Self::MempoolVsize),
+            "mempool_total_fees" => Some(Self::MempoolTotalFees),
+            "block_height" => Some(Self::BlockHeight),
+            _ => None,
+        }
+    }
+
+    /// Value of a snapshot metric, or `None` for fee rates
+    fn snapshot_value(self, snapshot: &MempoolSnapshot) -> Option<f64> {
+        match self {
+            Self::FeeRate { .. } => None,
+            Self::MempoolVsize => Some((snapshot.total_weight() as f64 / WU_PER_BYTE).ceil()),
+            Self::MempoolTotalFees => Some(snapshot.total_value() as f64),
+            Self::BlockHeight => Some(snapshot.block_height as f64),
+        }
+    }
+}
+
+/// Time range of a Grafana query
+#[derive(Debug, Deserialize)]
+pub struct QueryRange {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+}
+
+/// One series requested by a Grafana panel
+#[derive(Debug, Deserialize)]
+pub struct QueryTarget {
+    target: String,
+}
+
+/// Body of `POST /grafana/query`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrafanaQuery {
+    range: QueryRange,
+    #[serde(default)]
+    interval_ms: Option<i64>,
+    #[serde(default)]
+    max_data_points: Option<i64>,
+    targets: Vec<QueryTarget>,
+}
+
+/// One series of a query response, with `[value, unix_ms]` points
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeSeries {
+    pub target: String,
+    pub datapoints: Vec<(f64, i64)>,
+}
+
+/// GET /grafana - Lets Grafana's "Save & test" succeed
+pub async fn grafana_health() -> &'static str {
+    "OK"
+}
+
+/// POST /grafana/search - Lists the metrics that can be queried, one fee rate per block target
+/// and confidence level of the latest estimate plus the mempool statistics
+pub async fn search_metrics(State(collector): State<Arc<MempoolCollector>>) -> Json<Vec<String>> {
+    let mut metrics = Vec::new();
+    if let Some(estimate) = collector.get_latest_estimate().await {
+        for (blocks, target) in estimate.targets() {
+            for probability in target.probabilities.keys() {
+                metrics.push(format!(
+                    "{FEE_RATE_PREFIX}{blocks}:{probability}",
+                    probability = probability.0
+                ));
+            }
+        }
+    }
+    metrics.extend(SNAPSHOT_METRICS.map(String::from));
+    Json(metrics)
+}
+
+/// POST /grafana/query - Returns each requested metric over the range as a time series
+///
+/// Fee rates are recomputed from the stored snapshots, at most [`MAX_ESTIMATE_POINTS`] times
+/// per query; mempool statistics come straight from the snapshots.
+pub async fn query_metrics(
+    State(collector): State<Arc<MempoolCollector>>,
+    Json(query): Json<GrafanaQuery>,
+) -> Result<Json<Vec<TimeSeries>>, ApiError> {
+    let from = query.range.from;
+    let to = query.range.to.min(collector.now());
+    if from > to {
+        return Err(ApiError::BadRequest(
+            "Invalid range: from must not be after to".to_string(),
+        ));
+    }
+    if (to - from).num_days() > MAX_QUERY_DAYS {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid range: at most {MAX_QUERY_DAYS} days can be queried at once"
+        )));
+    }
+    let metrics = query
+        .targets
+        .iter()
+        .map(|target| {
+            Metric::parse(&target.target)
+                .map(|metric| (target.target.clone(), metric))
+                .ok_or_else(|| {
+                    ApiError::BadRequest(format!("Unknown metric: {name}", name = target.target))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let max_points = query
+        .max_data_points
+        .filter(|&points| points > 0)
+        .unwrap_or(DEFAULT_MAX_DATA_POINTS);
+    info!(
+        "Received Grafana query for {count} metrics from {from} to {to}",
+        count = metrics.len()
+    );
+
+    let mut series: Vec<TimeSeries> = metrics
+        .iter()
+        .map(|(target, _)| TimeSeries {
+            target: target.clone(),
+            datapoints: Vec::new(),
+        })
+        .collect();
+
+    if metrics
+        .iter()
+        .any(|(_, metric)| matches!(metric, Metric::FeeRate { .. }))
+    {
+        let step = estimate_step(from, to, query.interval_ms, max_points);
+        let mut estimates = collector
+            .estimate_series(from, to, chrono::Duration::seconds(step))
+            .await?;
+        while let Some(estimate) = estimates.recv().await {
+            let estimate = estimate?;
+            let timestamp = estimate.timestamp.timestamp_millis();
+            for ((_, metric), series) in metrics.iter().zip(&mut series) {
+                if let Metric::FeeRate {
+                    blocks,
+                    probability,
+                } = *metric
+                {
+                    if let Some(fee_rate) = estimate.get_fee_rate(blocks, probability) {
+                        series.datapoints.push((fee_rate, timestamp));
+                    }
+                }
+            }
+        }
+    }
+
+    if metrics
+        .iter()
+        .any(|(_, metric)| !matches!(metric, Metric::FeeRate { .. }))
+    {
+        let snapshots = collector.snapshots_between(from, to).await?;
+        let stride = snapshots.len().div_ceil(max_points as usize).max(1);
+        for snapshot in snapshots.iter().step_by(stride) {
+            let timestamp = snapshot.timestamp.timestamp_millis();
+            for ((_, metric), series) in metrics.iter().zip(&mut series) {
+                if let Some(value) = metric.snapshot_value(snapshot) {
+                    series.datapoints.push((value, timestamp));
+                }
+            }
+        }
+    }
+
+    Ok(Json(series))
+}
+
+/// Seconds between recomputed estimates: Grafana's interval, widened so the range holds no more
+/// than `max_points` or [`MAX_ESTIMATE_POINTS`] estimates
+fn estimate_step(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    interval_ms: Option<i64>,
+    max_points: i64,
+) -> i64 {
+    let range = (to - from).num_seconds();
+    let points = max_points.min(MAX_ESTIMATE_POINTS);
+    let interval = interval_ms.unwrap_or(0) / 1000;
+    interval
+        .max((range + points - 1) / points)
+        .max(MIN_STEP_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metric() {
+        assert_eq!(
+            Metric::parse("fee_rate:6:0.95"),
+            Some(Metric::FeeRate {
+                blocks: 6,
+                probability: 0.95
+            })
+        );
+        assert_eq!(Metric::parse("mempool_vsize"), Some(Metric::MempoolVsize));
+        assert_eq!(Metric::parse("block_height"), Some(Metric::BlockHeight));
+        assert_eq!(Metric::parse("fee_rate:6"), None);
+        assert_eq!(Metric::parse("fee_rate:six:0.5"), None);
+        assert_eq!(Metric::parse("mempool_size"), None);
+    }
+
+    #[test]
+    fn test_estimate_step() {
+        let to = Utc::now();
+        let day = chrono::Duration::days(1);
+        // A day of 1000 points is capped at 500 estimates
+        assert_eq!(estimate_step(to - day, to, None, 1000), 173);
+        // Grafana's interval wins when it is coarser
+        assert_eq!(estimate_step(to - day, to, Some(3_600_000), 1000), 3600);
+        // Never closer than a minute
+        assert_eq!(
+            estimate_step(to - chrono::Duration::hours(1), to, Some(1000), 1000),
+            60
+        );
+    }
+}
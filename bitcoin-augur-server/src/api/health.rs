@@ -0,0 +1,64 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::service::{MempoolCollector, WarmupPhase};
+
+/// Response of `GET /health/ready`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    /// Whether fee estimates are being served
+    pub ready: bool,
+    /// `waiting`, `loading`, `estimating` or `ready`
+    pub phase: String,
+    /// Stored snapshots read while warming up
+    pub snapshots_loaded: usize,
+    /// Stored snapshots in the estimation window being warmed up from
+    pub snapshots_total: usize,
+    /// Seconds since the latest snapshot was taken, once there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_age_seconds: Option<u64>,
+    /// Set while the latest snapshot is older than the maximum staleness
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+    /// Error of the latest mempool fetch, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_error: Option<String>,
+}
+
+/// GET /health/ready - Returns 200 once an estimate is served, no warm-up is running and the
+/// data is not stale, and 503 with the warm-up progress and data age otherwise
+pub async fn get_readiness(
+    State(collector): State<Arc<MempoolCollector>>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let status = collector.warmup_status().await;
+    let phase = match status.phase {
+        WarmupPhase::Waiting => "waiting",
+        WarmupPhase::Loading => "loading",
+        WarmupPhase::Estimating => "estimating",
+        WarmupPhase::Ready => "ready",
+    };
+    let stale = collector.is_stale().await;
+    let ready = status.phase == WarmupPhase::Ready && !stale;
+
+    let code = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        code,
+        Json(ReadinessResponse {
+            ready,
+            phase: phase.to_string(),
+            snapshots_loaded: status.loaded,
+            snapshots_total: status.total,
+            data_age_seconds: collector
+                .data_age()
+                .await
+                .map(|age| age.num_seconds().unsigned_abs()),
+            stale,
+            rpc_error: collector.rpc_error().await,
+        }),
+    )
+}
@@ -0,0 +1,110 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bitcoin_augur::WU_PER_BYTE;
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::error::ApiError;
+use crate::service::MempoolCollector;
+
+/// Most boundaries one histogram may be rebucketed to
+const MAX_BOUNDARIES: usize = 1000;
+
+/// Query parameters for the mempool histogram endpoint
+#[derive(Debug, Deserialize)]
+pub struct HistogramQuery {
+    /// Comma-separated fee rates in sat/vB to sum the buckets between, instead of listing every
+    /// bucket
+    boundaries: Option<String>,
+}
+
+/// Response of `GET /mempool/histogram`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MempoolHistogramResponse {
+    pub mempool_update_time: String,
+    pub block_height: u32,
+    /// Virtual size of the whole mempool
+    pub mempool_vsize: u64,
+    /// `[fee_rate, vsize]` pairs, highest fee rate first
+    pub fee_histogram: Vec<(f64, u64)>,
+}
+
+/// Parses a comma-separated list of positive fee rates
+fn parse_boundaries(list: &str) -> Result<Vec<f64>, ApiError> {
+    let boundaries = list
+        .split(',')
+        .map(|boundary| {
+            boundary
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| rate.is_finite() && *rate > 0.0)
+                .ok_or_else(|| {
+                    ApiError::BadRequest(format!(
+                        "Invalid boundary {boundary:?}: must be a positive fee rate in sat/vB"
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if boundaries.len() > MAX_BOUNDARIES {
+        return Err(ApiError::BadRequest(format!(
+            "Too many boundaries: at most {MAX_BOUNDARIES} are allowed"
+        )));
+    }
+    Ok(boundaries)
+}
+
+/// GET /mempool/histogram?boundaries={sat/vB,...} - Returns the virtual size of each fee rate
+/// bucket of the latest mempool snapshot, or summed between the given fee rates
+pub async fn get_mempool_histogram(
+    Query(HistogramQuery { boundaries }): Query<HistogramQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<MempoolHistogramResponse>, ApiError> {
+    info!("Received mempool histogram request");
+
+    let boundaries = boundaries
+        .as_deref()
+        .map(parse_boundaries)
+        .transpose()
+        .inspect_err(|e| warn!("Invalid histogram boundaries: {e}"))?;
+
+    let Some(snapshot) = collector.get_latest_snapshot().await else {
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No mempool snapshot available yet".to_string()),
+        });
+    };
+
+    let histogram = match &boundaries {
+        Some(boundaries) => snapshot.fee_histogram_with_boundaries(boundaries),
+        None => snapshot.fee_histogram(),
+    };
+    Ok(Json(MempoolHistogramResponse {
+        mempool_update_time: format_timestamp(snapshot.timestamp),
+        block_height: snapshot.block_height,
+        mempool_vsize: (snapshot.total_weight() as f64 / WU_PER_BYTE).ceil() as u64,
+        fee_histogram: histogram
+            .into_iter()
+            .map(|(fee_rate, vsize)| (format_fee_rate(fee_rate), vsize))
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_boundaries() {
+        assert_eq!(parse_boundaries("1, 2.5,100").unwrap(), [1.0, 2.5, 100.0]);
+        for list in ["", "1,,2", "0", "-1", "abc", "inf", "NaN"] {
+            assert!(parse_boundaries(list).is_err(), "{list}");
+        }
+        let many = vec!["1"; MAX_BOUNDARIES + 1].join(",");
+        assert!(parse_boundaries(&many).is_err());
+    }
+}
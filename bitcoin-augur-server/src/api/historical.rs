@@ -1,16 +1,17 @@
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
+use bitcoin_augur::AugurError;
+use bitcoin_augur_api_types::transform_fee_estimate;
 use serde::Deserialize;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 
-use super::error::ErrorResponse;
-use super::models::transform_fee_estimate;
-use crate::service::MempoolCollector;
+use super::error::ApiError;
+use crate::persistence::PersistenceError;
+use crate::service::{CollectorError, MempoolCollector};
 
 /// Query parameters for historical fee endpoint
 #[derive(Debug, Deserialize)]
@@ -23,27 +24,22 @@ pub struct HistoricalQuery {
 pub async fn get_historical_fee(
     Query(params): Query<HistoricalQuery>,
     State(collector): State<Arc<MempoolCollector>>,
-) -> Response {
+) -> Result<Response, ApiError> {
     info!(
         "Received request for historical fee estimates at timestamp {}",
         params.timestamp
     );
 
     // Validate timestamp (must be reasonable - not in future, not too far in past)
-    let now = chrono::Utc::now().timestamp();
+    let now = collector.now().timestamp();
     if params.timestamp > now {
         warn!(
             "Timestamp {timestamp} is in the future",
             timestamp = params.timestamp
         );
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "bad_request".to_string(),
-                message: "Timestamp cannot be in the future".to_string(),
-            }),
-        )
-            .into_response();
+        return Err(ApiError::BadRequest(
+            "Timestamp cannot be in the future".to_string(),
+        ));
     }
 
     // Don't allow timestamps more than 1 year in the past
@@ -53,77 +49,45 @@ pub async fn get_historical_fee(
             "Timestamp {timestamp} is too far in the past",
             timestamp = params.timestamp
         );
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "bad_request".to_string(),
-                message: "Timestamp is too far in the past (max 1 year)".to_string(),
-            }),
-        )
-            .into_response();
+        return Err(ApiError::BadRequest(
+            "Timestamp is too far in the past (max 1 year)".to_string(),
+        ));
     }
 
+    let no_data =
+        || ApiError::NoData("No historical data available for the requested timestamp".to_string());
+
     // Get historical estimate
-    match collector.get_estimate_for_timestamp(params.timestamp).await {
-        Ok(estimate) => {
-            if estimate.estimates.is_empty() {
-                debug!(
-                    "No historical data available for timestamp {}",
-                    params.timestamp
-                );
-                (
-                    StatusCode::NOT_FOUND,
-                    Json(ErrorResponse {
-                        error: "not_found".to_string(),
-                        message: "No historical data available for the requested timestamp"
-                            .to_string(),
-                    }),
-                )
-                    .into_response()
-            } else {
-                let response = transform_fee_estimate(estimate);
-                debug!(
-                    "Returning historical fee estimates with {} targets",
-                    response.estimates.len()
-                );
-                Json(response).into_response()
-            }
-        }
-        Err(err) => {
+    let estimate = collector
+        .get_estimate_for_timestamp(params.timestamp)
+        .await
+        .map_err(|err| {
             warn!("Failed to get historical fee estimates: {err}");
-
-            // Check error type for appropriate response
-            if err.to_string().contains("InvalidTimestamp") {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: "bad_request".to_string(),
-                        message: "Invalid timestamp format".to_string(),
-                    }),
-                )
-                    .into_response()
-            } else if err.to_string().contains("Insufficient") {
-                (
-                    StatusCode::NOT_FOUND,
-                    Json(ErrorResponse {
-                        error: "not_found".to_string(),
-                        message: "No historical data available for the requested timestamp"
-                            .to_string(),
-                    }),
-                )
-                    .into_response()
-            } else {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "internal_error".to_string(),
-                        message: "Failed to retrieve historical fee estimates".to_string(),
-                    }),
-                )
-                    .into_response()
+            match err {
+                CollectorError::PersistenceError(PersistenceError::InvalidTimestamp(_)) => {
+                    ApiError::BadRequest("Invalid timestamp format".to_string())
+                }
+                CollectorError::EstimationError(AugurError::InsufficientData(_)) => no_data(),
+                _ => ApiError::InternalError(
+                    "Failed to retrieve historical fee estimates".to_string(),
+                ),
             }
-        }
+        })?;
+
+    if estimate.is_empty() {
+        debug!(
+            "No historical data available for timestamp {}",
+            params.timestamp
+        );
+        return Err(no_data());
     }
+
+    let response = transform_fee_estimate(estimate);
+    debug!(
+        "Returning historical fee estimates with {} targets",
+        response.estimates.len()
+    );
+    Ok(Json(response).into_response())
 }
 
 #[cfg(test)]
@@ -131,6 +95,7 @@ mod tests {
     use super::*;
     use crate::bitcoin::{BitcoinRpcClient, BitcoinRpcConfig};
     use crate::persistence::SnapshotStore;
+    use axum::http::StatusCode;
     use bitcoin_augur::FeeEstimator;
     use tempfile::TempDir;
 
@@ -164,7 +129,8 @@ mod tests {
             }),
             State(collector),
         )
-        .await;
+        .await
+        .into_response();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
@@ -180,7 +146,8 @@ mod tests {
             }),
             State(collector),
         )
-        .await;
+        .await
+        .into_response();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
@@ -196,7 +163,8 @@ mod tests {
             }),
             State(collector),
         )
-        .await;
+        .await
+        .into_response();
 
         // Should return 404 when no data exists for timestamp
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
@@ -0,0 +1,123 @@
+use axum::{extract::State, http::HeaderMap, Json};
+use bitcoin_augur::{MempoolSnapshot, BUCKET_MAX};
+use bitcoin_augur_api_types::format_timestamp;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::info;
+
+use super::auth::require_bearer_token;
+use super::error::ApiError;
+use crate::server::AppState;
+
+/// Upper bound on snapshots accepted in a single ingestion request
+const MAX_INGESTED_SNAPSHOTS: usize = 1_000;
+
+/// How far ahead of the server's clock a snapshot timestamp may be
+const MAX_CLOCK_SKEW: Duration = Duration::minutes(5);
+
+/// Body of `POST /snapshots`
+#[derive(Debug, Deserialize)]
+pub struct IngestSnapshotsRequest {
+    /// Snapshots to store, in any order
+    pub snapshots: Vec<MempoolSnapshot>,
+}
+
+/// Result of a snapshot ingestion
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IngestSnapshotsResponse {
+    pub accepted: usize,
+    pub estimate_available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mempool_update_time: Option<String>,
+}
+
+/// Rejects snapshots no collector could have taken by `now`
+fn validate_snapshots(snapshots: &[MempoolSnapshot], now: DateTime<Utc>) -> Result<(), String> {
+    if snapshots.is_empty() {
+        return Err("At least one snapshot is required".to_string());
+    }
+    if snapshots.len() > MAX_INGESTED_SNAPSHOTS {
+        return Err(format!(
+            "At most {MAX_INGESTED_SNAPSHOTS} snapshots can be ingested per request"
+        ));
+    }
+
+    let mut timestamps = HashSet::new();
+    for (index, snapshot) in snapshots.iter().enumerate() {
+        let timestamp = snapshot.timestamp;
+        if snapshot.block_height == 0 {
+            return Err(format!("Snapshot {index} has block height 0"));
+        }
+        if timestamp > now + MAX_CLOCK_SKEW {
+            return Err(format!(
+                "Snapshot {index} is timestamped {timestamp}, in the future"
+            ));
+        }
+        if !timestamps.insert(timestamp) {
+            return Err(format!("Snapshot {index} repeats timestamp {timestamp}"));
+        }
+        if let Some(bucket) = snapshot
+            .bucketed_weights
+            .keys()
+            .chain(snapshot.bucketed_values.keys())
+            .chain(snapshot.bucketed_aged_weights.keys())
+            .chain(snapshot.bucketed_deferred_weights.keys())
+            .find(|&&bucket| bucket > BUCKET_MAX)
+        {
+            return Err(format!(
+                "Snapshot {index} has bucket {bucket} above the maximum of {BUCKET_MAX}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// POST /snapshots - Stores snapshots from an external collector and recomputes the estimate
+pub async fn ingest_snapshots(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<IngestSnapshotsRequest>,
+) -> Result<Json<IngestSnapshotsResponse>, ApiError> {
+    require_bearer_token(&headers, state.api.ingest_token.as_deref())?;
+    validate_snapshots(&request.snapshots, state.collector.now()).map_err(ApiError::BadRequest)?;
+
+    let accepted = request.snapshots.len();
+    info!("Ingesting {accepted} snapshots from an external collector");
+
+    let estimate = state.collector.ingest_snapshots(request.snapshots).await?;
+
+    Ok(Json(IngestSnapshotsResponse {
+        accepted,
+        estimate_available: estimate.is_some(),
+        mempool_update_time: estimate.map(|e| format_timestamp(e.timestamp)),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::BTreeMap;
+
+    fn snapshot(height: u32, timestamp: DateTime<Utc>) -> MempoolSnapshot {
+        MempoolSnapshot::new(height, timestamp, BTreeMap::from([(100, 4_000)]))
+    }
+
+    #[test]
+    fn test_validate_snapshots() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let earlier = now - Duration::minutes(1);
+        assert!(validate_snapshots(&[snapshot(100, earlier), snapshot(101, now)], now).is_ok());
+
+        assert!(validate_snapshots(&[], now).is_err());
+        assert!(validate_snapshots(&[snapshot(0, now)], now).is_err());
+        assert!(validate_snapshots(&[snapshot(100, now + Duration::hours(1))], now).is_err());
+        assert!(validate_snapshots(&[snapshot(100, now), snapshot(101, now)], now).is_err());
+
+        let mut out_of_range = snapshot(100, now);
+        out_of_range.bucketed_weights.insert(BUCKET_MAX + 1, 4_000);
+        assert!(validate_snapshots(&[out_of_range], now).is_err());
+    }
+}
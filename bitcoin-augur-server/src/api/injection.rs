@@ -0,0 +1,68 @@
+use axum::{extract::State, http::HeaderMap, Json};
+use bitcoin_augur::MempoolSnapshot;
+use bitcoin_augur_api_types::format_timestamp;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::auth::require_bearer_token;
+use super::error::ApiError;
+use crate::server::AppState;
+
+/// Upper bound on snapshots accepted in a single injection request
+const MAX_INJECTED_SNAPSHOTS: usize = 10_000;
+
+/// Body of `POST /test/snapshots`
+#[derive(Debug, Deserialize)]
+pub struct InjectSnapshotsRequest {
+    /// Snapshots to store, in any order
+    pub snapshots: Vec<MempoolSnapshot>,
+    /// Remove all previously stored snapshots first
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// Result of a snapshot injection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InjectSnapshotsResponse {
+    pub accepted: usize,
+    pub estimate_available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mempool_update_time: Option<String>,
+}
+
+/// POST /test/snapshots - Injects generated snapshots and recomputes the estimate (test-only)
+pub async fn inject_snapshots(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<InjectSnapshotsRequest>,
+) -> Result<Json<InjectSnapshotsResponse>, ApiError> {
+    require_bearer_token(&headers, state.api.test_api_token.as_deref())?;
+
+    if request.snapshots.is_empty() {
+        return Err(ApiError::BadRequest(
+            "At least one snapshot is required".to_string(),
+        ));
+    }
+    if request.snapshots.len() > MAX_INJECTED_SNAPSHOTS {
+        return Err(ApiError::BadRequest(format!(
+            "At most {MAX_INJECTED_SNAPSHOTS} snapshots can be injected per request"
+        )));
+    }
+
+    let accepted = request.snapshots.len();
+    info!(
+        "Injecting {accepted} snapshots (replace: {replace})",
+        replace = request.replace
+    );
+
+    let estimate = state
+        .collector
+        .inject_snapshots(request.snapshots, request.replace)
+        .await?;
+
+    Ok(Json(InjectSnapshotsResponse {
+        accepted,
+        estimate_available: estimate.is_some(),
+        mempool_update_time: estimate.map(|e| format_timestamp(e.timestamp)),
+    }))
+}
@@ -0,0 +1,185 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::service::{AlertStatus, LeaderStatus, MempoolCollector};
+
+/// Content type of the Prometheus text exposition format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// GET /metrics - Returns the state of every alert rule in the Prometheus text format, as
+/// `augur_alert_firing` and `augur_alert_value` gauges labelled by rule and severity, followed
+/// by how fresh the mempool data is and, when electing a collector, which instance leads
+pub async fn get_metrics(State(collector): State<Arc<MempoolCollector>>) -> impl IntoResponse {
+    let mut text = render(&collector.alert_statuses());
+    text.push_str(&render_freshness(
+        collector.data_age().await,
+        collector.is_stale().await,
+        collector.rpc_error().await.is_some(),
+    ));
+    text.push_str(
+        "# HELP augur_coalesced_requests_total Block target requests that joined an estimate \
+         already being computed\n",
+    );
+    text.push_str("# TYPE augur_coalesced_requests_total counter\n");
+    let _ = writeln!(
+        text,
+        "augur_coalesced_requests_total {}",
+        collector.coalesced_count()
+    );
+    if let Some(status) = collector.leader_status().await {
+        text.push_str(&render_leader(&status));
+    }
+    ([(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], text)
+}
+
+/// Renders the age of the latest snapshot, if any, whether it is stale and whether the latest
+/// mempool fetch failed as Prometheus gauges
+fn render_freshness(data_age: Option<chrono::Duration>, stale: bool, rpc_failing: bool) -> String {
+    let mut text = String::new();
+    if let Some(age) = data_age {
+        text.push_str("# HELP augur_data_age_seconds Seconds since the latest mempool snapshot\n");
+        text.push_str("# TYPE augur_data_age_seconds gauge\n");
+        let _ = writeln!(text, "augur_data_age_seconds {}", age.num_seconds());
+    }
+    text.push_str(
+        "# HELP augur_data_stale Whether the latest snapshot is older than the maximum staleness\n",
+    );
+    text.push_str("# TYPE augur_data_stale gauge\n");
+    let _ = writeln!(text, "augur_data_stale {}", u8::from(stale));
+    text.push_str("# HELP augur_rpc_failing Whether the latest mempool fetch failed\n");
+    text.push_str("# TYPE augur_rpc_failing gauge\n");
+    let _ = writeln!(text, "augur_rpc_failing {}", u8::from(rpc_failing));
+    text
+}
+
+/// Renders whether this instance holds the collector lease, and which instance does, as
+/// Prometheus gauges
+fn render_leader(status: &LeaderStatus) -> String {
+    let mut text = String::new();
+    text.push_str("# HELP augur_leader Whether this instance holds the collector lease\n");
+    text.push_str("# TYPE augur_leader gauge\n");
+    let _ = writeln!(
+        text,
+        "augur_leader{{instance=\"{instance}\"}} {leading}",
+        instance = escape(&status.instance),
+        leading = u8::from(status.is_leader())
+    );
+    if let Some(leader) = &status.leader {
+        text.push_str("# HELP augur_leader_info Instance holding the collector lease\n");
+        text.push_str("# TYPE augur_leader_info gauge\n");
+        let _ = writeln!(
+            text,
+            "augur_leader_info{{leader=\"{leader}\"}} 1",
+            leader = escape(leader)
+        );
+    }
+    text
+}
+
+/// Renders `statuses` as Prometheus gauges
+fn render(statuses: &[AlertStatus]) -> String {
+    let mut text = String::new();
+    text.push_str("# HELP augur_alert_firing Whether the alert rule is firing\n");
+    text.push_str("# TYPE augur_alert_firing gauge\n");
+    for status in statuses {
+        let _ = writeln!(
+            text,
+            "augur_alert_firing{{{labels}}} {firing}",
+            labels = labels(status),
+            firing = u8::from(status.firing)
+        );
+    }
+    text.push_str("# HELP augur_alert_value Latest value of the metric the alert rule watches\n");
+    text.push_str("# TYPE augur_alert_value gauge\n");
+    for status in statuses {
+        if let Some(value) = status.value {
+            let _ = writeln!(
+                text,
+                "augur_alert_value{{{labels}}} {value}",
+                labels = labels(status)
+            );
+        }
+    }
+    text
+}
+
+fn labels(status: &AlertStatus) -> String {
+    format!(
+        "rule=\"{rule}\",severity=\"{severity}\"",
+        rule = escape(&status.rule),
+        severity = status.severity
+    )
+}
+
+/// Escapes `value` for use inside a quoted label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::AlertSeverity;
+
+    #[test]
+    fn test_render_alert_gauges() {
+        let statuses = [
+            AlertStatus {
+                rule: "high_fees".to_string(),
+                severity: AlertSeverity::Critical,
+                firing: true,
+                value: Some(250.5),
+            },
+            AlertStatus {
+                rule: "say \"hi\"".to_string(),
+                severity: AlertSeverity::Info,
+                firing: false,
+                value: None,
+            },
+        ];
+        let text = render(&statuses);
+        assert!(text.contains("augur_alert_firing{rule=\"high_fees\",severity=\"critical\"} 1\n"));
+        assert!(text.contains("augur_alert_firing{rule=\"say \\\"hi\\\"\",severity=\"info\"} 0\n"));
+        assert!(
+            text.contains("augur_alert_value{rule=\"high_fees\",severity=\"critical\"} 250.5\n")
+        );
+        assert!(!text.contains("augur_alert_value{rule=\"say"));
+    }
+
+    #[test]
+    fn test_render_freshness_gauges() {
+        let text = render_freshness(Some(chrono::Duration::seconds(42)), true, false);
+        assert!(text.contains("augur_data_age_seconds 42\n"));
+        assert!(text.contains("augur_data_stale 1\n"));
+        assert!(text.contains("augur_rpc_failing 0\n"));
+
+        // No age before the first snapshot
+        let text = render_freshness(None, false, true);
+        assert!(!text.contains("augur_data_age_seconds"));
+        assert!(text.contains("augur_rpc_failing 1\n"));
+    }
+
+    #[test]
+    fn test_render_leader_gauges() {
+        let status = LeaderStatus {
+            instance: "augur-a".to_string(),
+            leader: Some("augur-b".to_string()),
+        };
+        let text = render_leader(&status);
+        assert!(text.contains("augur_leader{instance=\"augur-a\"} 0\n"));
+        assert!(text.contains("augur_leader_info{leader=\"augur-b\"} 1\n"));
+
+        // No leader info while nobody holds the lease
+        let status = LeaderStatus {
+            instance: "augur-a".to_string(),
+            leader: None,
+        };
+        let text = render_leader(&status);
+        assert!(text.contains("augur_leader{instance=\"augur-a\"} 0\n"));
+        assert!(!text.contains("augur_leader_info"));
+    }
+}
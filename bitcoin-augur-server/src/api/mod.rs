@@ -1,9 +1,68 @@
 //! HTTP API endpoints for fee estimation service
 
+mod admin;
+mod auth;
+mod bump;
+mod compare;
+mod curve;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod debug;
+mod depth;
 mod error;
+mod estimator_config;
+mod export;
 mod fee_endpoint;
+mod format;
+mod grafana;
+mod health;
+mod histogram;
 mod historical;
-mod models;
+mod ingest;
+mod injection;
+mod metrics;
+mod naming;
+mod position;
+mod preset;
+mod signing;
+mod staleness;
+mod stream;
+mod suggest;
+mod sync;
 
+pub use admin::get_cycles;
+#[allow(unused_imports)]
+pub use bitcoin_augur_api_types::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE};
+pub use bump::{get_cpfp_bump, get_rbf_bump};
+pub use compare::{get_fee_comparison, get_source_comparison};
+pub use curve::{get_confidence_curve, get_target_curve};
+#[cfg(feature = "dashboard")]
+pub use dashboard::get_dashboard;
+pub use debug::{get_realized_blocks, get_simulation};
+pub use depth::get_mempool_depth;
+pub use error::problem_details;
+#[allow(unused_imports)]
+pub use error::ApiError;
+pub use estimator_config::get_estimator_config;
+pub use export::export_historical_fees;
 pub use fee_endpoint::{get_fee_for_target, get_fees};
+pub use format::response_format;
+#[allow(unused_imports)]
+pub use format::{round_numbers, round_to};
+pub use grafana::{grafana_health, query_metrics, search_metrics};
+pub use health::get_readiness;
+pub use histogram::get_mempool_histogram;
 pub use historical::get_historical_fee;
+pub use ingest::ingest_snapshots;
+pub use injection::inject_snapshots;
+pub use metrics::get_metrics;
+pub use naming::{field_naming, FieldNaming};
+pub use position::get_position;
+pub use preset::get_preset;
+pub use signing::{sign_response, ResponseSigner};
+#[allow(unused_imports)]
+pub use signing::{KEY_ID_HEADER, SIGNATURE_HEADER};
+pub use staleness::reject_stale;
+pub use stream::get_fee_stream;
+pub use suggest::get_suggestion;
+pub use sync::get_sync_changes;
@@ -0,0 +1,139 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::format::{is_json, rewrite_json};
+
+/// Casing of JSON field names in responses
+///
+/// Response models are written in snake_case; camelCase is produced by renaming the fields of
+/// the serialized body. Clients pick a convention per request with an
+/// `Accept: application/json; profile="camelCase"` (or `"snake_case"`) header, falling back
+/// to the configured default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldNaming {
+    #[default]
+    SnakeCase,
+    CamelCase,
+}
+
+impl FieldNaming {
+    /// Convention requested by the `profile` parameter of the `Accept` header, if any
+    fn from_accept(headers: &HeaderMap) -> Option<Self> {
+        let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+        accept
+            .split([',', ';'])
+            .filter_map(|param| param.trim().strip_prefix("profile="))
+            .find_map(|profile| match profile.trim_matches('"') {
+                "camelCase" => Some(Self::CamelCase),
+                "snake_case" => Some(Self::SnakeCase),
+                _ => None,
+            })
+    }
+}
+
+/// Converts a snake_case name to camelCase, leaving names without underscores unchanged
+fn to_camel_case(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = !camel.is_empty();
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+/// Renames every object key in `value` to camelCase
+fn camel_case_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (to_camel_case(&key), camel_case_keys(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(camel_case_keys).collect()),
+        value => value,
+    }
+}
+
+/// Middleware rendering JSON response fields in the convention the request asks for
+pub async fn field_naming(
+    State(default): State<FieldNaming>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let naming = FieldNaming::from_accept(request.headers()).unwrap_or(default);
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+
+    if naming == FieldNaming::SnakeCase || !is_json(&response) {
+        return response;
+    }
+    rewrite_json(response, "renaming", camel_case_keys).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_camel_case() {
+        assert_eq!(to_camel_case("mempool_update_time"), "mempoolUpdateTime");
+        assert_eq!(to_camel_case("fee_rate"), "feeRate");
+        assert_eq!(to_camel_case("estimates"), "estimates");
+        assert_eq!(to_camel_case("0.05"), "0.05");
+        assert_eq!(to_camel_case("_private"), "private");
+    }
+
+    #[test]
+    fn test_camel_case_keys_renames_nested_objects() {
+        let body = json!({
+            "mempool_update_time": "2025-01-20T12:00:00.000Z",
+            "estimates": {"6": {"probabilities": {"0.50": {"fee_rate": 5.2}}}},
+            "buckets": [{"initial_weight": 4000, "added_weight": null}],
+        });
+        assert_eq!(
+            camel_case_keys(body),
+            json!({
+                "mempoolUpdateTime": "2025-01-20T12:00:00.000Z",
+                "estimates": {"6": {"probabilities": {"0.50": {"feeRate": 5.2}}}},
+                "buckets": [{"initialWeight": 4000, "addedWeight": null}],
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_accept() {
+        let accept = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+            FieldNaming::from_accept(&headers)
+        };
+        assert_eq!(
+            accept("application/json; profile=\"camelCase\""),
+            Some(FieldNaming::CamelCase)
+        );
+        assert_eq!(
+            accept("application/json;profile=snake_case, */*"),
+            Some(FieldNaming::SnakeCase)
+        );
+        assert_eq!(accept("application/json"), None);
+        assert_eq!(accept("application/json; profile=\"kebab\""), None);
+        assert_eq!(FieldNaming::from_accept(&HeaderMap::new()), None);
+    }
+}
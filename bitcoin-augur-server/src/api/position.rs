@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::error::ApiError;
+use crate::service::MempoolCollector;
+
+/// Query parameters for the position endpoint
+#[derive(Debug, Deserialize)]
+pub struct PositionQuery {
+    /// Fee rate of the transaction in sat/vB
+    fee_rate: f64,
+}
+
+/// How soon a transaction should confirm at one confidence level
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpectedConfirmation {
+    pub confidence: f64,
+    /// Shortest block target whose fee rate the transaction pays, or null if it pays less than
+    /// the longest target needs
+    pub blocks: Option<u32>,
+}
+
+/// Response of `GET /position`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PositionResponse {
+    pub mempool_update_time: String,
+    pub block_height: u32,
+    pub fee_rate: f64,
+    /// Virtual size of the mempool paying a higher fee rate, mined first
+    pub vbytes_above: u64,
+    /// Expected confirmation at each confidence level, lowest first
+    pub expected_blocks: Vec<ExpectedConfirmation>,
+    /// The node's current mempool minimum fee in sat/vB, or null if the node did not answer
+    pub mempool_min_fee: Option<f64>,
+    /// Whether the node's mempool would reject the transaction for paying too little
+    pub below_mempool_min_fee: Option<bool>,
+}
+
+/// GET /position?fee_rate={sat/vB} - Returns where a transaction paying `fee_rate` stands in the
+/// mempool and how soon it should confirm
+pub async fn get_position(
+    Query(PositionQuery { fee_rate }): Query<PositionQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<PositionResponse>, ApiError> {
+    info!("Received position request for {fee_rate} sat/vB");
+
+    if !fee_rate.is_finite() || fee_rate <= 0.0 {
+        warn!("Invalid fee_rate parameter: {fee_rate}");
+        return Err(ApiError::BadRequest(
+            "Invalid fee_rate: must be a positive number of sat/vB".to_string(),
+        ));
+    }
+
+    let (Some(snapshot), Some(estimate)) = (
+        collector.get_latest_snapshot().await,
+        collector.get_latest_estimate().await,
+    ) else {
+        return Err(match collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No fee estimates available yet".to_string()),
+        });
+    };
+
+    let expected_blocks = estimate
+        .get_available_confidence_levels()
+        .into_iter()
+        .map(|confidence| ExpectedConfirmation {
+            confidence,
+            blocks: estimate
+                .target_for_fee_rate(fee_rate, confidence)
+                .map(|target| target.blocks()),
+        })
+        .collect();
+    let mempool_min_fee = collector
+        .get_mempool_min_fee()
+        .await
+        .inspect_err(|e| warn!("Failed to fetch the mempool minimum fee: {e}"))
+        .ok();
+
+    Ok(Json(PositionResponse {
+        mempool_update_time: format_timestamp(snapshot.timestamp),
+        block_height: snapshot.block_height,
+        fee_rate,
+        vbytes_above: snapshot.vbytes_above(fee_rate),
+        expected_blocks,
+        mempool_min_fee: mempool_min_fee.map(format_fee_rate),
+        below_mempool_min_fee: mempool_min_fee.map(|min_fee| fee_rate < min_fee),
+    }))
+}
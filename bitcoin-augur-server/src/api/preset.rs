@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use bitcoin_augur::BucketWeighting;
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::error::ApiError;
+use crate::server::AppState;
+
+/// Response of `GET /fees/preset/{name}`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresetResponse {
+    pub mempool_update_time: String,
+    pub preset: String,
+    /// Block target the preset stands for
+    pub target_blocks: u32,
+    /// Confidence level the preset stands for
+    pub confidence: f64,
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+}
+
+/// GET /fees/preset/{name} - Returns the fee rate for a named preset such as `economy`, whose
+/// block target and confidence are configured on the server
+pub async fn get_preset(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<PresetResponse>, ApiError> {
+    info!("Received request for fee preset {name}");
+
+    let Some(preset) = state.api.presets.get(&name).copied() else {
+        warn!("Unknown fee preset: {name}");
+        return Err(ApiError::NotFound(format!(
+            "Unknown preset {name:?}; available presets: {available}",
+            available = state
+                .api
+                .presets
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    };
+
+    // The preset's confidence need not be one of the estimator's own levels
+    let estimate = state
+        .collector
+        .get_confidence_curve(
+            preset.target,
+            vec![preset.confidence],
+            BucketWeighting::Weight,
+        )
+        .await?;
+    let Some((target_blocks, fee_rate)) = estimate
+        .targets()
+        .find_map(|(blocks, target)| Some((blocks, target.get_fee_rate(preset.confidence)?)))
+    else {
+        return Err(match state.collector.rpc_error().await {
+            Some(error) => ApiError::UpstreamRpcDown(format!("Bitcoin RPC error: {error}")),
+            None => ApiError::WarmingUp("No fee estimates available yet".to_string()),
+        });
+    };
+
+    Ok(Json(PresetResponse {
+        mempool_update_time: format_timestamp(estimate.timestamp),
+        preset: name,
+        target_blocks: target_blocks.blocks(),
+        confidence: preset.confidence,
+        fee_rate: format_fee_rate(fee_rate),
+    }))
+}
@@ -0,0 +1,172 @@
+//! Ed25519 signing of fee estimate responses
+//!
+//! When a signing key is configured, every successful fee response carries the base64 signature
+//! of its exact body bytes in `X-Augur-Signature` and the signing key's identifier in
+//! `X-Augur-Key-Id`. Consumers holding the public key verify the body as received, before
+//! parsing it.
+
+use anyhow::{bail, ensure, Context, Result};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{Signer, SigningKey};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::error;
+
+/// Header carrying the base64 Ed25519 signature of the response body
+pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-augur-signature");
+
+/// Header identifying the key that produced the signature
+pub const KEY_ID_HEADER: HeaderName = HeaderName::from_static("x-augur-key-id");
+
+/// Signs response bodies with an Ed25519 key
+pub struct ResponseSigner {
+    key: SigningKey,
+    key_id: HeaderValue,
+}
+
+impl ResponseSigner {
+    /// Creates a signer from a 32-byte secret key, identified by `key_id` or, if none is given,
+    /// by its hex-encoded public key
+    pub fn new(secret: [u8; 32], key_id: Option<&str>) -> Result<Self> {
+        let key = SigningKey::from_bytes(&secret);
+        let key_id = match key_id {
+            Some(id) => id.to_string(),
+            None => to_hex(key.verifying_key().as_bytes()),
+        };
+        ensure!(
+            !key_id.is_empty() && key_id.chars().all(|c| c.is_ascii_graphic()),
+            "Signing key id must be non-empty printable ASCII without spaces"
+        );
+        let key_id = HeaderValue::from_str(&key_id).context("Invalid signing key id")?;
+        Ok(Self { key, key_id })
+    }
+
+    /// Loads the secret key from a file holding it as 64 hex characters
+    /// (e.g. `openssl rand -hex 32`)
+    pub fn from_key_file(path: impl AsRef<Path>, key_id: Option<&str>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read signing key file {path:?}"))?;
+        let secret = parse_hex_key(contents.trim())
+            .with_context(|| format!("Invalid signing key in {path:?}"))?;
+        Self::new(secret, key_id)
+    }
+
+    /// Identifier sent with every signature
+    pub fn key_id(&self) -> &str {
+        self.key_id.to_str().unwrap_or_default()
+    }
+
+    /// Hex-encoded public key consumers verify signatures with
+    pub fn public_key_hex(&self) -> String {
+        to_hex(self.key.verifying_key().as_bytes())
+    }
+
+    /// Base64 signature of `body`
+    pub fn sign(&self, body: &[u8]) -> String {
+        STANDARD.encode(self.key.sign(body).to_bytes())
+    }
+}
+
+fn parse_hex_key(hex: &str) -> Result<[u8; 32]> {
+    ensure!(
+        hex.len() == 64,
+        "Expected 64 hex characters, found {len}",
+        len = hex.len()
+    );
+    let mut key = [0u8; 32];
+    for (byte, chunk) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let Ok(pair) = std::str::from_utf8(chunk) else {
+            bail!("Key is not hex encoded");
+        };
+        *byte = u8::from_str_radix(pair, 16).context("Key is not hex encoded")?;
+    }
+    Ok(key)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Middleware adding the signature headers to successful responses
+pub async fn sign_response(
+    State(signer): State<Arc<ResponseSigner>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to buffer response for signing: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match HeaderValue::from_str(&signer.sign(&bytes)) {
+        Ok(signature) => {
+            parts.headers.insert(SIGNATURE_HEADER, signature);
+            parts.headers.insert(KEY_ID_HEADER, signer.key_id.clone());
+        }
+        Err(e) => {
+            error!("Failed to encode response signature: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier};
+
+    const SECRET_HEX: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
+    #[test]
+    fn test_signature_verifies_against_public_key() {
+        let signer = ResponseSigner::new(parse_hex_key(SECRET_HEX).unwrap(), None).unwrap();
+        // Public key of the RFC 8032 test vector for this secret
+        assert_eq!(
+            signer.public_key_hex(),
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+        );
+        assert_eq!(signer.key_id(), signer.public_key_hex());
+
+        let body = br#"{"mempool_update_time":"2025-01-20T12:00:00.000Z","estimates":{}}"#;
+        let signature = STANDARD.decode(signer.sign(body)).unwrap();
+        let signature = Signature::from_slice(&signature).unwrap();
+        let public_key = signer.key.verifying_key();
+        assert!(public_key.verify(body, &signature).is_ok());
+        assert!(public_key.verify(b"tampered", &signature).is_err());
+    }
+
+    #[test]
+    fn test_key_file_parsing() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("signing.key");
+
+        std::fs::write(&path, format!("{SECRET_HEX}\n")).unwrap();
+        let signer = ResponseSigner::from_key_file(&path, Some("augur-2025")).unwrap();
+        assert_eq!(signer.key_id(), "augur-2025");
+
+        std::fs::write(&path, &SECRET_HEX[..62]).unwrap();
+        assert!(ResponseSigner::from_key_file(&path, None).is_err());
+        std::fs::write(&path, SECRET_HEX.replace('9', "g")).unwrap();
+        assert!(ResponseSigner::from_key_file(&path, None).is_err());
+        assert!(ResponseSigner::from_key_file(temp_dir.path().join("missing"), None).is_err());
+        assert!(ResponseSigner::new([1; 32], Some("two words")).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tracing::warn;
+
+use super::error::ApiError;
+use crate::service::MempoolCollector;
+
+/// Rejects requests for current estimates with 503 while the latest snapshot is older than the
+/// collector's maximum staleness, naming the RPC error that keeps it from refreshing if any
+pub async fn reject_stale(
+    State(collector): State<Arc<MempoolCollector>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !collector.is_stale().await {
+        return next.run(request).await;
+    }
+
+    let age = collector.data_age().await.unwrap_or_default();
+    let max_staleness = collector.max_staleness().unwrap_or_default();
+    let mut detail = format!(
+        "Latest mempool snapshot is {age}s old, above the maximum of {max}s",
+        age = age.num_seconds(),
+        max = max_staleness.num_seconds()
+    );
+    if let Some(error) = collector.rpc_error().await {
+        detail.push_str(&format!("; Bitcoin RPC error: {error}"));
+    }
+    warn!("Refusing to serve stale estimates: {detail}");
+    ApiError::StaleData(detail).into_response()
+}
@@ -0,0 +1,85 @@
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+
+use super::fee_endpoint::current_response;
+use super::format::round_numbers;
+use crate::config::ResponseConfig;
+use crate::server::shutdown_receiver;
+use crate::service::MempoolCollector;
+
+/// How often a stream checks whether a new estimate was published
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Name of the server-sent events carrying estimates
+const FEES_EVENT: &str = "fees";
+
+/// State of one subscriber's stream
+struct Subscription {
+    collector: Arc<MempoolCollector>,
+    /// Time of the last estimate sent
+    last: Option<DateTime<Utc>>,
+    format: ResponseConfig,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl Subscription {
+    /// Waits for an estimate newer than the last one sent, or `None` on shutdown
+    async fn next_event(&mut self) -> Option<Result<Event, axum::Error>> {
+        loop {
+            if *self.shutdown.borrow() {
+                return None;
+            }
+            if let Some(estimate) = self.collector.get_latest_estimate().await {
+                if self.last != Some(estimate.timestamp) {
+                    self.last = Some(estimate.timestamp);
+                    let response = current_response(&self.collector, estimate).await;
+                    let id = response.mempool_update_time.clone();
+                    let event = serde_json::to_value(response)
+                        .map_err(axum::Error::new)
+                        .and_then(|body| {
+                            Event::default()
+                                .event(FEES_EVENT)
+                                .id(id)
+                                .json_data(round_numbers(body, self.format.decimal_places))
+                        });
+                    return Some(event);
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                _ = self.shutdown.changed() => {}
+            }
+        }
+    }
+}
+
+/// GET /fees/stream - Server-sent `fees` events with the same body as `/fees`, one for the
+/// current estimate and one whenever a new estimate is published
+///
+/// The stream ends when the server shuts down, so graceful shutdown does not wait on it.
+pub async fn get_fee_stream(
+    State(collector): State<Arc<MempoolCollector>>,
+    State(format): State<ResponseConfig>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    info!("Client subscribed to fee estimate updates");
+
+    let subscription = Subscription {
+        collector,
+        last: None,
+        format,
+        shutdown: shutdown_receiver(),
+    };
+    let events = stream::unfold(subscription, |mut subscription| async move {
+        let event = subscription.next_event().await?;
+        Some((event, subscription))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bitcoin_augur::{FeeSuggestion, WU_PER_BYTE};
+use bitcoin_augur_api_types::{format_fee_rate, format_timestamp};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::error::ApiError;
+use super::fee_endpoint::MAX_TARGET_BLOCKS;
+use crate::service::MempoolCollector;
+
+/// Largest transaction size accepted, the size of a whole block
+pub(super) const MAX_VSIZE: u64 = 1_000_000;
+
+/// Query parameters for the fee suggestion endpoint
+#[derive(Debug, Deserialize)]
+pub struct SuggestQuery {
+    /// Transaction size in virtual bytes
+    vsize: u64,
+    /// Latest acceptable confirmation, in blocks
+    deadline: u32,
+    /// Minimum confidence of confirming by the deadline
+    #[serde(default = "default_confidence")]
+    confidence: f64,
+}
+
+pub(super) fn default_confidence() -> f64 {
+    0.5
+}
+
+/// Response of `GET /suggest`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SuggestResponse {
+    pub mempool_update_time: String,
+    pub vsize: u64,
+    pub deadline_blocks: u32,
+    /// Block target the fee rate was taken from
+    pub target_blocks: u32,
+    /// Confidence level the fee rate was taken from
+    pub confidence: f64,
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+    /// Absolute fee in satoshis for `vsize`
+    pub fee: u64,
+}
+
+/// GET /suggest?vsize={vbytes}&deadline={blocks}&confidence={p} - Returns the fee rate and
+/// absolute fee for a transaction to confirm within the deadline
+pub async fn get_suggestion(
+    Query(params): Query<SuggestQuery>,
+    State(collector): State<Arc<MempoolCollector>>,
+) -> Result<Json<SuggestResponse>, ApiError> {
+    let SuggestQuery {
+        vsize,
+        deadline,
+        confidence,
+    } = params;
+
+    info!("Received fee suggestion request for {vsize} vB within {deadline} blocks");
+
+    let (timestamp, suggestion) = suggest(&collector, vsize, deadline, confidence).await?;
+
+    Ok(Json(SuggestResponse {
+        mempool_update_time: format_timestamp(timestamp),
+        vsize: suggestion.vsize,
+        deadline_blocks: deadline,
+        target_blocks: suggestion.target_blocks,
+        confidence: suggestion.confidence,
+        fee_rate: format_fee_rate(suggestion.fee_rate),
+        fee: suggestion.fee,
+    }))
+}
+
+/// Validates a suggestion request and answers it from a fresh estimate for `deadline`
+///
+/// Returns the estimate's timestamp with the suggestion.
+pub(super) async fn suggest(
+    collector: &MempoolCollector,
+    vsize: u64,
+    deadline: u32,
+    confidence: f64,
+) -> Result<(DateTime<Utc>, FeeSuggestion), ApiError> {
+    if vsize == 0 || vsize > MAX_VSIZE {
+        warn!("Invalid vsize parameter: {vsize}");
+        return Err(ApiError::BadRequest(format!(
+            "Invalid vsize: must be between 1 and {MAX_VSIZE}"
+        )));
+    }
+    if deadline == 0 || f64::from(deadline) > MAX_TARGET_BLOCKS {
+        warn!("Invalid deadline parameter: {deadline}");
+        return Err(ApiError::InvalidTarget(format!(
+            "Invalid deadline: must be between 1 and {MAX_TARGET_BLOCKS} blocks"
+        )));
+    }
+    if !(0.0..=1.0).contains(&confidence) {
+        warn!("Invalid confidence parameter: {confidence}");
+        return Err(ApiError::BadRequest(
+            "Invalid confidence: must be between 0 and 1".to_string(),
+        ));
+    }
+
+    let estimate = collector
+        .get_estimate_for_blocks(f64::from(deadline))
+        .await?;
+    if estimate.is_empty() {
+        return Err(ApiError::WarmingUp(
+            "No fee estimates available yet".to_string(),
+        ));
+    }
+
+    let weight = vsize * WU_PER_BYTE as u64;
+    let suggestion = estimate
+        .suggest_feerate_for(weight, deadline, confidence)
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "No estimate reaches a confidence of {confidence} within {deadline} blocks"
+            ))
+        })?;
+
+    Ok((estimate.timestamp, suggestion))
+}
@@ -0,0 +1,86 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Duration};
+use serde::Deserialize;
+use tracing::info;
+
+use super::auth::require_bearer_token;
+use super::error::ApiError;
+use crate::server::AppState;
+use crate::service::SyncChanges;
+
+/// Snapshots returned when the request does not ask for a number
+const DEFAULT_SYNC_LIMIT: usize = 1_000;
+
+/// Most snapshots returned per request
+const MAX_SYNC_LIMIT: usize = 10_000;
+
+/// Stretch of history loaded at a time while collecting changes
+const SYNC_WINDOW: Duration = Duration::days(1);
+
+/// Oldest history served, as for historical estimates
+const MAX_SYNC_AGE: Duration = Duration::days(365);
+
+/// Query parameters for the sync endpoint
+#[derive(Debug, Deserialize)]
+pub struct SyncQuery {
+    /// Unix time in seconds the returned snapshots are after; a year ago if left out
+    since: Option<i64>,
+    /// Most snapshots to return
+    limit: Option<usize>,
+}
+
+/// GET /sync/changes?since={unix_ts}&limit={n} - Returns the stored snapshots after `since`,
+/// oldest first, for a secondary server to copy
+pub async fn get_sync_changes(
+    Query(SyncQuery { since, limit }): Query<SyncQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SyncChanges>, ApiError> {
+    require_bearer_token(&headers, state.api.sync_token.as_deref())?;
+
+    let limit = limit.unwrap_or(DEFAULT_SYNC_LIMIT);
+    if !(1..=MAX_SYNC_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid limit: must be between 1 and {MAX_SYNC_LIMIT}"
+        )));
+    }
+    let now = state.collector.now();
+    let oldest = now - MAX_SYNC_AGE;
+    let since = match since {
+        Some(since) => DateTime::from_timestamp(since, 0)
+            .ok_or_else(|| ApiError::BadRequest("Invalid since timestamp".to_string()))?,
+        None => oldest,
+    };
+    info!("Received request for up to {limit} snapshots after {since}");
+
+    // Windows share their bounds, so snapshots are kept only past the last one taken
+    let mut next_since = since.timestamp();
+    let mut snapshots = Vec::new();
+    let mut more = false;
+    let mut from = since.max(oldest);
+    'windows: while from < now {
+        let to = (from + SYNC_WINDOW).min(now);
+        for snapshot in state.collector.snapshots_between(from, to).await? {
+            if snapshot.timestamp.timestamp() <= next_since {
+                continue;
+            }
+            if snapshots.len() == limit {
+                more = true;
+                break 'windows;
+            }
+            next_since = snapshot.timestamp.timestamp();
+            snapshots.push(snapshot);
+        }
+        from = to;
+    }
+
+    Ok(Json(SyncChanges {
+        snapshots,
+        next_since,
+        more,
+    }))
+}
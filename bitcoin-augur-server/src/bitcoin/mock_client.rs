@@ -1,13 +1,191 @@
-use super::RpcError;
+use super::{MinedBlock, RpcError, TxMempoolEntry};
+use anyhow::{ensure, Context, Result};
 use bitcoin_augur::MempoolTransaction;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::debug;
 
 /// Mock Bitcoin RPC client for testing
 #[derive(Clone, Default)]
-pub struct MockBitcoinClient;
+pub struct MockBitcoinClient {
+    scenario: Option<Arc<ScenarioTimeline>>,
+    polls: Arc<AtomicUsize>,
+    mempool_entries: Arc<HashMap<String, TxMempoolEntry>>,
+    blocks: Arc<HashMap<u32, MinedBlock>>,
+}
+
+/// Scripted sequence of mempool states loaded from a scenario file
+///
+/// ```yaml
+/// name: fee-spike
+/// repeat: false
+/// steps:
+///   - block_height: 850000
+///     polls: 10
+///     transactions:
+///       - { fee_rate: 2.0, weight: 2000, count: 500 }
+///   - blocks_mined: 1
+///     transactions:
+///       - { fee_rate: 50.0, weight: 2000, count: 2000 }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    /// Human readable scenario name
+    #[serde(default)]
+    pub name: String,
+    /// Start over from the first step after the last one instead of holding it
+    #[serde(default)]
+    pub repeat: bool,
+    /// Mempool states, each served for `polls` collection cycles
+    pub steps: Vec<ScenarioStep>,
+}
+
+/// A single mempool state within a scenario
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioStep {
+    /// Absolute block height for this step
+    #[serde(default)]
+    pub block_height: Option<u32>,
+    /// Blocks mined since the previous step (ignored when `block_height` is set)
+    #[serde(default)]
+    pub blocks_mined: u32,
+    /// Number of polls this state is returned for (default: 1)
+    #[serde(default = "default_polls")]
+    pub polls: usize,
+    /// Mempool contents; an empty list models a fully drained or evicted mempool
+    #[serde(default)]
+    pub transactions: Vec<ScenarioTransaction>,
+}
+
+/// A group of identical transactions in a scenario step
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioTransaction {
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+    /// Transaction weight in weight units
+    pub weight: u64,
+    /// Number of copies (default: 1)
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+fn default_polls() -> usize {
+    1
+}
+
+fn default_count() -> usize {
+    1
+}
+
+/// Scenario expanded into one entry per poll
+struct ScenarioTimeline {
+    states: Vec<(u32, Vec<MempoolTransaction>)>,
+    repeat: bool,
+}
+
+const DEFAULT_BLOCK_HEIGHT: u32 = 850000;
+
+impl Scenario {
+    /// Loads a scenario from a YAML or JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).with_context(|| {
+            format!("Failed to read scenario file {path}", path = path.display())
+        })?;
+
+        let scenario: Scenario = if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid scenario file {path}", path = path.display()))?
+        } else {
+            serde_yaml::from_str(&contents)
+                .with_context(|| format!("Invalid scenario file {path}", path = path.display()))?
+        };
+
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.steps.is_empty(),
+            "Scenario must have at least one step"
+        );
+        for (index, step) in self.steps.iter().enumerate() {
+            ensure!(step.polls > 0, "Step {index} must last at least one poll");
+            for tx in &step.transactions {
+                ensure!(
+                    tx.fee_rate.is_finite() && tx.fee_rate >= 0.0,
+                    "Step {index} has an invalid fee rate {rate}",
+                    rate = tx.fee_rate
+                );
+                ensure!(tx.weight > 0, "Step {index} has a zero-weight transaction");
+            }
+        }
+        Ok(())
+    }
+
+    fn into_timeline(self) -> ScenarioTimeline {
+        let mut states = Vec::new();
+        let mut height = DEFAULT_BLOCK_HEIGHT;
+
+        for (index, step) in self.steps.into_iter().enumerate() {
+            height = match step.block_height {
+                Some(absolute) => absolute,
+                None if index == 0 => height,
+                None => height.saturating_add(step.blocks_mined),
+            };
+
+            let transactions: Vec<MempoolTransaction> = step
+                .transactions
+                .iter()
+                .flat_map(|tx| {
+                    let fee = (tx.fee_rate * tx.weight as f64 / 4.0).round() as u64;
+                    std::iter::repeat_n(MempoolTransaction::new(tx.weight, fee), tx.count)
+                })
+                .collect();
+
+            for _ in 0..step.polls {
+                states.push((height, transactions.clone()));
+            }
+        }
+
+        ScenarioTimeline {
+            states,
+            repeat: self.repeat,
+        }
+    }
+}
 
 impl MockBitcoinClient {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Creates a mock client that advances through `scenario` on every poll
+    ///
+    /// Fails if the scenario has no steps or a step lasts no polls, which would leave nothing
+    /// to serve.
+    pub fn with_scenario(scenario: Scenario) -> Result<Self> {
+        scenario.validate()?;
+        Ok(Self {
+            scenario: Some(Arc::new(scenario.into_timeline())),
+            ..Self::default()
+        })
+    }
+
+    /// Serves `entry` as the mempool entry of `txid`
+    pub fn with_mempool_entry(mut self, txid: impl Into<String>, entry: TxMempoolEntry) -> Self {
+        Arc::make_mut(&mut self.mempool_entries).insert(txid.into(), entry);
+        self
+    }
+
+    /// Serves `block` at its height
+    pub fn with_block(mut self, block: MinedBlock) -> Self {
+        Arc::make_mut(&mut self.blocks).insert(block.height, block);
+        self
     }
 
     /// Test connection (always succeeds in mock mode)
@@ -17,12 +195,162 @@ impl MockBitcoinClient {
 
     /// Get current block height and mempool (returns mock data)
     pub async fn get_height_and_mempool(&self) -> Result<(u32, Vec<MempoolTransaction>), RpcError> {
+        if let Some(timeline) = &self.scenario {
+            let poll = self.polls.fetch_add(1, Ordering::SeqCst);
+            let index = if timeline.repeat {
+                poll % timeline.states.len()
+            } else {
+                poll.min(timeline.states.len() - 1)
+            };
+            let (height, transactions) = &timeline.states[index];
+            debug!(
+                "Scenario poll {poll}: height {height}, {count} transactions",
+                count = transactions.len()
+            );
+            return Ok((*height, transactions.clone()));
+        }
+
         // Return mock block height and some simple transactions
         let transactions = vec![
             MempoolTransaction::new(2000, 2000), // 1 sat/vB
             MempoolTransaction::new(2000, 4000), // 2 sat/vB
             MempoolTransaction::new(2000, 6000), // 3 sat/vB
         ];
-        Ok((850000, transactions))
+        Ok((DEFAULT_BLOCK_HEIGHT, transactions))
+    }
+
+    /// Get a mempool entry registered with `with_mempool_entry`
+    pub async fn get_mempool_entry(&self, txid: &str) -> Result<Option<TxMempoolEntry>, RpcError> {
+        Ok(self.mempool_entries.get(txid).copied())
+    }
+
+    /// Bitcoin Core's long-standing default incremental relay fee
+    pub async fn get_incremental_relay_fee(&self) -> Result<f64, RpcError> {
+        Ok(1.0)
+    }
+
+    /// A mempool with room to spare, admitting the default minimum relay fee
+    pub async fn get_mempool_min_fee(&self) -> Result<f64, RpcError> {
+        Ok(1.0)
+    }
+
+    /// A smart fee estimate of 10 sat/vB divided by the target, at least 1 sat/vB
+    pub async fn estimate_smart_fee(&self, conf_target: u32) -> Result<Option<f64>, RpcError> {
+        Ok(Some((10.0 / f64::from(conf_target.max(1))).max(1.0)))
+    }
+
+    /// Get a block registered with `with_block`
+    pub async fn get_block(&self, height: u32) -> Result<MinedBlock, RpcError> {
+        self.blocks
+            .get(&height)
+            .cloned()
+            .ok_or_else(|| RpcError::RpcError {
+                code: -8,
+                message: "Block height out of range".to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const SPIKE_SCENARIO: &str = r#"
+name: spike
+steps:
+  - block_height: 900000
+    polls: 2
+    transactions:
+      - { fee_rate: 2.0, weight: 2000, count: 3 }
+  - blocks_mined: 2
+    transactions:
+      - { fee_rate: 50.0, weight: 400 }
+  - blocks_mined: 1
+"#;
+
+    #[tokio::test]
+    async fn test_static_mock_data() {
+        let client = MockBitcoinClient::new();
+        let (height, transactions) = client.get_height_and_mempool().await.unwrap();
+        assert_eq!(height, 850000);
+        assert_eq!(transactions.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_scenario_advances_per_poll() {
+        let scenario: Scenario = serde_yaml::from_str(SPIKE_SCENARIO).unwrap();
+        let client = MockBitcoinClient::with_scenario(scenario).unwrap();
+
+        for _ in 0..2 {
+            let (height, transactions) = client.get_height_and_mempool().await.unwrap();
+            assert_eq!(height, 900000);
+            assert_eq!(transactions.len(), 3);
+            assert_eq!(transactions[0].fee, 1000);
+        }
+
+        let (height, transactions) = client.get_height_and_mempool().await.unwrap();
+        assert_eq!(height, 900002);
+        assert_eq!(transactions, vec![MempoolTransaction::new(400, 5000)]);
+
+        // Final step models a drained mempool and is held once the scenario ends
+        for _ in 0..2 {
+            let (height, transactions) = client.get_height_and_mempool().await.unwrap();
+            assert_eq!(height, 900003);
+            assert!(transactions.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeating_scenario_wraps_around() {
+        let mut scenario: Scenario = serde_yaml::from_str(SPIKE_SCENARIO).unwrap();
+        scenario.repeat = true;
+        let client = MockBitcoinClient::with_scenario(scenario).unwrap();
+
+        for _ in 0..4 {
+            client.get_height_and_mempool().await.unwrap();
+        }
+        let (height, _) = client.get_height_and_mempool().await.unwrap();
+        assert_eq!(height, 900000);
+    }
+
+    #[test]
+    fn test_scenario_from_json_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("scenario.json");
+        std::fs::write(
+            &path,
+            r#"{"steps": [{"transactions": [{"fee_rate": 1.5, "weight": 800}]}]}"#,
+        )
+        .unwrap();
+
+        let scenario = Scenario::from_file(&path).unwrap();
+        assert_eq!(scenario.steps.len(), 1);
+        assert_eq!(scenario.steps[0].polls, 1);
+    }
+
+    #[test]
+    fn test_bundled_scenarios_load() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("config/scenarios");
+        for name in ["fee_spike.yaml", "block_burst.yaml", "eviction.yaml"] {
+            let scenario = Scenario::from_file(dir.join(name)).unwrap();
+            assert!(!scenario.name.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_invalid_scenarios_rejected() {
+        let empty: Scenario = serde_yaml::from_str("steps: []").unwrap();
+        assert!(empty.validate().is_err());
+        assert!(MockBitcoinClient::with_scenario(empty).is_err());
+
+        let no_polls: Scenario = serde_yaml::from_str("steps: [{polls: 0}]").unwrap();
+        assert!(MockBitcoinClient::with_scenario(no_polls).is_err());
+
+        let zero_weight: Scenario =
+            serde_yaml::from_str("steps: [{transactions: [{fee_rate: 1.0, weight: 0}]}]").unwrap();
+        assert!(zero_weight.validate().is_err());
+
+        assert!(Scenario::from_file("/nonexistent/scenario.yaml").is_err());
     }
 }
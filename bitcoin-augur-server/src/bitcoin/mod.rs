@@ -1,9 +1,15 @@
 //! Bitcoin Core RPC client module for fetching mempool data
 
 mod mock_client;
+mod replay_client;
 mod rpc_client;
 mod traits;
 
-pub use mock_client::MockBitcoinClient;
-pub use rpc_client::{BitcoinRpcClient, BitcoinRpcConfig, RpcError};
+pub use mock_client::{MockBitcoinClient, Scenario};
+pub use replay_client::ReplayBitcoinClient;
+pub use rpc_client::{
+    BitcoinRpcClient, BitcoinRpcConfig, MinedBlock, RpcError, TxMempoolEntry, REQUIRED_RPC_METHODS,
+};
+#[allow(unused_imports)]
+pub use rpc_client::{NodeImplementation, RpcAccess};
 pub use traits::{BitcoinClient, BitcoinRpc};
@@ -0,0 +1,238 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bitcoin_augur::MempoolTransaction;
+use chrono::{DateTime, Utc};
+use tracing::debug;
+
+use super::rpc_client::parse_height_and_mempool;
+use super::{MinedBlock, RpcError, TxMempoolEntry};
+use crate::service::Clock;
+
+/// Bitcoin client that replays RPC responses captured with `--record-rpc`
+pub struct ReplayBitcoinClient {
+    files: Vec<PathBuf>,
+    position: AtomicUsize,
+    /// Moved to each response's capture time as it is replayed
+    clock: Clock,
+}
+
+/// Capture time encoded in a recorded file name (`rpc_{timestamp_ms}_{sequence}.json`)
+fn captured_at(path: &Path) -> Option<DateTime<Utc>> {
+    let stem = path.file_stem()?.to_str()?;
+    let timestamp_ms = stem.strip_prefix("rpc_")?.split('_').next()?.parse().ok()?;
+    DateTime::from_timestamp_millis(timestamp_ms)
+}
+
+impl ReplayBitcoinClient {
+    /// Loads the recorded responses in `dir`, ordered by file name
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, RpcError> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            RpcError::ReplayError(format!(
+                "cannot read replay directory {dir}: {e}",
+                dir = dir.display()
+            ))
+        })?;
+
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+            .collect();
+        files.sort();
+
+        Ok(Self {
+            files,
+            position: AtomicUsize::new(0),
+            clock: Clock::System,
+        })
+    }
+
+    /// Moves `clock` to the capture time of every replayed response, so snapshots carry the
+    /// recorded timestamps instead of the time of the replay
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Capture time of the first recorded response, if its file name records one
+    pub fn recording_start(&self) -> Option<DateTime<Utc>> {
+        self.files.first().and_then(|path| captured_at(path))
+    }
+
+    /// Number of recorded responses not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.files
+            .len()
+            .saturating_sub(self.position.load(Ordering::SeqCst))
+    }
+
+    /// Test connection (succeeds while recorded responses are available)
+    pub async fn test_connection(&self) -> Result<(), RpcError> {
+        if self.files.is_empty() {
+            return Err(RpcError::ReplayError(
+                "no recorded responses found".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the next recorded response, parsed exactly as a live one would be
+    pub async fn get_height_and_mempool(&self) -> Result<(u32, Vec<MempoolTransaction>), RpcError> {
+        let index = self.position.fetch_add(1, Ordering::SeqCst);
+        let path = self.files.get(index).ok_or_else(|| {
+            RpcError::ReplayError(format!(
+                "all {count} recorded responses have been replayed",
+                count = self.files.len()
+            ))
+        })?;
+
+        debug!("Replaying RPC response {path}", path = path.display());
+        let body = std::fs::read_to_string(path).map_err(|e| {
+            RpcError::ReplayError(format!("cannot read {path}: {e}", path = path.display()))
+        })?;
+
+        let response = parse_height_and_mempool(&body)?;
+        if let Some(at) = captured_at(path) {
+            self.clock.set(at);
+        }
+        Ok(response)
+    }
+
+    /// Mempool entries are not recorded, so they cannot be replayed
+    pub async fn get_mempool_entry(&self, _txid: &str) -> Result<Option<TxMempoolEntry>, RpcError> {
+        Err(RpcError::ReplayError(
+            "mempool entries are not available when replaying".to_string(),
+        ))
+    }
+
+    /// Network info is not recorded, so it cannot be replayed
+    pub async fn get_incremental_relay_fee(&self) -> Result<f64, RpcError> {
+        Err(RpcError::ReplayError(
+            "network info is not available when replaying".to_string(),
+        ))
+    }
+
+    /// Mempool info is not recorded, so it cannot be replayed
+    pub async fn get_mempool_min_fee(&self) -> Result<f64, RpcError> {
+        Err(RpcError::ReplayError(
+            "mempool info is not available when replaying".to_string(),
+        ))
+    }
+
+    /// Smart fee estimates are not recorded, so they cannot be replayed
+    pub async fn estimate_smart_fee(&self, conf_target: u32) -> Result<Option<f64>, RpcError> {
+        Err(RpcError::ReplayError(format!(
+            "the smart fee estimate for {conf_target} blocks is not available when replaying"
+        )))
+    }
+
+    /// Blocks are not recorded, so they cannot be replayed
+    pub async fn get_block(&self, height: u32) -> Result<MinedBlock, RpcError> {
+        Err(RpcError::ReplayError(format!(
+            "block {height} is not available when replaying"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn write_response(dir: &Path, name: &str, height: u32, weight: u64) {
+        let body = json!([
+            {
+                "result": { "blocks": height, "bestblockhash": "hash" },
+                "error": null,
+                "id": "blockchain-info"
+            },
+            {
+                "result": { "tx1": { "weight": weight, "fees": { "base": 0.00001000 } } },
+                "error": null,
+                "id": "mempool"
+            }
+        ]);
+        std::fs::write(dir.join(name), body.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replays_in_recorded_order() {
+        let temp_dir = TempDir::new().unwrap();
+        write_response(temp_dir.path(), "rpc_2000_000001.json", 850001, 800);
+        write_response(temp_dir.path(), "rpc_1000_000000.json", 850000, 400);
+        std::fs::write(temp_dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let client = ReplayBitcoinClient::new(temp_dir.path()).unwrap();
+        assert!(client.test_connection().await.is_ok());
+        assert_eq!(client.remaining(), 2);
+
+        let (height, txs) = client.get_height_and_mempool().await.unwrap();
+        assert_eq!(height, 850000);
+        assert_eq!(txs[0].weight, 400);
+
+        let (height, txs) = client.get_height_and_mempool().await.unwrap();
+        assert_eq!(height, 850001);
+        assert_eq!(txs[0].weight, 800);
+
+        assert_eq!(client.remaining(), 0);
+        assert!(matches!(
+            client.get_height_and_mempool().await,
+            Err(RpcError::ReplayError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_moves_clock_to_capture_time() {
+        let temp_dir = TempDir::new().unwrap();
+        write_response(
+            temp_dir.path(),
+            "rpc_1737374400000_000000.json",
+            850000,
+            400,
+        );
+        write_response(
+            temp_dir.path(),
+            "rpc_1737374430500_000001.json",
+            850000,
+            800,
+        );
+        write_response(temp_dir.path(), "unnamed.json", 850001, 1200);
+
+        let first = DateTime::from_timestamp_millis(1737374400000).unwrap();
+        let clock = Clock::simulated(first, chrono::Duration::zero());
+        let client = ReplayBitcoinClient::new(temp_dir.path())
+            .unwrap()
+            .with_clock(clock.clone());
+        assert_eq!(client.recording_start(), Some(first));
+
+        client.get_height_and_mempool().await.unwrap();
+        assert_eq!(clock.now(), first);
+
+        client.get_height_and_mempool().await.unwrap();
+        assert_eq!(
+            clock.now(),
+            DateTime::from_timestamp_millis(1737374430500).unwrap()
+        );
+
+        // A file without a capture time leaves the clock where it was
+        client.get_height_and_mempool().await.unwrap();
+        assert_eq!(
+            clock.now(),
+            DateTime::from_timestamp_millis(1737374430500).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_directory_fails_connection_test() {
+        let temp_dir = TempDir::new().unwrap();
+        let client = ReplayBitcoinClient::new(temp_dir.path()).unwrap();
+        assert!(client.test_connection().await.is_err());
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        assert!(ReplayBitcoinClient::new("/nonexistent/replay/dir").is_err());
+    }
+}
@@ -1,19 +1,34 @@
 use base64::Engine;
-use bitcoin_augur::MempoolTransaction;
-use reqwest::{header, Client};
+use bitcoin_augur::{MempoolTransaction, Package};
+use reqwest::{header, Client, Proxy, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
 use thiserror::Error;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+use crate::config::redact_secret;
 
 /// Bitcoin RPC configuration
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct BitcoinRpcConfig {
     pub url: String,
     pub username: String,
     pub password: String,
 }
 
+impl std::fmt::Debug for BitcoinRpcConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BitcoinRpcConfig")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &redact_secret(&self.password))
+            .finish()
+    }
+}
+
 /// Bitcoin RPC error types
 #[derive(Error, Debug)]
 pub enum RpcError {
@@ -33,6 +48,12 @@ pub enum RpcError {
     #[error("Missing required field: {0}")]
     #[allow(dead_code)]
     MissingField(String),
+
+    #[error("Replay failed: {0}")]
+    ReplayError(String),
+
+    #[error("Proxy error: {0}")]
+    ProxyError(String),
 }
 
 /// Bitcoin RPC client for fetching mempool data
@@ -40,6 +61,34 @@ pub struct BitcoinRpcClient {
     client: Client,
     config: BitcoinRpcConfig,
     auth_header: String,
+    recorder: Option<RpcRecorder>,
+    /// Node software, once detected
+    node: OnceLock<NodeImplementation>,
+    /// Host and port of the SOCKS5 proxy every request goes through, if any
+    proxy: Option<(String, u16)>,
+}
+
+/// Persists raw batch responses so they can be replayed offline
+struct RpcRecorder {
+    dir: PathBuf,
+    sequence: AtomicU64,
+}
+
+impl RpcRecorder {
+    fn record(&self, body: &str) {
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
+        let timestamp_ms = chrono::Utc::now().timestamp_millis();
+        let path = self
+            .dir
+            .join(format!("rpc_{timestamp_ms}_{sequence:06}.json"));
+
+        if let Err(e) = std::fs::write(&path, body) {
+            warn!(
+                "Failed to record RPC response to {path}: {e}",
+                path = path.display()
+            );
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -72,17 +121,243 @@ struct BlockchainInfo {
     best_block_hash: String,
 }
 
+/// A verbose getrawmempool entry, as reported by Bitcoin Core or by nodes that lack some of
+/// its fields
 #[derive(Deserialize)]
 struct MempoolEntry {
     #[serde(rename = "vsize")]
     vsize: Option<u64>,
     weight: Option<u64>,
-    fees: MempoolFees,
+    /// Virtual size on Bitcoin Core before 0.19, serialized size on btcd
+    size: Option<u64>,
+    fees: Option<MempoolFees>,
+    /// Fee in BTC, reported by btcd and older nodes instead of `fees`
+    fee: Option<f64>,
+    /// Fee of the transaction and its descendants in satoshis, reported by older nodes
+    #[serde(rename = "descendantfees")]
+    descendant_fees: Option<u64>,
+    #[serde(rename = "descendantcount")]
+    descendant_count: Option<u64>,
+    /// Unix time the transaction entered the mempool
+    time: Option<i64>,
+    /// Transactions in the package of the transaction and its unconfirmed ancestors
+    #[serde(rename = "ancestorcount")]
+    ancestor_count: Option<u64>,
+    /// Virtual size of that package
+    #[serde(rename = "ancestorsize")]
+    ancestor_size: Option<u64>,
 }
 
 #[derive(Deserialize)]
 struct MempoolFees {
     base: f64,
+    /// Fee of the transaction and its unconfirmed ancestors in BTC
+    ancestor: Option<f64>,
+}
+
+impl MempoolEntry {
+    /// Weight, falling back to four times the virtual or serialized size
+    fn weight(&self) -> Option<u64> {
+        self.weight
+            .or_else(|| self.vsize.map(|vsize| vsize * 4))
+            .or_else(|| self.size.map(|size| size * 4))
+    }
+
+    /// Fee in satoshis, from whichever field the node reports
+    fn fee_sats(&self) -> Option<u64> {
+        let fee_btc = self.fees.as_ref().map(|fees| fees.base).or(self.fee);
+        if let Some(fee_btc) = fee_btc {
+            return Some((fee_btc * SATS_PER_BTC) as u64);
+        }
+        // A transaction without descendants pays the whole descendant fee itself
+        match self.descendant_count {
+            Some(1) => self.descendant_fees,
+            _ => None,
+        }
+    }
+
+    /// The transaction and its unconfirmed ancestors, when the node reports all of them
+    fn ancestors(&self) -> Option<Package> {
+        let fee_btc = self.fees.as_ref()?.ancestor?;
+        Some(Package::new(
+            self.ancestor_count?,
+            self.ancestor_size?,
+            (fee_btc * SATS_PER_BTC).round() as u64,
+        ))
+    }
+}
+
+/// Bitcoin node software, told apart by the user agent it reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeImplementation {
+    BitcoinCore,
+    BitcoinKnots,
+    Btcd,
+    /// Any other node, with the user agent it reported
+    Other(String),
+}
+
+impl NodeImplementation {
+    /// Identifies the node from its `subversion`, such as `/Satoshi:27.1.0/Knots:20240801/`
+    pub fn from_subversion(subversion: &str) -> Self {
+        if subversion.contains("/Knots:") {
+            Self::BitcoinKnots
+        } else if subversion.contains("/btcd:") {
+            Self::Btcd
+        } else if subversion.starts_with("/Satoshi:") {
+            Self::BitcoinCore
+        } else {
+            Self::Other(subversion.to_string())
+        }
+    }
+
+    /// Whether the node reports mempool entries in Bitcoin Core's current format
+    pub fn is_core_compatible(&self) -> bool {
+        matches!(self, Self::BitcoinCore | Self::BitcoinKnots)
+    }
+}
+
+impl std::fmt::Display for NodeImplementation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BitcoinCore => write!(f, "Bitcoin Core"),
+            Self::BitcoinKnots => write!(f, "Bitcoin Knots"),
+            Self::Btcd => write!(f, "btcd"),
+            Self::Other(subversion) => write!(f, "unknown node {}", subversion),
+        }
+    }
+}
+
+/// RPC error code Bitcoin Core returns for a transaction that is not in its mempool
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+
+/// Port of a SOCKS proxy URL without one, as Tor and most proxies listen there
+const DEFAULT_SOCKS_PORT: u16 = 1080;
+
+/// How long the startup proxy check waits for a connection
+const PROXY_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// RPC error code for a method the node does not implement
+const RPC_METHOD_NOT_FOUND: i32 = -32601;
+
+/// Satoshis per bitcoin, the unit of every RPC fee
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// RPC methods the collector cannot work without, and all that restricted credentials need to
+/// allow besides the optional ones some features use
+pub const REQUIRED_RPC_METHODS: [&str; 3] =
+    ["getblockchaininfo", "getrawmempool", "getmempoolinfo"];
+
+/// Wallet method probed to tell restricted credentials from full ones; harmless to call
+const WALLET_PROBE_METHOD: &str = "listwallets";
+
+/// What the configured credentials may call, as found by
+/// [`BitcoinRpcClient::probe_rpc_access`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcAccess {
+    /// Required methods the node refused
+    pub missing: Vec<&'static str>,
+    /// Whether wallet methods may be called
+    pub wallet: bool,
+}
+
+/// A transaction's mempool entry together with its in-mempool relatives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxMempoolEntry {
+    /// The transaction alone
+    pub tx: Package,
+    /// The transaction and all its unconfirmed ancestors
+    pub ancestors: Package,
+    /// The transaction and all its unconfirmed descendants
+    pub descendants: Package,
+}
+
+/// A mined block's time and the transactions it confirmed
+#[derive(Debug, Clone)]
+pub struct MinedBlock {
+    pub height: u32,
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// Every transaction except the coinbase
+    pub transactions: Vec<MempoolTransaction>,
+}
+
+#[derive(Deserialize)]
+struct RawBlock {
+    time: i64,
+    tx: Vec<RawBlockTransaction>,
+}
+
+#[derive(Deserialize)]
+struct RawBlockTransaction {
+    weight: u64,
+    /// In BTC; absent for the coinbase
+    fee: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct NetworkInfo {
+    #[serde(rename = "incrementalfee")]
+    incremental_fee: f64,
+}
+
+#[derive(Deserialize)]
+struct NodeInfo {
+    #[serde(default)]
+    subversion: String,
+}
+
+#[derive(Deserialize)]
+struct SmartFeeEstimate {
+    /// Missing when the node has too little data, with the reason in `errors`
+    #[serde(rename = "feerate")]
+    fee_rate: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct MempoolInfo {
+    /// Lowest fee rate the node's mempool currently admits, in BTC/kvB
+    #[serde(rename = "mempoolminfee")]
+    mempool_min_fee: f64,
+}
+
+#[derive(Deserialize)]
+struct RawTxMempoolEntry {
+    vsize: u64,
+    #[serde(rename = "ancestorcount")]
+    ancestor_count: u64,
+    #[serde(rename = "ancestorsize")]
+    ancestor_size: u64,
+    #[serde(rename = "descendantcount")]
+    descendant_count: u64,
+    #[serde(rename = "descendantsize")]
+    descendant_size: u64,
+    fees: RawTxMempoolEntryFees,
+}
+
+#[derive(Deserialize)]
+struct RawTxMempoolEntryFees {
+    base: f64,
+    ancestor: f64,
+    descendant: f64,
+}
+
+impl From<RawTxMempoolEntry> for TxMempoolEntry {
+    fn from(raw: RawTxMempoolEntry) -> Self {
+        let sats = |btc: f64| (btc * SATS_PER_BTC).round() as u64;
+        Self {
+            tx: Package::new(1, raw.vsize, sats(raw.fees.base)),
+            ancestors: Package::new(
+                raw.ancestor_count,
+                raw.ancestor_size,
+                sats(raw.fees.ancestor),
+            ),
+            descendants: Package::new(
+                raw.descendant_count,
+                raw.descendant_size,
+                sats(raw.fees.descendant),
+            ),
+        }
+    }
 }
 
 impl BitcoinRpcClient {
@@ -95,6 +370,72 @@ impl BitcoinRpcClient {
             client: Client::new(),
             auth_header: format!("Basic {}", auth),
             config,
+            recorder: None,
+            node: OnceLock::new(),
+            proxy: None,
+        }
+    }
+
+    /// Records every raw getblockchaininfo/getrawmempool response into `dir`
+    pub fn with_recording(mut self, dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        info!("Recording raw RPC responses to {dir}", dir = dir.display());
+
+        self.recorder = Some(RpcRecorder {
+            dir: dir.to_path_buf(),
+            sequence: AtomicU64::new(0),
+        });
+        Ok(self)
+    }
+
+    /// Sends every request through the SOCKS5 proxy at `proxy`, such as
+    /// `socks5h://127.0.0.1:9050` to reach a node through Tor
+    ///
+    /// With `socks5h` the proxy resolves host names, which `.onion` addresses require.
+    pub fn with_proxy(mut self, proxy: &str) -> Result<Self, RpcError> {
+        let url = Url::parse(proxy)
+            .map_err(|e| RpcError::ProxyError(format!("invalid proxy URL {proxy}: {e}")))?;
+        if !matches!(url.scheme(), "socks5" | "socks5h") {
+            return Err(RpcError::ProxyError(format!(
+                "unsupported proxy scheme {scheme}, expected socks5 or socks5h",
+                scheme = url.scheme()
+            )));
+        }
+        let Some(host) = url.host_str() else {
+            return Err(RpcError::ProxyError(format!(
+                "proxy URL {proxy} has no host"
+            )));
+        };
+        let port = url.port().unwrap_or(DEFAULT_SOCKS_PORT);
+
+        self.proxy = Some((host.to_string(), port));
+        self.client = Client::builder().proxy(Proxy::all(url)?).build()?;
+        Ok(self)
+    }
+
+    /// Checks that the configured proxy accepts connections; succeeds without a proxy
+    ///
+    /// Tells an unreachable proxy apart from an unreachable node, which
+    /// [`test_connection`](Self::test_connection) cannot.
+    pub async fn test_proxy(&self) -> Result<(), RpcError> {
+        let Some((host, port)) = &self.proxy else {
+            return Ok(());
+        };
+
+        match tokio::time::timeout(
+            PROXY_CONNECT_TIMEOUT,
+            tokio::net::TcpStream::connect((host.as_str(), *port)),
+        )
+        .await
+        {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(RpcError::ProxyError(format!(
+                "cannot connect to proxy {host}:{port}: {e}"
+            ))),
+            Err(_) => Err(RpcError::ProxyError(format!(
+                "timed out connecting to proxy {host}:{port}"
+            ))),
         }
     }
 
@@ -136,75 +477,246 @@ impl BitcoinRpcClient {
             return Err(RpcError::InvalidResponse);
         }
 
-        let results: Vec<RpcResponse> = response.json().await?;
+        let body = response.text().await?;
 
-        if results.len() != 2 {
-            return Err(RpcError::InvalidResponse);
+        if let Some(recorder) = &self.recorder {
+            recorder.record(&body);
         }
 
-        // Parse blockchain info
-        let blockchain_result = &results[0];
-        if let Some(ref error) = blockchain_result.error {
+        parse_height_and_mempool(&body)
+    }
+
+    /// Gets the mempool entry of `txid`, or None if it is not in the node's mempool
+    pub async fn get_mempool_entry(&self, txid: &str) -> Result<Option<TxMempoolEntry>, RpcError> {
+        debug!("Fetching mempool entry for {txid}");
+
+        let request = RpcRequest {
+            jsonrpc: "1.0",
+            id: "mempool-entry".to_string(),
+            method: "getmempoolentry".to_string(),
+            params: vec![json!(txid)],
+        };
+
+        // Bitcoin Core answers RPC errors with a non-success status, so the body is parsed
+        // regardless to tell a missing transaction apart from a failure
+        let response = self
+            .client
+            .post(&self.config.url)
+            .header(header::AUTHORIZATION, &self.auth_header)
+            .header(header::CONTENT_TYPE, "application/json")
+            .json(&request)
+            .send()
+            .await?;
+        let status = response.status();
+        let result: RpcResponse = response.json().await.map_err(|e| {
+            error!("getmempoolentry failed with status: {status}");
+            RpcError::HttpError(e)
+        })?;
+
+        if let Some(error) = result.error {
+            if error.code == RPC_INVALID_ADDRESS_OR_KEY {
+                return Ok(None);
+            }
             return Err(RpcError::RpcError {
                 code: error.code,
-                message: error.message.clone(),
+                message: error.message,
             });
         }
 
-        let blockchain_info: BlockchainInfo = serde_json::from_value(
-            blockchain_result
-                .result
-                .as_ref()
-                .ok_or(RpcError::InvalidResponse)?
-                .clone(),
-        )?;
+        let entry: RawTxMempoolEntry =
+            serde_json::from_value(result.result.ok_or(RpcError::InvalidResponse)?)?;
+        Ok(Some(entry.into()))
+    }
 
-        debug!(
-            "Current blockchain height: {height}",
-            height = blockchain_info.blocks
-        );
+    /// Gets the node's incremental relay fee in sat/vB
+    pub async fn get_incremental_relay_fee(&self) -> Result<f64, RpcError> {
+        let request = RpcRequest {
+            jsonrpc: "1.0",
+            id: "network-info".to_string(),
+            method: "getnetworkinfo".to_string(),
+            params: vec![],
+        };
+
+        let response = self
+            .client
+            .post(&self.config.url)
+            .header(header::AUTHORIZATION, &self.auth_header)
+            .header(header::CONTENT_TYPE, "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            error!(
+                "getnetworkinfo failed with status: {status}",
+                status = response.status()
+            );
+            return Err(RpcError::InvalidResponse);
+        }
 
-        // Parse mempool transactions
-        let mempool_result = &results[1];
-        if let Some(ref error) = mempool_result.error {
+        let result: RpcResponse = response.json().await?;
+        if let Some(error) = result.error {
             return Err(RpcError::RpcError {
                 code: error.code,
-                message: error.message.clone(),
+                message: error.message,
             });
         }
 
-        let mempool_data = mempool_result
-            .result
-            .as_ref()
-            .ok_or(RpcError::InvalidResponse)?
-            .as_object()
-            .ok_or(RpcError::InvalidResponse)?;
+        let info: NetworkInfo =
+            serde_json::from_value(result.result.ok_or(RpcError::InvalidResponse)?)?;
+        // Reported in BTC/kvB
+        Ok(info.incremental_fee * SATS_PER_BTC / 1000.0)
+    }
 
-        let mut transactions = Vec::new();
+    /// Gets the block at `height` with the fee and weight of its transactions
+    pub async fn get_block(&self, height: u32) -> Result<MinedBlock, RpcError> {
+        debug!("Fetching block {height}");
+
+        let hash = self.call("getblockhash", vec![json!(height)]).await?;
+        let block: RawBlock =
+            serde_json::from_value(self.call("getblock", vec![hash, json!(2)]).await?)?;
+
+        let time =
+            chrono::DateTime::from_timestamp(block.time, 0).ok_or(RpcError::InvalidResponse)?;
+        let transactions = block
+            .tx
+            .into_iter()
+            .filter_map(|tx| {
+                let fee = (tx.fee? * SATS_PER_BTC).round() as u64;
+                Some(MempoolTransaction::new(tx.weight, fee))
+            })
+            .collect();
+        Ok(MinedBlock {
+            height,
+            time,
+            transactions,
+        })
+    }
 
-        for (_txid, entry_value) in mempool_data {
-            if let Ok(entry) = serde_json::from_value::<MempoolEntry>(entry_value.clone()) {
-                // Use weight if available, otherwise calculate from vsize
-                let weight = entry
-                    .weight
-                    .or_else(|| entry.vsize.map(|v| v * 4))
-                    .unwrap_or(0);
+    /// Gets the node's own `estimatesmartfee` answer for `conf_target` blocks in sat/vB, or
+    /// None if it has not collected enough data yet
+    pub async fn estimate_smart_fee(&self, conf_target: u32) -> Result<Option<f64>, RpcError> {
+        let estimate: SmartFeeEstimate = serde_json::from_value(
+            self.call("estimatesmartfee", vec![json!(conf_target)])
+                .await?,
+        )?;
+        // Reported in BTC/kvB
+        Ok(estimate
+            .fee_rate
+            .map(|fee_rate| fee_rate * SATS_PER_BTC / 1000.0))
+    }
 
-                if weight > 0 {
-                    // Convert BTC to satoshis
-                    let fee_sats = (entry.fees.base * 100_000_000.0) as u64;
+    /// Gets the lowest fee rate the node's mempool currently admits, in sat/vB
+    pub async fn get_mempool_min_fee(&self) -> Result<f64, RpcError> {
+        let info: MempoolInfo = serde_json::from_value(self.call("getmempoolinfo", vec![]).await?)?;
+        // Reported in BTC/kvB
+        Ok(info.mempool_min_fee * SATS_PER_BTC / 1000.0)
+    }
 
-                    transactions.push(MempoolTransaction::new(weight, fee_sats));
-                }
+    /// Detects which node software answers, remembering it for
+    /// [`node_implementation`](Self::node_implementation)
+    ///
+    /// Nodes without `getnetworkinfo` are reported as [`NodeImplementation::Other`].
+    pub async fn detect_node_implementation(&self) -> Result<NodeImplementation, RpcError> {
+        let node = match self.call("getnetworkinfo", vec![]).await {
+            Ok(info) => {
+                let info: NodeInfo = serde_json::from_value(info)?;
+                NodeImplementation::from_subversion(&info.subversion)
+            }
+            Err(RpcError::RpcError {
+                code: RPC_METHOD_NOT_FOUND,
+                ..
+            }) => NodeImplementation::Other(String::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(self.node.get_or_init(|| node).clone())
+    }
+
+    /// Node software detected by
+    /// [`detect_node_implementation`](Self::detect_node_implementation), if it ran
+    pub fn node_implementation(&self) -> Option<&NodeImplementation> {
+        self.node.get()
+    }
+
+    /// Probes which of the [`REQUIRED_RPC_METHODS`] and wallet methods the credentials may
+    /// call
+    ///
+    /// A method counts as allowed unless the node forbids it for these credentials, as
+    /// `rpcwhitelist` does, or does not implement it, as a node without a wallet does for wallet
+    /// methods.
+    pub async fn probe_rpc_access(&self) -> Result<RpcAccess, RpcError> {
+        let mut missing = Vec::new();
+        for method in REQUIRED_RPC_METHODS {
+            if !self.is_allowed(method).await? {
+                missing.push(method);
             }
         }
+        let wallet = self.is_allowed(WALLET_PROBE_METHOD).await?;
+        Ok(RpcAccess { missing, wallet })
+    }
 
-        info!(
-            "Fetched {count} mempool transactions",
-            count = transactions.len()
-        );
+    /// Whether the credentials may call `method`, without parameters
+    async fn is_allowed(&self, method: &str) -> Result<bool, RpcError> {
+        let request = RpcRequest {
+            jsonrpc: "1.0",
+            id: method.to_string(),
+            method: method.to_string(),
+            params: vec![],
+        };
 
-        Ok((blockchain_info.blocks, transactions))
+        let response = self
+            .client
+            .post(&self.config.url)
+            .header(header::AUTHORIZATION, &self.auth_header)
+            .header(header::CONTENT_TYPE, "application/json")
+            .json(&request)
+            .send()
+            .await?;
+        let status = response.status();
+        // Bitcoin Core answers methods outside the credentials' rpcwhitelist with an empty 403
+        if status == reqwest::StatusCode::FORBIDDEN {
+            return Ok(false);
+        }
+        let result: RpcResponse = response.json().await.map_err(|e| {
+            error!("{method} failed with status: {status}");
+            RpcError::HttpError(e)
+        })?;
+        Ok(result
+            .error
+            .is_none_or(|error| error.code != RPC_METHOD_NOT_FOUND))
+    }
+
+    /// Sends a single RPC request and returns its result
+    async fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcError> {
+        let request = RpcRequest {
+            jsonrpc: "1.0",
+            id: method.to_string(),
+            method: method.to_string(),
+            params,
+        };
+
+        // Bitcoin Core answers RPC errors with a non-success status, so the body is parsed first
+        let response = self
+            .client
+            .post(&self.config.url)
+            .header(header::AUTHORIZATION, &self.auth_header)
+            .header(header::CONTENT_TYPE, "application/json")
+            .json(&request)
+            .send()
+            .await?;
+        let status = response.status();
+        let result: RpcResponse = response.json().await.map_err(|e| {
+            error!("{method} failed with status: {status}");
+            RpcError::HttpError(e)
+        })?;
+
+        if let Some(error) = result.error {
+            return Err(RpcError::RpcError {
+                code: error.code,
+                message: error.message,
+            });
+        }
+        result.result.ok_or(RpcError::InvalidResponse)
     }
 
     /// Tests the RPC connection
@@ -253,6 +765,97 @@ impl BitcoinRpcClient {
     }
 }
 
+/// Parses a raw getblockchaininfo/getrawmempool batch response body
+pub(super) fn parse_height_and_mempool(
+    body: &str,
+) -> Result<(u32, Vec<MempoolTransaction>), RpcError> {
+    let results: Vec<RpcResponse> = serde_json::from_str(body)?;
+
+    if results.len() != 2 {
+        return Err(RpcError::InvalidResponse);
+    }
+
+    // Parse blockchain info
+    let blockchain_result = &results[0];
+    if let Some(ref error) = blockchain_result.error {
+        return Err(RpcError::RpcError {
+            code: error.code,
+            message: error.message.clone(),
+        });
+    }
+
+    let blockchain_info: BlockchainInfo = serde_json::from_value(
+        blockchain_result
+            .result
+            .as_ref()
+            .ok_or(RpcError::InvalidResponse)?
+            .clone(),
+    )?;
+
+    debug!(
+        "Current blockchain height: {height}",
+        height = blockchain_info.blocks
+    );
+
+    // Parse mempool transactions
+    let mempool_result = &results[1];
+    if let Some(ref error) = mempool_result.error {
+        return Err(RpcError::RpcError {
+            code: error.code,
+            message: error.message.clone(),
+        });
+    }
+
+    let mempool_data = mempool_result
+        .result
+        .as_ref()
+        .ok_or(RpcError::InvalidResponse)?
+        .as_object()
+        .ok_or(RpcError::InvalidResponse)?;
+
+    let mut transactions = Vec::new();
+    let mut unusable = 0;
+
+    for (_txid, entry_value) in mempool_data {
+        let Ok(entry) = serde_json::from_value::<MempoolEntry>(entry_value.clone()) else {
+            unusable += 1;
+            continue;
+        };
+        // Older nodes and btcd lack the weight or the fees object, so fall back to what they do
+        // report
+        match (entry.weight(), entry.fee_sats()) {
+            (Some(0), _) => {}
+            (Some(weight), Some(fee_sats)) => {
+                let mut transaction = MempoolTransaction::new(weight, fee_sats);
+                if let Some(ancestors) = entry.ancestors() {
+                    transaction = transaction.with_ancestors(ancestors);
+                }
+                transactions.push(
+                    match entry
+                        .time
+                        .and_then(|time| chrono::DateTime::from_timestamp(time, 0))
+                    {
+                        Some(time) => transaction.with_time(time),
+                        None => transaction,
+                    },
+                );
+            }
+            _ => unusable += 1,
+        }
+    }
+
+    if unusable > 0 {
+        warn!("Skipped {unusable} mempool entries without a usable size or fee");
+    }
+
+    info!(
+        "Fetched {count} mempool transactions",
+        count = transactions.len()
+    );
+
+    Ok((blockchain_info.blocks, transactions))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -390,8 +993,12 @@ mod tests {
                             "tx1": {
                                 "vsize": 250,
                                 "weight": 1000,
+                                "time": 1737374400,
+                                "ancestorcount": 2,
+                                "ancestorsize": 400,
                                 "fees": {
-                                    "base": 0.00001000
+                                    "base": 0.00001000,
+                                    "ancestor": 0.00001500
                                 }
                             },
                             "tx2": {
@@ -418,10 +1025,14 @@ mod tests {
         // Check first transaction
         assert_eq!(result.1[0].weight, 1000);
         assert_eq!(result.1[0].fee, 1000); // 0.00001 BTC = 1000 sats
+        assert_eq!(result.1[0].time.map(|t| t.timestamp()), Some(1737374400));
+        assert_eq!(result.1[0].ancestors, Some(Package::new(2, 400, 1500)));
 
         // Check second transaction (weight calculated from vsize)
         assert_eq!(result.1[1].weight, 600); // 150 * 4
         assert_eq!(result.1[1].fee, 2000); // 0.00002 BTC = 2000 sats
+        assert_eq!(result.1[1].time, None);
+        assert_eq!(result.1[1].ancestors, None);
     }
 
     #[tokio::test]
@@ -587,4 +1198,463 @@ mod tests {
         assert_eq!(result.1.len(), 1);
         assert_eq!(result.1[0].weight, 1000);
     }
+
+    #[tokio::test]
+    async fn test_recording_writes_raw_response() {
+        let mock_server = MockServer::start().await;
+        let record_dir = tempfile::TempDir::new().unwrap();
+
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        let body = json!([
+            {
+                "result": { "blocks": 850000, "bestblockhash": "hash" },
+                "error": null,
+                "id": "blockchain-info"
+            },
+            {
+                "result": { "tx1": { "weight": 1000, "fees": { "base": 0.00001000 } } },
+                "error": null,
+                "id": "mempool"
+            }
+        ]);
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body.clone()))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config)
+            .with_recording(record_dir.path())
+            .unwrap();
+        client.get_height_and_mempool().await.unwrap();
+        client.get_height_and_mempool().await.unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(record_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 2);
+
+        let recorded: Value =
+            serde_json::from_str(&std::fs::read_to_string(&files[0]).unwrap()).unwrap();
+        assert_eq!(recorded, body);
+
+        let (height, transactions) =
+            parse_height_and_mempool(&std::fs::read_to_string(&files[1]).unwrap()).unwrap();
+        assert_eq!(height, 850000);
+        assert_eq!(transactions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_mempool_entry() {
+        let mock_server = MockServer::start().await;
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "params": ["aa"] }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": {
+                    "vsize": 141,
+                    "weight": 561,
+                    "ancestorcount": 2,
+                    "ancestorsize": 251,
+                    "descendantcount": 1,
+                    "descendantsize": 141,
+                    "fees": {
+                        "base": 0.00000141,
+                        "modified": 0.00000141,
+                        "ancestor": 0.00000361,
+                        "descendant": 0.00000141
+                    }
+                },
+                "error": null,
+                "id": "mempool-entry"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "params": ["bb"] }),
+            ))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+                "result": null,
+                "error": { "code": -5, "message": "Transaction not in mempool" },
+                "id": "mempool-entry"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config);
+        let entry = client.get_mempool_entry("aa").await.unwrap().unwrap();
+        assert_eq!(entry.tx, Package::new(1, 141, 141));
+        assert_eq!(entry.ancestors, Package::new(2, 251, 361));
+        assert_eq!(entry.descendants, Package::new(1, 141, 141));
+
+        assert_eq!(client.get_mempool_entry("bb").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_incremental_relay_fee() {
+        let mock_server = MockServer::start().await;
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": { "version": 290000, "relayfee": 0.00001, "incrementalfee": 0.00001 },
+                "error": null,
+                "id": "network-info"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config);
+        let fee = client.get_incremental_relay_fee().await.unwrap();
+        assert!((fee - 1.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_get_block() {
+        let mock_server = MockServer::start().await;
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "getblockhash", "params": [900000] }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": "00ab",
+                "error": null,
+                "id": "getblockhash"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "getblock", "params": ["00ab", 2] }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": {
+                    "time": 1737374400,
+                    "tx": [
+                        { "txid": "cb", "weight": 800 },
+                        { "txid": "aa", "weight": 4000, "fee": 0.00002 }
+                    ]
+                },
+                "error": null,
+                "id": "getblock"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "getblockhash", "params": [900001] }),
+            ))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+                "result": null,
+                "error": { "code": -8, "message": "Block height out of range" },
+                "id": "getblockhash"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config);
+        let block = client.get_block(900000).await.unwrap();
+        assert_eq!(block.height, 900000);
+        assert_eq!(block.time.timestamp(), 1737374400);
+        // The coinbase has no fee and is left out
+        assert_eq!(
+            block.transactions,
+            vec![MempoolTransaction::new(4000, 2000)]
+        );
+
+        assert!(matches!(
+            client.get_block(900001).await,
+            Err(RpcError::RpcError { code: -8, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_smart_fee() {
+        let mock_server = MockServer::start().await;
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "estimatesmartfee", "params": [6] }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": { "feerate": 0.00012, "blocks": 6 },
+                "error": null,
+                "id": "estimatesmartfee"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "estimatesmartfee", "params": [144] }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": { "errors": ["Insufficient data or no feerate found"], "blocks": 0 },
+                "error": null,
+                "id": "estimatesmartfee"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config);
+        let fee_rate = client.estimate_smart_fee(6).await.unwrap().unwrap();
+        assert!((fee_rate - 12.0).abs() < 1e-9);
+        assert_eq!(client.estimate_smart_fee(144).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_mempool_min_fee() {
+        let mock_server = MockServer::start().await;
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "getmempoolinfo" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": { "size": 1200, "mempoolminfee": 0.00002, "minrelaytxfee": 0.00001 },
+                "error": null,
+                "id": "getmempoolinfo"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config);
+        let fee = client.get_mempool_min_fee().await.unwrap();
+        assert!((fee - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_entries_without_fees_or_weight() {
+        let body = json!([
+            {
+                "result": { "blocks": 850000, "bestblockhash": "hash" },
+                "error": null,
+                "id": "blockchain-info"
+            },
+            {
+                "result": {
+                    // btcd: fee in BTC next to the sizes
+                    "tx1": { "size": 250, "vsize": 200, "fee": 0.00001000 },
+                    // Bitcoin Core before 0.17: no vsize, fee only as the descendant fee
+                    "tx2": { "size": 150, "descendantcount": 1, "descendantfees": 3000 },
+                    // A parent's descendant fee includes its children's
+                    "tx3": { "size": 150, "descendantcount": 2, "descendantfees": 5000 },
+                    "tx4": { "fee": 0.00001000 }
+                },
+                "error": null,
+                "id": "mempool"
+            }
+        ]);
+
+        let (height, transactions) = parse_height_and_mempool(&body.to_string()).unwrap();
+        assert_eq!(height, 850000);
+        assert_eq!(
+            transactions,
+            vec![
+                MempoolTransaction::new(800, 1000),
+                MempoolTransaction::new(600, 3000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_node_implementation_from_subversion() {
+        assert_eq!(
+            NodeImplementation::from_subversion("/Satoshi:27.1.0/"),
+            NodeImplementation::BitcoinCore
+        );
+        assert_eq!(
+            NodeImplementation::from_subversion("/Satoshi:27.1.0/Knots:20240801/"),
+            NodeImplementation::BitcoinKnots
+        );
+        assert_eq!(
+            NodeImplementation::from_subversion("/btcwire:0.5.0/btcd:0.24.2/"),
+            NodeImplementation::Btcd
+        );
+        assert!(!NodeImplementation::from_subversion("/libbitcoin:3.0/").is_core_compatible());
+    }
+
+    #[tokio::test]
+    async fn test_detect_node_implementation() {
+        let mock_server = MockServer::start().await;
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "getnetworkinfo" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": { "version": 240200, "subversion": "/btcwire:0.5.0/btcd:0.24.2/" },
+                "error": null,
+                "id": "getnetworkinfo"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config);
+        assert_eq!(client.node_implementation(), None);
+        let node = client.detect_node_implementation().await.unwrap();
+        assert_eq!(node, NodeImplementation::Btcd);
+        assert_eq!(
+            client.node_implementation(),
+            Some(&NodeImplementation::Btcd)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proxy() {
+        let config = BitcoinRpcConfig {
+            url: "http://abcdefghijklmnop.onion:8332".to_string(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+        assert!(matches!(
+            BitcoinRpcClient::new(config.clone()).with_proxy("http://127.0.0.1:9050"),
+            Err(RpcError::ProxyError(_))
+        ));
+        assert!(matches!(
+            BitcoinRpcClient::new(config.clone()).with_proxy("127.0.0.1:9050"),
+            Err(RpcError::ProxyError(_))
+        ));
+
+        // Without a proxy there is nothing to check
+        assert!(BitcoinRpcClient::new(config.clone())
+            .test_proxy()
+            .await
+            .is_ok());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = BitcoinRpcClient::new(config.clone())
+            .with_proxy(&format!("socks5h://127.0.0.1:{port}"))
+            .unwrap();
+        assert!(client.test_proxy().await.is_ok());
+
+        drop(listener);
+        assert!(matches!(
+            client.test_proxy().await,
+            Err(RpcError::ProxyError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_probe_rpc_access() {
+        let mock_server = MockServer::start().await;
+        let config = BitcoinRpcConfig {
+            url: mock_server.uri(),
+            username: "test".to_string(),
+            password: "pass".to_string(),
+        };
+
+        // Whitelisted to the required methods but getmempoolinfo
+        for method_name in ["getblockchaininfo", "getrawmempool"] {
+            Mock::given(method("POST"))
+                .and(wiremock::matchers::body_partial_json(
+                    json!({ "method": method_name }),
+                ))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "result": {},
+                    "error": null,
+                    "id": method_name
+                })))
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&mock_server)
+            .await;
+
+        let client = BitcoinRpcClient::new(config.clone());
+        assert_eq!(
+            client.probe_rpc_access().await.unwrap(),
+            RpcAccess {
+                missing: vec!["getmempoolinfo"],
+                wallet: false,
+            }
+        );
+
+        // Full credentials on a node with a wallet
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": [],
+                "error": null,
+                "id": "probe"
+            })))
+            .mount(&mock_server)
+            .await;
+        assert_eq!(
+            client.probe_rpc_access().await.unwrap(),
+            RpcAccess {
+                missing: vec![],
+                wallet: true,
+            }
+        );
+
+        // Full credentials on a node built without a wallet
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .and(wiremock::matchers::body_partial_json(
+                json!({ "method": "listwallets" }),
+            ))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "result": null,
+                "error": { "code": -32601, "message": "Method not found" },
+                "id": "listwallets"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "result": {},
+                "error": null,
+                "id": "probe"
+            })))
+            .mount(&mock_server)
+            .await;
+        assert!(!client.probe_rpc_access().await.unwrap().wallet);
+    }
 }
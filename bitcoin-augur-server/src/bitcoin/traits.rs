@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use bitcoin_augur::MempoolTransaction;
 
-use super::RpcError;
+use super::{MinedBlock, RpcError, TxMempoolEntry};
 
 /// Trait for Bitcoin RPC operations
 #[async_trait]
@@ -11,12 +11,28 @@ pub trait BitcoinRpc: Send + Sync {
 
     /// Get current block height and mempool transactions
     async fn get_height_and_mempool(&self) -> Result<(u32, Vec<MempoolTransaction>), RpcError>;
+
+    /// Get a transaction's mempool entry, or None if it is not in the mempool
+    async fn get_mempool_entry(&self, txid: &str) -> Result<Option<TxMempoolEntry>, RpcError>;
+
+    /// Get the incremental relay fee replacements must add, in sat/vB
+    async fn get_incremental_relay_fee(&self) -> Result<f64, RpcError>;
+
+    /// Get the lowest fee rate the node's mempool currently admits, in sat/vB
+    async fn get_mempool_min_fee(&self) -> Result<f64, RpcError>;
+
+    /// Get the block at `height` with the transactions it confirmed
+    async fn get_block(&self, height: u32) -> Result<MinedBlock, RpcError>;
+
+    /// Get the node's own fee estimate for `conf_target` blocks in sat/vB, if it has one
+    async fn estimate_smart_fee(&self, conf_target: u32) -> Result<Option<f64>, RpcError>;
 }
 
-/// Wrapper enum for real or mock client
+/// Wrapper enum for real, mock or replaying client
 pub enum BitcoinClient {
     Real(super::BitcoinRpcClient),
     Mock(super::MockBitcoinClient),
+    Replay(super::ReplayBitcoinClient),
 }
 
 #[async_trait]
@@ -25,6 +41,7 @@ impl BitcoinRpc for BitcoinClient {
         match self {
             BitcoinClient::Real(client) => client.test_connection().await,
             BitcoinClient::Mock(client) => client.test_connection().await,
+            BitcoinClient::Replay(client) => client.test_connection().await,
         }
     }
 
@@ -32,6 +49,47 @@ impl BitcoinRpc for BitcoinClient {
         match self {
             BitcoinClient::Real(client) => client.get_height_and_mempool().await,
             BitcoinClient::Mock(client) => client.get_height_and_mempool().await,
+            BitcoinClient::Replay(client) => client.get_height_and_mempool().await,
+        }
+    }
+
+    async fn get_mempool_entry(&self, txid: &str) -> Result<Option<TxMempoolEntry>, RpcError> {
+        match self {
+            BitcoinClient::Real(client) => client.get_mempool_entry(txid).await,
+            BitcoinClient::Mock(client) => client.get_mempool_entry(txid).await,
+            BitcoinClient::Replay(client) => client.get_mempool_entry(txid).await,
+        }
+    }
+
+    async fn get_incremental_relay_fee(&self) -> Result<f64, RpcError> {
+        match self {
+            BitcoinClient::Real(client) => client.get_incremental_relay_fee().await,
+            BitcoinClient::Mock(client) => client.get_incremental_relay_fee().await,
+            BitcoinClient::Replay(client) => client.get_incremental_relay_fee().await,
+        }
+    }
+
+    async fn get_mempool_min_fee(&self) -> Result<f64, RpcError> {
+        match self {
+            BitcoinClient::Real(client) => client.get_mempool_min_fee().await,
+            BitcoinClient::Mock(client) => client.get_mempool_min_fee().await,
+            BitcoinClient::Replay(client) => client.get_mempool_min_fee().await,
+        }
+    }
+
+    async fn get_block(&self, height: u32) -> Result<MinedBlock, RpcError> {
+        match self {
+            BitcoinClient::Real(client) => client.get_block(height).await,
+            BitcoinClient::Mock(client) => client.get_block(height).await,
+            BitcoinClient::Replay(client) => client.get_block(height).await,
+        }
+    }
+
+    async fn estimate_smart_fee(&self, conf_target: u32) -> Result<Option<f64>, RpcError> {
+        match self {
+            BitcoinClient::Real(client) => client.estimate_smart_fee(conf_target).await,
+            BitcoinClient::Mock(client) => client.estimate_smart_fee(conf_target).await,
+            BitcoinClient::Replay(client) => client.estimate_smart_fee(conf_target).await,
         }
     }
 }
@@ -1,25 +1,39 @@
 //! Command-line interface configuration
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// Bitcoin Augur Server CLI
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     // Server options
-    /// Host to bind the server to
-    #[arg(short = 'H', long, default_value = "0.0.0.0")]
-    pub host: String,
+    /// Host to bind the server to [default: 0.0.0.0]
+    #[arg(short = 'H', long)]
+    pub host: Option<String>,
 
-    /// Port to listen on
-    #[arg(short, long, default_value_t = 8080)]
-    pub port: u16,
+    /// Port to listen on, or 0 for any free port [default: 8080]
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// Address to listen on instead of --host and --port, such as 127.0.0.1:8080 or [::]:8080;
+    /// may be repeated
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Vec<String>,
+
+    /// Write the bound addresses to this file, one per line, once the server is ready
+    #[arg(long, value_name = "PATH")]
+    pub ready_file: Option<String>,
+
+    /// When the server is ready, for --ready-file and systemd: once it listens, or once it
+    /// serves an estimate [default: listening]
+    #[arg(long, value_name = "WHEN", value_parser = ["listening", "warm"])]
+    pub ready_when: Option<String>,
 
     // Bitcoin RPC options
-    /// Bitcoin Core RPC URL
-    #[arg(long, default_value = "http://localhost:8332")]
-    pub rpc_url: String,
+    /// Bitcoin Core RPC URL [default: http://localhost:8332]
+    #[arg(long)]
+    pub rpc_url: Option<String>,
 
     /// Bitcoin Core RPC username
     #[arg(long)]
@@ -29,23 +43,143 @@ pub struct Cli {
     #[arg(long)]
     pub rpc_password: Option<String>,
 
+    /// File holding the Bitcoin Core RPC password, such as a Docker or Kubernetes secret
+    #[arg(long, value_name = "PATH")]
+    pub rpc_password_file: Option<String>,
+
+    /// Command printing the Bitcoin Core RPC password, such as `pass show bitcoin/rpc`
+    #[arg(long, value_name = "COMMAND")]
+    pub rpc_password_cmd: Option<String>,
+
     /// Path to Bitcoin Core cookie file (defaults to ~/.bitcoin/.cookie if no auth provided)
     #[arg(long)]
     pub rpc_cookie_file: Option<String>,
 
+    /// SOCKS5 proxy to reach Bitcoin Core through, e.g. socks5h://127.0.0.1:9050 for Tor
+    #[arg(long, value_name = "URL")]
+    pub rpc_proxy: Option<String>,
+
+    /// Start even though the RPC credentials may call wallet methods, instead of requiring
+    /// credentials restricted with rpcwhitelist
+    #[arg(long)]
+    pub allow_full_rpc: bool,
+
     // Data persistence
-    /// Directory for storing mempool snapshots
-    #[arg(short, long, default_value = "mempool_data")]
-    pub data_dir: String,
+    /// Directory for storing mempool snapshots [default: mempool_data]
+    #[arg(short, long, global = true)]
+    pub data_dir: Option<String>,
+
+    /// Days to keep old snapshots [default: 30]
+    #[arg(long)]
+    pub cleanup_days: Option<i64>,
 
-    /// Days to keep old snapshots
-    #[arg(long, default_value_t = 30)]
-    pub cleanup_days: i64,
+    /// Store a snapshot identical to the previous one as a small marker instead of in full
+    #[arg(long)]
+    pub dedup_snapshots: bool,
+
+    /// Copy the data directory next to itself before migrating it to a newer format
+    #[arg(long)]
+    pub backup_before_migration: bool,
+
+    /// S3 bucket to write snapshots through to and fetch days missing locally from
+    #[arg(long)]
+    pub s3_bucket: Option<String>,
+
+    /// Prefix of the snapshot keys in the S3 bucket
+    #[arg(long)]
+    pub s3_prefix: Option<String>,
+
+    /// URL of an S3-compatible service, such as http://localhost:9000 for MinIO
+    #[arg(long)]
+    pub s3_endpoint: Option<String>,
+
+    /// Region of the S3 bucket [default: us-east-1]
+    #[arg(long)]
+    pub s3_region: Option<String>,
 
     // Collection settings
-    /// Mempool collection interval in seconds
-    #[arg(long, default_value_t = 30)]
-    pub interval_secs: u64,
+    /// Mempool collection interval in seconds [default: 30]
+    #[arg(long)]
+    pub interval_secs: Option<u64>,
+
+    /// Poll as often as every SECS right after a block or while the inflow is volatile,
+    /// making the interval adapt to mempool activity
+    #[arg(long, value_name = "SECS")]
+    pub min_interval_secs: Option<u64>,
+
+    /// Back off to polling every SECS while the mempool is quiet, making the interval adapt
+    /// to mempool activity
+    #[arg(long, value_name = "SECS")]
+    pub max_interval_secs: Option<u64>,
+
+    /// Longest time between snapshots before estimates are flagged as degraded, in seconds
+    /// (0 disables the check)
+    #[arg(long, value_name = "SECS")]
+    pub max_gap_secs: Option<u64>,
+
+    /// Reconstruct snapshots for gaps in the history from the blocks mined during them
+    #[arg(long)]
+    pub backfill_gaps: bool,
+
+    /// Answer fee requests with 503 once the latest snapshot is more than SECS old, such as
+    /// while Bitcoin Core is unreachable (0 serves estimates however old)
+    #[arg(long, value_name = "SECS")]
+    pub max_staleness_secs: Option<u64>,
+
+    /// Keep estimates for up to N block targets requested at /fees/target/{n} until the next
+    /// snapshot or collection interval (0 recomputes every request)
+    #[arg(long, value_name = "N")]
+    pub target_cache_size: Option<usize>,
+
+    /// Estimate at most N distinct block targets per minute, answering others with 429
+    /// (0 allows any number)
+    #[arg(long, value_name = "N")]
+    pub max_targets_per_minute: Option<usize>,
+
+    /// Append every collected snapshot to FILE as an anonymized trace of bucketed weights,
+    /// without transaction ids, for sharing as a regression or benchmark scenario
+    #[arg(long, value_name = "FILE")]
+    pub record_trace: Option<String>,
+
+    /// Collect only while holding a lease in the data directory that expires SECS after each
+    /// renewal, so that one of several instances sharing the directory collects and another
+    /// takes over when it stops (0 always collects)
+    #[arg(long, value_name = "SECS")]
+    pub leader_lease_secs: Option<u64>,
+
+    /// Name to hold the collector lease under [default: host name and process id]
+    #[arg(long, value_name = "NAME")]
+    pub instance_id: Option<String>,
+
+    /// Fetch every new block to record the fee rates it actually confirmed, scoring estimates
+    /// against them at /debug/blocks and in evaluate
+    #[arg(long)]
+    pub block_feedback: bool,
+
+    /// Record the weight of transactions whose unconfirmed ancestors exceed Bitcoin Core's default
+    /// package limits (25 transactions, 101 kvB), keeping it out of the next block when estimating.
+    /// Only matters for nodes relaying with raised ancestor limits
+    #[arg(long)]
+    pub package_limits: bool,
+
+    /// Accept snapshots from external collectors at POST /snapshots, authenticated with this
+    /// bearer token
+    #[arg(long, value_name = "TOKEN")]
+    pub ingest_token: Option<String>,
+
+    /// Serve operator endpoints such as GET /admin/cycles, authenticated with this bearer token,
+    /// which also allows longer historical fee exports
+    #[arg(long, value_name = "TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Serve stored snapshots to secondary servers at GET /sync/changes, authenticated with this
+    /// bearer token
+    #[arg(long, value_name = "TOKEN")]
+    pub sync_token: Option<String>,
+
+    /// Do not poll a Bitcoin node; estimate only from snapshots sent to POST /snapshots
+    #[arg(long)]
+    pub ingest_only: bool,
 
     // Test mode
     /// Enable test mode with mock Bitcoin client
@@ -56,6 +190,128 @@ pub struct Cli {
     #[arg(long)]
     pub use_mock_data: bool,
 
+    /// Scenario file (YAML or JSON) the mock client advances through on each poll
+    #[arg(long, value_name = "FILE")]
+    pub mock_scenario: Option<String>,
+
+    /// Enable the authenticated test-only snapshot injection endpoint (POST /test/snapshots)
+    #[arg(long)]
+    pub enable_test_api: bool,
+
+    /// Bearer token required by the test-only endpoints
+    #[arg(long, value_name = "TOKEN")]
+    pub test_api_token: Option<String>,
+
+    /// Enable GET /debug/simulation, returning the per-bucket mining simulation behind an
+    /// estimate
+    #[arg(long)]
+    pub enable_debug_api: bool,
+
+    /// Serve a naive baseline strategy next to the augur estimate at GET /fees/compare, for
+    /// A/B evaluation: top-vmb (fee rates among the top N vMB of the mempool), core (the
+    /// node's estimatesmartfee) or mempool-space (its recommended fees). Repeat to serve several
+    #[arg(
+        long = "baseline",
+        value_name = "NAME",
+        value_parser = ["top-vmb", "core", "mempool-space"]
+    )]
+    pub baselines: Vec<String>,
+
+    /// Base URL of the mempool.space instance read by the mempool-space baseline
+    #[arg(long, value_name = "URL")]
+    pub mempool_space_url: Option<String>,
+
+    /// Bitcoin price feed to quote fee estimates in fiat with `?fiat=usd`, such as
+    /// https://mempool.space/api/v1/prices
+    #[arg(long, value_name = "URL")]
+    pub price_feed_url: Option<String>,
+
+    /// Decimal places every fractional number in a response is rounded to (default: 4)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(0..=15))]
+    pub decimal_places: Option<u32>,
+
+    /// Also serve the weighted median of augur and the baselines at GET /fees/compare, as
+    /// "ensemble". Repeat for each member, such as `--ensemble-weight augur=2`
+    #[arg(long = "ensemble-weight", value_name = "NAME=WEIGHT", value_parser = parse_ensemble_weight)]
+    pub ensemble_weights: Vec<(String, f64)>,
+
+    /// Casing of response field names unless a request asks for one with an
+    /// `Accept: application/json; profile="..."` header: snake_case or camel_case
+    #[arg(long, value_name = "CASE", value_parser = ["snake_case", "camel_case"])]
+    pub field_naming: Option<String>,
+
+    /// In test mode, advance a simulated clock by this many seconds per collection cycle
+    #[arg(long, value_name = "SECS")]
+    pub simulated_clock_step_secs: Option<u64>,
+
+    /// Start time of the simulated clock (RFC 3339, defaults to now)
+    #[arg(long, value_name = "TIME", requires = "simulated_clock_step_secs")]
+    pub simulated_clock_start: Option<String>,
+
+    // Estimation
+    /// Round every published fee rate: exact, ceil_tenth (up to 0.1 sat/vB) or ceil_integer
+    #[arg(long, value_name = "POLICY", value_parser = ["exact", "ceil_tenth", "ceil_integer"])]
+    pub fee_rounding: Option<String>,
+
+    /// Exclude snapshots whose total weight or fee bucket count is this many robust
+    /// standard deviations away from their neighbours' (e.g. 5)
+    #[arg(long, value_name = "Z")]
+    pub outlier_threshold: Option<f64>,
+
+    /// Clamp every published fee rate to at most this many sat/vB and flag it as capped
+    #[arg(long, value_name = "SAT_PER_VB")]
+    pub max_fee_rate: Option<f64>,
+
+    /// Keep each confidence level's fee rate at least FRACTION above the next lower level's
+    /// (e.g. 0.02 for 2%), so adjacent options never show the same fee rate
+    #[arg(long, value_name = "FRACTION")]
+    pub min_spread: Option<f64>,
+
+    /// Sample this many block arrival sequences per target instead of mining the expected
+    /// number of blocks at each confidence level
+    #[arg(long, value_name = "SAMPLES")]
+    pub monte_carlo_samples: Option<usize>,
+
+    /// Seed of the sampled block arrival sequences, so estimates are reproducible (default: 0)
+    #[arg(long, value_name = "SEED", requires = "monte_carlo_samples")]
+    pub monte_carlo_seed: Option<u64>,
+
+    /// Also estimate 1 and 2 block targets from the current mempool and short-term inflow;
+    /// these are flagged as high variance
+    #[arg(long)]
+    pub fast_targets: bool,
+
+    /// Learn hour-of-week inflow patterns from this many weeks of stored snapshots and
+    /// adjust long-term inflows for the coming hours
+    #[arg(long, value_name = "WEEKS")]
+    pub seasonality_weeks: Option<u32>,
+
+    /// Fraction of the seasonal adjustment applied, from 0 to 1 (default: 0.5)
+    #[arg(long, value_name = "FRACTION", requires = "seasonality_weeks")]
+    pub seasonality_blend: Option<f64>,
+
+    // Response caching
+    /// Omit the `valid_until` cache expiry times from fee responses
+    #[arg(long)]
+    pub no_valid_until: bool,
+
+    /// Fraction of a block target's expected confirmation time its estimate stays valid
+    #[arg(long, value_name = "FRACTION", conflicts_with = "no_valid_until")]
+    pub valid_until_fraction: Option<f64>,
+
+    /// Longest time any estimate stays valid, in seconds
+    #[arg(long, value_name = "SECS", conflicts_with = "no_valid_until")]
+    pub valid_until_max_secs: Option<u64>,
+
+    // Response signing
+    /// Sign fee responses with the Ed25519 secret key in this file (64 hex characters)
+    #[arg(long, value_name = "FILE")]
+    pub signing_key_file: Option<String>,
+
+    /// Key id sent with each signature (defaults to the hex-encoded public key)
+    #[arg(long, value_name = "ID")]
+    pub signing_key_id: Option<String>,
+
     // Logging
     /// Log filter (e.g., "bitcoin_augur_server=debug,bitcoin_augur=info")
     #[arg(long, default_value = "bitcoin_augur_server=info,bitcoin_augur=info")]
@@ -66,9 +322,99 @@ pub struct Cli {
     #[arg(long)]
     pub init_from_store: bool,
 
+    // Debugging
+    /// Persist every raw getblockchaininfo/getrawmempool response into this directory
+    #[arg(long, value_name = "DIR")]
+    pub record_rpc: Option<String>,
+
+    /// Replay RPC responses previously captured with --record-rpc instead of querying a node
+    #[arg(long, value_name = "DIR", conflicts_with = "record_rpc")]
+    pub replay_rpc: Option<String>,
+
     /// Path to configuration file (overridden by CLI args)
     #[arg(short, long)]
     pub config: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Offline commands run instead of the server
+#[derive(Subcommand)]
+pub enum Command {
+    /// Replay stored snapshots and report how often estimates confirmed within their target
+    Evaluate {
+        /// Days of stored snapshots to replay
+        #[arg(long, default_value_t = 30)]
+        days: i64,
+
+        /// Estimate at every Nth block only, to shorten long evaluations
+        #[arg(long, default_value_t = 1)]
+        block_step: u32,
+
+        /// Strategy to evaluate: augur, or the top-vmb baseline to compare it with
+        #[arg(long, default_value = "augur", value_parser = ["augur", "top-vmb"])]
+        strategy: String,
+
+        /// Also evaluate augur with the Monte Carlo simulation on the same snapshots and
+        /// compare it with the deterministic one
+        #[arg(long, conflicts_with = "strategy")]
+        compare_monte_carlo: bool,
+    },
+
+    /// Check every stored snapshot file for corruption or a wrong name
+    Fsck {
+        /// Delete corrupt files and move misnamed ones to where the store looks for them
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Pack the JSON snapshots of every day before today into one binary file per day
+    Pack,
+
+    /// Archive the snapshots of every month before the current one into one binary file per
+    /// month, removing their date directories
+    Archive,
+
+    /// Copy the snapshots of a primary server into the data directory through its
+    /// GET /sync/changes endpoint
+    Sync {
+        /// Base URL of the primary server, such as http://primary:8080
+        #[arg(long)]
+        from: String,
+
+        /// Bearer token the primary's sync endpoint requires
+        #[arg(long, value_name = "TOKEN")]
+        token: Option<String>,
+
+        /// Keep pulling new snapshots every collection interval instead of exiting once caught
+        /// up
+        #[arg(long)]
+        follow: bool,
+    },
+
+    /// Migrate the data directory to the current format, which also happens on startup
+    Migrate {
+        /// Report what each migration would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Check or print the configuration without starting the server
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+/// What to do with the configuration
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Report every problem with the configuration files and arguments
+    Validate,
+
+    /// Print the configuration the server would run with, as TOML with secrets redacted
+    PrintEffective,
 }
 
 /// Read Bitcoin Core cookie file and extract credentials
@@ -81,3 +427,14 @@ pub fn read_cookie_file(path: &str) -> Result<(String, String)> {
     }
     Ok((parts[0].to_string(), parts[1].to_string()))
 }
+
+/// Parse an ensemble member weight given as `NAME=WEIGHT`
+fn parse_ensemble_weight(value: &str) -> Result<(String, f64), String> {
+    let (name, weight) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=WEIGHT, got {value:?}"))?;
+    let weight = weight
+        .parse::<f64>()
+        .map_err(|e| format!("invalid weight {weight:?}: {e}"))?;
+    Ok((name.trim().to_string(), weight))
+}
@@ -1,8 +1,30 @@
+use bitcoin_augur::RoundingPolicy;
 use config::{Config, ConfigError, File};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
+use crate::api::FieldNaming;
 use crate::cli::{read_cookie_file, Cli};
+use crate::service::{AlertRule, Baseline};
+
+mod env;
+mod secrets;
+mod validate;
+
+use env::EnvSettings;
+
+#[allow(unused_imports)]
+pub use env::{env_vars, EnvVar, ENV_PREFIX};
+pub use validate::ConfigErrors;
+#[allow(unused_imports)]
+pub use validate::ConfigIssue;
+
+/// Configuration files read when `--config` is not given, in increasing priority
+const DEFAULT_CONFIG_FILES: [&str; 3] = ["augur.toml", "augur.yaml", "augur.json"];
+
+/// Placeholder printed instead of passwords and tokens
+pub(crate) const REDACTED: &str = "<redacted>";
 
 /// Application configuration
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
@@ -12,6 +34,18 @@ pub struct AppConfig {
     pub persistence: PersistenceConfig,
     pub collector: CollectorConfig,
     pub test_mode: TestModeConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub signing: SigningConfig,
+    #[serde(default)]
+    pub estimator: EstimatorConfig,
+    #[serde(default)]
+    pub validity: ValidityConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub response: ResponseConfig,
 }
 
 /// HTTP server configuration
@@ -21,6 +55,28 @@ pub struct ServerConfig {
     pub host: String,
     /// Port to listen on (default: 8080)
     pub port: u16,
+    /// Addresses to listen on instead of host and port, such as `127.0.0.1:8080` or
+    /// `[::]:8080` (default: none)
+    #[serde(default)]
+    pub listen: Vec<String>,
+    /// File the bound addresses are written to once the server is ready, one per line
+    /// (default: none)
+    #[serde(default)]
+    pub ready_file: Option<String>,
+    /// When the server is ready, for the ready file and systemd (default: listening)
+    #[serde(default)]
+    pub ready_when: ReadyWhen,
+}
+
+/// When the server counts as ready, to the ready file and systemd
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadyWhen {
+    /// As soon as every address listens
+    #[default]
+    Listening,
+    /// Once an estimate is served, as `GET /health/ready` reports
+    Warm,
 }
 
 impl Default for ServerConfig {
@@ -28,12 +84,34 @@ impl Default for ServerConfig {
         Self {
             host: "0.0.0.0".to_string(),
             port: 8080,
+            listen: Vec::new(),
+            ready_file: None,
+            ready_when: ReadyWhen::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Addresses the server binds: `listen`, or else `host` and `port`, with an IPv6 host
+    /// in brackets
+    pub fn bind_addresses(&self) -> Vec<String> {
+        if !self.listen.is_empty() {
+            return self.listen.clone();
+        }
+        if self.host.contains(':') && !self.host.starts_with('[') {
+            vec![format!(
+                "[{host}]:{port}",
+                host = self.host,
+                port = self.port
+            )]
+        } else {
+            vec![format!("{host}:{port}", host = self.host, port = self.port)]
         }
     }
 }
 
 /// Bitcoin RPC configuration
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct BitcoinRpcConfig {
     /// RPC URL (default: http://localhost:8332)
     pub url: String,
@@ -41,6 +119,19 @@ pub struct BitcoinRpcConfig {
     pub username: String,
     /// RPC password
     pub password: String,
+    /// File holding the RPC password, such as a Docker or Kubernetes secret (default: none)
+    #[serde(default)]
+    pub password_file: Option<String>,
+    /// Command printing the RPC password, run through `sh -c` (default: none)
+    #[serde(default)]
+    pub password_cmd: Option<String>,
+    /// SOCKS5 proxy the RPC requests go through, such as `socks5h://127.0.0.1:9050` for Tor
+    /// (default: none)
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Start even though the credentials may call wallet methods (default: false)
+    #[serde(default)]
+    pub allow_full_rpc: bool,
 }
 
 impl Default for BitcoinRpcConfig {
@@ -49,17 +140,69 @@ impl Default for BitcoinRpcConfig {
             url: "http://localhost:8332".to_string(),
             username: String::new(),
             password: String::new(),
+            password_file: None,
+            password_cmd: None,
+            proxy: None,
+            allow_full_rpc: false,
         }
     }
 }
 
+impl std::fmt::Debug for BitcoinRpcConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BitcoinRpcConfig")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &redact_secret(&self.password))
+            .field("password_file", &self.password_file)
+            .field("password_cmd", &self.password_cmd)
+            .field("proxy", &self.proxy)
+            .field("allow_full_rpc", &self.allow_full_rpc)
+            .finish()
+    }
+}
+
+/// `secret` as printed in logs: the placeholder, or nothing if it is unset
+pub(crate) fn redact_secret(secret: &str) -> &str {
+    if secret.is_empty() {
+        secret
+    } else {
+        REDACTED
+    }
+}
+
 /// Persistence configuration
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct PersistenceConfig {
     /// Directory for storing snapshots (default: mempool_data)
     pub data_directory: String,
     /// Days to keep old snapshots (default: 30)
     pub cleanup_days: i64,
+    /// Store a snapshot identical to the previous one as a small marker (default: false)
+    #[serde(default)]
+    pub dedup_snapshots: bool,
+    /// Copy the data directory aside before migrating it to a newer format (default: false)
+    #[serde(default)]
+    pub backup_before_migration: bool,
+    /// S3 bucket snapshots are written through to and missing days are fetched from
+    /// (default: none)
+    #[serde(default)]
+    pub s3_bucket: Option<String>,
+    /// Prefix of the snapshot keys in the bucket (default: none)
+    #[serde(default)]
+    pub s3_prefix: String,
+    /// URL of an S3-compatible service (default: the AWS endpoint of the region)
+    #[serde(default)]
+    pub s3_endpoint: Option<String>,
+    /// Region of the bucket (default: us-east-1)
+    #[serde(default)]
+    pub s3_region: Option<String>,
+    /// Access key ID (default: the AWS_ACCESS_KEY_ID environment variable)
+    #[serde(default)]
+    pub s3_access_key_id: String,
+    /// Secret access key (default: the AWS_SECRET_ACCESS_KEY environment variable)
+    #[serde(default)]
+    pub s3_secret_access_key: String,
 }
 
 impl Default for PersistenceConfig {
@@ -67,20 +210,144 @@ impl Default for PersistenceConfig {
         Self {
             data_directory: "mempool_data".to_string(),
             cleanup_days: 30,
+            dedup_snapshots: false,
+            backup_before_migration: false,
+            s3_bucket: None,
+            s3_prefix: String::new(),
+            s3_endpoint: None,
+            s3_region: None,
+            s3_access_key_id: String::new(),
+            s3_secret_access_key: String::new(),
         }
     }
 }
 
+impl std::fmt::Debug for PersistenceConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersistenceConfig")
+            .field("data_directory", &self.data_directory)
+            .field("cleanup_days", &self.cleanup_days)
+            .field("dedup_snapshots", &self.dedup_snapshots)
+            .field("backup_before_migration", &self.backup_before_migration)
+            .field("s3_bucket", &self.s3_bucket)
+            .field("s3_prefix", &self.s3_prefix)
+            .field("s3_endpoint", &self.s3_endpoint)
+            .field("s3_region", &self.s3_region)
+            .field("s3_access_key_id", &self.s3_access_key_id)
+            .field(
+                "s3_secret_access_key",
+                &redact_secret(&self.s3_secret_access_key),
+            )
+            .finish()
+    }
+}
+
 /// Mempool collector configuration
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CollectorConfig {
     /// Collection interval in milliseconds (default: 30000)
     pub interval_ms: u64,
+    /// Shortest interval the collector speeds up to right after a block or while the inflow
+    /// is volatile, in milliseconds (default: none, the interval is fixed)
+    #[serde(default)]
+    pub min_interval_ms: Option<u64>,
+    /// Longest interval the collector backs off to while the mempool is quiet, in
+    /// milliseconds (default: none, the interval is fixed)
+    #[serde(default)]
+    pub max_interval_ms: Option<u64>,
+    /// Relative difference between the short-term and long-term inflow rates that counts as
+    /// volatile, when the interval adapts (default: 0.5)
+    #[serde(default = "default_volatility_threshold")]
+    pub volatility_threshold: f64,
+    /// Never poll a Bitcoin node; snapshots only arrive through `POST /snapshots`
+    #[serde(default)]
+    pub ingest_only: bool,
+    /// Longer stretches without snapshots mark estimates as degraded; 0 disables the check
+    /// (default: 600)
+    #[serde(default = "default_max_gap_secs")]
+    pub max_gap_secs: u64,
+    /// Reconstruct snapshots for gaps from the blocks mined during them (default: false)
+    #[serde(default)]
+    pub backfill_gaps: bool,
+    /// Fee requests fail with 503 once the latest snapshot is older than this; 0 serves
+    /// estimates however old (default: 0)
+    #[serde(default)]
+    pub max_staleness_secs: u64,
+    /// Block targets whose `/fees/target/{n}` estimates are kept until the next snapshot or
+    /// collection interval; 0 recomputes every request (default: 64)
+    #[serde(default = "default_target_cache_size")]
+    pub target_cache_size: usize,
+    /// Distinct block targets estimated per minute, beyond which requests fail with 429; 0
+    /// allows any number (default: 0)
+    #[serde(default)]
+    pub max_targets_per_minute: usize,
+    /// Append every collected snapshot to this anonymized trace file (default: none)
+    #[serde(default)]
+    pub record_trace: Option<String>,
+    /// Label every collected snapshot with this source name, to compare it with other sources
+    /// at GET /fees/sources (default: none)
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Collection cycles whose timings are kept for `GET /admin/cycles`; 0 disables the cycle
+    /// log (default: 1000)
+    #[serde(default = "default_cycle_log_size")]
+    pub cycle_log_size: usize,
+    /// Collect only while holding a lease in the data directory, renewed every cycle and
+    /// expiring this many seconds after, so one of the instances sharing the directory
+    /// collects; 0 always collects (default: 0)
+    #[serde(default)]
+    pub leader_lease_secs: u64,
+    /// Name this instance holds the collector lease under (default: the host name and
+    /// process id)
+    #[serde(default)]
+    pub instance_id: Option<String>,
+    /// Fetch every new block to record the fee rates it confirmed, and score estimates against
+    /// them (default: false)
+    #[serde(default)]
+    pub block_feedback: bool,
+    /// Record the weight of transactions whose unconfirmed ancestors exceed Bitcoin Core's
+    /// default package limits, keeping it out of the next block when estimating (default: false)
+    #[serde(default)]
+    pub package_limits: bool,
+}
+
+fn default_volatility_threshold() -> f64 {
+    0.5
+}
+
+fn default_max_gap_secs() -> u64 {
+    600
+}
+
+fn default_target_cache_size() -> usize {
+    64
+}
+
+fn default_cycle_log_size() -> usize {
+    1000
 }
 
 impl Default for CollectorConfig {
     fn default() -> Self {
-        Self { interval_ms: 30000 }
+        Self {
+            interval_ms: 30000,
+            min_interval_ms: None,
+            max_interval_ms: None,
+            volatility_threshold: default_volatility_threshold(),
+            ingest_only: false,
+            max_gap_secs: default_max_gap_secs(),
+            backfill_gaps: false,
+            max_staleness_secs: 0,
+            target_cache_size: default_target_cache_size(),
+            max_targets_per_minute: 0,
+            record_trace: None,
+            source: None,
+            cycle_log_size: default_cycle_log_size(),
+            leader_lease_secs: 0,
+            instance_id: None,
+            block_feedback: false,
+            package_limits: false,
+        }
     }
 }
 
@@ -93,11 +360,274 @@ pub struct TestModeConfig {
     /// Use mock data instead of real Bitcoin data
     #[serde(default)]
     pub use_mock_data: bool,
+    /// Scenario file driving the mock client (timeline of mempool states)
+    #[serde(default)]
+    pub scenario_file: Option<String>,
+    /// Advance a simulated clock by this many seconds per collection cycle instead of
+    /// using wall-clock time
+    #[serde(default)]
+    pub simulated_clock_step_secs: Option<u64>,
+    /// Start time of the simulated clock (RFC 3339, default: now)
+    #[serde(default)]
+    pub simulated_clock_start: Option<String>,
+}
+
+/// Numeric formatting shared by every response
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct ResponseConfig {
+    /// Decimal places every fractional number is rounded to (default: 4)
+    pub decimal_places: u32,
+}
+
+impl ResponseConfig {
+    /// Most decimal places an `f64` carries meaningfully
+    pub const MAX_DECIMAL_PLACES: u32 = 15;
+}
+
+impl Default for ResponseConfig {
+    fn default() -> Self {
+        Self { decimal_places: 4 }
+    }
+}
+
+/// Alert rules evaluated after every collection
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct AlertsConfig {
+    /// Rules firing when a metric stays above or below a threshold (default: none)
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+    /// URL every firing and resolved alert is posted to as JSON (default: only logged)
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+/// Fee response signing configuration
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct SigningConfig {
+    /// File holding the Ed25519 secret key as 64 hex characters; responses are unsigned without it
+    #[serde(default)]
+    pub key_file: Option<String>,
+    /// Identifier sent with each signature (default: the hex-encoded public key)
+    #[serde(default)]
+    pub key_id: Option<String>,
+}
+
+/// Fee estimator configuration
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EstimatorConfig {
+    /// Confidence levels estimated for each target, ascending
+    /// (default: 0.05, 0.20, 0.50, 0.80, 0.95)
+    #[serde(default)]
+    pub probabilities: Option<Vec<f64>>,
+    /// Block targets estimated, ascending and at least 3 (default: 3, 6, 9, 12, 18, 24, 36, 48,
+    /// 72, 96, 144)
+    #[serde(default)]
+    pub block_targets: Option<Vec<f64>>,
+    /// Rounding applied to every published fee rate: exact, ceil_tenth or ceil_integer
+    /// (default: exact)
+    #[serde(default)]
+    pub rounding: RoundingPolicy,
+    /// Exclude snapshots whose modified z-score against their neighbours exceeds this
+    /// (default: disabled)
+    #[serde(default)]
+    pub outlier_threshold: Option<f64>,
+    /// Highest fee rate published in sat/vB; higher estimates are clamped and flagged
+    /// (default: uncapped)
+    #[serde(default)]
+    pub max_fee_rate: Option<f64>,
+    /// Smallest relative step between the fee rates of adjacent confidence levels, such as
+    /// 0.02 for 2%; lower ones are raised and flagged (default: none)
+    #[serde(default)]
+    pub min_spread: Option<f64>,
+    /// Sample this many block arrival sequences per target instead of mining the expected
+    /// number of blocks (default: disabled)
+    #[serde(default)]
+    pub monte_carlo_samples: Option<usize>,
+    /// Seed of the sampled block arrival sequences (default: 0)
+    #[serde(default)]
+    pub monte_carlo_seed: u64,
+    /// Also estimate 1 and 2 block targets, flagged as high variance (default: false)
+    #[serde(default)]
+    pub fast_targets: bool,
+    /// Weeks of stored snapshots the seasonal inflow profile is learned from
+    /// (default: disabled)
+    #[serde(default)]
+    pub seasonality_weeks: Option<u32>,
+    /// Fraction of the seasonal adjustment applied to long-term inflows (default: 0.5)
+    #[serde(default = "default_seasonality_blend")]
+    pub seasonality_blend: f64,
+    /// Inflow windows for ranges of block targets; other targets use 30 minutes and 24 hours
+    #[serde(default)]
+    pub target_windows: Vec<TargetWindowConfig>,
+}
+
+/// Short- and long-term inflow windows for a range of block targets
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TargetWindowConfig {
+    /// Smallest block target the windows apply to
+    pub min_target: u32,
+    /// Largest block target the windows apply to
+    pub max_target: u32,
+    /// Short-term inflow window in minutes
+    pub short_term_minutes: u32,
+    /// Long-term inflow window in hours
+    pub long_term_hours: u32,
+}
+
+fn default_seasonality_blend() -> f64 {
+    0.5
+}
+
+impl Default for EstimatorConfig {
+    fn default() -> Self {
+        Self {
+            probabilities: None,
+            block_targets: None,
+            rounding: RoundingPolicy::default(),
+            outlier_threshold: None,
+            max_fee_rate: None,
+            min_spread: None,
+            monte_carlo_samples: None,
+            monte_carlo_seed: 0,
+            fast_targets: false,
+            seasonality_weeks: None,
+            seasonality_blend: default_seasonality_blend(),
+            target_windows: Vec::new(),
+        }
+    }
+}
+
+/// `valid_until` metadata in fee responses
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ValidityConfig {
+    /// Include `valid_until` times in fee responses (default: true)
+    pub enabled: bool,
+    /// Fraction of a block target's expected confirmation time its estimate stays valid
+    /// (default: 0.1)
+    pub target_fraction: f64,
+    /// Longest time any estimate stays valid, in seconds (default: 3600)
+    pub max_ttl_secs: u64,
+}
+
+impl Default for ValidityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            target_fraction: 0.1,
+            max_ttl_secs: 3600,
+        }
+    }
+}
+
+/// Block target and confidence level a named fee preset stands for
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct PresetConfig {
+    /// Block target in blocks
+    pub target: f64,
+    /// Confidence level between 0 and 1
+    pub confidence: f64,
+}
+
+/// Presets served at `GET /fees/preset/{name}` unless configured otherwise
+fn default_presets() -> BTreeMap<String, PresetConfig> {
+    [
+        ("fastest", 3.0, 0.95),
+        ("half_hour", 3.0, 0.8),
+        ("hour", 6.0, 0.8),
+        ("economy", 144.0, 0.5),
+    ]
+    .into_iter()
+    .map(|(name, target, confidence)| (name.to_string(), PresetConfig { target, confidence }))
+    .collect()
+}
+
+/// HTTP API configuration
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ApiConfig {
+    /// Enable the test-only `POST /test/snapshots` injection endpoint
+    #[serde(default)]
+    pub test_api_enabled: bool,
+    /// Bearer token required by the test-only endpoints
+    #[serde(default)]
+    pub test_api_token: Option<String>,
+    /// Bearer token required by `POST /snapshots`; the endpoint is disabled without it
+    #[serde(default)]
+    pub ingest_token: Option<String>,
+    /// Bearer token required by the `/admin` endpoints, which are disabled without it, and by
+    /// historical fee exports longer than the public limits
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Bearer token required by `GET /sync/changes`; the endpoint is disabled without it
+    #[serde(default)]
+    pub sync_token: Option<String>,
+    /// Enable `GET /debug/simulation`, exposing the simulation behind an estimate
+    #[serde(default)]
+    pub debug_api_enabled: bool,
+    /// Casing of response field names when the request does not ask for one
+    #[serde(default)]
+    pub field_naming: FieldNaming,
+    /// Naive strategies served next to the augur estimate at `GET /fees/compare`: `top-vmb`
+    /// and `core` (default: none, the endpoint is disabled)
+    #[serde(default)]
+    pub baselines: Vec<Baseline>,
+    /// Base URL of the mempool.space instance read by the `mempool-space` baseline
+    #[serde(default)]
+    pub mempool_space_url: Option<String>,
+    /// Weight of each strategy in the ensemble served at `GET /fees/compare`, keyed by
+    /// `augur` or a baseline name (default: none, no ensemble)
+    #[serde(default)]
+    pub ensemble_weights: BTreeMap<String, f64>,
+    /// Named (target, confidence) pairs served at `GET /fees/preset/{name}`, replacing the
+    /// defaults `fastest`, `half_hour`, `hour` and `economy` when set
+    #[serde(default = "default_presets")]
+    pub presets: BTreeMap<String, PresetConfig>,
+    /// URL of the bitcoin price feed behind `?fiat=` on the fee endpoints, such as
+    /// mempool.space's `/api/v1/prices` (default: none, fiat costs are disabled)
+    #[serde(default)]
+    pub price_feed_url: Option<String>,
+    /// Seconds fetched prices are reused for (default: 300)
+    #[serde(default = "default_price_cache_secs")]
+    pub price_cache_secs: u64,
+}
+
+fn default_price_cache_secs() -> u64 {
+    300
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            test_api_enabled: false,
+            test_api_token: None,
+            ingest_token: None,
+            admin_token: None,
+            sync_token: None,
+            debug_api_enabled: false,
+            field_naming: FieldNaming::default(),
+            baselines: Vec::new(),
+            mempool_space_url: None,
+            ensemble_weights: BTreeMap::new(),
+            presets: default_presets(),
+            price_feed_url: None,
+            price_cache_secs: default_price_cache_secs(),
+        }
+    }
 }
 
 impl AppConfig {
-    /// Load configuration from file and CLI arguments
-    pub fn load_with_cli(cli: &Cli) -> Result<Self, ConfigError> {
+    /// Load configuration from file, `AUGUR_*` environment variables and CLI arguments,
+    /// reporting every problem with it at once
+    pub fn load_with_cli(cli: &Cli) -> Result<Self, ConfigErrors> {
+        Self::load_with_env(cli, std::env::vars())
+    }
+
+    /// Load configuration from file, the `AUGUR_*` variables among `env` and CLI arguments
+    pub fn load_with_env(
+        cli: &Cli,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigErrors> {
         let mut builder = Config::builder()
             // Default values (these will be overridden by CLI defaults and args)
             .set_default("server.host", "0.0.0.0")?
@@ -111,27 +641,216 @@ impl AppConfig {
             .set_default("test_mode.enabled", false)?
             .set_default("test_mode.use_mock_data", false)?;
 
-        // Load from config file if specified via CLI
-        if let Some(ref config_file) = cli.config {
-            builder = builder.add_source(File::from(Path::new(config_file)));
-        } else {
-            // Try to load from default config locations (optional)
-            builder = builder
-                .add_source(File::with_name("augur.toml").required(false))
-                .add_source(File::with_name("augur.yaml").required(false))
-                .add_source(File::with_name("augur.json").required(false));
+        // Load from the config file specified via CLI, or the default ones that exist
+        let files = config_files(cli);
+        for file in &files {
+            builder = builder.add_source(File::from(file.as_path()));
+        }
+
+        // Environment variables override files
+        let env = EnvSettings::read(env);
+        for (key, _, value) in &env.values {
+            builder = builder.set_override(key, value.clone())?;
         }
 
         // Apply CLI overrides (highest priority)
-        builder = builder
-            .set_override("server.host", cli.host.clone())?
-            .set_override("server.port", cli.port)?
-            .set_override("bitcoin_rpc.url", cli.rpc_url.clone())?
-            .set_override("persistence.data_directory", cli.data_dir.clone())?
-            .set_override("persistence.cleanup_days", cli.cleanup_days)?
-            .set_override("collector.interval_ms", cli.interval_secs * 1000)?
-            .set_override("test_mode.enabled", cli.test_mode)?
-            .set_override("test_mode.use_mock_data", cli.use_mock_data)?;
+        if let Some(ref host) = cli.host {
+            builder = builder.set_override("server.host", host.clone())?;
+        }
+        if let Some(port) = cli.port {
+            builder = builder.set_override("server.port", port)?;
+        }
+        if !cli.listen.is_empty() {
+            builder = builder.set_override("server.listen", cli.listen.clone())?;
+        }
+        if let Some(ref path) = cli.ready_file {
+            builder = builder.set_override("server.ready_file", path.clone())?;
+        }
+        if let Some(ref ready_when) = cli.ready_when {
+            builder = builder.set_override("server.ready_when", ready_when.clone())?;
+        }
+        if let Some(ref rpc_url) = cli.rpc_url {
+            builder = builder.set_override("bitcoin_rpc.url", rpc_url.clone())?;
+        }
+        if let Some(ref data_dir) = cli.data_dir {
+            builder = builder.set_override("persistence.data_directory", data_dir.clone())?;
+        }
+        if let Some(cleanup_days) = cli.cleanup_days {
+            builder = builder.set_override("persistence.cleanup_days", cleanup_days)?;
+        }
+        if let Some(interval_secs) = cli.interval_secs {
+            builder = builder.set_override("collector.interval_ms", interval_secs * 1000)?;
+        }
+        if cli.test_mode {
+            builder = builder.set_override("test_mode.enabled", true)?;
+        }
+        if cli.use_mock_data {
+            builder = builder.set_override("test_mode.use_mock_data", true)?;
+        }
+
+        if let Some(ref proxy) = cli.rpc_proxy {
+            builder = builder.set_override("bitcoin_rpc.proxy", proxy.clone())?;
+        }
+        if cli.allow_full_rpc {
+            builder = builder.set_override("bitcoin_rpc.allow_full_rpc", true)?;
+        }
+        if let Some(ref scenario) = cli.mock_scenario {
+            builder = builder.set_override("test_mode.scenario_file", scenario.clone())?;
+        }
+        if cli.enable_test_api {
+            builder = builder.set_override("api.test_api_enabled", true)?;
+        }
+        if let Some(ref token) = cli.test_api_token {
+            builder = builder.set_override("api.test_api_token", token.clone())?;
+        }
+        if cli.enable_debug_api {
+            builder = builder.set_override("api.debug_api_enabled", true)?;
+        }
+        if let Some(ref naming) = cli.field_naming {
+            builder = builder.set_override("api.field_naming", naming.clone())?;
+        }
+        if !cli.baselines.is_empty() {
+            builder = builder.set_override("api.baselines", cli.baselines.clone())?;
+        }
+        if let Some(ref url) = cli.mempool_space_url {
+            builder = builder.set_override("api.mempool_space_url", url.clone())?;
+        }
+        if let Some(ref url) = cli.price_feed_url {
+            builder = builder.set_override("api.price_feed_url", url.clone())?;
+        }
+        if let Some(places) = cli.decimal_places {
+            builder = builder.set_override("response.decimal_places", places)?;
+        }
+        for (name, weight) in &cli.ensemble_weights {
+            builder = builder.set_override(format!("api.ensemble_weights.{name}"), *weight)?;
+        }
+        if let Some(ref token) = cli.ingest_token {
+            builder = builder.set_override("api.ingest_token", token.clone())?;
+        }
+        if let Some(ref token) = cli.admin_token {
+            builder = builder.set_override("api.admin_token", token.clone())?;
+        }
+        if let Some(ref token) = cli.sync_token {
+            builder = builder.set_override("api.sync_token", token.clone())?;
+        }
+        if cli.dedup_snapshots {
+            builder = builder.set_override("persistence.dedup_snapshots", true)?;
+        }
+        if cli.backup_before_migration {
+            builder = builder.set_override("persistence.backup_before_migration", true)?;
+        }
+        if let Some(ref bucket) = cli.s3_bucket {
+            builder = builder.set_override("persistence.s3_bucket", bucket.clone())?;
+        }
+        if let Some(ref prefix) = cli.s3_prefix {
+            builder = builder.set_override("persistence.s3_prefix", prefix.clone())?;
+        }
+        if let Some(ref endpoint) = cli.s3_endpoint {
+            builder = builder.set_override("persistence.s3_endpoint", endpoint.clone())?;
+        }
+        if let Some(ref region) = cli.s3_region {
+            builder = builder.set_override("persistence.s3_region", region.clone())?;
+        }
+        if cli.ingest_only {
+            builder = builder.set_override("collector.ingest_only", true)?;
+        }
+        if let Some(min_interval_secs) = cli.min_interval_secs {
+            builder =
+                builder.set_override("collector.min_interval_ms", min_interval_secs * 1000)?;
+        }
+        if let Some(max_interval_secs) = cli.max_interval_secs {
+            builder =
+                builder.set_override("collector.max_interval_ms", max_interval_secs * 1000)?;
+        }
+        if let Some(max_gap_secs) = cli.max_gap_secs {
+            builder = builder.set_override("collector.max_gap_secs", max_gap_secs)?;
+        }
+        if cli.backfill_gaps {
+            builder = builder.set_override("collector.backfill_gaps", true)?;
+        }
+        if let Some(max_staleness_secs) = cli.max_staleness_secs {
+            builder = builder.set_override("collector.max_staleness_secs", max_staleness_secs)?;
+        }
+        if let Some(target_cache_size) = cli.target_cache_size {
+            builder =
+                builder.set_override("collector.target_cache_size", target_cache_size as u64)?;
+        }
+        if let Some(max_targets_per_minute) = cli.max_targets_per_minute {
+            builder = builder.set_override(
+                "collector.max_targets_per_minute",
+                max_targets_per_minute as u64,
+            )?;
+        }
+        if let Some(ref record_trace) = cli.record_trace {
+            builder = builder.set_override("collector.record_trace", record_trace.clone())?;
+        }
+        if let Some(leader_lease_secs) = cli.leader_lease_secs {
+            builder = builder.set_override("collector.leader_lease_secs", leader_lease_secs)?;
+        }
+        if let Some(ref instance_id) = cli.instance_id {
+            builder = builder.set_override("collector.instance_id", instance_id.clone())?;
+        }
+        if cli.block_feedback {
+            builder = builder.set_override("collector.block_feedback", true)?;
+        }
+        if cli.package_limits {
+            builder = builder.set_override("collector.package_limits", true)?;
+        }
+        if let Some(ref key_file) = cli.signing_key_file {
+            builder = builder.set_override("signing.key_file", key_file.clone())?;
+        }
+        if let Some(ref key_id) = cli.signing_key_id {
+            builder = builder.set_override("signing.key_id", key_id.clone())?;
+        }
+        if let Some(ref rounding) = cli.fee_rounding {
+            builder = builder.set_override("estimator.rounding", rounding.clone())?;
+        }
+        if let Some(threshold) = cli.outlier_threshold {
+            builder = builder.set_override("estimator.outlier_threshold", threshold)?;
+        }
+        if let Some(max_fee_rate) = cli.max_fee_rate {
+            builder = builder.set_override("estimator.max_fee_rate", max_fee_rate)?;
+        }
+        if let Some(min_spread) = cli.min_spread {
+            builder = builder.set_override("estimator.min_spread", min_spread)?;
+        }
+        if let Some(samples) = cli.monte_carlo_samples {
+            builder = builder.set_override("estimator.monte_carlo_samples", samples as u64)?;
+        }
+        if let Some(seed) = cli.monte_carlo_seed {
+            builder = builder.set_override("estimator.monte_carlo_seed", seed)?;
+        }
+        if cli.fast_targets {
+            builder = builder.set_override("estimator.fast_targets", true)?;
+        }
+        if let Some(weeks) = cli.seasonality_weeks {
+            builder = builder.set_override("estimator.seasonality_weeks", weeks)?;
+        }
+        if let Some(blend) = cli.seasonality_blend {
+            builder = builder.set_override("estimator.seasonality_blend", blend)?;
+        }
+        if cli.no_valid_until {
+            builder = builder.set_override("validity.enabled", false)?;
+        }
+        if let Some(fraction) = cli.valid_until_fraction {
+            builder = builder.set_override("validity.target_fraction", fraction)?;
+        }
+        if let Some(max_secs) = cli.valid_until_max_secs {
+            builder = builder.set_override("validity.max_ttl_secs", max_secs)?;
+        }
+        if let Some(step_secs) = cli.simulated_clock_step_secs {
+            builder = builder.set_override("test_mode.simulated_clock_step_secs", step_secs)?;
+        }
+        if let Some(ref start) = cli.simulated_clock_start {
+            builder = builder.set_override("test_mode.simulated_clock_start", start.clone())?;
+        }
+
+        if let Some(ref path) = cli.rpc_password_file {
+            builder = builder.set_override("bitcoin_rpc.password_file", path.clone())?;
+        }
+        if let Some(ref command) = cli.rpc_password_cmd {
+            builder = builder.set_override("bitcoin_rpc.password_cmd", command.clone())?;
+        }
 
         // Handle Bitcoin RPC credentials
         if let Some(ref cookie_file) = cli.rpc_cookie_file {
@@ -149,7 +868,10 @@ impl AppConfig {
             if let Some(ref password) = cli.rpc_password {
                 builder = builder.set_override("bitcoin_rpc.password", password.clone())?;
             }
-        } else {
+        } else if env.source_of("bitcoin_rpc.username").is_none()
+            && env.source_of("bitcoin_rpc.password").is_none()
+            && !has_password_source(&builder.build_cloned()?)
+        {
             // Try to use default Bitcoin cookie file if no credentials provided
             let default_cookie = dirs::home_dir()
                 .map(|home| home.join(".bitcoin").join(".cookie"))
@@ -169,7 +891,31 @@ impl AppConfig {
             }
         }
 
-        builder.build()?.try_deserialize()
+        validate::load(&builder.build()?, &files, &env)
+    }
+
+    /// This configuration with passwords and tokens replaced by a placeholder, for printing
+    pub fn redacted(&self) -> Self {
+        let redact = |secret: &mut String| {
+            if !secret.is_empty() {
+                *secret = REDACTED.to_string();
+            }
+        };
+        let mut config = self.clone();
+        redact(&mut config.bitcoin_rpc.password);
+        redact(&mut config.persistence.s3_secret_access_key);
+        for token in [
+            &mut config.api.test_api_token,
+            &mut config.api.ingest_token,
+            &mut config.api.admin_token,
+            &mut config.api.sync_token,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            redact(token);
+        }
+        config
     }
 
     /// Load configuration (deprecated - for backwards compatibility only)
@@ -209,6 +955,26 @@ impl AppConfig {
     }
 }
 
+/// Whether `config` reads the RPC password from a file or command, which the default cookie
+/// file must not override
+fn has_password_source(config: &Config) -> bool {
+    ["bitcoin_rpc.password_file", "bitcoin_rpc.password_cmd"]
+        .iter()
+        .any(|key| config.get_string(key).is_ok_and(|value| !value.is_empty()))
+}
+
+/// The configuration file given with `--config`, or the default ones that exist
+fn config_files(cli: &Cli) -> Vec<PathBuf> {
+    match cli.config {
+        Some(ref config_file) => vec![PathBuf::from(config_file)],
+        None => DEFAULT_CONFIG_FILES
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,6 +986,8 @@ mod tests {
         assert_eq!(config.server.port, 8080);
         assert_eq!(config.bitcoin_rpc.url, "http://localhost:8332");
         assert_eq!(config.persistence.data_directory, "mempool_data");
+        assert!(!config.persistence.dedup_snapshots);
+        assert!(!config.persistence.backup_before_migration);
         assert_eq!(config.collector.interval_ms, 30000);
     }
 
@@ -242,6 +1010,8 @@ mod tests {
             "/tmp/test",
             "--interval-secs",
             "60",
+            "--dedup-snapshots",
+            "--backup-before-migration",
         ])
         .unwrap();
 
@@ -251,9 +1021,740 @@ mod tests {
         assert_eq!(config.bitcoin_rpc.username, "testuser");
         assert_eq!(config.bitcoin_rpc.password, "testpass");
         assert_eq!(config.persistence.data_directory, "/tmp/test");
+        assert!(config.persistence.dedup_snapshots);
+        assert!(config.persistence.backup_before_migration);
         assert_eq!(config.collector.interval_ms, 60000);
     }
 
+    #[test]
+    fn test_rpc_connection_settings() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.bitcoin_rpc.proxy, None);
+        assert!(!config.bitcoin_rpc.allow_full_rpc);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--rpc-proxy",
+            "socks5h://127.0.0.1:9050",
+            "--allow-full-rpc",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.bitcoin_rpc.allow_full_rpc);
+        assert_eq!(
+            config.bitcoin_rpc.proxy.as_deref(),
+            Some("socks5h://127.0.0.1:9050")
+        );
+    }
+
+    #[test]
+    fn test_fee_rounding() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.rounding, RoundingPolicy::Exact);
+
+        let cli =
+            Cli::try_parse_from(["bitcoin-augur-server", "--fee-rounding", "ceil_tenth"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.rounding, RoundingPolicy::CeilTenth);
+
+        assert!(Cli::try_parse_from(["bitcoin-augur-server", "--fee-rounding", "floor"]).is_err());
+    }
+
+    #[test]
+    fn test_outlier_threshold() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.outlier_threshold, None);
+
+        let cli =
+            Cli::try_parse_from(["bitcoin-augur-server", "--outlier-threshold", "4.5"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.outlier_threshold, Some(4.5));
+    }
+
+    #[test]
+    fn test_max_fee_rate() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.max_fee_rate, None);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--max-fee-rate", "500"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.max_fee_rate, Some(500.0));
+    }
+
+    #[test]
+    fn test_min_spread() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.min_spread, None);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--min-spread", "0.02"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.min_spread, Some(0.02));
+    }
+
+    #[test]
+    fn test_monte_carlo() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.monte_carlo_samples, None);
+        assert_eq!(config.estimator.monte_carlo_seed, 0);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--monte-carlo-samples",
+            "1000",
+            "--monte-carlo-seed",
+            "7",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.monte_carlo_samples, Some(1000));
+        assert_eq!(config.estimator.monte_carlo_seed, 7);
+
+        // A seed alone has nothing to seed
+        assert!(Cli::try_parse_from(["bitcoin-augur-server", "--monte-carlo-seed", "7"]).is_err());
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--monte-carlo-samples", "1000000"])
+            .unwrap();
+        assert!(AppConfig::load_with_cli(&cli).is_err());
+    }
+
+    #[test]
+    fn test_fast_targets() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(!config.estimator.fast_targets);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--fast-targets"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.estimator.fast_targets);
+    }
+
+    #[test]
+    fn test_debug_api() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(!config.api.debug_api_enabled);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--enable-debug-api"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.api.debug_api_enabled);
+    }
+
+    #[test]
+    fn test_field_naming() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.api.field_naming, FieldNaming::SnakeCase);
+
+        let cli =
+            Cli::try_parse_from(["bitcoin-augur-server", "--field-naming", "camel_case"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.api.field_naming, FieldNaming::CamelCase);
+
+        assert!(Cli::try_parse_from(["bitcoin-augur-server", "--field-naming", "kebab"]).is_err());
+    }
+
+    #[test]
+    fn test_decimal_places() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.response.decimal_places, 4);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--decimal-places", "2"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.response.decimal_places, 2);
+
+        assert!(Cli::try_parse_from(["bitcoin-augur-server", "--decimal-places", "16"]).is_err());
+    }
+
+    #[test]
+    fn test_baselines() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.api.baselines.is_empty());
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--baseline",
+            "top-vmb",
+            "--baseline",
+            "core",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.api.baselines, vec![Baseline::TopVmb, Baseline::Core]);
+        assert!(config.api.ensemble_weights.is_empty());
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--baseline",
+            "mempool-space",
+            "--mempool-space-url",
+            "http://localhost:8999",
+            "--ensemble-weight",
+            "augur=2",
+            "--ensemble-weight",
+            "mempool-space=0.5",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.api.baselines, vec![Baseline::MempoolSpace]);
+        assert_eq!(
+            config.api.mempool_space_url.as_deref(),
+            Some("http://localhost:8999")
+        );
+        assert_eq!(
+            config.api.ensemble_weights,
+            BTreeMap::from([
+                ("augur".to_string(), 2.0),
+                ("mempool-space".to_string(), 0.5)
+            ])
+        );
+
+        assert!(Cli::try_parse_from(["bitcoin-augur-server", "--baseline", "median"]).is_err());
+        assert!(
+            Cli::try_parse_from(["bitcoin-augur-server", "--ensemble-weight", "augur"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_interval_bounds() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.min_interval_ms, None);
+        assert_eq!(config.collector.max_interval_ms, None);
+        assert_eq!(config.collector.volatility_threshold, 0.5);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--min-interval-secs",
+            "5",
+            "--max-interval-secs",
+            "120",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.min_interval_ms, Some(5000));
+        assert_eq!(config.collector.max_interval_ms, Some(120_000));
+    }
+
+    #[test]
+    fn test_gap_settings() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.max_gap_secs, 600);
+        assert!(!config.collector.backfill_gaps);
+        assert_eq!(config.collector.max_staleness_secs, 0);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--max-gap-secs",
+            "0",
+            "--backfill-gaps",
+            "--max-staleness-secs",
+            "300",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.max_gap_secs, 0);
+        assert!(config.collector.backfill_gaps);
+        assert_eq!(config.collector.max_staleness_secs, 300);
+    }
+
+    #[test]
+    fn test_target_cache_settings() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.target_cache_size, 64);
+        assert_eq!(config.collector.max_targets_per_minute, 0);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--target-cache-size",
+            "0",
+            "--max-targets-per-minute",
+            "20",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.target_cache_size, 0);
+        assert_eq!(config.collector.max_targets_per_minute, 20);
+    }
+
+    #[test]
+    fn test_s3_settings() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.persistence.s3_bucket, None);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--s3-bucket",
+            "augur-snapshots",
+            "--s3-prefix",
+            "mainnet",
+            "--s3-endpoint",
+            "http://localhost:9000",
+            "--s3-region",
+            "eu-west-1",
+        ])
+        .unwrap();
+        let mut config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(
+            config.persistence.s3_bucket.as_deref(),
+            Some("augur-snapshots")
+        );
+        assert_eq!(config.persistence.s3_prefix, "mainnet");
+        assert_eq!(
+            config.persistence.s3_endpoint.as_deref(),
+            Some("http://localhost:9000")
+        );
+        assert_eq!(config.persistence.s3_region.as_deref(), Some("eu-west-1"));
+
+        // The secret key never reaches the logs or the printed configuration
+        config.persistence.s3_secret_access_key = "s3cret".to_string();
+        assert!(!format!("{:?}", config.persistence).contains("s3cret"));
+        assert!(!toml::to_string(&config.redacted())
+            .unwrap()
+            .contains("s3cret"));
+    }
+
+    #[test]
+    fn test_record_trace() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.record_trace, None);
+
+        let cli =
+            Cli::try_parse_from(["bitcoin-augur-server", "--record-trace", "trace.jsonl"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(
+            config.collector.record_trace.as_deref(),
+            Some("trace.jsonl")
+        );
+    }
+
+    #[test]
+    fn test_leader_lease() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.leader_lease_secs, 0);
+        assert_eq!(config.collector.instance_id, None);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--leader-lease-secs",
+            "90",
+            "--instance-id",
+            "augur-a",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.collector.leader_lease_secs, 90);
+        assert_eq!(config.collector.instance_id.as_deref(), Some("augur-a"));
+    }
+
+    #[test]
+    fn test_block_feedback() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(!config.collector.block_feedback);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--block-feedback"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.collector.block_feedback);
+    }
+
+    #[test]
+    fn test_package_limits() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(!config.collector.package_limits);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--package-limits"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.collector.package_limits);
+    }
+
+    #[test]
+    fn test_target_windows() {
+        use clap::Parser;
+        use std::io::Write;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.estimator.target_windows.is_empty());
+
+        let mut file = tempfile::Builder::new().suffix(".yaml").tempfile().unwrap();
+        write!(
+            file,
+            "estimator:\n  target_windows:\n    - {{ min_target: 3, max_target: 6, \
+             short_term_minutes: 15, long_term_hours: 6 }}\n"
+        )
+        .unwrap();
+        let path = file.path().to_str().unwrap();
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--config", path]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(
+            config.estimator.target_windows,
+            vec![TargetWindowConfig {
+                min_target: 3,
+                max_target: 6,
+                short_term_minutes: 15,
+                long_term_hours: 6,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_environment_variables() {
+        use clap::Parser;
+        use std::io::Write;
+
+        let env = |vars: &[(&str, &str)]| {
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_env(
+            &cli,
+            env(&[
+                ("AUGUR_SERVER_PORT", "9000"),
+                ("AUGUR_RPC_URL", "http://node:8332"),
+                ("AUGUR_RPC_USERNAME", "augur"),
+                ("AUGUR_COLLECTOR_INTERVAL_MS", "10000"),
+                ("AUGUR_TEST_MODE_ENABLED", "true"),
+                ("AUGUR_ESTIMATOR_MAX_FEE_RATE", "500.5"),
+                ("AUGUR_API_BASELINES", "top-vmb,core"),
+                (
+                    "AUGUR_ESTIMATOR_TARGET_WINDOWS",
+                    r#"[{"min_target": 3, "max_target": 6, "short_term_minutes": 15,
+                        "long_term_hours": 6}]"#,
+                ),
+                (
+                    "AUGUR_API_PRESETS",
+                    r#"{"weekend": {"target": 288, "confidence": 0.5}}"#,
+                ),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.bitcoin_rpc.url, "http://node:8332");
+        assert_eq!(config.bitcoin_rpc.username, "augur");
+        assert_eq!(config.collector.interval_ms, 10000);
+        assert!(config.test_mode.enabled);
+        assert_eq!(config.estimator.max_fee_rate, Some(500.5));
+        assert_eq!(config.api.baselines, vec![Baseline::TopVmb, Baseline::Core]);
+        assert_eq!(config.estimator.target_windows[0].short_term_minutes, 15);
+        assert_eq!(config.api.presets["weekend"].target, 288.0);
+
+        // Files are overridden by the environment, which is overridden by arguments
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(file, "[server]\nport = 7000\nhost = \"127.0.0.1\"").unwrap();
+        let path = file.path().to_str().unwrap();
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--config", path]).unwrap();
+        let config = AppConfig::load_with_env(&cli, env(&[("AUGUR_SERVER_PORT", "9000")])).unwrap();
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.port, 9000);
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--config", path, "--port", "9100"])
+            .unwrap();
+        let config = AppConfig::load_with_env(&cli, env(&[("AUGUR_SERVER_PORT", "9000")])).unwrap();
+        assert_eq!(config.server.port, 9100);
+
+        // Problems name the variable they came from
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let errors = AppConfig::load_with_env(
+            &cli,
+            env(&[
+                ("AUGUR_SERVER_PORT", "eighty"),
+                ("AUGUR_RESPONSE_DECIMAL_PLACES", "20"),
+            ]),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(errors.contains("server.port: invalid type"));
+        assert!(errors.contains("(set by AUGUR_SERVER_PORT)"));
+        assert!(errors.contains("(set by AUGUR_RESPONSE_DECIMAL_PLACES)"));
+    }
+
+    #[test]
+    fn test_listen_addresses() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_env(&cli, Vec::new()).unwrap();
+        assert_eq!(config.server.bind_addresses(), ["0.0.0.0:8080"]);
+
+        let cli =
+            Cli::try_parse_from(["bitcoin-augur-server", "--host", "::", "--port", "0"]).unwrap();
+        let config = AppConfig::load_with_env(&cli, Vec::new()).unwrap();
+        assert_eq!(config.server.bind_addresses(), ["[::]:0"]);
+
+        let env = vec![(
+            "AUGUR_SERVER_LISTEN".to_string(),
+            "127.0.0.1:8080, [::1]:8080".to_string(),
+        )];
+        let config = AppConfig::load_with_env(&cli, env).unwrap();
+        assert_eq!(
+            config.server.bind_addresses(),
+            ["127.0.0.1:8080", "[::1]:8080"]
+        );
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--listen",
+            "127.0.0.1:0",
+            "--listen",
+            "[::1]:0",
+            "--ready-file",
+            "/run/augur.ready",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_env(&cli, Vec::new()).unwrap();
+        assert_eq!(config.server.bind_addresses(), ["127.0.0.1:0", "[::1]:0"]);
+        assert_eq!(
+            config.server.ready_file.as_deref(),
+            Some("/run/augur.ready")
+        );
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--listen", "::1:8080"]).unwrap();
+        let errors = AppConfig::load_with_env(&cli, Vec::new()).unwrap_err();
+        assert_eq!(errors.0[0].key, "server.listen[0]");
+    }
+
+    #[test]
+    fn test_rpc_password_sources() {
+        use clap::Parser;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let secret = temp_dir.path().join("rpc_password");
+        std::fs::write(&secret, "s3cret\n").unwrap();
+        let secret = secret.to_str().unwrap();
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--rpc-username", "augur"]).unwrap();
+        let env = vec![("AUGUR_RPC_PASSWORD_FILE".to_string(), secret.to_string())];
+        let config = AppConfig::load_with_env(&cli, env).unwrap();
+        assert_eq!(config.bitcoin_rpc.username, "augur");
+        assert_eq!(config.bitcoin_rpc.password, "s3cret");
+        assert!(!format!("{config:?}").contains("s3cret"));
+        assert!(!format!("{:?}", config.to_bitcoin_rpc_config()).contains("s3cret"));
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--rpc-password-cmd",
+            "echo from-command",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_env(&cli, Vec::new()).unwrap();
+        assert_eq!(config.bitcoin_rpc.password, "from-command");
+
+        // Only one source of the password may be configured
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--rpc-password",
+            "plain",
+            "--rpc-password-file",
+            secret,
+            "--rpc-password-cmd",
+            "echo from-command",
+        ])
+        .unwrap();
+        let errors = AppConfig::load_with_env(&cli, Vec::new()).unwrap_err();
+        let keys: Vec<&str> = errors.0.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(keys, ["bitcoin_rpc.password_cmd", "bitcoin_rpc.password"]);
+        assert!(!errors.to_string().contains("plain"));
+    }
+
+    #[test]
+    fn test_alert_rules() {
+        use crate::service::{AlertCondition, AlertMetric, AlertSeverity};
+        use clap::Parser;
+        use std::io::Write;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.alerts.rules.is_empty());
+        assert_eq!(config.alerts.webhook_url, None);
+
+        let mut file = tempfile::Builder::new().suffix(".yaml").tempfile().unwrap();
+        write!(
+            file,
+            "alerts:\n  webhook_url: http://localhost:9000/hook\n  rules:\n    - {{ name: high_fees, \
+             metric: \"fee_rate:6:0.95\", condition: above, threshold: 200, for_secs: 1800, \
+             severity: critical }}\n    - {{ name: empty, metric: mempool_vsize, condition: \
+             below, threshold: 1000000 }}\n"
+        )
+        .unwrap();
+        let path = file.path().to_str().unwrap();
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--config", path]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(
+            config.alerts.webhook_url.as_deref(),
+            Some("http://localhost:9000/hook")
+        );
+        assert_eq!(
+            config.alerts.rules,
+            vec![
+                AlertRule {
+                    name: "high_fees".to_string(),
+                    metric: AlertMetric::FeeRate {
+                        blocks: 6,
+                        probability: 0.95
+                    },
+                    condition: AlertCondition::Above,
+                    threshold: 200.0,
+                    for_secs: 1800,
+                    severity: AlertSeverity::Critical,
+                },
+                AlertRule {
+                    name: "empty".to_string(),
+                    metric: AlertMetric::MempoolVsize,
+                    condition: AlertCondition::Below,
+                    threshold: 1_000_000.0,
+                    for_secs: 0,
+                    severity: AlertSeverity::Warning,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        use clap::Parser;
+        use std::io::Write;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.api.presets, default_presets());
+        assert_eq!(
+            config.api.presets["economy"],
+            PresetConfig {
+                target: 144.0,
+                confidence: 0.5
+            }
+        );
+
+        // Configured presets replace the defaults
+        let mut file = tempfile::Builder::new().suffix(".yaml").tempfile().unwrap();
+        write!(
+            file,
+            "api:\n  presets:\n    checkout: {{ target: 2, confidence: 0.9 }}\n"
+        )
+        .unwrap();
+        let path = file.path().to_str().unwrap();
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--config", path]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(
+            config.api.presets,
+            BTreeMap::from([(
+                "checkout".to_string(),
+                PresetConfig {
+                    target: 2.0,
+                    confidence: 0.9
+                }
+            )])
+        );
+    }
+
+    #[test]
+    fn test_seasonality_options() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.seasonality_weeks, None);
+        assert_eq!(config.estimator.seasonality_blend, 0.5);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--seasonality-weeks",
+            "4",
+            "--seasonality-blend",
+            "0.8",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.estimator.seasonality_weeks, Some(4));
+        assert_eq!(config.estimator.seasonality_blend, 0.8);
+
+        // A blend means nothing without history to learn from
+        assert!(
+            Cli::try_parse_from(["bitcoin-augur-server", "--seasonality-blend", "0.8"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_validity_options() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(config.validity.enabled);
+        assert_eq!(config.validity.target_fraction, 0.1);
+        assert_eq!(config.validity.max_ttl_secs, 3600);
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-augur-server",
+            "--valid-until-fraction",
+            "0.25",
+            "--valid-until-max-secs",
+            "600",
+        ])
+        .unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert_eq!(config.validity.target_fraction, 0.25);
+        assert_eq!(config.validity.max_ttl_secs, 600);
+
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--no-valid-until"]).unwrap();
+        let config = AppConfig::load_with_cli(&cli).unwrap();
+        assert!(!config.validity.enabled);
+    }
+
     #[test]
     fn test_load_returns_default() {
         // load() should now return default config without environment access
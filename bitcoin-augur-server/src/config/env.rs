@@ -0,0 +1,230 @@
+//! Settings from `AUGUR_*` environment variables, so containers need no configuration file
+//!
+//! Every setting `section.key` is read from `AUGUR_{SECTION}_{KEY}` in upper case, such as
+//! `AUGUR_SERVER_PORT` or `AUGUR_ESTIMATOR_ROUNDING`. The `bitcoin_rpc` section is shortened to
+//! `RPC` (`AUGUR_RPC_URL`), and `AUGUR_BITCOIN_RPC_*` is accepted too. List settings take
+//! comma-separated values or a JSON array, and table settings a JSON object.
+
+use config::{Map, ValueKind};
+use serde_json::Value;
+
+use super::validate::{edit_distance, ConfigIssue};
+use super::AppConfig;
+
+/// Prefix of every environment variable read
+pub const ENV_PREFIX: &str = "AUGUR_";
+
+/// Sections whose environment variables are not prefixed by their own name
+const SECTION_PREFIXES: [(&str, &[&str]); 1] = [("bitcoin_rpc", &["RPC", "BITCOIN_RPC"])];
+
+/// A setting and an environment variable it can be read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvVar {
+    /// Name of the variable, such as `AUGUR_SERVER_PORT`
+    pub name: String,
+    /// Dotted path of the setting, such as `server.port`
+    pub key: String,
+    /// Whether the setting is a list, which may be given as comma-separated values
+    pub list: bool,
+}
+
+/// Every environment variable a setting can be read from, section by section
+pub fn env_vars() -> Vec<EnvVar> {
+    let Ok(Value::Object(sections)) = serde_json::to_value(AppConfig::default()) else {
+        return Vec::new();
+    };
+    let mut vars = Vec::new();
+    for (section, fields) in &sections {
+        let Value::Object(fields) = fields else {
+            continue;
+        };
+        let prefixes = SECTION_PREFIXES
+            .iter()
+            .find(|(name, _)| name == section)
+            .map_or_else(|| vec![section.as_str()], |(_, prefixes)| prefixes.to_vec());
+        for prefix in prefixes {
+            for (field, default) in fields {
+                vars.push(EnvVar {
+                    name: format!("{ENV_PREFIX}{prefix}_{field}").to_uppercase(),
+                    key: format!("{section}.{field}"),
+                    list: default.is_array(),
+                });
+            }
+        }
+    }
+    vars
+}
+
+/// Settings read from the environment
+#[derive(Debug, Default)]
+pub(super) struct EnvSettings {
+    /// Setting, the variable it was read from and its value
+    pub values: Vec<(String, String, config::Value)>,
+    /// `AUGUR_*` variables that name no setting or hold malformed values
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl EnvSettings {
+    /// Reads every `AUGUR_*` variable among `env`
+    pub fn read(env: impl IntoIterator<Item = (String, String)>) -> Self {
+        let vars = env_vars();
+        let mut settings = Self::default();
+        let mut env: Vec<(String, String)> = env
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        env.sort();
+        for (name, raw) in env {
+            let Some(var) = vars.iter().find(|var| var.name == name) else {
+                let hint = vars
+                    .iter()
+                    .map(|var| (edit_distance(&name, &var.name), &var.name))
+                    .filter(|(distance, _)| *distance <= 2)
+                    .min()
+                    .map(|(_, candidate)| format!(", did you mean `{candidate}`?"))
+                    .unwrap_or_default();
+                settings.issues.push(ConfigIssue::new(
+                    name,
+                    format!("unknown environment variable{hint}"),
+                ));
+                continue;
+            };
+            match parse(var, &raw) {
+                Ok(value) => settings.values.push((var.key.clone(), name, value)),
+                Err(message) => settings.issues.push(ConfigIssue::new(name, message)),
+            }
+        }
+        settings
+    }
+
+    /// Variable the setting `key`, or the setting containing it, was read from
+    pub fn source_of(&self, key: &str) -> Option<&str> {
+        self.values.iter().find_map(|(setting, name, _)| {
+            let inside = key
+                .strip_prefix(setting.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']));
+            inside.then_some(name.as_str())
+        })
+    }
+}
+
+/// The value of `var`, left as a string for the configuration to convert unless it is a list
+/// or JSON
+fn parse(var: &EnvVar, raw: &str) -> Result<config::Value, String> {
+    let origin = Some(&var.name);
+    let trimmed = raw.trim();
+    if trimmed.starts_with(['[', '{']) {
+        let json = serde_json::from_str(trimmed).map_err(|e| format!("invalid JSON: {e}"))?;
+        return Ok(from_json(json, origin));
+    }
+    if var.list {
+        let items = trimmed
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| config::Value::new(origin, item))
+            .collect::<Vec<_>>();
+        return Ok(config::Value::new(origin, ValueKind::Array(items)));
+    }
+    Ok(config::Value::new(origin, raw))
+}
+
+fn from_json(json: Value, origin: Option<&String>) -> config::Value {
+    let kind = match json {
+        Value::Null => ValueKind::Nil,
+        Value::Bool(value) => ValueKind::Boolean(value),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => ValueKind::I64(value),
+            None => number.as_u64().map_or_else(
+                || ValueKind::Float(number.as_f64().unwrap_or(f64::NAN)),
+                ValueKind::U64,
+            ),
+        },
+        Value::String(value) => ValueKind::String(value),
+        Value::Array(values) => ValueKind::Array(
+            values
+                .into_iter()
+                .map(|value| from_json(value, origin))
+                .collect(),
+        ),
+        Value::Object(map) => ValueKind::Table(
+            map.into_iter()
+                .map(|(key, value)| (key, from_json(value, origin)))
+                .collect::<Map<_, _>>(),
+        ),
+    };
+    config::Value::new(origin, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_every_setting_has_a_variable() {
+        let vars = env_vars();
+        let names: Vec<&str> = vars.iter().map(|var| var.name.as_str()).collect();
+        for name in [
+            "AUGUR_SERVER_PORT",
+            "AUGUR_RPC_URL",
+            "AUGUR_BITCOIN_RPC_PASSWORD",
+            "AUGUR_PERSISTENCE_DATA_DIRECTORY",
+            "AUGUR_COLLECTOR_INTERVAL_MS",
+            "AUGUR_TEST_MODE_ENABLED",
+            "AUGUR_API_PRESETS",
+            "AUGUR_ESTIMATOR_TARGET_WINDOWS",
+            "AUGUR_ALERTS_RULES",
+            "AUGUR_RESPONSE_DECIMAL_PLACES",
+        ] {
+            assert!(names.contains(&name), "{name} is missing");
+        }
+        assert!(vars
+            .iter()
+            .any(|var| var.name == "AUGUR_API_BASELINES" && var.list));
+    }
+
+    #[test]
+    fn test_read_reports_unknown_and_malformed_variables() {
+        let settings = EnvSettings::read(env(&[
+            ("AUGUR_SERVER_PROT", "9000"),
+            ("AUGUR_ESTIMATOR_TARGET_WINDOWS", "[{"),
+            ("AUGUR_API_BASELINES", "top-vmb, core"),
+            ("PATH", "/usr/bin"),
+        ]));
+        assert_eq!(settings.values.len(), 1);
+        let (key, name, value) = &settings.values[0];
+        assert_eq!(key, "api.baselines");
+        assert_eq!(name, "AUGUR_API_BASELINES");
+        assert_eq!(
+            value.clone().into_array().unwrap().len(),
+            2,
+            "comma-separated values are a list"
+        );
+
+        let issues: Vec<(&str, &str)> = settings
+            .issues
+            .iter()
+            .map(|issue| (issue.key.as_str(), issue.message.as_str()))
+            .collect();
+        assert_eq!(issues[0].0, "AUGUR_ESTIMATOR_TARGET_WINDOWS");
+        assert!(issues[0].1.starts_with("invalid JSON"));
+        assert_eq!(
+            issues[1],
+            (
+                "AUGUR_SERVER_PROT",
+                "unknown environment variable, did you mean `AUGUR_SERVER_PORT`?"
+            )
+        );
+        assert_eq!(
+            settings.source_of("api.baselines[1]"),
+            Some("AUGUR_API_BASELINES")
+        );
+        assert_eq!(settings.source_of("api.baselines_extra"), None);
+    }
+}
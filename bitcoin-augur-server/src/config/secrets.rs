@@ -0,0 +1,126 @@
+//! RPC passwords read from a file or printed by a command, so they never need to be written
+//! into the configuration or the environment
+//!
+//! `bitcoin_rpc.password_file` suits Docker and Kubernetes secrets mounted as files, and
+//! `bitcoin_rpc.password_cmd` a password manager such as `pass show bitcoin/rpc`. Either
+//! replaces `bitcoin_rpc.password` once the configuration is loaded.
+
+use std::fs;
+use std::process::Command;
+
+use super::validate::ConfigIssue;
+use super::BitcoinRpcConfig;
+
+/// Fills in `rpc.password` from its file or command, if one is configured
+pub(super) fn resolve_password(rpc: &mut BitcoinRpcConfig) -> Result<(), ConfigIssue> {
+    if let Some(ref path) = rpc.password_file {
+        rpc.password = read_password_file(path)
+            .map_err(|message| ConfigIssue::new("bitcoin_rpc.password_file", message))?;
+    } else if let Some(ref command) = rpc.password_cmd {
+        rpc.password = run_password_cmd(command)
+            .map_err(|message| ConfigIssue::new("bitcoin_rpc.password_cmd", message))?;
+    }
+    Ok(())
+}
+
+/// The password stored in the file at `path`, without its trailing newline
+fn read_password_file(path: &str) -> Result<String, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("failed to read {path:?}: {e}"))?;
+    let password = trim_newline(&contents);
+    if password.is_empty() {
+        return Err(format!("{path:?} is empty"));
+    }
+    Ok(password.to_string())
+}
+
+/// The password printed by `command`, run through `sh -c`, without its trailing newline
+///
+/// The command's output is never included in errors, since it may hold the password.
+fn run_password_cmd(command: &str) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| format!("failed to run: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "command failed with {status}",
+            status = output.status
+        ));
+    }
+    let stdout =
+        String::from_utf8(output.stdout).map_err(|_| "printed invalid UTF-8".to_string())?;
+    let password = trim_newline(&stdout);
+    if password.is_empty() {
+        return Err("command printed no password".to_string());
+    }
+    Ok(password.to_string())
+}
+
+/// `text` without its trailing line break, keeping any other whitespace, which may be part of
+/// the password
+fn trim_newline(text: &str) -> &str {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    text.strip_suffix('\r').unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_password_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("rpc_password");
+        std::fs::write(&path, " s3cret \n").unwrap();
+
+        let mut rpc = BitcoinRpcConfig {
+            password_file: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        resolve_password(&mut rpc).unwrap();
+        assert_eq!(
+            rpc.password, " s3cret ",
+            "only the trailing newline is dropped"
+        );
+
+        std::fs::write(&path, "\n").unwrap();
+        let issue = resolve_password(&mut rpc).unwrap_err();
+        assert_eq!(issue.key, "bitcoin_rpc.password_file");
+        assert!(issue.message.ends_with("is empty"));
+
+        rpc.password_file = Some(
+            temp_dir
+                .path()
+                .join("missing")
+                .to_string_lossy()
+                .to_string(),
+        );
+        let issue = resolve_password(&mut rpc).unwrap_err();
+        assert!(issue.message.starts_with("failed to read"));
+    }
+
+    #[test]
+    fn test_password_cmd() {
+        let mut rpc = BitcoinRpcConfig {
+            password_cmd: Some("printf 's3cret\\r\\n'".to_string()),
+            ..Default::default()
+        };
+        resolve_password(&mut rpc).unwrap();
+        assert_eq!(rpc.password, "s3cret");
+
+        rpc.password_cmd = Some("echo s3cret; exit 3".to_string());
+        let issue = resolve_password(&mut rpc).unwrap_err();
+        assert_eq!(issue.key, "bitcoin_rpc.password_cmd");
+        assert!(issue.message.contains("exit status: 3"));
+        assert!(
+            !issue.message.contains("s3cret"),
+            "the output may be the password"
+        );
+
+        rpc.password_cmd = Some("true".to_string());
+        let issue = resolve_password(&mut rpc).unwrap_err();
+        assert_eq!(issue.message, "command printed no password");
+    }
+}
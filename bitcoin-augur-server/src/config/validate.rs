@@ -0,0 +1,782 @@
+//! Schema validation of the configuration, reporting every problem at once
+//!
+//! The merged configuration is checked in three passes: keys in the configuration files that no
+//! setting reads, values of the wrong type, and values out of range. Each section is
+//! deserialized on its own, so a mistake in one does not hide mistakes in the others. Problems
+//! with keys set in a configuration file name the file, and the line for TOML files.
+
+use bitcoin_augur::{BlockTarget, MonteCarlo};
+use config::{Config, ConfigError};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use super::env::EnvSettings;
+use super::secrets;
+use super::{AppConfig, ResponseConfig};
+
+/// Settings whose keys are names chosen by the user rather than fields
+const OPEN_TABLES: [&str; 2] = ["api.ensemble_weights", "api.presets"];
+
+/// One problem with the configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// Dotted path of the offending key, such as `collector.interval_ms`
+    pub key: String,
+    pub message: String,
+    /// Configuration file the key was set in
+    pub file: Option<PathBuf>,
+    /// Line of `file` the key was set on, for TOML files
+    pub line: Option<usize>,
+}
+
+impl ConfigIssue {
+    pub(super) fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+            file: None,
+            line: None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: ", file.display(), line)?,
+            (Some(file), None) => write!(f, "{}: ", file.display())?,
+            _ => {}
+        }
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+/// Every problem found while loading the configuration
+#[derive(Debug, Error)]
+pub struct ConfigErrors(pub Vec<ConfigIssue>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [issue] = self.0.as_slice() {
+            return write!(f, "{}", issue);
+        }
+        write!(f, "{} configuration errors:", self.0.len())?;
+        for issue in &self.0 {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ConfigError> for ConfigErrors {
+    fn from(e: ConfigError) -> Self {
+        let key = match e {
+            ConfigError::Type { ref key, .. } => key.clone().unwrap_or_default(),
+            _ => String::new(),
+        };
+        Self(vec![ConfigIssue::new(key, message(e))])
+    }
+}
+
+/// Error message of `e` without the key and origin, which issues carry on their own
+fn message(e: ConfigError) -> String {
+    match e {
+        ConfigError::Type {
+            unexpected,
+            expected,
+            ..
+        } => format!("invalid type: {unexpected}, expected {expected}"),
+        e => e.to_string(),
+    }
+}
+
+/// Checks the merged `config`, read from `files` and `env` among other sources, and
+/// deserializes it
+pub(super) fn load(
+    config: &Config,
+    files: &[PathBuf],
+    env: &EnvSettings,
+) -> Result<AppConfig, ConfigErrors> {
+    let mut issues = env.issues.clone();
+    for file in files {
+        issues.extend(unknown_keys(file)?);
+    }
+
+    let mut failed = Vec::new();
+    let mut check = Sections {
+        config,
+        issues: &mut issues,
+        failed: &mut failed,
+    };
+    let mut app = AppConfig {
+        server: check.section("server"),
+        bitcoin_rpc: check.section("bitcoin_rpc"),
+        persistence: check.section("persistence"),
+        collector: check.section("collector"),
+        test_mode: check.section("test_mode"),
+        api: check.section("api"),
+        signing: check.section("signing"),
+        estimator: check.section("estimator"),
+        validity: check.section("validity"),
+        alerts: check.section("alerts"),
+        response: check.section("response"),
+    };
+    // Sections that failed to deserialize hold defaults, whose ranges say nothing
+    issues.extend(range_issues(&app).into_iter().filter(|issue| {
+        let section = issue.key.split('.').next().unwrap_or_default();
+        !failed.contains(&section)
+    }));
+
+    // Only a configuration otherwise valid runs its password command
+    if issues.is_empty() {
+        match secrets::resolve_password(&mut app.bitcoin_rpc) {
+            Ok(()) => return Ok(app),
+            Err(issue) => issues.push(issue),
+        }
+    }
+    locate(&mut issues, files, env);
+    Err(ConfigErrors(issues))
+}
+
+/// Deserializes the sections of a configuration one by one, recording the ones that fail
+struct Sections<'a> {
+    config: &'a Config,
+    issues: &'a mut Vec<ConfigIssue>,
+    failed: &'a mut Vec<&'static str>,
+}
+
+impl Sections<'_> {
+    /// The section `name`, or its default when it is missing or fails to deserialize
+    fn section<T: DeserializeOwned + Default>(&mut self, name: &'static str) -> T {
+        let value = match self.config.get::<config::Value>(name) {
+            Ok(value) => value,
+            Err(ConfigError::NotFound(_)) => return T::default(),
+            Err(e) => {
+                self.issues.push(ConfigIssue::new(name, message(e)));
+                self.failed.push(name);
+                return T::default();
+            }
+        };
+        match serde_path_to_error::deserialize(value) {
+            Ok(section) => section,
+            Err(e) => {
+                let key = match e.path().to_string().as_str() {
+                    "." => name.to_string(),
+                    path => format!("{name}.{path}"),
+                };
+                self.issues
+                    .push(ConfigIssue::new(key, message(e.into_inner())));
+                self.failed.push(name);
+                T::default()
+            }
+        }
+    }
+}
+
+/// Keys set in `file` that no setting reads, with the closest known key as a hint
+fn unknown_keys(file: &Path) -> Result<Vec<ConfigIssue>, ConfigErrors> {
+    let contents: Value = Config::builder()
+        .add_source(config::File::from(file))
+        .build()?
+        .try_deserialize()?;
+    let schema = serde_json::to_value(AppConfig::default()).unwrap_or_default();
+    let mut issues = Vec::new();
+    walk_unknown(&contents, &schema, "", &mut issues);
+    for issue in &mut issues {
+        issue.file = Some(file.to_path_buf());
+    }
+    Ok(issues)
+}
+
+fn walk_unknown(value: &Value, schema: &Value, prefix: &str, issues: &mut Vec<ConfigIssue>) {
+    let (Value::Object(map), Value::Object(known)) = (value, schema) else {
+        return;
+    };
+    for (key, value) in map {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match known.get(key) {
+            Some(_) if OPEN_TABLES.contains(&path.as_str()) => {}
+            Some(schema) => walk_unknown(value, schema, &path, issues),
+            None => {
+                let hint = known
+                    .keys()
+                    .map(|candidate| (edit_distance(key, candidate), candidate))
+                    .filter(|(distance, _)| *distance <= 2.max(key.len() / 3))
+                    .min()
+                    .map(|(_, candidate)| format!(", did you mean `{candidate}`?"))
+                    .unwrap_or_default();
+                issues.push(ConfigIssue::new(path, format!("unknown key{hint}")));
+            }
+        }
+    }
+}
+
+/// Levenshtein distance between `a` and `b`
+pub(super) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Whether `address` is a host and port to listen on, with an IPv6 host in brackets
+fn is_bind_address(address: &str) -> bool {
+    let Some((host, port)) = address.rsplit_once(':') else {
+        return false;
+    };
+    let host_ok = match host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+    {
+        Some(ipv6) => ipv6.parse::<std::net::Ipv6Addr>().is_ok(),
+        None => !host.is_empty() && !host.contains([':', '[', ']']),
+    };
+    host_ok && port.parse::<u16>().is_ok()
+}
+
+/// Values that deserialize but make no sense, such as a collection interval of zero
+fn range_issues(config: &AppConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let mut check = |ok: bool, key: &str, message: String| {
+        if !ok {
+            issues.push(ConfigIssue::new(key, message));
+        }
+    };
+
+    for (index, address) in config.server.listen.iter().enumerate() {
+        check(
+            is_bind_address(address),
+            &format!("server.listen[{index}]"),
+            format!("`{address}` is not host:port, such as 127.0.0.1:8080 or [::1]:8080"),
+        );
+    }
+
+    let rpc = &config.bitcoin_rpc;
+    if rpc.password_file.is_some() {
+        check(
+            rpc.password_cmd.is_none(),
+            "bitcoin_rpc.password_cmd",
+            "must not be set together with bitcoin_rpc.password_file".to_string(),
+        );
+    }
+    if rpc.password_file.is_some() || rpc.password_cmd.is_some() {
+        check(
+            rpc.password.is_empty(),
+            "bitcoin_rpc.password",
+            "must not be set together with bitcoin_rpc.password_file or password_cmd".to_string(),
+        );
+    }
+
+    let persistence = &config.persistence;
+    check(
+        !persistence.data_directory.is_empty(),
+        "persistence.data_directory",
+        "must not be empty".to_string(),
+    );
+    check(
+        persistence.cleanup_days > 0,
+        "persistence.cleanup_days",
+        format!(
+            "must be at least 1, got {days}",
+            days = persistence.cleanup_days
+        ),
+    );
+    if let Some(bucket) = &persistence.s3_bucket {
+        check(
+            !bucket.is_empty(),
+            "persistence.s3_bucket",
+            "must not be empty".to_string(),
+        );
+    }
+
+    let collector = &config.collector;
+    check(
+        collector.interval_ms > 0,
+        "collector.interval_ms",
+        "must be positive".to_string(),
+    );
+    let min_ms = collector.min_interval_ms.unwrap_or(collector.interval_ms);
+    let max_ms = collector.max_interval_ms.unwrap_or(collector.interval_ms);
+    check(
+        collector.min_interval_ms != Some(0),
+        "collector.min_interval_ms",
+        "must be positive".to_string(),
+    );
+    check(
+        min_ms <= max_ms,
+        "collector.min_interval_ms",
+        format!("({min_ms}) must not exceed collector.max_interval_ms ({max_ms})"),
+    );
+    // The lease is renewed once per cycle, so a shorter one would lapse between renewals
+    check(
+        collector.leader_lease_secs == 0 || collector.leader_lease_secs * 1000 > max_ms,
+        "collector.leader_lease_secs",
+        format!(
+            "({lease}) must be longer than the longest collection interval ({max_ms} ms)",
+            lease = collector.leader_lease_secs
+        ),
+    );
+    check(
+        collector
+            .instance_id
+            .as_deref()
+            .is_none_or(|instance| !instance.trim().is_empty()),
+        "collector.instance_id",
+        "must not be empty".to_string(),
+    );
+    check(
+        collector.volatility_threshold.is_finite() && collector.volatility_threshold > 0.0,
+        "collector.volatility_threshold",
+        format!(
+            "must be positive, got {threshold}",
+            threshold = collector.volatility_threshold
+        ),
+    );
+    check(
+        collector
+            .source
+            .as_deref()
+            .is_none_or(|source| !source.trim().is_empty()),
+        "collector.source",
+        "must not be empty".to_string(),
+    );
+
+    let estimator = &config.estimator;
+    if let Some(probabilities) = &estimator.probabilities {
+        check(
+            !probabilities.is_empty(),
+            "estimator.probabilities",
+            "must not be empty".to_string(),
+        );
+        for &probability in probabilities {
+            check(
+                probability > 0.0 && probability <= 1.0,
+                "estimator.probabilities",
+                format!("must be above 0 and at most 1, got {probability}"),
+            );
+        }
+        check(
+            probabilities.windows(2).all(|pair| pair[0] < pair[1]),
+            "estimator.probabilities",
+            "must be strictly ascending".to_string(),
+        );
+    }
+    if let Some(block_targets) = &estimator.block_targets {
+        let min_target = f64::from(BlockTarget::MIN_SIMULATED_BLOCKS);
+        check(
+            !block_targets.is_empty(),
+            "estimator.block_targets",
+            "must not be empty".to_string(),
+        );
+        for &target in block_targets {
+            check(
+                target.is_finite() && target >= min_target,
+                "estimator.block_targets",
+                format!("must be at least {min_target}, got {target}"),
+            );
+        }
+        check(
+            block_targets.windows(2).all(|pair| pair[0] < pair[1]),
+            "estimator.block_targets",
+            "must be strictly ascending".to_string(),
+        );
+    }
+    if let Some(threshold) = estimator.outlier_threshold {
+        check(
+            threshold.is_finite() && threshold > 0.0,
+            "estimator.outlier_threshold",
+            format!("must be positive, got {threshold}"),
+        );
+    }
+    if let Some(max_fee_rate) = estimator.max_fee_rate {
+        check(
+            max_fee_rate.is_finite() && max_fee_rate > 0.0,
+            "estimator.max_fee_rate",
+            format!("must be positive, got {max_fee_rate}"),
+        );
+    }
+    if let Some(min_spread) = estimator.min_spread {
+        check(
+            min_spread > 0.0 && min_spread <= 1.0,
+            "estimator.min_spread",
+            format!("must be above 0 and at most 1, got {min_spread}"),
+        );
+    }
+    if let Some(samples) = estimator.monte_carlo_samples {
+        check(
+            (1..=MonteCarlo::MAX_SAMPLES).contains(&samples),
+            "estimator.monte_carlo_samples",
+            format!(
+                "must be from 1 to {max}, got {samples}",
+                max = MonteCarlo::MAX_SAMPLES
+            ),
+        );
+    }
+    check(
+        estimator.seasonality_weeks != Some(0),
+        "estimator.seasonality_weeks",
+        "must be at least 1".to_string(),
+    );
+    check(
+        (0.0..=1.0).contains(&estimator.seasonality_blend),
+        "estimator.seasonality_blend",
+        format!(
+            "must be between 0 and 1, got {blend}",
+            blend = estimator.seasonality_blend
+        ),
+    );
+    for (index, windows) in estimator.target_windows.iter().enumerate() {
+        check(
+            windows.min_target <= windows.max_target,
+            &format!("estimator.target_windows[{index}].min_target"),
+            format!(
+                "({min}) must not exceed max_target ({max})",
+                min = windows.min_target,
+                max = windows.max_target
+            ),
+        );
+    }
+
+    let fraction = config.validity.target_fraction;
+    check(
+        !config.validity.enabled || (fraction.is_finite() && fraction > 0.0),
+        "validity.target_fraction",
+        format!("must be positive, got {fraction}"),
+    );
+
+    for (name, preset) in &config.api.presets {
+        check(
+            preset.target.is_finite() && preset.target > 0.0,
+            &format!("api.presets.{name}.target"),
+            format!("must be positive, got {target}", target = preset.target),
+        );
+        check(
+            (0.0..=1.0).contains(&preset.confidence),
+            &format!("api.presets.{name}.confidence"),
+            format!(
+                "must be between 0 and 1, got {confidence}",
+                confidence = preset.confidence
+            ),
+        );
+    }
+
+    for (index, rule) in config.alerts.rules.iter().enumerate() {
+        check(
+            rule.threshold.is_finite(),
+            &format!("alerts.rules[{index}].threshold"),
+            format!(
+                "must be finite, got {threshold}",
+                threshold = rule.threshold
+            ),
+        );
+    }
+
+    check(
+        config.response.decimal_places <= ResponseConfig::MAX_DECIMAL_PLACES,
+        "response.decimal_places",
+        format!(
+            "must be at most {max}, got {places}",
+            max = ResponseConfig::MAX_DECIMAL_PLACES,
+            places = config.response.decimal_places
+        ),
+    );
+
+    issues
+}
+
+/// Points each issue at the environment variable setting its key, or else at the file and line
+/// setting its key or section
+///
+/// An issue without a file is blamed on the last of `files` setting the key, since later files
+/// take precedence.
+fn locate(issues: &mut [ConfigIssue], files: &[PathBuf], env: &EnvSettings) {
+    let sources: Vec<(&PathBuf, Source)> = files
+        .iter()
+        .map(|file| (file, Source::read(file)))
+        .collect();
+    for issue in issues.iter_mut() {
+        if let Some(name) = env.source_of(&issue.key) {
+            issue.message = format!("{message} (set by {name})", message = issue.message);
+            continue;
+        }
+        let section = issue.key.split(['.', '[']).next().unwrap_or_default();
+        let candidates = sources
+            .iter()
+            .rev()
+            .filter(|(file, _)| issue.file.as_ref().is_none_or(|blamed| blamed == *file));
+        for (file, source) in candidates {
+            let found = match source {
+                Source::Toml(lines) => lines
+                    .get(&issue.key)
+                    .or_else(|| lines.get(section))
+                    .map(|line| Some(*line)),
+                Source::Other(contents) => contents.get(section).map(|_| None),
+            };
+            if let Some(line) = found {
+                issue.file = Some(file.to_path_buf());
+                issue.line = line;
+                break;
+            }
+        }
+    }
+}
+
+/// What a configuration file says about where its keys are
+enum Source {
+    /// Line of every key, for TOML files
+    Toml(HashMap<String, usize>),
+    /// Contents of other files, which only tell which sections they set
+    Other(Value),
+}
+
+impl Source {
+    fn read(file: &Path) -> Self {
+        if file
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            let text = std::fs::read_to_string(file).unwrap_or_default();
+            return Self::Toml(toml_key_lines(&text));
+        }
+        let contents = Config::builder()
+            .add_source(config::File::from(file))
+            .build()
+            .and_then(|contents| contents.try_deserialize())
+            .unwrap_or_default();
+        Self::Other(contents)
+    }
+}
+
+/// Line of every key in a TOML document, by dotted path with `[index]` for array items
+fn toml_key_lines(text: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    if let Ok(document) = toml_edit::ImDocument::parse(text) {
+        walk_toml(text, document.as_table(), "", &mut lines);
+    }
+    lines
+}
+
+fn walk_toml(
+    text: &str,
+    table: &dyn toml_edit::TableLike,
+    prefix: &str,
+    lines: &mut HashMap<String, usize>,
+) {
+    let line_of = |span: Option<std::ops::Range<usize>>| {
+        span.and_then(|span| text.get(..span.start))
+            .map(|before| before.matches('\n').count() + 1)
+    };
+    for (key, item) in table.iter() {
+        let path = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        let key_line = table
+            .get_key_value(key)
+            .and_then(|(key, _)| line_of(key.span()));
+        if let Some(line) = key_line.or_else(|| line_of(item.span())) {
+            lines.entry(path.clone()).or_insert(line);
+        }
+        match item {
+            toml_edit::Item::ArrayOfTables(tables) => {
+                for (index, table) in tables.iter().enumerate() {
+                    let path = format!("{path}[{index}]");
+                    if let Some(line) = line_of(table.span()) {
+                        lines.insert(path.clone(), line);
+                    }
+                    walk_toml(text, table, &path, lines);
+                }
+            }
+            toml_edit::Item::Value(toml_edit::Value::Array(values)) => {
+                for (index, value) in values.iter().enumerate() {
+                    let path = format!("{path}[{index}]");
+                    if let Some(line) = line_of(value.span()) {
+                        lines.insert(path.clone(), line);
+                    }
+                    if let toml_edit::Value::InlineTable(table) = value {
+                        walk_toml(text, table, &path, lines);
+                    }
+                }
+            }
+            item => {
+                if let Some(table) = item.as_table_like() {
+                    walk_toml(text, table, &path, lines);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+    use tempfile::TempDir;
+
+    fn load_file(name: &str, contents: &str) -> (TempDir, Result<AppConfig, ConfigErrors>) {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        let cli = Cli::try_parse_from(["bitcoin-augur-server", "--config", path.to_str().unwrap()])
+            .unwrap();
+        (temp_dir, AppConfig::load_with_cli(&cli))
+    }
+
+    #[test]
+    fn test_reports_every_error_with_lines() {
+        let (_temp_dir, result) = load_file(
+            "augur.toml",
+            r#"[server]
+host = "127.0.0.1"
+prot = 9000
+
+[collector]
+max_gap_secs = "often"
+
+[validity]
+target_fraction = -1.0
+
+[estimator]
+seasonality_blend = 2.0
+"#,
+        );
+        let issues = result.unwrap_err().0;
+        let found: Vec<(&str, Option<usize>)> = issues
+            .iter()
+            .map(|issue| (issue.key.as_str(), issue.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("server.prot", Some(3)),
+                ("collector.max_gap_secs", Some(6)),
+                ("estimator.seasonality_blend", Some(12)),
+                ("validity.target_fraction", Some(9)),
+            ]
+        );
+        assert!(issues[0].message.contains("did you mean `port`?"));
+        assert!(issues[1].message.contains("invalid type"));
+        assert!(issues[0]
+            .to_string()
+            .ends_with("augur.toml:3: server.prot: unknown key, did you mean `port`?"));
+    }
+
+    #[test]
+    fn test_valid_file_loads() {
+        let (_temp_dir, result) = load_file(
+            "augur.toml",
+            r#"[api.presets.weekend]
+target = 288.0
+confidence = 0.5
+
+[api.ensemble_weights]
+augur = 1.0
+
+[[alerts.rules]]
+name = "high_fees"
+metric = "fee_rate:6:0.95"
+condition = "above"
+threshold = 200.0
+"#,
+        );
+        let config = result.unwrap();
+        assert_eq!(config.api.presets["weekend"].target, 288.0);
+        assert_eq!(config.alerts.rules.len(), 1);
+    }
+
+    #[test]
+    fn test_nested_keys_in_arrays() {
+        let (_temp_dir, result) = load_file(
+            "augur.toml",
+            r#"[[alerts.rules]]
+name = "high_fees"
+metric = "fee_rate:6:0.95"
+condition = "sideways"
+threshold = 200.0
+"#,
+        );
+        let issues = result.unwrap_err().0;
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "alerts.rules[0].condition");
+        assert_eq!(issues[0].line, Some(4));
+    }
+
+    #[test]
+    fn test_yaml_errors_name_the_file() {
+        let (_temp_dir, result) = load_file("augur.yaml", "validity:\n  target_fraction: 0.0\n");
+        let issues = result.unwrap_err().0;
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "validity.target_fraction");
+        assert!(issues[0].file.as_ref().unwrap().ends_with("augur.yaml"));
+        assert_eq!(issues[0].line, None);
+    }
+
+    #[test]
+    fn test_estimator_levels_and_targets() {
+        let (_temp_dir, result) = load_file(
+            "augur.toml",
+            "[estimator]\nprobabilities = [0.1, 0.5, 0.9]\nblock_targets = [3.0, 10.0, 50.0]\n",
+        );
+        let config = result.unwrap();
+        assert_eq!(config.estimator.probabilities, Some(vec![0.1, 0.5, 0.9]));
+        assert_eq!(config.estimator.block_targets, Some(vec![3.0, 10.0, 50.0]));
+
+        let (_temp_dir, result) = load_file(
+            "augur.toml",
+            "[estimator]\nprobabilities = [0.5, 0.1]\nblock_targets = [2.0, 6.0]\n",
+        );
+        let issues = result.unwrap_err().0;
+        let keys: Vec<&str> = issues.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec!["estimator.probabilities", "estimator.block_targets"]
+        );
+        assert!(issues[0].message.contains("ascending"));
+        assert!(issues[1].message.contains("at least 3"));
+    }
+
+    #[test]
+    fn test_bind_addresses() {
+        for address in ["127.0.0.1:8080", "[::]:0", "[::1]:8080", "localhost:9000"] {
+            assert!(is_bind_address(address), "{address}");
+        }
+        for address in [
+            "127.0.0.1",
+            "::1:8080",
+            "[::1]",
+            ":8080",
+            "[nope]:80",
+            "host:99999",
+        ] {
+            assert!(!is_bind_address(address), "{address}");
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("prot", "port"), 2);
+        assert_eq!(edit_distance("interval", "interval_ms"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,484 @@
+//! Historical accuracy evaluation against stored snapshots
+//!
+//! Stored snapshots are replayed in order. At the first snapshot of each new block, the
+//! estimation strategy runs on the preceding estimation window, exactly as the collector would have.
+//! Each estimate is then checked against what was actually mined afterwards: the outflow
+//! between the last snapshot before a block and the first one after it shows the lowest fee
+//! rate bucket that lost weight, which is taken as the lowest fee rate that block confirmed.
+//! Blocks whose contents the collector fetched use the threshold realized from their
+//! transactions instead. A fee rate counts as confirmed within `n` blocks if any of the next `n` blocks confirmed
+//! that rate or lower.
+//!
+//! A calibrated estimator confirms within target about as often as its confidence level.
+//! Any [`EstimationStrategy`] can be evaluated, so alternative models can be compared with
+//! the default one on the same history.
+
+use anyhow::{ensure, Context, Result};
+use bitcoin_augur::{EstimationStrategy, MempoolSnapshot};
+use chrono::{DateTime, Duration, Local, Utc};
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+use crate::persistence::SnapshotStore;
+use crate::service::{estimation_window, read_realized_blocks};
+
+/// Confirmation statistics for one block target and confidence level
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalibrationCell {
+    /// Estimates whose outcome could be determined
+    pub samples: usize,
+    /// Estimates whose fee rate confirmed within the target
+    pub confirmed: usize,
+    fee_rate_sum: f64,
+}
+
+impl CalibrationCell {
+    /// Fraction of samples that confirmed within the target
+    pub fn hit_rate(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.confirmed as f64 / self.samples as f64
+        }
+    }
+
+    /// Mean estimated fee rate in sat/vB
+    pub fn mean_fee_rate(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.fee_rate_sum / self.samples as f64
+        }
+    }
+
+    /// Counts an estimate of `fee_rate`, which confirmed within the target or not
+    pub fn record(&mut self, fee_rate: f64, confirmed: bool) {
+        self.samples += 1;
+        self.fee_rate_sum += fee_rate;
+        if confirmed {
+            self.confirmed += 1;
+        }
+    }
+}
+
+/// Calibration of every confidence level and block target
+#[derive(Debug, Default)]
+pub struct EvaluationReport {
+    /// Name of the strategy evaluated
+    pub strategy: String,
+    pub snapshots: usize,
+    pub blocks_evaluated: usize,
+    /// Blocks whose threshold was realized from their transactions rather than inferred
+    pub realized_blocks: usize,
+    /// Cells keyed by confidence level (as in the API, e.g. "0.50"), then block target
+    pub calibration: BTreeMap<String, BTreeMap<u32, CalibrationCell>>,
+}
+
+/// Lowest fee rate confirmed by each block, inferred from snapshot outflow
+///
+/// Blocks found between two snapshots share the pair's outflow. Blocks without any observed
+/// outflow are recorded as confirming nothing.
+pub fn mined_fee_rates(snapshots: &[MempoolSnapshot]) -> BTreeMap<u32, f64> {
+    let mut rates = BTreeMap::new();
+
+    for pair in snapshots.windows(2) {
+        let (before, after) = (&pair[0], &pair[1]);
+        if after.block_height <= before.block_height {
+            continue;
+        }
+
+        let lowest_mined = before
+            .bucketed_weights
+            .iter()
+            .find(|(bucket, &weight)| {
+                after.bucketed_weights.get(bucket).copied().unwrap_or(0) < weight
+            })
+            .map(|(&bucket, _)| (f64::from(bucket) / 100.0).exp())
+            .unwrap_or(f64::INFINITY);
+
+        for height in before.block_height + 1..=after.block_height {
+            rates.insert(height, lowest_mined);
+        }
+    }
+
+    rates
+}
+
+/// Replays `snapshots` (sorted by time), estimating with `strategy` at every `block_step`th block
+///
+/// The lowest fee rate confirmed by each block in `realized` is taken from it rather than
+/// inferred from snapshot outflow.
+pub fn evaluate(
+    snapshots: &[MempoolSnapshot],
+    strategy: &dyn EstimationStrategy,
+    block_step: u32,
+    realized: &BTreeMap<u32, f64>,
+) -> Result<EvaluationReport> {
+    ensure!(block_step > 0, "Block step must be at least 1");
+
+    let mut mined = mined_fee_rates(snapshots);
+    let realized_blocks = mined
+        .keys()
+        .filter(|height| realized.contains_key(height))
+        .count();
+    mined.extend(realized);
+    let window = estimation_window(strategy);
+    let mut report = EvaluationReport {
+        strategy: strategy.name().to_string(),
+        snapshots: snapshots.len(),
+        realized_blocks,
+        ..Default::default()
+    };
+
+    let mut window_start = 0;
+    let mut blocks_seen = 0u32;
+    for (index, snapshot) in snapshots.iter().enumerate().skip(1) {
+        // Only the first snapshot after a new block is an estimation point
+        if snapshot.block_height <= snapshots[index - 1].block_height {
+            continue;
+        }
+        blocks_seen += 1;
+        if !(blocks_seen - 1).is_multiple_of(block_step) {
+            continue;
+        }
+
+        let cutoff = snapshot
+            .timestamp
+            .checked_sub_signed(window)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        while snapshots[window_start].timestamp < cutoff {
+            window_start += 1;
+        }
+        let estimate = strategy
+            .estimate(&snapshots[window_start..=index], None)
+            .with_context(|| {
+                format!(
+                    "Estimation failed at block {height}",
+                    height = snapshot.block_height
+                )
+            })?;
+        report.blocks_evaluated += 1;
+
+        for (target_key, block_target) in estimate.targets() {
+            let target = target_key.blocks();
+            // The outcome is only known once every block within the target was observed
+            let outcome: Option<Vec<f64>> = (1..=target)
+                .map(|offset| {
+                    let height = snapshot.block_height.checked_add(offset)?;
+                    mined.get(&height).copied()
+                })
+                .collect();
+            let Some(outcome) = outcome else {
+                continue;
+            };
+
+            for (probability, &fee_rate) in &block_target.probabilities {
+                report
+                    .calibration
+                    .entry(format!("{p:.2}", p = probability.0))
+                    .or_default()
+                    .entry(target)
+                    .or_default()
+                    .record(
+                        fee_rate,
+                        outcome.iter().any(|&mined_rate| mined_rate <= fee_rate),
+                    );
+            }
+        }
+
+        debug!(
+            "Evaluated block {height} with {count} snapshots",
+            height = snapshot.block_height,
+            count = index + 1 - window_start
+        );
+    }
+
+    Ok(report)
+}
+
+/// Loads the last `days` days of snapshots from `store` and evaluates each of `strategies` on
+/// them, reporting in the same order
+pub fn evaluate_store(
+    store: &SnapshotStore,
+    strategies: &[&dyn EstimationStrategy],
+    days: i64,
+    block_step: u32,
+) -> Result<Vec<EvaluationReport>> {
+    ensure!(days > 0, "Days must be at least 1");
+
+    let end = Local::now();
+    let start = end - Duration::days(days);
+    let mut snapshots = store
+        .get_snapshots(start, end)
+        .context("Failed to load stored snapshots")?;
+    snapshots.sort_by_key(|s| s.timestamp);
+    let realized: BTreeMap<u32, f64> = read_realized_blocks(&store.realized_blocks_path())
+        .context("Failed to load realized blocks")?
+        .iter()
+        .map(|block| (block.height, block.confirmed_fee_rate()))
+        .collect();
+
+    info!(
+        "Evaluating {count} snapshots from the last {days} days",
+        count = snapshots.len()
+    );
+    strategies
+        .iter()
+        .map(|&strategy| evaluate(&snapshots, strategy, block_step, &realized))
+        .collect()
+}
+
+impl EvaluationReport {
+    /// Prints one calibration table per confidence level
+    pub fn print(&self) {
+        println!(
+            "Evaluated {strategy} at {blocks} blocks from {snapshots} snapshots",
+            strategy = self.strategy,
+            blocks = self.blocks_evaluated,
+            snapshots = self.snapshots
+        );
+        if self.realized_blocks > 0 {
+            println!(
+                "{realized} blocks scored against the fee rates their transactions paid",
+                realized = self.realized_blocks
+            );
+        }
+        if self.calibration.is_empty() {
+            println!("Not enough history to evaluate any estimate");
+            return;
+        }
+
+        for (confidence, targets) in &self.calibration {
+            let expected = confidence.parse::<f64>().unwrap_or_default() * 100.0;
+            println!();
+            println!("Confidence {confidence} (expected confirmation rate {expected:.0}%)");
+            println!(
+                "{target:>8} {samples:>8} {confirmed:>10} {rate:>10} {fee:>14}",
+                target = "Target",
+                samples = "Samples",
+                confirmed = "Confirmed",
+                rate = "Hit rate",
+                fee = "Mean sat/vB"
+            );
+            for (target, cell) in targets {
+                println!(
+                    "{target:>8} {samples:>8} {confirmed:>10} {rate:>9.1}% {fee:>14.2}",
+                    samples = cell.samples,
+                    confirmed = cell.confirmed,
+                    rate = cell.hit_rate() * 100.0,
+                    fee = cell.mean_fee_rate()
+                );
+            }
+        }
+    }
+
+    /// Prints this report's hit rates and mean fee rates next to those of `other`, evaluated on
+    /// the same snapshots
+    pub fn print_comparison(&self, other: &EvaluationReport) {
+        println!(
+            "Comparing {strategy} with {other_strategy}",
+            strategy = self.strategy,
+            other_strategy = other.strategy
+        );
+        for (confidence, targets) in &self.calibration {
+            let Some(other_targets) = other.calibration.get(confidence) else {
+                continue;
+            };
+            let expected = confidence.parse::<f64>().unwrap_or_default() * 100.0;
+            println!();
+            println!("Confidence {confidence} (expected confirmation rate {expected:.0}%)");
+            println!(
+                "{target:>8} {rate:>10} {other_rate:>10} {fee:>12} {other_fee:>12}",
+                target = "Target",
+                rate = "Hit rate",
+                other_rate = "Other",
+                fee = "sat/vB",
+                other_fee = "Other"
+            );
+            for (target, cell) in targets {
+                let Some(other_cell) = other_targets.get(target) else {
+                    continue;
+                };
+                println!(
+                    "{target:>8} {rate:>9.1}% {other_rate:>9.1}% {fee:>12.2} {other_fee:>12.2}",
+                    rate = cell.hit_rate() * 100.0,
+                    other_rate = other_cell.hit_rate() * 100.0,
+                    fee = cell.mean_fee_rate(),
+                    other_fee = other_cell.mean_fee_rate()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::{BlockTarget, FeeEstimate, FeeEstimator, MonteCarlo, OrderedFloat};
+    use chrono::TimeZone;
+
+    fn snapshot(height: u32, minutes: i64, buckets: &[(i32, u64)]) -> MempoolSnapshot {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        MempoolSnapshot::new(
+            height,
+            start + Duration::minutes(minutes),
+            buckets.iter().copied().collect(),
+        )
+    }
+
+    #[test]
+    fn test_mined_fee_rates_from_outflow() {
+        let snapshots = vec![
+            snapshot(100, 0, &[(100, 4_000), (300, 8_000)]),
+            snapshot(100, 5, &[(100, 4_000), (300, 9_000)]),
+            // Block 101 only cleared the 300 bucket
+            snapshot(101, 10, &[(100, 4_000), (300, 1_000)]),
+            // Blocks 102 and 103 together cleared everything
+            snapshot(103, 20, &[]),
+            // Block 104 found an empty mempool
+            snapshot(104, 30, &[]),
+        ];
+
+        let rates = mined_fee_rates(&snapshots);
+        assert!((rates[&101] - 3f64.exp()).abs() < 1e-9);
+        assert!((rates[&102] - 1f64.exp()).abs() < 1e-9);
+        assert_eq!(rates[&102], rates[&103]);
+        assert!(rates[&104].is_infinite());
+        assert!(!rates.contains_key(&100));
+    }
+
+    #[test]
+    fn test_evaluate_counts_only_resolved_targets() {
+        // A steady mempool where every block clears fee rates of e^1 and above
+        let mut snapshots = Vec::new();
+        for block in 0..20u32 {
+            let minutes = i64::from(block) * 10;
+            snapshots.push(snapshot(
+                100 + block,
+                minutes,
+                &[(0, 400_000), (100, 400_000)],
+            ));
+            snapshots.push(snapshot(
+                100 + block,
+                minutes + 5,
+                &[(0, 800_000), (100, 800_000)],
+            ));
+        }
+
+        let report = evaluate(&snapshots, &FeeEstimator::new(), 1, &BTreeMap::new()).unwrap();
+        assert_eq!(report.blocks_evaluated, 19);
+
+        for targets in report.calibration.values() {
+            for (&target, cell) in targets {
+                // Estimates near the end cannot be resolved for longer targets
+                assert!(cell.samples <= 19usize.saturating_sub(target as usize - 1));
+                assert!(cell.confirmed <= cell.samples);
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_step_skips_blocks() {
+        let snapshots: Vec<_> = (0..10u32)
+            .map(|block| snapshot(100 + block, i64::from(block) * 10, &[(100, 1_000)]))
+            .collect();
+
+        let every_block = evaluate(&snapshots, &FeeEstimator::new(), 1, &BTreeMap::new()).unwrap();
+        let every_third = evaluate(&snapshots, &FeeEstimator::new(), 3, &BTreeMap::new()).unwrap();
+        assert_eq!(every_block.blocks_evaluated, 9);
+        assert_eq!(every_third.blocks_evaluated, 3);
+        assert!(evaluate(&snapshots, &FeeEstimator::new(), 0, &BTreeMap::new()).is_err());
+    }
+
+    /// Always estimates the same fee rate for 3 blocks at 50%
+    struct Constant(f64);
+
+    impl EstimationStrategy for Constant {
+        fn name(&self) -> &str {
+            "constant"
+        }
+
+        fn estimate(
+            &self,
+            snapshots: &[MempoolSnapshot],
+            _num_blocks: Option<f64>,
+        ) -> bitcoin_augur::Result<FeeEstimate> {
+            let target = BlockTarget::new(3, BTreeMap::from([(OrderedFloat(0.5), self.0)]));
+            Ok(FeeEstimate::new(
+                BTreeMap::from([(3, target)]),
+                snapshots.last().unwrap().timestamp,
+            ))
+        }
+    }
+
+    #[test]
+    fn test_evaluate_alternative_strategy() {
+        // Every block clears fee rates of e^1 (about 2.7 sat/vB) and above, leaving 1 sat/vB
+        // transactions to pile up
+        let snapshots: Vec<_> = (0..10u32)
+            .flat_map(|block| {
+                let minutes = i64::from(block) * 10;
+                let backlog = 400_000 * u64::from(block + 1);
+                [
+                    snapshot(100 + block, minutes, &[(0, backlog), (100, 400_000)]),
+                    snapshot(100 + block, minutes + 5, &[(0, backlog), (100, 800_000)]),
+                ]
+            })
+            .collect();
+
+        let high = evaluate(&snapshots, &Constant(3.0), 1, &BTreeMap::new()).unwrap();
+        let low = evaluate(&snapshots, &Constant(2.0), 1, &BTreeMap::new()).unwrap();
+        assert_eq!(high.strategy, "constant");
+        assert_eq!(high.blocks_evaluated, 9);
+        // Estimates from the last three blocks are not resolved yet
+        assert_eq!(high.calibration["0.50"][&3].samples, 6);
+        assert_eq!(high.calibration["0.50"][&3].hit_rate(), 1.0);
+        assert_eq!(low.calibration["0.50"][&3].hit_rate(), 0.0);
+        assert_eq!(low.calibration["0.50"][&3].mean_fee_rate(), 2.0);
+
+        let augur = evaluate(&snapshots, &FeeEstimator::new(), 1, &BTreeMap::new()).unwrap();
+        assert_eq!(augur.strategy, "augur");
+
+        // The Monte Carlo simulation is scored on the same blocks
+        let monte_carlo = FeeEstimator::new().with_monte_carlo(MonteCarlo::new(50, 0).unwrap());
+        let sampled = evaluate(&snapshots, &monte_carlo, 1, &BTreeMap::new()).unwrap();
+        assert_eq!(sampled.strategy, "augur-monte-carlo");
+        assert_eq!(sampled.blocks_evaluated, augur.blocks_evaluated);
+        assert_eq!(
+            sampled.calibration["0.50"][&3].samples,
+            augur.calibration["0.50"][&3].samples
+        );
+    }
+
+    #[test]
+    fn test_realized_blocks_replace_inferred_rates() {
+        let snapshots: Vec<_> = (0..10u32)
+            .flat_map(|block| {
+                let minutes = i64::from(block) * 10;
+                let backlog = 400_000 * u64::from(block + 1);
+                [
+                    snapshot(100 + block, minutes, &[(0, backlog), (100, 400_000)]),
+                    snapshot(100 + block, minutes + 5, &[(0, backlog), (100, 800_000)]),
+                ]
+            })
+            .collect();
+
+        // The blocks' own transactions show they confirmed nothing below 4 sat/vB
+        let realized: BTreeMap<u32, f64> = (101..110).map(|height| (height, 4.0)).collect();
+        let report = evaluate(&snapshots, &Constant(3.0), 1, &realized).unwrap();
+        assert_eq!(report.realized_blocks, 9);
+        assert_eq!(report.calibration["0.50"][&3].hit_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_cell_rates() {
+        let cell = CalibrationCell {
+            samples: 4,
+            confirmed: 3,
+            fee_rate_sum: 10.0,
+        };
+        assert_eq!(cell.hit_rate(), 0.75);
+        assert_eq!(cell.mean_fee_rate(), 2.5);
+        assert_eq!(CalibrationCell::default().hit_rate(), 0.0);
+    }
+}
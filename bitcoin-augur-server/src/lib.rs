@@ -3,6 +3,8 @@ pub mod api;
 pub mod bitcoin;
 pub mod cli;
 pub mod config;
+pub mod evaluation;
 pub mod persistence;
 pub mod server;
 pub mod service;
+pub mod systemd;
@@ -4,24 +4,38 @@ mod api;
 mod bitcoin;
 mod cli;
 mod config;
+mod evaluation;
 mod persistence;
 mod server;
 mod service;
+mod systemd;
 
-use anyhow::{Context, Result};
-use bitcoin_augur::FeeEstimator;
+use anyhow::{bail, ensure, Context, Result};
+use bitcoin_augur::{
+    EstimationStrategy, FeeEstimator, MonteCarlo, OutlierFilter, PackageLimits, TargetWindows,
+    TopVmbStrategy,
+};
 use clap::Parser;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
-    bitcoin::{BitcoinClient, BitcoinRpcClient, MockBitcoinClient},
-    cli::Cli,
-    config::AppConfig,
-    persistence::SnapshotStore,
-    server::{create_app, run_server},
-    service::MempoolCollector,
+    api::ResponseSigner,
+    bitcoin::{
+        BitcoinClient, BitcoinRpcClient, MockBitcoinClient, ReplayBitcoinClient, Scenario,
+        REQUIRED_RPC_METHODS,
+    },
+    cli::{Cli, Command, ConfigCommand},
+    config::{AppConfig, ReadyWhen},
+    persistence::{CrashReport, RunManifest, S3Config, S3Storage, SnapshotStore},
+    server::{create_app_with_signer, run_server, Readiness},
+    service::{
+        Alerts, Baseline, Baselines, BlockFeedback, Clock, CycleLog, GapPolicy, IntervalPolicy,
+        LeaderLease, MempoolCollector, SeasonalityPolicy, SyncClient, TargetCachePolicy,
+        TraceRecorder, ValidityPolicy,
+    },
 };
 
 #[tokio::main]
@@ -40,18 +54,148 @@ async fn main() -> Result<()> {
         )
         .init();
 
-    info!("Bitcoin Augur Server starting...");
-
     // Load configuration with CLI overrides
     let config = AppConfig::load_with_cli(&cli).context("Failed to load configuration")?;
 
+    match cli.command {
+        Some(Command::Evaluate {
+            days,
+            block_step,
+            ref strategy,
+            compare_monte_carlo,
+        }) => {
+            let store = SnapshotStore::new(&config.persistence.data_directory)
+                .context("Failed to open snapshot store")?;
+            if compare_monte_carlo {
+                // Both run the configured estimator, sampling the configured number of block
+                // arrival sequences or the default
+                let mut deterministic_config = config.clone();
+                let samples = deterministic_config
+                    .estimator
+                    .monte_carlo_samples
+                    .take()
+                    .unwrap_or(MonteCarlo::DEFAULT_SAMPLES);
+                let deterministic = build_estimator(&deterministic_config)?;
+                let sampled = deterministic
+                    .clone()
+                    .with_monte_carlo(MonteCarlo::new(samples, config.estimator.monte_carlo_seed)?);
+                let reports = evaluation::evaluate_store(
+                    &store,
+                    &[&deterministic, &sampled],
+                    days,
+                    block_step,
+                )?;
+                for report in &reports {
+                    report.print();
+                    println!();
+                }
+                reports[0].print_comparison(&reports[1]);
+                return Ok(());
+            }
+            let strategy: Box<dyn EstimationStrategy> = match strategy.as_str() {
+                "top-vmb" => Box::new(TopVmbStrategy::new()),
+                _ => Box::new(build_estimator(&config)?),
+            };
+            let reports = evaluation::evaluate_store(&store, &[&*strategy], days, block_step)?;
+            reports[0].print();
+            return Ok(());
+        }
+        Some(Command::Fsck { repair }) => {
+            let data_dir = &config.persistence.data_directory;
+            let report = persistence::fsck(Path::new(data_dir), repair)
+                .with_context(|| format!("Failed to check snapshot store {data_dir}"))?;
+            report.print();
+            let unresolved = report.unresolved();
+            if unresolved > 0 {
+                let hint = if repair { "" } else { ", rerun with --repair" };
+                bail!("{unresolved} problems left in the snapshot store{hint}");
+            }
+            return Ok(());
+        }
+        Some(Command::Pack) => {
+            let store = SnapshotStore::new(&config.persistence.data_directory)
+                .context("Failed to open snapshot store")?;
+            let today = chrono::Utc::now().date_naive();
+            let packed = store
+                .pack_before(today)
+                .context("Failed to pack snapshots")?;
+            println!("Packed {packed} date directories");
+            return Ok(());
+        }
+        Some(Command::Archive) => {
+            let store = SnapshotStore::new(&config.persistence.data_directory)
+                .context("Failed to open snapshot store")?;
+            let this_month = persistence::month_of(chrono::Utc::now().date_naive());
+            let archived = store
+                .archive_before(this_month)
+                .context("Failed to archive snapshots")?;
+            println!("Archived {archived} months");
+            return Ok(());
+        }
+        Some(Command::Config { ref command }) => {
+            match command {
+                ConfigCommand::Validate => println!("Configuration is valid"),
+                ConfigCommand::PrintEffective => print!(
+                    "{config}",
+                    config = toml::to_string_pretty(&config.redacted())
+                        .context("Failed to print configuration")?
+                ),
+            }
+            return Ok(());
+        }
+        Some(Command::Sync {
+            ref from,
+            ref token,
+            follow,
+        }) => {
+            let store = Arc::new(
+                SnapshotStore::new(&config.persistence.data_directory)
+                    .context("Failed to open snapshot store")?,
+            );
+            let client = SyncClient::new(from, token.clone());
+            let interval = std::time::Duration::from_millis(config.collector.interval_ms);
+            loop {
+                match client.pull(store.clone()).await {
+                    Ok(stored) if !follow => {
+                        println!("Stored {stored} snapshots from {from}");
+                        return Ok(());
+                    }
+                    Ok(0) => {}
+                    Ok(stored) => info!("Stored {stored} snapshots from {from}"),
+                    // A follower outlives outages of the primary
+                    Err(e) if follow => error!("Failed to sync from {from}: {e}"),
+                    Err(e) => return Err(e).with_context(|| format!("Failed to sync from {from}")),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        }
+        Some(Command::Migrate { dry_run }) => {
+            let data_dir = &config.persistence.data_directory;
+            let report = persistence::migrate(
+                Path::new(data_dir),
+                dry_run,
+                config.persistence.backup_before_migration,
+            )
+            .with_context(|| format!("Failed to migrate data directory {data_dir}"))?;
+            report.print();
+            return Ok(());
+        }
+        None => {}
+    }
+
+    info!("Bitcoin Augur Server starting...");
+
     info!("Configuration loaded:");
     info!(
-        "  Server: {host}:{port}",
-        host = config.server.host,
-        port = config.server.port
+        "  Server: {addresses}",
+        addresses = config.server.bind_addresses().join(", ")
     );
     info!("  Bitcoin RPC: {url}", url = config.bitcoin_rpc.url);
+    if let Some(ref path) = config.bitcoin_rpc.password_file {
+        info!("  RPC password: read from {path}");
+    } else if config.bitcoin_rpc.password_cmd.is_some() {
+        info!("  RPC password: printed by bitcoin_rpc.password_cmd");
+    }
     info!(
         "  Data directory: {dir}",
         dir = config.persistence.data_directory
@@ -60,18 +204,135 @@ async fn main() -> Result<()> {
         "  Collection interval: {interval}ms",
         interval = config.collector.interval_ms
     );
+    if let Some(probabilities) = &config.estimator.probabilities {
+        info!("  Confidence levels: {probabilities:?}");
+    }
+    if let Some(block_targets) = &config.estimator.block_targets {
+        info!("  Block targets: {block_targets:?}");
+    }
+    info!(
+        "  Fee rounding: {rounding}",
+        rounding = config.estimator.rounding
+    );
+    match config.estimator.outlier_threshold {
+        Some(threshold) => info!("  Outlier rejection: modified z-score above {threshold}"),
+        None => info!("  Outlier rejection: disabled"),
+    }
+    match config.estimator.max_fee_rate {
+        Some(max_fee_rate) => info!("  Maximum fee rate: {max_fee_rate} sat/vB"),
+        None => info!("  Maximum fee rate: uncapped"),
+    }
+    if let Some(min_spread) = config.estimator.min_spread {
+        info!(
+            "  Minimum spread between confidence levels: {percent}%",
+            percent = min_spread * 100.0
+        );
+    }
+    if let Some(samples) = config.estimator.monte_carlo_samples {
+        info!(
+            "  Monte Carlo simulation: {samples} block arrival sequences per target, seed {seed}",
+            seed = config.estimator.monte_carlo_seed
+        );
+    }
+    if config.estimator.fast_targets {
+        info!("  Fast targets: 1 and 2 blocks, flagged as high variance");
+    }
+    for windows in &config.estimator.target_windows {
+        info!(
+            "  Inflow windows for targets {min}-{max}: {short} minutes / {long} hours",
+            min = windows.min_target,
+            max = windows.max_target,
+            short = windows.short_term_minutes,
+            long = windows.long_term_hours
+        );
+    }
+    for (name, preset) in &config.api.presets {
+        info!(
+            "  Preset {name}: {target} blocks at {confidence} confidence",
+            target = preset.target,
+            confidence = preset.confidence
+        );
+    }
+    let seasonality = build_seasonality_policy(&config)?;
     info!("  Test mode: {enabled}", enabled = config.test_mode.enabled);
 
-    // Initialize Bitcoin RPC client (use mock if in test mode)
-    let bitcoin_client = if config.test_mode.enabled {
+    let ingest_only = config.collector.ingest_only;
+    ensure!(
+        !ingest_only
+            || config
+                .api
+                .ingest_token
+                .as_deref()
+                .is_some_and(|token| !token.is_empty()),
+        "--ingest-only requires --ingest-token"
+    );
+
+    // Initialize Bitcoin RPC client (replay recorded responses, or use mock if in test mode)
+    let mut clock = build_clock(&config)?;
+    let bitcoin_client = if ingest_only {
+        // Never called: the collector does not poll in ingest-only mode
+        info!("Ingest-only mode - snapshots arrive through POST /snapshots");
+        BitcoinClient::Real(BitcoinRpcClient::new(config.to_bitcoin_rpc_config()))
+    } else if let Some(replay_dir) = &cli.replay_rpc {
+        let mut client = ReplayBitcoinClient::new(replay_dir)
+            .context("Failed to load recorded RPC responses")?;
+        info!(
+            "Replaying {count} recorded RPC responses from {replay_dir}",
+            count = client.remaining()
+        );
+        // Snapshots carry the capture times, which only move as responses are replayed
+        if let Some(start) = client.recording_start() {
+            info!("Recording starts at {start}");
+            clock = Clock::simulated(start, chrono::Duration::zero());
+            client = client.with_clock(clock.clone());
+        }
+        BitcoinClient::Replay(client)
+    } else if config.test_mode.enabled {
         info!("Running in test mode - using mock Bitcoin client");
-        BitcoinClient::Mock(MockBitcoinClient::new())
+        match &config.test_mode.scenario_file {
+            Some(path) => {
+                let scenario = Scenario::from_file(path)?;
+                info!(
+                    "Loaded mock scenario '{name}' with {steps} steps from {path}",
+                    name = scenario.name,
+                    steps = scenario.steps.len()
+                );
+                BitcoinClient::Mock(MockBitcoinClient::with_scenario(scenario)?)
+            }
+            None => BitcoinClient::Mock(MockBitcoinClient::new()),
+        }
     } else {
-        let client = BitcoinRpcClient::new(config.to_bitcoin_rpc_config());
+        let mut client = BitcoinRpcClient::new(config.to_bitcoin_rpc_config());
+        if let Some(record_dir) = &cli.record_rpc {
+            client = client.with_recording(record_dir).with_context(|| {
+                format!("Failed to create RPC recording directory {record_dir}")
+            })?;
+        }
+
+        if let Some(proxy) = &config.bitcoin_rpc.proxy {
+            client = client
+                .with_proxy(proxy)
+                .with_context(|| format!("Invalid bitcoin_rpc.proxy {proxy}"))?;
+            match client.test_proxy().await {
+                Ok(()) => info!("  RPC proxy: {proxy}"),
+                Err(e) => error!("RPC proxy check failed: {e}"),
+            }
+        }
 
         // Test Bitcoin connection
         match client.test_connection().await {
-            Ok(_) => info!("Successfully connected to Bitcoin Core"),
+            Ok(_) => {
+                info!("Successfully connected to Bitcoin Core");
+                match client.detect_node_implementation().await {
+                    Ok(node) if node.is_core_compatible() => info!("  Node: {node}"),
+                    Ok(node) => warn!(
+                        "  Node: {node} - mempool entries are parsed from fallback fields, and \
+                         fee bumping and the core baseline may be unavailable"
+                    ),
+                    Err(e) => warn!("Failed to detect the node implementation: {e}"),
+                }
+                check_rpc_access(&client, config.bitcoin_rpc.allow_full_rpc).await?;
+            }
             Err(e) => {
                 error!("Failed to connect to Bitcoin Core: {e}");
                 error!("Please ensure Bitcoin Core is running and RPC credentials are correct");
@@ -82,36 +343,213 @@ async fn main() -> Result<()> {
         BitcoinClient::Real(client)
     };
 
+    // Bring the data directory to the current format before anything reads it
+    let data_dir = &config.persistence.data_directory;
+    let migration = persistence::migrate(
+        Path::new(data_dir),
+        false,
+        config.persistence.backup_before_migration,
+    )
+    .with_context(|| format!("Failed to migrate data directory {data_dir}"))?;
+    if !migration.steps.is_empty() {
+        info!(
+            "Migrated data directory from format {from} to {to}",
+            from = migration.from,
+            to = migration.to
+        );
+    }
+
     // Initialize persistence store
-    let snapshot_store = SnapshotStore::new(&config.persistence.data_directory)
+    let mut snapshot_store = SnapshotStore::new(&config.persistence.data_directory)
         .context("Failed to initialize snapshot store")?;
+    if config.persistence.dedup_snapshots {
+        info!("Storing repeated snapshots as markers");
+        snapshot_store = snapshot_store.with_dedup();
+    }
+    if let Some(storage) = build_object_storage(&config)? {
+        snapshot_store = snapshot_store.with_remote(
+            Arc::new(storage),
+            std::time::Duration::from_millis(config.collector.interval_ms),
+        )?;
+    }
+
+    // Record this run, and report the previous one if it never shut down cleanly
+    let run = RunManifest::new(persistence::config_hash(
+        &serde_json::to_string(&config).context("Failed to describe configuration")?,
+    ));
+    match persistence::begin_run(Path::new(data_dir), &run) {
+        Ok(Some(crashed)) => {
+            let last = snapshot_store.get_latest_snapshot().unwrap_or_else(|e| {
+                warn!("Failed to read the last snapshot for the crash report: {e}");
+                None
+            });
+            let report = CrashReport::new(crashed, &run.config_hash, last.as_ref());
+            error!(
+                "Previous run did not shut down cleanly: {report}",
+                report = serde_json::to_string(&report).context("Failed to describe crash")?
+            );
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to write run manifest to {data_dir}: {e}"),
+    }
+
+    let cycle_log_path = snapshot_store.cycle_log_path();
+    let realized_blocks_path = snapshot_store.realized_blocks_path();
 
     // Initialize fee estimator
-    let fee_estimator = FeeEstimator::new();
+    let fee_estimator = build_estimator(&config)?;
 
     // Create mempool collector
-    let collector = Arc::new(MempoolCollector::new(
-        bitcoin_client,
-        snapshot_store,
-        fee_estimator,
-    ));
+    let estimator_settings =
+        serde_json::to_string(&config.estimator).context("Failed to describe estimator")?;
+    let mut collector = MempoolCollector::new(bitcoin_client, snapshot_store, fee_estimator)
+        .with_clock(clock)
+        .with_estimate_cache(estimator_settings);
+    if let Some(policy) = build_validity_policy(&config)? {
+        collector = collector.with_validity(policy);
+    }
+    if let Some(policy) = seasonality {
+        collector = collector.with_seasonality(policy);
+    }
+    if let Some(policy) = build_gap_policy(&config) {
+        collector = collector.with_gap_policy(policy);
+    }
+    if config.collector.max_staleness_secs > 0 {
+        info!(
+            "  Maximum staleness: {max}s",
+            max = config.collector.max_staleness_secs
+        );
+        collector = collector.with_max_staleness(chrono::Duration::seconds(
+            config.collector.max_staleness_secs as i64,
+        ));
+    }
+    if let Some(policy) = build_interval_policy(&config)? {
+        collector = collector.with_interval_policy(policy);
+    }
+    if let Some(policy) = build_target_cache_policy(&config) {
+        collector = collector.with_target_cache(policy);
+    }
+    if let Some(lease) = build_leader_lease(&config) {
+        collector = collector.with_leader_lease(lease);
+    }
+    if let Some(alerts) = build_alerts(&config)? {
+        collector = collector.with_alerts(alerts);
+    }
+    if !config.api.baselines.is_empty() || !config.api.ensemble_weights.is_empty() {
+        ensure!(
+            !(ingest_only && config.api.baselines.contains(&Baseline::Core)),
+            "--baseline core needs a Bitcoin node and cannot be used with --ingest-only"
+        );
+        info!(
+            "Serving baselines {baselines:?} next to the augur estimate at GET /fees/compare",
+            baselines = config.api.baselines
+        );
+        let mut baselines = Baselines::new(&config.api.baselines);
+        if let Some(ref url) = config.api.mempool_space_url {
+            baselines = baselines.with_mempool_space_url(url);
+        }
+        if !config.api.ensemble_weights.is_empty() {
+            info!(
+                "Serving the ensemble of {weights:?} at GET /fees/compare",
+                weights = config.api.ensemble_weights
+            );
+            baselines = baselines
+                .with_ensemble(config.api.ensemble_weights.clone())
+                .map_err(anyhow::Error::msg)?;
+        }
+        collector = collector.with_baselines(baselines);
+    }
+    if let Some(ref url) = config.api.price_feed_url {
+        info!(
+            "Quoting fee estimates in fiat with prices from {url}, cached for {secs}s",
+            secs = config.api.price_cache_secs
+        );
+    }
+    if let Some(ref path) = config.collector.record_trace {
+        let recorder = TraceRecorder::open(path)
+            .with_context(|| format!("Failed to open trace file {path:?}"))?;
+        info!("Recording anonymized snapshot trace to {path}");
+        collector = collector.with_trace_recorder(recorder);
+    }
+    if let Some(ref source) = config.collector.source {
+        info!("Labelling collected snapshots as source {source:?}");
+        collector = collector.with_source(source.clone());
+    }
+    if config.collector.cycle_log_size > 0 {
+        let log = CycleLog::open(&cycle_log_path, config.collector.cycle_log_size)
+            .with_context(|| format!("Failed to open cycle log {cycle_log_path:?}"))?;
+        collector = collector.with_cycle_log(log);
+    }
+    if config.collector.block_feedback {
+        ensure!(
+            !ingest_only,
+            "--block-feedback needs a Bitcoin node and cannot be used with --ingest-only"
+        );
+        let feedback = BlockFeedback::open(&realized_blocks_path)
+            .with_context(|| format!("Failed to open realized blocks {realized_blocks_path:?}"))?;
+        info!("Realizing new blocks into {realized_blocks_path:?} to score estimates");
+        collector = collector.with_block_feedback(feedback);
+    }
+    if config.collector.package_limits {
+        let limits = PackageLimits::default();
+        info!(
+            "Keeping transactions beyond {count} ancestors or {vsize} vB out of the next block",
+            count = limits.max_count,
+            vsize = limits.max_vsize
+        );
+        collector = collector.with_package_limits(limits);
+    }
+    let collector = Arc::new(collector);
+
+    // Learn the seasonal profile before the first estimate, then relearn it daily
+    if seasonality.is_some() {
+        if let Err(e) = collector.refresh_seasonality().await {
+            warn!("Failed to learn seasonal inflow profile: {e}");
+        }
+        let collector_seasonality = collector.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(24 * 60 * 60));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = collector_seasonality.refresh_seasonality().await {
+                    error!("Seasonal profile refresh failed: {e}");
+                }
+            }
+        });
+    }
 
-    // Initialize from stored snapshots if requested
+    // Serve the estimate cached before the restart while stored snapshots are reloaded
     if cli.init_from_store {
-        info!("Initializing fee estimates from stored snapshots...");
-        match collector.initialize_from_store().await {
-            Ok(_) => info!("Successfully initialized estimates from stored snapshots"),
-            Err(e) => warn!("Failed to initialize from store: {e}"),
+        match collector.restore_cached_estimate().await {
+            Ok(Some(estimate)) => info!(
+                "Serving cached fee estimates from {timestamp} until they are recomputed",
+                timestamp = estimate.timestamp
+            ),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to restore cached fee estimates: {e}"),
         }
     }
 
-    // Spawn background collection task
+    // Initialize from stored snapshots if requested, then start collecting, in the background
     let collector_handle = collector.clone();
+    let init_from_store = cli.init_from_store;
     let interval_ms = config.collector.interval_ms;
     tokio::spawn(async move {
-        info!("Starting mempool collector with {interval_ms}ms interval");
-        if let Err(e) = collector_handle.start(interval_ms).await {
-            error!("Mempool collector error: {e}");
+        if init_from_store {
+            info!("Initializing fee estimates from stored snapshots...");
+            match collector_handle.initialize_from_store().await {
+                Ok(_) => info!("Successfully initialized estimates from stored snapshots"),
+                Err(e) => warn!("Failed to initialize from store: {e}"),
+            }
+        }
+
+        if !ingest_only {
+            info!("Starting mempool collector with {interval_ms}ms interval");
+            if let Err(e) = collector_handle.start(interval_ms).await {
+                error!("Mempool collector error: {e}");
+            }
         }
     });
 
@@ -134,13 +572,392 @@ async fn main() -> Result<()> {
     });
 
     // Create and run HTTP server
-    let app = create_app(collector);
+    if config.api.test_api_enabled {
+        anyhow::ensure!(
+            config
+                .api
+                .test_api_token
+                .as_deref()
+                .is_some_and(|token| !token.is_empty()),
+            "--enable-test-api requires --test-api-token"
+        );
+        warn!("Test-only snapshot injection endpoint enabled at POST /test/snapshots");
+    }
+    if config.api.debug_api_enabled {
+        warn!("Simulation debug endpoint enabled at GET /debug/simulation");
+    }
+    if config.api.ingest_token.is_some() {
+        info!("Accepting snapshots from external collectors at POST /snapshots");
+    }
+    if config.api.admin_token.is_some() {
+        info!("Serving collection cycle timings at GET /admin/cycles");
+        info!("Allowing long historical fee exports with the admin token");
+    }
+    let signer = match config.signing.key_file {
+        Some(ref key_file) => {
+            let signer = ResponseSigner::from_key_file(key_file, config.signing.key_id.as_deref())?;
+            info!(
+                "Signing fee responses with key {key_id} (public key {public_key})",
+                key_id = signer.key_id(),
+                public_key = signer.public_key_hex()
+            );
+            Some(Arc::new(signer))
+        }
+        None => None,
+    };
+    let app = create_app_with_signer(
+        collector.clone(),
+        config.api.clone(),
+        config.response,
+        signer,
+    );
 
-    run_server(app, config.server.host, config.server.port)
+    let readiness = Readiness {
+        ready_file: config.server.ready_file.as_ref().map(PathBuf::from),
+        warm: (config.server.ready_when == ReadyWhen::Warm).then(|| collector.clone()),
+    };
+    run_server(app, &config.server.bind_addresses(), readiness)
         .await
         .context("Failed to run HTTP server")?;
+    collector.release_lease();
 
+    if let Err(e) = persistence::complete_run(Path::new(data_dir), &run) {
+        warn!("Failed to mark run manifest complete in {data_dir}: {e}");
+    }
     info!("Bitcoin Augur Server shut down");
 
     Ok(())
 }
+
+/// Builds the fee estimator from the `estimator` settings
+fn build_estimator(config: &AppConfig) -> Result<FeeEstimator> {
+    let estimator = match (
+        &config.estimator.probabilities,
+        &config.estimator.block_targets,
+    ) {
+        (None, None) => FeeEstimator::new(),
+        (probabilities, block_targets) => {
+            let defaults = FeeEstimator::new();
+            FeeEstimator::with_config(
+                probabilities
+                    .clone()
+                    .unwrap_or_else(|| FeeEstimator::DEFAULT_PROBABILITIES.to_vec()),
+                block_targets
+                    .clone()
+                    .unwrap_or_else(|| FeeEstimator::DEFAULT_BLOCK_TARGETS.to_vec()),
+                defaults.short_term_window(),
+                defaults.long_term_window(),
+            )
+            .context("Invalid estimator probabilities or block targets")?
+        }
+    };
+    let mut estimator = estimator
+        .with_rounding(config.estimator.rounding)
+        .with_incremental_inflows();
+    if let Some(threshold) = config.estimator.outlier_threshold {
+        let filter = OutlierFilter::new(threshold, OutlierFilter::DEFAULT_WINDOW)
+            .context("Invalid outlier threshold")?;
+        estimator = estimator.with_outlier_filter(filter);
+    }
+    if config.estimator.fast_targets {
+        estimator = estimator.with_fast_targets();
+    }
+    if let Some(max_fee_rate) = config.estimator.max_fee_rate {
+        estimator = estimator
+            .with_max_fee_rate(max_fee_rate)
+            .context("Invalid maximum fee rate")?;
+    }
+    if let Some(min_spread) = config.estimator.min_spread {
+        estimator = estimator
+            .with_min_spread(min_spread)
+            .context("Invalid minimum spread")?;
+    }
+    if let Some(samples) = config.estimator.monte_carlo_samples {
+        let monte_carlo = MonteCarlo::new(samples, config.estimator.monte_carlo_seed)
+            .context("Invalid Monte Carlo simulation")?;
+        estimator = estimator.with_monte_carlo(monte_carlo);
+    }
+    if !config.estimator.target_windows.is_empty() {
+        let target_windows = config
+            .estimator
+            .target_windows
+            .iter()
+            .map(|windows| {
+                TargetWindows::new(
+                    f64::from(windows.min_target),
+                    f64::from(windows.max_target),
+                    chrono::Duration::minutes(i64::from(windows.short_term_minutes)),
+                    chrono::Duration::hours(i64::from(windows.long_term_hours)),
+                )
+                .with_context(|| {
+                    format!(
+                        "Invalid inflow windows for targets {min}-{max}",
+                        min = windows.min_target,
+                        max = windows.max_target
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        estimator = estimator
+            .with_target_windows(target_windows)
+            .context("Invalid target windows")?;
+    }
+    Ok(estimator)
+}
+
+/// Validates the seasonal inflow settings, `None` when seasonality is disabled
+fn build_seasonality_policy(config: &AppConfig) -> Result<Option<SeasonalityPolicy>> {
+    let Some(weeks) = config.estimator.seasonality_weeks else {
+        info!("  Seasonal inflow: disabled");
+        return Ok(None);
+    };
+
+    let blend = config.estimator.seasonality_blend;
+    let kept_days = config.persistence.cleanup_days;
+    if i64::from(weeks) * 7 > kept_days {
+        warn!(
+            "Seasonality asks for {weeks} weeks of history but snapshots are kept {kept_days} days"
+        );
+    }
+    info!("  Seasonal inflow: learned from {weeks} weeks of history, blend {blend}");
+    Ok(Some(SeasonalityPolicy { weeks, blend }))
+}
+
+/// Derives the `valid_until` policy from the configured collection interval and limits
+fn build_validity_policy(config: &AppConfig) -> Result<Option<ValidityPolicy>> {
+    let validity = &config.validity;
+    if !validity.enabled {
+        info!("  Valid-until metadata: disabled");
+        return Ok(None);
+    }
+
+    let fraction = validity.target_fraction;
+    let policy = ValidityPolicy {
+        collection_interval: chrono::Duration::milliseconds(config.collector.interval_ms as i64),
+        target_fraction: fraction,
+        max_ttl: chrono::Duration::seconds(validity.max_ttl_secs as i64),
+    };
+    info!(
+        "  Valid-until metadata: {fraction} of each target's expected time, at most {max}s",
+        max = validity.max_ttl_secs
+    );
+    Ok(Some(policy))
+}
+
+/// Gap detection settings from the collector configuration, or `None` when disabled
+fn build_gap_policy(config: &AppConfig) -> Option<GapPolicy> {
+    let collector = &config.collector;
+    if collector.max_gap_secs == 0 {
+        info!("  Gap detection: disabled");
+        return None;
+    }
+    info!(
+        "  Gap detection: gaps over {max}s, backfill {backfill}",
+        max = collector.max_gap_secs,
+        backfill = if collector.backfill_gaps {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    Some(GapPolicy {
+        max_gap: chrono::Duration::seconds(collector.max_gap_secs as i64),
+        backfill: collector.backfill_gaps,
+    })
+}
+
+/// S3 storage the snapshots are written through to, if a bucket is configured
+///
+/// Credentials left out of the configuration are read from the standard AWS environment
+/// variables.
+fn build_object_storage(config: &AppConfig) -> Result<Option<S3Storage>> {
+    let persistence = &config.persistence;
+    let Some(bucket) = &persistence.s3_bucket else {
+        return Ok(None);
+    };
+    let from_env = |value: &str, var: &str| {
+        if value.is_empty() {
+            std::env::var(var).unwrap_or_default()
+        } else {
+            value.to_string()
+        }
+    };
+    let region = persistence
+        .s3_region
+        .clone()
+        .unwrap_or_else(|| "us-east-1".to_string());
+    let storage = S3Storage::new(S3Config {
+        endpoint: persistence
+            .s3_endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{region}.amazonaws.com")),
+        bucket: bucket.clone(),
+        prefix: persistence.s3_prefix.clone(),
+        access_key_id: from_env(&persistence.s3_access_key_id, "AWS_ACCESS_KEY_ID"),
+        secret_access_key: from_env(&persistence.s3_secret_access_key, "AWS_SECRET_ACCESS_KEY"),
+        region,
+    })
+    .context("Invalid persistence.s3_endpoint")?;
+    ensure!(
+        !storage.config().access_key_id.is_empty()
+            && !storage.config().secret_access_key.is_empty(),
+        "persistence.s3_bucket needs persistence.s3_access_key_id and s3_secret_access_key, \
+         or AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY"
+    );
+    info!(
+        "Writing snapshots through to s3://{bucket}/{prefix} at {endpoint}",
+        prefix = storage.config().prefix,
+        endpoint = storage.config().endpoint
+    );
+    Ok(Some(storage))
+}
+
+/// Target cache whose estimates live as long as a collection interval, if caching or capping
+/// targets is configured
+fn build_target_cache_policy(config: &AppConfig) -> Option<TargetCachePolicy> {
+    let collector = &config.collector;
+    if collector.target_cache_size == 0 && collector.max_targets_per_minute == 0 {
+        return None;
+    }
+    info!(
+        "  Target cache: {size} targets",
+        size = collector.target_cache_size
+    );
+    if collector.max_targets_per_minute > 0 {
+        info!(
+            "  Maximum distinct targets per minute: {max}",
+            max = collector.max_targets_per_minute
+        );
+    }
+    Some(TargetCachePolicy {
+        capacity: collector.target_cache_size,
+        ttl: chrono::Duration::milliseconds(collector.interval_ms as i64),
+        max_targets_per_minute: (collector.max_targets_per_minute > 0)
+            .then_some(collector.max_targets_per_minute),
+    })
+}
+
+/// Lease on collecting shared with the other instances using the data directory, if a lease
+/// duration is configured
+fn build_leader_lease(config: &AppConfig) -> Option<LeaderLease> {
+    let collector = &config.collector;
+    if collector.leader_lease_secs == 0 {
+        return None;
+    }
+    let instance = collector.instance_id.clone().unwrap_or_else(|| {
+        let host = std::env::var("HOSTNAME")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "augur".to_string());
+        format!("{host}-{pid}", pid = std::process::id())
+    });
+    info!(
+        "  Leader election: {secs}s lease as {instance}",
+        secs = collector.leader_lease_secs
+    );
+    Some(LeaderLease::new(
+        &config.persistence.data_directory,
+        instance,
+        chrono::Duration::seconds(collector.leader_lease_secs as i64),
+    ))
+}
+
+/// Adaptive collection interval from the collector configuration, or `None` when neither
+/// bound is set and the interval is fixed
+///
+/// A missing bound is the configured interval, so setting only one lets the collector either
+/// speed up or back off.
+fn build_interval_policy(config: &AppConfig) -> Result<Option<IntervalPolicy>> {
+    let collector = &config.collector;
+    if collector.min_interval_ms.is_none() && collector.max_interval_ms.is_none() {
+        return Ok(None);
+    }
+    let min_ms = collector.min_interval_ms.unwrap_or(collector.interval_ms);
+    let max_ms = collector.max_interval_ms.unwrap_or(collector.interval_ms);
+    info!(
+        "  Adaptive collection interval: {min_ms}ms to {max_ms}ms, volatile above {threshold}",
+        threshold = collector.volatility_threshold
+    );
+    Ok(Some(IntervalPolicy {
+        min: std::time::Duration::from_millis(min_ms),
+        max: std::time::Duration::from_millis(max_ms),
+        volatility_threshold: collector.volatility_threshold,
+    }))
+}
+
+/// Alert rules from the configuration, or `None` when there are none
+fn build_alerts(config: &AppConfig) -> Result<Option<Alerts>> {
+    let rules = &config.alerts.rules;
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let mut alerts = Alerts::new(rules.clone()).map_err(anyhow::Error::msg)?;
+    info!(
+        "Evaluating {count} alert rules after every collection",
+        count = rules.len()
+    );
+    if let Some(ref url) = config.alerts.webhook_url {
+        info!("Posting alerts to {url}");
+        alerts = alerts.with_webhook(url);
+    }
+    Ok(Some(alerts))
+}
+
+/// Refuses to start with RPC credentials that lack a required method, or that may call wallet
+/// methods unless `allow_full_rpc`
+///
+/// Restricting the credentials to the few methods the server needs limits what a leak of them
+/// exposes.
+async fn check_rpc_access(client: &BitcoinRpcClient, allow_full_rpc: bool) -> Result<()> {
+    let access = match client.probe_rpc_access().await {
+        Ok(access) => access,
+        Err(e) => {
+            warn!("Failed to probe which RPC methods the credentials may call: {e}");
+            return Ok(());
+        }
+    };
+    ensure!(
+        access.missing.is_empty(),
+        "The Bitcoin RPC credentials may not call {missing}",
+        missing = access.missing.join(", ")
+    );
+    if access.wallet {
+        ensure!(
+            allow_full_rpc,
+            "The Bitcoin RPC credentials may call wallet methods; restrict them with \
+             rpcwhitelist=<user>:{methods} or pass --allow-full-rpc",
+            methods = REQUIRED_RPC_METHODS.join(",")
+        );
+        warn!("The Bitcoin RPC credentials may call wallet methods (--allow-full-rpc)");
+    } else {
+        info!("  RPC credentials: no wallet access");
+    }
+    Ok(())
+}
+
+/// Selects the collector clock: simulated only when requested in test mode
+fn build_clock(config: &AppConfig) -> Result<Clock> {
+    let Some(step_secs) = config.test_mode.simulated_clock_step_secs else {
+        return Ok(Clock::System);
+    };
+
+    if !config.test_mode.enabled {
+        warn!("Simulated clock requires test mode; using the system clock");
+        return Ok(Clock::System);
+    }
+
+    let start = match &config.test_mode.simulated_clock_start {
+        Some(start) => chrono::DateTime::parse_from_rfc3339(start)
+            .with_context(|| format!("Invalid simulated clock start time: {start}"))?
+            .with_timezone(&chrono::Utc),
+        None => chrono::Utc::now(),
+    };
+
+    info!("Simulated clock advances {step_secs}s per collection cycle from {start}");
+    Ok(Clock::simulated(
+        start,
+        chrono::Duration::seconds(step_secs as i64),
+    ))
+}
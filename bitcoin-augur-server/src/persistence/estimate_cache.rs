@@ -0,0 +1,135 @@
+//! The latest fee estimate, kept on disk so a restarted server can serve it right away
+//!
+//! Recomputing estimates from a full window of snapshots takes a while after a restart. The
+//! collector writes each estimate it publishes next to the snapshots, together with a
+//! fingerprint of the snapshots and estimator settings it was computed from. On startup the
+//! cached estimate is served while the window is reloaded; if the fingerprint of the reloaded
+//! window still matches, the estimate is kept instead of being recalculated.
+
+use bitcoin_augur::{FeeEstimate, MempoolSnapshot};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+use super::snapshot_store::PersistenceError;
+
+/// Name of the cache file inside the data directory
+pub const ESTIMATE_CACHE_FILE_NAME: &str = "estimate_cache.json";
+
+/// A fee estimate with the fingerprint of its inputs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEstimate {
+    /// Configured estimator settings, which must match for the estimate to be restored
+    pub settings: String,
+    /// Hex SHA-256 of the estimator settings and snapshots, from [`fingerprint`]
+    pub fingerprint: String,
+    pub estimate: FeeEstimate,
+}
+
+/// Fingerprint of an estimate computed with `settings` from `snapshots`
+///
+/// `settings` is any stable description of the estimator; snapshots are hashed in the order
+/// given, with their heights, timestamps and bucket weights.
+pub fn fingerprint(settings: &str, snapshots: &[MempoolSnapshot]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((settings.len() as u64).to_le_bytes());
+    hasher.update(settings.as_bytes());
+    hasher.update((snapshots.len() as u64).to_le_bytes());
+    for snapshot in snapshots {
+        hasher.update(snapshot.block_height.to_le_bytes());
+        hasher.update(snapshot.timestamp.timestamp().to_le_bytes());
+        hasher.update(snapshot.timestamp.timestamp_subsec_nanos().to_le_bytes());
+        hasher.update((snapshot.bucketed_weights.len() as u64).to_le_bytes());
+        for (bucket, weight) in &snapshot.bucketed_weights {
+            hasher.update(bucket.to_le_bytes());
+            hasher.update(weight.to_le_bytes());
+        }
+        // Snapshots without deferred weights fingerprint as they did before
+        if !snapshot.bucketed_deferred_weights.is_empty() {
+            hasher.update((snapshot.bucketed_deferred_weights.len() as u64).to_le_bytes());
+            for (bucket, weight) in &snapshot.bucketed_deferred_weights {
+                hasher.update(bucket.to_le_bytes());
+                hasher.update(weight.to_le_bytes());
+            }
+        }
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Writes `cached` to `path`, renaming it into place so readers never see a partial file
+pub fn write_estimate_cache(path: &Path, cached: &CachedEstimate) -> Result<(), PersistenceError> {
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_vec(cached)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Reads the cached estimate at `path`, or `None` if there is none
+pub fn read_estimate_cache(path: &Path) -> Result<Option<CachedEstimate>, PersistenceError> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::{BlockTarget, OrderedFloat};
+    use chrono::{TimeZone, Utc};
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn snapshot(height: u32, weight: u64) -> MempoolSnapshot {
+        let timestamp = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        MempoolSnapshot::new(height, timestamp, BTreeMap::from([(100, weight)]))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(ESTIMATE_CACHE_FILE_NAME);
+        assert!(read_estimate_cache(&path).unwrap().is_none());
+
+        let target = BlockTarget::new(6, BTreeMap::from([(OrderedFloat(0.5), 5.2)]));
+        let timestamp = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let cached = CachedEstimate {
+            settings: String::new(),
+            fingerprint: fingerprint("", &[snapshot(100, 4_000)]),
+            estimate: FeeEstimate::new(BTreeMap::from([(6, target)]), timestamp),
+        };
+        write_estimate_cache(&path, &cached).unwrap();
+
+        let read = read_estimate_cache(&path).unwrap().unwrap();
+        assert_eq!(read.fingerprint, cached.fingerprint);
+        assert_eq!(read.estimate.timestamp, timestamp);
+        assert_eq!(read.estimate.get_fee_rate(6, 0.5), Some(5.2));
+
+        fs::write(&path, b"{").unwrap();
+        assert!(matches!(
+            read_estimate_cache(&path),
+            Err(PersistenceError::JsonError(_))
+        ));
+    }
+
+    #[test]
+    fn test_fingerprint_covers_settings_and_snapshots() {
+        let snapshots = [snapshot(100, 4_000), snapshot(101, 8_000)];
+        let base = fingerprint("exact", &snapshots);
+        assert_eq!(base.len(), 64);
+        assert_eq!(base, fingerprint("exact", &snapshots));
+
+        assert_ne!(base, fingerprint("ceil_tenth", &snapshots));
+        assert_ne!(base, fingerprint("exact", &snapshots[..1]));
+        assert_ne!(
+            base,
+            fingerprint("exact", &[snapshot(100, 4_000), snapshot(101, 8_001)])
+        );
+    }
+}
@@ -0,0 +1,496 @@
+//! Consistency check and repair of a snapshot store directory
+//!
+//! Every snapshot must live at `YYYY-MM-DD/{block_height}_{unix_time}.json`, named after its own
+//! content, in the date directory's pack, or in its month's archive under `archive/YYYY-MM.pack`.
+//! Files that do not parse, and markers naming a
+//! snapshot that is not stored, are corrupt. Files that parse but sit elsewhere are misnamed,
+//! which hides them from range queries that select files by name. Repair deletes corrupt files and
+//! moves misnamed ones to where their content belongs.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use super::estimate_cache::ESTIMATE_CACHE_FILE_NAME;
+use super::migrations::FORMAT_VERSION_FILE_NAME;
+use super::run_manifest::RUN_MANIFEST_FILE_NAME;
+use super::snapshot_archive::{archive_month, ARCHIVE_DIR_NAME};
+use super::snapshot_pack::{SnapshotPack, PACK_FILE_NAME};
+use super::snapshot_store::{
+    read_snapshot_file, snapshot_path, PersistenceError, CYCLE_LOG_FILE_NAME,
+    LEADER_LEASE_FILE_NAME, LEADER_LOCK_FILE_NAME, REALIZED_BLOCKS_FILE_NAME,
+};
+
+/// Something wrong with one entry of the store
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// A snapshot file that cannot be read or parsed
+    Corrupt { error: String },
+    /// A valid snapshot stored under the wrong name or date directory
+    Misnamed { expected: PathBuf },
+    /// A misnamed snapshot whose expected path holds a different snapshot
+    Conflict { expected: PathBuf },
+    /// A file or directory the store never writes, left untouched
+    Unrecognized,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Corrupt { error } => write!(f, "corrupt ({error})"),
+            Self::Misnamed { expected } => write!(f, "misnamed, expected {expected:?}"),
+            Self::Conflict { expected } => {
+                write!(f, "misnamed, but {expected:?} holds a different snapshot")
+            }
+            Self::Unrecognized => f.write_str("not part of the store"),
+        }
+    }
+}
+
+/// A problem found at `path`, and whether repair resolved it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub problem: Problem,
+    pub repaired: bool,
+}
+
+/// Outcome of checking a store
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    pub files_checked: usize,
+    pub valid: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl FsckReport {
+    /// Problems that make snapshots unreadable or invisible and were not repaired
+    pub fn unresolved(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| !f.repaired && f.problem != Problem::Unrecognized)
+            .count()
+    }
+
+    /// Prints every finding followed by a summary
+    pub fn print(&self) {
+        for finding in &self.findings {
+            let status = if finding.repaired { " [repaired]" } else { "" };
+            println!(
+                "{path}: {problem}{status}",
+                path = finding.path.display(),
+                problem = finding.problem
+            );
+        }
+        let repaired = self.findings.iter().filter(|f| f.repaired).count();
+        println!(
+            "Checked {files} files: {valid} valid, {problems} problems, {repaired} repaired",
+            files = self.files_checked,
+            valid = self.valid,
+            problems = self.findings.len()
+        );
+    }
+}
+
+pub(super) fn is_date_dir(path: &Path) -> bool {
+    path.is_dir()
+        && path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| chrono::NaiveDate::parse_from_str(name, "%Y-%m-%d").is_ok())
+}
+
+/// Checks every file under `data_dir`, repairing corrupt and misnamed snapshots if `repair`
+pub fn fsck(data_dir: &Path, repair: bool) -> Result<FsckReport, PersistenceError> {
+    if !data_dir.is_dir() {
+        return Err(PersistenceError::InvalidPath(format!(
+            "{path} is not a directory",
+            path = data_dir.display()
+        )));
+    }
+
+    let mut report = FsckReport::default();
+    let mut misnamed = Vec::new();
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(data_dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    for dir in entries {
+        let name = dir.file_name().and_then(|n| n.to_str());
+        if name == Some(ESTIMATE_CACHE_FILE_NAME)
+            || name == Some(FORMAT_VERSION_FILE_NAME)
+            || name == Some(RUN_MANIFEST_FILE_NAME)
+            || name == Some(LEADER_LEASE_FILE_NAME)
+            || name == Some(LEADER_LOCK_FILE_NAME)
+            || name == Some(REALIZED_BLOCKS_FILE_NAME)
+            || name.is_some_and(|name| name.starts_with(CYCLE_LOG_FILE_NAME))
+        {
+            continue;
+        }
+        if name == Some(ARCHIVE_DIR_NAME) && dir.is_dir() {
+            let mut archives: Vec<PathBuf> = fs::read_dir(&dir)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<_, _>>()?;
+            archives.sort();
+            for path in archives {
+                if path.is_file() && archive_month(&path).is_some() {
+                    check_pack(path, repair, &mut report)?;
+                } else {
+                    report.findings.push(Finding {
+                        path,
+                        problem: Problem::Unrecognized,
+                        repaired: false,
+                    });
+                }
+            }
+            continue;
+        }
+        if !is_date_dir(&dir) {
+            report.findings.push(Finding {
+                path: dir,
+                problem: Problem::Unrecognized,
+                repaired: false,
+            });
+            continue;
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        files.sort();
+
+        for path in files {
+            if path.file_name().and_then(|n| n.to_str()) == Some(PACK_FILE_NAME) {
+                check_pack(path, repair, &mut report)?;
+                continue;
+            }
+            if !path.is_file() || path.extension().and_then(|s| s.to_str()) != Some("json") {
+                report.findings.push(Finding {
+                    path,
+                    problem: Problem::Unrecognized,
+                    repaired: false,
+                });
+                continue;
+            }
+            report.files_checked += 1;
+
+            match read_snapshot_file(&path) {
+                Ok(snapshot) => {
+                    let expected = snapshot_path(data_dir, &snapshot);
+                    if expected == path {
+                        report.valid += 1;
+                    } else {
+                        misnamed.push((path, expected));
+                    }
+                }
+                Err(e) => {
+                    if repair {
+                        fs::remove_file(&path)?;
+                        info!("Removed corrupt snapshot {path}", path = path.display());
+                    }
+                    report.findings.push(Finding {
+                        path,
+                        problem: Problem::Corrupt {
+                            error: e.to_string(),
+                        },
+                        repaired: repair,
+                    });
+                }
+            }
+        }
+    }
+
+    // When repairing, corrupt files are gone by now, so an existing expected path holds a
+    // valid snapshot
+    for (path, expected) in misnamed {
+        let finding = relocate(&path, &expected, repair)?;
+        report.findings.push(finding);
+    }
+
+    if repair {
+        remove_empty_date_dirs(data_dir)?;
+    }
+
+    Ok(report)
+}
+
+/// Decodes every snapshot of the day pack or monthly archive at `path`, deleting it if corrupt
+/// and `repair`
+fn check_pack(
+    path: PathBuf,
+    repair: bool,
+    report: &mut FsckReport,
+) -> Result<(), PersistenceError> {
+    report.files_checked += 1;
+    match SnapshotPack::open(&path).and_then(|pack| pack.snapshots()) {
+        Ok(_) => report.valid += 1,
+        Err(e) => {
+            if repair {
+                fs::remove_file(&path)?;
+                info!(
+                    "Removed corrupt snapshot pack {path}",
+                    path = path.display()
+                );
+            }
+            report.findings.push(Finding {
+                path,
+                problem: Problem::Corrupt {
+                    error: e.to_string(),
+                },
+                repaired: repair,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Moves a misnamed snapshot to `expected`, or drops it if an identical copy is already there
+pub(super) fn relocate(
+    path: &Path,
+    expected: &Path,
+    repair: bool,
+) -> Result<Finding, PersistenceError> {
+    let duplicate = if expected.exists() {
+        let existing = fs::read_to_string(expected)?;
+        let moved = fs::read_to_string(path)?;
+        let same = serde_json::from_str::<serde_json::Value>(&existing).ok()
+            == serde_json::from_str::<serde_json::Value>(&moved).ok();
+        if !same {
+            return Ok(Finding {
+                path: path.to_path_buf(),
+                problem: Problem::Conflict {
+                    expected: expected.to_path_buf(),
+                },
+                repaired: false,
+            });
+        }
+        true
+    } else {
+        false
+    };
+
+    if repair {
+        if duplicate {
+            fs::remove_file(path)?;
+            info!("Removed duplicate snapshot {path}", path = path.display());
+        } else {
+            if let Some(parent) = expected.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(path, expected)?;
+            info!(
+                "Moved snapshot {path} to {expected}",
+                path = path.display(),
+                expected = expected.display()
+            );
+        }
+    }
+
+    Ok(Finding {
+        path: path.to_path_buf(),
+        problem: Problem::Misnamed {
+            expected: expected.to_path_buf(),
+        },
+        repaired: repair,
+    })
+}
+
+pub(super) fn remove_empty_date_dirs(data_dir: &Path) -> Result<(), PersistenceError> {
+    for entry in fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        if is_date_dir(&path) && fs::read_dir(&path)?.next().is_none() {
+            fs::remove_dir(&path)?;
+            debug!("Removed empty directory {path}", path = path.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::SnapshotStore;
+    use bitcoin_augur::MempoolSnapshot;
+    use chrono::{TimeZone, Utc};
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn snapshot(height: u32, hour: u32) -> MempoolSnapshot {
+        let timestamp = Utc.with_ymd_and_hms(2025, 1, 20, hour, 0, 0).unwrap();
+        MempoolSnapshot::new(height, timestamp, BTreeMap::from([(100, 4_000)]))
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_clean_store_has_no_findings() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        store.save_snapshot(&snapshot(100, 1)).unwrap();
+        store.save_snapshot(&snapshot(101, 2)).unwrap();
+        write(&temp_dir.path().join(ESTIMATE_CACHE_FILE_NAME), "{}");
+        write(&temp_dir.path().join(FORMAT_VERSION_FILE_NAME), "3\n");
+        write(&temp_dir.path().join(RUN_MANIFEST_FILE_NAME), "{}");
+        write(&temp_dir.path().join(CYCLE_LOG_FILE_NAME), "");
+        write(&temp_dir.path().join(LEADER_LEASE_FILE_NAME), "{}");
+        write(&temp_dir.path().join(LEADER_LOCK_FILE_NAME), "");
+        write(&temp_dir.path().join(REALIZED_BLOCKS_FILE_NAME), "");
+
+        let report = fsck(temp_dir.path(), false).unwrap();
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.valid, 2);
+        assert!(report.findings.is_empty());
+    }
+
+    #[test]
+    fn test_reports_without_repair() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let corrupt = root.join("2025-01-20/100_1737334800.json");
+        let misnamed = root.join("2025-01-21/whatever.json");
+        write(&corrupt, "{\"block_height\": 100, \"times");
+        write(
+            &misnamed,
+            &serde_json::to_string(&snapshot(101, 2)).unwrap(),
+        );
+        write(&root.join("notes.txt"), "");
+
+        let report = fsck(root, false).unwrap();
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.valid, 0);
+        assert_eq!(report.findings.len(), 3);
+        assert_eq!(report.unresolved(), 2);
+        assert!(corrupt.exists() && misnamed.exists());
+    }
+
+    #[test]
+    fn test_repair_removes_and_relocates() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let store = SnapshotStore::new(root).unwrap();
+        store.save_snapshot(&snapshot(100, 1)).unwrap();
+
+        write(&root.join("2025-01-20/100_1.json"), "not json");
+        // A copy of the stored snapshot and a snapshot filed under the wrong date
+        write(
+            &root.join("2025-01-19/copy.json"),
+            &serde_json::to_string(&snapshot(100, 1)).unwrap(),
+        );
+        write(
+            &root.join("2025-01-22/101_0.json"),
+            &serde_json::to_string(&snapshot(101, 2)).unwrap(),
+        );
+
+        let report = fsck(root, true).unwrap();
+        assert_eq!(report.unresolved(), 0);
+        assert!(report.findings.iter().all(|f| f.repaired));
+
+        // Both snapshots are now where range queries look for them
+        let after = fsck(root, false).unwrap();
+        assert_eq!(after.valid, 2);
+        assert!(after.findings.is_empty());
+        assert!(!root.join("2025-01-19").exists());
+        assert!(!root.join("2025-01-22").exists());
+    }
+
+    #[test]
+    fn test_conflicting_copy_is_left_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let store = SnapshotStore::new(root).unwrap();
+        store.save_snapshot(&snapshot(100, 1)).unwrap();
+
+        let mut different = snapshot(100, 1);
+        different.bucketed_weights.insert(200, 8_000);
+        let copy = root.join("2025-01-20/copy.json");
+        write(&copy, &serde_json::to_string(&different).unwrap());
+
+        let report = fsck(root, true).unwrap();
+        assert_eq!(report.unresolved(), 1);
+        assert!(matches!(
+            report.findings[0].problem,
+            Problem::Conflict { .. }
+        ));
+        assert!(copy.exists());
+    }
+
+    #[test]
+    fn test_checks_packs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let store = SnapshotStore::new(root).unwrap();
+        store.save_snapshot(&snapshot(100, 1)).unwrap();
+        store
+            .pack_before(chrono::NaiveDate::from_ymd_opt(2025, 1, 21).unwrap())
+            .unwrap();
+        let corrupt = root.join("2025-01-19").join(PACK_FILE_NAME);
+        write(&corrupt, "not a pack");
+
+        let report = fsck(root, true).unwrap();
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.valid, 1);
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.unresolved(), 0);
+        assert!(!corrupt.exists());
+    }
+
+    #[test]
+    fn test_checks_archives() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let store = SnapshotStore::new(root).unwrap();
+        store.save_snapshot(&snapshot(100, 1)).unwrap();
+        store
+            .archive_before(chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap())
+            .unwrap();
+        let stray = root.join(ARCHIVE_DIR_NAME).join("notes.txt");
+        write(&stray, "not an archive");
+
+        let report = fsck(root, false).unwrap();
+        assert_eq!(report.files_checked, 1);
+        assert_eq!(report.valid, 1);
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].problem, Problem::Unrecognized);
+
+        let corrupt = root.join(ARCHIVE_DIR_NAME).join("2025-01.pack");
+        write(&corrupt, "not a pack");
+        let report = fsck(root, true).unwrap();
+        assert_eq!(report.unresolved(), 0);
+        assert!(!corrupt.exists());
+    }
+
+    #[test]
+    fn test_checks_dedup_markers() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let store = SnapshotStore::new(root).unwrap().with_dedup();
+        let first = snapshot(100, 1);
+        let same = MempoolSnapshot::new(
+            100,
+            first.timestamp + chrono::Duration::minutes(1),
+            first.bucketed_weights.clone(),
+        );
+        store.save_snapshot(&first).unwrap();
+        store.save_snapshot(&same).unwrap();
+
+        let report = fsck(root, false).unwrap();
+        assert_eq!(report.valid, 2);
+        assert!(report.findings.is_empty());
+
+        // Without the snapshot it names, a marker is corrupt
+        fs::remove_file(snapshot_path(root, &first)).unwrap();
+        let report = fsck(root, true).unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert!(matches!(
+            report.findings[0].problem,
+            Problem::Corrupt { .. }
+        ));
+        assert!(!snapshot_path(root, &same).exists());
+    }
+}
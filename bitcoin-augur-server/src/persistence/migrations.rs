@@ -0,0 +1,475 @@
+//! Versioned layout of the data directory, and the migrations between versions
+//!
+//! The data directory records its layout in a `FORMAT_VERSION` file. A directory without one
+//! predates versioning: it is treated as version 1 if it holds any date directories or archives,
+//! and as new otherwise. On startup every migration from the recorded version to [`FORMAT_VERSION`] runs in
+//! order, and the file is rewritten after each one, so an interrupted upgrade resumes where it
+//! stopped. A directory written by a newer release is refused rather than misread.
+//!
+//! | Version | Layout |
+//! |---------|--------|
+//! | 1 | JSON snapshots, possibly in directories named after the local date |
+//! | 2 | JSON snapshots in directories named after their UTC date |
+//! | 3 | As 2, and past days may be packed into one indexed binary file each |
+//! | 4 | As 3, and past months may be archived into one indexed binary file each |
+//!
+//! A version says what a reader must understand, not what the directory holds: packing and
+//! archiving stay with the `pack` and `archive` commands, so the steps to 3 and 4 change no
+//! files. They only keep releases that cannot read packs from starting on a directory that may
+//! hold them. Snapshots are not compressed: packs are the compact form, and a compressed JSON
+//! layout is not part of any version.
+
+use chrono::NaiveDate;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use super::fsck::{is_date_dir, relocate, remove_empty_date_dirs, Problem};
+use super::snapshot_archive::ARCHIVE_DIR_NAME;
+use super::snapshot_store::{read_snapshot_file, snapshot_path, PersistenceError};
+
+/// Layout version written by this release
+pub const FORMAT_VERSION: u32 = 4;
+
+/// Name of the file recording the layout version in the data directory
+pub const FORMAT_VERSION_FILE_NAME: &str = "FORMAT_VERSION";
+
+/// One step from the previous format version to `version`
+struct Migration {
+    version: u32,
+    description: &'static str,
+    /// Applies the step and returns how many files or directories it changed, or with
+    /// `dry_run` only counts them
+    apply: fn(&Path, bool) -> Result<usize, PersistenceError>,
+}
+
+const MIGRATIONS: [Migration; 3] = [
+    Migration {
+        version: 2,
+        description: "move snapshots into directories named after their UTC date",
+        apply: move_to_utc_dirs,
+    },
+    Migration {
+        version: 3,
+        description: "allow packs of past days, written by the pack command",
+        apply: no_changes,
+    },
+    Migration {
+        version: 4,
+        description: "allow archives of past months, written by the archive command",
+        apply: no_changes,
+    },
+];
+
+/// A migration that ran, or would run in a dry run
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStep {
+    pub version: u32,
+    pub description: &'static str,
+    pub changes: usize,
+}
+
+/// Outcome of [`migrate`]
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    pub steps: Vec<MigrationStep>,
+    /// Copy of the data directory taken before migrating
+    pub backup: Option<PathBuf>,
+    pub dry_run: bool,
+}
+
+impl MigrationReport {
+    /// Prints every step followed by a summary
+    pub fn print(&self) {
+        let verb = if self.dry_run {
+            "Would migrate"
+        } else {
+            "Migrated"
+        };
+        for step in &self.steps {
+            println!(
+                "{verb} to format {version}: {description} ({changes} changes)",
+                version = step.version,
+                description = step.description,
+                changes = step.changes
+            );
+        }
+        if let Some(backup) = &self.backup {
+            println!(
+                "Backed up data directory to {backup}",
+                backup = backup.display()
+            );
+        }
+        if self.steps.is_empty() {
+            println!("Data directory is at format {to}", to = self.to);
+        } else {
+            println!(
+                "{verb} data directory from format {from} to {to}",
+                from = self.from,
+                to = self.to
+            );
+        }
+    }
+}
+
+/// Reads the format version of `data_dir`, inferring it for directories that predate versioning
+pub fn format_version(data_dir: &Path) -> Result<u32, PersistenceError> {
+    let path = data_dir.join(FORMAT_VERSION_FILE_NAME);
+    match fs::read_to_string(&path) {
+        Ok(contents) => contents.trim().parse().map_err(|_| {
+            PersistenceError::UnsupportedFormat(format!(
+                "{path} does not hold a version number",
+                path = path.display()
+            ))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let legacy = data_dir.is_dir()
+                && (data_dir.join(ARCHIVE_DIR_NAME).is_dir()
+                    || fs::read_dir(data_dir)?
+                        .map(|entry| entry.map(|e| is_date_dir(&e.path())))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .contains(&true));
+            Ok(if legacy { 1 } else { FORMAT_VERSION })
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Migrates `data_dir` to [`FORMAT_VERSION`], copying it aside first if `backup`
+///
+/// With `dry_run` nothing is written, and each step reports what it would change in the
+/// directory as it is now.
+pub fn migrate(
+    data_dir: &Path,
+    dry_run: bool,
+    backup: bool,
+) -> Result<MigrationReport, PersistenceError> {
+    let from = format_version(data_dir)?;
+    if from > FORMAT_VERSION {
+        return Err(PersistenceError::UnsupportedFormat(format!(
+            "{path} has format {from}, newer than the supported {FORMAT_VERSION}",
+            path = data_dir.display()
+        )));
+    }
+
+    let mut report = MigrationReport {
+        from,
+        to: FORMAT_VERSION,
+        dry_run,
+        ..Default::default()
+    };
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > from).collect();
+
+    if dry_run {
+        for migration in pending {
+            report.steps.push(MigrationStep {
+                version: migration.version,
+                description: migration.description,
+                changes: (migration.apply)(data_dir, true)?,
+            });
+        }
+        return Ok(report);
+    }
+
+    if backup && !pending.is_empty() {
+        let backup_dir = backup_path(data_dir, from)?;
+        copy_dir(data_dir, &backup_dir)?;
+        info!(
+            "Backed up {path} to {backup}",
+            path = data_dir.display(),
+            backup = backup_dir.display()
+        );
+        report.backup = Some(backup_dir);
+    }
+
+    fs::create_dir_all(data_dir)?;
+    for migration in pending {
+        info!(
+            "Migrating {path} to format {version}: {description}",
+            path = data_dir.display(),
+            version = migration.version,
+            description = migration.description
+        );
+        let changes = (migration.apply)(data_dir, false)?;
+        write_format_version(data_dir, migration.version)?;
+        report.steps.push(MigrationStep {
+            version: migration.version,
+            description: migration.description,
+            changes,
+        });
+    }
+    // Also records the version of a new directory, which needs no migrations
+    write_format_version(data_dir, FORMAT_VERSION)?;
+
+    Ok(report)
+}
+
+fn write_format_version(data_dir: &Path, version: u32) -> Result<(), PersistenceError> {
+    fs::write(
+        data_dir.join(FORMAT_VERSION_FILE_NAME),
+        format!("{version}\n"),
+    )?;
+    Ok(())
+}
+
+/// `data_dir` with `-backup-v<from>` appended, next to it
+fn backup_path(data_dir: &Path, from: u32) -> Result<PathBuf, PersistenceError> {
+    let name = data_dir.file_name().ok_or_else(|| {
+        PersistenceError::InvalidPath(format!(
+            "{path} has no name to derive a backup from",
+            path = data_dir.display()
+        ))
+    })?;
+    let backup = data_dir.with_file_name(format!(
+        "{name}-backup-v{from}",
+        name = name.to_string_lossy()
+    ));
+    if backup.exists() {
+        return Err(PersistenceError::InvalidPath(format!(
+            "backup {path} already exists",
+            path = backup.display()
+        )));
+    }
+    Ok(backup)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn date_dirs(data_dir: &Path) -> Result<Vec<(NaiveDate, PathBuf)>, PersistenceError> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        let date = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|name| NaiveDate::parse_from_str(name, "%Y-%m-%d").ok());
+        if let Some(date) = date.filter(|_| path.is_dir()) {
+            dirs.push((date, path));
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn json_files(dir: &Path) -> Result<Vec<PathBuf>, PersistenceError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("json") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Version 2: moves each snapshot to the directory of its UTC date
+///
+/// Unreadable snapshots are left for `fsck`, and a snapshot is only moved onto an identical one.
+fn move_to_utc_dirs(data_dir: &Path, dry_run: bool) -> Result<usize, PersistenceError> {
+    let mut files = Vec::new();
+    for (_, dir) in date_dirs(data_dir)? {
+        files.extend(json_files(&dir)?);
+    }
+
+    let mut moved = 0;
+    for path in files {
+        let Ok(snapshot) = read_snapshot_file(&path) else {
+            continue;
+        };
+        let expected = snapshot_path(data_dir, &snapshot);
+        if expected == path {
+            continue;
+        }
+        match relocate(&path, &expected, !dry_run)?.problem {
+            Problem::Conflict { expected } => warn!(
+                "Left {path} in place, {expected} holds a different snapshot",
+                path = path.display(),
+                expected = expected.display()
+            ),
+            _ => moved += 1,
+        }
+    }
+
+    if !dry_run {
+        remove_empty_date_dirs(data_dir)?;
+    }
+    Ok(moved)
+}
+
+/// Versions 3 and 4: readers must accept packs and archives, which only commands write
+fn no_changes(_data_dir: &Path, _dry_run: bool) -> Result<usize, PersistenceError> {
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::SnapshotStore;
+    use bitcoin_augur::MempoolSnapshot;
+    use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn snapshot(block_height: u32, timestamp: DateTime<Utc>) -> MempoolSnapshot {
+        MempoolSnapshot::new(
+            block_height,
+            timestamp,
+            BTreeMap::from([(100, 4_000), (200, 8_000)]),
+        )
+    }
+
+    /// Writes `snapshot` as JSON into the date directory `dir_name`
+    fn write_legacy(data_dir: &Path, dir_name: &str, snapshot: &MempoolSnapshot) -> PathBuf {
+        let dir = data_dir.join(dir_name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!(
+            "{height}_{ts}.json",
+            height = snapshot.block_height,
+            ts = snapshot.timestamp.timestamp()
+        ));
+        fs::write(&path, serde_json::to_string(snapshot).unwrap()).unwrap();
+        path
+    }
+
+    /// A version 1 store with one snapshot filed under the next local date
+    fn legacy_store(data_dir: &Path) -> Vec<MempoolSnapshot> {
+        let first = Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+        let late = Utc.with_ymd_and_hms(2025, 1, 15, 23, 30, 0).unwrap();
+        let snapshots = vec![snapshot(850_000, first), snapshot(850_001, late)];
+        write_legacy(data_dir, "2025-01-15", &snapshots[0]);
+        write_legacy(data_dir, "2025-01-16", &snapshots[1]);
+        snapshots
+    }
+
+    #[test]
+    fn test_new_directory_gets_current_version() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new()?;
+        let data_dir = temp_dir.path().join("data");
+
+        let report = migrate(&data_dir, false, true)?;
+        assert!(report.steps.is_empty());
+        assert!(report.backup.is_none());
+        assert_eq!(format_version(&data_dir)?, FORMAT_VERSION);
+        assert_eq!(
+            fs::read_to_string(data_dir.join(FORMAT_VERSION_FILE_NAME))?,
+            format!("{FORMAT_VERSION}\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrates_legacy_store() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new()?;
+        let data_dir = temp_dir.path().join("data");
+        let snapshots = legacy_store(&data_dir);
+        assert_eq!(format_version(&data_dir)?, 1);
+
+        let report = migrate(&data_dir, false, false)?;
+        assert_eq!(report.from, 1);
+        assert_eq!(
+            report
+                .steps
+                .iter()
+                .map(|s| (s.version, s.changes))
+                .collect::<Vec<_>>(),
+            vec![(2, 1), (3, 0), (4, 0)]
+        );
+        assert_eq!(format_version(&data_dir)?, FORMAT_VERSION);
+        assert!(!data_dir.join("2025-01-16").exists());
+        // Past days are left for the pack and archive commands
+        assert_eq!(json_files(&data_dir.join("2025-01-15"))?.len(), 2);
+        assert!(!data_dir.join(ARCHIVE_DIR_NAME).exists());
+
+        let store = SnapshotStore::new(&data_dir)?;
+        let start = Utc.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap();
+        let loaded = store.get_snapshots(
+            start.with_timezone(&Local) - Duration::days(1),
+            (start + Duration::days(2)).with_timezone(&Local),
+        )?;
+        let key = |s: &MempoolSnapshot| (s.block_height, s.timestamp, s.bucketed_weights.clone());
+        assert_eq!(
+            loaded.iter().map(key).collect::<Vec<_>>(),
+            snapshots.iter().map(key).collect::<Vec<_>>()
+        );
+
+        // Nothing is left to do
+        assert!(migrate(&data_dir, false, false)?.steps.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_changes_nothing() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new()?;
+        let data_dir = temp_dir.path().join("data");
+        legacy_store(&data_dir);
+
+        let report = migrate(&data_dir, true, true)?;
+        assert!(report.dry_run);
+        assert!(report.backup.is_none());
+        assert_eq!(
+            report.steps.iter().map(|s| s.changes).collect::<Vec<_>>(),
+            vec![1, 0, 0]
+        );
+        assert!(!data_dir.join(FORMAT_VERSION_FILE_NAME).exists());
+        assert!(!temp_dir.path().join("data-backup-v1").exists());
+        assert_eq!(json_files(&data_dir.join("2025-01-16"))?.len(), 1);
+        assert_eq!(format_version(&data_dir)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_keeps_original_layout() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new()?;
+        let data_dir = temp_dir.path().join("data");
+        legacy_store(&data_dir);
+        let original = fs::read_to_string(&json_files(&data_dir.join("2025-01-16"))?[0])?;
+
+        let report = migrate(&data_dir, false, true)?;
+        let backup = temp_dir.path().join("data-backup-v1");
+        assert_eq!(report.backup.as_deref(), Some(backup.as_path()));
+        let backed_up = json_files(&backup.join("2025-01-16"))?;
+        assert_eq!(fs::read_to_string(&backed_up[0])?, original);
+        assert!(!backup.join(FORMAT_VERSION_FILE_NAME).exists());
+
+        // A second backup of the same version is refused rather than overwritten
+        fs::remove_file(data_dir.join(FORMAT_VERSION_FILE_NAME))?;
+        assert!(matches!(
+            migrate(&data_dir, false, true),
+            Err(PersistenceError::InvalidPath(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_format_is_refused() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new()?;
+        write_format_version(temp_dir.path(), FORMAT_VERSION + 1)?;
+
+        assert!(matches!(
+            migrate(temp_dir.path(), false, false),
+            Err(PersistenceError::UnsupportedFormat(_))
+        ));
+        fs::write(temp_dir.path().join(FORMAT_VERSION_FILE_NAME), "three")?;
+        assert!(matches!(
+            format_version(temp_dir.path()),
+            Err(PersistenceError::UnsupportedFormat(_))
+        ));
+        Ok(())
+    }
+}
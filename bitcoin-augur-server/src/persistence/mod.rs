@@ -1,5 +1,30 @@
 //! Persistence layer for storing mempool snapshots
 
+mod estimate_cache;
+mod fsck;
+mod migrations;
+mod object_storage;
+mod run_manifest;
+mod snapshot_archive;
+mod snapshot_pack;
 mod snapshot_store;
 
-pub use snapshot_store::{PersistenceError, SnapshotStore};
+pub use estimate_cache::{fingerprint, CachedEstimate};
+pub use fsck::fsck;
+#[allow(unused_imports)]
+pub use fsck::{Finding, FsckReport, Problem};
+pub use migrations::migrate;
+#[allow(unused_imports)]
+pub use migrations::{
+    format_version, MigrationReport, MigrationStep, FORMAT_VERSION, FORMAT_VERSION_FILE_NAME,
+};
+#[allow(unused_imports)]
+pub use object_storage::SnapshotStorage;
+pub use object_storage::{S3Config, S3Storage};
+#[allow(unused_imports)]
+pub use run_manifest::RUN_MANIFEST_FILE_NAME;
+pub use run_manifest::{begin_run, complete_run, config_hash, CrashReport, RunManifest};
+pub use snapshot_archive::month_of;
+pub use snapshot_store::{
+    LoadProgress, PersistenceError, SnapshotStore, LEADER_LEASE_FILE_NAME, LEADER_LOCK_FILE_NAME,
+};
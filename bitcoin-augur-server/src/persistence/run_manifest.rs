@@ -0,0 +1,182 @@
+//! Manifest of the server run using the data directory, for postmortems of runs that crashed
+//!
+//! On startup the server writes a manifest with its version, a hash of its configuration and
+//! the start time, and marks it complete when it shuts down cleanly. A manifest still
+//! incomplete on the next startup means the previous run crashed or was killed, and a
+//! [`CrashReport`] describing it is logged.
+
+use bitcoin_augur::MempoolSnapshot;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+use super::snapshot_store::PersistenceError;
+
+/// Name of the manifest file inside the data directory
+pub const RUN_MANIFEST_FILE_NAME: &str = "run_manifest.json";
+
+/// What a server run recorded about itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// Server version
+    pub version: String,
+    /// Hex SHA-256 of the configuration, from [`config_hash`]
+    pub config_hash: String,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    /// When the run shut down cleanly, or `None` while it runs or if it crashed
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl RunManifest {
+    /// Manifest of the current process, started now
+    pub fn new(config_hash: String) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash,
+            pid: std::process::id(),
+            started_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+}
+
+/// A run that never marked its manifest complete
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    pub run: RunManifest,
+    /// Whether the crashed run used the same configuration as the current one
+    pub same_config: bool,
+    /// Time of the last snapshot written to the data directory
+    pub last_snapshot_time: Option<DateTime<Utc>>,
+    pub last_snapshot_height: Option<u32>,
+    /// Seconds between the crashed run's start and the last snapshot, negative when the run
+    /// wrote none
+    pub last_snapshot_after_secs: Option<i64>,
+}
+
+impl CrashReport {
+    /// Describes the crashed `run`, given the current configuration hash and the last snapshot
+    /// in the data directory
+    pub fn new(run: RunManifest, config_hash: &str, last: Option<&MempoolSnapshot>) -> Self {
+        Self {
+            same_config: run.config_hash == config_hash,
+            last_snapshot_time: last.map(|snapshot| snapshot.timestamp),
+            last_snapshot_height: last.map(|snapshot| snapshot.block_height),
+            last_snapshot_after_secs: last
+                .map(|snapshot| (snapshot.timestamp - run.started_at).num_seconds()),
+            run,
+        }
+    }
+}
+
+/// Hash identifying a configuration, given any stable serialization of it
+pub fn config_hash(config: &str) -> String {
+    Sha256::digest(config.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Records `manifest` as the current run of `data_dir`, returning the previous run if it never
+/// shut down cleanly
+pub fn begin_run(
+    data_dir: &Path,
+    manifest: &RunManifest,
+) -> Result<Option<RunManifest>, PersistenceError> {
+    let path = data_dir.join(RUN_MANIFEST_FILE_NAME);
+    let previous = match fs::read(&path) {
+        Ok(bytes) => match serde_json::from_slice::<RunManifest>(&bytes) {
+            Ok(previous) => Some(previous),
+            Err(e) => {
+                warn!(
+                    "Ignoring unreadable run manifest {path}: {e}",
+                    path = path.display()
+                );
+                None
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    write_manifest(&path, manifest)?;
+    Ok(previous.filter(|previous| previous.completed_at.is_none()))
+}
+
+/// Marks the run recorded in `data_dir` as shut down cleanly
+pub fn complete_run(data_dir: &Path, manifest: &RunManifest) -> Result<(), PersistenceError> {
+    let completed = RunManifest {
+        completed_at: Some(Utc::now()),
+        ..manifest.clone()
+    };
+    write_manifest(&data_dir.join(RUN_MANIFEST_FILE_NAME), &completed)
+}
+
+/// Writes `manifest` to `path`, renaming it into place so a crash never leaves a partial file
+fn write_manifest(path: &Path, manifest: &RunManifest) -> Result<(), PersistenceError> {
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_vec_pretty(manifest)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_incomplete_run_is_reported_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let hash = config_hash("{}");
+
+        // A fresh directory has no previous run
+        let first = RunManifest::new(hash.clone());
+        assert_eq!(begin_run(dir, &first).unwrap(), None);
+
+        // The first run crashed: the second run finds it
+        let second = RunManifest::new(hash.clone());
+        assert_eq!(begin_run(dir, &second).unwrap(), Some(first));
+
+        // The second run shut down cleanly: the third finds nothing
+        complete_run(dir, &second).unwrap();
+        assert_eq!(begin_run(dir, &RunManifest::new(hash)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unreadable_manifest_is_replaced() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(RUN_MANIFEST_FILE_NAME);
+        fs::write(&path, "not json").unwrap();
+
+        let manifest = RunManifest::new(config_hash("{}"));
+        assert_eq!(begin_run(temp_dir.path(), &manifest).unwrap(), None);
+        let written: RunManifest = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written, manifest);
+    }
+
+    #[test]
+    fn test_crash_report() {
+        let run = RunManifest::new(config_hash("old"));
+        let during =
+            MempoolSnapshot::empty(850_000, run.started_at + chrono::Duration::seconds(90));
+        let report = CrashReport::new(run.clone(), &config_hash("new"), Some(&during));
+        assert!(!report.same_config);
+        assert_eq!(report.last_snapshot_height, Some(850_000));
+        assert_eq!(report.last_snapshot_after_secs, Some(90));
+
+        // A crashed run that wrote no snapshot of its own
+        let before =
+            MempoolSnapshot::empty(850_000, run.started_at - chrono::Duration::seconds(90));
+        let report = CrashReport::new(run.clone(), &config_hash("old"), Some(&before));
+        assert!(report.same_config);
+        assert_eq!(report.last_snapshot_after_secs, Some(-90));
+
+        let report = CrashReport::new(run, &config_hash("old"), None);
+        assert_eq!(report.last_snapshot_time, None);
+    }
+}
@@ -0,0 +1,83 @@
+//! Archive tier of the snapshot store: one pack per month
+//!
+//! Recent days stay in their date directories as JSON files or a day's pack. After years of
+//! operation those directories add up to thousands of entries, so months that are over are
+//! merged into a single pack each under `data_dir/archive/YYYY-MM.pack`. Archives use the pack
+//! format, so a range query maps the months it spans and decodes only the snapshots it returns.
+
+use chrono::{Datelike, NaiveDate};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::snapshot_store::PersistenceError;
+
+/// Directory in the data directory that holds the monthly archives
+pub const ARCHIVE_DIR_NAME: &str = "archive";
+
+/// First day of the month holding `date`
+pub fn month_of(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// First day of the month after the one holding `date`
+pub fn next_month(date: NaiveDate) -> Option<NaiveDate> {
+    let month = month_of(date);
+    match month.month() {
+        12 => NaiveDate::from_ymd_opt(month.year() + 1, 1, 1),
+        m => NaiveDate::from_ymd_opt(month.year(), m + 1, 1),
+    }
+}
+
+/// Where the archive of the month holding `date` is kept
+pub fn archive_path(data_dir: &Path, date: NaiveDate) -> PathBuf {
+    data_dir
+        .join(ARCHIVE_DIR_NAME)
+        .join(format!("{}.pack", date.format("%Y-%m")))
+}
+
+/// Month of an archive file named `YYYY-MM.pack`
+pub fn archive_month(path: &Path) -> Option<NaiveDate> {
+    if path.extension().and_then(|s| s.to_str()) != Some("pack") {
+        return None;
+    }
+    let name = path.file_stem()?.to_str()?;
+    NaiveDate::parse_from_str(&format!("{name}-01"), "%Y-%m-%d").ok()
+}
+
+/// Every monthly archive in `data_dir`, oldest first
+pub fn archives(data_dir: &Path) -> Result<Vec<(NaiveDate, PathBuf)>, PersistenceError> {
+    let dir = data_dir.join(ARCHIVE_DIR_NAME);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(month) = archive_month(&path).filter(|_| path.is_file()) {
+            archives.push((month, path));
+        }
+    }
+    archives.sort();
+    Ok(archives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_months_and_archive_names() {
+        let date = NaiveDate::from_ymd_opt(2024, 12, 17).unwrap();
+        assert_eq!(
+            month_of(date),
+            NaiveDate::from_ymd_opt(2024, 12, 1).unwrap()
+        );
+        assert_eq!(next_month(date), NaiveDate::from_ymd_opt(2025, 1, 1));
+
+        let path = archive_path(Path::new("data"), date);
+        assert_eq!(path, Path::new("data/archive/2024-12.pack"));
+        assert_eq!(archive_month(&path), Some(month_of(date)));
+        assert_eq!(archive_month(Path::new("2024-12.pack.partial")), None);
+        assert_eq!(archive_month(Path::new("2024-13.pack")), None);
+    }
+}
@@ -0,0 +1,298 @@
+//! Binary pack of one day of snapshots, read through a memory map
+//!
+//! Range queries over JSON files spend most of their time opening and parsing files. A pack
+//! holds a whole date directory in one file: a header, a fixed-size index sorted by time, and
+//! the bucket weights of each snapshot. Opening a pack maps it and checks the header; a snapshot
+//! is only decoded when it is read, so a range query touches just the records it returns.
+//! Bucket values, which only ingested snapshots carry, aged and deferred weights and source
+//! labels are not packed.
+//!
+//! All integers are little-endian:
+//!
+//! ```text
+//! header   MAGIC, version: u32, count: u32
+//! index    count x (unix seconds: i64, nanoseconds: u32, block height: u32, offset: u64)
+//! record   buckets: u32, buckets x (bucket index: i32, weight: u64)
+//! ```
+
+use bitcoin_augur::MempoolSnapshot;
+use chrono::{DateTime, Utc};
+use memmap2::Mmap;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::ops::Range;
+use std::path::Path;
+
+use super::snapshot_store::PersistenceError;
+
+/// Name of the pack file inside a date directory
+pub const PACK_FILE_NAME: &str = "snapshots.pack";
+
+const MAGIC: &[u8; 8] = b"AUGRPACK";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 16;
+const INDEX_ENTRY_LEN: usize = 24;
+const BUCKET_LEN: usize = 12;
+
+/// Writes `snapshots` to a pack at `path`, sorted by timestamp, replacing any existing file
+///
+/// The pack is written next to `path` and renamed into place, so readers never see a partial
+/// file.
+pub fn write_pack(path: &Path, snapshots: &[MempoolSnapshot]) -> Result<(), PersistenceError> {
+    let mut sorted: Vec<&MempoolSnapshot> = snapshots.iter().collect();
+    sorted.sort_by_key(|s| s.timestamp);
+
+    let count = u32::try_from(sorted.len())
+        .map_err(|_| PersistenceError::InvalidPack("too many snapshots".to_string()))?;
+    let mut index = Vec::with_capacity(sorted.len() * INDEX_ENTRY_LEN);
+    let mut records = Vec::new();
+    let records_start = HEADER_LEN + sorted.len() * INDEX_ENTRY_LEN;
+
+    for snapshot in sorted {
+        let offset = (records_start + records.len()) as u64;
+        index.extend_from_slice(&snapshot.timestamp.timestamp().to_le_bytes());
+        index.extend_from_slice(&snapshot.timestamp.timestamp_subsec_nanos().to_le_bytes());
+        index.extend_from_slice(&snapshot.block_height.to_le_bytes());
+        index.extend_from_slice(&offset.to_le_bytes());
+
+        records.extend_from_slice(&(snapshot.bucketed_weights.len() as u32).to_le_bytes());
+        for (bucket, weight) in &snapshot.bucketed_weights {
+            records.extend_from_slice(&bucket.to_le_bytes());
+            records.extend_from_slice(&weight.to_le_bytes());
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(records_start + records.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&count.to_le_bytes());
+    bytes.extend_from_slice(&index);
+    bytes.extend_from_slice(&records);
+
+    let partial = path.with_extension("pack.partial");
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// A memory-mapped pack whose snapshots are decoded on access
+pub struct SnapshotPack {
+    map: Mmap,
+    len: usize,
+}
+
+impl SnapshotPack {
+    /// Maps the pack at `path`, checking its header and index size
+    pub fn open(path: &Path) -> Result<Self, PersistenceError> {
+        let file = File::open(path)?;
+        // SAFETY: the store only replaces packs by renaming a new file over them, which leaves
+        // this mapping on the old inode, and never modifies a pack in place.
+        let map = unsafe { Mmap::map(&file)? };
+
+        if map.len() < HEADER_LEN || &map[..8] != MAGIC {
+            return Err(PersistenceError::InvalidPack(
+                "missing pack header".to_string(),
+            ));
+        }
+        let version = read_u32(&map, 8)?;
+        if version != VERSION {
+            return Err(PersistenceError::InvalidPack(format!(
+                "unsupported pack version {version}"
+            )));
+        }
+        let len = read_u32(&map, 12)? as usize;
+        if map.len() < HEADER_LEN + len * INDEX_ENTRY_LEN {
+            return Err(PersistenceError::InvalidPack("truncated index".to_string()));
+        }
+
+        Ok(Self { map, len })
+    }
+
+    /// Number of snapshots in the pack
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the pack holds no snapshots
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Timestamp of the snapshot at `index`, read from the index alone
+    pub fn timestamp(&self, index: usize) -> Result<DateTime<Utc>, PersistenceError> {
+        let entry = self.entry_start(index);
+        let seconds = read_i64(&self.map, entry)?;
+        let nanos = read_u32(&self.map, entry + 8)?;
+        DateTime::from_timestamp(seconds, nanos).ok_or(PersistenceError::InvalidTimestamp(seconds))
+    }
+
+    /// Decodes the snapshot at `index`
+    pub fn get(&self, index: usize) -> Result<MempoolSnapshot, PersistenceError> {
+        if index >= self.len {
+            return Err(PersistenceError::InvalidPack(format!(
+                "no snapshot {index} in a pack of {len}",
+                len = self.len
+            )));
+        }
+        let entry = self.entry_start(index);
+        let timestamp = self.timestamp(index)?;
+        let block_height = read_u32(&self.map, entry + 12)?;
+        let offset = usize::try_from(read_u64(&self.map, entry + 16)?)
+            .map_err(|_| PersistenceError::InvalidPack("record offset overflows".to_string()))?;
+
+        let buckets = read_u32(&self.map, offset)? as usize;
+        let mut bucketed_weights = BTreeMap::new();
+        for i in 0..buckets {
+            let at = offset + 4 + i * BUCKET_LEN;
+            bucketed_weights.insert(read_i32(&self.map, at)?, read_u64(&self.map, at + 4)?);
+        }
+
+        Ok(MempoolSnapshot::new(
+            block_height,
+            timestamp,
+            bucketed_weights,
+        ))
+    }
+
+    /// Decodes the newest snapshot, if the pack holds any
+    pub fn last(&self) -> Result<Option<MempoolSnapshot>, PersistenceError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        self.get(self.len - 1).map(Some)
+    }
+
+    /// Indices of the snapshots taken from `start` to `end`, inclusive
+    pub fn range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Range<usize>, PersistenceError> {
+        let first = self.partition_point(|t| t < start)?;
+        let last = self.partition_point(|t| t <= end)?;
+        Ok(first..last.max(first))
+    }
+
+    /// Decodes every snapshot, in timestamp order
+    pub fn snapshots(&self) -> Result<Vec<MempoolSnapshot>, PersistenceError> {
+        (0..self.len).map(|i| self.get(i)).collect()
+    }
+
+    fn entry_start(&self, index: usize) -> usize {
+        HEADER_LEN + index * INDEX_ENTRY_LEN
+    }
+
+    /// First index whose timestamp fails `pred`, by binary search over the sorted index
+    fn partition_point(
+        &self,
+        pred: impl Fn(DateTime<Utc>) -> bool,
+    ) -> Result<usize, PersistenceError> {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if pred(self.timestamp(mid)?) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+}
+
+fn bytes<const N: usize>(map: &[u8], at: usize) -> Result<[u8; N], PersistenceError> {
+    at.checked_add(N)
+        .and_then(|end| map.get(at..end))
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or_else(|| PersistenceError::InvalidPack(format!("record at byte {at} is truncated")))
+}
+
+fn read_u32(map: &[u8], at: usize) -> Result<u32, PersistenceError> {
+    bytes(map, at).map(u32::from_le_bytes)
+}
+
+fn read_i32(map: &[u8], at: usize) -> Result<i32, PersistenceError> {
+    bytes(map, at).map(i32::from_le_bytes)
+}
+
+fn read_u64(map: &[u8], at: usize) -> Result<u64, PersistenceError> {
+    bytes(map, at).map(u64::from_le_bytes)
+}
+
+fn read_i64(map: &[u8], at: usize) -> Result<i64, PersistenceError> {
+    bytes(map, at).map(i64::from_le_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn snapshot(height: u32, minute: u32) -> MempoolSnapshot {
+        let timestamp = Utc.with_ymd_and_hms(2025, 1, 20, 12, minute, 0).unwrap()
+            + chrono::Duration::nanoseconds(123);
+        let weights = BTreeMap::from([(-5, 1), (100, 4_000), (i32::MAX, u64::MAX)]);
+        MempoolSnapshot::new(height, timestamp, weights)
+    }
+
+    #[test]
+    fn test_round_trip_sorted_by_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(PACK_FILE_NAME);
+        let snapshots = vec![snapshot(102, 20), snapshot(100, 0), snapshot(101, 10)];
+        write_pack(&path, &snapshots).unwrap();
+
+        let pack = SnapshotPack::open(&path).unwrap();
+        assert_eq!(pack.len(), 3);
+        let decoded = pack.snapshots().unwrap();
+        let heights: Vec<u32> = decoded.iter().map(|s| s.block_height).collect();
+        assert_eq!(heights, vec![100, 101, 102]);
+        assert_eq!(decoded[1].timestamp, snapshots[2].timestamp);
+        assert_eq!(decoded[1].bucketed_weights, snapshots[2].bucketed_weights);
+        assert!(pack.get(3).is_err());
+    }
+
+    #[test]
+    fn test_range_selects_by_timestamp() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(PACK_FILE_NAME);
+        let snapshots: Vec<_> = (0..6).map(|i| snapshot(100 + i, i * 10)).collect();
+        write_pack(&path, &snapshots).unwrap();
+        let pack = SnapshotPack::open(&path).unwrap();
+
+        let at = |minute| Utc.with_ymd_and_hms(2025, 1, 20, 12, minute, 0).unwrap();
+        assert_eq!(pack.range(at(5), at(30)).unwrap(), 1..3);
+        assert_eq!(pack.range(at(0), at(59)).unwrap(), 0..6);
+        assert!(pack.range(at(31), at(35)).unwrap().is_empty());
+        assert!(pack.range(at(40), at(20)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_damaged_packs() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(PACK_FILE_NAME);
+
+        fs::write(&path, b"not a pack").unwrap();
+        assert!(matches!(
+            SnapshotPack::open(&path),
+            Err(PersistenceError::InvalidPack(_))
+        ));
+
+        write_pack(&path, &[snapshot(100, 0)]).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 4);
+        fs::write(&path, bytes).unwrap();
+        let pack = SnapshotPack::open(&path).unwrap();
+        assert!(matches!(pack.get(0), Err(PersistenceError::InvalidPack(_))));
+
+        // A record offset at the end of the address space is corrupt, not an overflow
+        write_pack(&path, &[snapshot(100, 0)]).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        let offset = HEADER_LEN + 16;
+        bytes[offset..offset + 8].copy_from_slice(&(u64::MAX - 1).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        let pack = SnapshotPack::open(&path).unwrap();
+        assert!(matches!(pack.last(), Err(PersistenceError::InvalidPack(_))));
+    }
+}
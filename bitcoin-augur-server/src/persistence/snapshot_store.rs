@@ -1,9 +1,24 @@
 use bitcoin_augur::MempoolSnapshot;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tracing::{debug, error, info};
+use tracing::{debug, info, warn};
+
+use super::estimate_cache::{
+    read_estimate_cache, write_estimate_cache, CachedEstimate, ESTIMATE_CACHE_FILE_NAME,
+};
+use super::object_storage::SnapshotStorage;
+use super::snapshot_archive::{archive_path, archives, month_of, next_month, ARCHIVE_DIR_NAME};
+use super::snapshot_pack::{write_pack, SnapshotPack, PACK_FILE_NAME};
 
 /// Persistence layer errors
 #[derive(Error, Debug)]
@@ -19,11 +34,214 @@ pub enum PersistenceError {
 
     #[error("Invalid timestamp: {0}")]
     InvalidTimestamp(i64),
+
+    #[error("Invalid snapshot pack: {0}")]
+    InvalidPack(String),
+
+    #[error("Snapshot {0} is the same as a snapshot that is not stored")]
+    MissingSource(String),
+
+    #[error("Unsupported data directory format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Object storage error: {0}")]
+    Remote(String),
+}
+
+/// Most threads reading snapshot files at once
+pub const MAX_LOAD_THREADS: usize = 8;
+
+/// Name of the collection cycle log in the data directory; rotated logs add a suffix
+pub const CYCLE_LOG_FILE_NAME: &str = "cycles.jsonl";
+
+/// Name of the file of blocks whose contents were fetched after they were mined
+pub const REALIZED_BLOCKS_FILE_NAME: &str = "realized_blocks.jsonl";
+
+/// Name of the collector lease in the data directory
+pub const LEADER_LEASE_FILE_NAME: &str = "leader.json";
+
+/// Name of the file locked while the collector lease is read and written
+pub const LEADER_LOCK_FILE_NAME: &str = "leader.lock";
+
+/// How far [`SnapshotStore::stream_snapshots`] has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    /// Snapshots read so far
+    pub loaded: usize,
+    /// Snapshots in the requested range
+    pub total: usize,
+}
+
+/// Where a snapshot is stored: `data_dir/YYYY-MM-DD/blockheight_timestamp.json`
+pub(super) fn snapshot_path(data_dir: &Path, snapshot: &MempoolSnapshot) -> PathBuf {
+    let date_str = snapshot.timestamp.format("%Y-%m-%d").to_string();
+    data_dir.join(date_str).join(snapshot_file_name(snapshot))
+}
+
+/// Name of a snapshot's file in its date directory
+fn snapshot_file_name(snapshot: &MempoolSnapshot) -> String {
+    format!(
+        "{}_{}.json",
+        snapshot.block_height,
+        snapshot.timestamp.timestamp()
+    )
+}
+
+/// Stored in place of a snapshot identical to an earlier one of the same day, when
+/// deduplicating
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SnapshotMarker {
+    block_height: u32,
+    timestamp: DateTime<Utc>,
+    /// Timestamp of the snapshot with the same height and buckets
+    same_as: DateTime<Utc>,
+}
+
+/// Reads the snapshot file at `path`, taking the buckets of a marker from the snapshot it
+/// names in the same date directory
+pub(super) fn read_snapshot_file(path: &Path) -> Result<MempoolSnapshot, PersistenceError> {
+    let content = fs::read_to_string(path)?;
+    // A full snapshot fails to parse as a marker at its first bucket
+    let Ok(marker) = serde_json::from_str::<SnapshotMarker>(&content) else {
+        return Ok(serde_json::from_str(&content)?);
+    };
+
+    let data_dir = path
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""));
+    let source = find_snapshot(data_dir, marker.block_height, marker.same_as)?
+        .ok_or_else(|| PersistenceError::MissingSource(path.display().to_string()))?;
+    Ok(MempoolSnapshot {
+        block_height: marker.block_height,
+        timestamp: marker.timestamp,
+        ..source
+    })
+}
+
+/// The snapshot written in full at `timestamp`, from its JSON file or its date's pack
+fn find_snapshot(
+    data_dir: &Path,
+    block_height: u32,
+    timestamp: DateTime<Utc>,
+) -> Result<Option<MempoolSnapshot>, PersistenceError> {
+    let path = snapshot_path(data_dir, &MempoolSnapshot::empty(block_height, timestamp));
+    if let Ok(content) = fs::read_to_string(&path) {
+        if let Ok(snapshot) = serde_json::from_str::<MempoolSnapshot>(&content) {
+            if snapshot.timestamp == timestamp {
+                return Ok(Some(snapshot));
+            }
+        }
+    }
+
+    let pack_path = path.with_file_name(PACK_FILE_NAME);
+    if pack_path.is_file() {
+        let pack = SnapshotPack::open(&pack_path)?;
+        for index in pack.range(timestamp, timestamp)? {
+            let snapshot = pack.get(index)?;
+            if snapshot.block_height == block_height {
+                return Ok(Some(snapshot));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Hash of a snapshot's height, bucket weights, bucket values, aged and deferred weights and
+/// source
+fn content_hash(snapshot: &MempoolSnapshot) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(snapshot.block_height.to_le_bytes());
+    for (bucket, weight) in &snapshot.bucketed_weights {
+        hasher.update(bucket.to_le_bytes());
+        hasher.update(weight.to_le_bytes());
+    }
+    // Snapshots without values hash as they did before values existed; the tag keeps value
+    // entries from reading as more weights
+    if !snapshot.bucketed_values.is_empty() {
+        hasher.update(b"values");
+    }
+    for (bucket, value) in &snapshot.bucketed_values {
+        hasher.update(bucket.to_le_bytes());
+        hasher.update(value.to_le_bytes());
+    }
+    if !snapshot.bucketed_aged_weights.is_empty() {
+        hasher.update(b"aged");
+    }
+    for (bucket, weight) in &snapshot.bucketed_aged_weights {
+        hasher.update(bucket.to_le_bytes());
+        hasher.update(weight.to_le_bytes());
+    }
+    if !snapshot.bucketed_deferred_weights.is_empty() {
+        hasher.update(b"deferred");
+    }
+    for (bucket, weight) in &snapshot.bucketed_deferred_weights {
+        hasher.update(bucket.to_le_bytes());
+        hasher.update(weight.to_le_bytes());
+    }
+    if let Some(source) = &snapshot.source {
+        hasher.update(b"source");
+        hasher.update(source.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// The last snapshot written in full, which identical snapshots are stored as markers of
+#[derive(Debug, Clone, Copy)]
+struct WrittenSnapshot {
+    hash: [u8; 32],
+    timestamp: DateTime<Utc>,
+}
+
+/// Object storage snapshots are written through to and missing days are fetched from
+struct RemoteTier {
+    storage: Arc<dyn SnapshotStorage>,
+    /// Queue of the uploader thread, so saving a snapshot never waits on the network
+    uploads: mpsc::Sender<Upload>,
+    /// Past days this store already fetched
+    fetched: Mutex<HashSet<NaiveDate>>,
+    /// Shortest time between two listings of today
+    list_interval: Duration,
+    /// When today was last listed
+    listed_today: Mutex<Option<Instant>>,
+}
+
+/// Work for the uploader thread of a remote tier
+enum Upload {
+    Put {
+        key: String,
+        body: Vec<u8>,
+    },
+    /// Answers once every upload queued before it is done
+    #[cfg(test)]
+    Flush(mpsc::Sender<()>),
+}
+
+/// Uploads queued snapshots to `storage` until the store is dropped
+fn upload_snapshots(storage: &dyn SnapshotStorage, uploads: mpsc::Receiver<Upload>) {
+    for upload in uploads {
+        match upload {
+            // The local file is enough to serve the snapshot, so a failed upload is only logged
+            Upload::Put { key, body } => {
+                if let Err(e) = storage.put(&key, &body) {
+                    warn!("Failed to upload snapshot {key}: {e}");
+                }
+            }
+            #[cfg(test)]
+            Upload::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
 }
 
 /// Manages persistent storage of mempool snapshots
 pub struct SnapshotStore {
     data_dir: PathBuf,
+    /// Last snapshot written in full, when deduplicating
+    dedup: Option<Mutex<Option<WrittenSnapshot>>>,
+    remote: Option<RemoteTier>,
 }
 
 impl SnapshotStore {
@@ -39,30 +257,120 @@ impl SnapshotStore {
             path = data_dir.display()
         );
 
-        Ok(Self { data_dir })
+        Ok(Self {
+            data_dir,
+            dedup: None,
+            remote: None,
+        })
+    }
+
+    /// Stores a snapshot identical to the last one written in full on the same day as a
+    /// marker naming it
+    pub fn with_dedup(mut self) -> Self {
+        self.dedup = Some(Mutex::new(None));
+        self
+    }
+
+    /// Writes every snapshot through to `storage` and fetches the snapshots of days missing
+    /// from the data directory from it, keeping the data directory as a local cache
+    ///
+    /// Uploads run on a background thread, in the order snapshots are saved; a snapshot still
+    /// queued when the process exits stays local. Today is listed at most once per
+    /// `list_interval`, normally the collection interval, however often snapshots are read.
+    ///
+    /// # Errors
+    /// If the upload thread cannot be started
+    pub fn with_remote(
+        mut self,
+        storage: Arc<dyn SnapshotStorage>,
+        list_interval: Duration,
+    ) -> Result<Self, PersistenceError> {
+        let (uploads, queue) = mpsc::channel();
+        let uploader = storage.clone();
+        thread::Builder::new()
+            .name("snapshot-upload".to_string())
+            .spawn(move || upload_snapshots(&*uploader, queue))?;
+        self.remote = Some(RemoteTier {
+            storage,
+            uploads,
+            fetched: Mutex::new(HashSet::new()),
+            list_interval,
+            listed_today: Mutex::new(None),
+        });
+        Ok(self)
+    }
+
+    /// Waits for every snapshot saved so far to be uploaded
+    #[cfg(test)]
+    fn wait_for_uploads(&self) {
+        if let Some(remote) = &self.remote {
+            let (done, finished) = mpsc::channel();
+            if remote.uploads.send(Upload::Flush(done)).is_ok() {
+                let _ = finished.recv();
+            }
+        }
     }
 
     /// Saves a mempool snapshot to disk
     pub fn save_snapshot(&self, snapshot: &MempoolSnapshot) -> Result<(), PersistenceError> {
-        // Create directory structure: data/YYYY-MM-DD/
-        let date_str = snapshot.timestamp.format("%Y-%m-%d").to_string();
-        let date_dir = self.data_dir.join(&date_str);
-        fs::create_dir_all(&date_dir)?;
+        let file_path = snapshot_path(&self.data_dir, snapshot);
+        if let Some(date_dir) = file_path.parent() {
+            fs::create_dir_all(date_dir)?;
+        }
 
-        // Create filename: blockheight_timestamp.json
-        let filename = format!(
-            "{}_{}.json",
-            snapshot.block_height,
-            snapshot.timestamp.timestamp()
-        );
-        let file_path = date_dir.join(filename);
+        // Serialize and save snapshot, or a marker if it repeats the previous one
+        let (json, written) = match &self.dedup {
+            Some(previous) => {
+                let hash = content_hash(snapshot);
+                let previous = *previous.lock().unwrap_or_else(PoisonError::into_inner);
+                match previous {
+                    Some(written)
+                        if written.hash == hash
+                            && written.timestamp.date_naive()
+                                == snapshot.timestamp.date_naive() =>
+                    {
+                        let marker = SnapshotMarker {
+                            block_height: snapshot.block_height,
+                            timestamp: snapshot.timestamp,
+                            same_as: written.timestamp,
+                        };
+                        (serde_json::to_string_pretty(&marker)?, None)
+                    }
+                    _ => (
+                        serde_json::to_string_pretty(snapshot)?,
+                        Some(WrittenSnapshot {
+                            hash,
+                            timestamp: snapshot.timestamp,
+                        }),
+                    ),
+                }
+            }
+            None => (serde_json::to_string_pretty(snapshot)?, None),
+        };
+        fs::write(&file_path, &json)?;
 
-        // Serialize and save snapshot
-        let json = serde_json::to_string_pretty(snapshot)?;
-        fs::write(&file_path, json)?;
+        // Only a snapshot that reached the disk can be pointed at by later markers
+        if let (Some(previous), Some(written)) = (&self.dedup, written) {
+            *previous.lock().unwrap_or_else(PoisonError::into_inner) = Some(written);
+        }
 
         debug!("Saved snapshot to: {path}", path = file_path.display());
 
+        if let Some(remote) = &self.remote {
+            let key = format!(
+                "{date}/{name}",
+                date = snapshot.timestamp.format("%Y-%m-%d"),
+                name = snapshot_file_name(snapshot)
+            );
+            let upload = Upload::Put {
+                key,
+                body: json.into_bytes(),
+            };
+            if remote.uploads.send(upload).is_err() {
+                warn!("Snapshot upload thread stopped, keeping the snapshot local");
+            }
+        }
+
         Ok(())
     }
 
@@ -73,6 +381,54 @@ impl SnapshotStore {
         end: DateTime<Local>,
     ) -> Result<Vec<MempoolSnapshot>, PersistenceError> {
         let mut snapshots = Vec::new();
+        self.stream_snapshots(start, end, |snapshot, progress| {
+            if snapshots.is_empty() {
+                snapshots.reserve_exact(progress.total);
+            }
+            snapshots.push(snapshot);
+        })?;
+
+        // Sort snapshots by timestamp
+        snapshots.sort_by_key(|s| s.timestamp);
+
+        debug!(
+            "Retrieved {} snapshots from {} to {}",
+            snapshots.len(),
+            start.format("%Y-%m-%d %H:%M:%S"),
+            end.format("%Y-%m-%d %H:%M:%S")
+        );
+
+        Ok(snapshots)
+    }
+
+    /// Reads the snapshots from `start` to `end`, handing each to `on_snapshot` with the
+    /// progress so far as soon as it is read
+    ///
+    /// Archived and packed snapshots come first, in timestamp order. JSON files are read on up to
+    /// [`MAX_LOAD_THREADS`] threads and arrive in no particular order, with only a few read
+    /// ahead of `on_snapshot` at any time, so memory use is bounded by what it keeps.
+    pub fn stream_snapshots(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        mut on_snapshot: impl FnMut(MempoolSnapshot, LoadProgress),
+    ) -> Result<LoadProgress, PersistenceError> {
+        let mut packs = Vec::new();
+        let mut files = Vec::new();
+        let (start_utc, end_utc) = (start.with_timezone(&Utc), end.with_timezone(&Utc));
+        self.fetch_remote(start_utc.date_naive(), end_utc.date_naive());
+
+        // Decode only the archived snapshots inside the range, month by month
+        let mut month = Some(month_of(start_utc.date_naive()));
+        while let Some(current) = month.filter(|month| *month <= end_utc.date_naive()) {
+            let path = archive_path(&self.data_dir, current);
+            if path.is_file() {
+                let pack = SnapshotPack::open(&path)?;
+                let range = pack.range(start_utc, end_utc)?;
+                packs.push((pack, range));
+            }
+            month = next_month(current);
+        }
 
         // Iterate through date directories
         let mut current_date = start.date_naive();
@@ -83,7 +439,15 @@ impl SnapshotStore {
             let date_dir = self.data_dir.join(&date_str);
 
             if date_dir.exists() && date_dir.is_dir() {
-                // Read all JSON files in the directory
+                // Decode only the packed snapshots inside the range
+                let pack_path = date_dir.join(PACK_FILE_NAME);
+                if pack_path.is_file() {
+                    let pack = SnapshotPack::open(&pack_path)?;
+                    let range = pack.range(start_utc, end_utc)?;
+                    packs.push((pack, range));
+                }
+
+                // Collect the JSON files in the directory
                 for entry in fs::read_dir(&date_dir)? {
                     let entry = entry?;
                     let path = entry.path();
@@ -96,10 +460,7 @@ impl SnapshotStore {
                                 .with_timezone(&Local);
 
                             if snapshot_time >= start && snapshot_time <= end {
-                                // Load and parse the snapshot
-                                let content = fs::read_to_string(&path)?;
-                                let snapshot: MempoolSnapshot = serde_json::from_str(&content)?;
-                                snapshots.push(snapshot);
+                                files.push(path);
                             }
                         }
                     }
@@ -112,23 +473,66 @@ impl SnapshotStore {
                 .ok_or_else(|| PersistenceError::InvalidPath("Date overflow".to_string()))?;
         }
 
-        // Sort snapshots by timestamp
-        snapshots.sort_by_key(|s| s.timestamp);
+        let mut progress = LoadProgress {
+            loaded: 0,
+            total: packs.iter().map(|(_, range)| range.len()).sum::<usize>() + files.len(),
+        };
+        let mut emit = |snapshot| {
+            progress.loaded += 1;
+            on_snapshot(snapshot, progress);
+        };
+
+        for (pack, range) in packs {
+            for index in range {
+                emit(pack.get(index)?);
+            }
+        }
 
-        debug!(
-            "Retrieved {} snapshots from {} to {}",
-            snapshots.len(),
-            start.format("%Y-%m-%d %H:%M:%S"),
-            end.format("%Y-%m-%d %H:%M:%S")
-        );
+        let threads = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(MAX_LOAD_THREADS)
+            .min(files.len());
+        if threads <= 1 {
+            for path in &files {
+                emit(read_snapshot_file(path)?);
+            }
+        } else {
+            let next = AtomicUsize::new(0);
+            let (sender, receiver) = mpsc::sync_channel(threads * 2);
+            thread::scope(|scope| {
+                for _ in 0..threads {
+                    let sender = sender.clone();
+                    let (next, files) = (&next, &files);
+                    scope.spawn(move || {
+                        while let Some(path) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            // The receiver is gone once reading failed elsewhere
+                            if sender.send(read_snapshot_file(path)).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+                drop(sender);
 
-        Ok(snapshots)
+                for snapshot in receiver {
+                    emit(snapshot?);
+                }
+                Ok::<_, PersistenceError>(())
+            })?;
+        }
+
+        Ok(progress)
     }
 
     /// Gets the most recent snapshot
-    #[allow(dead_code)]
     pub fn get_latest_snapshot(&self) -> Result<Option<MempoolSnapshot>, PersistenceError> {
         let mut latest: Option<(i64, PathBuf)> = None;
+        let mut latest_packed: Option<MempoolSnapshot> = None;
+
+        // Only the newest archive can hold the latest archived snapshot
+        if let Some((_, path)) = archives(&self.data_dir)?.last() {
+            latest_packed = SnapshotPack::open(path)?.last()?;
+        }
 
         // Scan all date directories
         for entry in fs::read_dir(&self.data_dir)? {
@@ -136,6 +540,19 @@ impl SnapshotStore {
             let path = entry.path();
 
             if path.is_dir() {
+                // Packs are sorted, so only their last snapshot can be the latest
+                let pack_path = path.join(PACK_FILE_NAME);
+                if pack_path.is_file() {
+                    if let Some(snapshot) = SnapshotPack::open(&pack_path)?.last()? {
+                        if latest_packed
+                            .as_ref()
+                            .is_none_or(|current| current.timestamp < snapshot.timestamp)
+                        {
+                            latest_packed = Some(snapshot);
+                        }
+                    }
+                }
+
                 // Scan JSON files in this directory
                 for file_entry in fs::read_dir(&path)? {
                     let file_entry = file_entry?;
@@ -152,30 +569,189 @@ impl SnapshotStore {
             }
         }
 
-        if let Some((_, path)) = latest {
-            let content = fs::read_to_string(&path)?;
-            let snapshot: MempoolSnapshot = serde_json::from_str(&content)?;
-            Ok(Some(snapshot))
-        } else {
-            Ok(None)
+        match latest {
+            Some((timestamp, _))
+                if latest_packed
+                    .as_ref()
+                    .is_some_and(|packed| packed.timestamp.timestamp() > timestamp) =>
+            {
+                Ok(latest_packed)
+            }
+            Some((_, path)) => Ok(Some(read_snapshot_file(&path)?)),
+            None => Ok(latest_packed),
+        }
+    }
+
+    /// Packs every date directory before `cutoff` into one binary file, returning the number
+    /// of directories packed
+    ///
+    /// JSON snapshots are merged into the directory's pack, replacing packed snapshots with the
+    /// same timestamp, and removed once the pack is written.
+    pub fn pack_before(&self, cutoff: NaiveDate) -> Result<usize, PersistenceError> {
+        let mut packed_count = 0;
+
+        for entry in fs::read_dir(&self.data_dir)? {
+            let path = entry?.path();
+            let dir_date = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|name| NaiveDate::parse_from_str(name, "%Y-%m-%d").ok());
+
+            if path.is_dir() && dir_date.is_some_and(|date| date < cutoff) {
+                if let Some(count) = Self::pack_dir(&path)? {
+                    packed_count += 1;
+                    info!("Packed {count} snapshots in {path}", path = path.display());
+                }
+            }
+        }
+
+        Ok(packed_count)
+    }
+
+    /// Merges the JSON files of `date_dir` into its pack, or returns `None` if there are none
+    fn pack_dir(date_dir: &Path) -> Result<Option<usize>, PersistenceError> {
+        let mut json_files = Vec::new();
+        for entry in fs::read_dir(date_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("json") {
+                json_files.push(path);
+            }
+        }
+        if json_files.is_empty() {
+            return Ok(None);
+        }
+
+        let pack_path = date_dir.join(PACK_FILE_NAME);
+        let mut snapshots = BTreeMap::new();
+        if pack_path.is_file() {
+            for snapshot in SnapshotPack::open(&pack_path)?.snapshots()? {
+                snapshots.insert(snapshot.timestamp, snapshot);
+            }
+        }
+        for path in &json_files {
+            let snapshot = read_snapshot_file(path)?;
+            snapshots.insert(snapshot.timestamp, snapshot);
+        }
+
+        let snapshots: Vec<MempoolSnapshot> = snapshots.into_values().collect();
+        write_pack(&pack_path, &snapshots)?;
+        for path in &json_files {
+            fs::remove_file(path)?;
+        }
+
+        Ok(Some(snapshots.len()))
+    }
+
+    /// Archives every month that ends before `cutoff` into one pack per month, returning the
+    /// number of months archived
+    ///
+    /// The snapshots of the month's date directories, packed or JSON, are merged into its
+    /// archive, replacing archived snapshots with the same timestamp, and the directories are
+    /// removed once the archive is written.
+    pub fn archive_before(&self, cutoff: NaiveDate) -> Result<usize, PersistenceError> {
+        let mut months: BTreeMap<NaiveDate, Vec<PathBuf>> = BTreeMap::new();
+        for entry in fs::read_dir(&self.data_dir)? {
+            let path = entry?.path();
+            let dir_date = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|name| NaiveDate::parse_from_str(name, "%Y-%m-%d").ok());
+
+            if let Some(date) = dir_date.filter(|_| path.is_dir()) {
+                if next_month(date).is_some_and(|next| next <= cutoff) {
+                    months.entry(month_of(date)).or_default().push(path);
+                }
+            }
+        }
+
+        for (month, date_dirs) in &months {
+            let count = self.archive_month(*month, date_dirs)?;
+            info!(
+                "Archived {count} snapshots of {month} from {days} date directories",
+                month = month.format("%Y-%m"),
+                days = date_dirs.len()
+            );
         }
+
+        Ok(months.len())
     }
 
-    /// Gets snapshots from the last N hours
-    pub fn get_recent_snapshots(
+    /// Merges `date_dirs` into the archive of `month`, returning the number of snapshots it
+    /// holds
+    fn archive_month(
         &self,
-        hours: i64,
-    ) -> Result<Vec<MempoolSnapshot>, PersistenceError> {
-        let end = Local::now();
-        let start = end - chrono::Duration::hours(hours);
-        self.get_snapshots(start, end)
+        month: NaiveDate,
+        date_dirs: &[PathBuf],
+    ) -> Result<usize, PersistenceError> {
+        let path = archive_path(&self.data_dir, month);
+        let mut snapshots = BTreeMap::new();
+        if path.is_file() {
+            for snapshot in SnapshotPack::open(&path)?.snapshots()? {
+                snapshots.insert(snapshot.timestamp, snapshot);
+            }
+        }
+        for date_dir in date_dirs {
+            let pack_path = date_dir.join(PACK_FILE_NAME);
+            if pack_path.is_file() {
+                for snapshot in SnapshotPack::open(&pack_path)?.snapshots()? {
+                    snapshots.insert(snapshot.timestamp, snapshot);
+                }
+            }
+            for entry in fs::read_dir(date_dir)? {
+                let file = entry?.path();
+                if file.extension().and_then(|s| s.to_str()) == Some("json") {
+                    let snapshot = read_snapshot_file(&file)?;
+                    snapshots.insert(snapshot.timestamp, snapshot);
+                }
+            }
+        }
+
+        let snapshots: Vec<MempoolSnapshot> = snapshots.into_values().collect();
+        fs::create_dir_all(self.data_dir.join(ARCHIVE_DIR_NAME))?;
+        write_pack(&path, &snapshots)?;
+        for date_dir in date_dirs {
+            fs::remove_dir_all(date_dir)?;
+        }
+
+        Ok(snapshots.len())
     }
 
     /// Cleans up old snapshots older than the specified number of days
+    ///
+    /// Archives of months that ended before the cutoff are deleted, and the archive of the month
+    /// holding it is rewritten without the older snapshots.
     pub fn cleanup_old_snapshots(&self, days_to_keep: i64) -> Result<usize, PersistenceError> {
         let cutoff_date = Local::now().date_naive() - chrono::Duration::days(days_to_keep);
         let mut deleted_count = 0;
 
+        for (month, path) in archives(&self.data_dir)? {
+            if next_month(month).is_some_and(|next| next <= cutoff_date) {
+                fs::remove_file(&path)?;
+                deleted_count += 1;
+                info!(
+                    "Deleted old snapshot archive: {path}",
+                    path = path.display()
+                );
+            } else if month < cutoff_date {
+                let pack = SnapshotPack::open(&path)?;
+                let keep = pack.range(
+                    cutoff_date.and_time(chrono::NaiveTime::MIN).and_utc(),
+                    DateTime::<Utc>::MAX_UTC,
+                )?;
+                if keep.len() < pack.len() {
+                    let snapshots = keep
+                        .map(|index| pack.get(index))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    write_pack(&path, &snapshots)?;
+                    info!(
+                        "Removed {removed} old snapshots from archive {path}",
+                        removed = pack.len() - snapshots.len(),
+                        path = path.display()
+                    );
+                }
+            }
+        }
+
         for entry in fs::read_dir(&self.data_dir)? {
             let entry = entry?;
             let path = entry.path();
@@ -198,6 +774,152 @@ impl SnapshotStore {
         Ok(deleted_count)
     }
 
+    /// Removes every stored snapshot, returning the number of date directories and monthly
+    /// archives deleted
+    pub fn clear(&self) -> Result<usize, PersistenceError> {
+        let mut deleted_count = 0;
+
+        for (_, path) in archives(&self.data_dir)? {
+            fs::remove_file(&path)?;
+            deleted_count += 1;
+        }
+
+        for entry in fs::read_dir(&self.data_dir)? {
+            let path = entry?.path();
+            let is_date_dir = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|name| chrono::NaiveDate::parse_from_str(name, "%Y-%m-%d").is_ok());
+
+            if path.is_dir() && is_date_dir {
+                fs::remove_dir_all(&path)?;
+                deleted_count += 1;
+            }
+        }
+
+        info!("Cleared {deleted_count} snapshot directories");
+        Ok(deleted_count)
+    }
+
+    /// Replaces the cached latest estimate
+    pub fn save_cached_estimate(&self, cached: &CachedEstimate) -> Result<(), PersistenceError> {
+        write_estimate_cache(&self.data_dir.join(ESTIMATE_CACHE_FILE_NAME), cached)
+    }
+
+    /// Reads the cached latest estimate, if one was saved
+    pub fn load_cached_estimate(&self) -> Result<Option<CachedEstimate>, PersistenceError> {
+        read_estimate_cache(&self.data_dir.join(ESTIMATE_CACHE_FILE_NAME))
+    }
+
+    /// Where the collection cycle log is kept
+    pub fn cycle_log_path(&self) -> PathBuf {
+        self.data_dir.join(CYCLE_LOG_FILE_NAME)
+    }
+
+    /// Where blocks realized after they were mined are kept
+    pub fn realized_blocks_path(&self) -> PathBuf {
+        self.data_dir.join(REALIZED_BLOCKS_FILE_NAME)
+    }
+
+    /// Downloads the snapshots of the days from `start` to `end` that are in the remote
+    /// storage but not the data directory
+    ///
+    /// Past days are fetched once per store, and never once packed or archived, since their
+    /// JSON files are gone by then. Today is listed again once the list interval passed, to
+    /// pick up the snapshots other replicas keep writing. Failures are logged and leave the
+    /// local snapshots to serve.
+    fn fetch_remote(&self, start: NaiveDate, end: NaiveDate) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        let today = Utc::now().date_naive();
+        for date in start.iter_days().take_while(|date| *date <= end.min(today)) {
+            let fetched = remote
+                .fetched
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if fetched.contains(&date) {
+                continue;
+            }
+            drop(fetched);
+
+            if date == today {
+                let mut listed = remote
+                    .listed_today
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                if listed.is_some_and(|listed| listed.elapsed() < remote.list_interval) {
+                    continue;
+                }
+                *listed = Some(Instant::now());
+            }
+
+            let date_dir = self.data_dir.join(date.format("%Y-%m-%d").to_string());
+            if !date_dir.join(PACK_FILE_NAME).is_file()
+                && !archive_path(&self.data_dir, date).is_file()
+            {
+                match self.fetch_day(&*remote.storage, &date_dir) {
+                    Ok(0) => {}
+                    Ok(count) => info!(
+                        "Fetched {count} snapshots into {path}",
+                        path = date_dir.display()
+                    ),
+                    Err(e) => {
+                        warn!(
+                            "Failed to fetch snapshots into {path}: {e}",
+                            path = date_dir.display()
+                        );
+                        continue;
+                    }
+                }
+            }
+            if date < today {
+                remote
+                    .fetched
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(date);
+            }
+        }
+    }
+
+    /// Downloads the snapshot files of `date_dir` missing from it, returning how many
+    fn fetch_day(
+        &self,
+        storage: &dyn SnapshotStorage,
+        date_dir: &Path,
+    ) -> Result<usize, PersistenceError> {
+        let date_str = date_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| PersistenceError::InvalidPath(date_dir.display().to_string()))?;
+        let mut count = 0;
+        for key in storage.list(&format!("{date_str}/"))? {
+            // Only snapshot files directly in the day, never paths leaving the data directory
+            let Some(name) = key
+                .strip_prefix(date_str)
+                .and_then(|name| name.strip_prefix('/'))
+                .filter(|name| !name.contains('/') && name.ends_with(".json"))
+            else {
+                continue;
+            };
+            let path = date_dir.join(name);
+            if path.exists() || Self::extract_timestamp_from_filename(&path).is_none() {
+                continue;
+            }
+            let Some(body) = storage.get(&key)? else {
+                continue;
+            };
+            fs::create_dir_all(date_dir)?;
+            // Readers never see a partly written file
+            let partial = path.with_extension("json.partial");
+            fs::write(&partial, body)?;
+            fs::rename(&partial, &path)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
     /// Extracts timestamp from snapshot filename
     fn extract_timestamp_from_filename(path: &Path) -> Option<i64> {
         let filename = path.file_stem()?.to_str()?;
@@ -229,6 +951,15 @@ mod tests {
         MempoolSnapshot::from_transactions(transactions, block_height, timestamp)
     }
 
+    /// Snapshots stored in the last `hours`
+    fn recent_snapshots(
+        store: &SnapshotStore,
+        hours: i64,
+    ) -> Result<Vec<MempoolSnapshot>, PersistenceError> {
+        let end = Local::now();
+        store.get_snapshots(end - chrono::Duration::hours(hours), end)
+    }
+
     #[test]
     fn test_snapshot_store_creation() -> Result<(), PersistenceError> {
         let temp_dir = TempDir::new().unwrap();
@@ -257,7 +988,7 @@ mod tests {
         store.save_snapshot(&snapshot)?;
 
         // Retrieve snapshots from the last hour
-        let retrieved = store.get_recent_snapshots(1)?;
+        let retrieved = recent_snapshots(&store, 1)?;
 
         assert_eq!(retrieved.len(), 1);
         assert_eq!(retrieved[0].block_height, 850000);
@@ -321,7 +1052,7 @@ mod tests {
         assert_eq!(deleted, 1);
 
         // Verify recent snapshot still exists
-        let remaining = store.get_recent_snapshots(1)?;
+        let remaining = recent_snapshots(&store, 1)?;
         assert_eq!(remaining.len(), 1);
         assert_eq!(remaining[0].block_height, 850001);
 
@@ -398,8 +1129,12 @@ mod tests {
 
     #[test]
     fn test_persistence_error_handling() -> Result<(), PersistenceError> {
-        // Test invalid path
-        let result = SnapshotStore::new("/nonexistent/readonly/path");
+        // A path nested under a regular file cannot be created, even with elevated privileges
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("not_a_directory");
+        fs::write(&file_path, b"")?;
+
+        let result = SnapshotStore::new(file_path.join("snapshots"));
         assert!(result.is_err());
 
         Ok(())
@@ -421,7 +1156,7 @@ mod tests {
         fs::write(&corrupted_file, "{ invalid json }")?;
 
         // Try to retrieve snapshots - should skip the corrupted one
-        let result = store.get_recent_snapshots(1);
+        let result = recent_snapshots(&store, 1);
 
         // Should succeed but only return the valid snapshot
         assert!(result.is_ok());
@@ -438,7 +1173,7 @@ mod tests {
         let store = SnapshotStore::new(temp_dir.path())?;
 
         // Query empty store
-        let snapshots = store.get_recent_snapshots(24)?;
+        let snapshots = recent_snapshots(&store, 24)?;
         assert_eq!(snapshots.len(), 0);
 
         let latest = store.get_latest_snapshot()?;
@@ -462,7 +1197,7 @@ mod tests {
 
         // Save and retrieve
         store.save_snapshot(&large_snapshot)?;
-        let retrieved = store.get_recent_snapshots(1)?;
+        let retrieved = recent_snapshots(&store, 1)?;
 
         assert_eq!(retrieved.len(), 1);
         assert_eq!(retrieved[0].block_height, 850000);
@@ -473,4 +1208,428 @@ mod tests {
 
         Ok(())
     }
+
+    /// Object storage in memory, counting listings
+    #[derive(Default)]
+    struct MemoryStorage {
+        objects: Mutex<BTreeMap<String, Vec<u8>>>,
+        lists: AtomicUsize,
+    }
+
+    impl SnapshotStorage for MemoryStorage {
+        fn put(&self, key: &str, body: &[u8]) -> Result<(), PersistenceError> {
+            self.objects
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), body.to_vec());
+            Ok(())
+        }
+
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, PersistenceError> {
+            Ok(self.objects.lock().unwrap().get(key).cloned())
+        }
+
+        fn list(&self, prefix: &str) -> Result<Vec<String>, PersistenceError> {
+            self.lists.fetch_add(1, Ordering::SeqCst);
+            Ok(self
+                .objects
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|key| key.starts_with(prefix))
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_replicas_share_snapshots_through_remote() -> Result<(), PersistenceError> {
+        let remote = Arc::new(MemoryStorage::default());
+        let (dir_a, dir_b) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let writer =
+            SnapshotStore::new(dir_a.path())?.with_remote(remote.clone(), Duration::ZERO)?;
+        let now = Utc::now();
+        let past = now - chrono::Duration::days(2);
+        writer.save_snapshot(&create_test_snapshot(100, past))?;
+        writer.save_snapshot(&create_test_snapshot(101, now))?;
+        writer.wait_for_uploads();
+        assert_eq!(remote.objects.lock().unwrap().len(), 2);
+
+        // A replica with an empty data directory reads the history from the remote
+        let reader =
+            SnapshotStore::new(dir_b.path())?.with_remote(remote.clone(), Duration::ZERO)?;
+        let start = (past - chrono::Duration::hours(1)).with_timezone(&Local);
+        let end = (now + chrono::Duration::minutes(1)).with_timezone(&Local);
+        let heights = |snapshots: Vec<MempoolSnapshot>| {
+            snapshots.iter().map(|s| s.block_height).collect::<Vec<_>>()
+        };
+        assert_eq!(heights(reader.get_snapshots(start, end)?), vec![100, 101]);
+        assert!(snapshot_path(dir_b.path(), &create_test_snapshot(100, past)).is_file());
+        let lists = remote.lists.load(Ordering::SeqCst);
+
+        // Past days are fetched once, while today picks up snapshots written since
+        writer.save_snapshot(&create_test_snapshot(
+            102,
+            now + chrono::Duration::seconds(1),
+        ))?;
+        writer.wait_for_uploads();
+        assert_eq!(
+            heights(reader.get_snapshots(start, end)?),
+            vec![100, 101, 102]
+        );
+        assert_eq!(remote.lists.load(Ordering::SeqCst), lists + 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_today_is_listed_once_per_interval() -> Result<(), PersistenceError> {
+        let remote = Arc::new(MemoryStorage::default());
+        let (dir_a, dir_b) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let writer =
+            SnapshotStore::new(dir_a.path())?.with_remote(remote.clone(), Duration::ZERO)?;
+        let reader = SnapshotStore::new(dir_b.path())?
+            .with_remote(remote.clone(), Duration::from_secs(3600))?;
+        let now = Utc::now();
+        let start = (now - chrono::Duration::minutes(1)).with_timezone(&Local);
+        let end = (now + chrono::Duration::minutes(1)).with_timezone(&Local);
+
+        writer.save_snapshot(&create_test_snapshot(100, now))?;
+        writer.wait_for_uploads();
+        assert_eq!(reader.get_snapshots(start, end)?.len(), 1);
+        let lists = remote.lists.load(Ordering::SeqCst);
+
+        // Reads within the interval serve the local copy without listing again
+        writer.save_snapshot(&create_test_snapshot(
+            101,
+            now + chrono::Duration::seconds(1),
+        ))?;
+        writer.wait_for_uploads();
+        assert_eq!(reader.get_snapshots(start, end)?.len(), 1);
+        assert_eq!(remote.lists.load(Ordering::SeqCst), lists);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pack_before_merges_json_into_pack() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path())?;
+        let day = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let next_day = day + chrono::Duration::days(1);
+
+        store.save_snapshot(&create_test_snapshot(100, day))?;
+        store.save_snapshot(&create_test_snapshot(101, day + chrono::Duration::hours(1)))?;
+        store.save_snapshot(&create_test_snapshot(102, next_day))?;
+
+        assert_eq!(store.pack_before(next_day.date_naive())?, 1);
+        let day_dir = temp_dir.path().join("2025-01-20");
+        let files: Vec<_> = fs::read_dir(&day_dir)?.collect::<Result<_, _>>()?;
+        assert_eq!(files.len(), 1);
+        assert!(day_dir.join(PACK_FILE_NAME).is_file());
+
+        // A snapshot saved after packing is merged on the next run
+        store.save_snapshot(&create_test_snapshot(103, day + chrono::Duration::hours(2)))?;
+        assert_eq!(store.pack_before(next_day.date_naive())?, 1);
+        assert_eq!(store.pack_before(next_day.date_naive())?, 0);
+
+        let start = (day - chrono::Duration::hours(1)).with_timezone(&Local);
+        let end = (next_day + chrono::Duration::hours(1)).with_timezone(&Local);
+        let heights: Vec<u32> = store
+            .get_snapshots(start, end)?
+            .iter()
+            .map(|s| s.block_height)
+            .collect();
+        assert_eq!(heights, vec![100, 101, 103, 102]);
+
+        let inner = store.get_snapshots(
+            (day + chrono::Duration::minutes(30)).with_timezone(&Local),
+            (day + chrono::Duration::minutes(90)).with_timezone(&Local),
+        )?;
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner[0].block_height, 101);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_before_merges_past_months() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path())?;
+        let packed = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let month_end = Utc.with_ymd_and_hms(2025, 1, 31, 23, 0, 0).unwrap();
+        let february = Utc.with_ymd_and_hms(2025, 2, 1, 0, 30, 0).unwrap();
+        store.save_snapshot(&create_test_snapshot(100, packed))?;
+        store.pack_before(month_end.date_naive())?;
+        store.save_snapshot(&create_test_snapshot(101, month_end))?;
+        store.save_snapshot(&create_test_snapshot(102, february))?;
+
+        let march = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        assert_eq!(store.archive_before(february.date_naive())?, 1);
+        assert!(!temp_dir.path().join("2025-01-20").exists());
+        assert!(!temp_dir.path().join("2025-01-31").exists());
+        assert!(temp_dir.path().join("2025-02-01").is_dir());
+        assert!(archive_path(temp_dir.path(), packed.date_naive()).is_file());
+
+        // Range queries read archives and date directories alike
+        let heights = |store: &SnapshotStore| -> Result<Vec<u32>, PersistenceError> {
+            Ok(store
+                .get_snapshots(
+                    (packed - chrono::Duration::days(10)).with_timezone(&Local),
+                    (february + chrono::Duration::days(1)).with_timezone(&Local),
+                )?
+                .iter()
+                .map(|s| s.block_height)
+                .collect())
+        };
+        assert_eq!(heights(&store)?, vec![100, 101, 102]);
+        let inner = store.get_snapshots(
+            (month_end - chrono::Duration::hours(1)).with_timezone(&Local),
+            (month_end + chrono::Duration::hours(1)).with_timezone(&Local),
+        )?;
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner[0].block_height, 101);
+
+        // A snapshot saved into an archived month is merged on the next run
+        store.save_snapshot(&create_test_snapshot(
+            99,
+            packed - chrono::Duration::days(5),
+        ))?;
+        assert_eq!(store.archive_before(march)?, 2);
+        assert_eq!(store.archive_before(march)?, 0);
+        assert_eq!(heights(&store)?, vec![99, 100, 101, 102]);
+        assert_eq!(store.get_latest_snapshot()?.unwrap().block_height, 102);
+
+        assert_eq!(store.clear()?, 2);
+        assert!(store.get_latest_snapshot()?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleanup_trims_archives() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path())?;
+        // Snapshots five days either side of the cutoff, in a month that is archived
+        let today = Local::now().date_naive();
+        let cutoff = month_of(today - chrono::Duration::days(60)) + chrono::Duration::days(10);
+        let at = |date: NaiveDate| date.and_hms_opt(12, 0, 0).unwrap().and_utc();
+        let old = create_test_snapshot(100, at(cutoff - chrono::Duration::days(5)));
+        let kept = create_test_snapshot(101, at(cutoff + chrono::Duration::days(5)));
+        let older_month = create_test_snapshot(99, at(cutoff - chrono::Duration::days(40)));
+        for snapshot in [&older_month, &old, &kept] {
+            store.save_snapshot(snapshot)?;
+        }
+        assert_eq!(store.archive_before(month_of(today))?, 2);
+
+        let deleted = store.cleanup_old_snapshots((today - cutoff).num_days())?;
+        assert_eq!(deleted, 1);
+        let remaining = store.get_snapshots(
+            at(cutoff - chrono::Duration::days(60)).with_timezone(&Local),
+            Local::now(),
+        )?;
+        assert_eq!(
+            remaining.iter().map(|s| s.block_height).collect::<Vec<_>>(),
+            vec![101]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_stores_markers_for_repeated_snapshots() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path())?.with_dedup();
+        let start = Utc.with_ymd_and_hms(2025, 1, 20, 23, 50, 0).unwrap();
+        let quiet = create_test_snapshot(100, start).bucketed_weights;
+        let busy = BTreeMap::from([(100, 9_000)]);
+
+        // Polls every two minutes; the one after midnight starts the new day in full
+        let polls = [&quiet, &quiet, &busy, &quiet, &quiet, &quiet];
+        let snapshots: Vec<MempoolSnapshot> = polls
+            .iter()
+            .enumerate()
+            .map(|(i, &weights)| {
+                let timestamp = start + chrono::Duration::minutes(2 * i as i64);
+                MempoolSnapshot::new(100, timestamp, weights.clone())
+            })
+            .collect();
+        for snapshot in &snapshots {
+            store.save_snapshot(snapshot)?;
+        }
+
+        let markers: Vec<bool> = snapshots
+            .iter()
+            .map(|snapshot| {
+                let content = fs::read_to_string(snapshot_path(temp_dir.path(), snapshot))?;
+                Ok(content.contains("same_as"))
+            })
+            .collect::<Result<_, PersistenceError>>()?;
+        assert_eq!(markers, vec![false, true, false, false, true, false]);
+
+        let read = |store: &SnapshotStore| {
+            store.get_snapshots(
+                (start - chrono::Duration::hours(1)).with_timezone(&Local),
+                (start + chrono::Duration::hours(1)).with_timezone(&Local),
+            )
+        };
+        let check = |read: Vec<MempoolSnapshot>| {
+            assert_eq!(read.len(), snapshots.len());
+            for (read, written) in read.iter().zip(&snapshots) {
+                assert_eq!(read.timestamp, written.timestamp);
+                assert_eq!(read.bucketed_weights, written.bucketed_weights);
+            }
+        };
+        check(read(&store)?);
+        let latest = store.get_latest_snapshot()?.unwrap();
+        assert_eq!(latest.timestamp, snapshots[5].timestamp);
+
+        // Packing resolves markers into full snapshots
+        store.pack_before(snapshots[5].timestamp.date_naive())?;
+        check(read(&store)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_skips_snapshots_that_failed_to_write() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path())?.with_dedup();
+        let start = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let first = create_test_snapshot(100, start);
+        let second = MempoolSnapshot::new(
+            100,
+            start + chrono::Duration::minutes(2),
+            first.bucketed_weights.clone(),
+        );
+
+        // A directory in the way makes the first write fail
+        fs::create_dir_all(snapshot_path(temp_dir.path(), &first))?;
+        assert!(store.save_snapshot(&first).is_err());
+
+        // So the identical snapshot after it is written in full, not as a marker to nothing
+        store.save_snapshot(&second)?;
+        let content = fs::read_to_string(snapshot_path(temp_dir.path(), &second))?;
+        assert!(!content.contains("same_as"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_snapshots_reports_progress() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new()?;
+        let store = SnapshotStore::new(temp_dir.path())?;
+        let base = Utc.with_ymd_and_hms(2025, 1, 15, 22, 0, 0).unwrap();
+
+        // Yesterday packed, today as JSON files read on several threads
+        for i in 0..200 {
+            let timestamp = base + chrono::Duration::minutes(i);
+            store.save_snapshot(&create_test_snapshot(850_000 + i as u32, timestamp))?;
+        }
+        store.pack_before(base.date_naive() + chrono::Duration::days(1))?;
+        for i in 200..400 {
+            let timestamp = base + chrono::Duration::minutes(i);
+            store.save_snapshot(&create_test_snapshot(850_000 + i as u32, timestamp))?;
+        }
+
+        let start = (base + chrono::Duration::minutes(100)).with_timezone(&Local);
+        let end = (base + chrono::Duration::minutes(349)).with_timezone(&Local);
+        let mut progress = Vec::new();
+        let mut streamed = Vec::new();
+        let done = store.stream_snapshots(start, end, |snapshot, p| {
+            streamed.push(snapshot.block_height);
+            progress.push(p);
+        })?;
+        let snapshots = store.get_snapshots(start, end)?;
+
+        assert_eq!(
+            done,
+            LoadProgress {
+                loaded: 250,
+                total: 250
+            }
+        );
+        streamed.sort();
+        assert_eq!(
+            streamed,
+            snapshots.iter().map(|s| s.block_height).collect::<Vec<_>>()
+        );
+        assert_eq!(snapshots.len(), 250);
+        assert!(snapshots
+            .windows(2)
+            .all(|w| w[0].timestamp < w[1].timestamp));
+        assert_eq!(snapshots[0].block_height, 850_100);
+        assert_eq!(progress.len(), 250);
+        assert!(progress.iter().all(|p| p.total == 250));
+        assert_eq!(
+            progress.iter().map(|p| p.loaded).collect::<Vec<_>>(),
+            (1..=250).collect::<Vec<_>>()
+        );
+
+        // A corrupt file fails the whole load
+        fs::write(
+            snapshot_path(temp_dir.path(), &snapshots[200]),
+            "not a snapshot",
+        )?;
+        assert!(store.get_snapshots(start, end).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_latest_snapshot_from_pack() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path())?;
+        let day = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+
+        store.save_snapshot(&create_test_snapshot(100, day - chrono::Duration::days(1)))?;
+        store.save_snapshot(&create_test_snapshot(101, day))?;
+        store.pack_before(day.date_naive() + chrono::Duration::days(1))?;
+
+        let latest = store.get_latest_snapshot()?.unwrap();
+        assert_eq!(latest.block_height, 101);
+        assert_eq!(latest.timestamp, day);
+
+        Ok(())
+    }
+
+    /// Loads a day of snapshots at one-minute spacing from JSON files and from a pack.
+    /// Run with `--nocapture` to see the timings.
+    #[test]
+    fn test_benchmark_packed_range_loading() -> Result<(), PersistenceError> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path())?;
+        let day = Utc.with_ymd_and_hms(2025, 1, 20, 0, 0, 0).unwrap();
+
+        for minute in 0..1440 {
+            let weights = (0..500)
+                .map(|bucket| (bucket, 4_000 + minute as u64))
+                .collect();
+            let timestamp = day + chrono::Duration::minutes(minute);
+            store.save_snapshot(&MempoolSnapshot::new(850_000, timestamp, weights))?;
+        }
+        let start = day.with_timezone(&Local);
+        let end = (day + chrono::Duration::hours(23)).with_timezone(&Local);
+
+        let timer = std::time::Instant::now();
+        let from_json = store.get_snapshots(start, end)?;
+        let json_time = timer.elapsed();
+
+        store.pack_before(day.date_naive() + chrono::Duration::days(1))?;
+        let timer = std::time::Instant::now();
+        let from_pack = store.get_snapshots(start, end)?;
+        let pack_time = timer.elapsed();
+
+        println!(
+            "Loaded {count} snapshots: JSON {json_time:?}, pack {pack_time:?}",
+            count = from_pack.len()
+        );
+        assert_eq!(from_json.len(), 23 * 60 + 1);
+        assert_eq!(from_pack.len(), from_json.len());
+        for (json, packed) in from_json.iter().zip(&from_pack) {
+            assert_eq!(json.timestamp, packed.timestamp);
+            assert_eq!(json.bucketed_weights, packed.bucketed_weights);
+        }
+
+        Ok(())
+    }
 }
@@ -1,28 +1,192 @@
-use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
-use std::sync::Arc;
+use axum::{
+    extract::FromRef,
+    http::StatusCode,
+    middleware,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use futures_util::future::try_join_all;
+use std::future::IntoFuture;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use tokio::sync::watch;
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer},
 };
-use tracing::{info, Level};
+use tracing::{info, warn, Level};
 
 use crate::{
-    api::{get_fee_for_target, get_fees, get_historical_fee},
-    service::MempoolCollector,
+    api::{
+        export_historical_fees, field_naming, get_confidence_curve, get_cpfp_bump, get_cycles,
+        get_estimator_config, get_fee_comparison, get_fee_for_target, get_fee_stream, get_fees,
+        get_historical_fee, get_mempool_depth, get_mempool_histogram, get_metrics, get_position,
+        get_preset, get_rbf_bump, get_readiness, get_realized_blocks, get_simulation,
+        get_source_comparison, get_suggestion, get_sync_changes, get_target_curve, grafana_health,
+        ingest_snapshots, inject_snapshots, problem_details, query_metrics, reject_stale,
+        response_format, search_metrics, sign_response, ResponseSigner,
+    },
+    config::{ApiConfig, ResponseConfig},
+    service::{MempoolCollector, PriceFeed, WarmupPhase},
+    systemd,
 };
 
+/// Set once a shutdown signal is received, ending long-lived responses such as the fee stream
+static SHUTDOWN: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::Sender::new(false));
+
+/// Receiver that changes to `true` when the server starts shutting down
+pub(crate) fn shutdown_receiver() -> watch::Receiver<bool> {
+    SHUTDOWN.subscribe()
+}
+
+/// Shared state available to all HTTP handlers
+#[derive(Clone)]
+pub struct AppState {
+    pub collector: Arc<MempoolCollector>,
+    pub api: Arc<ApiConfig>,
+    /// Bitcoin prices fiat costs are quoted with, when a price feed is configured
+    pub prices: Option<Arc<PriceFeed>>,
+    pub response: ResponseConfig,
+}
+
+impl FromRef<AppState> for Arc<MempoolCollector> {
+    fn from_ref(state: &AppState) -> Self {
+        state.collector.clone()
+    }
+}
+
+impl FromRef<AppState> for ResponseConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.response
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<PriceFeed>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.prices.clone()
+    }
+}
+
 /// Create the Axum application router
+#[allow(dead_code)]
 pub fn create_app(collector: Arc<MempoolCollector>) -> Router {
-    Router::new()
-        // Fee estimation endpoints
+    create_app_with_config(collector, ApiConfig::default())
+}
+
+/// Create the Axum application router with explicit API settings
+pub fn create_app_with_config(collector: Arc<MempoolCollector>, api: ApiConfig) -> Router {
+    create_app_with_signer(collector, api, ResponseConfig::default(), None)
+}
+
+/// Create the Axum application router, formatting numbers as `response` says and signing fee
+/// responses with `signer` if given
+pub fn create_app_with_signer(
+    collector: Arc<MempoolCollector>,
+    api: ApiConfig,
+    response: ResponseConfig,
+    signer: Option<Arc<ResponseSigner>>,
+) -> Router {
+    let naming = middleware::from_fn_with_state(api.field_naming, field_naming);
+    let format = middleware::from_fn_with_state(response, response_format);
+    let prices = api.price_feed_url.as_ref().map(|url| {
+        Arc::new(PriceFeed::new(
+            url,
+            std::time::Duration::from_secs(api.price_cache_secs),
+        ))
+    });
+
+    // Fee estimation endpoints, formatted and renamed before they are signed
+    let mut fee_router = Router::new()
         .route("/fees", get(get_fees))
         .route("/fees/target/{num_blocks}", get(get_fee_for_target))
+        .route("/fees/curve", get(get_confidence_curve))
+        .route("/fees/targets", get(get_target_curve))
+        .route("/fees/preset/{name}", get(get_preset))
+        .route("/fees/sources", get(get_source_comparison))
+        .route("/suggest", get(get_suggestion))
+        .route("/position", get(get_position))
+        .route("/bump/cpfp", get(get_cpfp_bump))
+        .route("/bump/rbf", get(get_rbf_bump));
+    // Baselines are only routed when the collector estimates any
+    if collector.has_baselines() {
+        fee_router = fee_router.route("/fees/compare", get(get_fee_comparison));
+    }
+    // Current estimates fail once the data is too old, while past ones are still served
+    fee_router = fee_router
+        .route_layer(middleware::from_fn_with_state(
+            collector.clone(),
+            reject_stale,
+        ))
         .route("/historical_fee", get(get_historical_fee))
-        // Health check endpoint
+        .route_layer(format.clone())
+        .route_layer(naming.clone());
+    if let Some(signer) = signer {
+        fee_router = fee_router.route_layer(middleware::from_fn_with_state(signer, sign_response));
+    }
+
+    // Health check endpoint
+    // Signing would buffer the never-ending stream, so it is not a fee route, nor are
+    // exports or Grafana queries
+    let mut router = Router::new()
+        .route("/fees/stream", get(get_fee_stream))
+        .route("/historical_fees/export", get(export_historical_fees))
+        .route("/mempool/depth", get(get_mempool_depth))
+        .route("/mempool/histogram", get(get_mempool_histogram))
+        .route("/config/estimator", get(get_estimator_config))
+        .route("/grafana", get(grafana_health))
+        .route("/grafana/search", post(search_metrics))
+        .route("/grafana/query", post(query_metrics))
         .route("/health", get(health_check))
+        .route("/metrics", get(get_metrics))
+        .route("/health/ready", get(get_readiness));
+
+    // Snapshot ingestion is only routed when a token is configured for it
+    if api.ingest_token.is_some() {
+        router = router.route("/snapshots", post(ingest_snapshots));
+    }
+
+    // Replication is only routed when a token is configured for it
+    if api.sync_token.is_some() {
+        router = router.route("/sync/changes", get(get_sync_changes));
+    }
+
+    // Operator endpoints are only routed when a token is configured for them
+    if api.admin_token.is_some() {
+        router = router.route("/admin/cycles", get(get_cycles));
+    }
+
+    // Test-only endpoints are not routed at all unless explicitly enabled
+    if api.test_api_enabled {
+        router = router.route("/test/snapshots", post(inject_snapshots));
+    }
+
+    // Simulation internals are only exposed when debugging is enabled
+    if api.debug_api_enabled {
+        router = router
+            .route("/debug/simulation", get(get_simulation))
+            .route("/debug/blocks", get(get_realized_blocks));
+    }
+
+    // The dashboard is only built into servers compiled with the dashboard feature
+    #[cfg(feature = "dashboard")]
+    {
+        router = router.route("/ui", get(crate::api::get_dashboard));
+    }
+
+    router
+        .route_layer(format)
+        .route_layer(naming)
+        .merge(fee_router)
         // Add shared state
-        .with_state(collector)
+        .with_state(AppState {
+            collector,
+            api: Arc::new(api),
+            prices,
+            response,
+        })
         // Add middleware
+        .layer(middleware::from_fn(problem_details))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
@@ -42,22 +206,96 @@ async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
 
-/// Run the HTTP server
-pub async fn run_server(app: Router, host: String, port: u16) -> Result<(), std::io::Error> {
-    let addr = format!("{}:{}", host, port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+/// Interval at which the server checks whether the collector has warmed up
+const WARM_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How the server tells process supervisors and test harnesses that it is ready
+#[derive(Default)]
+pub struct Readiness {
+    /// File the bound addresses are written to, one per line, once the server is ready
+    pub ready_file: Option<PathBuf>,
+    /// Collector whose warm-up must finish first, or none to be ready once every address
+    /// listens
+    pub warm: Option<Arc<MempoolCollector>>,
+}
+
+impl Readiness {
+    /// Waits until the server is ready, then writes the ready file with the `bound` addresses
+    /// and notifies systemd
+    async fn announce(&self, bound: &str) -> Result<(), std::io::Error> {
+        if let Some(ref collector) = self.warm {
+            let mut shutdown = shutdown_receiver();
+            while collector.warmup_status().await.phase != WarmupPhase::Ready {
+                tokio::select! {
+                    _ = tokio::time::sleep(WARM_POLL_INTERVAL) => {}
+                    _ = shutdown.wait_for(|shutdown| *shutdown) => return Ok(()),
+                }
+            }
+        }
+        if let Some(ref path) = self.ready_file {
+            write_ready_file(path, bound)?;
+        }
+        if systemd::notify("READY=1")? {
+            info!("Notified systemd that the server is ready");
+        }
+        info!("Server is ready");
+        Ok(())
+    }
+}
+
+/// Run the HTTP server on every address in `addresses`, announcing as `readiness` says once it
+/// is ready
+///
+/// An address with port 0 binds any free port, which is logged and written to the ready file.
+pub async fn run_server(
+    app: Router,
+    addresses: &[String],
+    readiness: Readiness,
+) -> Result<(), std::io::Error> {
+    let mut listeners = Vec::new();
+    for address in addresses {
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .map_err(|e| std::io::Error::new(e.kind(), format!("Failed to bind {address}: {e}")))?;
+        info!(
+            "HTTP server listening on http://{local}",
+            local = listener.local_addr()?
+        );
+        listeners.push(listener);
+    }
+    let bound = listeners
+        .iter()
+        .map(|listener| listener.local_addr().map(|local| format!("{local}\n")))
+        .collect::<Result<String, _>>()?;
 
-    info!("HTTP server listening on http://{addr}");
     info!("API endpoints:");
     info!("  GET /fees - Current fee estimates");
     info!("  GET /fees/target/{{num_blocks}} - Fee estimates for specific target");
     info!("  GET /historical_fee?timestamp={{unix_ts}} - Historical fee estimates");
+    info!("  GET /fees/stream - Fee estimates as server-sent events");
+    info!("  GET /config/estimator - Estimator configuration");
     info!("  GET /health - Health check");
+    info!("  GET /health/ready - Readiness and warm-up progress");
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .map_err(std::io::Error::other)
+    tokio::spawn(shutdown_signal());
+    let servers = listeners.into_iter().map(|listener| {
+        axum::serve(listener, app.clone())
+            .with_graceful_shutdown(shutdown_requested())
+            .into_future()
+    });
+    let served = tokio::try_join!(readiness.announce(&bound), try_join_all(servers));
+
+    if let Some(ref path) = readiness.ready_file {
+        let _ = std::fs::remove_file(path);
+    }
+    served.map(|_| ())
+}
+
+/// Writes `contents` to `path` through a temporary file, so readers never see it half written
+fn write_ready_file(path: &Path, contents: &str) -> Result<(), std::io::Error> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, contents)?;
+    std::fs::rename(&partial, path)
 }
 
 /// Graceful shutdown signal handler
@@ -67,6 +305,15 @@ async fn shutdown_signal() {
         .expect("Failed to install CTRL+C signal handler");
 
     info!("Received shutdown signal, shutting down gracefully...");
+    SHUTDOWN.send_replace(true);
+    if let Err(e) = systemd::notify("STOPPING=1") {
+        warn!("Failed to notify systemd of the shutdown: {e}");
+    }
+}
+
+/// Resolves once a shutdown signal is received
+async fn shutdown_requested() {
+    let _ = shutdown_receiver().wait_for(|shutdown| *shutdown).await;
 }
 
 #[cfg(test)]
@@ -114,6 +361,47 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_listens_on_every_address() {
+        let app = create_test_app().await;
+        let temp_dir = TempDir::new().unwrap();
+        let ready_file = temp_dir.path().join("ready");
+
+        let addresses = vec!["127.0.0.1:0".to_string(), "127.0.0.1:0".to_string()];
+        let path = ready_file.clone();
+        let readiness = Readiness {
+            ready_file: Some(path),
+            warm: None,
+        };
+        let server = tokio::spawn(async move { run_server(app, &addresses, readiness).await });
+
+        let mut bound = None;
+        for _ in 0..100 {
+            if let Ok(contents) = std::fs::read_to_string(&ready_file) {
+                bound = Some(contents);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        let bound = bound.expect("the ready file is written once the server listens");
+        let bound: Vec<std::net::SocketAddr> =
+            bound.lines().map(|line| line.parse().unwrap()).collect();
+        assert_eq!(bound.len(), 2);
+        assert_ne!(
+            bound[0].port(),
+            bound[1].port(),
+            "each port 0 picks a free port"
+        );
+
+        for address in bound {
+            let response = reqwest::get(format!("http://{address}/health"))
+                .await
+                .unwrap();
+            assert!(response.status().is_success());
+        }
+        server.abort();
+    }
+
     #[tokio::test]
     async fn test_fees_endpoint_exists() {
         let app = create_test_app().await;
@@ -128,4 +416,68 @@ mod tests {
         // Will return 503 (no data) but endpoint exists
         assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
     }
+
+    #[tokio::test]
+    async fn test_health_stays_fast_while_estimating() {
+        use crate::bitcoin::{BitcoinClient, MockBitcoinClient};
+        use crate::service::Clock;
+        use bitcoin_augur::{MempoolSnapshot, MempoolTransaction};
+        use chrono::TimeZone;
+        use std::time::{Duration, Instant};
+
+        // A day of snapshots taken every 30 seconds, with a block every 10 minutes
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        let end = chrono::Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        for i in 0..2880u64 {
+            let transactions = (0..300)
+                .map(|j| MempoolTransaction::new(400 + j * 3, 500 + j * 41 + i % 97))
+                .collect();
+            let timestamp = end - chrono::Duration::seconds(30 * (2879 - i as i64));
+            let height = 850_000 + (i / 20) as u32;
+            store
+                .save_snapshot(&MempoolSnapshot::from_transactions(
+                    transactions,
+                    height,
+                    timestamp,
+                ))
+                .unwrap();
+        }
+        let collector = Arc::new(
+            MempoolCollector::new(
+                BitcoinClient::Mock(MockBitcoinClient::new()),
+                store,
+                FeeEstimator::new(),
+            )
+            .with_clock(Clock::simulated(end, chrono::Duration::seconds(30))),
+        );
+        let app = create_app(collector.clone());
+
+        // The test runtime has a single thread, so any estimation on it would stall /health
+        let recalculation = tokio::spawn({
+            let collector = collector.clone();
+            async move { collector.initialize_from_store().await }
+        });
+        let mut latencies = Vec::new();
+        while !recalculation.is_finished() {
+            let request = Request::builder()
+                .uri("/health")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let started = Instant::now();
+            let response = app.clone().oneshot(request).await.unwrap();
+            latencies.push(started.elapsed());
+            assert_eq!(response.status(), StatusCode::OK);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        recalculation.await.unwrap().unwrap();
+        assert!(collector.get_latest_estimate().await.is_some());
+
+        assert!(latencies.len() >= 10, "only {} requests", latencies.len());
+        let slowest = latencies.iter().max().unwrap();
+        assert!(
+            *slowest < Duration::from_millis(10),
+            "slowest /health took {slowest:?}"
+        );
+    }
 }
@@ -0,0 +1,124 @@
+//! Collection intervals that follow mempool activity
+//!
+//! A fixed interval polls the node as often on a quiet night as during a fee spike. An adaptive
+//! interval drops to its minimum right after a block is found or while the short-term inflow
+//! strays far from the long-term average, and backs off towards its maximum while neither
+//! happens, so quiet periods cost fewer RPC calls without slowing the reaction to busy ones.
+
+use bitcoin_augur::MempoolSnapshot;
+use std::time::Duration;
+
+/// Factor the interval grows by after each quiet cycle
+const BACKOFF_FACTOR: f64 = 1.5;
+
+/// Recent stretch whose inflow is compared against the whole estimation window
+const SHORT_TERM_MINUTES: i64 = 10;
+
+/// Bounds and sensitivity of an adaptive collection interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntervalPolicy {
+    /// Interval after a block or while the inflow is volatile
+    pub min: Duration,
+    /// Longest interval quiet periods back off to
+    pub max: Duration,
+    /// Relative difference between the short-term and long-term inflow rates above which the
+    /// mempool counts as volatile
+    pub volatility_threshold: f64,
+}
+
+impl IntervalPolicy {
+    /// Interval until the next cycle, given the `current` one, whether the latest cycle saw a
+    /// new block and the inflow [`volatility`] of its estimation window
+    pub fn next(&self, current: Duration, new_block: bool, volatility: Option<f64>) -> Duration {
+        if new_block || volatility.is_some_and(|v| v >= self.volatility_threshold) {
+            return self.min;
+        }
+        current.mul_f64(BACKOFF_FACTOR).clamp(self.min, self.max)
+    }
+}
+
+/// Relative difference between the inflow rate of the last [`SHORT_TERM_MINUTES`] of
+/// `snapshots` and that of all of them
+///
+/// Inflow is the weight the mempool gained between consecutive snapshots at the same height;
+/// blocks are left out so that confirmations do not count as negative inflow. Returns `None`
+/// while either rate cannot be measured or the long-term rate is zero.
+pub fn volatility(snapshots: &[MempoolSnapshot]) -> Option<f64> {
+    let latest = snapshots.last()?.timestamp;
+    let since = latest - chrono::Duration::minutes(SHORT_TERM_MINUTES);
+    let short_start = snapshots.partition_point(|snapshot| snapshot.timestamp < since);
+
+    let long_term = inflow_rate(snapshots)?;
+    let short_term = inflow_rate(&snapshots[short_start..])?;
+    (long_term > 0.0).then(|| (short_term - long_term).abs() / long_term)
+}
+
+/// Weight gained per second between consecutive `snapshots` at the same height
+fn inflow_rate(snapshots: &[MempoolSnapshot]) -> Option<f64> {
+    let (added, secs) = snapshots
+        .windows(2)
+        .filter(|pair| pair[0].block_height == pair[1].block_height)
+        .fold((0.0, 0.0), |(added, secs), pair| {
+            let growth = pair[1]
+                .total_weight()
+                .saturating_sub(pair[0].total_weight());
+            let elapsed = (pair[1].timestamp - pair[0].timestamp).num_milliseconds() as f64;
+            (added + growth as f64, secs + elapsed / 1000.0)
+        });
+    (secs > 0.0).then(|| added / secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::collections::BTreeMap;
+
+    const POLICY: IntervalPolicy = IntervalPolicy {
+        min: Duration::from_secs(10),
+        max: Duration::from_secs(60),
+        volatility_threshold: 0.5,
+    };
+
+    /// One snapshot a minute at a single height, gaining `growth(minute)` weight each minute
+    fn snapshots(minutes: i64, growth: impl Fn(i64) -> u64) -> Vec<MempoolSnapshot> {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let mut weight = 1_000;
+        (0..minutes)
+            .map(|minute| {
+                weight += growth(minute);
+                MempoolSnapshot::new(
+                    850_000,
+                    start + chrono::Duration::minutes(minute),
+                    BTreeMap::from([(100, weight)]),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_interval_backs_off_until_activity() {
+        let quiet = POLICY.next(Duration::from_secs(10), false, Some(0.1));
+        assert_eq!(quiet, Duration::from_secs(15));
+        assert_eq!(
+            POLICY.next(Duration::from_secs(50), false, None),
+            POLICY.max
+        );
+
+        assert_eq!(POLICY.next(POLICY.max, true, None), POLICY.min);
+        assert_eq!(POLICY.next(POLICY.max, false, Some(0.5)), POLICY.min);
+    }
+
+    #[test]
+    fn test_volatility_compares_recent_inflow() {
+        let steady = snapshots(60, |_| 100);
+        assert_eq!(volatility(&steady), Some(0.0));
+
+        // The last few minutes gain ten times as much as the rest of the hour
+        let surge = snapshots(60, |minute| if minute >= 55 { 1_000 } else { 100 });
+        assert!(volatility(&surge).unwrap() > 1.0);
+
+        assert_eq!(volatility(&snapshots(60, |_| 0)), None);
+        assert_eq!(volatility(&steady[..1]), None);
+    }
+}
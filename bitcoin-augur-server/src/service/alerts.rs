@@ -0,0 +1,457 @@
+//! Alert rules evaluated after every collection cycle
+//!
+//! A rule watches one metric of the latest estimate or snapshot, such as `fee_rate:6:0.95`, and
+//! fires once its condition has held for `for_secs`. Firing and resolving are logged at the
+//! rule's severity, posted to a webhook when one is configured, and the state of every rule is
+//! exposed as the `augur_alert_firing` Prometheus gauge at `GET /metrics`.
+
+use bitcoin_augur::{FeeEstimate, MempoolSnapshot, WU_PER_BYTE};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// A value an alert rule can watch, named like the Grafana metrics
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AlertMetric {
+    /// Estimated fee rate in sat/vB to confirm within `blocks` with `probability`
+    FeeRate { blocks: u32, probability: f64 },
+    /// Virtual size of the mempool
+    MempoolVsize,
+    /// Total fees of the mempool in sats
+    MempoolTotalFees,
+}
+
+impl AlertMetric {
+    /// Current value of the metric, if the estimate has it
+    fn value(self, estimate: Option<&FeeEstimate>, snapshot: &MempoolSnapshot) -> Option<f64> {
+        match self {
+            Self::FeeRate {
+                blocks,
+                probability,
+            } => estimate?.get_fee_rate(blocks, probability),
+            Self::MempoolVsize => Some((snapshot.total_weight() as f64 / WU_PER_BYTE).ceil()),
+            Self::MempoolTotalFees => Some(snapshot.total_value() as f64),
+        }
+    }
+}
+
+impl TryFrom<String> for AlertMetric {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let unknown = || {
+            format!(
+                "unknown alert metric {name:?}, expected fee_rate:{{blocks}}:{{probability}}, \
+                 mempool_vsize or mempool_total_fees"
+            )
+        };
+        if let Some(rest) = name.strip_prefix("fee_rate:") {
+            let (blocks, probability) = rest.split_once(':').ok_or_else(unknown)?;
+            return Ok(Self::FeeRate {
+                blocks: blocks.parse().map_err(|_| unknown())?,
+                probability: probability.parse().map_err(|_| unknown())?,
+            });
+        }
+        match name.as_str() {
+            "mempool_vsize" => Ok(Self::MempoolVsize),
+            "mempool_total_fees" => Ok(Self::MempoolTotalFees),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl From<AlertMetric> for String {
+    fn from(metric: AlertMetric) -> Self {
+        metric.to_string()
+    }
+}
+
+impl fmt::Display for AlertMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FeeRate {
+                blocks,
+                probability,
+            } => write!(f, "fee_rate:{}:{}", blocks, probability),
+            Self::MempoolVsize => write!(f, "mempool_vsize"),
+            Self::MempoolTotalFees => write!(f, "mempool_total_fees"),
+        }
+    }
+}
+
+/// Which side of the threshold makes a rule's condition hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertCondition {
+    Above,
+    Below,
+}
+
+/// How urgent a firing alert is, which also picks the level it is logged at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for AlertSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A configured alert rule, e.g. the 6 block 95% fee rate above 200 sat/vB for 30 minutes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Name the alert is reported under
+    pub name: String,
+    pub metric: AlertMetric,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    /// Seconds the condition must hold before the alert fires (default: 0, at once)
+    #[serde(default)]
+    pub for_secs: u64,
+    #[serde(default)]
+    pub severity: AlertSeverity,
+}
+
+impl AlertRule {
+    fn holds(&self, value: f64) -> bool {
+        match self.condition {
+            AlertCondition::Above => value > self.threshold,
+            AlertCondition::Below => value < self.threshold,
+        }
+    }
+}
+
+/// Whether a notification reports an alert starting or ending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// Notification sent when an alert fires or resolves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertNotification {
+    pub rule: String,
+    pub state: AlertState,
+    pub severity: AlertSeverity,
+    pub metric: AlertMetric,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    /// Value of the metric when the notification was raised
+    pub value: f64,
+    /// When the condition started holding
+    pub since: DateTime<Utc>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Current state of one rule, as exposed at `GET /metrics`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertStatus {
+    pub rule: String,
+    pub severity: AlertSeverity,
+    pub firing: bool,
+    /// Latest value of the metric, if it has been seen
+    pub value: Option<f64>,
+}
+
+/// Progress of one rule between cycles
+#[derive(Debug, Clone, Default)]
+struct RuleState {
+    /// When the condition started holding, if it holds
+    pending_since: Option<DateTime<Utc>>,
+    firing: bool,
+    value: Option<f64>,
+}
+
+/// Alert rules and their state
+pub struct Alerts {
+    rules: Vec<AlertRule>,
+    states: Mutex<Vec<RuleState>>,
+    webhook: Option<(reqwest::Client, String)>,
+}
+
+impl Alerts {
+    /// Evaluates `rules`, failing if they are unnamed or share names
+    pub fn new(rules: Vec<AlertRule>) -> Result<Self, String> {
+        let mut names = HashSet::new();
+        for rule in &rules {
+            if rule.name.trim().is_empty() {
+                return Err("alert rules must be named".to_string());
+            }
+            if !names.insert(rule.name.as_str()) {
+                return Err(format!(
+                    "alert rule {name:?} is defined twice",
+                    name = rule.name
+                ));
+            }
+        }
+        let states = Mutex::new(vec![RuleState::default(); rules.len()]);
+        Ok(Self {
+            rules,
+            states,
+            webhook: None,
+        })
+    }
+
+    /// Also posts every notification as JSON to `url`
+    pub fn with_webhook(mut self, url: impl Into<String>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("HTTP client with a timeout builds");
+        self.webhook = Some((http, url.into()));
+        self
+    }
+
+    /// Advances every rule to `now` from the latest estimate and snapshot, returning the
+    /// alerts that fired or resolved
+    ///
+    /// A rule whose metric is missing, such as a fee rate the estimate does not have, keeps
+    /// its state until it is seen again.
+    pub fn evaluate(
+        &self,
+        now: DateTime<Utc>,
+        estimate: Option<&FeeEstimate>,
+        snapshot: &MempoolSnapshot,
+    ) -> Vec<AlertNotification> {
+        let mut states = self.states.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut notifications = Vec::new();
+        for (rule, state) in self.rules.iter().zip(states.iter_mut()) {
+            let Some(value) = rule.metric.value(estimate, snapshot) else {
+                continue;
+            };
+            state.value = Some(value);
+
+            let notify = |state: AlertState, since: DateTime<Utc>| AlertNotification {
+                rule: rule.name.clone(),
+                state,
+                severity: rule.severity,
+                metric: rule.metric,
+                condition: rule.condition,
+                threshold: rule.threshold,
+                value,
+                since,
+                timestamp: now,
+            };
+            if rule.holds(value) {
+                let since = *state.pending_since.get_or_insert(now);
+                let held = (now - since).num_seconds().max(0) as u64;
+                if !state.firing && held >= rule.for_secs {
+                    state.firing = true;
+                    notifications.push(notify(AlertState::Firing, since));
+                }
+            } else if let Some(since) = state.pending_since.take() {
+                if state.firing {
+                    state.firing = false;
+                    notifications.push(notify(AlertState::Resolved, since));
+                }
+            }
+        }
+        notifications
+    }
+
+    /// Logs `notifications` and posts them to the webhook in the background
+    pub fn notify(&self, notifications: Vec<AlertNotification>) {
+        for notification in &notifications {
+            log_notification(notification);
+        }
+        let Some((http, url)) = &self.webhook else {
+            return;
+        };
+        for notification in notifications {
+            let http = http.clone();
+            let url = url.clone();
+            // Delivery is best effort and never holds up a collection
+            tokio::spawn(async move {
+                let result = async {
+                    http.post(&url)
+                        .json(&notification)
+                        .send()
+                        .await?
+                        .error_for_status()
+                }
+                .await;
+                if let Err(e) = result {
+                    warn!(
+                        "Failed to post alert {rule:?} to webhook: {e}",
+                        rule = notification.rule
+                    );
+                }
+            });
+        }
+    }
+
+    /// State of every rule, in configuration order
+    pub fn statuses(&self) -> Vec<AlertStatus> {
+        let states = self.states.lock().unwrap_or_else(PoisonError::into_inner);
+        self.rules
+            .iter()
+            .zip(states.iter())
+            .map(|(rule, state)| AlertStatus {
+                rule: rule.name.clone(),
+                severity: rule.severity,
+                firing: state.firing,
+                value: state.value,
+            })
+            .collect()
+    }
+}
+
+/// Logs `notification` at its severity, or at info once resolved
+fn log_notification(notification: &AlertNotification) {
+    let AlertNotification {
+        rule,
+        severity,
+        metric,
+        condition,
+        threshold,
+        value,
+        since,
+        ..
+    } = notification;
+    match (notification.state, severity) {
+        (AlertState::Resolved, _) => {
+            info!("Alert {rule:?} resolved: {metric} is {value}, firing since {since}")
+        }
+        (AlertState::Firing, AlertSeverity::Critical) => error!(
+            "Alert {rule:?} ({severity}) firing: {metric} is {value}, {condition:?} {threshold} \
+             since {since}"
+        ),
+        (AlertState::Firing, AlertSeverity::Warning) => warn!(
+            "Alert {rule:?} ({severity}) firing: {metric} is {value}, {condition:?} {threshold} \
+             since {since}"
+        ),
+        (AlertState::Firing, AlertSeverity::Info) => info!(
+            "Alert {rule:?} ({severity}) firing: {metric} is {value}, {condition:?} {threshold} \
+             since {since}"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::{BlockTarget, OrderedFloat};
+    use std::collections::BTreeMap;
+
+    fn estimate(fee_rate: f64) -> FeeEstimate {
+        let probabilities = BTreeMap::from([(OrderedFloat(0.95), fee_rate)]);
+        FeeEstimate::new(
+            BTreeMap::from([(6, BlockTarget::new(6, probabilities))]),
+            Utc::now(),
+        )
+    }
+
+    fn high_fees() -> AlertRule {
+        AlertRule {
+            name: "high_fees".to_string(),
+            metric: AlertMetric::FeeRate {
+                blocks: 6,
+                probability: 0.95,
+            },
+            condition: AlertCondition::Above,
+            threshold: 200.0,
+            for_secs: 1800,
+            severity: AlertSeverity::Critical,
+        }
+    }
+
+    #[test]
+    fn test_parse_alert_metric() {
+        assert_eq!(
+            AlertMetric::try_from("fee_rate:6:0.95".to_string()),
+            Ok(AlertMetric::FeeRate {
+                blocks: 6,
+                probability: 0.95
+            })
+        );
+        assert_eq!(
+            AlertMetric::try_from("mempool_vsize".to_string()),
+            Ok(AlertMetric::MempoolVsize)
+        );
+        assert!(AlertMetric::try_from("fee_rate:6".to_string()).is_err());
+        assert!(AlertMetric::try_from("block_height".to_string()).is_err());
+
+        let metric = AlertMetric::FeeRate {
+            blocks: 6,
+            probability: 0.95,
+        };
+        assert_eq!(String::from(metric), "fee_rate:6:0.95");
+    }
+
+    #[test]
+    fn test_alert_fires_after_duration_and_resolves() {
+        let alerts = Alerts::new(vec![high_fees()]).unwrap();
+        let snapshot = MempoolSnapshot::empty(100, Utc::now());
+        let start = Utc::now();
+        let minutes = |m| start + chrono::Duration::minutes(m);
+
+        assert!(alerts
+            .evaluate(start, Some(&estimate(250.0)), &snapshot)
+            .is_empty());
+        assert!(alerts
+            .evaluate(minutes(20), Some(&estimate(300.0)), &snapshot)
+            .is_empty());
+        // Without an estimate the rule keeps waiting
+        assert!(alerts.evaluate(minutes(25), None, &snapshot).is_empty());
+
+        let fired = alerts.evaluate(minutes(30), Some(&estimate(260.0)), &snapshot);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].state, AlertState::Firing);
+        assert_eq!(fired[0].since, start);
+        assert_eq!(fired[0].value, 260.0);
+        assert!(alerts.statuses()[0].firing);
+
+        // Fires once for as long as the condition holds
+        assert!(alerts
+            .evaluate(minutes(40), Some(&estimate(270.0)), &snapshot)
+            .is_empty());
+
+        let resolved = alerts.evaluate(minutes(50), Some(&estimate(150.0)), &snapshot);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].state, AlertState::Resolved);
+        assert!(!alerts.statuses()[0].firing);
+        assert_eq!(alerts.statuses()[0].value, Some(150.0));
+    }
+
+    #[test]
+    fn test_alert_interrupted_before_duration_does_not_fire() {
+        let alerts = Alerts::new(vec![high_fees()]).unwrap();
+        let snapshot = MempoolSnapshot::empty(100, Utc::now());
+        let start = Utc::now();
+        let minutes = |m| start + chrono::Duration::minutes(m);
+
+        alerts.evaluate(start, Some(&estimate(250.0)), &snapshot);
+        assert!(alerts
+            .evaluate(minutes(20), Some(&estimate(100.0)), &snapshot)
+            .is_empty());
+        assert!(alerts
+            .evaluate(minutes(40), Some(&estimate(250.0)), &snapshot)
+            .is_empty());
+        assert!(!alerts.statuses()[0].firing);
+    }
+
+    #[test]
+    fn test_alert_rules_must_have_unique_names() {
+        assert!(Alerts::new(vec![high_fees(), high_fees()]).is_err());
+        let mut unnamed = high_fees();
+        unnamed.name = " ".to_string();
+        assert!(Alerts::new(vec![unnamed]).is_err());
+    }
+}
@@ -0,0 +1,487 @@
+//! Naive reference strategies served next to the augur estimate for A/B evaluation
+//!
+//! After every augur estimate, each enabled baseline estimates from the same snapshots and
+//! the results are served side by side at `GET /fees/compare`. `top-vmb` reads the fee rates
+//! ranking among the top N vMB of the newest snapshot; `core` passes through the node's own
+//! `estimatesmartfee` answers and `mempool-space` the recommended fees of a mempool.space
+//! instance, both fetched once per collection. An optional weighted ensemble combines augur
+//! with any of them.
+
+use bitcoin_augur::{
+    BlockTarget, EnsembleStrategy, EstimationStrategy, FeeEstimate, FeeEstimator, MempoolSnapshot,
+    TopVmbStrategy,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::bitcoin::{BitcoinRpc, RpcError};
+
+/// A baseline strategy that can be enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Baseline {
+    /// Fee rates ranking among the top N vMB of the current mempool
+    TopVmb,
+    /// The node's `estimatesmartfee`
+    Core,
+    /// Recommended fees of a mempool.space instance
+    MempoolSpace,
+}
+
+/// Name of the combined estimate served next to the baselines
+const ENSEMBLE: &str = "ensemble";
+
+/// Estimate suggesting one rate per block target at every confidence level
+fn flat_estimate(
+    rates: &BTreeMap<u32, f64>,
+    probabilities: &[f64],
+    snapshots: &[MempoolSnapshot],
+    num_blocks: Option<f64>,
+) -> FeeEstimate {
+    let timestamp = snapshots
+        .last()
+        .map_or_else(Utc::now, |snapshot| snapshot.timestamp);
+    let estimates = rates
+        .iter()
+        .filter(|(&blocks, _)| num_blocks.is_none_or(|target| f64::from(blocks) == target))
+        .map(|(&blocks, &rate)| {
+            let fee_rates = probabilities.iter().map(|&probability| (probability, rate));
+            (blocks, BlockTarget::from_fee_rates(blocks, fee_rates))
+        })
+        .collect();
+    FeeEstimate::new(estimates, timestamp)
+}
+
+/// The Bitcoin node's own smart fee estimates, as of the last [`refresh`](Self::refresh)
+///
+/// Core estimates one fee rate per target, so every confidence level gets the same rate.
+pub struct CoreSmartFee {
+    block_targets: Vec<u32>,
+    probabilities: Vec<f64>,
+    rates: std::sync::RwLock<BTreeMap<u32, f64>>,
+}
+
+impl Default for CoreSmartFee {
+    fn default() -> Self {
+        Self {
+            block_targets: FeeEstimator::DEFAULT_BLOCK_TARGETS
+                .iter()
+                .map(|&target| target as u32)
+                .collect(),
+            probabilities: FeeEstimator::DEFAULT_PROBABILITIES.to_vec(),
+            rates: std::sync::RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl CoreSmartFee {
+    /// Asks `client` for its estimate of every block target, replacing the previous answers
+    ///
+    /// On failure no estimates are kept, so outdated ones are never served.
+    pub async fn refresh(&self, client: &impl BitcoinRpc) -> Result<(), RpcError> {
+        let mut rates = BTreeMap::new();
+        let result = async {
+            for &target in &self.block_targets {
+                if let Some(rate) = client.estimate_smart_fee(target).await? {
+                    rates.insert(target, rate);
+                }
+            }
+            Ok(())
+        }
+        .await;
+        if result.is_err() {
+            rates.clear();
+        }
+        *self.rates.write().unwrap_or_else(|e| e.into_inner()) = rates;
+        result
+    }
+}
+
+impl EstimationStrategy for CoreSmartFee {
+    fn name(&self) -> &str {
+        "core"
+    }
+
+    fn estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> bitcoin_augur::Result<FeeEstimate> {
+        let rates = self.rates.read().unwrap_or_else(|e| e.into_inner());
+        Ok(flat_estimate(
+            &rates,
+            &self.probabilities,
+            snapshots,
+            num_blocks,
+        ))
+    }
+}
+
+/// Fee rates recommended by mempool.space's `GET /api/v1/fees/recommended`, in sat/vB
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendedFees {
+    fastest_fee: f64,
+    half_hour_fee: f64,
+    hour_fee: f64,
+    economy_fee: f64,
+}
+
+/// The recommended fees of a mempool.space instance, as of the last [`refresh`](Self::refresh)
+///
+/// Its next block, half hour, hour and economy recommendations stand for the 1, 3, 6 and 144
+/// block targets, at every confidence level.
+pub struct MempoolSpaceFees {
+    url: String,
+    http: reqwest::Client,
+    probabilities: Vec<f64>,
+    rates: std::sync::RwLock<BTreeMap<u32, f64>>,
+}
+
+impl MempoolSpaceFees {
+    /// Public instance used unless another is configured
+    pub const DEFAULT_URL: &'static str = "https://mempool.space";
+
+    /// Reads the recommended fees of the instance at `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("HTTP client with a timeout builds"),
+            probabilities: FeeEstimator::DEFAULT_PROBABILITIES.to_vec(),
+            rates: std::sync::RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Fetches the current recommendations, replacing the previous ones
+    ///
+    /// On failure no recommendations are kept, so outdated ones are never served.
+    pub async fn refresh(&self) -> Result<(), reqwest::Error> {
+        let result = async {
+            self.http
+                .get(format!("{}/api/v1/fees/recommended", self.url))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<RecommendedFees>()
+                .await
+        }
+        .await;
+        let rates = match &result {
+            Ok(fees) => BTreeMap::from([
+                (1, fees.fastest_fee),
+                (3, fees.half_hour_fee),
+                (6, fees.hour_fee),
+                (144, fees.economy_fee),
+            ]),
+            Err(_) => BTreeMap::new(),
+        };
+        *self.rates.write().unwrap_or_else(|e| e.into_inner()) = rates;
+        result.map(|_| ())
+    }
+}
+
+impl EstimationStrategy for MempoolSpaceFees {
+    fn name(&self) -> &str {
+        "mempool-space"
+    }
+
+    fn estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> bitcoin_augur::Result<FeeEstimate> {
+        let rates = self.rates.read().unwrap_or_else(|e| e.into_inner());
+        Ok(flat_estimate(
+            &rates,
+            &self.probabilities,
+            snapshots,
+            num_blocks,
+        ))
+    }
+}
+
+/// The enabled baselines and their latest estimates
+pub struct Baselines {
+    top_vmb: Option<TopVmbStrategy>,
+    core: Option<CoreSmartFee>,
+    mempool_space: Option<MempoolSpaceFees>,
+    ensemble_weights: BTreeMap<String, f64>,
+    latest: RwLock<BTreeMap<String, FeeEstimate>>,
+}
+
+impl Baselines {
+    /// Runs each baseline in `enabled`, reading mempool.space at its public instance
+    pub fn new(enabled: &[Baseline]) -> Self {
+        Self {
+            top_vmb: enabled
+                .contains(&Baseline::TopVmb)
+                .then(TopVmbStrategy::new),
+            core: enabled
+                .contains(&Baseline::Core)
+                .then(CoreSmartFee::default),
+            mempool_space: enabled
+                .contains(&Baseline::MempoolSpace)
+                .then(|| MempoolSpaceFees::new(MempoolSpaceFees::DEFAULT_URL)),
+            ensemble_weights: BTreeMap::new(),
+            latest: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Reads the recommended fees of the mempool.space instance at `url` instead
+    pub fn with_mempool_space_url(mut self, url: &str) -> Self {
+        if self.mempool_space.is_some() {
+            self.mempool_space = Some(MempoolSpaceFees::new(url));
+        }
+        self
+    }
+
+    /// Also serves the weighted median of augur and the baselines, keyed by strategy name
+    ///
+    /// Strategies without a weight are left out of the ensemble; a name that is neither
+    /// `augur` nor an enabled baseline, or a weight that is not positive, is rejected.
+    pub fn with_ensemble(mut self, weights: BTreeMap<String, f64>) -> Result<Self, String> {
+        let names = self
+            .strategies()
+            .iter()
+            .map(|strategy| strategy.name())
+            .collect::<Vec<_>>();
+        for (name, &weight) in &weights {
+            if name != "augur" && !names.contains(&name.as_str()) {
+                return Err(format!(
+                    "Ensemble member {name} is neither augur nor an enabled baseline"
+                ));
+            }
+            if !weight.is_finite() || weight <= 0.0 {
+                return Err(format!(
+                    "Weight of ensemble member {name} must be positive, got {weight}"
+                ));
+            }
+        }
+        self.ensemble_weights = weights;
+        Ok(self)
+    }
+
+    /// The enabled strategies
+    fn strategies(&self) -> Vec<&dyn EstimationStrategy> {
+        let mut strategies: Vec<&dyn EstimationStrategy> = Vec::new();
+        if let Some(top_vmb) = &self.top_vmb {
+            strategies.push(top_vmb);
+        }
+        if let Some(core) = &self.core {
+            strategies.push(core);
+        }
+        if let Some(mempool_space) = &self.mempool_space {
+            strategies.push(mempool_space);
+        }
+        strategies
+    }
+
+    /// Re-estimates every baseline from `snapshots`, refreshing the node's estimates and
+    /// mempool.space's recommendations first, then combines them with `augur` into the ensemble
+    ///
+    /// A failing baseline is logged and left out until it recovers.
+    pub async fn update(
+        &self,
+        client: &impl BitcoinRpc,
+        snapshots: &[MempoolSnapshot],
+        augur: &FeeEstimate,
+    ) {
+        if let Some(core) = &self.core {
+            if let Err(e) = core.refresh(client).await {
+                warn!("Failed to fetch the node's smart fee estimates: {e}");
+            }
+        }
+        if let Some(mempool_space) = &self.mempool_space {
+            if let Err(e) = mempool_space.refresh().await {
+                warn!("Failed to fetch mempool.space's recommended fees: {e}");
+            }
+        }
+
+        let mut latest = BTreeMap::new();
+        for strategy in self.strategies() {
+            match strategy.estimate(snapshots, None) {
+                Ok(estimate) if !estimate.is_empty() => {
+                    latest.insert(strategy.name().to_string(), estimate);
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Baseline {name} failed to estimate: {e}",
+                    name = strategy.name()
+                ),
+            }
+        }
+        if !self.ensemble_weights.is_empty() {
+            let members =
+                self.ensemble_weights
+                    .iter()
+                    .filter_map(|(name, &weight)| match name.as_str() {
+                        "augur" => Some((augur, weight)),
+                        name => latest.get(name).map(|estimate| (estimate, weight)),
+                    });
+            let ensemble = EnsembleStrategy::combine(members);
+            if !ensemble.is_empty() {
+                latest.insert(ENSEMBLE.to_string(), ensemble);
+            }
+        }
+        *self.latest.write().await = latest;
+    }
+
+    /// Latest estimate of each baseline, keyed by strategy name
+    pub async fn latest(&self) -> BTreeMap<String, FeeEstimate> {
+        self.latest.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::{BitcoinClient, MockBitcoinClient, ReplayBitcoinClient};
+    use bitcoin_augur::BlockTargetKey;
+    use tempfile::TempDir;
+
+    fn snapshot() -> MempoolSnapshot {
+        MempoolSnapshot::new(850_000, Utc::now(), BTreeMap::from([(300, 8_000_000)]))
+    }
+
+    #[tokio::test]
+    async fn test_update_runs_enabled_baselines() {
+        let client = BitcoinClient::Mock(MockBitcoinClient::new());
+        let baselines = Baselines::new(&[Baseline::TopVmb, Baseline::Core]);
+        baselines
+            .update(&client, &[snapshot()], &FeeEstimate::empty(Utc::now()))
+            .await;
+
+        let latest = baselines.latest().await;
+        assert_eq!(latest.keys().collect::<Vec<_>>(), vec!["core", "top-vmb"]);
+        // The mock node answers 10 sat/vB divided by the target
+        assert_eq!(latest["core"].get_fee_rate(3, 0.05), Some(10.0 / 3.0));
+        assert_eq!(latest["core"].get_fee_rate(3, 0.95), Some(10.0 / 3.0));
+        assert_eq!(latest["core"].get_fee_rate(144, 0.5), Some(1.0));
+        assert!(latest["top-vmb"].get_fee_rate(3, 0.5).unwrap() > 19.0);
+
+        let only_top = Baselines::new(&[Baseline::TopVmb]);
+        only_top
+            .update(&client, &[snapshot()], &FeeEstimate::empty(Utc::now()))
+            .await;
+        assert_eq!(only_top.latest().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_core_failure_drops_its_estimates() {
+        let core = CoreSmartFee::default();
+        let mock = BitcoinClient::Mock(MockBitcoinClient::new());
+        core.refresh(&mock).await.unwrap();
+        let estimate = core.estimate(&[snapshot()], Some(6.0)).unwrap();
+        assert_eq!(estimate.available_targets(), vec![BlockTargetKey::from(6)]);
+
+        let dir = TempDir::new().unwrap();
+        let replay = BitcoinClient::Replay(ReplayBitcoinClient::new(dir.path()).unwrap());
+        assert!(core.refresh(&replay).await.is_err());
+        assert!(core
+            .estimate(&[snapshot()], None)
+            .unwrap()
+            .estimates
+            .is_empty());
+
+        let baselines = Baselines::new(&[Baseline::Core]);
+        baselines
+            .update(&replay, &[snapshot()], &FeeEstimate::empty(Utc::now()))
+            .await;
+        assert!(baselines.latest().await.is_empty());
+    }
+
+    /// Serves fixed recommendations like mempool.space, returning its URL
+    async fn serve_recommended_fees() -> String {
+        let app = axum::Router::new().route(
+            "/api/v1/fees/recommended",
+            axum::routing::get(|| async {
+                axum::Json(serde_json::json!({
+                    "fastestFee": 12,
+                    "halfHourFee": 8,
+                    "hourFee": 6,
+                    "economyFee": 2,
+                    "minimumFee": 1
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_mempool_space_recommendations() {
+        let fees = MempoolSpaceFees::new(format!("{}/", serve_recommended_fees().await));
+        fees.refresh().await.unwrap();
+        let estimate = fees.estimate(&[snapshot()], None).unwrap();
+        assert_eq!(
+            estimate.available_targets(),
+            [1, 3, 6, 144].map(BlockTargetKey::from)
+        );
+        assert_eq!(estimate.get_fee_rate(3, 0.95), Some(8.0));
+        assert_eq!(estimate.get_fee_rate(144, 0.05), Some(2.0));
+
+        let unreachable = MempoolSpaceFees::new("http://127.0.0.1:1");
+        assert!(unreachable.refresh().await.is_err());
+        assert!(unreachable
+            .estimate(&[snapshot()], None)
+            .unwrap()
+            .estimates
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_takes_weighted_median() {
+        let client = BitcoinClient::Mock(MockBitcoinClient::new());
+        let url = serve_recommended_fees().await;
+        let baselines = Baselines::new(&[Baseline::Core, Baseline::MempoolSpace])
+            .with_mempool_space_url(&url)
+            .with_ensemble(BTreeMap::from([
+                ("augur".to_string(), 1.0),
+                ("core".to_string(), 1.0),
+                ("mempool-space".to_string(), 1.0),
+            ]))
+            .unwrap();
+        let augur = bitcoin_augur::TopVmbStrategy::new()
+            .estimate(&[snapshot()], None)
+            .unwrap();
+        baselines.update(&client, &[snapshot()], &augur).await;
+
+        let latest = baselines.latest().await;
+        assert_eq!(
+            latest.keys().collect::<Vec<_>>(),
+            vec!["core", "ensemble", "mempool-space"]
+        );
+        // Top-vMB suggests about 20 sat/vB for 3 blocks, the mock node 3.3 and mempool.space 8
+        assert_eq!(latest["ensemble"].get_fee_rate(3, 0.5), Some(8.0));
+        // Only mempool.space answers for 1 block, and two equal weights meet in the middle
+        assert_eq!(latest["ensemble"].get_fee_rate(1, 0.5), Some(12.0));
+        assert_eq!(
+            latest["ensemble"].get_fee_rate(9, 0.5),
+            Some((augur.get_fee_rate(9, 0.5).unwrap() + 10.0 / 9.0) / 2.0)
+        );
+
+        let weights = |name: &str, weight| BTreeMap::from([(name.to_string(), weight)]);
+        let core = || Baselines::new(&[Baseline::Core]);
+        assert!(core().with_ensemble(weights("top-vmb", 1.0)).is_err());
+        assert!(core().with_ensemble(weights("core", 0.0)).is_err());
+        assert!(core().with_ensemble(weights("augur", 2.0)).is_ok());
+    }
+
+    #[test]
+    fn test_baseline_names() {
+        assert_eq!(
+            serde_json::from_str::<Vec<Baseline>>(r#"["top-vmb", "core", "mempool-space"]"#)
+                .unwrap(),
+            vec![Baseline::TopVmb, Baseline::Core, Baseline::MempoolSpace]
+        );
+    }
+}
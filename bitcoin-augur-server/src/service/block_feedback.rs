@@ -0,0 +1,303 @@
+//! Fee rates that new blocks actually confirmed, and how estimates fared against them
+//!
+//! After each new block, the collector fetches the block's transactions and buckets them by fee
+//! rate like a mempool snapshot. The block's realized threshold is the lowest fee rate it
+//! confirmed once the cheapest [`THRESHOLD_TAIL`] of its weight is left out. That tail is mostly
+//! parents paid for by their children and transactions the miner chose to include. Realized
+//! blocks are appended to a file in the data directory next to the snapshots, so historical
+//! evaluation can use them instead of inferring each threshold from snapshot outflow.
+//!
+//! The estimate published at the first snapshot of each block is then scored as the following
+//! blocks are realized. A target of `n` blocks is resolved once the next `n` blocks are realized.
+//! It counts as confirmed if any of them had a threshold at or below the estimated fee rate,
+//! as in [`crate::evaluation`].
+
+use bitcoin_augur::{FeeEstimate, MempoolSnapshot};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::bitcoin::MinedBlock;
+use crate::evaluation::CalibrationCell;
+
+/// Share of a block's weight, from its lowest fee rate up, left out of its threshold
+pub const THRESHOLD_TAIL: f64 = 0.05;
+
+/// Realized blocks kept in memory, about a week of blocks
+const MAX_REALIZED_BLOCKS: usize = 1_008;
+
+/// What a mined block confirmed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedBlock {
+    pub height: u32,
+    pub time: DateTime<Utc>,
+    /// Lowest fee rate confirmed in sat/vB, leaving out the cheapest tail of the block; `None`
+    /// for a block without fee-paying transactions
+    pub threshold: Option<f64>,
+    /// Transactions confirmed, except the coinbase
+    pub transactions: usize,
+    /// Weight confirmed in each fee rate bucket
+    pub bucketed_weights: BTreeMap<i32, u64>,
+}
+
+impl RealizedBlock {
+    /// Buckets the transactions of `block` and finds its threshold
+    pub fn from_block(block: &MinedBlock) -> Self {
+        let bucketed_weights = MempoolSnapshot::from_transactions(
+            block.transactions.clone(),
+            block.height,
+            block.time,
+        )
+        .bucketed_weights;
+        let total: u64 = bucketed_weights.values().sum();
+        let covered = total as f64 * (1.0 - THRESHOLD_TAIL);
+
+        let mut above = 0;
+        let threshold = bucketed_weights
+            .iter()
+            .rev()
+            .find_map(|(&bucket, &weight)| {
+                above += weight;
+                (above as f64 >= covered).then(|| (f64::from(bucket) / 100.0).exp())
+            });
+
+        Self {
+            height: block.height,
+            time: block.time,
+            threshold: threshold.filter(|_| total > 0),
+            transactions: block.transactions.len(),
+            bucketed_weights,
+        }
+    }
+
+    /// Lowest fee rate the block confirmed, infinite if it confirmed none
+    pub fn confirmed_fee_rate(&self) -> f64 {
+        self.threshold.unwrap_or(f64::INFINITY)
+    }
+
+    /// Total weight confirmed
+    pub fn weight(&self) -> u64 {
+        self.bucketed_weights.values().sum()
+    }
+}
+
+/// Realized blocks, kept in memory and appended to a file, and the running calibration of
+/// the estimates scored against them
+pub struct BlockFeedback {
+    path: PathBuf,
+    state: Mutex<FeedbackState>,
+}
+
+#[derive(Default)]
+struct FeedbackState {
+    /// Latest realized blocks by height
+    realized: BTreeMap<u32, RealizedBlock>,
+    /// Estimates published at the first snapshot of each block, until their longest target
+    /// is resolved
+    pending: BTreeMap<u32, FeeEstimate>,
+    /// Cells keyed by confidence level (as in the API, e.g. "0.50"), then block target
+    calibration: BTreeMap<String, BTreeMap<u32, CalibrationCell>>,
+}
+
+impl BlockFeedback {
+    /// Opens the feedback file at `path`, loading the blocks already realized
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut state = FeedbackState::default();
+        for block in read_realized_blocks(&path)? {
+            state.realized.insert(block.height, block);
+        }
+        state.trim();
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Appends `block`, replacing any block realized at its height, and scores the pending
+    /// estimates it resolves
+    ///
+    /// The block is kept in memory even if writing it fails.
+    pub fn record_block(&self, block: RealizedBlock) -> io::Result<()> {
+        let line = serde_json::to_string(&block)?;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let height = block.height;
+        state.realized.insert(height, block);
+        state.score(height);
+        state.trim();
+        drop(state);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Scores `estimate`, made at the first snapshot of block `height`, as the next blocks are
+    /// realized
+    pub fn track_estimate(&self, height: u32, estimate: FeeEstimate) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.pending.entry(height).or_insert(estimate);
+    }
+
+    /// The latest `limit` realized blocks, newest first
+    pub fn recent(&self, limit: usize) -> Vec<RealizedBlock> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.realized.values().rev().take(limit).cloned().collect()
+    }
+
+    /// How often scored estimates confirmed within their target, by confidence and target
+    pub fn calibration(&self) -> BTreeMap<String, BTreeMap<u32, CalibrationCell>> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.calibration.clone()
+    }
+}
+
+impl FeedbackState {
+    /// Scores the target each pending estimate reaches at the newly realized block `height`
+    fn score(&mut self, height: u32) {
+        for (&made_at, estimate) in self.pending.range(..height) {
+            let target = height - made_at;
+            let Some(block_target) = estimate.get_estimates_for_target(target) else {
+                continue;
+            };
+            let outcome: Option<Vec<f64>> = (made_at + 1..=height)
+                .map(|h| self.realized.get(&h).map(RealizedBlock::confirmed_fee_rate))
+                .collect();
+            let Some(outcome) = outcome else {
+                continue;
+            };
+
+            for (probability, &fee_rate) in &block_target.probabilities {
+                self.calibration
+                    .entry(format!("{p:.2}", p = probability.0))
+                    .or_default()
+                    .entry(target)
+                    .or_default()
+                    .record(
+                        fee_rate,
+                        outcome.iter().any(|&mined_rate| mined_rate <= fee_rate),
+                    );
+            }
+        }
+
+        // Estimates whose every target is resolved are done
+        self.pending.retain(|&made_at, estimate| {
+            estimate
+                .targets()
+                .last()
+                .is_some_and(|(longest, _)| made_at.saturating_add(longest.blocks()) > height)
+        });
+    }
+
+    /// Forgets the oldest realized blocks beyond what is kept
+    fn trim(&mut self) {
+        while self.realized.len() > MAX_REALIZED_BLOCKS {
+            self.realized.pop_first();
+        }
+    }
+}
+
+/// Realized blocks in the file at `path`, oldest first, or none if it does not exist
+///
+/// Lines that do not parse are skipped.
+pub fn read_realized_blocks(path: &Path) -> io::Result<Vec<RealizedBlock>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut blocks = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(block) = serde_json::from_str(&line?) {
+            blocks.push(block);
+        }
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::{BlockTarget, MempoolTransaction, OrderedFloat};
+    use tempfile::TempDir;
+
+    fn block(height: u32, fee_rates: &[u64]) -> MinedBlock {
+        let start = DateTime::from_timestamp(1_737_374_400, 0).unwrap();
+        MinedBlock {
+            height,
+            time: start + chrono::Duration::minutes(i64::from(height % 1000) * 10),
+            // 1000 vB transactions paying each fee rate
+            transactions: fee_rates
+                .iter()
+                .map(|&rate| MempoolTransaction::new(4_000, rate * 1_000))
+                .collect(),
+        }
+    }
+
+    fn estimate(fee_rates: &[(u32, f64)]) -> FeeEstimate {
+        let estimates = fee_rates
+            .iter()
+            .map(|&(target, fee_rate)| {
+                let probabilities = BTreeMap::from([(OrderedFloat(0.5), fee_rate)]);
+                (target, BlockTarget::new(target, probabilities))
+            })
+            .collect();
+        FeeEstimate::new(estimates, Utc::now())
+    }
+
+    #[test]
+    fn test_threshold_leaves_out_the_cheapest_tail() {
+        // 20 transactions at 10 sat/vB and one at 1 sat/vB, under 5% of the weight
+        let mut fee_rates = vec![10; 20];
+        fee_rates.push(1);
+        let realized = RealizedBlock::from_block(&block(850_001, &fee_rates));
+
+        assert_eq!(realized.transactions, 21);
+        assert_eq!(realized.weight(), 21 * 4_000);
+        let threshold = realized.threshold.unwrap();
+        assert!((threshold - 10.0).abs() < 0.1, "threshold {threshold}");
+
+        let empty = RealizedBlock::from_block(&block(850_002, &[]));
+        assert_eq!(empty.threshold, None);
+        assert!(empty.confirmed_fee_rate().is_infinite());
+    }
+
+    #[test]
+    fn test_estimates_are_scored_as_blocks_are_realized() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("realized_blocks.jsonl");
+        let feedback = BlockFeedback::open(&path).unwrap();
+
+        // 5 sat/vB for the next block, 3 sat/vB within two blocks
+        feedback.track_estimate(850_000, estimate(&[(1, 5.0), (2, 3.0)]));
+        feedback
+            .record_block(RealizedBlock::from_block(&block(850_001, &[8; 10])))
+            .unwrap();
+        feedback
+            .record_block(RealizedBlock::from_block(&block(850_002, &[2; 10])))
+            .unwrap();
+
+        let calibration = feedback.calibration();
+        let cells = &calibration["0.50"];
+        // The first block only confirmed 8 sat/vB and above
+        assert_eq!((cells[&1].samples, cells[&1].confirmed), (1, 0));
+        // The second confirmed 2 sat/vB, within two blocks of the estimate
+        assert_eq!((cells[&2].samples, cells[&2].confirmed), (1, 1));
+
+        // Realized blocks survive a restart, newest first
+        let reopened = BlockFeedback::open(&path).unwrap();
+        let heights: Vec<_> = reopened.recent(10).iter().map(|b| b.height).collect();
+        assert_eq!(heights, vec![850_002, 850_001]);
+        assert!(reopened.calibration().is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+/// Source of the current time for the collector
+///
+/// Production uses the system clock. In test mode a simulated clock can be used instead: it
+/// only moves when the collector finishes a cycle, so a day of collection can be replayed in
+/// seconds while snapshot timestamps (and therefore the estimator's time windows) still span
+/// the full simulated period.
+#[derive(Clone, Debug, Default)]
+pub enum Clock {
+    #[default]
+    System,
+    Simulated(SimulatedClock),
+}
+
+/// Virtual clock advanced explicitly by a fixed step
+#[derive(Clone, Debug)]
+pub struct SimulatedClock {
+    now_ms: Arc<AtomicI64>,
+    step: Duration,
+}
+
+impl Clock {
+    /// Creates a simulated clock starting at `start` that advances by `step` per tick
+    pub fn simulated(start: DateTime<Utc>, step: Duration) -> Self {
+        Clock::Simulated(SimulatedClock {
+            now_ms: Arc::new(AtomicI64::new(start.timestamp_millis())),
+            step,
+        })
+    }
+
+    /// Returns the current (possibly virtual) time
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Clock::System => Utc::now(),
+            Clock::Simulated(clock) => {
+                DateTime::from_timestamp_millis(clock.now_ms.load(Ordering::SeqCst))
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    /// Advances a simulated clock by one step; no-op for the system clock
+    pub fn tick(&self) {
+        if let Clock::Simulated(clock) = self {
+            self.advance(clock.step);
+        }
+    }
+
+    /// Advances a simulated clock by an arbitrary duration; no-op for the system clock
+    pub fn advance(&self, by: Duration) {
+        if let Clock::Simulated(clock) = self {
+            clock
+                .now_ms
+                .fetch_add(by.num_milliseconds(), Ordering::SeqCst);
+        }
+    }
+
+    /// Moves a simulated clock to `at`; no-op for the system clock
+    pub fn set(&self, at: DateTime<Utc>) {
+        if let Clock::Simulated(clock) = self {
+            clock.now_ms.store(at.timestamp_millis(), Ordering::SeqCst);
+        }
+    }
+
+    /// Whether this clock is simulated
+    pub fn is_simulated(&self) -> bool {
+        matches!(self, Clock::Simulated(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_simulated_clock_moves_only_on_tick() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, Duration::minutes(5));
+
+        assert!(clock.is_simulated());
+        assert_eq!(clock.now(), start);
+
+        clock.tick();
+        clock.tick();
+        assert_eq!(clock.now(), start + Duration::minutes(10));
+
+        clock.advance(Duration::hours(1));
+        assert_eq!(clock.now(), start + Duration::minutes(70));
+
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+
+    #[test]
+    fn test_clones_share_simulated_time() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, Duration::seconds(30));
+        let shared = clock.clone();
+
+        clock.tick();
+        assert_eq!(shared.now(), start + Duration::seconds(30));
+    }
+
+    #[test]
+    fn test_system_clock_ignores_ticks() {
+        let clock = Clock::System;
+        let before = Utc::now();
+        clock.tick();
+        clock.advance(Duration::days(1));
+        clock.set(before - Duration::days(1));
+        assert!(clock.now() >= before);
+        assert!(clock.now() < before + Duration::hours(1));
+        assert!(!clock.is_simulated());
+    }
+}
@@ -0,0 +1,223 @@
+//! Timings of recent collection cycles
+//!
+//! Every collection fetches the mempool, buckets it into a snapshot, saves it, loads the
+//! estimation window and estimates. A cycle log keeps how long each step of the latest cycles
+//! took, in memory for `GET /admin/cycles` and as one JSON line per cycle in a file that is
+//! rotated once it holds as many cycles as are kept, so operators can see where time goes
+//! without attaching a profiler.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+
+/// What one collection cycle did and how long each step took
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleRecord {
+    pub started_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u32>,
+    /// Mempool transactions fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<usize>,
+    /// Milliseconds spent fetching the mempool over RPC
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetch_ms: Option<f64>,
+    /// Milliseconds spent bucketing the transactions into a snapshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_ms: Option<f64>,
+    /// Milliseconds spent saving the snapshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_ms: Option<f64>,
+    /// Milliseconds spent loading the estimation window, backfilling any gaps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_ms: Option<f64>,
+    /// Milliseconds spent estimating and publishing the estimate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate_ms: Option<f64>,
+    /// Milliseconds the whole cycle took
+    pub total_ms: f64,
+    /// Milliseconds until the next cycle, when the collection interval adapts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    /// Why the cycle failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CycleRecord {
+    /// A record of a cycle starting at `started_at`, with no step timed yet
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at,
+            block_height: None,
+            transactions: None,
+            fetch_ms: None,
+            parse_ms: None,
+            save_ms: None,
+            load_ms: None,
+            estimate_ms: None,
+            total_ms: 0.0,
+            interval_ms: None,
+            error: None,
+        }
+    }
+}
+
+/// Milliseconds since `start`, to the microsecond
+pub fn elapsed_ms(start: Instant) -> f64 {
+    (start.elapsed().as_secs_f64() * 1_000_000.0).round() / 1_000.0
+}
+
+/// The latest cycles in memory, mirrored to a rotating file
+pub struct CycleLog {
+    path: PathBuf,
+    capacity: usize,
+    state: Mutex<LogState>,
+}
+
+struct LogState {
+    /// Latest cycles, oldest first
+    recent: VecDeque<CycleRecord>,
+    writer: BufWriter<File>,
+    /// Cycles in the current file
+    written: usize,
+}
+
+impl CycleLog {
+    /// Opens the log at `path`, keeping the latest `capacity` cycles
+    ///
+    /// Cycles already in the file and its rotated predecessor are loaded, so the log survives
+    /// restarts. Lines that do not parse are skipped.
+    pub fn open(path: impl Into<PathBuf>, capacity: usize) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let previous = read_records(&rotated_path(&path))?;
+        let current = read_records(&path)?;
+        let written = current.len();
+        let mut recent: VecDeque<_> = previous.into_iter().chain(current).collect();
+        while recent.len() > capacity {
+            recent.pop_front();
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            capacity,
+            state: Mutex::new(LogState {
+                recent,
+                writer: BufWriter::new(file),
+                written,
+            }),
+        })
+    }
+
+    /// File the log is written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds `record`, rotating the file first if it is full
+    ///
+    /// The record is kept in memory even if writing it fails.
+    pub fn record(&self, record: CycleRecord) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let line = serde_json::to_string(&record)?;
+        state.recent.push_back(record);
+        if state.recent.len() > self.capacity {
+            state.recent.pop_front();
+        }
+
+        if state.written >= self.capacity {
+            state.writer.flush()?;
+            fs::rename(&self.path, rotated_path(&self.path))?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            state.writer = BufWriter::new(file);
+            state.written = 0;
+        }
+        writeln!(state.writer, "{line}")?;
+        state.writer.flush()?;
+        state.written += 1;
+        Ok(())
+    }
+
+    /// The latest `limit` cycles, newest first
+    pub fn recent(&self, limit: usize) -> Vec<CycleRecord> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.recent.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+/// Where a full log file is moved to make room for new cycles
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+/// Records in the file at `path`, or none if it does not exist
+fn read_records(path: &Path) -> io::Result<Vec<CycleRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(record) = serde_json::from_str(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    fn record(minute: i64) -> CycleRecord {
+        let start = DateTime::from_timestamp(1_737_374_400, 0).unwrap();
+        CycleRecord {
+            block_height: Some(850_000),
+            total_ms: 12.5,
+            ..CycleRecord::new(start + Duration::minutes(minute))
+        }
+    }
+
+    #[test]
+    fn test_log_rotates_and_survives_reopening() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cycles.jsonl");
+
+        let log = CycleLog::open(&path, 3).unwrap();
+        for minute in 0..5 {
+            log.record(record(minute)).unwrap();
+        }
+        let recent = log.recent(10);
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0], record(4));
+        assert_eq!(log.recent(1), vec![record(4)]);
+
+        // The fourth cycle rotated the first three away
+        assert_eq!(read_records(&path).unwrap().len(), 2);
+        assert_eq!(read_records(&rotated_path(&path)).unwrap().len(), 3);
+        drop(log);
+
+        let reopened = CycleLog::open(&path, 3).unwrap();
+        assert_eq!(reopened.recent(10), vec![record(4), record(3), record(2)]);
+        reopened.record(record(5)).unwrap();
+        assert_eq!(reopened.recent(1), vec![record(5)]);
+    }
+}
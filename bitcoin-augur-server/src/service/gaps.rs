@@ -0,0 +1,233 @@
+//! Gaps in the stored snapshot history, and their reconstruction from mined blocks
+//!
+//! While the server is down no snapshots are taken, so once it is back the inflow windows
+//! cover less time than they claim and estimates lean on whatever was collected around the gap.
+//! Estimates are flagged as degraded while a gap longer than the policy allows is inside the
+//! estimation window. Optionally, the gap is backfilled: the blocks mined during it tell how
+//! much weight each fee bucket confirmed, which together with how much the mempool grew gives
+//! the inflow over the gap.
+
+use bitcoin_augur::{MempoolSnapshot, MempoolTransaction};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+use crate::bitcoin::MinedBlock;
+
+/// Settings for detecting and backfilling gaps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GapPolicy {
+    /// Longest time between consecutive snapshots that is not a gap
+    pub max_gap: Duration,
+    /// Reconstruct snapshots for gaps from the blocks mined during them
+    pub backfill: bool,
+}
+
+/// A stretch of time without snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SnapshotGap {
+    /// Time and block height of the last snapshot before the gap
+    pub start: DateTime<Utc>,
+    pub start_height: u32,
+    /// Time and block height of the first snapshot after the gap
+    pub end: DateTime<Utc>,
+    pub end_height: u32,
+}
+
+/// Gaps longer than `max_gap` between consecutive `snapshots`, which must be sorted by time
+pub fn find_gaps(snapshots: &[MempoolSnapshot], max_gap: Duration) -> Vec<SnapshotGap> {
+    snapshots
+        .windows(2)
+        .filter(|pair| pair[1].timestamp - pair[0].timestamp > max_gap)
+        .map(|pair| SnapshotGap {
+            start: pair[0].timestamp,
+            start_height: pair[0].block_height,
+            end: pair[1].timestamp,
+            end_height: pair[1].block_height,
+        })
+        .collect()
+}
+
+/// Synthetic snapshots filling the gap between `before` and `after`, at most `spacing` apart
+///
+/// `blocks` are the blocks mined during the gap, in height order. A bucket's inflow over the
+/// gap is its growth from `before` to `after` plus the weight the blocks confirmed from it, and
+/// arrives at a constant rate. Each block starts a new height at its block time (kept inside
+/// the gap), from a mempool interpolated between `before` and `after`.
+pub fn reconstruct(
+    before: &MempoolSnapshot,
+    after: &MempoolSnapshot,
+    blocks: &[MinedBlock],
+    spacing: Duration,
+) -> Vec<MempoolSnapshot> {
+    let gap = after.timestamp - before.timestamp;
+    if gap <= Duration::zero() || spacing <= Duration::zero() {
+        return Vec::new();
+    }
+    let gap_secs = gap.num_milliseconds() as f64 / 1000.0;
+
+    let mined: Vec<MempoolTransaction> = blocks
+        .iter()
+        .flat_map(|block| block.transactions.iter().copied())
+        .collect();
+    let mined = MempoolSnapshot::from_transactions(mined, 0, before.timestamp).bucketed_weights;
+
+    let buckets: Vec<i32> = before
+        .bucketed_weights
+        .keys()
+        .chain(after.bucketed_weights.keys())
+        .chain(mined.keys())
+        .copied()
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let weight = |weights: &BTreeMap<i32, u64>, bucket| weights.get(&bucket).copied().unwrap_or(0);
+    // Inflow per second, and the mempool at a point of the gap interpolated between its ends
+    let rates: Vec<f64> = buckets
+        .iter()
+        .map(|&bucket| {
+            let growth = weight(&after.bucketed_weights, bucket) as f64
+                - weight(&before.bucketed_weights, bucket) as f64
+                + weight(&mined, bucket) as f64;
+            growth.max(0.0) / gap_secs
+        })
+        .collect();
+    let level = |fraction: f64, bucket| {
+        let start = weight(&before.bucketed_weights, bucket) as f64;
+        let end = weight(&after.bucketed_weights, bucket) as f64;
+        start + (end - start) * fraction
+    };
+
+    // Each height runs from its block's time to the next block's time
+    let mut segments = vec![(before.block_height, before.timestamp)];
+    for block in blocks {
+        let last = segments
+            .last()
+            .map_or(before.timestamp, |&(_, start)| start);
+        let time = block
+            .time
+            .min(after.timestamp - Duration::seconds(1))
+            .max(last);
+        segments.push((block.height, time));
+    }
+
+    let mut snapshots = Vec::new();
+    for (i, &(height, start)) in segments.iter().enumerate() {
+        let end = segments
+            .get(i + 1)
+            .map_or(after.timestamp, |&(_, next)| next);
+        let start_level = (start - before.timestamp).num_milliseconds() as f64 / 1000.0 / gap_secs;
+
+        let mut times = Vec::new();
+        let mut time = start;
+        while time < end {
+            times.push(time);
+            time += spacing;
+        }
+        // Close the height just before the next one starts, so its growth covers the segment
+        let last = end - Duration::seconds(1);
+        if times.last().is_some_and(|&t| t < last) {
+            times.push(last);
+        }
+
+        for time in times {
+            if time == before.timestamp {
+                continue;
+            }
+            let elapsed = (time - start).num_milliseconds() as f64 / 1000.0;
+            let weights = buckets
+                .iter()
+                .zip(&rates)
+                .filter_map(|(&bucket, rate)| {
+                    let weight = (level(start_level, bucket) + rate * elapsed).round() as u64;
+                    (weight > 0).then_some((bucket, weight))
+                })
+                .collect();
+            snapshots.push(MempoolSnapshot::new(height, time, weights));
+        }
+    }
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn snapshot(height: u32, minutes: i64, weight: u64) -> MempoolSnapshot {
+        MempoolSnapshot::new(height, at(minutes), BTreeMap::from([(100, weight)]))
+    }
+
+    #[test]
+    fn test_find_gaps() {
+        let snapshots = [
+            snapshot(100, 0, 1),
+            snapshot(100, 1, 1),
+            snapshot(101, 61, 1),
+            snapshot(101, 62, 1),
+            snapshot(102, 72, 1),
+        ];
+        let gaps = find_gaps(&snapshots, Duration::minutes(10));
+        assert_eq!(
+            gaps,
+            vec![SnapshotGap {
+                start: at(1),
+                start_height: 100,
+                end: at(61),
+                end_height: 101,
+            }]
+        );
+        assert!(find_gaps(&snapshots, Duration::hours(1)).is_empty());
+    }
+
+    #[test]
+    fn test_reconstruct_spreads_inflow_over_blocks() {
+        // Blocks at minutes 20 and 40 each confirmed 12,000 WU from a single bucket
+        let fee_rate_tx = MempoolTransaction::new(12_000, 9_000);
+        let bucket = *MempoolSnapshot::from_transactions(vec![fee_rate_tx], 0, at(0))
+            .bucketed_weights
+            .keys()
+            .next()
+            .unwrap();
+        let before = MempoolSnapshot::new(100, at(0), BTreeMap::from([(bucket, 10_000)]));
+        let after = MempoolSnapshot::new(102, at(60), BTreeMap::from([(bucket, 10_000)]));
+        let blocks = [
+            MinedBlock {
+                height: 101,
+                time: at(20),
+                transactions: vec![fee_rate_tx],
+            },
+            MinedBlock {
+                height: 102,
+                time: at(40),
+                transactions: vec![fee_rate_tx],
+            },
+        ];
+
+        let snapshots = reconstruct(&before, &after, &blocks, Duration::minutes(5));
+        assert!(find_gaps(
+            &[vec![before.clone()], snapshots.clone(), vec![after.clone()]].concat(),
+            Duration::minutes(5)
+        )
+        .is_empty());
+
+        // 24,000 WU over an hour: 8,000 WU per 20 minute block, on top of a flat mempool
+        let height_101: Vec<_> = snapshots.iter().filter(|s| s.block_height == 101).collect();
+        assert_eq!(height_101.first().unwrap().timestamp, at(20));
+        assert_eq!(
+            height_101.first().unwrap().bucketed_weights[&bucket],
+            10_000
+        );
+        assert_eq!(
+            height_101.last().unwrap().timestamp,
+            at(40) - Duration::seconds(1)
+        );
+        let grown = height_101.last().unwrap().bucketed_weights[&bucket];
+        assert!((7_990..=8_000).contains(&(grown - 10_000)), "{grown}");
+
+        assert!(reconstruct(&after, &before, &blocks, Duration::minutes(5)).is_empty());
+    }
+}
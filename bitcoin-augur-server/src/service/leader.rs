@@ -0,0 +1,179 @@
+//! Lease deciding which of several instances sharing a data directory collects
+//!
+//! Instances sharing storage would otherwise all poll their nodes and write interleaved
+//! snapshots. The lease is a small file in the data directory naming its holder and when it
+//! expires. Every collection cycle, each instance takes the lease if it is free, expired or
+//! already its own, under an exclusive lock on a second file so two instances never take it at
+//! once. The holder collects and renews the lease; the others estimate from the snapshots it
+//! stores, and take over once it stops renewing.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::persistence::{LEADER_LEASE_FILE_NAME, LEADER_LOCK_FILE_NAME};
+
+/// Holder of the lease and until when it holds it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Lease {
+    holder: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Who holds the lease, as last seen by an instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderStatus {
+    /// This instance
+    pub instance: String,
+    /// Holder of the lease, if any instance holds an unexpired one
+    pub leader: Option<String>,
+}
+
+impl LeaderStatus {
+    /// Whether this instance holds the lease
+    pub fn is_leader(&self) -> bool {
+        self.leader.as_deref() == Some(self.instance.as_str())
+    }
+}
+
+/// Lease on collecting, taken and renewed by one instance at a time
+#[derive(Debug)]
+pub struct LeaderLease {
+    data_dir: PathBuf,
+    instance: String,
+    ttl: chrono::Duration,
+}
+
+impl LeaderLease {
+    /// Lease kept in `data_dir` for `ttl` after each renewal, held under the name `instance`
+    pub fn new(
+        data_dir: impl AsRef<Path>,
+        instance: impl Into<String>,
+        ttl: chrono::Duration,
+    ) -> Self {
+        Self {
+            data_dir: data_dir.as_ref().to_path_buf(),
+            instance: instance.into(),
+            ttl,
+        }
+    }
+
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// Takes or renews the lease at `now` if it is free, expired or already held by this
+    /// instance, returning who holds it afterwards
+    ///
+    /// While another instance has the lock, the lease is only read.
+    pub fn try_acquire(&self, now: DateTime<Utc>) -> io::Result<LeaderStatus> {
+        let lock = File::create(self.data_dir.join(LEADER_LOCK_FILE_NAME))?;
+        let locked = match lock.try_lock() {
+            Ok(()) => true,
+            Err(TryLockError::WouldBlock) => false,
+            Err(TryLockError::Error(e)) => return Err(e),
+        };
+
+        let current = self.read().filter(|lease| lease.expires_at > now);
+        let leader = match current {
+            Some(lease) if lease.holder != self.instance || !locked => lease.holder,
+            _ if locked => {
+                self.write(&Lease {
+                    holder: self.instance.clone(),
+                    expires_at: now + self.ttl,
+                })?;
+                self.instance.clone()
+            }
+            _ => {
+                return Ok(LeaderStatus {
+                    instance: self.instance.clone(),
+                    leader: None,
+                })
+            }
+        };
+        Ok(LeaderStatus {
+            instance: self.instance.clone(),
+            leader: Some(leader),
+        })
+    }
+
+    /// Gives up the lease if this instance holds it, so another takes over without waiting
+    /// for it to expire
+    pub fn release(&self) -> io::Result<()> {
+        let lock = File::create(self.data_dir.join(LEADER_LOCK_FILE_NAME))?;
+        lock.lock()?;
+        if self
+            .read()
+            .is_some_and(|lease| lease.holder == self.instance)
+        {
+            fs::remove_file(self.data_dir.join(LEADER_LEASE_FILE_NAME))?;
+        }
+        Ok(())
+    }
+
+    /// The lease on file, treating an unreadable one as free
+    fn read(&self) -> Option<Lease> {
+        let content = fs::read_to_string(self.data_dir.join(LEADER_LEASE_FILE_NAME)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Replaces the lease on file, so readers without the lock never see half of it
+    fn write(&self, lease: &Lease) -> io::Result<()> {
+        let path = self.data_dir.join(LEADER_LEASE_FILE_NAME);
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string(lease)?)?;
+        fs::rename(&partial, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lease_fails_over_once_expired() {
+        let temp_dir = TempDir::new().unwrap();
+        let ttl = chrono::Duration::seconds(30);
+        let a = LeaderLease::new(temp_dir.path(), "a", ttl);
+        let b = LeaderLease::new(temp_dir.path(), "b", ttl);
+        let now = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+
+        assert!(a.try_acquire(now).unwrap().is_leader());
+        let status = b.try_acquire(now).unwrap();
+        assert!(!status.is_leader());
+        assert_eq!(status.leader.as_deref(), Some("a"));
+
+        // Renewing keeps the lease past its first expiry
+        assert!(a
+            .try_acquire(now + chrono::Duration::seconds(20))
+            .unwrap()
+            .is_leader());
+        let later = now + chrono::Duration::seconds(40);
+        assert!(!b.try_acquire(later).unwrap().is_leader());
+
+        // Once the holder stops renewing, another instance takes over
+        let expired = later + ttl;
+        assert!(b.try_acquire(expired).unwrap().is_leader());
+        assert!(!a.try_acquire(expired).unwrap().is_leader());
+    }
+
+    #[test]
+    fn test_released_lease_is_taken_at_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let ttl = chrono::Duration::seconds(30);
+        let a = LeaderLease::new(temp_dir.path(), "a", ttl);
+        let b = LeaderLease::new(temp_dir.path(), "b", ttl);
+        let now = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+
+        assert!(a.try_acquire(now).unwrap().is_leader());
+        // Only the holder can release the lease
+        b.release().unwrap();
+        assert!(!b.try_acquire(now).unwrap().is_leader());
+        a.release().unwrap();
+        assert!(b.try_acquire(now).unwrap().is_leader());
+    }
+}
@@ -1,13 +1,66 @@
-use bitcoin_augur::{FeeEstimate, FeeEstimator, MempoolSnapshot};
+use bitcoin_augur::{
+    BlockTarget, BlockTargetKey, BucketWeighting, EstimationStrategy, FeeEstimate, FeeEstimator,
+    MempoolSnapshot, OrderedFloat, PackageLimits, Simulation, SourceEstimates, HOURS_PER_WEEK,
+};
 use chrono::{DateTime, Local, Utc};
-use std::sync::Arc;
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
 use thiserror::Error;
-use tokio::sync::RwLock;
-use tokio::time::{interval, Duration};
+use tokio::sync::{mpsc, RwLock};
+use tokio::time::{interval, sleep_until, Duration};
 use tracing::{debug, error, info, warn};
 
-use crate::bitcoin::{BitcoinClient, BitcoinRpc, RpcError};
-use crate::persistence::{PersistenceError, SnapshotStore};
+use super::adaptive_interval::{volatility, IntervalPolicy};
+use super::alerts::{AlertStatus, Alerts};
+use super::baselines::Baselines;
+use super::block_feedback::{BlockFeedback, RealizedBlock};
+use super::cycle_log::{elapsed_ms, CycleLog, CycleRecord};
+use super::gaps::{find_gaps, reconstruct, GapPolicy, SnapshotGap};
+use super::leader::{LeaderLease, LeaderStatus};
+use super::seasonality::SeasonalityPolicy;
+use super::target_cache::{TargetCache, TargetCachePolicy};
+use super::trace::TraceRecorder;
+use super::validity::{observed_block_interval, ValidityPolicy, DEFAULT_BLOCK_INTERVAL};
+use super::warmup::{Warmup, WarmupStatus};
+use super::Clock;
+use crate::bitcoin::{BitcoinClient, BitcoinRpc, RpcError, TxMempoolEntry};
+use crate::persistence::{fingerprint, CachedEstimate, PersistenceError, SnapshotStore};
+
+/// Hours of history fed into the estimator
+pub const ESTIMATION_WINDOW_HOURS: i64 = 24;
+
+/// History fed into `strategy`: the estimation window, or longer if the strategy reads further
+/// back, as the default estimator's inflow windows may
+pub fn estimation_window(strategy: &dyn EstimationStrategy) -> chrono::Duration {
+    chrono::Duration::hours(ESTIMATION_WINDOW_HOURS).max(strategy.history_window())
+}
+
+/// Estimates of a series computed ahead of the consumer reading them
+const SERIES_BUFFER: usize = 16;
+
+/// Hours of snapshots a series loads at a time, beyond the estimation window
+const SERIES_CHUNK_HOURS: i64 = 24;
+
+/// Most blocks fetched to backfill a single gap
+const MAX_BACKFILL_BLOCKS: u32 = 144;
+
+/// Most blocks realized after a single collection, the latest ones when more were mined
+const MAX_REALIZED_BLOCKS: u32 = 6;
+
+/// Runs `estimate` on the blocking thread pool, so estimating from a long window never holds up
+/// the runtime threads serving requests
+async fn estimate_blocking<T: Send + 'static>(
+    estimator: Arc<FeeEstimator>,
+    snapshots: Vec<MempoolSnapshot>,
+    estimate: impl FnOnce(&FeeEstimator, &[MempoolSnapshot]) -> bitcoin_augur::Result<T>
+        + Send
+        + 'static,
+) -> Result<T, CollectorError> {
+    Ok(tokio::task::spawn_blocking(move || estimate(&estimator, &snapshots)).await??)
+}
 
 /// Mempool collector errors
 #[derive(Error, Debug)]
@@ -21,18 +74,90 @@ pub enum CollectorError {
     #[error("Estimation error: {0}")]
     EstimationError(#[from] bitcoin_augur::AugurError),
 
+    #[error("Estimation task failed: {0}")]
+    TaskFailed(#[from] tokio::task::JoinError),
+
     #[error("Service is shutting down")]
     #[allow(dead_code)]
     Shutdown,
+
+    #[error("Too many block targets: {0}")]
+    TooManyTargets(String),
+
+    /// Error of a computation shared by concurrent requests
+    #[error("{0}")]
+    Coalesced(Arc<CollectorError>),
+}
+
+impl CollectorError {
+    /// The error behind a coalesced one, or this error
+    pub fn cause(&self) -> &CollectorError {
+        match self {
+            CollectorError::Coalesced(err) => err.cause(),
+            err => err,
+        }
+    }
 }
 
+/// Estimate for a block target being computed, awaited by every request for that target
+type PendingEstimate = Shared<BoxFuture<'static, Result<FeeEstimate, Arc<CollectorError>>>>;
+
 /// Service that periodically collects mempool data and calculates fee estimates
 pub struct MempoolCollector {
     bitcoin_client: Arc<BitcoinClient>,
     snapshot_store: Arc<SnapshotStore>,
-    fee_estimator: Arc<FeeEstimator>,
+    /// Replaced whenever the seasonal profile is refreshed
+    fee_estimator: Arc<RwLock<Arc<FeeEstimator>>>,
     latest_estimate: Arc<RwLock<Option<FeeEstimate>>>,
     latest_snapshot: Arc<RwLock<Option<MempoolSnapshot>>>,
+    /// Block interval observed in the latest estimation window
+    block_interval: Arc<RwLock<Option<chrono::Duration>>>,
+    /// Error of the latest failed mempool fetch, cleared by the next successful one
+    rpc_error: Arc<RwLock<Option<String>>>,
+    validity: Option<ValidityPolicy>,
+    seasonality: Option<SeasonalityPolicy>,
+    /// Estimator settings published estimates are cached under, when caching is enabled
+    cache_settings: Option<String>,
+    gap_policy: Option<GapPolicy>,
+    /// Gaps in the latest estimation window
+    gaps: Arc<RwLock<Vec<SnapshotGap>>>,
+    /// Gaps a backfill was attempted for, successful or not
+    backfilled: Arc<RwLock<HashSet<SnapshotGap>>>,
+    /// Chain reorganizations seen since startup
+    reorgs: Arc<AtomicU64>,
+    /// Progress of loading stored snapshots on startup
+    warmup: Arc<Warmup>,
+    clock: Clock,
+    /// Anonymized trace every collected snapshot is appended to
+    trace: Option<Arc<TraceRecorder>>,
+    /// Reference strategies re-estimated along with every estimate
+    baselines: Option<Arc<Baselines>>,
+    /// Label of collected snapshots, telling them apart from other sources' in the store
+    source: Option<String>,
+    /// Timings of the latest collection cycles
+    cycle_log: Option<Arc<CycleLog>>,
+    /// Bounds of the collection interval, when it follows mempool activity
+    interval_policy: Option<IntervalPolicy>,
+    /// Interval until the next collection, when it follows mempool activity
+    next_interval: Arc<RwLock<Option<Duration>>>,
+    /// Rules evaluated after every collection
+    alerts: Option<Arc<Alerts>>,
+    /// Age of the latest snapshot past which fee requests fail, when limited
+    max_staleness: Option<chrono::Duration>,
+    /// Block target estimates being computed, keyed by the target's bits
+    pending_targets: Arc<Mutex<HashMap<u64, PendingEstimate>>>,
+    /// Block target requests served by a computation already in flight
+    coalesced: Arc<AtomicU64>,
+    /// Estimates for single block targets kept between requests, when caching them
+    target_cache: Option<Arc<TargetCache>>,
+    /// Lease on collecting, when several instances share the data directory
+    leader_lease: Option<Arc<LeaderLease>>,
+    /// Holder of the lease as of the latest cycle
+    leader: Arc<RwLock<Option<LeaderStatus>>>,
+    /// Contents of new blocks and the scores of the estimates made before them, when fetching
+    /// new blocks
+    block_feedback: Option<Arc<BlockFeedback>>,
+    package_limits: Option<PackageLimits>,
 }
 
 impl MempoolCollector {
@@ -45,45 +170,501 @@ impl MempoolCollector {
         Self {
             bitcoin_client: Arc::new(bitcoin_client),
             snapshot_store: Arc::new(snapshot_store),
-            fee_estimator: Arc::new(fee_estimator),
+            fee_estimator: Arc::new(RwLock::new(Arc::new(fee_estimator))),
             latest_estimate: Arc::new(RwLock::new(None)),
             latest_snapshot: Arc::new(RwLock::new(None)),
+            block_interval: Arc::new(RwLock::new(None)),
+            rpc_error: Arc::new(RwLock::new(None)),
+            validity: None,
+            seasonality: None,
+            cache_settings: None,
+            gap_policy: None,
+            gaps: Arc::new(RwLock::new(Vec::new())),
+            backfilled: Arc::new(RwLock::new(HashSet::new())),
+            reorgs: Arc::new(AtomicU64::new(0)),
+            warmup: Arc::new(Warmup::default()),
+            clock: Clock::System,
+            trace: None,
+            baselines: None,
+            source: None,
+            cycle_log: None,
+            interval_policy: None,
+            next_interval: Arc::new(RwLock::new(None)),
+            alerts: None,
+            max_staleness: None,
+            pending_targets: Arc::new(Mutex::new(HashMap::new())),
+            coalesced: Arc::new(AtomicU64::new(0)),
+            target_cache: None,
+            leader_lease: None,
+            leader: Arc::new(RwLock::new(None)),
+            block_feedback: None,
+            package_limits: None,
+        }
+    }
+
+    /// Attaches `valid_until` times derived from `policy` to served estimates
+    pub fn with_validity(mut self, policy: ValidityPolicy) -> Self {
+        self.validity = Some(policy);
+        self
+    }
+
+    /// Learns a seasonal inflow profile from stored history on each
+    /// [`refresh_seasonality`](Self::refresh_seasonality)
+    pub fn with_seasonality(mut self, policy: SeasonalityPolicy) -> Self {
+        self.seasonality = Some(policy);
+        self
+    }
+
+    /// Saves every published estimate in the snapshot store, fingerprinted with `settings`
+    ///
+    /// `settings` describes the estimator configuration: a cached estimate is only restored
+    /// while it is unchanged.
+    pub fn with_estimate_cache(mut self, settings: impl Into<String>) -> Self {
+        self.cache_settings = Some(settings.into());
+        self
+    }
+
+    /// Flags estimates as degraded while the estimation window has gaps longer than
+    /// `policy` allows, backfilling them from mined blocks if it says so
+    pub fn with_gap_policy(mut self, policy: GapPolicy) -> Self {
+        self.gap_policy = Some(policy);
+        self
+    }
+
+    /// Uses `clock` instead of the system clock for snapshot timestamps and history windows
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Appends every collected snapshot to `recorder`
+    pub fn with_trace_recorder(mut self, recorder: TraceRecorder) -> Self {
+        self.trace = Some(Arc::new(recorder));
+        self
+    }
+
+    /// Re-estimates `baselines` from the same snapshots after every estimate
+    pub fn with_baselines(mut self, baselines: Baselines) -> Self {
+        self.baselines = Some(Arc::new(baselines));
+        self
+    }
+
+    /// Labels every collected snapshot with `source`
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Records the timings of every collection cycle in `log`
+    pub fn with_cycle_log(mut self, log: CycleLog) -> Self {
+        self.cycle_log = Some(Arc::new(log));
+        self
+    }
+
+    /// Adapts the collection interval to mempool activity within `policy`'s bounds
+    pub fn with_interval_policy(mut self, policy: IntervalPolicy) -> Self {
+        self.interval_policy = Some(policy);
+        self
+    }
+
+    /// Evaluates `alerts` after every collection
+    pub fn with_alerts(mut self, alerts: Alerts) -> Self {
+        self.alerts = Some(Arc::new(alerts));
+        self
+    }
+
+    /// Keeps estimates for single block targets between requests and caps the distinct targets
+    /// computed per minute
+    pub fn with_target_cache(mut self, policy: TargetCachePolicy) -> Self {
+        self.target_cache = Some(Arc::new(TargetCache::new(policy)));
+        self
+    }
+
+    /// Collects only while holding `lease`, estimating from the snapshots the holder stores
+    /// otherwise
+    pub fn with_leader_lease(mut self, lease: LeaderLease) -> Self {
+        self.leader_lease = Some(Arc::new(lease));
+        self
+    }
+
+    /// Fetches every new block to record the fee rates it confirmed in `feedback`, scoring the
+    /// estimates made before it
+    pub fn with_block_feedback(mut self, feedback: BlockFeedback) -> Self {
+        self.block_feedback = Some(Arc::new(feedback));
+        self
+    }
+
+    /// Records the weight of transactions whose ancestor package exceeds `limits` in snapshots,
+    /// which estimates keep out of the next block only
+    pub fn with_package_limits(mut self, limits: PackageLimits) -> Self {
+        self.package_limits = Some(limits);
+        self
+    }
+
+    /// Blocks realized so far and how estimates scored against them, when fetching new blocks
+    pub fn block_feedback(&self) -> Option<&BlockFeedback> {
+        self.block_feedback.as_deref()
+    }
+
+    /// Fails fee requests with 503 once the latest snapshot is older than `max_staleness`,
+    /// rather than serving an estimate that no longer reflects the mempool
+    pub fn with_max_staleness(mut self, max_staleness: chrono::Duration) -> Self {
+        self.max_staleness = Some(max_staleness);
+        self
+    }
+
+    /// Age of the latest snapshot past which fee requests fail, if limited
+    pub fn max_staleness(&self) -> Option<chrono::Duration> {
+        self.max_staleness
+    }
+
+    /// State of every alert rule, or none without alerts
+    pub fn alert_statuses(&self) -> Vec<AlertStatus> {
+        self.alerts
+            .as_ref()
+            .map(|alerts| alerts.statuses())
+            .unwrap_or_default()
+    }
+
+    /// The latest `limit` collection cycles, newest first, or none without a cycle log
+    pub fn recent_cycles(&self, limit: usize) -> Vec<CycleRecord> {
+        self.cycle_log
+            .as_ref()
+            .map(|log| log.recent(limit))
+            .unwrap_or_default()
+    }
+
+    /// Whether collection cycles are logged
+    pub fn has_cycle_log(&self) -> bool {
+        self.cycle_log.is_some()
+    }
+
+    /// Whether baseline strategies are estimated next to the augur estimate
+    pub fn has_baselines(&self) -> bool {
+        self.baselines.is_some()
+    }
+
+    /// Latest estimate of each baseline strategy, keyed by its name
+    pub async fn baseline_estimates(&self) -> BTreeMap<String, FeeEstimate> {
+        match &self.baselines {
+            Some(baselines) => baselines.latest().await,
+            None => BTreeMap::new(),
+        }
+    }
+
+    /// Current time as seen by the collector
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// The estimator currently in use
+    pub async fn fee_estimator(&self) -> Arc<FeeEstimator> {
+        self.fee_estimator.read().await.clone()
+    }
+
+    /// The current estimator, measuring buckets by `weighting`
+    async fn weighted_estimator(&self, weighting: BucketWeighting) -> Arc<FeeEstimator> {
+        let estimator = self.fee_estimator().await;
+        if estimator.weighting() == weighting {
+            return estimator;
+        }
+        Arc::new(estimator.as_ref().clone().with_weighting(weighting))
+    }
+
+    /// Loads the snapshots inside the estimation window ending at the collector's current time
+    async fn recent_snapshots(&self) -> Result<Vec<MempoolSnapshot>, CollectorError> {
+        let end = self.clock.now().with_timezone(&Local);
+        let start = end - estimation_window(&*self.fee_estimator().await);
+        self.load_snapshots(start, end).await
+    }
+
+    /// Loads the stored snapshots from `start` to `end` on the blocking thread pool
+    async fn load_snapshots(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<Vec<MempoolSnapshot>, CollectorError> {
+        let store = self.snapshot_store.clone();
+        Ok(tokio::task::spawn_blocking(move || store.get_snapshots(start, end)).await??)
+    }
+
+    /// Loads the estimation window like [`recent_snapshots`](Self::recent_snapshots), tracking
+    /// progress in the warm-up status
+    async fn warm_up_snapshots(&self) -> Result<Vec<MempoolSnapshot>, CollectorError> {
+        let end = self.clock.now().with_timezone(&Local);
+        let start = end - estimation_window(&*self.fee_estimator().await);
+        let store = self.snapshot_store.clone();
+        let warmup = self.warmup.clone();
+        Ok(tokio::task::spawn_blocking(move || {
+            // Each snapshot goes straight into the window, sized once the total is known
+            let mut window = Vec::new();
+            store.stream_snapshots(start, end, |snapshot, progress| {
+                if window.is_empty() {
+                    window.reserve_exact(progress.total);
+                }
+                window.push(snapshot);
+                warmup.loaded(progress);
+            })?;
+            window.sort_by_key(|snapshot: &MempoolSnapshot| snapshot.timestamp);
+            Ok::<_, PersistenceError>(window)
+        })
+        .await??)
+    }
+
+    /// Fingerprint of an estimate from `snapshots` with the current estimator, when caching
+    async fn estimate_fingerprint(&self, snapshots: &[MempoolSnapshot]) -> Option<String> {
+        let settings = self.cache_settings.as_deref()?;
+        let estimator = self.fee_estimator().await;
+        // The seasonal profile is learned from stored history rather than configured
+        let settings = match estimator.seasonality() {
+            Some((profile, blend)) => format!("{settings}\n{profile:?}\n{blend}"),
+            None => settings.to_string(),
+        };
+        Some(fingerprint(&settings, snapshots))
+    }
+
+    /// Caches `estimate` under `fingerprint`, logging rather than failing on errors
+    fn cache_estimate(&self, fingerprint: String, estimate: &FeeEstimate) {
+        let Some(settings) = &self.cache_settings else {
+            return;
+        };
+        let cached = CachedEstimate {
+            settings: settings.clone(),
+            fingerprint,
+            estimate: estimate.clone(),
+        };
+        if let Err(e) = self.snapshot_store.save_cached_estimate(&cached) {
+            warn!("Failed to cache fee estimate: {e}");
+        }
+    }
+
+    /// Makes `estimate` the latest one unless a newer estimate was published meanwhile
+    async fn publish_if_newer(&self, estimate: FeeEstimate) {
+        let mut latest = self.latest_estimate.write().await;
+        if latest
+            .as_ref()
+            .is_none_or(|current| current.timestamp <= estimate.timestamp)
+        {
+            *latest = Some(estimate);
         }
     }
 
     /// Starts the collection service with the specified interval
+    ///
+    /// With an [`IntervalPolicy`], `interval_ms` is only the first interval; later ones follow
+    /// mempool activity.
     pub async fn start(&self, interval_ms: u64) -> Result<(), CollectorError> {
         let mut interval = interval(Duration::from_millis(interval_ms));
 
         info!("Starting mempool collector with {interval_ms}ms interval");
+        if self.clock.is_simulated() {
+            info!(
+                "Using simulated clock starting at {start}",
+                start = self.clock.now()
+            );
+        }
+        if let Some(policy) = self.interval_policy {
+            let first = Duration::from_millis(interval_ms).clamp(policy.min, policy.max);
+            *self.next_interval.write().await = Some(first);
+        }
 
         // Perform initial collection immediately
-        if let Err(e) = self.update_fee_estimates().await {
+        let mut started = tokio::time::Instant::now();
+        if let Err(e) = self.run_cycle().await {
             warn!("Initial fee estimate update failed: {e}");
         }
 
+        if self.interval_policy.is_some() {
+            loop {
+                // Counted from the start of the previous cycle, like a fixed interval
+                let wait = self.next_interval.read().await.unwrap_or(interval.period());
+                sleep_until(started + wait).await;
+                started = tokio::time::Instant::now();
+
+                if let Err(e) = self.run_cycle().await {
+                    error!("Failed to update fee estimates: {e}");
+                }
+            }
+        }
+
+        // The first tick completes immediately, and the initial collection stands in for it
+        interval.tick().await;
         loop {
             interval.tick().await;
 
-            if let Err(e) = self.update_fee_estimates().await {
+            if let Err(e) = self.run_cycle().await {
                 error!("Failed to update fee estimates: {e}");
                 // Continue running despite errors
             }
         }
     }
 
-    /// Updates fee estimates by collecting fresh mempool data
+    /// Runs one collection cycle, then moves a simulated clock on by a step
+    ///
+    /// The clock only moves once a cycle has completed, so the first snapshot is taken at the
+    /// clock's start.
+    async fn run_cycle(&self) -> Result<(), CollectorError> {
+        let result = self.update_fee_estimates().await;
+        self.clock.tick();
+        result
+    }
+
+    /// Updates fee estimates by collecting fresh mempool data, logging the cycle's timings
     async fn update_fee_estimates(&self) -> Result<(), CollectorError> {
         debug!("Updating fee estimates");
+        if !self.hold_lease().await {
+            return self.follow_leader().await;
+        }
+
+        let started = Instant::now();
+        let mut cycle = CycleRecord::new(self.clock.now());
+        let result = self.collect(&mut cycle).await;
+        cycle.total_ms = elapsed_ms(started);
+        if let Err(e) = &result {
+            cycle.error = Some(e.to_string());
+        }
+
+        // The cycle log is best effort and never fails a collection
+        if let Some(log) = &self.cycle_log {
+            if let Err(e) = log.record(cycle) {
+                warn!(
+                    "Failed to append to cycle log {path:?}: {e}",
+                    path = log.path()
+                );
+            }
+        }
+        result
+    }
+
+    /// Takes or renews the collector lease, returning whether this instance collects
+    ///
+    /// Without a lease every instance collects. An instance that cannot read the lease
+    /// collects nothing, so a broken shared directory never yields two collectors.
+    async fn hold_lease(&self) -> bool {
+        let Some(lease) = &self.leader_lease else {
+            return true;
+        };
+        // Leases are compared across processes, so they run on the wall clock
+        let status = lease.try_acquire(Utc::now()).unwrap_or_else(|e| {
+            warn!("Failed to take the collector lease: {e}");
+            LeaderStatus {
+                instance: lease.instance().to_string(),
+                leader: None,
+            }
+        });
+
+        let mut current = self.leader.write().await;
+        if current.as_ref().map(|current| &current.leader) != Some(&status.leader) {
+            match &status.leader {
+                Some(_) if status.is_leader() => {
+                    info!(
+                        "Took the collector lease as {instance}",
+                        instance = status.instance
+                    )
+                }
+                Some(leader) => info!("Following collector leader {leader}"),
+                None => warn!("No instance holds the collector lease"),
+            }
+        }
+        let leading = status.is_leader();
+        *current = Some(status);
+        leading
+    }
+
+    /// Estimates from the snapshots the lease holder stores, instead of collecting
+    async fn follow_leader(&self) -> Result<(), CollectorError> {
+        let store = self.snapshot_store.clone();
+        if let Some(stored) =
+            tokio::task::spawn_blocking(move || store.get_latest_snapshot()).await??
+        {
+            let mut latest = self.latest_snapshot.write().await;
+            if latest
+                .as_ref()
+                .is_none_or(|current| current.timestamp < stored.timestamp)
+            {
+                *latest = Some(stored);
+            }
+        }
+
+        let snapshots = self.recent_snapshots().await?;
+        self.publish_estimate(&snapshots, false).await;
+        Ok(())
+    }
+
+    /// Holder of the collector lease as of the latest cycle, or `None` without a lease
+    pub async fn leader_status(&self) -> Option<LeaderStatus> {
+        self.leader.read().await.clone()
+    }
+
+    /// Gives up the collector lease if this instance holds it, so another instance takes over
+    /// without waiting for it to expire
+    pub fn release_lease(&self) {
+        if let Some(lease) = &self.leader_lease {
+            if let Err(e) = lease.release() {
+                warn!("Failed to release the collector lease: {e}");
+            }
+        }
+    }
 
+    /// Collects one snapshot and re-estimates, timing each step in `cycle`
+    async fn collect(&self, cycle: &mut CycleRecord) -> Result<(), CollectorError> {
         // Fetch current mempool data from Bitcoin Core
-        let (height, transactions) = self.bitcoin_client.get_height_and_mempool().await?;
+        let step = Instant::now();
+        let (height, transactions) = match self.bitcoin_client.get_height_and_mempool().await {
+            Ok(mempool) => {
+                *self.rpc_error.write().await = None;
+                mempool
+            }
+            Err(e) => {
+                *self.rpc_error.write().await = Some(e.to_string());
+                return Err(e.into());
+            }
+        };
+        cycle.fetch_ms = Some(elapsed_ms(step));
+        cycle.block_height = Some(height);
+        cycle.transactions = Some(transactions.len());
+
+        // A lower height than the previous snapshot means its blocks were reorganized away
+        let mut new_block = false;
+        let mut previous_height = None;
+        if let Some(previous) = self.latest_snapshot.read().await.as_ref() {
+            new_block = height > previous.block_height;
+            previous_height = Some(previous.block_height);
+            if height < previous.block_height {
+                self.reorgs.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "Block height dropped from {previous_height} to {height}, the chain was \
+                     reorganized; earlier snapshots above {height} are excluded from inflows",
+                    previous_height = previous.block_height
+                );
+            }
+        }
 
         // Create snapshot
-        let snapshot = MempoolSnapshot::from_transactions(transactions, height, Utc::now());
+        let step = Instant::now();
+        let deferred = self
+            .package_limits
+            .map(|limits| limits.deferred_weights(&transactions));
+        let mut snapshot =
+            MempoolSnapshot::from_transactions(transactions, height, self.clock.now());
+        snapshot.bucketed_deferred_weights = deferred.unwrap_or_default();
+        snapshot.source.clone_from(&self.source);
+        cycle.parse_ms = Some(elapsed_ms(step));
 
         // Save snapshot to disk
+        let step = Instant::now();
         self.snapshot_store.save_snapshot(&snapshot)?;
+        cycle.save_ms = Some(elapsed_ms(step));
+
+        // A trace is best effort and never fails a collection
+        if let Some(trace) = &self.trace {
+            if let Err(e) = trace.record(&snapshot) {
+                warn!(
+                    "Failed to append snapshot to trace {path:?}: {e}",
+                    path = trace.path()
+                );
+            }
+        }
 
         // Update latest snapshot
         {
@@ -92,30 +673,380 @@ impl MempoolCollector {
         }
 
         // Get last 24 hours of snapshots for estimation
-        let snapshots = self.snapshot_store.get_recent_snapshots(24)?;
+        let step = Instant::now();
+        let snapshots = self.recent_snapshots().await?;
+        let snapshots = self.backfill_gaps(snapshots).await?;
+        cycle.load_ms = Some(elapsed_ms(step));
 
-        if !snapshots.is_empty() {
-            // Calculate new fee estimates
-            match self.fee_estimator.calculate_estimates(&snapshots, None) {
-                Ok(estimate) => {
-                    info!(
-                        "Successfully calculated fee estimates with {} block targets",
-                        estimate.estimates.len()
-                    );
+        if let (Some(feedback), Some(previous_height), true) =
+            (&self.block_feedback, previous_height, new_block)
+        {
+            self.realize_blocks(feedback, previous_height, height).await;
+        }
 
-                    // Update latest estimate
-                    let mut latest = self.latest_estimate.write().await;
-                    *latest = Some(estimate);
-                }
+        let step = Instant::now();
+        let estimate = self.publish_estimate(&snapshots, new_block).await;
+        cycle.estimate_ms = Some(elapsed_ms(step));
+
+        // The first estimate of each block is scored against the blocks that follow it
+        if let (Some(feedback), Some(estimate), true) = (&self.block_feedback, &estimate, new_block)
+        {
+            feedback.track_estimate(height, estimate.clone());
+        }
+
+        if let Some(alerts) = &self.alerts {
+            let notifications = alerts.evaluate(self.clock.now(), estimate.as_ref(), &snapshot);
+            alerts.notify(notifications);
+        }
+
+        if let Some(interval) = self.adapt_interval(new_block, &snapshots).await {
+            cycle.interval_ms = Some(interval.as_millis() as u64);
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the blocks mined after `previous_height` up to `height` and records what they
+    /// confirmed in `feedback`
+    ///
+    /// Feedback is best effort: a block that cannot be fetched is logged and left unrealized,
+    /// leaving the targets it would resolve unscored.
+    async fn realize_blocks(&self, feedback: &BlockFeedback, previous_height: u32, height: u32) {
+        let first = (previous_height + 1).max(height.saturating_sub(MAX_REALIZED_BLOCKS - 1));
+        for block_height in first..=height {
+            let block = match self.bitcoin_client.get_block(block_height).await {
+                Ok(block) => RealizedBlock::from_block(&block),
                 Err(e) => {
-                    warn!("Failed to calculate fee estimates: {e}");
+                    warn!("Failed to fetch block {block_height} for feedback: {e}");
+                    continue;
                 }
+            };
+            debug!(
+                "Block {block_height} confirmed {count} transactions down to {threshold:?} sat/vB",
+                count = block.transactions,
+                threshold = block.threshold
+            );
+            if let Err(e) = feedback.record_block(block) {
+                warn!("Failed to store realized block {block_height}: {e}");
             }
-        } else {
+        }
+    }
+
+    /// Picks the interval until the next collection from the latest cycle, if it adapts
+    async fn adapt_interval(
+        &self,
+        new_block: bool,
+        snapshots: &[MempoolSnapshot],
+    ) -> Option<Duration> {
+        let policy = self.interval_policy?;
+        let volatility = volatility(snapshots);
+        let mut next_interval = self.next_interval.write().await;
+        let current = next_interval.unwrap_or(policy.min);
+        let next = policy.next(current, new_block, volatility);
+        if next != current {
+            debug!(
+                "Collection interval {current:?} -> {next:?} (new block: {new_block}, inflow \
+                 volatility: {volatility:?})"
+            );
+        }
+        *next_interval = Some(next);
+        Some(next)
+    }
+
+    /// Interval until the next collection, when it follows mempool activity
+    pub async fn next_interval(&self) -> Option<Duration> {
+        *self.next_interval.read().await
+    }
+
+    /// Remembers the gaps in the estimation window `snapshots`, logging when it degrades or
+    /// recovers
+    async fn record_gaps(&self, snapshots: &[MempoolSnapshot]) {
+        let Some(policy) = self.gap_policy else {
+            return;
+        };
+        let gaps = find_gaps(snapshots, policy.max_gap);
+        let mut current = self.gaps.write().await;
+        match (current.is_empty(), gaps.is_empty()) {
+            (true, false) => {
+                for gap in &gaps {
+                    warn!(
+                        "No snapshots from {start} to {end} (heights {start_height} to \
+                         {end_height}), estimates are degraded until the gap leaves the window",
+                        start = gap.start,
+                        end = gap.end,
+                        start_height = gap.start_height,
+                        end_height = gap.end_height
+                    );
+                }
+            }
+            (false, true) => info!("Estimation window has no gaps anymore"),
+            _ => {}
+        }
+        *current = gaps;
+    }
+
+    /// Fills gaps in `snapshots` with snapshots reconstructed from the blocks mined during
+    /// them, returning the reloaded window if any were stored
+    ///
+    /// Each gap is attempted once; gaps spanning more than [`MAX_BACKFILL_BLOCKS`] blocks are
+    /// left alone.
+    async fn backfill_gaps(
+        &self,
+        snapshots: Vec<MempoolSnapshot>,
+    ) -> Result<Vec<MempoolSnapshot>, CollectorError> {
+        let Some(policy) = self.gap_policy.filter(|policy| policy.backfill) else {
+            return Ok(snapshots);
+        };
+
+        let mut stored = 0;
+        for (i, gap) in find_gaps(&snapshots, policy.max_gap)
+            .into_iter()
+            .enumerate()
+        {
+            if !self.backfilled.write().await.insert(gap) {
+                continue;
+            }
+            let blocks = gap.end_height.saturating_sub(gap.start_height);
+            if blocks > MAX_BACKFILL_BLOCKS {
+                warn!(
+                    "Not backfilling the gap from {start} to {end}: {blocks} blocks were mined",
+                    start = gap.start,
+                    end = gap.end
+                );
+                continue;
+            }
+
+            let mut mined = Vec::new();
+            for height in gap.start_height + 1..=gap.end_height {
+                match self.bitcoin_client.get_block(height).await {
+                    Ok(block) => mined.push(block),
+                    Err(e) => {
+                        warn!(
+                            "Not backfilling the gap from {start}: {e}",
+                            start = gap.start
+                        );
+                        break;
+                    }
+                }
+            }
+            if mined.len() as u32 != blocks {
+                continue;
+            }
+
+            // Gaps are found between consecutive snapshots, one pair per gap
+            let Some(pair) = snapshots
+                .windows(2)
+                .filter(|pair| pair[1].timestamp - pair[0].timestamp > policy.max_gap)
+                .nth(i)
+            else {
+                continue;
+            };
+            let reconstructed = reconstruct(&pair[0], &pair[1], &mined, policy.max_gap / 2);
+            for snapshot in &reconstructed {
+                self.snapshot_store.save_snapshot(snapshot)?;
+            }
+            info!(
+                "Backfilled the gap from {start} to {end} with {count} snapshots from {blocks} \
+                 blocks",
+                start = gap.start,
+                end = gap.end,
+                count = reconstructed.len()
+            );
+            stored += reconstructed.len();
+        }
+
+        if stored == 0 {
+            return Ok(snapshots);
+        }
+        self.recent_snapshots().await
+    }
+
+    /// Whether the latest estimation window has gaps, making estimates less reliable
+    pub async fn is_degraded(&self) -> bool {
+        !self.gaps.read().await.is_empty()
+    }
+
+    /// Recomputes the estimate from `snapshots` and makes it the latest one
+    ///
+    /// After a `new_block`, the previous estimate's long-term inflows are reused when the
+    /// estimator computes inflows incrementally.
+    async fn publish_estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        new_block: bool,
+    ) -> Option<FeeEstimate> {
+        if snapshots.is_empty() {
             warn!("No historical snapshots available for fee estimation");
+            return None;
         }
+        self.record_gaps(snapshots).await;
+        *self.block_interval.write().await = observed_block_interval(snapshots);
 
-        Ok(())
+        let estimator = self.fee_estimator().await;
+        if let Some(filter) = estimator.outlier_filter() {
+            for outlier in filter.detect(snapshots) {
+                warn!(
+                    "Excluding outlier snapshot at {timestamp} (height {height}): \
+                     {weight} WU in {buckets} buckets, expected about {expected_weight} WU in \
+                     {expected_buckets} buckets (z-score {z:.1})",
+                    timestamp = outlier.timestamp,
+                    height = outlier.block_height,
+                    weight = outlier.total_weight,
+                    buckets = outlier.bucket_count,
+                    expected_weight = outlier.expected_weight,
+                    expected_buckets = outlier.expected_bucket_count,
+                    z = outlier.z_score
+                );
+            }
+        }
+
+        // Calculate new fee estimates
+        let fingerprint = self.estimate_fingerprint(snapshots).await;
+        let after_block = new_block && estimator.has_incremental_inflows();
+        let estimate = estimate_blocking(
+            estimator.clone(),
+            snapshots.to_vec(),
+            move |estimator, snapshots| {
+                if after_block {
+                    estimator.recompute_after_block(snapshots, None)
+                } else {
+                    estimator.calculate_estimates(snapshots, None)
+                }
+            },
+        )
+        .await;
+        match estimate {
+            Ok(estimate) => {
+                info!(
+                    "Successfully calculated fee estimates with {} block targets",
+                    estimate.targets().count()
+                );
+                log_capped(&estimate, estimator.max_fee_rate());
+                log_widened(&estimate, estimator.min_spread());
+                if let Some(fingerprint) = fingerprint {
+                    self.cache_estimate(fingerprint, &estimate);
+                }
+
+                // Update latest estimate
+                *self.latest_estimate.write().await = Some(estimate.clone());
+
+                if let Some(baselines) = &self.baselines {
+                    baselines
+                        .update(&*self.bitcoin_client, snapshots, &estimate)
+                        .await;
+                }
+                Some(estimate)
+            }
+            Err(e) => {
+                warn!("Failed to calculate fee estimates: {e}");
+                None
+            }
+        }
+    }
+
+    /// Stores externally generated snapshots and recomputes the estimate from them
+    ///
+    /// The estimation window ends at the newest injected snapshot rather than the current
+    /// time, so injected history is used regardless of its timestamps. With `replace`, all
+    /// previously stored snapshots are removed first.
+    pub async fn inject_snapshots(
+        &self,
+        mut snapshots: Vec<MempoolSnapshot>,
+        replace: bool,
+    ) -> Result<Option<FeeEstimate>, CollectorError> {
+        if replace {
+            self.snapshot_store.clear()?;
+            *self.latest_estimate.write().await = None;
+        }
+        // Injected snapshots may reuse the times of the ones they overwrite
+        self.fee_estimator().await.clear_inflow_cache();
+        if let Some(cache) = &self.target_cache {
+            cache.clear();
+        }
+
+        snapshots.sort_by_key(|s| s.timestamp);
+        for snapshot in &snapshots {
+            self.snapshot_store.save_snapshot(snapshot)?;
+        }
+
+        let Some(newest) = snapshots.last() else {
+            return Ok(None);
+        };
+        *self.latest_snapshot.write().await = Some(newest.clone());
+
+        let end = newest.timestamp.with_timezone(&Local);
+        let start = end - estimation_window(&*self.fee_estimator().await);
+        let window = self.load_snapshots(start, end).await?;
+
+        Ok(self.publish_estimate(&window, false).await)
+    }
+
+    /// Stores snapshots taken by an external collector and recomputes the estimate
+    ///
+    /// Unlike injection, the estimation window ends at the collector's current time, exactly as
+    /// if the snapshots had been collected here.
+    pub async fn ingest_snapshots(
+        &self,
+        snapshots: Vec<MempoolSnapshot>,
+    ) -> Result<Option<FeeEstimate>, CollectorError> {
+        // Snapshots up to the latest one may overwrite stored ones the inflow cache measured
+        let newest_stored = self
+            .latest_snapshot
+            .read()
+            .await
+            .as_ref()
+            .map(|s| s.timestamp);
+        if snapshots
+            .iter()
+            .any(|s| newest_stored.is_some_and(|newest| s.timestamp <= newest))
+        {
+            self.fee_estimator().await.clear_inflow_cache();
+        }
+        for snapshot in &snapshots {
+            self.snapshot_store.save_snapshot(snapshot)?;
+        }
+        // Snapshots older than the latest one change the window without changing its end
+        if let Some(cache) = &self.target_cache {
+            cache.clear();
+        }
+
+        if let Some(newest) = snapshots.into_iter().max_by_key(|s| s.timestamp) {
+            let mut latest = self.latest_snapshot.write().await;
+            if latest
+                .as_ref()
+                .is_none_or(|current| current.timestamp < newest.timestamp)
+            {
+                *latest = Some(newest);
+            }
+        }
+
+        let window = self.recent_snapshots().await?;
+        Ok(self.publish_estimate(&window, false).await)
+    }
+
+    /// Error of the latest mempool fetch, if it failed
+    pub async fn rpc_error(&self) -> Option<String> {
+        self.rpc_error.read().await.clone()
+    }
+
+    /// Time since the latest snapshot was taken, or `None` before the first one
+    pub async fn data_age(&self) -> Option<chrono::Duration> {
+        let latest = self.latest_snapshot.read().await;
+        let taken = latest.as_ref()?.timestamp;
+        Some((self.clock.now() - taken).max(chrono::Duration::zero()))
+    }
+
+    /// Whether the latest snapshot is older than the maximum staleness
+    pub async fn is_stale(&self) -> bool {
+        match (self.max_staleness, self.data_age().await) {
+            (Some(max_staleness), Some(age)) => age > max_staleness,
+            _ => false,
+        }
+    }
+
+    /// Number of chain reorganizations seen since startup
+    pub fn reorg_count(&self) -> u64 {
+        self.reorgs.load(Ordering::Relaxed)
     }
 
     /// Gets the latest fee estimate
@@ -123,41 +1054,144 @@ impl MempoolCollector {
         self.latest_estimate.read().await.clone()
     }
 
+    /// Expiry time of each block target in `estimate`, or `None` without a validity policy
+    pub async fn valid_until(
+        &self,
+        estimate: &FeeEstimate,
+    ) -> Option<BTreeMap<BlockTargetKey, DateTime<Utc>>> {
+        let policy = self.validity?;
+        let block_interval = self
+            .block_interval
+            .read()
+            .await
+            .unwrap_or(DEFAULT_BLOCK_INTERVAL);
+        Some(policy.valid_until(estimate, block_interval))
+    }
+
+    /// Progress towards serving a first estimate
+    pub async fn warmup_status(&self) -> WarmupStatus {
+        let has_estimate = self.latest_estimate.read().await.is_some();
+        self.warmup.status(has_estimate)
+    }
+
     /// Gets the latest mempool snapshot
     #[allow(dead_code)]
     pub async fn get_latest_snapshot(&self) -> Option<MempoolSnapshot> {
         self.latest_snapshot.read().await.clone()
     }
 
-    /// Initialize estimates from stored snapshots (for testing)
-    #[doc(hidden)]
-    #[allow(dead_code)]
+    /// Serves the estimate cached before the last restart until a fresh one is published
+    ///
+    /// Returns the restored estimate, or `None` if caching is disabled, nothing was cached, or
+    /// the cached estimate was computed with other settings.
+    pub async fn restore_cached_estimate(&self) -> Result<Option<FeeEstimate>, CollectorError> {
+        let Some(settings) = &self.cache_settings else {
+            return Ok(None);
+        };
+        let Some(cached) = self.snapshot_store.load_cached_estimate()? else {
+            return Ok(None);
+        };
+        if &cached.settings != settings {
+            info!("Ignoring cached fee estimate computed with other estimator settings");
+            return Ok(None);
+        }
+
+        let mut latest = self.latest_estimate.write().await;
+        if latest.is_some() {
+            return Ok(None);
+        }
+        *latest = Some(cached.estimate.clone());
+        Ok(Some(cached.estimate))
+    }
+
+    /// Initialize estimates from stored snapshots
+    ///
+    /// With an estimate cache, a cached estimate whose fingerprint matches the stored snapshots
+    /// is reused instead of being recalculated. Snapshot files are read in parallel, and
+    /// progress is logged and reported by [`warmup_status`](Self::warmup_status).
     pub async fn initialize_from_store(&self) -> Result<(), CollectorError> {
+        self.warmup.start_loading();
+        let result = self.warm_up().await;
+        self.warmup.finish();
+        result
+    }
+
+    /// Publishes an estimate from the stored estimation window
+    async fn warm_up(&self) -> Result<(), CollectorError> {
         // Get recent snapshots
-        let snapshots = self.snapshot_store.get_recent_snapshots(24)?;
+        let snapshots = self.warm_up_snapshots().await?;
+        self.warmup.start_estimating();
 
         if !snapshots.is_empty() {
-            // Calculate fee estimates
-            match self.fee_estimator.calculate_estimates(&snapshots, None) {
+            *self.block_interval.write().await = observed_block_interval(&snapshots);
+            self.record_gaps(&snapshots).await;
+
+            let fingerprint = self.estimate_fingerprint(&snapshots).await;
+            let latest_snapshot = snapshots.last().cloned();
+            let baseline_window = self.baselines.is_some().then(|| snapshots.clone());
+            let cached = match &fingerprint {
+                Some(fingerprint) => self
+                    .snapshot_store
+                    .load_cached_estimate()
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to read cached fee estimate: {e}");
+                        None
+                    })
+                    .filter(|cached| &cached.fingerprint == fingerprint),
+                None => None,
+            };
+            let estimate = match cached {
+                Some(cached) => {
+                    info!(
+                        "Cached fee estimates match the stored snapshots, skipping recalculation"
+                    );
+                    Ok(cached.estimate)
+                }
+                None => estimate_blocking(
+                    self.fee_estimator().await,
+                    snapshots,
+                    |estimator, snapshots| estimator.calculate_estimates(snapshots, None),
+                )
+                .await
+                .inspect(|estimate| {
+                    if let Some(fingerprint) = fingerprint {
+                        self.cache_estimate(fingerprint, estimate);
+                    }
+                }),
+            };
+
+            match estimate {
                 Ok(estimate) => {
                     info!(
                         "Initialized with fee estimates for {} block targets",
-                        estimate.estimates.len()
+                        estimate.targets().count()
                     );
 
                     // Update latest estimate
-                    let mut latest = self.latest_estimate.write().await;
-                    *latest = Some(estimate);
+                    let augur = baseline_window.is_some().then(|| estimate.clone());
+                    self.publish_if_newer(estimate).await;
+                    if let (Some(baselines), Some(window), Some(augur)) =
+                        (&self.baselines, baseline_window, augur)
+                    {
+                        baselines
+                            .update(&*self.bitcoin_client, &window, &augur)
+                            .await;
+                    }
 
                     // Also set a snapshot if we have one
-                    if let Some(snapshot) = snapshots.last() {
+                    if let Some(snapshot) = latest_snapshot {
                         let mut latest_snap = self.latest_snapshot.write().await;
-                        *latest_snap = Some(snapshot.clone());
+                        if latest_snap
+                            .as_ref()
+                            .is_none_or(|current| current.timestamp < snapshot.timestamp)
+                        {
+                            *latest_snap = Some(snapshot);
+                        }
                     }
                 }
                 Err(e) => {
                     warn!("Failed to calculate initial fee estimates: {e}");
-                    return Err(CollectorError::EstimationError(e));
+                    return Err(e);
                 }
             }
         }
@@ -166,34 +1200,201 @@ impl MempoolCollector {
     }
 
     /// Calculates fee estimates for a specific block target
+    ///
+    /// Concurrent requests for the same target share a single computation rather than each
+    /// estimating from the whole window. With a target cache, estimates computed from the
+    /// latest snapshot are served again until the cache's TTL, and targets beyond the cap on
+    /// distinct targets per minute fail.
     pub async fn get_estimate_for_blocks(
         &self,
         num_blocks: f64,
     ) -> Result<FeeEstimate, CollectorError> {
-        // Get recent snapshots
-        let snapshots = self.snapshot_store.get_recent_snapshots(24)?;
+        let snapshot = self
+            .latest_snapshot
+            .read()
+            .await
+            .as_ref()
+            .map(|snapshot| snapshot.timestamp);
+        if let Some(estimate) = self
+            .target_cache
+            .as_ref()
+            .and_then(|cache| cache.get(num_blocks, snapshot, self.clock.now()))
+        {
+            debug!("get_estimate_for_blocks: cached estimate for {num_blocks} blocks");
+            return Ok(estimate);
+        }
 
-        debug!(
-            "get_estimate_for_blocks: num_blocks={}, snapshots_count={}",
-            num_blocks,
-            snapshots.len()
-        );
+        let key = num_blocks.to_bits();
+        let pending = {
+            let mut pending_targets = self
+                .pending_targets
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            match pending_targets.get(&key) {
+                Some(pending) => {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    debug!("get_estimate_for_blocks: joining estimate for {num_blocks} blocks");
+                    pending.clone()
+                }
+                None => {
+                    if let Some(cache) = &self.target_cache {
+                        if !cache.admit(num_blocks, self.clock.now()) {
+                            return Err(CollectorError::TooManyTargets(format!(
+                                "at most {} distinct block targets are estimated per minute",
+                                cache.policy().max_targets_per_minute.unwrap_or_default()
+                            )));
+                        }
+                    }
+                    let pending = self.estimate_for_blocks(num_blocks, snapshot).shared();
+                    pending_targets.insert(key, pending.clone());
+                    pending
+                }
+            }
+        };
+
+        // Only the last request holding the error gets it back as it was
+        pending
+            .await
+            .map_err(|err| Arc::try_unwrap(err).unwrap_or_else(CollectorError::Coalesced))
+    }
+
+    /// Computation behind [`get_estimate_for_blocks`](Self::get_estimate_for_blocks), which
+    /// stops being shared once it completes, caching its estimate as computed from the latest
+    /// `snapshot`
+    fn estimate_for_blocks(
+        &self,
+        num_blocks: f64,
+        snapshot: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'static, Result<FeeEstimate, Arc<CollectorError>>> {
+        let fee_estimator = self.fee_estimator.clone();
+        let snapshot_store = self.snapshot_store.clone();
+        let clock = self.clock.clone();
+        let pending_targets = self.pending_targets.clone();
+        let target_cache = self.target_cache.clone();
+
+        async move {
+            let computed_at = clock.now();
+            let result = async {
+                let estimator = fee_estimator.read().await.clone();
+                let end = clock.now().with_timezone(&Local);
+                let start = end - estimation_window(&*estimator);
+                let snapshots =
+                    tokio::task::spawn_blocking(move || snapshot_store.get_snapshots(start, end))
+                        .await??;
+
+                debug!(
+                    "get_estimate_for_blocks: num_blocks={}, snapshots_count={}",
+                    num_blocks,
+                    snapshots.len()
+                );
+
+                if snapshots.is_empty() {
+                    return Ok(FeeEstimate::empty(clock.now()));
+                }
+
+                // Calculate estimates for specific target
+                let estimate =
+                    estimate_blocking(estimator, snapshots, move |estimator, snapshots| {
+                        estimator.calculate_estimates(snapshots, Some(num_blocks))
+                    })
+                    .await?;
+
+                debug!(
+                    "get_estimate_for_blocks: estimate has {} targets",
+                    estimate.targets().count()
+                );
 
+                Ok::<_, CollectorError>(estimate)
+            }
+            .await;
+
+            if let (Some(cache), Ok(estimate)) = (&target_cache, &result) {
+                cache.insert(num_blocks, snapshot, computed_at, estimate.clone());
+            }
+            pending_targets
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&num_blocks.to_bits());
+            result.map_err(Arc::new)
+        }
+        .boxed()
+    }
+
+    /// Number of block target requests served by a computation already in flight
+    pub fn coalesced_count(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// Calculates fee rates for a specific block target at each of `probabilities`, with
+    /// buckets measured by `weighting`
+    pub async fn get_confidence_curve(
+        &self,
+        num_blocks: f64,
+        probabilities: Vec<f64>,
+        weighting: BucketWeighting,
+    ) -> Result<FeeEstimate, CollectorError> {
+        let snapshots = self.recent_snapshots().await?;
         if snapshots.is_empty() {
-            return Ok(FeeEstimate::empty(Utc::now()));
+            return Ok(FeeEstimate::empty(self.clock.now()));
         }
 
-        // Calculate estimates for specific target
-        let estimate = self
-            .fee_estimator
-            .calculate_estimates(&snapshots, Some(num_blocks))?;
+        estimate_blocking(
+            self.weighted_estimator(weighting).await,
+            snapshots,
+            move |estimator, snapshots| {
+                estimator.confidence_curve(snapshots, num_blocks, &probabilities)
+            },
+        )
+        .await
+    }
 
-        debug!(
-            "get_estimate_for_blocks: estimate has {} targets",
-            estimate.estimates.len()
-        );
+    /// Calculates fee rates for each of `block_targets` at a single confidence level, with
+    /// buckets measured by `weighting`
+    pub async fn get_target_curve(
+        &self,
+        probability: f64,
+        block_targets: Vec<f64>,
+        weighting: BucketWeighting,
+    ) -> Result<FeeEstimate, CollectorError> {
+        let snapshots = self.recent_snapshots().await?;
+        if snapshots.is_empty() {
+            return Ok(FeeEstimate::empty(self.clock.now()));
+        }
+
+        estimate_blocking(
+            self.weighted_estimator(weighting).await,
+            snapshots,
+            move |estimator, snapshots| {
+                estimator.target_curve(snapshots, probability, &block_targets)
+            },
+        )
+        .await
+    }
+
+    /// Estimates each snapshot source in the estimation window separately, plus their median
+    pub async fn get_source_estimates(&self) -> Result<SourceEstimates, CollectorError> {
+        let snapshots = self.recent_snapshots().await?;
+        estimate_blocking(
+            self.fee_estimator().await,
+            snapshots,
+            |estimator, snapshots| estimator.calculate_by_source(snapshots, None),
+        )
+        .await
+    }
 
-        Ok(estimate)
+    /// Runs the mining simulations behind the estimate for one target and confidence level
+    pub async fn simulate(
+        &self,
+        num_blocks: f64,
+        probability: f64,
+    ) -> Result<Simulation, CollectorError> {
+        let snapshots = self.recent_snapshots().await?;
+        estimate_blocking(
+            self.fee_estimator().await,
+            snapshots,
+            move |estimator, snapshots| estimator.simulate(snapshots, num_blocks, probability),
+        )
+        .await
     }
 
     /// Gets fee estimate for a historical timestamp
@@ -205,16 +1406,105 @@ impl MempoolCollector {
             .ok_or(PersistenceError::InvalidTimestamp(timestamp))?
             .with_timezone(&Local);
 
-        // Get snapshots from 24 hours before the target time
-        let start = datetime - chrono::Duration::days(1);
-        let snapshots = self.snapshot_store.get_snapshots(start, datetime)?;
+        // Get the estimation window of snapshots before the target time
+        let estimator = self.fee_estimator().await;
+        let start = datetime - estimation_window(&*estimator);
+        let snapshots = self.load_snapshots(start, datetime).await?;
 
         if snapshots.is_empty() {
             return Ok(FeeEstimate::empty(datetime.with_timezone(&Utc)));
         }
 
-        let estimate = self.fee_estimator.calculate_estimates(&snapshots, None)?;
-        Ok(estimate)
+        estimate_blocking(estimator, snapshots, |estimator, snapshots| {
+            estimator.calculate_estimates(snapshots, None)
+        })
+        .await
+    }
+
+    /// Recomputes the estimate at every `step` from `from` to `to`, as
+    /// [`get_estimate_for_timestamp`](Self::get_estimate_for_timestamp) would have, sending
+    /// each one as soon as it is computed
+    ///
+    /// Snapshots are loaded [`SERIES_CHUNK_HOURS`] at a time and dropped once they leave the
+    /// estimation window, so a long range never sits in memory at once. Points with too few
+    /// snapshots in their estimation window are skipped; any other error is sent and ends the
+    /// series.
+    pub async fn estimate_series(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step: chrono::Duration,
+    ) -> Result<mpsc::Receiver<Result<FeeEstimate, CollectorError>>, CollectorError> {
+        let estimator = self.fee_estimator().await;
+        let window = estimation_window(&*estimator);
+        let store = self.snapshot_store.clone();
+
+        let (sender, receiver) = mpsc::channel(SERIES_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            let mut snapshots = VecDeque::new();
+            let mut loaded_until = from - window;
+            let mut point = from;
+            while point <= to {
+                // Chunks share their boundary, so snapshots already loaded are skipped
+                if loaded_until < point {
+                    let chunk_end = (point + chrono::Duration::hours(SERIES_CHUNK_HOURS)).min(to);
+                    let chunk = match store.get_snapshots(
+                        loaded_until.with_timezone(&Local),
+                        chunk_end.with_timezone(&Local),
+                    ) {
+                        Ok(chunk) => chunk,
+                        Err(e) => {
+                            let _ = sender.blocking_send(Err(e.into()));
+                            break;
+                        }
+                    };
+                    let last = snapshots.back().map(|s: &MempoolSnapshot| s.timestamp);
+                    snapshots.extend(
+                        chunk
+                            .into_iter()
+                            .filter(|s| last.is_none_or(|last| s.timestamp > last)),
+                    );
+                    loaded_until = chunk_end;
+                }
+                while snapshots
+                    .front()
+                    .is_some_and(|s| s.timestamp < point - window)
+                {
+                    snapshots.pop_front();
+                }
+
+                let end = snapshots.partition_point(|s| s.timestamp <= point);
+                point += step;
+                if end == 0 {
+                    continue;
+                }
+
+                let in_window = &snapshots.make_contiguous()[..end];
+                let estimate = match estimator.calculate_estimates(in_window, None) {
+                    Err(bitcoin_augur::AugurError::InsufficientData(_)) => continue,
+                    result => result.map_err(CollectorError::from),
+                };
+                let failed = estimate.is_err();
+                // Stop once the receiver is gone or after an error
+                if sender.blocking_send(estimate).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
+    /// Loads the stored snapshots from `from` to `to`, oldest first
+    pub async fn snapshots_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<MempoolSnapshot>, CollectorError> {
+        let mut snapshots = self
+            .load_snapshots(from.with_timezone(&Local), to.with_timezone(&Local))
+            .await?;
+        snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+        Ok(snapshots)
     }
 
     /// Performs cleanup of old snapshots
@@ -225,6 +1515,50 @@ impl MempoolCollector {
         Ok(deleted)
     }
 
+    /// Relearns the seasonal inflow profile from stored history and starts estimating with it
+    ///
+    /// # Returns
+    /// The number of hours of the week the profile covers, or `None` without a seasonality
+    /// policy
+    pub async fn refresh_seasonality(&self) -> Result<Option<usize>, CollectorError> {
+        let Some(policy) = self.seasonality else {
+            return Ok(None);
+        };
+
+        let profile = policy.load_profile(&self.snapshot_store, self.clock.now())?;
+        let hours_covered = profile.hours_covered();
+        let estimator = (*self.fee_estimator().await)
+            .clone()
+            .with_seasonality(profile, policy.blend)?;
+        *self.fee_estimator.write().await = Arc::new(estimator);
+
+        info!(
+            "Learned seasonal inflow profile from {weeks} weeks of history, covering \
+             {hours_covered} of {total} hours of the week",
+            weeks = policy.weeks,
+            total = HOURS_PER_WEEK
+        );
+        Ok(Some(hours_covered))
+    }
+
+    /// Looks up a transaction in the node's mempool
+    pub async fn get_mempool_entry(
+        &self,
+        txid: &str,
+    ) -> Result<Option<TxMempoolEntry>, CollectorError> {
+        Ok(self.bitcoin_client.get_mempool_entry(txid).await?)
+    }
+
+    /// Gets the node's incremental relay fee in sat/vB
+    pub async fn get_incremental_relay_fee(&self) -> Result<f64, CollectorError> {
+        Ok(self.bitcoin_client.get_incremental_relay_fee().await?)
+    }
+
+    /// Gets the lowest fee rate the node's mempool currently admits, in sat/vB
+    pub async fn get_mempool_min_fee(&self) -> Result<f64, CollectorError> {
+        Ok(self.bitcoin_client.get_mempool_min_fee().await?)
+    }
+
     /// Tests the Bitcoin RPC connection
     #[allow(dead_code)]
     pub async fn test_connection(&self) -> Result<(), CollectorError> {
@@ -232,3 +1566,736 @@ impl MempoolCollector {
         Ok(())
     }
 }
+
+/// Warns about the fee rates clamped to `max_fee_rate` in `estimate`
+fn log_capped(estimate: &FeeEstimate, max_fee_rate: Option<f64>) {
+    let Some(max_fee_rate) = max_fee_rate else {
+        return;
+    };
+    let capped = flagged(estimate, |target| &target.capped);
+    if capped.is_empty() {
+        return;
+    }
+    warn!(
+        "Clamped {count} fee rates to the maximum of {max_fee_rate} sat/vB \
+         (target@confidence): {list}",
+        count = capped.len(),
+        list = capped.join(", ")
+    );
+}
+
+/// Notes the fee rates raised to keep `min_spread` between confidence levels in `estimate`
+fn log_widened(estimate: &FeeEstimate, min_spread: Option<f64>) {
+    let Some(min_spread) = min_spread else {
+        return;
+    };
+    let widened = flagged(estimate, |target| &target.widened);
+    if widened.is_empty() {
+        return;
+    }
+    debug!(
+        "Raised {count} fee rates to keep a spread of {min_spread} between confidence levels \
+         (target@confidence): {list}",
+        count = widened.len(),
+        list = widened.join(", ")
+    );
+}
+
+/// The fee rates of `estimate` in the set `flags` picks from each target, as
+/// `target@confidence`
+fn flagged(
+    estimate: &FeeEstimate,
+    flags: impl Fn(&BlockTarget) -> &BTreeSet<OrderedFloat>,
+) -> Vec<String> {
+    estimate
+        .targets()
+        .flat_map(|(blocks, target)| {
+            flags(target).iter().map(move |probability| {
+                format!("{blocks}@{confidence:.2}", confidence = probability.0)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::{MinedBlock, MockBitcoinClient, Scenario};
+    use bitcoin_augur::MempoolTransaction;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn block_per_poll_scenario(polls: usize) -> Scenario {
+        let steps: Vec<_> = (0..polls)
+            .map(|i| {
+                serde_json::json!({
+                    "blocks_mined": 1,
+                    "transactions": [
+                        { "fee_rate": 2.0, "weight": 4000, "count": 200 + i % 7 },
+                        { "fee_rate": 20.0, "weight": 4000, "count": 100 + i % 5 },
+                    ],
+                })
+            })
+            .collect();
+
+        serde_json::from_value(serde_json::json!({ "name": "test", "steps": steps })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ingest_over_stored_snapshots_refreshes_inflows() {
+        let now = Utc::now();
+        let history = |growth: u64| -> Vec<MempoolSnapshot> {
+            (0..12u32)
+                .map(|i| {
+                    let weights = BTreeMap::from([
+                        (100, 400_000 * growth * u64::from(i % 3 + 1)),
+                        (300, 4_000_000),
+                    ]);
+                    let taken = now - chrono::Duration::minutes(60 - 5 * i64::from(i));
+                    MempoolSnapshot::new(850_000 + i / 3, taken, weights)
+                })
+                .collect()
+        };
+        let collector = |dir: &TempDir| {
+            MempoolCollector::new(
+                BitcoinClient::Mock(MockBitcoinClient::new()),
+                SnapshotStore::new(dir.path()).unwrap(),
+                FeeEstimator::new().with_incremental_inflows(),
+            )
+        };
+
+        // Replacing the stored history gives what ingesting only the replacement gives
+        let (dir, fresh_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let overwritten = collector(&dir);
+        overwritten.ingest_snapshots(history(1)).await.unwrap();
+        let replaced = overwritten.ingest_snapshots(history(20)).await.unwrap();
+        let fresh = collector(&fresh_dir)
+            .ingest_snapshots(history(20))
+            .await
+            .unwrap();
+
+        let (replaced, fresh) = (replaced.unwrap(), fresh.unwrap());
+        assert!(!fresh.estimates.is_empty());
+        for (target, expected) in &fresh.estimates {
+            assert_eq!(
+                replaced.estimates[target].probabilities,
+                expected.probabilities
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulated_clock_collects_a_day_without_sleeping() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, chrono::Duration::minutes(30));
+
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(50)).unwrap(),
+            ),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone());
+
+        let started = std::time::Instant::now();
+        for _ in 0..50 {
+            collector.update_fee_estimates().await.unwrap();
+            clock.tick();
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(60));
+
+        // 50 cycles of 30 minutes span 25 virtual hours
+        let last_poll = start + chrono::Duration::minutes(49 * 30);
+        let latest = collector.get_latest_snapshot().await.unwrap();
+        assert_eq!(latest.timestamp, last_poll);
+
+        // The estimation window is anchored at virtual time, not wall-clock time
+        let window = collector.recent_snapshots().await.unwrap();
+        assert_eq!(window.len(), 48);
+        assert!(window.iter().all(|s| s.timestamp > start));
+
+        let estimate = collector.get_latest_estimate().await.unwrap();
+        assert!(!estimate.estimates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_first_snapshot_is_taken_at_simulated_clock_start() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, chrono::Duration::minutes(30));
+        let collector = Arc::new(
+            MempoolCollector::new(
+                BitcoinClient::Mock(
+                    MockBitcoinClient::with_scenario(block_per_poll_scenario(2)).unwrap(),
+                ),
+                SnapshotStore::new(temp_dir.path()).unwrap(),
+                FeeEstimator::new(),
+            )
+            .with_clock(clock.clone()),
+        );
+
+        let running = tokio::spawn({
+            let collector = collector.clone();
+            async move { collector.start(3_600_000).await }
+        });
+        // The clock moves once the first cycle has completed
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+        while clock.now() == start {
+            assert!(std::time::Instant::now() < deadline, "no cycle completed");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        running.abort();
+
+        let snapshot = collector.get_latest_snapshot().await.unwrap();
+        assert_eq!(snapshot.timestamp, start);
+        assert_eq!(clock.now(), start + chrono::Duration::minutes(30));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_series_matches_historical_estimates() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, chrono::Duration::minutes(30));
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(12)).unwrap(),
+            ),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone());
+        for _ in 0..12 {
+            collector.update_fee_estimates().await.unwrap();
+            clock.tick();
+        }
+
+        // Hourly from an hour before the first snapshot, which has nothing to estimate from
+        let mut series = collector
+            .estimate_series(
+                start - chrono::Duration::hours(1),
+                start + chrono::Duration::hours(5),
+                chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        let mut estimates = Vec::new();
+        while let Some(estimate) = series.recv().await {
+            estimates.push(estimate.unwrap());
+        }
+
+        assert_eq!(estimates.len(), 6);
+        for (hour, estimate) in (0..).zip(&estimates) {
+            let point = start + chrono::Duration::hours(hour);
+            let historical = collector
+                .get_estimate_for_timestamp(point.timestamp())
+                .await
+                .unwrap();
+            assert_eq!(estimate.timestamp, historical.timestamp);
+            assert_eq!(
+                estimate.get_fee_rate(6, 0.5),
+                historical.get_fee_rate(6, 0.5)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_series_spanning_several_chunks() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, chrono::Duration::hours(2));
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(30)).unwrap(),
+            ),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone());
+        for _ in 0..30 {
+            collector.update_fee_estimates().await.unwrap();
+            clock.tick();
+        }
+
+        // 58 hours of snapshots, loaded a day at a time and dropped after the window
+        let mut series = collector
+            .estimate_series(
+                start,
+                start + chrono::Duration::hours(58),
+                chrono::Duration::hours(2),
+            )
+            .await
+            .unwrap();
+        let mut estimates = Vec::new();
+        while let Some(estimate) = series.recv().await {
+            estimates.push(estimate.unwrap());
+        }
+
+        assert_eq!(estimates.len(), 30);
+        for estimate in &estimates {
+            let historical = collector
+                .get_estimate_for_timestamp(estimate.timestamp.timestamp())
+                .await
+                .unwrap();
+            assert_eq!(
+                estimate.get_fee_rate(6, 0.5),
+                historical.get_fee_rate(6, 0.5)
+            );
+            assert_eq!(
+                estimate.get_fee_rate(144, 0.95),
+                historical.get_fee_rate(144, 0.95)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_valid_until_uses_observed_block_interval() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, chrono::Duration::minutes(30));
+        let policy = ValidityPolicy {
+            collection_interval: chrono::Duration::seconds(30),
+            target_fraction: 0.1,
+            max_ttl: chrono::Duration::hours(1),
+        };
+
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(10)).unwrap(),
+            ),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone());
+
+        collector.update_fee_estimates().await.unwrap();
+        let estimate = collector.get_latest_estimate().await.unwrap();
+        assert!(collector.valid_until(&estimate).await.is_none());
+
+        let collector = collector.with_validity(policy);
+        // Before enough blocks were seen, the nominal 10 minute interval is assumed
+        let valid_until = collector.valid_until(&estimate).await.unwrap();
+        assert_eq!(
+            valid_until[&BlockTargetKey::from(3)],
+            estimate.timestamp + chrono::Duration::minutes(3)
+        );
+
+        for _ in 0..9 {
+            clock.tick();
+            collector.update_fee_estimates().await.unwrap();
+        }
+
+        // One block per 30 minute poll
+        let estimate = collector.get_latest_estimate().await.unwrap();
+        let valid_until = collector.valid_until(&estimate).await.unwrap();
+        assert_eq!(valid_until.len(), estimate.estimates.len());
+        assert_eq!(
+            valid_until[&BlockTargetKey::from(3)],
+            estimate.timestamp + chrono::Duration::minutes(9)
+        );
+        assert_eq!(
+            valid_until[&BlockTargetKey::from(144)],
+            estimate.timestamp + chrono::Duration::hours(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_seasonality_installs_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 1, 27, 0, 0, 0).unwrap();
+
+        // Two snapshots of the same block in each of the last 48 hours
+        for hour in 1..=48 {
+            let start = end - chrono::Duration::hours(hour);
+            for (minutes, weight) in [(0, 100_000), (40, 400_000)] {
+                store
+                    .save_snapshot(&MempoolSnapshot::new(
+                        850_000 - hour as u32,
+                        start + chrono::Duration::minutes(minutes),
+                        BTreeMap::from([(100, weight)]),
+                    ))
+                    .unwrap();
+            }
+        }
+
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(MockBitcoinClient::new()),
+            store,
+            FeeEstimator::new(),
+        )
+        .with_clock(Clock::simulated(end, chrono::Duration::minutes(1)));
+        assert_eq!(collector.refresh_seasonality().await.unwrap(), None);
+        assert!(collector.fee_estimator().await.seasonality().is_none());
+
+        let collector = collector.with_seasonality(SeasonalityPolicy {
+            weeks: 2,
+            blend: 0.5,
+        });
+        assert_eq!(collector.refresh_seasonality().await.unwrap(), Some(48));
+
+        let estimator = collector.fee_estimator().await;
+        let (profile, blend) = estimator.seasonality().unwrap();
+        assert_eq!(blend, 0.5);
+        // Sunday 12:00 UTC gained 300,000 WU in 40 minutes
+        assert_eq!(profile.inflow_rate(6 * 24 + 12), Some(75_000.0));
+    }
+
+    #[tokio::test]
+    async fn test_cached_estimate_survives_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, chrono::Duration::minutes(30));
+        let restart = |settings: &str| {
+            MempoolCollector::new(
+                BitcoinClient::Mock(MockBitcoinClient::new()),
+                SnapshotStore::new(temp_dir.path()).unwrap(),
+                FeeEstimator::new(),
+            )
+            .with_clock(clock.clone())
+            .with_estimate_cache(settings)
+        };
+
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(10)).unwrap(),
+            ),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone())
+        .with_estimate_cache("a");
+        for _ in 0..10 {
+            collector.update_fee_estimates().await.unwrap();
+            clock.tick();
+        }
+        let estimate = collector.get_latest_estimate().await.unwrap();
+
+        // Mark the cached estimate to tell it apart from a recalculated one
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        let mut cached = store.load_cached_estimate().unwrap().unwrap();
+        assert_eq!(cached.estimate.timestamp, estimate.timestamp);
+        cached.estimate.estimates.retain(|&blocks, _| blocks == 6);
+        store.save_cached_estimate(&cached).unwrap();
+
+        // Served before the snapshots are reloaded, and kept while they still match
+        let collector = restart("a");
+        let restored = collector.restore_cached_estimate().await.unwrap().unwrap();
+        assert_eq!(restored.estimates.len(), 1);
+        assert_eq!(
+            collector
+                .get_latest_estimate()
+                .await
+                .unwrap()
+                .estimates
+                .len(),
+            1
+        );
+        collector.initialize_from_store().await.unwrap();
+        assert_eq!(
+            collector
+                .get_latest_estimate()
+                .await
+                .unwrap()
+                .estimates
+                .len(),
+            1
+        );
+
+        // Other settings recalculate and replace the cache
+        let collector = restart("b");
+        assert!(collector.restore_cached_estimate().await.unwrap().is_none());
+        assert!(collector.get_latest_estimate().await.is_none());
+        collector.initialize_from_store().await.unwrap();
+        let recalculated = collector.get_latest_estimate().await.unwrap();
+        assert_eq!(recalculated.estimates.len(), estimate.estimates.len());
+        assert_eq!(store.load_cached_estimate().unwrap().unwrap().settings, "b");
+    }
+
+    #[tokio::test]
+    async fn test_gaps_degrade_until_backfilled() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let block = MinedBlock {
+            height: 850_003,
+            time: start + chrono::Duration::minutes(30),
+            transactions: vec![MempoolTransaction::new(4000, 8000)],
+        };
+
+        for backfill in [false, true] {
+            let temp_dir = TempDir::new().unwrap();
+            let clock = Clock::simulated(start, chrono::Duration::minutes(1));
+            let collector = MempoolCollector::new(
+                BitcoinClient::Mock(
+                    MockBitcoinClient::with_scenario(block_per_poll_scenario(10))
+                        .unwrap()
+                        .with_block(block.clone()),
+                ),
+                SnapshotStore::new(temp_dir.path()).unwrap(),
+                FeeEstimator::new(),
+            )
+            .with_clock(clock.clone())
+            .with_gap_policy(GapPolicy {
+                max_gap: chrono::Duration::minutes(10),
+                backfill,
+            });
+
+            for _ in 0..3 {
+                collector.update_fee_estimates().await.unwrap();
+                clock.tick();
+            }
+            assert!(!collector.is_degraded().await);
+
+            // An hour without polls, during which one block was mined
+            clock.advance(chrono::Duration::hours(1));
+            collector.update_fee_estimates().await.unwrap();
+            let window = collector.recent_snapshots().await.unwrap();
+            if backfill {
+                assert!(!collector.is_degraded().await);
+                assert!(window.len() > 4);
+                assert!(window
+                    .iter()
+                    .any(|s| s.block_height == block.height && s.timestamp == block.time));
+            } else {
+                assert!(collector.is_degraded().await);
+                assert_eq!(window.len(), 4);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_goes_stale_while_rpc_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let clock = Clock::simulated(start, chrono::Duration::minutes(1));
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(3)).unwrap(),
+            ),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone())
+        .with_max_staleness(chrono::Duration::minutes(5));
+        assert_eq!(collector.data_age().await, None);
+        assert!(!collector.is_stale().await);
+
+        collector.update_fee_estimates().await.unwrap();
+        assert_eq!(collector.data_age().await, Some(chrono::Duration::zero()));
+
+        // Polls stop refreshing the snapshot, as when the node is unreachable
+        clock.advance(chrono::Duration::minutes(5));
+        assert!(!collector.is_stale().await);
+        clock.advance(chrono::Duration::seconds(1));
+        assert_eq!(
+            collector.data_age().await,
+            Some(chrono::Duration::seconds(301))
+        );
+        assert!(collector.is_stale().await);
+
+        // The next successful poll is fresh again
+        collector.update_fee_estimates().await.unwrap();
+        assert!(!collector.is_stale().await);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_target_requests_share_one_estimate() {
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Clock::simulated(
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            chrono::Duration::minutes(1),
+        );
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(5)).unwrap(),
+            ),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone());
+        for _ in 0..5 {
+            collector.update_fee_estimates().await.unwrap();
+            clock.tick();
+        }
+
+        // Holding the estimator keeps the first request's estimate in flight until all joined
+        let estimator = collector.fee_estimator.write().await;
+        let requests =
+            futures_util::future::join_all((0..8).map(|_| collector.get_estimate_for_blocks(7.0)));
+        tokio::pin!(requests);
+        assert!(futures_util::poll!(&mut requests).is_pending());
+        assert_eq!(collector.coalesced_count(), 7);
+        drop(estimator);
+        let estimates = requests.await;
+        let first = estimates[0].as_ref().unwrap();
+        assert!(first.get_fee_rate(7, 0.5).is_some());
+        for estimate in &estimates {
+            let estimate = estimate.as_ref().unwrap();
+            assert_eq!(estimate.timestamp, first.timestamp);
+            assert_eq!(estimate.get_fee_rate(7, 0.5), first.get_fee_rate(7, 0.5));
+        }
+
+        // Completed estimates are not reused, and other targets are computed separately
+        collector.get_estimate_for_blocks(7.0).await.unwrap();
+        let (_, invalid) = tokio::join!(
+            collector.get_estimate_for_blocks(7.0),
+            collector.get_estimate_for_blocks(0.5),
+        );
+        assert_eq!(collector.coalesced_count(), 7);
+        assert!(matches!(
+            invalid,
+            Err(CollectorError::EstimationError(
+                bitcoin_augur::AugurError::InvalidParameter(_)
+            ))
+        ));
+
+        // Requests sharing a failed estimate each get its error
+        let estimator = collector.fee_estimator.write().await;
+        let requests =
+            futures_util::future::join_all((0..3).map(|_| collector.get_estimate_for_blocks(0.5)));
+        tokio::pin!(requests);
+        assert!(futures_util::poll!(&mut requests).is_pending());
+        drop(estimator);
+        let failures = requests.await;
+        assert_eq!(collector.coalesced_count(), 9);
+        for failure in failures {
+            assert!(matches!(
+                failure.unwrap_err().cause(),
+                CollectorError::EstimationError(bitcoin_augur::AugurError::InvalidParameter(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reorg_is_detected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut scenario = block_per_poll_scenario(6);
+        // The fourth poll sees the chain fall back from 850002 to 850001
+        scenario.steps[3].block_height = Some(850_001);
+        let clock = Clock::simulated(
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            chrono::Duration::minutes(1),
+        );
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(MockBitcoinClient::with_scenario(scenario).unwrap()),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone());
+
+        for _ in 0..3 {
+            collector.update_fee_estimates().await.unwrap();
+            clock.tick();
+        }
+        assert_eq!(collector.reorg_count(), 0);
+
+        collector.update_fee_estimates().await.unwrap();
+        assert_eq!(collector.reorg_count(), 1);
+        assert_eq!(
+            collector.get_latest_snapshot().await.unwrap().block_height,
+            850_001
+        );
+        assert!(collector.get_latest_estimate().await.is_some());
+
+        clock.tick();
+        collector.update_fee_estimates().await.unwrap();
+        assert_eq!(collector.reorg_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_interval_adapts_to_blocks_and_quiet() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut scenario = block_per_poll_scenario(1);
+        // The same mempool at the same height, polled three times, then a block
+        let mut quiet = scenario.steps[0].clone();
+        quiet.blocks_mined = 0;
+        quiet.polls = 3;
+        let block = scenario.steps[0].clone();
+        scenario.steps.extend([quiet, block]);
+        let policy = IntervalPolicy {
+            min: Duration::from_secs(10),
+            max: Duration::from_secs(20),
+            volatility_threshold: 0.5,
+        };
+        let clock = Clock::simulated(
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            chrono::Duration::minutes(1),
+        );
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(MockBitcoinClient::with_scenario(scenario).unwrap()),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone())
+        .with_interval_policy(policy);
+
+        let mut intervals = Vec::new();
+        for _ in 0..5 {
+            collector.update_fee_estimates().await.unwrap();
+            intervals.push(collector.next_interval().await.unwrap().as_secs());
+            clock.tick();
+        }
+        // Backs off from the minimum while nothing happens, up to the maximum, until a block
+        assert_eq!(intervals, vec![15, 20, 20, 20, 10]);
+    }
+
+    #[tokio::test]
+    async fn test_cycles_are_logged() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        let log = CycleLog::open(store.cycle_log_path(), 2).unwrap();
+        let clock = Clock::simulated(
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            chrono::Duration::minutes(1),
+        );
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(
+                MockBitcoinClient::with_scenario(block_per_poll_scenario(3)).unwrap(),
+            ),
+            store,
+            FeeEstimator::new(),
+        )
+        .with_clock(clock.clone())
+        .with_cycle_log(log);
+
+        for _ in 0..3 {
+            collector.update_fee_estimates().await.unwrap();
+            clock.tick();
+        }
+
+        let cycles = collector.recent_cycles(10);
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(
+            cycles[0].started_at,
+            clock.now() - chrono::Duration::minutes(1)
+        );
+        assert_eq!(cycles[0].block_height, Some(850_002));
+        assert_eq!(cycles[0].transactions, Some(304));
+        for step in [
+            cycles[0].fetch_ms,
+            cycles[0].parse_ms,
+            cycles[0].save_ms,
+            cycles[0].load_ms,
+            cycles[0].estimate_ms,
+        ] {
+            assert!(step.unwrap() <= cycles[0].total_ms);
+        }
+        assert_eq!(cycles[0].error, None);
+    }
+
+    #[tokio::test]
+    async fn test_system_clock_is_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(MockBitcoinClient::new()),
+            SnapshotStore::new(temp_dir.path()).unwrap(),
+            FeeEstimator::new(),
+        );
+
+        let before = Utc::now();
+        collector.update_fee_estimates().await.unwrap();
+        let latest = collector.get_latest_snapshot().await.unwrap();
+        assert!(latest.timestamp >= before - chrono::Duration::seconds(1));
+        assert!(collector.now() >= before);
+    }
+}
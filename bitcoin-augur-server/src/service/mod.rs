@@ -1,5 +1,43 @@
 //! Service layer for background tasks
 
+mod adaptive_interval;
+mod alerts;
+mod baselines;
+mod block_feedback;
+mod clock;
+mod cycle_log;
+mod gaps;
+mod leader;
 mod mempool_collector;
+mod price_feed;
+mod replication;
+mod seasonality;
+mod target_cache;
+mod trace;
+mod validity;
+mod warmup;
 
-pub use mempool_collector::{CollectorError, MempoolCollector};
+pub use adaptive_interval::IntervalPolicy;
+#[allow(unused_imports)]
+pub use alerts::{AlertCondition, AlertMetric, AlertNotification, AlertSeverity, AlertState};
+pub use alerts::{AlertRule, AlertStatus, Alerts};
+pub use baselines::{Baseline, Baselines};
+pub use block_feedback::{read_realized_blocks, BlockFeedback, RealizedBlock};
+pub use clock::Clock;
+pub use cycle_log::{CycleLog, CycleRecord};
+pub use gaps::GapPolicy;
+pub use leader::{LeaderLease, LeaderStatus};
+pub use mempool_collector::{estimation_window, CollectorError, MempoolCollector};
+pub use price_feed::PriceFeed;
+#[allow(unused_imports)]
+pub use price_feed::PriceFeedError;
+#[allow(unused_imports)]
+pub use replication::SyncError;
+pub use replication::{SyncChanges, SyncClient};
+pub use seasonality::SeasonalityPolicy;
+pub use target_cache::TargetCachePolicy;
+pub use trace::TraceRecorder;
+pub use validity::ValidityPolicy;
+pub use warmup::WarmupPhase;
+#[allow(unused_imports)]
+pub use warmup::WarmupStatus;
@@ -0,0 +1,181 @@
+//! Bitcoin prices for quoting fee estimates in fiat
+//!
+//! The feed is fetched at most once per cache period, whatever the request rate. It may answer
+//! in the format of mempool.space's `/api/v1/prices` (`{"time": ..., "USD": 65000, ...}`) or of
+//! CoinGecko's simple price (`{"bitcoin": {"usd": 65000}}`); every three letter key with a
+//! numeric value is read as a currency code.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Price feed errors
+#[derive(Error, Debug)]
+pub enum PriceFeedError {
+    #[error("Price feed request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Price feed response has no prices")]
+    NoPrices,
+}
+
+/// Prices fetched at one time, keyed by upper case currency code
+struct Prices {
+    fetched_at: Instant,
+    prices: HashMap<String, f64>,
+}
+
+/// A price feed whose latest answer is cached for a while
+pub struct PriceFeed {
+    url: String,
+    http: reqwest::Client,
+    cache_ttl: Duration,
+    cached: RwLock<Option<Prices>>,
+}
+
+impl PriceFeed {
+    /// Reads prices from `url`, fetching them again once they are `cache_ttl` old
+    pub fn new(url: impl Into<String>, cache_ttl: Duration) -> Self {
+        Self {
+            url: url.into(),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("HTTP client with a timeout builds"),
+            cache_ttl,
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// Price of one bitcoin in `currency`, or `None` when the feed does not quote it
+    ///
+    /// When the feed cannot be reached, the last prices fetched are used however old they are.
+    pub async fn price(&self, currency: &str) -> Result<Option<f64>, PriceFeedError> {
+        let currency = currency.to_uppercase();
+        if let Some(cached) = self.cached.read().await.as_ref() {
+            if cached.fetched_at.elapsed() < self.cache_ttl {
+                return Ok(cached.prices.get(&currency).copied());
+            }
+        }
+
+        let mut cached = self.cached.write().await;
+        // Another request may have refreshed the prices while this one waited
+        if let Some(prices) = cached.as_ref() {
+            if prices.fetched_at.elapsed() < self.cache_ttl {
+                return Ok(prices.prices.get(&currency).copied());
+            }
+        }
+        match self.fetch().await {
+            Ok(prices) => {
+                let price = prices.get(&currency).copied();
+                *cached = Some(Prices {
+                    fetched_at: Instant::now(),
+                    prices,
+                });
+                Ok(price)
+            }
+            Err(e) => match cached.as_ref() {
+                Some(stale) => {
+                    warn!(
+                        "Failed to refresh prices from {url}, using prices {age}s old: {e}",
+                        url = self.url,
+                        age = stale.fetched_at.elapsed().as_secs()
+                    );
+                    Ok(stale.prices.get(&currency).copied())
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn fetch(&self) -> Result<HashMap<String, f64>, PriceFeedError> {
+        let body: Value = self
+            .http
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let prices = parse_prices(&body);
+        if prices.is_empty() {
+            return Err(PriceFeedError::NoPrices);
+        }
+        Ok(prices)
+    }
+}
+
+/// Reads the prices out of a mempool.space or CoinGecko style response
+fn parse_prices(body: &Value) -> HashMap<String, f64> {
+    let quotes = body.get("bitcoin").unwrap_or(body);
+    let Some(quotes) = quotes.as_object() else {
+        return HashMap::new();
+    };
+    quotes
+        .iter()
+        .filter(|(code, _)| code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()))
+        .filter_map(|(code, price)| {
+            let price = price.as_f64().filter(|price| *price > 0.0)?;
+            Some((code.to_uppercase(), price))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_parse_prices() {
+        let mempool_space = json!({"time": 1_750_000_000, "USD": 65000, "EUR": 60000.5});
+        let prices = parse_prices(&mempool_space);
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices["USD"], 65000.0);
+        assert_eq!(prices["EUR"], 60000.5);
+
+        let coingecko = json!({"bitcoin": {"usd": 65000, "jpy": 9_500_000}});
+        let prices = parse_prices(&coingecko);
+        assert_eq!(prices["USD"], 65000.0);
+        assert_eq!(prices["JPY"], 9_500_000.0);
+
+        assert!(parse_prices(&json!([1, 2])).is_empty());
+        assert!(parse_prices(&json!({"USD": "a lot"})).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_price_is_cached_and_served_stale_when_feed_fails() {
+        let server = MockServer::start().await;
+        for feed in ["/cached", "/stale"] {
+            Mock::given(method("GET"))
+                .and(path(feed))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({"USD": 65000})))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+        }
+
+        // Fresh prices are served from the cache: each mock only answers once
+        let feed = PriceFeed::new(
+            format!("{uri}/cached", uri = server.uri()),
+            Duration::from_secs(60),
+        );
+        assert_eq!(feed.price("usd").await.unwrap(), Some(65000.0));
+        assert_eq!(feed.price("USD").await.unwrap(), Some(65000.0));
+        assert_eq!(feed.price("eur").await.unwrap(), None);
+
+        // Expired prices are kept when the feed stops answering
+        let feed = PriceFeed::new(format!("{uri}/stale", uri = server.uri()), Duration::ZERO);
+        assert_eq!(feed.price("usd").await.unwrap(), Some(65000.0));
+        assert_eq!(feed.price("usd").await.unwrap(), Some(65000.0));
+
+        // Without any prices fetched, a failing feed is an error
+        let feed = PriceFeed::new(format!("{uri}/missing", uri = server.uri()), Duration::ZERO);
+        assert!(feed.price("usd").await.is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Copying the snapshots of a primary server into a secondary's data directory
+//!
+//! A secondary pages through the primary's `GET /sync/changes`, asking each time for the
+//! snapshots after the latest one it holds, and stores them as if it had collected them. Every
+//! estimate is computed from stored snapshots alone, so once caught up the secondary serves the
+//! same historical estimates as the primary without sharing its storage.
+
+use bitcoin_augur::MempoolSnapshot;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::persistence::{PersistenceError, SnapshotStore};
+
+/// Snapshots asked for per request
+const SYNC_PAGE_SIZE: usize = 1_000;
+
+/// Replication errors
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("Sync request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Persistence error: {0}")]
+    Persistence(#[from] PersistenceError),
+
+    #[error("Task join error: {0}")]
+    Join(#[from] tokio::task::JoinError),
+}
+
+/// Response of `GET /sync/changes`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncChanges {
+    /// Snapshots after the requested time, oldest first
+    pub snapshots: Vec<MempoolSnapshot>,
+    /// Unix time to ask for the following changes from
+    pub next_since: i64,
+    /// Whether more snapshots follow `next_since` already
+    pub more: bool,
+}
+
+/// Pulls the snapshots of a primary server
+pub struct SyncClient {
+    /// Base URL of the primary, without a trailing slash
+    url: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl SyncClient {
+    /// Pulls from the server at `url`, authenticating with `token` if given
+    pub fn new(url: &str, token: Option<String>) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(60))
+                .build()
+                .expect("HTTP client with a timeout builds"),
+        }
+    }
+
+    /// Stores every snapshot of the primary newer than the latest one in `store`, returning how
+    /// many were stored
+    pub async fn pull(&self, store: Arc<SnapshotStore>) -> Result<usize, SyncError> {
+        let latest = {
+            let store = store.clone();
+            tokio::task::spawn_blocking(move || store.get_latest_snapshot()).await??
+        };
+        let mut since = latest.map(|snapshot| snapshot.timestamp.timestamp());
+        let mut stored = 0;
+        loop {
+            let mut request = self
+                .http
+                .get(format!("{url}/sync/changes", url = self.url))
+                .query(&[("limit", SYNC_PAGE_SIZE)]);
+            if let Some(since) = since {
+                request = request.query(&[("since", since)]);
+            }
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            let changes: SyncChanges = request.send().await?.error_for_status()?.json().await?;
+            debug!(
+                "Pulled {count} snapshots from {url}",
+                count = changes.snapshots.len(),
+                url = self.url
+            );
+
+            stored += changes.snapshots.len();
+            let store = store.clone();
+            let snapshots = changes.snapshots;
+            tokio::task::spawn_blocking(move || {
+                snapshots
+                    .iter()
+                    .try_for_each(|snapshot| store.save_snapshot(snapshot))
+            })
+            .await??;
+
+            since = Some(changes.next_since);
+            if !changes.more {
+                return Ok(stored);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone, Utc};
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn changes(heights: &[u32], more: bool) -> SyncChanges {
+        let start = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let snapshots: Vec<_> = heights
+            .iter()
+            .map(|&height| {
+                MempoolSnapshot::new(
+                    height,
+                    start + chrono::Duration::minutes(i64::from(height)),
+                    BTreeMap::from([(100, 4_000)]),
+                )
+            })
+            .collect();
+        SyncChanges {
+            next_since: snapshots.last().map_or(0, |s| s.timestamp.timestamp()),
+            snapshots,
+            more,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pull_pages_from_latest_snapshot() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let store = Arc::new(SnapshotStore::new(temp_dir.path()).unwrap());
+        let held = changes(&[1], false).snapshots.remove(0);
+        store.save_snapshot(&held).unwrap();
+
+        let first = changes(&[2, 3], true);
+        let second = changes(&[4], false);
+        Mock::given(method("GET"))
+            .and(path("/sync/changes"))
+            .and(query_param("since", held.timestamp.timestamp().to_string()))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&first))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/sync/changes"))
+            .and(query_param("since", first.next_since.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&second))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = SyncClient::new(&format!("{}/", server.uri()), Some("secret".to_string()));
+        assert_eq!(client.pull(store.clone()).await.unwrap(), 3);
+
+        let start = held.timestamp.with_timezone(&Local);
+        let heights: Vec<_> = store
+            .get_snapshots(start, start + chrono::Duration::hours(1))
+            .unwrap()
+            .iter()
+            .map(|snapshot| snapshot.block_height)
+            .collect();
+        assert_eq!(heights, vec![1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Seasonal inflow profile learned from stored history
+//!
+//! Fee inflow follows the week: weekday business hours are busier than weekend nights. The
+//! profile is learned from weeks of stored snapshots, far more than one estimation window, so
+//! it is rebuilt periodically rather than on every collection.
+
+use bitcoin_augur::SeasonalProfile;
+use chrono::{DateTime, Duration, Local, Utc};
+
+use crate::persistence::{PersistenceError, SnapshotStore};
+
+/// Settings for learning the seasonal inflow profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeasonalityPolicy {
+    /// Weeks of stored history the profile is learned from
+    pub weeks: u32,
+    /// Fraction of the seasonal adjustment applied to long-term inflows
+    pub blend: f64,
+}
+
+impl SeasonalityPolicy {
+    /// Learns the profile from the stored snapshots of the weeks before `end`
+    ///
+    /// Snapshots are loaded a day at a time, so only one day is held in memory.
+    pub fn load_profile(
+        &self,
+        store: &SnapshotStore,
+        end: DateTime<Utc>,
+    ) -> Result<SeasonalProfile, PersistenceError> {
+        let mut profile = SeasonalProfile::new();
+        let end = end.with_timezone(&Local);
+        let mut day_start = end - Duration::weeks(i64::from(self.weeks));
+
+        while day_start < end {
+            let day_end = (day_start + Duration::days(1)).min(end);
+            profile.record(&store.get_snapshots(day_start, day_end)?);
+            day_start = day_end;
+        }
+
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::MempoolSnapshot;
+    use chrono::TimeZone;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_profile_spans_the_configured_weeks() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 1, 27, 0, 0, 0).unwrap();
+
+        // Snapshots every 10 minutes over the last three days, the mempool growing by
+        // 100,000 WU each time with a block every hour
+        for i in 0..3 * 24 * 6 {
+            let snapshot = MempoolSnapshot::new(
+                850_000 + i / 6,
+                end - Duration::minutes(10 * i64::from(3 * 24 * 6 - i)),
+                BTreeMap::from([(100, 100_000 * u64::from(i % 6 + 1))]),
+            );
+            store.save_snapshot(&snapshot).unwrap();
+        }
+
+        let policy = SeasonalityPolicy {
+            weeks: 1,
+            blend: 0.5,
+        };
+        let profile = policy.load_profile(&store, end).unwrap();
+        assert_eq!(profile.hours_covered(), 3 * 24);
+        // Friday noon
+        assert_eq!(profile.inflow_rate(4 * 24 + 12), Some(100_000.0));
+        // Monday noon, before the stored history
+        assert_eq!(profile.inflow_rate(12), None);
+    }
+}
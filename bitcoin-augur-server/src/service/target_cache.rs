@@ -0,0 +1,232 @@
+//! Estimates for single block targets, kept between requests
+//!
+//! Every `/fees/target/{n}` request estimates its target from the whole window, which costs
+//! about as much as a collection cycle. The cache keeps the latest estimates per target for as
+//! long as they were computed from the latest snapshot and no longer than a collection interval,
+//! evicting the least recently used target once full. A cap on the distinct targets computed per
+//! minute keeps clients walking through targets from turning the cache into a CPU sink.
+
+use bitcoin_augur::FeeEstimate;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+/// Stretch the cap on distinct targets applies to
+const RATE_WINDOW_SECS: i64 = 60;
+
+/// Size, lifetime and request cap of a target cache
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetCachePolicy {
+    /// Targets kept at most
+    pub capacity: usize,
+    /// Age past which a cached estimate is recomputed
+    pub ttl: chrono::Duration,
+    /// Distinct targets computed per minute at most, when capped
+    pub max_targets_per_minute: Option<usize>,
+}
+
+/// Estimate cached for a block target
+#[derive(Debug, Clone)]
+struct CachedTarget {
+    estimate: FeeEstimate,
+    /// Time of the latest snapshot when the estimate was computed
+    snapshot: Option<DateTime<Utc>>,
+    computed_at: DateTime<Utc>,
+    /// Value of the use counter when the target was last read or written
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<u64, CachedTarget>,
+    uses: u64,
+    /// Targets computed in the last minute, with when each was first computed in it
+    recent: HashMap<u64, DateTime<Utc>>,
+}
+
+/// LRU cache of estimates for single block targets, keyed by the target's bits
+#[derive(Debug)]
+pub struct TargetCache {
+    policy: TargetCachePolicy,
+    state: Mutex<State>,
+}
+
+impl TargetCache {
+    pub fn new(policy: TargetCachePolicy) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn policy(&self) -> &TargetCachePolicy {
+        &self.policy
+    }
+
+    /// Estimate cached for `num_blocks` from the `snapshot` that is the latest at `now`, unless
+    /// it is older than the TTL
+    pub fn get(
+        &self,
+        num_blocks: f64,
+        snapshot: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<FeeEstimate> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.uses += 1;
+        let uses = state.uses;
+        let entry = state.entries.get_mut(&num_blocks.to_bits())?;
+        if entry.snapshot != snapshot || now - entry.computed_at >= self.policy.ttl {
+            return None;
+        }
+        entry.last_used = uses;
+        Some(entry.estimate.clone())
+    }
+
+    /// Records that `num_blocks` is about to be computed at `now`, or returns `false` if that
+    /// would exceed the distinct targets allowed per minute
+    ///
+    /// Targets already computed in the last minute are always admitted again.
+    pub fn admit(&self, num_blocks: f64, now: DateTime<Utc>) -> bool {
+        let Some(max) = self.policy.max_targets_per_minute else {
+            return true;
+        };
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let since = now - chrono::Duration::seconds(RATE_WINDOW_SECS);
+        state.recent.retain(|_, first| *first > since);
+        let key = num_blocks.to_bits();
+        if state.recent.contains_key(&key) {
+            return true;
+        }
+        if state.recent.len() >= max {
+            return false;
+        }
+        state.recent.insert(key, now);
+        true
+    }
+
+    /// Caches `estimate` for `num_blocks`, computed at `now` from the latest `snapshot`,
+    /// evicting the least recently used target if the cache is full
+    pub fn insert(
+        &self,
+        num_blocks: f64,
+        snapshot: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+        estimate: FeeEstimate,
+    ) {
+        if self.policy.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.uses += 1;
+        let key = num_blocks.to_bits();
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.policy.capacity {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        let last_used = state.uses;
+        state.entries.insert(
+            key,
+            CachedTarget {
+                estimate,
+                snapshot,
+                computed_at: now,
+                last_used,
+            },
+        );
+    }
+
+    /// Forgets every cached estimate, as when older snapshots are stored
+    pub fn clear(&self) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entries
+            .clear();
+    }
+
+    /// Number of targets cached
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entries
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn policy(capacity: usize, max_targets_per_minute: Option<usize>) -> TargetCachePolicy {
+        TargetCachePolicy {
+            capacity,
+            ttl: chrono::Duration::seconds(30),
+            max_targets_per_minute,
+        }
+    }
+
+    #[test]
+    fn test_entries_expire_with_new_snapshots_and_ttl() {
+        let cache = TargetCache::new(policy(4, None));
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let snapshot = Some(now);
+        cache.insert(7.0, snapshot, now, FeeEstimate::empty(now));
+
+        assert!(cache.get(7.0, snapshot, now).is_some());
+        assert!(cache.get(8.0, snapshot, now).is_none());
+        // A newer snapshot or an estimate as old as the TTL is recomputed
+        let later = now + chrono::Duration::seconds(10);
+        assert!(cache.get(7.0, Some(later), later).is_none());
+        assert!(cache
+            .get(7.0, snapshot, now + chrono::Duration::seconds(29))
+            .is_some());
+        assert!(cache
+            .get(7.0, snapshot, now + chrono::Duration::seconds(30))
+            .is_none());
+    }
+
+    #[test]
+    fn test_least_recently_used_target_is_evicted() {
+        let cache = TargetCache::new(policy(2, None));
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        cache.insert(7.0, None, now, FeeEstimate::empty(now));
+        cache.insert(8.0, None, now, FeeEstimate::empty(now));
+        // Reading 7 makes 8 the least recently used
+        assert!(cache.get(7.0, None, now).is_some());
+        cache.insert(9.0, None, now, FeeEstimate::empty(now));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(7.0, None, now).is_some());
+        assert!(cache.get(8.0, None, now).is_none());
+        assert!(cache.get(9.0, None, now).is_some());
+
+        // Nothing is kept without capacity
+        let cache = TargetCache::new(policy(0, None));
+        cache.insert(7.0, None, now, FeeEstimate::empty(now));
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_distinct_targets_per_minute_are_capped() {
+        let cache = TargetCache::new(policy(4, Some(2)));
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        assert!(cache.admit(7.0, now));
+        assert!(cache.admit(8.0, now));
+        assert!(!cache.admit(9.0, now));
+        // Targets already computed this minute are not counted twice
+        assert!(cache.admit(7.0, now + chrono::Duration::seconds(30)));
+
+        // A minute later the first targets no longer count
+        assert!(cache.admit(9.0, now + chrono::Duration::seconds(60)));
+        assert!(TargetCache::new(policy(4, None)).admit(9.0, now));
+    }
+}
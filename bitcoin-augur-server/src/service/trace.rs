@@ -0,0 +1,111 @@
+//! Anonymized mempool traces for sharing collected history
+//!
+//! A trace is a text file with one snapshot per line in the `MempoolSnapshot` JSON form: the
+//! block height, the time to the second and the weight in each fee rate bucket. It holds no
+//! transaction ids, sizes or fees, so a trace recorded from a real node can be published as a
+//! regression or benchmark scenario. The regression tests replay traces with
+//! `differential --generator trace`.
+
+use bitcoin_augur::MempoolSnapshot;
+use chrono::SubsecRound;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Appends collected snapshots to a trace file
+pub struct TraceRecorder {
+    path: PathBuf,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl TraceRecorder {
+    /// Opens `path` for appending, creating it and its parent directory if needed
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// File the trace is written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `snapshot` as one line, flushed so a crash loses at most the line being written
+    pub fn record(&self, snapshot: &MempoolSnapshot) -> io::Result<()> {
+        let line = trace_line(snapshot)?;
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| io::Error::other("Trace writer poisoned"))?;
+        writer.write_all(line.as_bytes())?;
+        writer.flush()
+    }
+}
+
+/// `snapshot` as a trace line, with its timestamp truncated to the second and its source label
+/// dropped
+fn trace_line(snapshot: &MempoolSnapshot) -> io::Result<String> {
+    let anonymized = MempoolSnapshot {
+        timestamp: snapshot.timestamp.trunc_subsecs(0),
+        source: None,
+        ..snapshot.clone()
+    };
+    let mut line = serde_json::to_string(&anonymized)?;
+    line.push('\n');
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_augur::MempoolTransaction;
+    use chrono::{TimeZone, Utc};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_trace_holds_only_buckets() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("traces").join("node.jsonl");
+        let timestamp = Utc.timestamp_opt(1_737_374_400, 123_456_789).unwrap();
+        let snapshot = MempoolSnapshot::from_transactions(
+            vec![
+                MempoolTransaction::new(400, 1_000),
+                MempoolTransaction::new(800, 2_000),
+            ],
+            850_000,
+            timestamp,
+        )
+        .with_source("node-a");
+
+        let recorder = TraceRecorder::open(&path).unwrap();
+        recorder.record(&snapshot).unwrap();
+        drop(recorder);
+        // Reopening appends rather than truncating
+        TraceRecorder::open(&path)
+            .unwrap()
+            .record(&snapshot)
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let value: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        let mut fields: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+        fields.sort();
+        assert_eq!(fields, ["block_height", "bucketed_weights", "timestamp"]);
+
+        let recorded: MempoolSnapshot = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(recorded.timestamp, timestamp.trunc_subsecs(0));
+        assert_eq!(recorded.block_height, snapshot.block_height);
+        assert_eq!(recorded.bucketed_weights, snapshot.bucketed_weights);
+    }
+}
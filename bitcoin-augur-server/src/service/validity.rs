@@ -0,0 +1,177 @@
+//! How long a published fee estimate can be cached
+//!
+//! An estimate never changes before the next collection, and the longer a block target, the
+//! less a few minutes of new mempool activity move it. Each target's estimate is therefore
+//! valid for a fraction of the time its blocks are expected to take, measured with the block
+//! interval observed in the estimation window, but never less than one collection interval.
+
+use bitcoin_augur::{BlockTargetKey, FeeEstimate, MempoolSnapshot};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+/// Block interval assumed until enough blocks have been observed
+pub const DEFAULT_BLOCK_INTERVAL: Duration = Duration::minutes(10);
+
+/// Blocks that must be observed before the measured interval replaces the default
+const MIN_OBSERVED_BLOCKS: u32 = 6;
+
+/// Settings deriving each block target's cache lifetime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValidityPolicy {
+    /// Time between collections, the shortest lifetime of any estimate
+    pub collection_interval: Duration,
+    /// Fraction of a target's expected confirmation time its estimate stays valid
+    pub target_fraction: f64,
+    /// Longest lifetime of any estimate
+    pub max_ttl: Duration,
+}
+
+impl ValidityPolicy {
+    /// Lifetime of the estimate for `target_blocks` when blocks arrive every `block_interval`
+    pub fn ttl(
+        &self,
+        target_blocks: impl Into<BlockTargetKey>,
+        block_interval: Duration,
+    ) -> Duration {
+        let expected_ms = block_interval.num_milliseconds() as f64 * target_blocks.into().target();
+        let ttl = Duration::milliseconds((expected_ms * self.target_fraction) as i64);
+        ttl.min(self.max_ttl).max(self.collection_interval)
+    }
+
+    /// Expiry time of every block target in `estimate`
+    pub fn valid_until(
+        &self,
+        estimate: &FeeEstimate,
+        block_interval: Duration,
+    ) -> BTreeMap<BlockTargetKey, DateTime<Utc>> {
+        estimate
+            .targets()
+            .map(|(target, _)| {
+                (
+                    target,
+                    estimate.timestamp + self.ttl(target, block_interval),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Mean time between block discoveries in `snapshots`
+///
+/// A block is discovered at the first snapshot showing its height, ignoring blocks that were
+/// reorganized away. Returns `None` until enough blocks were discovered for the mean to be
+/// meaningful.
+pub fn observed_block_interval(snapshots: &[MempoolSnapshot]) -> Option<Duration> {
+    let mut ordered: Vec<(DateTime<Utc>, u32)> = snapshots
+        .iter()
+        .map(|s| (s.timestamp, s.block_height))
+        .collect();
+    ordered.sort_unstable();
+
+    // Drop heights a later snapshot reorganized away, as MempoolSnapshot::remove_orphaned does
+    let mut lowest_later = u32::MAX;
+    let mut chain: Vec<(DateTime<Utc>, u32)> = ordered
+        .into_iter()
+        .rev()
+        .filter(|&(_, height)| {
+            let kept = height <= lowest_later;
+            lowest_later = lowest_later.min(height);
+            kept
+        })
+        .collect();
+    chain.reverse();
+
+    let mut discoveries = chain
+        .windows(2)
+        .filter(|pair| pair[1].1 > pair[0].1)
+        .map(|pair| pair[1]);
+    let (first_time, first_height) = discoveries.next()?;
+    let (last_time, last_height) = discoveries.next_back()?;
+
+    let blocks = last_height - first_height;
+    if blocks < MIN_OBSERVED_BLOCKS || last_time <= first_time {
+        return None;
+    }
+    Some((last_time - first_time) / blocks as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn policy() -> ValidityPolicy {
+        ValidityPolicy {
+            collection_interval: Duration::seconds(30),
+            target_fraction: 0.1,
+            max_ttl: Duration::hours(1),
+        }
+    }
+
+    fn snapshot(height: u32, minutes: i64) -> MempoolSnapshot {
+        let start = Utc.with_ymd_and_hms(2025, 1, 20, 0, 0, 0).unwrap();
+        MempoolSnapshot::new(height, start + Duration::minutes(minutes), BTreeMap::new())
+    }
+
+    #[test]
+    fn test_ttl_bounds() {
+        let policy = policy();
+        let interval = DEFAULT_BLOCK_INTERVAL;
+        // 10% of 6 blocks of 10 minutes
+        assert_eq!(policy.ttl(6, interval), Duration::minutes(6));
+        // Capped for long targets, never below the collection interval for short ones
+        assert_eq!(policy.ttl(144, interval), Duration::hours(1));
+        assert_eq!(policy.ttl(3, Duration::seconds(20)), Duration::seconds(30));
+        // Longer targets never expire earlier
+        for target in [3, 6, 12, 24, 144] {
+            assert!(policy.ttl(target, interval) <= policy.ttl(target * 2, interval));
+        }
+    }
+
+    #[test]
+    fn test_valid_until_per_target() {
+        let timestamp = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let estimate = FeeEstimate::new(
+            BTreeMap::from([
+                (3, bitcoin_augur::BlockTarget::new(3, BTreeMap::new())),
+                (12, bitcoin_augur::BlockTarget::new(12, BTreeMap::new())),
+            ]),
+            timestamp,
+        );
+
+        let valid_until = policy().valid_until(&estimate, DEFAULT_BLOCK_INTERVAL);
+        assert_eq!(
+            valid_until[&BlockTargetKey::from(3)],
+            timestamp + Duration::minutes(3)
+        );
+        assert_eq!(
+            valid_until[&BlockTargetKey::from(12)],
+            timestamp + Duration::minutes(12)
+        );
+    }
+
+    #[test]
+    fn test_observed_block_interval() {
+        // A block every 5 minutes, sampled every minute
+        let snapshots: Vec<_> = (0..60).map(|m| snapshot(100 + m as u32 / 5, m)).collect();
+        assert_eq!(
+            observed_block_interval(&snapshots),
+            Some(Duration::minutes(5))
+        );
+
+        // Too few blocks to trust
+        assert_eq!(observed_block_interval(&snapshots[..20]), None);
+        assert_eq!(observed_block_interval(&[]), None);
+    }
+
+    #[test]
+    fn test_observed_block_interval_after_reorg() {
+        // A block at 111 seen once, then reorganized away
+        let mut snapshots: Vec<_> = (0..60).map(|m| snapshot(100 + m as u32 / 5, m)).collect();
+        snapshots.insert(50, snapshot(111, 49));
+        assert_eq!(
+            observed_block_interval(&snapshots),
+            Some(Duration::minutes(5))
+        );
+    }
+}
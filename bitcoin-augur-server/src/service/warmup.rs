@@ -0,0 +1,83 @@
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use tracing::info;
+
+use crate::persistence::LoadProgress;
+
+/// Stage of the startup warm-up from stored snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmupPhase {
+    /// No estimate to serve yet, and none being computed from stored snapshots
+    Waiting,
+    /// Reading the estimation window from the snapshot store
+    Loading,
+    /// Computing the first estimate from the loaded snapshots
+    Estimating,
+    /// An estimate is being served
+    Ready,
+}
+
+/// Snapshot of the warm-up progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmupStatus {
+    pub phase: WarmupPhase,
+    /// Stored snapshots read so far
+    pub loaded: usize,
+    /// Stored snapshots in the estimation window
+    pub total: usize,
+}
+
+const IDLE: u8 = 0;
+const LOADING: u8 = 1;
+const ESTIMATING: u8 = 2;
+
+/// Warm-up progress shared between the collector and the readiness endpoint
+#[derive(Debug, Default)]
+pub(super) struct Warmup {
+    stage: AtomicU8,
+    loaded: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl Warmup {
+    pub fn start_loading(&self) {
+        self.loaded.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+        self.stage.store(LOADING, Ordering::Relaxed);
+    }
+
+    /// Records `progress`, logging every tenth of the window read
+    pub fn loaded(&self, progress: LoadProgress) {
+        let LoadProgress { loaded, total } = progress;
+        self.total.store(total, Ordering::Relaxed);
+        let previous = self.loaded.swap(loaded, Ordering::Relaxed);
+        if loaded == total || previous * 10 / total != loaded * 10 / total {
+            info!(
+                "Loaded {loaded} of {total} stored snapshots ({percent}%)",
+                percent = loaded * 100 / total
+            );
+        }
+    }
+
+    pub fn start_estimating(&self) {
+        self.stage.store(ESTIMATING, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self) {
+        self.stage.store(IDLE, Ordering::Relaxed);
+    }
+
+    /// Current progress, `Ready` outside of a warm-up if `has_estimate`
+    pub fn status(&self, has_estimate: bool) -> WarmupStatus {
+        let phase = match self.stage.load(Ordering::Relaxed) {
+            LOADING => WarmupPhase::Loading,
+            ESTIMATING => WarmupPhase::Estimating,
+            _ if has_estimate => WarmupPhase::Ready,
+            _ => WarmupPhase::Waiting,
+        };
+        WarmupStatus {
+            phase,
+            loaded: self.loaded.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Notifications to systemd for services of `Type=notify`
+//!
+//! systemd passes the socket to notify in `NOTIFY_SOCKET`, either a path or the name of an
+//! abstract socket prefixed with `@`. Outside systemd the variable is unset and notifications are
+//! skipped.
+
+use std::io;
+use std::os::unix::net::UnixDatagram;
+
+/// Variable systemd passes the notification socket in
+const NOTIFY_SOCKET: &str = "NOTIFY_SOCKET";
+
+/// Sends `state`, such as `READY=1`, to systemd, returning whether there was a socket to send
+/// it to
+pub fn notify(state: &str) -> io::Result<bool> {
+    match std::env::var(NOTIFY_SOCKET) {
+        Ok(socket) if !socket.is_empty() => send(&socket, state).map(|()| true),
+        _ => Ok(false),
+    }
+}
+
+/// Sends `state` to the notification socket `socket`
+fn send(socket: &str, state: &str) -> io::Result<()> {
+    let datagram = UnixDatagram::unbound()?;
+    match socket.strip_prefix('@') {
+        Some(name) => send_abstract(&datagram, name, state),
+        None => datagram.send_to(state.as_bytes(), socket).map(|_| ()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn send_abstract(datagram: &UnixDatagram, name: &str, state: &str) -> io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+
+    let address = SocketAddr::from_abstract_name(name)?;
+    datagram.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_abstract(_datagram: &UnixDatagram, name: &str, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("abstract socket @{name} is only supported on Linux"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_send_to_socket_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notify.sock");
+        let systemd = UnixDatagram::bind(&path).unwrap();
+
+        send(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buffer = [0; 64];
+        let received = systemd.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..received], b"READY=1");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_send_to_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+
+        let name = format!("augur-notify-test-{pid}", pid = std::process::id());
+        let address = SocketAddr::from_abstract_name(&name).unwrap();
+        let systemd = UnixDatagram::bind_addr(&address).unwrap();
+
+        send(&format!("@{name}"), "STOPPING=1").unwrap();
+        let mut buffer = [0; 64];
+        let received = systemd.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..received], b"STOPPING=1");
+    }
+}
@@ -1,9 +1,20 @@
 use axum::http::StatusCode;
-use bitcoin_augur::{FeeEstimator, MempoolSnapshot};
-use bitcoin_augur_server::bitcoin::{BitcoinClient, BitcoinRpcClient, BitcoinRpcConfig};
+use bitcoin_augur::{FeeEstimator, MempoolSnapshot, Package, RoundingPolicy};
+use bitcoin_augur_server::api::{
+    ErrorCode, Problem, ResponseSigner, KEY_ID_HEADER, PROBLEM_CONTENT_TYPE, SIGNATURE_HEADER,
+};
+use bitcoin_augur_server::bitcoin::{
+    BitcoinClient, BitcoinRpcClient, BitcoinRpcConfig, MockBitcoinClient, TxMempoolEntry,
+};
+use bitcoin_augur_server::config::{ApiConfig, PresetConfig, ResponseConfig};
 use bitcoin_augur_server::persistence::SnapshotStore;
-use bitcoin_augur_server::server::create_app;
-use bitcoin_augur_server::service::MempoolCollector;
+use bitcoin_augur_server::server::{
+    create_app, create_app_with_config, create_app_with_signer, run_server, Readiness,
+};
+use bitcoin_augur_server::service::{
+    Baseline, Baselines, Clock, CycleLog, CycleRecord, GapPolicy, MempoolCollector, SyncChanges,
+    TargetCachePolicy, ValidityPolicy,
+};
 use chrono::Utc;
 use std::collections::BTreeMap;
 use std::sync::Arc;
@@ -13,7 +24,21 @@ use tower::ServiceExt;
 /// Create test app with mock collector
 /// Returns both the app and the TempDir to keep it alive
 async fn create_test_app() -> anyhow::Result<(axum::Router, TempDir)> {
-    let temp_dir = TempDir::new()?;
+    let (collector, temp_dir) = create_test_collector().await?;
+    Ok((create_app(collector), temp_dir))
+}
+
+/// Create a collector initialized from stored test snapshots
+async fn create_test_collector() -> anyhow::Result<(Arc<MempoolCollector>, TempDir)> {
+    create_test_collector_with(FeeEstimator::new(), None).await
+}
+
+/// Create a collector with the given estimator and validity policy, initialized from stored
+/// test snapshots
+async fn create_test_collector_with(
+    fee_estimator: FeeEstimator,
+    validity: Option<ValidityPolicy>,
+) -> anyhow::Result<(Arc<MempoolCollector>, TempDir)> {
     let config = BitcoinRpcConfig {
         url: "http://localhost:8332".to_string(),
         username: "test".to_string(),
@@ -21,8 +46,17 @@ async fn create_test_app() -> anyhow::Result<(axum::Router, TempDir)> {
     };
 
     let bitcoin_client = BitcoinClient::Real(BitcoinRpcClient::new(config));
+    create_test_collector_for(bitcoin_client, fee_estimator, validity).await
+}
+
+/// Create a collector backed by `bitcoin_client`, initialized from stored test snapshots
+async fn create_test_collector_for(
+    bitcoin_client: BitcoinClient,
+    fee_estimator: FeeEstimator,
+    validity: Option<ValidityPolicy>,
+) -> anyhow::Result<(Arc<MempoolCollector>, TempDir)> {
+    let temp_dir = TempDir::new()?;
     let snapshot_store = SnapshotStore::new(temp_dir.path())?;
-    let fee_estimator = FeeEstimator::new();
 
     // Save test snapshots to persistence
     let snapshots = create_test_snapshots();
@@ -30,16 +64,16 @@ async fn create_test_app() -> anyhow::Result<(axum::Router, TempDir)> {
         snapshot_store.save_snapshot(snapshot)?;
     }
 
-    let collector = Arc::new(MempoolCollector::new(
-        bitcoin_client,
-        snapshot_store,
-        fee_estimator,
-    ));
+    let mut collector = MempoolCollector::new(bitcoin_client, snapshot_store, fee_estimator);
+    if let Some(policy) = validity {
+        collector = collector.with_validity(policy);
+    }
+    let collector = Arc::new(collector);
 
     // Initialize the collector with estimates from the saved snapshots
     collector.initialize_from_store().await?;
 
-    Ok((create_app(collector), temp_dir))
+    Ok((collector, temp_dir))
 }
 
 /// Create test snapshots with realistic data
@@ -91,6 +125,87 @@ async fn test_health_endpoint() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_readiness_endpoint() -> anyhow::Result<()> {
+    let ready = |app: axum::Router| async move {
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/health/ready")
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1024).await?;
+        anyhow::Ok((status, serde_json::from_slice::<serde_json::Value>(&body)?))
+    };
+
+    // Nothing to serve yet
+    let (app, _temp_dir) = create_empty_app(ApiConfig::default())?;
+    let (status, body) = ready(app).await?;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["phase"], "waiting");
+
+    // Warmed up from the five stored snapshots
+    let (app, _temp_dir) = create_test_app().await?;
+    let (status, body) = ready(app).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ready"], true);
+    assert_eq!(body["phase"], "ready");
+    assert_eq!(body["snapshots_loaded"], 5);
+    assert_eq!(body["snapshots_total"], 5);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ready_file_waits_for_warm_up() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config = BitcoinRpcConfig {
+        url: "http://localhost:8332".to_string(),
+        username: "test".to_string(),
+        password: "test".to_string(),
+    };
+    let collector = Arc::new(MempoolCollector::new(
+        BitcoinClient::Real(BitcoinRpcClient::new(config)),
+        SnapshotStore::new(temp_dir.path())?,
+        FeeEstimator::new(),
+    ));
+
+    let ready_file = temp_dir.path().join("ready");
+    let readiness = Readiness {
+        ready_file: Some(ready_file.clone()),
+        warm: Some(collector.clone()),
+    };
+    let app = create_app(collector.clone());
+    let server =
+        tokio::spawn(async move { run_server(app, &["127.0.0.1:0".to_string()], readiness).await });
+
+    // Listening, but with no estimate to serve yet
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert!(!ready_file.exists());
+
+    collector
+        .inject_snapshots(create_test_snapshots(), false)
+        .await?;
+    let mut bound = None;
+    for _ in 0..100 {
+        if let Ok(contents) = std::fs::read_to_string(&ready_file) {
+            bound = Some(contents);
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let bound = bound.expect("the ready file is written once the server is warm");
+    let address: std::net::SocketAddr = bound.trim().parse()?;
+    let response = reqwest::get(format!("http://{address}/health/ready")).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    server.abort();
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_fees_endpoint() -> anyhow::Result<()> {
     let (app, _temp_dir) = create_test_app().await?;
@@ -167,96 +282,2032 @@ async fn test_fees_target_endpoint() -> anyhow::Result<()> {
 }
 
 #[tokio::test]
-async fn test_invalid_target() -> anyhow::Result<()> {
+async fn test_fee_stream_endpoint() -> anyhow::Result<()> {
+    use futures_util::StreamExt;
+
     let (app, _temp_dir) = create_test_app().await?;
 
     let response = app
         .oneshot(
             axum::http::Request::builder()
-                .uri("/fees/target/1001") // Over 1000 limit
+                .uri("/fees/stream")
                 .body(axum::body::Body::empty())?,
         )
         .await?;
 
-    // Should return 400 for invalid target
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[axum::http::header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+
+    // The current estimate is sent right away, in the same form as /fees
+    let mut body = response.into_body().into_data_stream();
+    let frame = body.next().await.unwrap()?;
+    let frame = String::from_utf8(frame.to_vec())?;
+    let mut lines = frame.lines();
+    assert_eq!(lines.next(), Some("event: fees"));
+    let data = frame
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let fee_response: serde_json::Value = serde_json::from_str(data)?;
+    assert!(fee_response["estimates"]["6"]["probabilities"]["0.50"]["fee_rate"].is_number());
+    assert!(frame.contains(&format!(
+        "id: {time}",
+        time = fee_response["mempool_update_time"].as_str().unwrap()
+    )));
 
     Ok(())
 }
 
 #[tokio::test]
-async fn test_historical_endpoint() -> anyhow::Result<()> {
+async fn test_invalid_target() -> anyhow::Result<()> {
     let (app, _temp_dir) = create_test_app().await?;
 
-    // Test with a timestamp that falls within our test data range (20 minutes ago)
-    let timestamp = (Utc::now() - chrono::Duration::minutes(20)).timestamp();
     let response = app
         .oneshot(
             axum::http::Request::builder()
-                .uri(format!("/historical_fee?timestamp={}", timestamp))
+                .uri("/fees/target/1001") // Over 1000 limit
                 .body(axum::body::Body::empty())?,
         )
         .await?;
 
+    // Should return 400 for invalid target
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_target_validation() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_test_app().await?;
+    let get = |uri: String| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    for target in [
+        "abc", "-1", "0", "-0.5", "NaN", "inf", "1000.5", "1e9", "1", "2",
+    ] {
+        let uri = format!("/fees/target/{target}");
+        let response = app.clone().oneshot(get(uri.clone())?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+        assert_eq!(
+            read_problem(response).await?.code,
+            ErrorCode::InvalidTarget,
+            "{uri}"
+        );
+    }
+
+    // Decimal targets are served like the library estimates them
+    let mut served = Vec::new();
+    for target in ["6", "6.0"] {
+        let response = app
+            .clone()
+            .oneshot(get(format!("/fees/target/{target}"))?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK, "{target}");
+        let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+        let mut fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+        served.push(fee_response["estimates"].take());
+    }
+    assert!(served[0]["6"].is_object());
+    assert_eq!(served[0], served[1]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_confidence_curve_endpoint() -> anyhow::Result<()> {
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let estimate = collector.get_latest_estimate().await.unwrap();
+    let app = create_app(collector);
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    let response = app.clone().oneshot(get("/fees/curve?target=6")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let curve: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(curve["target_blocks"], 6.0);
+    let points = curve["curve"].as_array().unwrap();
+    assert_eq!(points.len(), 20);
+
+    // Passes through the served estimate, and never gets cheaper with confidence
+    let rate_at = |confidence: f64| {
+        points
+            .iter()
+            .find(|point| point["confidence"] == confidence)
+            .and_then(|point| point["fee_rate"].as_f64())
+    };
+    for confidence in [0.05, 0.5, 0.95] {
+        let served = estimate.get_fee_rate(6, confidence).unwrap();
+        assert!((rate_at(confidence).unwrap() - served).abs() < 1e-3);
+    }
+    let rates: Vec<f64> = points
+        .iter()
+        .map(|point| point["fee_rate"].as_f64().unwrap())
+        .collect();
+    assert!(rates.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(curve["weighting"], "weight");
+
+    // Collected snapshots carry no output values, so weighting by value changes nothing
+    let response = app
+        .clone()
+        .oneshot(get("/fees/curve?target=6&weighting=value")?)
+        .await?;
     assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let by_value: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(by_value["weighting"], "value");
+    assert_eq!(by_value["curve"], curve["curve"]);
 
+    // Fractional targets are reported as requested
+    let response = app.clone().oneshot(get("/fees/curve?target=6.5")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
     let body = axum::body::to_bytes(response.into_body(), 10240).await?;
-    let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+    let fractional: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(fractional["target_blocks"], 6.5);
 
-    // Verify response structure
-    assert!(fee_response["mempool_update_time"].is_string());
-    assert!(fee_response["estimates"].is_object());
+    for uri in [
+        "/fees/curve",
+        "/fees/curve?target=6&weighting=fee",
+        "/fees/curve?target=0",
+        "/fees/curve?target=2",
+        "/fees/curve?target=1001",
+        "/fees/curve?target=abc",
+    ] {
+        let response = app.clone().oneshot(get(uri)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
 
     Ok(())
 }
 
 #[tokio::test]
-async fn test_historical_missing_timestamp() -> anyhow::Result<()> {
-    let (app, _temp_dir) = create_test_app().await?;
+async fn test_target_curve_endpoint() -> anyhow::Result<()> {
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let estimate = collector.get_latest_estimate().await.unwrap();
+    let app = create_app(collector);
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    let response = app
+        .clone()
+        .oneshot(get("/fees/targets?confidence=0.95")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 65536).await?;
+    let curve: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(curve["confidence"], 0.95);
+    let points = curve["curve"].as_array().unwrap();
+    let blocks: Vec<f64> = points
+        .iter()
+        .map(|point| point["blocks"].as_f64().unwrap())
+        .collect();
+    assert_eq!(blocks, (3..=144).map(f64::from).collect::<Vec<f64>>());
+
+    // Passes through the served estimate, and never gets more expensive with the target
+    for point in points {
+        let blocks = point["blocks"].as_f64().unwrap() as u32;
+        if let Some(served) = estimate.get_fee_rate(blocks, 0.95) {
+            assert!((point["fee_rate"].as_f64().unwrap() - served).abs() < 1e-3);
+        }
+    }
+    let rates: Vec<f64> = points
+        .iter()
+        .map(|point| point["fee_rate"].as_f64().unwrap())
+        .collect();
+    assert!(rates.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    for uri in [
+        "/fees/targets",
+        "/fees/targets?confidence=1.5",
+        "/fees/targets?confidence=abc",
+    ] {
+        let response = app.clone().oneshot(get(uri)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_preset_endpoint() -> anyhow::Result<()> {
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let estimate = collector.get_latest_estimate().await.unwrap();
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+    let preset = |response: axum::response::Response| async move {
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+        anyhow::Ok(serde_json::from_slice::<serde_json::Value>(&body)?)
+    };
+
+    let app = create_app(collector.clone());
+    let body = preset(app.clone().oneshot(get("/fees/preset/economy")?).await?).await?;
+    assert_eq!(body["preset"], "economy");
+    assert_eq!(body["target_blocks"], 144);
+    assert_eq!(body["confidence"], 0.5);
+    let served = estimate.get_fee_rate(144, 0.5).unwrap();
+    assert!((body["fee_rate"].as_f64().unwrap() - served).abs() < 1e-3);
+
+    let response = app.clone().oneshot(get("/fees/preset/warp")?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Configured presets replace the defaults, at any confidence level
+    let api = ApiConfig {
+        presets: BTreeMap::from([(
+            "checkout".to_string(),
+            PresetConfig {
+                target: 6.0,
+                confidence: 0.9,
+            },
+        )]),
+        ..Default::default()
+    };
+    let app = create_app_with_config(collector, api);
+    let body = preset(app.clone().oneshot(get("/fees/preset/checkout")?).await?).await?;
+    assert_eq!(body["target_blocks"], 6);
+    let fee_rate = body["fee_rate"].as_f64().unwrap();
+    assert!(fee_rate >= estimate.get_fee_rate(6, 0.8).unwrap() - 1e-3);
+    assert!(fee_rate <= estimate.get_fee_rate(6, 0.95).unwrap() + 1e-3);
+    let response = app.oneshot(get("/fees/preset/economy")?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mempool_depth_endpoint() -> anyhow::Result<()> {
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let snapshot = collector.get_latest_snapshot().await.unwrap();
+    let app = create_app(collector);
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    let response = app
+        .clone()
+        .oneshot(get("/mempool/depth?vsize=1000")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let depth: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(depth["vsize"], 1000);
+    assert_eq!(depth["block_height"], snapshot.block_height);
+    let fee_rate = depth["fee_rate"].as_f64().unwrap();
+    assert!((fee_rate - snapshot.fee_rate_at_depth(1000).unwrap()).abs() < 1e-3);
+
+    // Deeper than the whole mempool
+    let mempool_vsize = depth["mempool_vsize"].as_u64().unwrap();
+    let response = app
+        .clone()
+        .oneshot(get(&format!("/mempool/depth?vsize={mempool_vsize}"))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let depth: serde_json::Value = serde_json::from_slice(&body)?;
+    assert!(depth["fee_rate"].is_null());
+
+    for uri in ["/mempool/depth", "/mempool/depth?vsize=-1"] {
+        let response = app.clone().oneshot(get(uri)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mempool_histogram_endpoint() -> anyhow::Result<()> {
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let snapshot = collector.get_latest_snapshot().await.unwrap();
+    let app = create_app(collector);
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+    let histogram = |body: &[u8]| -> anyhow::Result<Vec<(f64, u64)>> {
+        let value: serde_json::Value = serde_json::from_slice(body)?;
+        Ok(serde_json::from_value(value["fee_histogram"].clone())?)
+    };
+
+    // Every bucket, highest fee rate first
+    let response = app.clone().oneshot(get("/mempool/histogram")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let value: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(value["block_height"], snapshot.block_height);
+    let buckets = histogram(&body)?;
+    assert_eq!(buckets.len(), snapshot.bucket_count());
+    assert!(buckets.windows(2).all(|pair| pair[0].0 > pair[1].0));
+    let mempool_vsize = value["mempool_vsize"].as_u64().unwrap();
+    let total: u64 = buckets.iter().map(|&(_, vsize)| vsize).sum();
+    assert!(total >= mempool_vsize && total <= mempool_vsize + buckets.len() as u64);
+
+    // Rebucketed to boundaries low enough to hold the whole mempool
+    let response = app
+        .clone()
+        .oneshot(get("/mempool/histogram?boundaries=0.1,10,5,2")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let ranges = histogram(&body)?;
+    let rates: Vec<f64> = ranges.iter().map(|&(rate, _)| rate).collect();
+    assert_eq!(rates, [10.0, 5.0, 2.0, 0.1]);
+    let total: u64 = ranges.iter().map(|&(_, vsize)| vsize).sum();
+    assert!(total >= mempool_vsize && total <= mempool_vsize + ranges.len() as u64);
+    // The top range holds everything paying 10 sat/vB or more
+    assert_eq!(
+        ranges[0].1,
+        snapshot.fee_histogram_with_boundaries(&[10.0])[0].1
+    );
+
+    for uri in [
+        "/mempool/histogram?boundaries=",
+        "/mempool/histogram?boundaries=1,0",
+        "/mempool/histogram?boundaries=1,x",
+    ] {
+        let response = app.clone().oneshot(get(uri)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimator_config_endpoint() -> anyhow::Result<()> {
+    let estimator = FeeEstimator::new()
+        .with_fast_targets()
+        .with_max_fee_rate(500.0)?;
+    let (collector, _temp_dir) = create_test_collector_with(estimator, None).await?;
+    let app = create_app(collector);
 
+    let request = axum::http::Request::builder()
+        .uri("/config/estimator")
+        .body(axum::body::Body::empty())?;
+    let response = app.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let config: bitcoin_augur_api_types::EstimatorConfigResponse = serde_json::from_slice(&body)?;
+
+    assert_eq!(config.server_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(config.probabilities, FeeEstimator::DEFAULT_PROBABILITIES);
+    assert_eq!(
+        config.block_targets,
+        [
+            FeeEstimator::FAST_BLOCK_TARGETS,
+            FeeEstimator::DEFAULT_BLOCK_TARGETS
+        ]
+        .concat()
+    );
+    assert!(config.fast_targets);
+    assert_eq!(config.short_term_window_secs, 1800);
+    assert_eq!(config.long_term_window_secs, 86400);
+    assert_eq!(config.max_fee_rate, Some(500.0));
+    assert_eq!(config.buckets.scale, 100.0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dashboard_is_served_with_the_feature() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_test_app().await?;
     let response = app
         .oneshot(
             axum::http::Request::builder()
-                .uri("/historical_fee")
+                .uri("/ui")
                 .body(axum::body::Body::empty())?,
         )
         .await?;
 
-    // Should return 400 for missing timestamp
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    if !cfg!(feature = "dashboard") {
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        return Ok(());
+    }
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/html; charset=utf-8"
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let page = String::from_utf8(body.to_vec())?;
+    // The page reads everything it shows from the public endpoints
+    for endpoint in ["/fees", "/historical_fees/export", "/mempool/depth"] {
+        assert!(page.contains(endpoint), "{endpoint}");
+    }
 
     Ok(())
 }
 
 #[tokio::test]
-async fn test_concurrent_requests() -> anyhow::Result<()> {
+async fn test_position_endpoint() -> anyhow::Result<()> {
+    let client = BitcoinClient::Mock(MockBitcoinClient::new());
+    let (collector, _temp_dir) =
+        create_test_collector_for(client, FeeEstimator::new(), None).await?;
+    let snapshot = collector.get_latest_snapshot().await.unwrap();
+    let estimate = collector.get_latest_estimate().await.unwrap();
+    let app = create_app(collector);
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+    let position = |response: axum::response::Response| async move {
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+        anyhow::Ok(serde_json::from_slice::<serde_json::Value>(&body)?)
+    };
+
+    let response = app.clone().oneshot(get("/position?fee_rate=12.5")?).await?;
+    let body = position(response).await?;
+    assert_eq!(body["fee_rate"], 12.5);
+    assert_eq!(body["vbytes_above"], snapshot.vbytes_above(12.5));
+    assert_eq!(body["mempool_min_fee"], 1.0);
+    assert_eq!(body["below_mempool_min_fee"], false);
+    let expected = body["expected_blocks"].as_array().unwrap();
+    assert_eq!(
+        expected.len(),
+        estimate.get_available_confidence_levels().len()
+    );
+    for level in expected {
+        let confidence = level["confidence"].as_f64().unwrap();
+        assert_eq!(
+            level["blocks"].as_u64().map(|blocks| blocks as u32),
+            estimate
+                .target_for_fee_rate(12.5, confidence)
+                .map(|target| target.blocks())
+        );
+    }
+
+    // Ahead of the whole mempool, but too cheap to be admitted
+    let response = app.clone().oneshot(get("/position?fee_rate=1000")?).await?;
+    assert_eq!(position(response).await?["vbytes_above"], 0);
+    let response = app.clone().oneshot(get("/position?fee_rate=0.5")?).await?;
+    assert_eq!(position(response).await?["below_mempool_min_fee"], true);
+
+    for uri in [
+        "/position",
+        "/position?fee_rate=0",
+        "/position?fee_rate=abc",
+    ] {
+        let response = app.clone().oneshot(get(uri)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_suggest_endpoint() -> anyhow::Result<()> {
     let (app, _temp_dir) = create_test_app().await?;
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
 
-    // Send multiple concurrent requests
-    let mut handles = vec![];
+    let response = app
+        .clone()
+        .oneshot(get("/suggest?vsize=141&deadline=6&confidence=0.6")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
 
-    for _ in 0..5 {
-        let app_clone = app.clone();
-        let handle = tokio::spawn(async move {
-            let response = app_clone
-                .oneshot(
-                    axum::http::Request::builder()
-                        .uri("/fees")
-                        .body(axum::body::Body::empty())
-                        .unwrap(),
-                )
-                .await
-                .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let suggestion: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(suggestion["vsize"], 141);
+    assert_eq!(suggestion["deadline_blocks"], 6);
+    assert_eq!(suggestion["target_blocks"], 6);
+    // The next configured confidence level at or above the requested one
+    assert_eq!(suggestion["confidence"], 0.8);
 
-            response.status()
-        });
-        handles.push(handle);
+    let fee_rate = suggestion["fee_rate"].as_f64().unwrap();
+    let fee = suggestion["fee"].as_u64().unwrap();
+    assert!(fee_rate >= 1.0);
+    assert!((fee as f64 - fee_rate * 141.0).abs() < 1.0);
+
+    // Confidence defaults to 0.5
+    let response = app
+        .clone()
+        .oneshot(get("/suggest?vsize=200&deadline=12")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let suggestion: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(suggestion["confidence"], 0.5);
+
+    for uri in [
+        "/suggest?vsize=0&deadline=6",
+        "/suggest?vsize=141&deadline=1001",
+        "/suggest?vsize=141&deadline=6&confidence=1.5",
+        "/suggest?deadline=6",
+    ] {
+        let response = app.clone().oneshot(get(uri)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
     }
 
-    // All requests should succeed
-    for handle in handles {
-        let status = handle.await?;
-        assert_eq!(status, StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cpfp_bump_endpoint() -> anyhow::Result<()> {
+    let parent_txid = "ab".repeat(32);
+    // A 141 vB parent paying 1 sat/vB on top of a 110 vB ancestor paying 2 sat/vB
+    let entry = TxMempoolEntry {
+        tx: Package::new(1, 141, 141),
+        ancestors: Package::new(2, 251, 361),
+        descendants: Package::new(1, 141, 141),
+    };
+    let bitcoin_client =
+        BitcoinClient::Mock(MockBitcoinClient::new().with_mempool_entry(&parent_txid, entry));
+    let (collector, _temp_dir) =
+        create_test_collector_for(bitcoin_client, FeeEstimator::new(), None).await?;
+    let app = create_app(collector);
+    let get = |uri: String| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    let response = app
+        .clone()
+        .oneshot(get(format!(
+            "/bump/cpfp?parent_txid={parent_txid}&target=6&confidence=0.8&child_vsize=120"
+        ))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let bump: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(bump["parent_txid"], parent_txid.as_str());
+    assert_eq!(bump["target_blocks"], 6);
+    assert_eq!(bump["ancestors"]["count"], 2);
+    assert_eq!(bump["child_vsize"], 120);
+
+    // The child lifts the whole package to the estimated rate
+    let fee_rate = bump["fee_rate"].as_f64().unwrap();
+    let child_fee = bump["child_fee"].as_u64().unwrap();
+    assert_eq!(bump["package"]["count"], 3);
+    assert_eq!(bump["package"]["vsize"], 371);
+    assert_eq!(bump["package"]["fee"].as_u64().unwrap(), 361 + child_fee);
+    assert!(bump["package"]["fee_rate"].as_f64().unwrap() >= fee_rate - 1e-4);
+
+    // Unknown transactions and malformed ids
+    let response = app
+        .clone()
+        .oneshot(get(format!(
+            "/bump/cpfp?parent_txid={txid}&target=6",
+            txid = "cd".repeat(32)
+        ))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(get("/bump/cpfp?parent_txid=abcd&target=6".to_string())?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rbf_bump_endpoint() -> anyhow::Result<()> {
+    let txid = "ab".repeat(32);
+    // A 200 vB transaction paying 1 sat/vB, with a child that brings the evicted fees to 5000
+    let entry = TxMempoolEntry {
+        tx: Package::new(1, 200, 200),
+        ancestors: Package::new(1, 200, 200),
+        descendants: Package::new(2, 310, 5_000),
+    };
+    let bitcoin_client =
+        BitcoinClient::Mock(MockBitcoinClient::new().with_mempool_entry(&txid, entry));
+    let (collector, _temp_dir) =
+        create_test_collector_for(bitcoin_client, FeeEstimator::new(), None).await?;
+    let app = create_app(collector);
+    let get = |uri: String| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    let response = app
+        .clone()
+        .oneshot(get(format!("/bump/rbf?txid={txid}&target=6"))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let bump: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(bump["txid"], txid.as_str());
+    assert_eq!(bump["replacement_vsize"], 200);
+    assert_eq!(bump["evicted"]["fee"], 5_000);
+    assert_eq!(bump["incremental_relay_fee"], 1.0);
+
+    // The evicted fees plus 1 sat/vB for the replacement, unless the estimate asks for more
+    assert_eq!(bump["relay_minimum_fee"], 5_200);
+    let fee = bump["fee"].as_u64().unwrap();
+    assert_eq!(
+        fee,
+        bump["target_fee"].as_u64().unwrap().max(5_200),
+        "{bump}"
+    );
+
+    // A larger replacement
+    let response = app
+        .clone()
+        .oneshot(get(format!("/bump/rbf?txid={txid}&target=6&vsize=250"))?)
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let bump: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(bump["replacement_vsize"], 250);
+    assert_eq!(bump["relay_minimum_fee"], 5_250);
+
+    let response = app
+        .clone()
+        .oneshot(get(format!(
+            "/bump/rbf?txid={missing}&target=6",
+            missing = "cd".repeat(32)
+        ))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_historical_endpoint() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_test_app().await?;
+
+    // Test with a timestamp that falls within our test data range (20 minutes ago)
+    let timestamp = (Utc::now() - chrono::Duration::minutes(20)).timestamp();
+    let response = app
+        .oneshot(
+            axum::http::Request::builder()
+                .uri(format!("/historical_fee?timestamp={}", timestamp))
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+
+    // Verify response structure
+    assert!(fee_response["mempool_update_time"].is_string());
+    assert!(fee_response["estimates"].is_object());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stale_data_is_refused() -> anyhow::Result<()> {
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    // The newest stored snapshot is taken about an hour before the collector's clock
+    for (max_staleness, stale) in [(120, false), (30, true)] {
+        let temp_dir = TempDir::new()?;
+        let snapshot_store = SnapshotStore::new(temp_dir.path())?;
+        for snapshot in &create_test_snapshots() {
+            snapshot_store.save_snapshot(snapshot)?;
+        }
+        let clock = Clock::simulated(
+            Utc::now() + chrono::Duration::hours(1),
+            chrono::Duration::minutes(1),
+        );
+        let collector = Arc::new(
+            MempoolCollector::new(
+                BitcoinClient::Mock(MockBitcoinClient::new()),
+                snapshot_store,
+                FeeEstimator::new(),
+            )
+            .with_clock(clock)
+            .with_max_staleness(chrono::Duration::minutes(max_staleness)),
+        );
+        collector.initialize_from_store().await?;
+        let app = create_app(collector);
+
+        let response = app.clone().oneshot(get("/fees")?).await?;
+        let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+        if stale {
+            let problem: Problem = serde_json::from_slice(&body)?;
+            assert_eq!(problem.status, 503);
+            assert_eq!(problem.code, ErrorCode::StaleData);
+            assert!(problem.detail.contains("above the maximum of 1800s"));
+        } else {
+            let fees: serde_json::Value = serde_json::from_slice(&body)?;
+            let age = fees["data_age_seconds"].as_u64().unwrap();
+            assert!((3540..3660).contains(&age), "{age}");
+        }
+
+        let response = app.clone().oneshot(get("/health/ready")?).await?;
+        let expected = if stale {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        };
+        assert_eq!(response.status(), expected);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await?;
+        let readiness: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(readiness["stale"].as_bool().unwrap_or_default(), stale);
+        assert!(readiness["data_age_seconds"].as_u64().unwrap() >= 3540);
+
+        // Past estimates stay available
+        let timestamp = (Utc::now() - chrono::Duration::minutes(20)).timestamp();
+        let response = app
+            .clone()
+            .oneshot(get(&format!("/historical_fee?timestamp={timestamp}"))?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.oneshot(get("/metrics")?).await?;
+        let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+        let metrics = String::from_utf8(body.to_vec())?;
+        assert!(metrics.contains(&format!("augur_data_stale {}\n", u8::from(stale))));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distinct_targets_per_minute_are_capped() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let snapshot_store = SnapshotStore::new(temp_dir.path())?;
+    for snapshot in &create_test_snapshots() {
+        snapshot_store.save_snapshot(snapshot)?;
+    }
+    let collector = Arc::new(
+        MempoolCollector::new(
+            BitcoinClient::Mock(MockBitcoinClient::new()),
+            snapshot_store,
+            FeeEstimator::new(),
+        )
+        .with_target_cache(TargetCachePolicy {
+            capacity: 8,
+            ttl: chrono::Duration::seconds(30),
+            max_targets_per_minute: Some(2),
+        }),
+    );
+    collector.initialize_from_store().await?;
+    let app = create_app(collector);
+
+    for target in ["6", "12", "6", "6.0"] {
+        let request = axum::http::Request::builder()
+            .uri(format!("/fees/target/{target}"))
+            .body(axum::body::Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::OK, "{target}");
+    }
+
+    let request = axum::http::Request::builder()
+        .uri("/fees/target/24")
+        .body(axum::body::Body::empty())?;
+    let response = app.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let body = axum::body::to_bytes(response.into_body(), 1024).await?;
+    let problem: Problem = serde_json::from_slice(&body)?;
+    assert_eq!(problem.code, ErrorCode::TooManyTargets);
+    assert!(problem.detail.contains("at most 2 distinct block targets"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_historical_missing_timestamp() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_test_app().await?;
+
+    let response = app
+        .oneshot(
+            axum::http::Request::builder()
+                .uri("/historical_fee")
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+
+    // Should return 400 for missing timestamp
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_historical_export_endpoint() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_test_app().await?;
+
+    let to = Utc::now().timestamp();
+    let from = to - 3600;
+    let response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .uri(format!(
+                    "/historical_fees/export?from={from}&to={to}&step=600"
+                ))
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/csv");
+
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let csv = String::from_utf8(body.to_vec())?;
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("timestamp,block_target,probability,fee_rate")
+    );
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert!(!rows.is_empty());
+    for row in &rows {
+        assert_eq!(row.len(), 4);
+        assert!(row[1].parse::<u32>().is_ok());
+        assert!(row[3].parse::<f64>()? > 0.0);
+    }
+
+    // Future ranges and steps that would recompute too many estimates are rejected
+    for query in [
+        format!("from={from}&to={future}", future = to + 3600),
+        format!("from={from}&to={to}&step=1"),
+        format!("from={from}&to={to}&format=xlsx"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(format!("/historical_fees/export?{query}"))
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_historical_export_limits_depend_on_admin_token() -> anyhow::Result<()> {
+    let get = |uri: &str, token: Option<&str>| {
+        let mut builder = axum::http::Request::builder().uri(uri);
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {token}"));
+        }
+        builder.body(axum::body::Body::empty())
+    };
+    let api = ApiConfig {
+        admin_token: Some("admin".to_string()),
+        ..Default::default()
+    };
+    let (app, _temp_dir) = create_empty_app(api)?;
+
+    let to = Utc::now().timestamp();
+    let week = format!(
+        "/historical_fees/export?from={from}&to={to}&step=3600",
+        from = to - 7 * 24 * 3600
+    );
+    let response = app.clone().oneshot(get(&week, None)?).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app.clone().oneshot(get(&week, Some("wrong"))?).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.clone().oneshot(get(&week, Some("admin"))?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    assert!(body.starts_with(b"timestamp,block_target,probability,fee_rate"));
+
+    // Without an admin token configured, no token unlocks the longer range
+    let (app, _temp_dir) = create_empty_app(ApiConfig::default())?;
+    let response = app.oneshot(get(&week, Some("admin"))?).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_grafana_datasource_endpoints() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_test_app().await?;
+
+    let response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .uri("/grafana")
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/grafana/search")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from("{}"))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let metrics: Vec<String> = serde_json::from_slice(&body)?;
+    assert!(metrics.contains(&"fee_rate:6:0.5".to_string()));
+    assert!(metrics.contains(&"mempool_vsize".to_string()));
+
+    let to = Utc::now();
+    let from = to - chrono::Duration::hours(1);
+    let query = |targets: &str| {
+        format!(
+            r#"{{"range":{{"from":"{from}","to":"{to}"}},"intervalMs":60000,"maxDataPoints":100,"targets":[{targets}]}}"#,
+            from = from.to_rfc3339(),
+            to = to.to_rfc3339()
+        )
+    };
+    let response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/grafana/query")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(query(
+                    r#"{"target":"fee_rate:6:0.5","refId":"A"},{"target":"mempool_vsize","refId":"B"}"#,
+                )))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+    let series: Vec<serde_json::Value> = serde_json::from_slice(&body)?;
+    assert_eq!(series.len(), 2);
+    assert_eq!(series[0]["target"], "fee_rate:6:0.5");
+    assert_eq!(series[1]["target"], "mempool_vsize");
+    for series in &series {
+        let datapoints = series["datapoints"].as_array().unwrap();
+        assert!(!datapoints.is_empty());
+        for point in datapoints {
+            assert!(point[0].as_f64().unwrap() > 0.0);
+            let timestamp = point[1].as_i64().unwrap();
+            assert!(timestamp >= from.timestamp_millis() && timestamp <= to.timestamp_millis());
+        }
+    }
+
+    // Unknown metrics are rejected
+    let response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/grafana/query")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(query(
+                    r#"{"target":"mempool_size"}"#,
+                )))?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fiat_costs() -> anyhow::Result<()> {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let prices = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"time": 1_750_000_000, "USD": 100000})),
+        )
+        .mount(&prices)
+        .await;
+
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let api = ApiConfig {
+        price_feed_url: Some(prices.uri()),
+        ..ApiConfig::default()
+    };
+    let app = create_app_with_config(collector.clone(), api);
+
+    for uri in ["/fees?fiat=usd", "/fees/target/6?fiat=USD"] {
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await?;
+        let fees: bitcoin_augur_api_types::FeeEstimateResponse = serde_json::from_slice(&body)?;
+        let quote = fees.fiat.clone().expect("fiat quote");
+        assert_eq!(quote.currency, "USD");
+        assert_eq!(quote.btc_price, 100_000.0);
+        for target in fees.estimates.values() {
+            for probability in target.probabilities.values() {
+                let expected = probability.fee_rate * quote.tx_vsize as f64 / 1e8 * 100_000.0;
+                let cost = probability.fiat_cost.expect("fiat cost");
+                assert!((cost - expected).abs() <= 0.005, "{cost} vs {expected}");
+            }
+        }
+    }
+
+    // Unknown currencies, malformed codes and servers without a feed are rejected
+    let no_feed = create_app(collector);
+    for (app, uri) in [
+        (&app, "/fees?fiat=eur"),
+        (&app, "/fees?fiat=dollars"),
+        (&no_feed, "/fees?fiat=usd"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_concurrent_requests() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_test_app().await?;
+
+    // Send multiple concurrent requests
+    let mut handles = vec![];
+
+    for _ in 0..5 {
+        let app_clone = app.clone();
+        let handle = tokio::spawn(async move {
+            let response = app_clone
+                .oneshot(
+                    axum::http::Request::builder()
+                        .uri("/fees")
+                        .body(axum::body::Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            response.status()
+        });
+        handles.push(handle);
+    }
+
+    // All requests should succeed
+    for handle in handles {
+        let status = handle.await?;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    Ok(())
+}
+
+/// Create an app with no stored data, optionally exposing the test injection API
+fn create_empty_app(api: ApiConfig) -> anyhow::Result<(axum::Router, TempDir)> {
+    let temp_dir = TempDir::new()?;
+    let config = BitcoinRpcConfig {
+        url: "http://localhost:8332".to_string(),
+        username: "test".to_string(),
+        password: "test".to_string(),
+    };
+
+    let collector = Arc::new(MempoolCollector::new(
+        BitcoinClient::Real(BitcoinRpcClient::new(config)),
+        SnapshotStore::new(temp_dir.path())?,
+        FeeEstimator::new(),
+    ));
+
+    Ok((create_app_with_config(collector, api), temp_dir))
+}
+
+fn injection_request(
+    token: Option<&str>,
+    body: &serde_json::Value,
+) -> axum::http::Request<axum::body::Body> {
+    post_request("/test/snapshots", token, body)
+}
+
+fn post_request(
+    uri: &str,
+    token: Option<&str>,
+    body: &serde_json::Value,
+) -> axum::http::Request<axum::body::Body> {
+    let mut builder = axum::http::Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    builder
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_injection_endpoint_disabled_by_default() -> anyhow::Result<()> {
+    let (app, _temp_dir) = create_empty_app(ApiConfig::default())?;
+    let body = serde_json::json!({ "snapshots": create_test_snapshots() });
+
+    let response = app.oneshot(injection_request(Some("token"), &body)).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_injected_snapshots_drive_estimates() -> anyhow::Result<()> {
+    let api = ApiConfig {
+        test_api_enabled: true,
+        test_api_token: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let (app, _temp_dir) = create_empty_app(api)?;
+
+    // Inject history two days old: estimates must come from it, not the wall clock window
+    let snapshots: Vec<MempoolSnapshot> = create_test_snapshots()
+        .into_iter()
+        .map(|s| {
+            MempoolSnapshot::new(
+                s.block_height,
+                s.timestamp - chrono::Duration::days(2),
+                s.bucketed_weights,
+            )
+        })
+        .collect();
+    let body = serde_json::json!({ "snapshots": snapshots, "replace": true });
+
+    let response = app.clone().oneshot(injection_request(None, &body)).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .clone()
+        .oneshot(injection_request(Some("wrong"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .clone()
+        .oneshot(injection_request(Some("secret"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let injected: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(injected["accepted"], 5);
+    assert_eq!(injected["estimate_available"], true);
+
+    let expected = FeeEstimator::new().calculate_estimates(&snapshots, None)?;
+    let response = app
+        .oneshot(
+            axum::http::Request::builder()
+                .uri("/fees")
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let fees: serde_json::Value = serde_json::from_slice(&body)?;
+
+    let expected_rate = expected.get_fee_rate(6, 0.5).unwrap();
+    let served_rate = fees["estimates"]["6"]["probabilities"]["0.50"]["fee_rate"]
+        .as_f64()
+        .unwrap();
+    assert!((served_rate - expected_rate).abs() < 1e-3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_injection_rejects_empty_payload() -> anyhow::Result<()> {
+    let api = ApiConfig {
+        test_api_enabled: true,
+        test_api_token: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let (app, _temp_dir) = create_empty_app(api)?;
+    let body = serde_json::json!({ "snapshots": [] });
+
+    let response = app
+        .oneshot(injection_request(Some("secret"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_signed_fee_responses() -> anyhow::Result<()> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use ed25519_dalek::{Signature, SigningKey, Verifier};
+
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let secret = [7u8; 32];
+    let signer = Arc::new(ResponseSigner::new(secret, Some("test-key"))?);
+    let app = create_app_with_signer(
+        collector,
+        ApiConfig::default(),
+        ResponseConfig::default(),
+        Some(signer),
+    );
+
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    let response = app.clone().oneshot(get("/fees")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[KEY_ID_HEADER], "test-key");
+    let signature = STANDARD.decode(response.headers()[SIGNATURE_HEADER].as_bytes())?;
+    let signature = Signature::from_slice(&signature)?;
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+
+    // The signature covers the exact body bytes that were sent
+    let public_key = SigningKey::from_bytes(&secret).verifying_key();
+    assert!(public_key.verify(&body, &signature).is_ok());
+    serde_json::from_slice::<serde_json::Value>(&body)?;
+
+    // Errors and non-fee endpoints are not signed
+    let response = app.clone().oneshot(get("/fees/target/0")?).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!response.headers().contains_key(SIGNATURE_HEADER));
+    let response = app.oneshot(get("/health")?).await?;
+    assert!(!response.headers().contains_key(SIGNATURE_HEADER));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_camel_case_field_naming() -> anyhow::Result<()> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use ed25519_dalek::{Signature, SigningKey, Verifier};
+
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let secret = [7u8; 32];
+    let signer = Arc::new(ResponseSigner::new(secret, None)?);
+    let app = create_app_with_signer(
+        collector,
+        ApiConfig::default(),
+        ResponseConfig::default(),
+        Some(signer),
+    );
+
+    let get = |uri: &str, accept: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .header(axum::http::header::ACCEPT, accept)
+            .body(axum::body::Body::empty())
+    };
+
+    // Snake case stays the default
+    let response = app
+        .clone()
+        .oneshot(get("/fees/target/6", "application/json")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[axum::http::header::VARY], "accept");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+    assert!(fee_response.get("mempool_update_time").is_some());
+    assert!(fee_response["estimates"]["6"]["probabilities"]["0.50"]
+        .get("fee_rate")
+        .is_some());
+
+    let response = app
+        .clone()
+        .oneshot(get(
+            "/fees/target/6",
+            "application/json; profile=\"camelCase\"",
+        )?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let signature = STANDARD.decode(response.headers()[SIGNATURE_HEADER].as_bytes())?;
+    let signature = Signature::from_slice(&signature)?;
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+    assert!(fee_response.get("mempoolUpdateTime").is_some());
+    assert!(fee_response.get("mempool_update_time").is_none());
+    assert!(fee_response["estimates"]["6"]["probabilities"]["0.50"]
+        .get("feeRate")
+        .is_some());
+
+    // The signature covers the renamed body
+    let public_key = SigningKey::from_bytes(&secret).verifying_key();
+    assert!(public_key.verify(&body, &signature).is_ok());
+
+    // Problem details keep their field names
+    let response = app
+        .oneshot(get(
+            "/fees/target/0",
+            "application/json; profile=camelCase",
+        )?)
+        .await?;
+    assert_eq!(read_problem(response).await?.code, ErrorCode::InvalidTarget);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rounded_fee_responses() -> anyhow::Result<()> {
+    let estimator = FeeEstimator::new().with_rounding(RoundingPolicy::CeilTenth);
+    let (collector, _temp_dir) = create_test_collector_with(estimator, None).await?;
+    let app = create_app(collector);
+
+    for uri in ["/fees", "/fees/target/6"] {
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+        let estimates = fee_response["estimates"].as_object().unwrap();
+        assert!(!estimates.is_empty());
+
+        // Every published rate is already a multiple of 0.1 sat/vB
+        for block_target in estimates.values() {
+            for probability in block_target["probabilities"].as_object().unwrap().values() {
+                let fee_rate = probability["fee_rate"].as_f64().unwrap();
+                assert_eq!(RoundingPolicy::CeilTenth.apply(fee_rate), fee_rate);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Asserts every number in `value` prints with at most `decimal_places` decimals and no
+/// separators other than the decimal point
+fn assert_decimal_places(value: &serde_json::Value, decimal_places: usize) {
+    match value {
+        serde_json::Value::Number(number) => {
+            let text = number.to_string();
+            assert!(
+                text.chars()
+                    .all(|c| c.is_ascii_digit() || c == '.' || c == '-'),
+                "{text} is not a plain number"
+            );
+            let decimals = text
+                .split_once('.')
+                .map_or(0, |(_, decimals)| decimals.len());
+            assert!(decimals <= decimal_places, "{text} has {decimals} decimals");
+        }
+        serde_json::Value::Array(values) => values
+            .iter()
+            .for_each(|value| assert_decimal_places(value, decimal_places)),
+        serde_json::Value::Object(map) => map
+            .values()
+            .for_each(|value| assert_decimal_places(value, decimal_places)),
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn test_response_decimal_places() -> anyhow::Result<()> {
+    let (collector, _temp_dir) = create_test_collector().await?;
+    let app = create_app_with_signer(
+        collector,
+        ApiConfig::default(),
+        ResponseConfig { decimal_places: 2 },
+        None,
+    );
+
+    let to = Utc::now();
+    let from = to - chrono::Duration::hours(1);
+    let grafana_query = serde_json::json!({
+        "range": {"from": from, "to": to},
+        "targets": [{"target": "fee_rate:6:0.5"}, {"target": "mempool_vsize"}],
+    });
+    let requests = [
+        ("GET", "/fees", None),
+        ("GET", "/fees/target/6", None),
+        ("GET", "/fees/curve?target=6", None),
+        ("GET", "/mempool/depth?vsize=1000", None),
+        ("GET", "/mempool/histogram", None),
+        ("POST", "/grafana/query", Some(grafana_query.to_string())),
+    ];
+    for (method, uri, body) in requests {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(axum::body::Body::empty, axum::body::Body::from))?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let value: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_decimal_places(&value, 2);
+    }
+
+    // The CSV export rounds its fee rates the same way
+    let to = Utc::now().timestamp();
+    let from = to - 3600;
+    let response = app
+        .oneshot(
+            axum::http::Request::builder()
+                .uri(format!(
+                    "/historical_fees/export?from={from}&to={to}&step=600"
+                ))
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let csv = String::from_utf8(body.to_vec())?;
+    for row in csv.lines().skip(1) {
+        let fee_rate = row.rsplit(',').next().unwrap();
+        let decimals = fee_rate
+            .split_once('.')
+            .map_or(0, |(_, decimals)| decimals.len());
+        assert!(decimals <= 2, "{fee_rate} has {decimals} decimals");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fast_targets() -> anyhow::Result<()> {
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    // Not estimated unless enabled
+    let (app, _temp_dir) = create_test_app().await?;
+    let response = app.oneshot(get("/fees/target/1")?).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let estimator = FeeEstimator::new().with_fast_targets();
+    let (collector, _temp_dir) = create_test_collector_with(estimator, None).await?;
+    let app = create_app(collector);
+
+    let response = app.clone().oneshot(get("/fees")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(fee_response["estimates"]["1"]["high_variance"], true);
+    assert_eq!(fee_response["estimates"]["2"]["high_variance"], true);
+    // Longer targets keep the reference format
+    assert!(fee_response["estimates"]["3"]
+        .get("high_variance")
+        .is_none());
+
+    let response = app.clone().oneshot(get("/fees/target/2")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+    let estimates = fee_response["estimates"].as_object().unwrap();
+    assert_eq!(estimates.len(), 1);
+    assert_eq!(estimates["2"]["high_variance"], true);
+
+    // Only whole blocks below the simulated minimum
+    let response = app.oneshot(get("/fees/target/1.5")?).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_debug_simulation() -> anyhow::Result<()> {
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    // Not routed unless enabled
+    let (app, _temp_dir) = create_test_app().await?;
+    let response = app.oneshot(get("/debug/simulation?target=6")?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let (collector, _temp_dir) = create_test_collector_with(FeeEstimator::new(), None).await?;
+    let api = ApiConfig {
+        debug_api_enabled: true,
+        ..ApiConfig::default()
+    };
+    let app = create_app_with_config(collector, api);
+
+    let response = app
+        .clone()
+        .oneshot(get("/debug/simulation?target=6&confidence=0.95")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let simulation: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(simulation["target_blocks"], 6);
+    assert_eq!(simulation["confidence"], 0.95);
+    for run in ["short_term", "long_term"] {
+        let buckets = simulation[run]["buckets"].as_array().unwrap();
+        assert!(!buckets.is_empty());
+        for bucket in buckets {
+            assert!(bucket["initial_weight"].as_u64().is_some());
+            assert!(bucket["remaining_weight"].as_u64().is_some());
+        }
+    }
+
+    // Fast targets are not simulated
+    let response = app
+        .clone()
+        .oneshot(get("/debug/simulation?target=2")?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Blocks are only realized when block feedback is on
+    let response = app.oneshot(get("/debug/blocks")?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fee_comparison_with_baselines() -> anyhow::Result<()> {
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    // Not routed unless a baseline is enabled
+    let (app, _temp_dir) = create_test_app().await?;
+    let response = app.oneshot(get("/fees/compare")?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let temp_dir = TempDir::new()?;
+    let snapshot_store = SnapshotStore::new(temp_dir.path())?;
+    for snapshot in &create_test_snapshots() {
+        snapshot_store.save_snapshot(snapshot)?;
+    }
+    let collector = MempoolCollector::new(
+        BitcoinClient::Mock(MockBitcoinClient::new()),
+        snapshot_store,
+        FeeEstimator::new(),
+    )
+    .with_baselines(Baselines::new(&[Baseline::TopVmb, Baseline::Core]));
+    let collector = Arc::new(collector);
+    collector.initialize_from_store().await?;
+    let app = create_app(collector);
+
+    let response = app.oneshot(get("/fees/compare")?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let comparison: bitcoin_augur_api_types::FeeComparisonResponse = serde_json::from_slice(&body)?;
+    assert!(comparison.augur.has_estimates());
+    assert_eq!(
+        comparison.baselines.keys().collect::<Vec<_>>(),
+        vec!["core", "top-vmb"]
+    );
+    for baseline in comparison.baselines.values() {
+        assert_eq!(
+            baseline.mempool_update_time,
+            comparison.augur.mempool_update_time
+        );
+        assert!(baseline.get_target(6).is_some());
+    }
+    // The mock node estimates 10 sat/vB divided by the target
+    let core = comparison.baselines["core"].get_target(144).unwrap();
+    assert_eq!(core.get_fee_rate(0.95), Some(1.0));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_valid_until_metadata() -> anyhow::Result<()> {
+    let policy = ValidityPolicy {
+        collection_interval: chrono::Duration::seconds(30),
+        target_fraction: 0.1,
+        max_ttl: chrono::Duration::hours(1),
+    };
+    let (collector, _temp_dir) =
+        create_test_collector_with(FeeEstimator::new(), Some(policy)).await?;
+    let app = create_app(collector);
+
+    for uri in ["/fees", "/fees/target/6"] {
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+        let parse = |value: &serde_json::Value| {
+            chrono::DateTime::parse_from_rfc3339(value.as_str().unwrap()).unwrap()
+        };
+        let updated = parse(&fee_response["mempool_update_time"]);
+        let valid_until = parse(&fee_response["valid_until"]);
+
+        // The response expires with its earliest target, never before the next collection
+        let estimates = fee_response["estimates"].as_object().unwrap();
+        let target_expiries: Vec<_> = estimates
+            .values()
+            .map(|target| parse(&target["valid_until"]))
+            .collect();
+        assert_eq!(target_expiries.iter().min(), Some(&valid_until));
+        assert!(valid_until >= updated + chrono::Duration::seconds(30));
+    }
+
+    // Without a policy, the format matches the reference implementation
+    let (app, _temp_dir) = create_test_app().await?;
+    let response = app
+        .oneshot(
+            axum::http::Request::builder()
+                .uri("/fees")
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+    assert!(fee_response.get("valid_until").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_degraded_flag_for_gaps() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let snapshot_store = SnapshotStore::new(temp_dir.path())?;
+    // Test snapshots are 10 minutes apart
+    for snapshot in create_test_snapshots() {
+        snapshot_store.save_snapshot(&snapshot)?;
+    }
+
+    let get = |uri: &str| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+    for (max_gap_minutes, degraded) in [(5, true), (15, false)] {
+        let collector = MempoolCollector::new(
+            BitcoinClient::Mock(MockBitcoinClient::new()),
+            SnapshotStore::new(temp_dir.path())?,
+            FeeEstimator::new(),
+        )
+        .with_gap_policy(GapPolicy {
+            max_gap: chrono::Duration::minutes(max_gap_minutes),
+            backfill: false,
+        });
+        collector.initialize_from_store().await?;
+        let app = create_app(Arc::new(collector));
+
+        for uri in ["/fees", "/fees/target/6"] {
+            let response = app.clone().oneshot(get(uri)?).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+            let fee_response: serde_json::Value = serde_json::from_slice(&body)?;
+            assert_eq!(fee_response.get("degraded").is_some(), degraded, "{uri}");
+            if degraded {
+                assert_eq!(fee_response["degraded"], true);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ingested_snapshots_drive_estimates() -> anyhow::Result<()> {
+    let body = serde_json::json!({ "snapshots": create_test_snapshots() });
+
+    // Not routed without a token
+    let (app, _temp_dir) = create_empty_app(ApiConfig::default())?;
+    let response = app
+        .oneshot(post_request("/snapshots", Some("secret"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let api = ApiConfig {
+        ingest_token: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let (app, _temp_dir) = create_empty_app(api)?;
+
+    let response = app
+        .clone()
+        .oneshot(post_request("/snapshots", Some("wrong"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .clone()
+        .oneshot(post_request("/snapshots", Some("secret"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let ingested: serde_json::Value = serde_json::from_slice(&bytes)?;
+    assert_eq!(ingested["accepted"], 5);
+    assert_eq!(ingested["estimate_available"], true);
+
+    // The ingested history is served like collected history
+    let response = app
+        .oneshot(
+            axum::http::Request::builder()
+                .uri("/fees")
+                .body(axum::body::Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let fees: serde_json::Value = serde_json::from_slice(&bytes)?;
+    assert_eq!(fees["mempool_update_time"], ingested["mempool_update_time"]);
+    assert!(!fees["estimates"].as_object().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_source_comparison() -> anyhow::Result<()> {
+    let api = ApiConfig {
+        ingest_token: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let (app, _temp_dir) = create_empty_app(api)?;
+    let get = || {
+        axum::http::Request::builder()
+            .uri("/fees/sources")
+            .body(axum::body::Body::empty())
+    };
+
+    let response = app.clone().oneshot(get()?).await?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Node B sees every bucket three times as full, a minute after node A
+    let node_a = create_test_snapshots()
+        .into_iter()
+        .map(|snapshot| snapshot.with_source("node-a"));
+    let node_b = create_test_snapshots().into_iter().map(|mut snapshot| {
+        snapshot.timestamp -= chrono::Duration::minutes(1);
+        snapshot.bucketed_weights.values_mut().for_each(|w| *w *= 3);
+        snapshot.with_source("node-b")
+    });
+    let snapshots: Vec<_> = node_a.chain(node_b).collect();
+    let body = serde_json::json!({ "snapshots": snapshots });
+    let response = app
+        .clone()
+        .oneshot(post_request("/snapshots", Some("secret"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.oneshot(get()?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let comparison: bitcoin_augur_api_types::SourceComparisonResponse =
+        serde_json::from_slice(&bytes)?;
+    assert_eq!(
+        comparison.sources.keys().collect::<Vec<_>>(),
+        ["node-a", "node-b"]
+    );
+    assert!(comparison.combined.has_estimates());
+    assert!(comparison.sources.values().all(|e| e.has_estimates()));
+    assert_eq!(comparison.deviations.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_admin_cycles_endpoint() -> anyhow::Result<()> {
+    let get = |uri: &str, token: Option<&str>| {
+        let mut builder = axum::http::Request::builder().uri(uri);
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {token}"));
+        }
+        builder.body(axum::body::Body::empty())
+    };
+
+    // Not routed without an admin token
+    let (app, _temp_dir) = create_empty_app(ApiConfig::default())?;
+    let response = app.oneshot(get("/admin/cycles", Some("admin"))?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let temp_dir = TempDir::new()?;
+    let log = CycleLog::open(temp_dir.path().join("cycles.jsonl"), 10)?;
+    let start = Utc::now();
+    for minute in 0..3 {
+        log.record(CycleRecord {
+            block_height: Some(911275 + minute),
+            total_ms: 5.0,
+            ..CycleRecord::new(start + chrono::Duration::minutes(i64::from(minute)))
+        })?;
+    }
+    let collector = MempoolCollector::new(
+        BitcoinClient::Mock(MockBitcoinClient::new()),
+        SnapshotStore::new(temp_dir.path())?,
+        FeeEstimator::new(),
+    )
+    .with_cycle_log(log);
+    let api = ApiConfig {
+        admin_token: Some("admin".to_string()),
+        ..Default::default()
+    };
+    let app = create_app_with_config(Arc::new(collector), api);
+
+    let response = app
+        .clone()
+        .oneshot(get("/admin/cycles", Some("wrong"))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .clone()
+        .oneshot(get("/admin/cycles?limit=0", Some("admin"))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .oneshot(get("/admin/cycles?limit=2", Some("admin"))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let body: serde_json::Value = serde_json::from_slice(&bytes)?;
+    let cycles = body["cycles"].as_array().unwrap();
+    assert_eq!(cycles.len(), 2);
+    assert_eq!(cycles[0]["block_height"], 911277);
+    assert_eq!(cycles[1]["block_height"], 911276);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_changes_endpoint() -> anyhow::Result<()> {
+    let get = |uri: &str, token: Option<&str>| {
+        let mut builder = axum::http::Request::builder().uri(uri);
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {token}"));
+        }
+        builder.body(axum::body::Body::empty())
+    };
+
+    // Not routed without a sync token
+    let (app, _temp_dir) = create_empty_app(ApiConfig::default())?;
+    let response = app.oneshot(get("/sync/changes", Some("sync"))?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let api = ApiConfig {
+        ingest_token: Some("secret".to_string()),
+        sync_token: Some("sync".to_string()),
+        ..Default::default()
+    };
+    let (app, _temp_dir) = create_empty_app(api)?;
+    let body = serde_json::json!({ "snapshots": create_test_snapshots() });
+    let response = app
+        .clone()
+        .oneshot(post_request("/snapshots", Some("secret"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(get("/sync/changes", Some("wrong"))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .clone()
+        .oneshot(get("/sync/changes?limit=0", Some("sync"))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Paging from the start returns every snapshot once, oldest first
+    let mut heights = Vec::new();
+    let mut pages = 0;
+    let mut uri = "/sync/changes?limit=2".to_string();
+    loop {
+        let response = app.clone().oneshot(get(&uri, Some("sync"))?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let changes: SyncChanges = serde_json::from_slice(&bytes)?;
+        heights.extend(changes.snapshots.iter().map(|s| s.block_height));
+        pages += 1;
+        if !changes.more {
+            break;
+        }
+        uri = format!("/sync/changes?limit=2&since={}", changes.next_since);
+    }
+    assert_eq!(heights, vec![911279, 911278, 911277, 911276, 911275]);
+    assert_eq!(pages, 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ingestion_rejects_invalid_snapshots() -> anyhow::Result<()> {
+    let api = ApiConfig {
+        ingest_token: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let (app, temp_dir) = create_empty_app(api)?;
+
+    let mut snapshots = create_test_snapshots();
+    snapshots[0].timestamp = Utc::now() + chrono::Duration::hours(1);
+    let body = serde_json::json!({ "snapshots": snapshots });
+
+    let response = app
+        .oneshot(post_request("/snapshots", Some("secret"), &body))
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Nothing from a rejected batch is stored
+    assert_eq!(std::fs::read_dir(temp_dir.path())?.count(), 0);
+
+    Ok(())
+}
+
+/// Reads an RFC 7807 body, checking its content type and that it repeats the status
+async fn read_problem(response: axum::response::Response) -> anyhow::Result<Problem> {
+    let status = response.status();
+    assert_eq!(
+        response.headers()[axum::http::header::CONTENT_TYPE],
+        PROBLEM_CONTENT_TYPE
+    );
+    let body = axum::body::to_bytes(response.into_body(), 10240).await?;
+    let problem: Problem = serde_json::from_slice(&body)?;
+    assert_eq!(problem.status, status.as_u16());
+    assert_eq!(problem.problem_type, "about:blank");
+    assert!(!problem.detail.is_empty());
+    Ok(problem)
+}
+
+#[tokio::test]
+async fn test_error_responses_are_problems() -> anyhow::Result<()> {
+    let get = |uri: String| {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(axum::body::Body::empty())
+    };
+
+    let (app, _empty_dir) = create_empty_app(ApiConfig::default())?;
+    let response = app.oneshot(get("/fees".to_string())?).await?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(read_problem(response).await?.code, ErrorCode::WarmingUp);
+
+    let (app, _temp_dir) = create_test_app().await?;
+    let year_ago = (Utc::now() - chrono::Duration::days(300)).timestamp();
+    let cases = [
+        (
+            "/fees/target/1001".to_string(),
+            400,
+            ErrorCode::InvalidTarget,
+        ),
+        (
+            "/suggest?vsize=141&deadline=0".to_string(),
+            400,
+            ErrorCode::InvalidTarget,
+        ),
+        (
+            "/historical_fee".to_string(),
+            400,
+            ErrorCode::InvalidRequest,
+        ),
+        (
+            format!("/historical_fee?timestamp={year_ago}"),
+            404,
+            ErrorCode::NoData,
+        ),
+        ("/no/such/route".to_string(), 404, ErrorCode::NotFound),
+        (
+            format!("/bump/rbf?txid={txid}&target=6", txid = "ab".repeat(32)),
+            503,
+            ErrorCode::UpstreamRpcDown,
+        ),
+    ];
+    for (uri, status, code) in cases {
+        let response = app.clone().oneshot(get(uri.clone())?).await?;
+        assert_eq!(response.status().as_u16(), status, "{uri}");
+        assert_eq!(read_problem(response).await?.code, code, "{uri}");
     }
 
     Ok(())
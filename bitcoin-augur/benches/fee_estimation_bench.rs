@@ -2,6 +2,11 @@ use bitcoin_augur::{FeeEstimator, MempoolSnapshot, MempoolTransaction};
 use chrono::{Duration, Utc};
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 
+// Shared with the integration tests, which use the rest of it
+#[allow(dead_code, unused_imports)]
+#[path = "../tests/helpers.rs"]
+mod helpers;
+
 /// Generate a mempool snapshot with the specified number of transactions
 fn generate_mempool_snapshot(num_transactions: usize) -> MempoolSnapshot {
     let mut transactions = Vec::with_capacity(num_transactions);
@@ -26,9 +31,8 @@ fn generate_snapshot_history(
 ) -> Vec<MempoolSnapshot> {
     let mut snapshots = Vec::with_capacity(num_snapshots);
     let mut base_time = Utc::now() - Duration::hours(24);
-    let mut height = 800000;
 
-    for _ in 0..num_snapshots {
+    for height in (800000..).take(num_snapshots) {
         let transactions: Vec<MempoolTransaction> = (0..txs_per_snapshot)
             .map(|i| {
                 let fee_rate = (i as f64 % 100.0) + 1.0;
@@ -42,7 +46,6 @@ fn generate_snapshot_history(
         snapshots.push(snapshot);
 
         base_time += Duration::minutes(10);
-        height += 1;
     }
 
     snapshots
@@ -114,6 +117,21 @@ fn benchmark_confidence_levels(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_trace_corpus(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trace_corpus");
+
+    // Each recorded trace as one estimation window
+    for (name, trace) in helpers::trace_corpus() {
+        let estimator = FeeEstimator::new();
+
+        group.bench_with_input(BenchmarkId::new("trace", &name), &trace, |b, trace| {
+            b.iter(|| estimator.calculate_estimates(trace, None));
+        });
+    }
+
+    group.finish();
+}
+
 // Poisson calculation is tested implicitly through fee estimation
 // since it's an internal implementation detail
 
@@ -121,6 +139,7 @@ criterion_group!(
     benches,
     benchmark_fee_estimation,
     benchmark_multi_snapshot_estimation,
-    benchmark_confidence_levels
+    benchmark_confidence_levels,
+    benchmark_trace_corpus
 );
 criterion_main!(benches);
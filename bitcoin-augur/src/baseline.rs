@@ -0,0 +1,180 @@
+use chrono::Utc;
+
+use crate::{
+    error::{AugurError, Result},
+    BlockTarget, BlockTargetKey, EstimationStrategy, FeeEstimate, FeeEstimator, MempoolSnapshot,
+    OrderedFloat,
+};
+
+/// One virtual megabyte, what one block holds, in virtual bytes
+const VMB: f64 = 1_000_000.0;
+
+/// Fee rate suggested once the mempool holds less than the depth looked at, in sat/vB
+const MIN_FEE_RATE: f64 = 1.0;
+
+/// Naive baseline reading the newest snapshot only: the fee rate ranking among the top N vMB
+/// of the mempool for an N block target.
+///
+/// At confidence `p`, the suggested fee rate is the one ahead of a fraction `p` of the top N
+/// vMB: the rate found `(1 - p) × N` vMB deep, counting from the highest fee rate down. Inflows
+/// and block timing are ignored, which is what makes it a useful reference point for the
+/// augur simulation.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{EstimationStrategy, MempoolSnapshot, TopVmbStrategy};
+/// use chrono::Utc;
+/// use std::collections::BTreeMap;
+///
+/// // 3 vMB paying about 20 sat/vB, then 3 vMB paying about 5 sat/vB
+/// let weights = BTreeMap::from([(300, 12_000_000), (161, 12_000_000)]);
+/// let snapshot = MempoolSnapshot::new(850_000, Utc::now(), weights);
+///
+/// let estimate = TopVmbStrategy::new().estimate(&[snapshot], None)?;
+/// assert!(estimate.get_fee_rate(3, 0.5).unwrap() > 19.0);
+/// assert!(estimate.get_fee_rate(6, 0.05).unwrap() < 6.0);
+/// # Ok::<(), bitcoin_augur::AugurError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopVmbStrategy {
+    block_targets: Vec<f64>,
+    probabilities: Vec<f64>,
+}
+
+impl Default for TopVmbStrategy {
+    fn default() -> Self {
+        Self {
+            block_targets: FeeEstimator::DEFAULT_BLOCK_TARGETS.to_vec(),
+            probabilities: FeeEstimator::DEFAULT_PROBABILITIES.to_vec(),
+        }
+    }
+}
+
+impl TopVmbStrategy {
+    /// Creates the strategy with the default block targets and confidence levels of
+    /// [`FeeEstimator`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the strategy for other block targets and confidence levels.
+    ///
+    /// Targets must be at least 1 block and probabilities between 0.0 and 1.0.
+    pub fn with_config(block_targets: Vec<f64>, probabilities: Vec<f64>) -> Result<Self> {
+        if block_targets.is_empty() || probabilities.is_empty() {
+            return Err(AugurError::invalid_config(
+                "At least one block target and probability level must be provided",
+            ));
+        }
+        if block_targets
+            .iter()
+            .any(|&target| !target.is_finite() || target < 1.0)
+        {
+            return Err(AugurError::invalid_config(
+                "All block targets must be at least 1",
+            ));
+        }
+        if probabilities.iter().any(|&p| !(0.0..=1.0).contains(&p)) {
+            return Err(AugurError::invalid_config(
+                "All probabilities must be between 0.0 and 1.0",
+            ));
+        }
+        Ok(Self {
+            block_targets,
+            probabilities,
+        })
+    }
+}
+
+impl EstimationStrategy for TopVmbStrategy {
+    fn name(&self) -> &str {
+        "top-vmb"
+    }
+
+    fn estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> Result<FeeEstimate> {
+        if let Some(blocks) = num_blocks {
+            if !blocks.is_finite() || blocks < 1.0 {
+                return Err(AugurError::invalid_parameter(
+                    "num_blocks must be at least 1",
+                ));
+            }
+        }
+        let Some(latest) = snapshots.last() else {
+            return Ok(FeeEstimate::empty(Utc::now()));
+        };
+
+        let targets = match num_blocks {
+            Some(blocks) => vec![blocks],
+            None => self.block_targets.clone(),
+        };
+        let estimates = targets
+            .into_iter()
+            .map(|blocks| {
+                let probabilities = self
+                    .probabilities
+                    .iter()
+                    .map(|&probability| {
+                        let depth = ((1.0 - probability) * blocks * VMB) as u64;
+                        let fee_rate = latest
+                            .fee_rate_at_depth(depth)
+                            .map_or(MIN_FEE_RATE, |rate| rate.max(MIN_FEE_RATE));
+                        (OrderedFloat(probability), fee_rate)
+                    })
+                    .collect();
+                let target = BlockTargetKey::try_from(blocks)?;
+                Ok((target, BlockTarget::new(target.blocks(), probabilities)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(FeeEstimate::from_targets(estimates, latest.timestamp))
+    }
+
+    fn history_window(&self) -> chrono::Duration {
+        chrono::Duration::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// 2 vMB at bucket 300 (about 20 sat/vB) above 2 vMB at bucket 100 (about 2.7 sat/vB)
+    fn snapshot() -> MempoolSnapshot {
+        let weights = BTreeMap::from([(300, 8_000_000), (100, 8_000_000)]);
+        MempoolSnapshot::new(850_000, Utc::now(), weights)
+    }
+
+    #[test]
+    fn test_fee_rate_follows_depth() {
+        let strategy = TopVmbStrategy::with_config(vec![1.0, 3.0, 6.0], vec![0.05, 0.5]).unwrap();
+        let estimate = strategy.estimate(&[snapshot()], None).unwrap();
+        let high = 3f64.exp();
+        let low = 1f64.exp();
+
+        // Half a block deep is well inside the top bucket
+        assert_eq!(estimate.get_fee_rate(1, 0.5), Some(high));
+        // 1.5 vMB deep at 50% for 3 blocks, 2.85 vMB deep at 5%
+        assert_eq!(estimate.get_fee_rate(3, 0.5), Some(high));
+        assert_eq!(estimate.get_fee_rate(3, 0.05), Some(low));
+        // 5.7 vMB deep runs past the 4 vMB mempool
+        assert_eq!(estimate.get_fee_rate(6, 0.05), Some(MIN_FEE_RATE));
+        assert_eq!(strategy.name(), "top-vmb");
+    }
+
+    #[test]
+    fn test_single_target_and_validation() {
+        let strategy = TopVmbStrategy::new();
+        let estimate = strategy.estimate(&[snapshot()], Some(2.0)).unwrap();
+        assert_eq!(estimate.available_targets(), vec![2]);
+        assert!(strategy.estimate(&[snapshot()], Some(0.5)).is_err());
+        assert!(strategy.estimate(&[], None).unwrap().estimates.is_empty());
+
+        assert!(TopVmbStrategy::with_config(vec![], vec![0.5]).is_err());
+        assert!(TopVmbStrategy::with_config(vec![0.0], vec![0.5]).is_err());
+        assert!(TopVmbStrategy::with_config(vec![3.0], vec![1.5]).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::{Duration, Utc};
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{AugurError, Result},
+    BlockTarget, BlockTargetKey, EstimationStrategy, FeeEstimate, MempoolSnapshot, OrderedFloat,
+};
+
+/// Combines several strategies into one estimate, hedging against the failure modes of any
+/// single model.
+///
+/// Every block target and confidence level gets the weighted median of the fee rates the
+/// members suggest for it. A member that fails or leaves a target out is skipped there, so the
+/// others carry the estimate; only when every member fails does the ensemble fail.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{EnsembleStrategy, EstimationStrategy, FeeEstimator, MempoolSnapshot, TopVmbStrategy};
+/// use chrono::Utc;
+/// use std::collections::BTreeMap;
+///
+/// let snapshot = MempoolSnapshot::new(850_000, Utc::now(), BTreeMap::from([(300, 8_000_000)]));
+/// let ensemble = EnsembleStrategy::new()
+///     .with_member(FeeEstimator::new(), 2.0)?
+///     .with_member(TopVmbStrategy::new(), 1.0)?;
+///
+/// let estimate = ensemble.estimate(&[snapshot], None)?;
+/// assert!(estimate.get_fee_rate(3, 0.5).is_some());
+/// # Ok::<(), bitcoin_augur::AugurError>(())
+/// ```
+#[derive(Default)]
+pub struct EnsembleStrategy {
+    members: Vec<(Box<dyn EstimationStrategy>, f64)>,
+}
+
+impl EnsembleStrategy {
+    /// Creates an ensemble without members; add them with [`with_member`](Self::with_member).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `strategy` with a relative `weight`, which must be positive.
+    pub fn with_member(
+        mut self,
+        strategy: impl EstimationStrategy + 'static,
+        weight: f64,
+    ) -> Result<Self> {
+        if !weight.is_finite() || weight <= 0.0 {
+            return Err(AugurError::invalid_config(format!(
+                "Weight of ensemble member {} must be positive, got {weight}",
+                strategy.name()
+            )));
+        }
+        self.members.push((Box::new(strategy), weight));
+        Ok(self)
+    }
+
+    /// Names and weights of the members, in the order they were added
+    pub fn members(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.members
+            .iter()
+            .map(|(strategy, weight)| (strategy.name(), *weight))
+    }
+
+    /// Combines estimates already made, each paired with its weight.
+    ///
+    /// Each block target and confidence level present in any estimate gets the weighted median
+    /// of the rates suggested for it. The result is timestamped with the newest estimate.
+    pub fn combine<'a>(estimates: impl IntoIterator<Item = (&'a FeeEstimate, f64)>) -> FeeEstimate {
+        let mut rates: BTreeMap<(BlockTargetKey, OrderedFloat), Vec<(f64, f64)>> = BTreeMap::new();
+        let mut timestamp = None;
+        for (estimate, weight) in estimates {
+            timestamp = timestamp.max(Some(estimate.timestamp));
+            for (blocks, target) in estimate.targets() {
+                for (&probability, &rate) in &target.probabilities {
+                    rates
+                        .entry((blocks, probability))
+                        .or_default()
+                        .push((rate, weight));
+                }
+            }
+        }
+
+        let mut targets: BTreeMap<BlockTargetKey, BTreeMap<OrderedFloat, f64>> = BTreeMap::new();
+        for ((target, probability), rates) in rates {
+            if let Some(rate) = weighted_median(rates) {
+                targets.entry(target).or_default().insert(probability, rate);
+            }
+        }
+        let estimates = targets.into_iter().map(|(target, probabilities)| {
+            (target, BlockTarget::new(target.blocks(), probabilities))
+        });
+        FeeEstimate::from_targets(estimates, timestamp.unwrap_or_else(Utc::now))
+    }
+}
+
+/// Median of `(rate, weight)` pairs: the rate at which half of the total weight lies below.
+///
+/// When the halfway point falls exactly between two rates, their mean is returned, so two
+/// members of equal weight meet in the middle.
+fn weighted_median(mut rates: Vec<(f64, f64)>) -> Option<f64> {
+    rates.retain(|(rate, weight)| rate.is_finite() && *weight > 0.0);
+    rates.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let half = rates.iter().map(|(_, weight)| weight).sum::<f64>() / 2.0;
+
+    let mut below = 0.0;
+    for (i, &(rate, weight)) in rates.iter().enumerate() {
+        below += weight;
+        if (below - half).abs() <= half * 1e-12 {
+            return Some(
+                rates
+                    .get(i + 1)
+                    .map_or(rate, |&(next, _)| (rate + next) / 2.0),
+            );
+        }
+        if below > half {
+            return Some(rate);
+        }
+    }
+    None
+}
+
+impl EstimationStrategy for EnsembleStrategy {
+    fn name(&self) -> &str {
+        "ensemble"
+    }
+
+    fn estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> Result<FeeEstimate> {
+        if self.members.is_empty() {
+            return Err(AugurError::invalid_config(
+                "An ensemble needs at least one member",
+            ));
+        }
+
+        let mut estimates = Vec::new();
+        let mut first_error = None;
+        for (strategy, weight) in &self.members {
+            match strategy.estimate(snapshots, num_blocks) {
+                Ok(estimate) => estimates.push((estimate, *weight)),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if estimates.is_empty() {
+            return Err(first_error.unwrap_or_else(|| {
+                AugurError::calculation("Every ensemble member failed without an error")
+            }));
+        }
+
+        let mut combined = Self::combine(
+            estimates
+                .iter()
+                .map(|(estimate, weight)| (estimate, *weight)),
+        );
+        if let Some(latest) = snapshots.last() {
+            combined.timestamp = latest.timestamp;
+        }
+        Ok(combined)
+    }
+
+    /// The longest window any member reads
+    fn history_window(&self) -> Duration {
+        self.members
+            .iter()
+            .map(|(strategy, _)| strategy.history_window())
+            .max()
+            .unwrap_or_else(Duration::zero)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Suggests the same rate for target 6 at 50%, or fails
+    struct Fixed(Option<f64>);
+
+    impl EstimationStrategy for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn estimate(&self, _: &[MempoolSnapshot], _: Option<f64>) -> Result<FeeEstimate> {
+            let rate = self
+                .0
+                .ok_or_else(|| AugurError::calculation("no estimate"))?;
+            let target = BlockTarget::new(6, BTreeMap::from([(OrderedFloat(0.5), rate)]));
+            Ok(FeeEstimate::new(BTreeMap::from([(6, target)]), Utc::now()))
+        }
+    }
+
+    #[test]
+    fn test_weighted_median() {
+        assert_eq!(weighted_median(vec![(5.0, 1.0)]), Some(5.0));
+        assert_eq!(
+            weighted_median(vec![(9.0, 1.0), (1.0, 1.0), (5.0, 1.0)]),
+            Some(5.0)
+        );
+        // Heavy enough to outvote the other two
+        assert_eq!(
+            weighted_median(vec![(9.0, 3.0), (1.0, 1.0), (5.0, 1.0)]),
+            Some(9.0)
+        );
+        assert_eq!(weighted_median(vec![(2.0, 1.0), (4.0, 1.0)]), Some(3.0));
+        assert_eq!(weighted_median(vec![]), None);
+    }
+
+    #[test]
+    fn test_ensemble_skips_failing_members() {
+        let ensemble = EnsembleStrategy::new()
+            .with_member(Fixed(Some(2.0)), 1.0)
+            .unwrap()
+            .with_member(Fixed(Some(10.0)), 2.0)
+            .unwrap()
+            .with_member(Fixed(None), 5.0)
+            .unwrap();
+        let estimate = ensemble.estimate(&[], None).unwrap();
+        assert_eq!(estimate.get_fee_rate(6, 0.5), Some(10.0));
+        assert_eq!(ensemble.members().count(), 3);
+
+        let failing = EnsembleStrategy::new()
+            .with_member(Fixed(None), 1.0)
+            .unwrap();
+        assert!(failing.estimate(&[], None).is_err());
+        assert!(EnsembleStrategy::new().estimate(&[], None).is_err());
+        assert!(EnsembleStrategy::new()
+            .with_member(Fixed(None), 0.0)
+            .is_err());
+    }
+}
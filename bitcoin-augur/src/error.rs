@@ -23,6 +23,10 @@ pub enum AugurError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    /// More snapshot history than the estimator's maximum history allows.
+    #[error("History too long: {0}")]
+    HistoryTooLong(String),
+
     /// Date/time related error.
     #[error("Time error: {0}")]
     Time(String),
@@ -51,4 +55,9 @@ impl AugurError {
     pub fn invalid_parameter(msg: impl Into<String>) -> Self {
         Self::InvalidParameter(msg.into())
     }
+
+    /// Creates a HistoryTooLong error.
+    pub fn history_too_long(msg: impl Into<String>) -> Self {
+        Self::HistoryTooLong(msg.into())
+    }
 }
@@ -1,7 +1,11 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::{AugurError, WU_PER_BYTE};
 
 /// Represents a complete fee estimate with predictions for various block targets
 /// and confidence levels.
@@ -9,6 +13,11 @@ use std::fmt;
 /// This struct contains fee rate estimates organized by confirmation target (in blocks)
 /// and confidence level (as a probability between 0.0 and 1.0).
 ///
+/// Whole block targets are keyed by their number of blocks in [`estimates`](Self::estimates).
+/// Targets configured with a fractional number of blocks, such as 6.5, keep their own estimate
+/// in [`fractional_estimates`](Self::fractional_estimates) instead of replacing the whole one.
+/// [`targets`](Self::targets) and the other [`BlockTargetKey`] accessors see both.
+///
 /// # Example
 /// ```
 /// use bitcoin_augur::FeeEstimate;
@@ -29,9 +38,14 @@ use std::fmt;
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeeEstimate {
-    /// Map of block targets to their respective BlockTarget estimates
+    /// Map of whole block targets to their respective BlockTarget estimates
     pub estimates: BTreeMap<u32, BlockTarget>,
 
+    /// Map of fractional block targets, as configured on the estimator, to their respective
+    /// BlockTarget estimates
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fractional_estimates: BTreeMap<BlockTargetKey, BlockTarget>,
+
     /// When this estimate was calculated
     pub timestamp: DateTime<Utc>,
 }
@@ -41,18 +55,84 @@ impl FeeEstimate {
     pub fn new(estimates: BTreeMap<u32, BlockTarget>, timestamp: DateTime<Utc>) -> Self {
         Self {
             estimates,
+            fractional_estimates: BTreeMap::new(),
             timestamp,
         }
     }
 
+    /// Creates a new fee estimate from block targets, given as [`BlockTargetKey`]s or whole
+    /// blocks, and their estimates.
+    ///
+    /// # Example
+    /// ```
+    /// use bitcoin_augur::{BlockTarget, BlockTargetKey, FeeEstimate};
+    /// use chrono::Utc;
+    ///
+    /// let key = BlockTargetKey::try_from(6.5).unwrap();
+    /// let target = |rate| BlockTarget::from_fee_rates(6, [(0.5, rate)]);
+    /// let estimate = FeeEstimate::from_targets(
+    ///     [(BlockTargetKey::from(6), target(5.0)), (key, target(4.0))],
+    ///     Utc::now(),
+    /// );
+    ///
+    /// assert_eq!(estimate.get_fee_rate(6, 0.5), Some(5.0));
+    /// assert_eq!(estimate.get_target_fee_rate(key, 0.5), Some(4.0));
+    /// ```
+    pub fn from_targets<K: Into<BlockTargetKey>>(
+        estimates: impl IntoIterator<Item = (K, BlockTarget)>,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let mut estimate = Self::empty(timestamp);
+        for (target, block_target) in estimates {
+            estimate.insert_target(target, block_target);
+        }
+        estimate
+    }
+
     /// Creates an empty fee estimate with no estimates available.
     pub fn empty(timestamp: DateTime<Utc>) -> Self {
-        Self {
-            estimates: BTreeMap::new(),
-            timestamp,
+        Self::new(BTreeMap::new(), timestamp)
+    }
+
+    /// Whether no block target has an estimate, whole or fractional.
+    pub fn is_empty(&self) -> bool {
+        self.estimates.is_empty() && self.fractional_estimates.is_empty()
+    }
+
+    /// Adds or replaces the estimate for a block target, whole or fractional.
+    pub fn insert_target(&mut self, target: impl Into<BlockTargetKey>, block_target: BlockTarget) {
+        let target = target.into();
+        if target.is_whole() {
+            self.estimates.insert(target.blocks(), block_target);
+        } else {
+            self.fractional_estimates.insert(target, block_target);
         }
     }
 
+    /// Keeps only the block targets, whole or fractional, for which `keep` returns true.
+    pub fn retain_targets(&mut self, mut keep: impl FnMut(BlockTargetKey, &BlockTarget) -> bool) {
+        self.estimates
+            .retain(|&blocks, target| keep(blocks.into(), target));
+        self.fractional_estimates
+            .retain(|&target, block_target| keep(target, block_target));
+    }
+
+    /// Iterates over every block target, whole or fractional, in ascending order.
+    pub fn targets(&self) -> impl Iterator<Item = (BlockTargetKey, &BlockTarget)> + '_ {
+        let mut targets: Vec<_> = self
+            .estimates
+            .iter()
+            .map(|(&blocks, target)| (BlockTargetKey::from(blocks), target))
+            .chain(
+                self.fractional_estimates
+                    .iter()
+                    .map(|(&target, block_target)| (target, block_target)),
+            )
+            .collect();
+        targets.sort_by_key(|&(target, _)| target);
+        targets.into_iter()
+    }
+
     /// Gets the recommended fee rate for a specific target block count and confidence level.
     ///
     /// # Arguments
@@ -67,6 +147,24 @@ impl FeeEstimate {
             .and_then(|target| target.get_fee_rate(probability))
     }
 
+    /// Gets the recommended fee rate for a block target, whole or fractional, and confidence
+    /// level.
+    ///
+    /// # Arguments
+    /// * `target` - The desired confirmation target, as configured on the estimator
+    /// * `probability` - The desired confidence level (between 0.0 and 1.0)
+    ///
+    /// # Returns
+    /// The fee rate in sat/vB, or None if the estimate is not available
+    pub fn get_target_fee_rate(
+        &self,
+        target: impl Into<BlockTargetKey>,
+        probability: f64,
+    ) -> Option<f64> {
+        self.get_target(target)
+            .and_then(|target| target.get_fee_rate(probability))
+    }
+
     /// Gets all fee rate estimates for a specific target block count.
     ///
     /// # Arguments
@@ -79,7 +177,24 @@ impl FeeEstimate {
         self.estimates.get(&target_blocks)
     }
 
-    /// Gets the nearest available block target to the requested target.
+    /// Gets all fee rate estimates for a block target, whole or fractional.
+    ///
+    /// # Arguments
+    /// * `target` - The desired confirmation target, as configured on the estimator
+    ///
+    /// # Returns
+    /// A BlockTarget containing fee rates for various confidence levels,
+    /// or None if no estimates are available for this target
+    pub fn get_target(&self, target: impl Into<BlockTargetKey>) -> Option<&BlockTarget> {
+        let target = target.into();
+        if target.is_whole() {
+            self.estimates.get(&target.blocks())
+        } else {
+            self.fractional_estimates.get(&target)
+        }
+    }
+
+    /// Gets the nearest available whole block target to the requested target.
     ///
     /// This is useful when the exact requested block target is not available.
     ///
@@ -88,6 +203,7 @@ impl FeeEstimate {
     ///
     /// # Returns
     /// The nearest available block target, or None if no estimates are available
+    #[deprecated(note = "fractional targets are skipped; use `nearest_target`")]
     pub fn get_nearest_block_target(&self, target_blocks: u32) -> Option<u32> {
         if self.estimates.is_empty() {
             return None;
@@ -103,15 +219,167 @@ impl FeeEstimate {
             .copied()
     }
 
-    /// Returns all available block targets in ascending order.
+    /// Gets the nearest available block target, whole or fractional, to the requested target.
+    ///
+    /// This is useful when the exact requested block target is not available. Ties go to the
+    /// shorter target.
+    ///
+    /// # Arguments
+    /// * `target` - The desired confirmation target in blocks, whole or as a key
+    ///
+    /// # Returns
+    /// The nearest available block target, or None if no estimates are available
+    pub fn nearest_target(&self, target: impl Into<BlockTargetKey>) -> Option<BlockTargetKey> {
+        let requested = target.into().target();
+        self.targets().map(|(target, _)| target).min_by(|a, b| {
+            (a.target() - requested)
+                .abs()
+                .total_cmp(&(b.target() - requested).abs())
+        })
+    }
+
+    /// Suggests a fee rate and absolute fee for a transaction that must confirm within
+    /// `deadline_blocks` with at least `confidence` probability.
+    ///
+    /// The fee rate is taken from the longest available block target within the deadline and
+    /// the lowest available confidence level at or above `confidence`, so the suggestion never
+    /// aims later or less confidently than asked.
+    ///
+    /// # Arguments
+    /// * `tx_weight` - The transaction's weight in weight units
+    /// * `deadline_blocks` - The latest acceptable confirmation, in blocks
+    /// * `confidence` - The minimum confidence level (between 0.0 and 1.0)
+    ///
+    /// # Returns
+    /// The suggestion, or None if no block target or confidence level satisfies the request
+    ///
+    /// # Example
+    /// ```
+    /// use bitcoin_augur::{BlockTarget, FeeEstimate};
+    /// use chrono::Utc;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let target = BlockTarget::from_fee_rates(6, [(0.5, 2.5), (0.95, 4.0)]);
+    /// let estimate = FeeEstimate::new(
+    ///     BTreeMap::from([(6, target)]),
+    ///     Utc::now(),
+    /// );
+    ///
+    /// // A 561 WU transaction is 141 vB
+    /// let suggestion = estimate.suggest_feerate_for(561, 10, 0.8).unwrap();
+    /// assert_eq!((suggestion.target_blocks, suggestion.confidence), (6, 0.95));
+    /// assert_eq!((suggestion.vsize, suggestion.fee), (141, 564));
+    /// ```
+    pub fn suggest_feerate_for(
+        &self,
+        tx_weight: u64,
+        deadline_blocks: u32,
+        confidence: f64,
+    ) -> Option<FeeSuggestion> {
+        let (target, block_target) = self
+            .targets()
+            .take_while(|&(target, _)| target <= BlockTargetKey::from(deadline_blocks))
+            .last()?;
+        let (probability, &fee_rate) = block_target
+            .probabilities
+            .range(OrderedFloat(confidence)..)
+            .next()?;
+
+        let vsize = tx_weight.div_ceil(WU_PER_BYTE as u64);
+        Some(FeeSuggestion {
+            target_blocks: target.blocks(),
+            target,
+            confidence: probability.0,
+            fee_rate,
+            vsize,
+            fee: (fee_rate * vsize as f64).ceil() as u64,
+        })
+    }
+
+    /// Returns the shortest whole block target whose fee rate at `probability` is at most
+    /// `fee_rate`: how soon a transaction paying `fee_rate` should confirm with that confidence.
+    ///
+    /// # Returns
+    /// The block target, or None if even the longest target needs a higher fee rate or
+    /// `probability` is not an estimated confidence level
+    ///
+    /// # Example
+    /// ```
+    /// # #![allow(deprecated)]
+    /// use bitcoin_augur::{BlockTarget, FeeEstimate};
+    /// use chrono::Utc;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let target = |blocks, rate| BlockTarget::from_fee_rates(blocks, [(0.5, rate)]);
+    /// let estimate = FeeEstimate::new(
+    ///     BTreeMap::from([(3, target(3, 10.0)), (6, target(6, 5.0)), (12, target(12, 2.0))]),
+    ///     Utc::now(),
+    /// );
+    ///
+    /// assert_eq!(estimate.blocks_for_fee_rate(6.0, 0.5), Some(6));
+    /// assert_eq!(estimate.blocks_for_fee_rate(1.0, 0.5), None);
+    /// assert_eq!(estimate.blocks_for_fee_rate(6.0, 0.95), None);
+    /// ```
+    #[deprecated(note = "fractional targets are skipped; use `target_for_fee_rate`")]
+    pub fn blocks_for_fee_rate(&self, fee_rate: f64, probability: f64) -> Option<u32> {
+        self.estimates
+            .iter()
+            .find(|(_, target)| {
+                target
+                    .get_fee_rate(probability)
+                    .is_some_and(|rate| rate <= fee_rate)
+            })
+            .map(|(&blocks, _)| blocks)
+    }
+
+    /// Returns the shortest block target, whole or fractional, whose fee rate at `probability`
+    /// is at most `fee_rate`.
+    ///
+    /// # Returns
+    /// The block target as configured, or None if even the longest target needs a higher fee
+    /// rate or `probability` is not an estimated confidence level
+    ///
+    /// # Example
+    /// ```
+    /// use bitcoin_augur::{BlockTarget, BlockTargetKey, FeeEstimate};
+    /// use chrono::Utc;
+    ///
+    /// let key = |target| BlockTargetKey::try_from(target).unwrap();
+    /// let target = |blocks, rate| BlockTarget::from_fee_rates(blocks, [(0.5, rate)]);
+    /// let estimate = FeeEstimate::from_targets(
+    ///     [(key(3.0), target(3, 10.0)), (key(6.5), target(6, 5.0)), (key(12.0), target(12, 2.0))],
+    ///     Utc::now(),
+    /// );
+    ///
+    /// assert_eq!(estimate.target_for_fee_rate(6.0, 0.5), Some(key(6.5)));
+    /// assert_eq!(estimate.target_for_fee_rate(1.0, 0.5), None);
+    /// assert_eq!(estimate.target_for_fee_rate(6.0, 0.95), None);
+    /// ```
+    pub fn target_for_fee_rate(&self, fee_rate: f64, probability: f64) -> Option<BlockTargetKey> {
+        self.targets()
+            .find(|(_, target)| {
+                target
+                    .get_fee_rate(probability)
+                    .is_some_and(|rate| rate <= fee_rate)
+            })
+            .map(|(target, _)| target)
+    }
+
+    /// Returns all available whole block targets in ascending order.
+    #[deprecated(note = "fractional targets are skipped; use `available_targets`")]
     pub fn get_available_block_targets(&self) -> Vec<u32> {
         self.estimates.keys().copied().collect()
     }
 
+    /// Returns all available block targets, whole or fractional, in ascending order.
+    pub fn available_targets(&self) -> Vec<BlockTargetKey> {
+        self.targets().map(|(target, _)| target).collect()
+    }
+
     /// Returns all available confidence levels in ascending order.
     pub fn get_available_confidence_levels(&self) -> Vec<f64> {
         let mut levels = std::collections::HashSet::new();
-        for target in self.estimates.values() {
+        for (_, target) in self.targets() {
             for prob in target.probabilities.keys() {
                 levels.insert(prob.0.to_bits()); // Use to_bits on the inner f64
             }
@@ -127,7 +395,7 @@ impl fmt::Display for FeeEstimate {
     ///
     /// The table shows all block targets as rows and all confidence levels as columns.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.estimates.is_empty() {
+        if self.is_empty() {
             return Ok(());
         }
 
@@ -142,7 +410,7 @@ impl fmt::Display for FeeEstimate {
         writeln!(f)?;
 
         // Data rows
-        for (blocks, target) in &self.estimates {
+        for (blocks, target) in self.targets() {
             write!(f, "{:10}", blocks)?;
             for prob in &probabilities {
                 if let Some(fee_rate) = target.get_fee_rate(*prob) {
@@ -158,6 +426,30 @@ impl fmt::Display for FeeEstimate {
     }
 }
 
+/// A fee rate and the resulting absolute fee for a transaction of a given size.
+///
+/// Created by [`FeeEstimate::suggest_feerate_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeeSuggestion {
+    /// The block target the fee rate was taken from, in whole blocks
+    pub target_blocks: u32,
+
+    /// The block target the fee rate was taken from, as configured
+    pub target: BlockTargetKey,
+
+    /// The confidence level the fee rate was taken from
+    pub confidence: f64,
+
+    /// Fee rate in sat/vB
+    pub fee_rate: f64,
+
+    /// Transaction size in virtual bytes, rounded up from its weight
+    pub vsize: u64,
+
+    /// Absolute fee in satoshis, rounded up
+    pub fee: u64,
+}
+
 /// Represents fee estimates for a specific block target with multiple confidence levels.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockTarget {
@@ -168,17 +460,69 @@ pub struct BlockTarget {
     /// Key: probability (0.0 to 1.0)
     /// Value: fee rate in sat/vB
     pub probabilities: BTreeMap<OrderedFloat, f64>,
+
+    /// Confidence levels whose fee rate was clamped to the estimator's maximum fee rate
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub capped: BTreeSet<OrderedFloat>,
+
+    /// Confidence levels whose fee rate was raised to keep the estimator's minimum spread above
+    /// the next lower level
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub widened: BTreeSet<OrderedFloat>,
 }
 
 impl BlockTarget {
+    /// Shortest target the mining simulation estimates; shorter ones use the fast model.
+    pub const MIN_SIMULATED_BLOCKS: u32 = 3;
+
     /// Creates a new block target.
     pub fn new(blocks: u32, probabilities: BTreeMap<OrderedFloat, f64>) -> Self {
         Self {
             blocks,
             probabilities,
+            capped: BTreeSet::new(),
+            widened: BTreeSet::new(),
         }
     }
 
+    /// Creates a new block target from `(confidence level, fee rate)` pairs.
+    ///
+    /// # Example
+    /// ```
+    /// use bitcoin_augur::BlockTarget;
+    ///
+    /// let target = BlockTarget::from_fee_rates(6, [(0.5, 2.5), (0.95, 4.0)]);
+    /// assert_eq!(target.get_fee_rate(0.95), Some(4.0));
+    /// ```
+    pub fn from_fee_rates(blocks: u32, fee_rates: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        let probabilities = fee_rates
+            .into_iter()
+            .map(|(probability, fee_rate)| (OrderedFloat(probability), fee_rate))
+            .collect();
+        Self::new(blocks, probabilities)
+    }
+
+    /// Iterates over `(confidence level, fee rate)` pairs by ascending confidence level.
+    ///
+    /// # Example
+    /// ```
+    /// use bitcoin_augur::BlockTarget;
+    ///
+    /// let target = BlockTarget::from_fee_rates(6, [(0.95, 4.0), (0.5, 2.5)]);
+    /// let fee_rates: Vec<(f64, f64)> = target.fee_rates().collect();
+    /// assert_eq!(fee_rates, [(0.5, 2.5), (0.95, 4.0)]);
+    /// ```
+    pub fn fee_rates(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.probabilities
+            .iter()
+            .map(|(probability, &fee_rate)| (probability.0, fee_rate))
+    }
+
+    /// Iterates over the estimated confidence levels in ascending order.
+    pub fn confidence_levels(&self) -> impl Iterator<Item = f64> + '_ {
+        self.probabilities.keys().map(|probability| probability.0)
+    }
+
     /// Gets the fee rate for a specific confidence level.
     ///
     /// # Arguments
@@ -189,14 +533,224 @@ impl BlockTarget {
     pub fn get_fee_rate(&self, probability: f64) -> Option<f64> {
         self.probabilities.get(&OrderedFloat(probability)).copied()
     }
+
+    /// Whether the fee rate for a confidence level was clamped to the maximum fee rate.
+    pub fn is_capped(&self, probability: f64) -> bool {
+        self.capped.contains(&OrderedFloat(probability))
+    }
+
+    /// Whether the fee rate for a confidence level was raised to keep the minimum spread.
+    pub fn is_widened(&self, probability: f64) -> bool {
+        self.widened.contains(&OrderedFloat(probability))
+    }
+
+    /// Whether the target is too short for the mining simulation to smooth out chance.
+    ///
+    /// Whether one or two blocks arrive quickly or slowly dominates their outcome, so these
+    /// estimates vary far more between updates and miss more often than longer targets.
+    pub fn is_high_variance(&self) -> bool {
+        self.blocks < Self::MIN_SIMULATED_BLOCKS
+    }
+}
+
+/// A block target as configured on the estimator, whole or fractional.
+///
+/// Keys [`FeeEstimate::fractional_estimates`] and the target accessors such as
+/// [`FeeEstimate::targets`].
+///
+/// Targets keep the `f64` they were configured with, so a 6.5 block target neither replaces
+/// nor hides behind the 6 block one. Whole blocks convert with [`From<u32>`], and `f64` targets
+/// with [`TryFrom<f64>`], which rejects NaN, infinite and negative values. A whole number of
+/// blocks gives the same key either way, and every other value keys exactly the target
+/// configured with that `f64`. [`blocks`](Self::blocks) is the whole number of blocks a target
+/// confirms within, rounding fractional targets down.
+///
+/// Keys serialize as their [`Display`](fmt::Display) form, such as `"6"` or `"6.5"`.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::BlockTargetKey;
+///
+/// assert_eq!(BlockTargetKey::from(6), BlockTargetKey::try_from(6.0).unwrap());
+/// let key = BlockTargetKey::try_from(6.5).unwrap();
+/// assert_eq!((key.target(), key.blocks()), (6.5, 6));
+/// assert_eq!(key.to_string(), "6.5");
+/// assert!(BlockTargetKey::from(6) < key);
+/// assert!(BlockTargetKey::try_from(f64::NAN).is_err());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BlockTargetKey(f64);
+
+impl BlockTargetKey {
+    /// The target in blocks, as configured.
+    pub fn target(self) -> f64 {
+        self.0
+    }
+
+    /// The whole number of blocks the target confirms within.
+    pub fn blocks(self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Whether the target is a whole number of blocks.
+    pub fn is_whole(self) -> bool {
+        self.0.fract() == 0.0
+    }
+}
+
+impl From<u32> for BlockTargetKey {
+    fn from(blocks: u32) -> Self {
+        Self(f64::from(blocks))
+    }
+}
+
+impl TryFrom<f64> for BlockTargetKey {
+    type Error = AugurError;
+
+    fn try_from(target: f64) -> Result<Self, Self::Error> {
+        if !target.is_finite() || target < 0.0 {
+            return Err(AugurError::invalid_parameter(format!(
+                "Block target must be a finite, non-negative number of blocks, got {target}"
+            )));
+        }
+        Ok(Self(target))
+    }
+}
+
+impl From<BlockTargetKey> for f64 {
+    fn from(key: BlockTargetKey) -> Self {
+        key.0
+    }
+}
+
+impl PartialEq for BlockTargetKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for BlockTargetKey {}
+
+impl PartialOrd for BlockTargetKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BlockTargetKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for BlockTargetKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl PartialEq<u32> for BlockTargetKey {
+    fn eq(&self, blocks: &u32) -> bool {
+        *self == Self::from(*blocks)
+    }
+}
+
+impl PartialEq<f64> for BlockTargetKey {
+    fn eq(&self, target: &f64) -> bool {
+        self.0 == *target
+    }
+}
+
+impl fmt::Display for BlockTargetKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Serialize for BlockTargetKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockTargetKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl de::Visitor<'_> for KeyVisitor {
+            type Value = BlockTargetKey;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a block target")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|target| BlockTargetKey::try_from(target).ok())
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                Ok(BlockTargetKey(value as f64))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+                BlockTargetKey::try_from(value)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Float(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(KeyVisitor)
+    }
 }
 
 /// A wrapper around f64 that implements Ord for use in BTreeMap.
 /// This is safe for our use case as we only use valid probability values (0.0 to 1.0).
+///
+/// Keys compare exactly, with no tolerance: a confidence level is found with the very `f64` the
+/// estimator was configured with, so 0.95 finds 0.95 but not 0.9500001. NaN compares equal to
+/// every value and must not be used as a key. Converts to and from `f64` with [`From`], and
+/// [`BlockTarget::fee_rates`] reads a target's estimates without naming the wrapper at all.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::OrderedFloat;
+///
+/// let confidence = OrderedFloat::from(0.95);
+/// assert_eq!(f64::from(confidence), 0.95);
+/// assert_eq!(confidence.get(), 0.95);
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct OrderedFloat(pub f64);
 
+impl OrderedFloat {
+    /// The wrapped value.
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl From<f64> for OrderedFloat {
+    fn from(value: f64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<OrderedFloat> for f64 {
+    fn from(value: OrderedFloat) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for OrderedFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
 impl Eq for OrderedFloat {}
 
 impl PartialOrd for OrderedFloat {
@@ -244,6 +798,68 @@ mod tests {
     }
 
     #[test]
+    fn test_plain_f64_accessors() {
+        let target = BlockTarget::from_fee_rates(6, [(0.95, 10.0), (0.05, 2.0), (0.5, 5.0)]);
+        assert_eq!(
+            target.fee_rates().collect::<Vec<_>>(),
+            [(0.05, 2.0), (0.5, 5.0), (0.95, 10.0)]
+        );
+        assert_eq!(
+            target.confidence_levels().collect::<Vec<_>>(),
+            [0.05, 0.5, 0.95]
+        );
+        assert_eq!(target.probabilities[&OrderedFloat::from(0.5)], 5.0);
+
+        // Lookups are exact
+        assert_eq!(target.get_fee_rate(0.5), Some(5.0));
+        assert_eq!(target.get_fee_rate(0.5 + 1e-12), None);
+
+        let confidence: OrderedFloat = 0.95.into();
+        assert_eq!(f64::from(confidence), 0.95);
+        assert_eq!(confidence.to_string(), "0.95");
+    }
+
+    #[test]
+    fn test_suggest_feerate_for() {
+        let probabilities = |rates: [f64; 3]| {
+            BTreeMap::from([
+                (OrderedFloat(0.2), rates[0]),
+                (OrderedFloat(0.5), rates[1]),
+                (OrderedFloat(0.95), rates[2]),
+            ])
+        };
+        let mut estimates = BTreeMap::new();
+        estimates.insert(3, BlockTarget::new(3, probabilities([4.0, 6.0, 9.0])));
+        estimates.insert(6, BlockTarget::new(6, probabilities([2.0, 3.5, 5.25])));
+        let estimate = FeeEstimate::new(estimates, Utc::now());
+
+        // Exact target and confidence; 400 WU is 100 vB
+        let suggestion = estimate.suggest_feerate_for(400, 6, 0.5).unwrap();
+        assert_eq!(suggestion.target_blocks, 6);
+        assert_eq!(suggestion.confidence, 0.5);
+        assert_eq!(suggestion.fee_rate, 3.5);
+        assert_eq!(suggestion.vsize, 100);
+        assert_eq!(suggestion.fee, 350);
+
+        // Deadline between targets uses the earlier one, confidence rounds up
+        let suggestion = estimate.suggest_feerate_for(401, 5, 0.6).unwrap();
+        assert_eq!(suggestion.target_blocks, 3);
+        assert_eq!(suggestion.confidence, 0.95);
+        assert_eq!(suggestion.vsize, 101);
+        assert_eq!(suggestion.fee, 909);
+
+        // Fractional fees round up to whole satoshis
+        let suggestion = estimate.suggest_feerate_for(4 * 141, 144, 0.95).unwrap();
+        assert_eq!(suggestion.target_blocks, 6);
+        assert_eq!(suggestion.fee, 741);
+
+        // Nothing within the deadline or at the confidence
+        assert!(estimate.suggest_feerate_for(400, 2, 0.5).is_none());
+        assert!(estimate.suggest_feerate_for(400, 6, 0.99).is_none());
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn test_get_nearest_block_target() {
         let mut estimates = BTreeMap::new();
         estimates.insert(3, BlockTarget::new(3, BTreeMap::new()));
@@ -257,4 +873,80 @@ mod tests {
         assert_eq!(fee_estimate.get_nearest_block_target(10), Some(12));
         assert_eq!(fee_estimate.get_nearest_block_target(1), Some(3));
     }
+
+    #[test]
+    fn test_fractional_block_targets() {
+        let key = |target: f64| BlockTargetKey::try_from(target).unwrap();
+        let target = |blocks, rate| BlockTarget::from_fee_rates(blocks, [(0.5, rate)]);
+        let estimate = FeeEstimate::from_targets(
+            [
+                (key(6.0), target(6, 5.0)),
+                (key(6.5), target(6, 4.0)),
+                (key(12.0), target(12, 2.0)),
+            ],
+            Utc::now(),
+        );
+
+        // 6.5 keeps its own estimate instead of replacing the 6 block one
+        assert_eq!(estimate.estimates.len(), 2);
+        assert_eq!(estimate.fractional_estimates.len(), 1);
+        assert_eq!(estimate.get_fee_rate(6, 0.5), Some(5.0));
+        assert_eq!(estimate.get_target_fee_rate(6, 0.5), Some(5.0));
+        assert_eq!(estimate.get_target_fee_rate(key(6.5), 0.5), Some(4.0));
+        assert_eq!(estimate.available_targets(), [6.0, 6.5, 12.0]);
+        assert_eq!(estimate.nearest_target(7), Some(key(6.5)));
+        assert_eq!(estimate.nearest_target(key(9.25)), Some(key(6.5)));
+
+        // Suggestions report the target as configured and in whole blocks
+        let suggestion = estimate.suggest_feerate_for(400, 7, 0.5).unwrap();
+        assert_eq!(
+            (
+                suggestion.target,
+                suggestion.target_blocks,
+                suggestion.fee_rate
+            ),
+            (key(6.5), 6, 4.0)
+        );
+        assert_eq!(estimate.target_for_fee_rate(4.5, 0.5), Some(key(6.5)));
+
+        // The whole block accessors only see whole targets
+        #[allow(deprecated)]
+        {
+            assert_eq!(estimate.get_available_block_targets(), vec![6, 12]);
+            assert_eq!(estimate.blocks_for_fee_rate(4.5, 0.5), Some(12));
+            assert_eq!(estimate.get_nearest_block_target(7), Some(6));
+        }
+
+        // Whole targets serialize as before, fractional ones alongside
+        let json = serde_json::to_value(&estimate).unwrap();
+        let keys: Vec<&String> = json["estimates"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["12", "6"]);
+        assert!(json["fractional_estimates"]["6.5"].is_object());
+        let restored: FeeEstimate = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.available_targets(), estimate.available_targets());
+
+        let mut whole_only = estimate.clone();
+        whole_only.retain_targets(|target, _| target.is_whole());
+        let json = serde_json::to_value(&whole_only).unwrap();
+        assert!(json.get("fractional_estimates").is_none());
+    }
+
+    #[test]
+    fn test_invalid_block_targets_are_rejected() {
+        for target in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -1.0] {
+            assert!(BlockTargetKey::try_from(target).is_err(), "{target}");
+        }
+        assert_eq!(BlockTargetKey::try_from(0.0).unwrap(), 0);
+
+        for key in ["\"NaN\"", "\"inf\"", "\"-6\"", "\"six\""] {
+            assert!(
+                serde_json::from_str::<BlockTargetKey>(key).is_err(),
+                "{key}"
+            );
+        }
+        assert_eq!(
+            serde_json::from_str::<BlockTargetKey>("\"6.5\"").unwrap(),
+            6.5
+        );
+    }
 }
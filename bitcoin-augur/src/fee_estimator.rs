@@ -1,11 +1,17 @@
-use chrono::{Duration, Utc};
-use std::collections::BTreeMap;
+use chrono::{DateTime, Duration, Utc};
+use ndarray::{Array1, Array2};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, PoisonError};
 
 use crate::{
     error::{AugurError, Result},
-    fee_estimate::{BlockTarget, FeeEstimate, OrderedFloat},
-    internal::{FeeCalculator, InflowCalculator, SnapshotArray},
-    MempoolSnapshot,
+    fee_estimate::{BlockTarget, BlockTargetKey, FeeEstimate, OrderedFloat},
+    internal::{EstimateCache, FeeCalculator, InflowCalculator, SnapshotArray},
+    simulation::{valid_fee_rate, Simulation, SimulationRun},
+    sources::{group_by_source, SourceEstimates},
+    stages::{bucket_map, grid_rows, EstimateTrace},
+    BucketWeighting, HistoryLimit, MempoolSnapshot, MonteCarlo, OutlierFilter, RoundingPolicy,
+    SeasonalProfile, TargetWindows,
 };
 
 /// The main entry point for calculating Bitcoin fee estimates.
@@ -39,11 +45,31 @@ use crate::{
 ///     println!("Recommended fee rate for 6 blocks at 95% confidence: {:.2} sat/vB", fee_rate);
 /// }
 /// ```
+#[derive(Clone)]
 pub struct FeeEstimator {
     probabilities: Vec<f64>,
     block_targets: Vec<f64>,
     short_term_window: Duration,
     long_term_window: Duration,
+    rounding: RoundingPolicy,
+    outlier_filter: Option<OutlierFilter>,
+    fast_targets: bool,
+    max_fee_rate: Option<f64>,
+    /// Smallest relative step between the fee rates of adjacent confidence levels
+    min_spread: Option<f64>,
+    /// Seasonal profile and how strongly it adjusts long-term inflows
+    seasonality: Option<(SeasonalProfile, f64)>,
+    /// Inflow windows overriding the default pair for ranges of targets
+    target_windows: Vec<TargetWindows>,
+    /// Converted snapshots and their growth shared by clones, when inflows are computed
+    /// incrementally
+    inflow_cache: Option<Arc<Mutex<EstimateCache>>>,
+    /// Bounds on the history a single estimate reads
+    max_history: Option<HistoryLimit>,
+    /// What each bucket's share of the mempool is measured in
+    weighting: BucketWeighting,
+    /// Sampled block arrivals replacing the expected number of blocks, if set
+    monte_carlo: Option<MonteCarlo>,
     #[allow(dead_code)]
     calculator: FeeCalculator,
 }
@@ -57,6 +83,9 @@ impl FeeEstimator {
     /// Default confidence levels for fee estimation (5%, 20%, 50%, 80%, 95%).
     pub const DEFAULT_PROBABILITIES: &'static [f64] = &[0.05, 0.20, 0.50, 0.80, 0.95];
 
+    /// Block targets added by [`with_fast_targets`](Self::with_fast_targets) (1, 2 blocks).
+    pub const FAST_BLOCK_TARGETS: &'static [f64] = &[1.0, 2.0];
+
     /// Creates a new FeeEstimator with default settings.
     ///
     /// Default settings:
@@ -110,20 +139,289 @@ impl FeeEstimator {
             block_targets,
             short_term_window,
             long_term_window,
+            rounding: RoundingPolicy::default(),
+            outlier_filter: None,
+            fast_targets: false,
+            max_fee_rate: None,
+            min_spread: None,
+            seasonality: None,
+            target_windows: Vec::new(),
+            inflow_cache: None,
+            max_history: None,
+            weighting: BucketWeighting::default(),
+            monte_carlo: None,
             calculator,
         })
     }
 
+    /// The confidence levels estimated for each target.
+    pub fn probabilities(&self) -> &[f64] {
+        &self.probabilities
+    }
+
+    /// The block targets estimated, besides the [fast targets](Self::with_fast_targets).
+    pub fn block_targets(&self) -> &[f64] {
+        &self.block_targets
+    }
+
+    /// The short-term inflow window of targets without [target windows](Self::with_target_windows).
+    pub fn short_term_window(&self) -> Duration {
+        self.short_term_window
+    }
+
+    /// The long-term inflow window of targets without [target windows](Self::with_target_windows).
+    pub fn long_term_window(&self) -> Duration {
+        self.long_term_window
+    }
+
+    /// Sets how fee rates are rounded in the returned estimates (exact by default).
+    pub fn with_rounding(mut self, rounding: RoundingPolicy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// The rounding policy applied to every estimate.
+    pub fn rounding(&self) -> RoundingPolicy {
+        self.rounding
+    }
+
+    /// Clamps every estimate to at most `max_fee_rate` sat/vB (uncapped by default).
+    ///
+    /// A safety net against anomalous mempool data: clamped fee rates are listed in
+    /// [`BlockTarget::capped`]. The cap is applied after rounding.
+    pub fn with_max_fee_rate(mut self, max_fee_rate: f64) -> Result<Self> {
+        if !max_fee_rate.is_finite() || max_fee_rate < 1.0 {
+            return Err(AugurError::invalid_config(
+                "Maximum fee rate must be at least 1 sat/vB",
+            ));
+        }
+        self.max_fee_rate = Some(max_fee_rate);
+        Ok(self)
+    }
+
+    /// The highest fee rate any estimate may ask for, if capped.
+    pub fn max_fee_rate(&self) -> Option<f64> {
+        self.max_fee_rate
+    }
+
+    /// Keeps each confidence level's fee rate at least `min_spread` (a fraction, such as 0.02
+    /// for 2%) above the next lower level's, for the same target (no spread by default).
+    ///
+    /// After monotonicity, adjacent confidence levels often share a fee rate, which a UI
+    /// offering a few options shows as identical choices. Raised fee rates are listed in
+    /// [`BlockTarget::widened`]. The spread is applied after rounding, rounding the raised fee
+    /// rate again, and before the maximum fee rate caps it.
+    pub fn with_min_spread(mut self, min_spread: f64) -> Result<Self> {
+        if !(min_spread > 0.0 && min_spread <= 1.0) {
+            return Err(AugurError::invalid_config(
+                "Minimum spread must be above 0 and at most 1",
+            ));
+        }
+        self.min_spread = Some(min_spread);
+        Ok(self)
+    }
+
+    /// The smallest relative step between adjacent confidence levels' fee rates, if any.
+    pub fn min_spread(&self) -> Option<f64> {
+        self.min_spread
+    }
+
+    /// Mines sampled block arrival sequences instead of the expected number of blocks
+    /// (deterministic by default).
+    ///
+    /// See [`MonteCarlo`] for how the sequences are drawn and turned into estimates.
+    /// [Fast targets](Self::with_fast_targets) and [`simulate`](Self::simulate) keep mining a
+    /// fixed number of blocks.
+    pub fn with_monte_carlo(mut self, monte_carlo: MonteCarlo) -> Self {
+        self.monte_carlo = Some(monte_carlo);
+        self
+    }
+
+    /// The Monte Carlo simulation settings, if block arrivals are sampled.
+    pub fn monte_carlo(&self) -> Option<MonteCarlo> {
+        self.monte_carlo
+    }
+
+    /// Excludes snapshots judged outliers by `filter` before estimating (none by default).
+    pub fn with_outlier_filter(mut self, filter: OutlierFilter) -> Self {
+        self.outlier_filter = Some(filter);
+        self
+    }
+
+    /// The outlier filter applied to snapshots, if any.
+    pub fn outlier_filter(&self) -> Option<OutlierFilter> {
+        self.outlier_filter
+    }
+
+    /// Also estimates targets of 1 and 2 blocks (off by default).
+    ///
+    /// These targets skip the mining simulation: exactly one or two blocks are mined from the
+    /// current mempool, with only short-term inflow arriving in the meantime. Their estimates
+    /// never ask less than the shortest simulated target and are flagged by
+    /// [`BlockTarget::is_high_variance`].
+    pub fn with_fast_targets(mut self) -> Self {
+        self.fast_targets = true;
+        self
+    }
+
+    /// Whether targets of 1 and 2 blocks are estimated.
+    pub fn has_fast_targets(&self) -> bool {
+        self.fast_targets
+    }
+
+    /// Keeps each block's snapshot growth between calls to compute inflows incrementally
+    /// (off by default).
+    ///
+    /// Estimating again after one more snapshot then only converts that snapshot and compares
+    /// the snapshots of its block, which saves most of the work when estimating on every
+    /// collection. Estimates are unchanged. It also enables
+    /// [`recompute_after_block`](Self::recompute_after_block).
+    /// Snapshots are recognised by block height and timestamp, so call
+    /// [`clear_inflow_cache`](Self::clear_inflow_cache) if stored snapshots are replaced. Clones
+    /// of the estimator share the cache.
+    pub fn with_incremental_inflows(mut self) -> Self {
+        self.inflow_cache = Some(Arc::default());
+        self
+    }
+
+    /// Whether inflows are computed incrementally.
+    pub fn has_incremental_inflows(&self) -> bool {
+        self.inflow_cache.is_some()
+    }
+
+    /// Forgets the snapshots and growth kept for incremental inflows.
+    pub fn clear_inflow_cache(&self) {
+        if let Some(cache) = &self.inflow_cache {
+            cache.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+    }
+
+    /// Bounds the snapshot history a single estimate may read (unbounded by default).
+    ///
+    /// Guards against accidentally estimating from months of snapshots: histories over the
+    /// limit fail with [`AugurError::HistoryTooLong`], or lose their oldest snapshots if the
+    /// limit is [`truncating`](HistoryLimit::truncating).
+    pub fn with_max_history(mut self, limit: HistoryLimit) -> Self {
+        self.max_history = Some(limit);
+        self
+    }
+
+    /// The bounds on the snapshot history of an estimate, if any.
+    pub fn max_history(&self) -> Option<HistoryLimit> {
+        self.max_history
+    }
+
+    /// Sets what each fee rate bucket's share of the mempool is measured in (weight by default).
+    ///
+    /// Cheap enough to call per request on a clone of a configured estimator. A clone switched
+    /// to another weighting keeps incremental inflows but stops sharing the original's cache,
+    /// whose growth was measured the original way.
+    pub fn with_weighting(mut self, weighting: BucketWeighting) -> Self {
+        if weighting != self.weighting && self.inflow_cache.is_some() {
+            self.inflow_cache = Some(Arc::default());
+        }
+        self.weighting = weighting;
+        self
+    }
+
+    /// What each fee rate bucket's share of the mempool is measured in.
+    pub fn weighting(&self) -> BucketWeighting {
+        self.weighting
+    }
+
+    /// Blends the seasonal outlook of `profile` into the long-term inflows (none by default).
+    ///
+    /// Long-term inflows average the whole long-term window, which overstates inflow ahead of a
+    /// quiet period such as a weekend and understates it ahead of a busy one. The profile's
+    /// [`adjustment`](SeasonalProfile::adjustment) from the long-term window to the longest
+    /// target scales them by `1 - blend + blend * adjustment`.
+    ///
+    /// # Arguments
+    /// * `profile` - Inflow by hour of the week, ideally from several weeks of history
+    /// * `blend` - How much of the seasonal adjustment to apply (between 0.0 and 1.0)
+    pub fn with_seasonality(mut self, profile: SeasonalProfile, blend: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&blend) {
+            return Err(AugurError::invalid_config(
+                "Seasonal blend must be between 0.0 and 1.0",
+            ));
+        }
+        self.seasonality = Some((profile, blend));
+        Ok(self)
+    }
+
+    /// The seasonal profile and blend applied to long-term inflows, if any.
+    pub fn seasonality(&self) -> Option<(&SeasonalProfile, f64)> {
+        self.seasonality
+            .as_ref()
+            .map(|(profile, blend)| (profile, *blend))
+    }
+
+    /// Measures inflows over different windows for ranges of block targets (none by default).
+    ///
+    /// Targets covered by none of the ranges use the windows the estimator was created with.
+    /// Each group of targets sharing windows is simulated separately, and fee rates are then
+    /// kept from rising with the target across groups.
+    ///
+    /// # Errors
+    /// If two ranges share a target
+    pub fn with_target_windows(mut self, target_windows: Vec<TargetWindows>) -> Result<Self> {
+        for (i, windows) in target_windows.iter().enumerate() {
+            if target_windows[..i]
+                .iter()
+                .any(|other| other.overlaps(windows))
+            {
+                return Err(AugurError::invalid_config(
+                    "Target window ranges must not overlap",
+                ));
+            }
+        }
+        self.target_windows = target_windows;
+        Ok(self)
+    }
+
+    /// The inflow windows configured for ranges of targets.
+    pub fn target_windows(&self) -> &[TargetWindows] {
+        &self.target_windows
+    }
+
+    /// The short- and long-term inflow windows used for `target`.
+    pub fn windows_for(&self, target: f64) -> (Duration, Duration) {
+        self.target_windows
+            .iter()
+            .find(|windows| windows.contains(target))
+            .map_or((self.short_term_window, self.long_term_window), |windows| {
+                (windows.short_term(), windows.long_term())
+            })
+    }
+
+    /// How far back the estimator's inflow windows reach, for all targets.
+    ///
+    /// Snapshots older than this before the newest one do not affect estimates.
+    pub fn history_window(&self) -> Duration {
+        self.target_windows
+            .iter()
+            .flat_map(|windows| [windows.short_term(), windows.long_term()])
+            .fold(
+                self.short_term_window.max(self.long_term_window),
+                Duration::max,
+            )
+    }
+
     /// Calculates fee estimates based on historical mempool snapshots.
     ///
     /// This method analyzes the provided mempool snapshots to generate fee estimates
     /// for each block target and confidence level.
     ///
+    /// The short- and long-term windows are measured back from the newest snapshot's
+    /// timestamp rather than the wall clock, so snapshots recorded under a simulated
+    /// clock are windowed exactly like live ones.
+    ///
     /// # Arguments
     /// * `snapshots` - A slice of historical mempool snapshots, ideally covering
     ///   at least the past 24 hours.
     /// * `num_blocks` - Optional specific block target to estimate for.
-    ///   If provided, must be at least 3.0 (we can't simulate partial blocks).
+    ///   If provided, must be at least 3.0 (we can't simulate partial blocks),
+    ///   or exactly 1.0 or 2.0 with [`with_fast_targets`](Self::with_fast_targets).
     ///
     /// # Returns
     /// A `FeeEstimate` object containing the calculated estimates, or an error if
@@ -132,66 +430,579 @@ impl FeeEstimator {
         &self,
         snapshots: &[MempoolSnapshot],
         num_blocks: Option<f64>,
+    ) -> Result<FeeEstimate> {
+        self.estimate(snapshots, num_blocks, false)
+    }
+
+    /// Recalculates fee estimates after the newest snapshot, the first of a new block, was
+    /// added to the snapshots of the previous estimate.
+    ///
+    /// Requires [`with_incremental_inflows`](Self::with_incremental_inflows). The long-term
+    /// inflows of the previous estimate are reused, and only the mempool and short-term inflows
+    /// are derived again. A block's first snapshot has no growth of its own, so the long-term
+    /// inflows only miss the collection interval the window moved by, which matters little
+    /// over a long-term window of many hours.
+    ///
+    /// Falls back to [`calculate_estimates`](Self::calculate_estimates) when the snapshots
+    /// before the newest one do not end where the previous estimate did, or the newest one is
+    /// not from a later block.
+    ///
+    /// # Arguments
+    /// * `snapshots` - The previous estimate's snapshots followed by the new block's first
+    /// * `num_blocks` - Block target, as for `calculate_estimates`
+    pub fn recompute_after_block(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> Result<FeeEstimate> {
+        if self.inflow_cache.is_none() {
+            return Err(AugurError::invalid_config(
+                "Recomputing after a block requires incremental inflows",
+            ));
+        }
+        self.estimate(snapshots, num_blocks, true)
+    }
+
+    /// Calculates fee estimates, reusing the previous estimate's long-term inflows if
+    /// `after_block`.
+    fn estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+        after_block: bool,
     ) -> Result<FeeEstimate> {
         // Validate num_blocks if specified
         if let Some(blocks) = num_blocks {
-            if blocks < 3.0 {
-                return Err(AugurError::invalid_parameter(
-                    "num_blocks must be at least 3 if specified",
-                ));
-            }
+            self.check_num_blocks(blocks)?;
         }
 
         if snapshots.is_empty() {
             return Ok(FeeEstimate::empty(Utc::now()));
         }
 
-        // Sort snapshots by timestamp
-        let mut ordered_snapshots = snapshots.to_vec();
-        ordered_snapshots.sort_by_key(|s| s.timestamp);
+        // Split the targets between the mining simulation and the fast model
+        let requested = match num_blocks {
+            Some(blocks) => vec![blocks],
+            None if self.fast_targets => {
+                let mut targets = self.block_targets.clone();
+                targets.extend(
+                    Self::FAST_BLOCK_TARGETS
+                        .iter()
+                        .filter(|target| !self.block_targets.contains(target)),
+                );
+                targets
+            }
+            None => self.block_targets.clone(),
+        };
+        let min_simulated = f64::from(BlockTarget::MIN_SIMULATED_BLOCKS);
+        let (mut fast_targets, targets): (Vec<f64>, Vec<f64>) = requested
+            .into_iter()
+            .partition(|&target| self.fast_targets && target < min_simulated);
+        fast_targets.sort_by(f64::total_cmp);
 
-        // Convert to internal array representation
-        let snapshot_arrays: Vec<SnapshotArray> = ordered_snapshots
-            .iter()
-            .map(SnapshotArray::from_snapshot)
-            .collect();
+        // Fast estimates are floored by the shortest simulated target, so simulate one even
+        // when only a fast target was asked for
+        let simulated = if targets.is_empty() {
+            vec![min_simulated]
+        } else {
+            targets.clone()
+        };
+        let snapshot_arrays = self.snapshot_arrays(snapshots)?;
+        let Some(timestamp) = snapshot_arrays.last().map(|latest| latest.timestamp) else {
+            return Ok(FeeEstimate::empty(Utc::now()));
+        };
 
-        // Extract latest mempool weights
-        let latest_mempool_weights = &snapshot_arrays.last().unwrap().buckets;
+        // Calculate fee estimates using the core algorithm
+        let fee_matrix = self.fee_matrix(
+            &snapshot_arrays,
+            &simulated,
+            after_block,
+            |calculator, inputs| {
+                calculator.get_fee_estimates(
+                    &inputs.mempool,
+                    &inputs.short_term_inflows,
+                    &inputs.long_term_inflows,
+                )
+            },
+        );
 
-        // Calculate inflow rates
-        let short_term_inflows =
-            InflowCalculator::calculate_inflows(&snapshot_arrays, self.short_term_window);
+        // Convert to FeeEstimate structure
+        let mut estimates = self.convert_to_fee_estimate(&fee_matrix, timestamp, &simulated)?;
 
-        let long_term_inflows =
-            InflowCalculator::calculate_inflows(&snapshot_arrays, self.long_term_window);
+        if !fast_targets.is_empty() {
+            let fast_matrix = self.fee_matrix(
+                &snapshot_arrays,
+                &fast_targets,
+                after_block,
+                |calculator, inputs| {
+                    calculator.get_fast_fee_estimates(&inputs.mempool, &inputs.short_term_inflows)
+                },
+            );
+            let fast = self.convert_to_fee_estimate(&fast_matrix, timestamp, &fast_targets)?;
 
-        // Use custom calculator if num_blocks is specified
-        let (calculator, targets) = if let Some(blocks) = num_blocks {
-            let custom_calc = FeeCalculator::new(self.probabilities.clone(), vec![blocks]);
-            (custom_calc, vec![blocks])
-        } else {
-            (
-                FeeCalculator::new(self.probabilities.clone(), self.block_targets.clone()),
-                self.block_targets.clone(),
+            let floor = estimates.targets().next().map(|(_, target)| target.clone());
+            if targets.is_empty() {
+                estimates = FeeEstimate::empty(timestamp);
+            }
+            for (blocks, target) in fast.targets() {
+                let mut target = target.clone();
+                if let Some(floor) = &floor {
+                    for (probability, rate) in target.probabilities.iter_mut() {
+                        let Some(&min_rate) = floor.probabilities.get(probability) else {
+                            continue;
+                        };
+                        if min_rate > *rate {
+                            *rate = min_rate;
+                            if floor.capped.contains(probability) {
+                                target.capped.insert(*probability);
+                            }
+                            if floor.widened.contains(probability) {
+                                target.widened.insert(*probability);
+                            }
+                        }
+                    }
+                }
+                estimates.insert_target(blocks, target);
+            }
+        }
+
+        Ok(estimates)
+    }
+
+    /// Calculates fee estimates from each source's snapshots separately, plus the median across
+    /// sources.
+    ///
+    /// Snapshots are grouped by [`source_label`](MempoolSnapshot::source_label), so snapshots
+    /// without a label form a source of their own. Incremental inflows are not used, as sources
+    /// may share block heights and timestamps.
+    ///
+    /// # Arguments
+    /// * `snapshots` - Historical mempool snapshots from any number of sources
+    /// * `num_blocks` - Block target, as for `calculate_estimates`
+    pub fn calculate_by_source(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> Result<SourceEstimates> {
+        let estimator = Self {
+            inflow_cache: None,
+            ..self.clone()
+        };
+        let by_source = group_by_source(snapshots)
+            .into_iter()
+            .map(|(source, snapshots)| {
+                Ok((
+                    source,
+                    estimator.calculate_estimates(&snapshots, num_blocks)?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(SourceEstimates::new(by_source))
+    }
+
+    /// Calculates fee estimates for one block target at each of `probabilities` rather than the
+    /// estimator's own confidence levels.
+    ///
+    /// Sampling a dense grid of confidence levels traces fee rate as a function of confidence,
+    /// from which a caller can pick its own risk tolerance.
+    ///
+    /// # Arguments
+    /// * `snapshots` - Historical mempool snapshots, as for `calculate_estimates`
+    /// * `num_blocks` - Block target, as for `calculate_estimates`
+    /// * `probabilities` - Confidence levels (each between 0.0 and 1.0)
+    ///
+    /// # Returns
+    /// An estimate holding only `num_blocks`, or no target at all without snapshots
+    pub fn confidence_curve(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: f64,
+        probabilities: &[f64],
+    ) -> Result<FeeEstimate> {
+        self.with_grid(probabilities.to_vec(), self.block_targets.clone())?
+            .calculate_estimates(snapshots, Some(num_blocks))
+    }
+
+    /// Calculates fee estimates for each of `block_targets` at a single confidence level, in one
+    /// estimation pass.
+    ///
+    /// The snapshots are prepared and inflows measured once for every target, and fee rates
+    /// never rise with the target, so a dense list of targets traces a consistent fee-vs-target
+    /// curve.
+    ///
+    /// # Arguments
+    /// * `snapshots` - Historical mempool snapshots, as for `calculate_estimates`
+    /// * `probability` - Confidence level (between 0.0 and 1.0)
+    /// * `block_targets` - Block targets, each valid as `num_blocks` for `calculate_estimates`
+    ///
+    /// # Returns
+    /// An estimate holding exactly the estimable `block_targets`
+    pub fn target_curve(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        probability: f64,
+        block_targets: &[f64],
+    ) -> Result<FeeEstimate> {
+        for &blocks in block_targets {
+            self.check_num_blocks(blocks)?;
+        }
+
+        let mut estimate = self
+            .with_grid(vec![probability], block_targets.to_vec())?
+            .calculate_estimates(snapshots, None)?;
+        // Fast targets are estimated whenever enabled, even if not asked for
+        estimate.retain_targets(|target, _| block_targets.contains(&target.target()));
+        Ok(estimate)
+    }
+
+    /// Runs the mining simulations behind one estimate and keeps each fee bucket's weights.
+    ///
+    /// Both the short- and long-term runs are returned along with their blend, which is the
+    /// estimate [`calculate_estimates`](Self::calculate_estimates) starts from before
+    /// monotonicity, rounding and capping are applied.
+    ///
+    /// # Arguments
+    /// * `snapshots` - Historical mempool snapshots, as for `calculate_estimates`
+    /// * `num_blocks` - Block target, at least 3.0
+    /// * `probability` - Confidence level, above 0.0 and at most 1.0
+    pub fn simulate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: f64,
+        probability: f64,
+    ) -> Result<Simulation> {
+        if !num_blocks.is_finite() || num_blocks < f64::from(BlockTarget::MIN_SIMULATED_BLOCKS) {
+            return Err(AugurError::invalid_parameter(
+                "num_blocks must be at least 3 to simulate",
+            ));
+        }
+        if !(probability > 0.0 && probability <= 1.0) {
+            return Err(AugurError::invalid_parameter(
+                "probability must be above 0.0 and at most 1.0",
+            ));
+        }
+
+        let snapshot_arrays = self.snapshot_arrays(snapshots)?;
+        let inputs = self
+            .inputs(
+                &snapshot_arrays,
+                self.windows_for(num_blocks),
+                num_blocks,
+                false,
             )
+            .ok_or_else(|| AugurError::insufficient_data("No snapshots to simulate from"))?;
+
+        let calculator = FeeCalculator::new(vec![probability], vec![num_blocks])
+            .with_first_block_deferred(inputs.deferred.clone());
+        let expected_blocks = calculator.expected_blocks(0, 0);
+        let mean_blocks = num_blocks as usize;
+        let initial_weights = &inputs.mempool + &(&inputs.short_term_inflows / 2.0);
+
+        let run = |inflows: &Array1<f64>| {
+            let remaining =
+                calculator.mine_blocks(&initial_weights, inflows, expected_blocks, mean_blocks);
+            // Without blocks to mine the estimate falls back to the cheapest bucket
+            let (added, bucket_index) = if expected_blocks == 0 {
+                (Array1::zeros(inflows.len()), 0)
+            } else {
+                (
+                    inflows * mean_blocks as f64,
+                    calculator.find_best_index(&remaining),
+                )
+            };
+            SimulationRun::new(&initial_weights, &added, &remaining, bucket_index)
         };
+        let short_term = run(&inputs.short_term_inflows);
+        let long_term = run(&inputs.long_term_inflows);
 
-        // Calculate fee estimates using the core algorithm
-        let fee_matrix = calculator.get_fee_estimates(
-            latest_mempool_weights,
-            &short_term_inflows,
-            &long_term_inflows,
+        let long_term_weight = FeeCalculator::long_term_weight(num_blocks);
+        let fee_rate = valid_fee_rate(
+            short_term.bucket_index() * (1.0 - long_term_weight)
+                + long_term.bucket_index() * long_term_weight,
         );
 
-        // Convert to FeeEstimate structure
-        let estimates = self.convert_to_fee_estimate(
-            &fee_matrix,
-            ordered_snapshots.last().unwrap().timestamp,
-            &targets,
+        Ok(Simulation {
+            timestamp: inputs.timestamp,
+            blocks: num_blocks,
+            probability,
+            expected_blocks,
+            long_term_weight,
+            short_term,
+            long_term,
+            fee_rate,
+        })
+    }
+
+    /// Runs the estimation pipeline and keeps what each [`Stage`](crate::Stage) produced.
+    ///
+    /// Covers the simulated targets, or only `num_blocks` if given, up to the fee rates
+    /// [`calculate_estimates`](Self::calculate_estimates) rounds and caps. Every traced target
+    /// must share the same inflow windows.
+    ///
+    /// # Arguments
+    /// * `snapshots` - Historical mempool snapshots, as for `calculate_estimates`
+    /// * `num_blocks` - Optional block target, at least 3.0
+    pub fn trace(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> Result<EstimateTrace> {
+        let targets = match num_blocks {
+            Some(blocks) => vec![blocks],
+            None => self.block_targets.clone(),
+        };
+        let min_simulated = f64::from(BlockTarget::MIN_SIMULATED_BLOCKS);
+        if targets
+            .iter()
+            .any(|target| !target.is_finite() || *target < min_simulated)
+        {
+            return Err(AugurError::invalid_parameter(
+                "Only targets of at least 3 blocks can be traced",
+            ));
+        }
+        let windows = self.windows_for(targets[0]);
+        if targets
+            .iter()
+            .any(|&target| self.windows_for(target) != windows)
+        {
+            return Err(AugurError::invalid_parameter(
+                "Traced targets must share their inflow windows",
+            ));
+        }
+
+        let snapshot_arrays = self.snapshot_arrays(snapshots)?;
+        let longest = targets.iter().copied().fold(0.0, f64::max);
+        let inputs = self
+            .inputs(&snapshot_arrays, windows, longest, false)
+            .ok_or_else(|| AugurError::insufficient_data("No snapshots to trace"))?;
+
+        let calculator = FeeCalculator::new(self.probabilities.clone(), targets.clone())
+            .with_monte_carlo(self.monte_carlo)
+            .with_first_block_deferred(inputs.deferred.clone());
+        let stages = calculator.stages(
+            &inputs.mempool,
+            &inputs.short_term_inflows,
+            &inputs.long_term_inflows,
         );
 
-        Ok(estimates)
+        Ok(EstimateTrace {
+            timestamp: inputs.timestamp,
+            block_targets: targets,
+            probabilities: self.probabilities.clone(),
+            snapshots: snapshot_arrays.len(),
+            mempool: bucket_map(&inputs.mempool),
+            short_term_inflows: bucket_map(&inputs.short_term_inflows),
+            long_term_inflows: bucket_map(&inputs.long_term_inflows),
+            expected_blocks: grid_rows(calculator.expected_blocks_matrix()),
+            short_term_buckets: grid_rows(&stages.short_term),
+            long_term_buckets: grid_rows(&stages.long_term),
+            weighted_buckets: grid_rows(&stages.weighted),
+            fee_rates: grid_rows(&stages.fee_rates),
+        })
+    }
+
+    /// Checks that `blocks` is a block target the estimator can estimate on its own.
+    fn check_num_blocks(&self, blocks: f64) -> Result<()> {
+        if !blocks.is_finite() {
+            return Err(AugurError::invalid_parameter("num_blocks must be finite"));
+        }
+        if blocks < f64::from(BlockTarget::MIN_SIMULATED_BLOCKS) {
+            if !self.fast_targets {
+                return Err(AugurError::invalid_parameter(
+                    "num_blocks must be at least 3 if specified",
+                ));
+            }
+            if !Self::FAST_BLOCK_TARGETS.contains(&blocks) {
+                return Err(AugurError::invalid_parameter(
+                    "num_blocks below 3 must be exactly 1 or 2",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// A copy of the estimator that estimates `probabilities` for `block_targets` instead of its
+    /// own, with every other setting kept.
+    fn with_grid(&self, probabilities: Vec<f64>, block_targets: Vec<f64>) -> Result<Self> {
+        let configured = Self::with_config(
+            probabilities,
+            block_targets,
+            self.short_term_window,
+            self.long_term_window,
+        )?;
+        Ok(Self {
+            probabilities: configured.probabilities,
+            block_targets: configured.block_targets,
+            calculator: configured.calculator,
+            ..self.clone()
+        })
+    }
+
+    /// Estimates `targets` with `estimate`, each group of targets sharing inflow windows from
+    /// its own inputs, reusing the previous long-term inflows if `after_block`.
+    ///
+    /// Rows follow `targets`. When the targets span several groups, each target's fee rates are
+    /// also capped by those of the next shorter target, as within a single simulation.
+    fn fee_matrix(
+        &self,
+        snapshot_arrays: &[SnapshotArray],
+        targets: &[f64],
+        after_block: bool,
+        estimate: impl Fn(&FeeCalculator, &SimulationInputs) -> Array2<Option<f64>>,
+    ) -> Array2<Option<f64>> {
+        let mut groups: Vec<((Duration, Duration), Vec<usize>)> = Vec::new();
+        for (row, &target) in targets.iter().enumerate() {
+            let windows = self.windows_for(target);
+            match groups
+                .iter_mut()
+                .find(|(group_windows, _)| *group_windows == windows)
+            {
+                Some((_, rows)) => rows.push(row),
+                None => groups.push((windows, vec![row])),
+            }
+        }
+
+        let mut matrix = Array2::from_elem((targets.len(), self.probabilities.len()), None);
+        for (windows, rows) in &groups {
+            let group: Vec<f64> = rows.iter().map(|&row| targets[row]).collect();
+            let longest = group.iter().copied().fold(0.0, f64::max);
+            let Some(inputs) = self.inputs(snapshot_arrays, *windows, longest, after_block) else {
+                continue;
+            };
+
+            let calculator = FeeCalculator::new(self.probabilities.clone(), group)
+                .with_monte_carlo(self.monte_carlo)
+                .with_first_block_deferred(inputs.deferred.clone());
+            let group_matrix = estimate(&calculator, &inputs);
+            for (group_row, &row) in rows.iter().enumerate() {
+                matrix.row_mut(row).assign(&group_matrix.row(group_row));
+            }
+        }
+
+        if groups.len() > 1 {
+            let mut order: Vec<usize> = (0..targets.len()).collect();
+            order.sort_by(|&a, &b| targets[a].total_cmp(&targets[b]));
+            for pair in order.windows(2) {
+                let (shorter, longer) = (pair[0], pair[1]);
+                for prob_idx in 0..self.probabilities.len() {
+                    if let (Some(ceiling), Some(rate)) =
+                        (matrix[[shorter, prob_idx]], matrix[[longer, prob_idx]])
+                    {
+                        matrix[[longer, prob_idx]] = Some(rate.min(ceiling));
+                    }
+                }
+            }
+        }
+
+        matrix
+    }
+
+    /// Bounds the history, drops outlier snapshots and converts the rest, oldest first, to
+    /// bucket arrays.
+    fn snapshot_arrays(&self, snapshots: &[MempoolSnapshot]) -> Result<Vec<SnapshotArray>> {
+        // Check the history's size before doing any work proportional to it
+        let bounded;
+        let snapshots = match &self.max_history {
+            Some(limit) => {
+                bounded = limit.apply(snapshots)?;
+                &bounded
+            }
+            None => snapshots,
+        };
+        let weighted;
+        let snapshots = match self.weighting {
+            BucketWeighting::Weight => snapshots,
+            weighting => {
+                weighted = snapshots
+                    .iter()
+                    .map(|s| weighting.apply(s))
+                    .collect::<Vec<_>>();
+                &weighted
+            }
+        };
+
+        // Drop outliers and sort snapshots by timestamp
+        let mut ordered_snapshots = match self.outlier_filter {
+            Some(filter) => filter.filter(snapshots).0,
+            None => snapshots.to_vec(),
+        };
+        ordered_snapshots.sort_by_key(|s| s.timestamp);
+
+        // Snapshots from blocks that were reorganized away would pair with the replacement
+        // chain's snapshots at the same height
+        MempoolSnapshot::remove_orphaned(&mut ordered_snapshots);
+
+        // Convert to internal array representation
+        Ok(match &self.inflow_cache {
+            Some(cache) => cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .snapshot_arrays(&ordered_snapshots),
+            None => ordered_snapshots
+                .iter()
+                .map(SnapshotArray::from_snapshot)
+                .collect(),
+        })
+    }
+
+    /// Derives the mempool and inflows simulations start from, with inflows measured over the
+    /// short- and long-term `windows`.
+    ///
+    /// Seasonality adjusts the long-term inflows for the period `longest_target` spans. With
+    /// `after_block`, the long-term inflows of the previous estimate are reused when possible.
+    fn inputs(
+        &self,
+        snapshot_arrays: &[SnapshotArray],
+        windows: (Duration, Duration),
+        longest_target: f64,
+        after_block: bool,
+    ) -> Option<SimulationInputs> {
+        let (short_term_window, long_term_window) = windows;
+        let latest = snapshot_arrays.last()?;
+
+        // Calculate inflow rates
+        let (short_term_inflows, mut long_term_inflows) = match &self.inflow_cache {
+            Some(cache) => {
+                let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+                let short_term_inflows = InflowCalculator::calculate_inflows_cached(
+                    snapshot_arrays,
+                    short_term_window,
+                    &mut cache.inflows,
+                );
+                let long_term_inflows = match after_block
+                    .then(|| cache.long_term_inflows_after_block(snapshot_arrays, windows))
+                    .flatten()
+                {
+                    Some(inflows) => inflows,
+                    None => InflowCalculator::calculate_inflows_cached(
+                        snapshot_arrays,
+                        long_term_window,
+                        &mut cache.inflows,
+                    ),
+                };
+                cache.keep_long_term_inflows(latest, windows, &long_term_inflows);
+                (short_term_inflows, long_term_inflows)
+            }
+            None => (
+                InflowCalculator::calculate_inflows(snapshot_arrays, short_term_window),
+                InflowCalculator::calculate_inflows(snapshot_arrays, long_term_window),
+            ),
+        };
+
+        // Expect the seasonal inflow of the period the longest target spans
+        if let Some((profile, blend)) = &self.seasonality {
+            let horizon = Duration::seconds((longest_target * 600.0) as i64);
+            let adjustment = profile.adjustment(latest.timestamp, long_term_window, horizon);
+            long_term_inflows *= 1.0 - blend + blend * adjustment;
+        }
+
+        Some(SimulationInputs {
+            timestamp: latest.timestamp,
+            mempool: latest.buckets.to_dense(),
+            deferred: (latest.deferred.sum() > 0.0).then(|| latest.deferred.to_dense()),
+            short_term_inflows,
+            long_term_inflows,
+        })
     }
 
     /// Converts the raw fee matrix to a structured FeeEstimate object.
@@ -200,28 +1011,58 @@ impl FeeEstimator {
         fee_matrix: &ndarray::Array2<Option<f64>>,
         timestamp: chrono::DateTime<chrono::Utc>,
         targets: &[f64],
-    ) -> FeeEstimate {
-        let mut estimates = BTreeMap::new();
+    ) -> Result<FeeEstimate> {
+        let mut estimates = FeeEstimate::empty(timestamp);
 
         for (block_idx, &mean_blocks) in targets.iter().enumerate() {
             let mut probabilities = BTreeMap::new();
+            let mut capped = BTreeSet::new();
+            let mut widened = BTreeSet::new();
+            let mut below = None;
 
             for (prob_idx, &prob) in self.probabilities.iter().enumerate() {
                 if let Some(fee_rate) = fee_matrix[[block_idx, prob_idx]] {
+                    let mut fee_rate = self.rounding.apply(fee_rate);
+                    if let (Some(min_spread), Some(below)) = (self.min_spread, below) {
+                        let floor = below * (1.0 + min_spread);
+                        if fee_rate < floor {
+                            fee_rate = self.rounding.apply(floor);
+                            widened.insert(OrderedFloat(prob));
+                        }
+                    }
+                    below = Some(fee_rate);
+                    if let Some(max_fee_rate) = self.max_fee_rate {
+                        if fee_rate > max_fee_rate {
+                            fee_rate = max_fee_rate;
+                            capped.insert(OrderedFloat(prob));
+                        }
+                    }
                     probabilities.insert(OrderedFloat(prob), fee_rate);
                 }
             }
 
             if !probabilities.is_empty() {
-                let block_target = BlockTarget::new(mean_blocks as u32, probabilities);
-                estimates.insert(mean_blocks as u32, block_target);
+                let mut block_target = BlockTarget::new(mean_blocks as u32, probabilities);
+                block_target.capped = capped;
+                block_target.widened = widened;
+                estimates.insert_target(BlockTargetKey::try_from(mean_blocks)?, block_target);
             }
         }
 
-        FeeEstimate::new(estimates, timestamp)
+        Ok(estimates)
     }
 }
 
+/// Mempool weights and inflow rates of a set of snapshots, ready for simulation
+struct SimulationInputs {
+    timestamp: DateTime<Utc>,
+    mempool: Array1<f64>,
+    /// Weight of the latest snapshot the next block cannot take, if any
+    deferred: Option<Array1<f64>>,
+    short_term_inflows: Array1<f64>,
+    long_term_inflows: Array1<f64>,
+}
+
 impl Default for FeeEstimator {
     fn default() -> Self {
         let probabilities = Self::DEFAULT_PROBABILITIES.to_vec();
@@ -233,6 +1074,17 @@ impl Default for FeeEstimator {
             block_targets,
             short_term_window: Duration::minutes(30),
             long_term_window: Duration::hours(24),
+            rounding: RoundingPolicy::default(),
+            outlier_filter: None,
+            fast_targets: false,
+            max_fee_rate: None,
+            min_spread: None,
+            seasonality: None,
+            target_windows: Vec::new(),
+            inflow_cache: None,
+            max_history: None,
+            weighting: BucketWeighting::default(),
+            monte_carlo: None,
             calculator,
         }
     }
@@ -241,7 +1093,7 @@ impl Default for FeeEstimator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::MempoolTransaction;
+    use crate::{MempoolTransaction, Stage};
 
     #[test]
     fn test_fee_estimator_creation() {
@@ -271,6 +1123,44 @@ mod tests {
         assert_eq!(estimator.block_targets.len(), 2);
     }
 
+    #[test]
+    fn test_fractional_targets_keep_their_own_key() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..6)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let estimator = FeeEstimator::with_config(
+            vec![0.5],
+            vec![6.0, 6.5],
+            Duration::minutes(30),
+            Duration::hours(24),
+        )
+        .unwrap();
+        let estimate = estimator.calculate_estimates(&snapshots, None).unwrap();
+        assert_eq!(estimate.available_targets(), [6.0, 6.5]);
+        let key = BlockTargetKey::try_from(6.5).unwrap();
+        assert_eq!(estimate.fractional_estimates[&key].blocks, 6);
+
+        let single = estimator
+            .calculate_estimates(&snapshots, Some(6.5))
+            .unwrap();
+        assert_eq!(single.available_targets(), [6.5]);
+        assert_eq!(
+            single.get_target_fee_rate(key, 0.5),
+            estimate.get_target_fee_rate(key, 0.5)
+        );
+    }
+
     #[test]
     fn test_invalid_config() {
         // Empty probabilities
@@ -313,8 +1203,869 @@ mod tests {
         let result = estimator.calculate_estimates(std::slice::from_ref(&snapshot), Some(2.0));
         assert!(result.is_err());
 
+        // num_blocks not finite
+        for blocks in [f64::NAN, f64::INFINITY] {
+            let result =
+                estimator.calculate_estimates(std::slice::from_ref(&snapshot), Some(blocks));
+            assert!(result.is_err());
+        }
+
         // num_blocks valid
         let result = estimator.calculate_estimates(&[snapshot], Some(6.0));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_rounding_applies_to_every_estimate() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..6)
+            .map(|i| {
+                let transactions = (0..200)
+                    .map(|j| MempoolTransaction::new(400 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32,
+                    base + Duration::minutes(i as i64 * 10),
+                )
+            })
+            .collect();
+
+        let exact = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+        assert!(!exact.estimates.is_empty());
+
+        for policy in [RoundingPolicy::CeilTenth, RoundingPolicy::CeilInteger] {
+            let estimator = FeeEstimator::new().with_rounding(policy);
+            assert_eq!(estimator.rounding(), policy);
+            let rounded = estimator.calculate_estimates(&snapshots, None).unwrap();
+
+            for (target, block_target) in &exact.estimates {
+                for (probability, &rate) in &block_target.probabilities {
+                    let rounded_rate = rounded.estimates[target].probabilities[probability];
+                    assert_eq!(rounded_rate, policy.apply(rate));
+                    assert_eq!(policy.apply(rounded_rate), rounded_rate);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_outlier_filter_excludes_glitches() {
+        let base = Utc::now();
+        let mut snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..200)
+                    .map(|j| MempoolTransaction::new(400 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 4,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+        let clean = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+
+        // An empty mempool read between two full ones
+        snapshots.insert(
+            6,
+            MempoolSnapshot::empty(850001, base + Duration::minutes(27)),
+        );
+        let estimator = FeeEstimator::new().with_outlier_filter(OutlierFilter::default());
+        assert_eq!(estimator.outlier_filter(), Some(OutlierFilter::default()));
+
+        let filtered = estimator.calculate_estimates(&snapshots, None).unwrap();
+        assert!(!clean.estimates.is_empty());
+        for (target, block_target) in &clean.estimates {
+            assert_eq!(
+                filtered.estimates[target].probabilities,
+                block_target.probabilities
+            );
+        }
+    }
+
+    #[test]
+    fn test_reorged_snapshots_are_excluded() {
+        let base = Utc::now();
+        let snapshot = |i: i64, height: u32, count: u64| {
+            let transactions = (0..count)
+                .map(|j| {
+                    MempoolTransaction::new(40_000 + j * 700, 100_000 + j * 3_700 + i as u64 * 11)
+                })
+                .collect();
+            MempoolSnapshot::from_transactions(transactions, height, base + Duration::minutes(i))
+        };
+        let mut snapshots: Vec<_> = (0..12)
+            .map(|i| snapshot(i * 5, 850000 + i as u32 / 4, 200))
+            .collect();
+        let clean = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+
+        // A block at 850002 that was reorganized away, seen before the chain fell back to
+        // 850001. Paired with the later 850002 snapshots, its small mempool would look like
+        // heavy inflow.
+        snapshots.insert(5, snapshot(21, 850002, 10));
+        snapshots.insert(6, snapshot(22, 850002, 10));
+        let estimates = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+        assert!(!clean.estimates.is_empty());
+        for (target, block_target) in &clean.estimates {
+            assert_eq!(
+                estimates.estimates[target].probabilities,
+                block_target.probabilities
+            );
+        }
+    }
+
+    #[test]
+    fn test_seasonality_lowers_fees_before_quiet_weekend() {
+        use chrono::{Datelike, TimeZone};
+
+        // A week of hourly inflow where weekends see a third of the weekday inflow
+        let monday = Utc.with_ymd_and_hms(2025, 1, 20, 0, 0, 0).unwrap();
+        let history: Vec<_> = (0..7 * 24)
+            .flat_map(|hour| {
+                let time = monday + Duration::hours(hour);
+                let inflow = if time.weekday().num_days_from_monday() >= 5 {
+                    1_000_000
+                } else {
+                    3_000_000
+                };
+                [
+                    MempoolSnapshot::new(800_000, time, BTreeMap::new()),
+                    MempoolSnapshot::new(
+                        800_000,
+                        time + Duration::minutes(30),
+                        BTreeMap::from([(100, inflow)]),
+                    ),
+                ]
+            })
+            .collect();
+        let profile = SeasonalProfile::from_snapshots(&history);
+
+        // A busy Friday ending at midnight, a block every 10 minutes outgrowing its capacity
+        let friday = Utc.with_ymd_and_hms(2025, 1, 24, 0, 0, 0).unwrap();
+        let backlog: BTreeMap<i32, u64> = (100..=500).step_by(50).map(|b| (b, 2_000_000)).collect();
+        let snapshots: Vec<_> = (0..144)
+            .flat_map(|block| {
+                let time = friday + Duration::minutes(block * 10 + 5);
+                let mut grown = backlog.clone();
+                *grown.entry(100).or_default() += 2_000_000;
+                *grown.entry(300).or_default() += 1_000_000;
+                let height = 850_000 + block as u32;
+                [
+                    MempoolSnapshot::new(height, time, backlog.clone()),
+                    MempoolSnapshot::new(height, time + Duration::minutes(5), grown),
+                ]
+            })
+            .collect();
+
+        let plain = FeeEstimator::new()
+            .calculate_estimates(&snapshots, Some(144.0))
+            .unwrap();
+        let unblended = FeeEstimator::new()
+            .with_seasonality(profile.clone(), 0.0)
+            .unwrap()
+            .calculate_estimates(&snapshots, Some(144.0))
+            .unwrap();
+        let estimator = FeeEstimator::new()
+            .with_seasonality(profile.clone(), 1.0)
+            .unwrap();
+        assert_eq!(estimator.seasonality(), Some((&profile, 1.0)));
+        let seasonal = estimator
+            .calculate_estimates(&snapshots, Some(144.0))
+            .unwrap();
+
+        let plain = &plain.estimates[&144].probabilities;
+        assert_eq!(&unblended.estimates[&144].probabilities, plain);
+        let seasonal = &seasonal.estimates[&144].probabilities;
+        assert!(plain.iter().all(|(p, rate)| seasonal[p] <= *rate));
+        assert!(plain.iter().any(|(p, rate)| seasonal[p] < *rate));
+
+        assert!(FeeEstimator::new().with_seasonality(profile, 1.5).is_err());
+    }
+    #[test]
+    fn test_fast_targets() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let plain = FeeEstimator::new();
+        assert!(!plain.has_fast_targets());
+        assert!(plain.calculate_estimates(&snapshots, Some(1.0)).is_err());
+
+        let estimator = FeeEstimator::new().with_fast_targets();
+        assert!(estimator.has_fast_targets());
+        for blocks in [0.5, 1.5, 2.5] {
+            assert!(estimator
+                .calculate_estimates(&snapshots, Some(blocks))
+                .is_err());
+        }
+
+        let estimate = estimator.calculate_estimates(&snapshots, None).unwrap();
+        assert_eq!(estimate.estimates.len(), 13);
+        for (blocks, target) in &estimate.estimates {
+            assert_eq!(target.is_high_variance(), *blocks < 3);
+        }
+        for probability in FeeEstimator::DEFAULT_PROBABILITIES {
+            let rate = |blocks| estimate.get_fee_rate(blocks, *probability).unwrap();
+            assert!(rate(1) >= rate(2));
+            assert!(rate(2) >= rate(3));
+        }
+
+        // Asking for a fast target alone gives the same estimate
+        let single = estimator
+            .calculate_estimates(&snapshots, Some(1.0))
+            .unwrap();
+        assert_eq!(single.estimates.len(), 1);
+        assert_eq!(
+            single.estimates[&1].probabilities,
+            estimate.estimates[&1].probabilities
+        );
+    }
+
+    #[test]
+    fn test_max_fee_rate_clamps_and_flags() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let uncapped = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+        let mut rates: Vec<f64> = uncapped
+            .estimates
+            .values()
+            .flat_map(|target| target.probabilities.values().copied())
+            .collect();
+        rates.sort_by(f64::total_cmp);
+        let cap = rates[rates.len() / 2];
+        assert!(rates.last().unwrap() > &cap);
+
+        let estimator = FeeEstimator::new().with_max_fee_rate(cap).unwrap();
+        assert_eq!(estimator.max_fee_rate(), Some(cap));
+        let capped = estimator.calculate_estimates(&snapshots, None).unwrap();
+
+        for (blocks, target) in &uncapped.estimates {
+            let capped_target = &capped.estimates[blocks];
+            for (probability, &rate) in &target.probabilities {
+                assert_eq!(capped_target.probabilities[probability], rate.min(cap));
+                assert_eq!(capped_target.is_capped(probability.0), rate > cap);
+            }
+            assert!(target.capped.is_empty());
+        }
+
+        for cap in [0.5, f64::NAN, f64::INFINITY] {
+            assert!(FeeEstimator::new().with_max_fee_rate(cap).is_err());
+        }
+    }
+
+    #[test]
+    fn test_min_spread_separates_confidence_levels() {
+        let base = Utc::now();
+        // One fee rate throughout, so every confidence level estimates the same
+        let snapshots: Vec<_> = (0..6)
+            .map(|i| {
+                let transactions = vec![MempoolTransaction::new(4000, 10_000); 2000];
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let flat = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+        let estimator = FeeEstimator::new()
+            .with_rounding(RoundingPolicy::CeilTenth)
+            .with_min_spread(0.02)
+            .unwrap();
+        assert_eq!(estimator.min_spread(), Some(0.02));
+        let spread = estimator.calculate_estimates(&snapshots, None).unwrap();
+
+        assert!(spread
+            .estimates
+            .values()
+            .any(|target| !target.widened.is_empty()));
+        for (blocks, target) in &spread.estimates {
+            assert!(flat.estimates[blocks].widened.is_empty());
+            let rates: Vec<f64> = target.probabilities.values().copied().collect();
+            for pair in rates.windows(2) {
+                assert!(pair[1] >= pair[0] * 1.02, "{blocks} blocks: {rates:?}");
+            }
+            // Only the levels that had to be raised are flagged
+            for (probability, &rate) in &target.probabilities {
+                let flat_rate = RoundingPolicy::CeilTenth
+                    .apply(flat.estimates[blocks].probabilities[probability]);
+                assert_eq!(target.is_widened(probability.0), rate > flat_rate);
+            }
+        }
+
+        for spread in [0.0, -0.1, 1.5, f64::NAN] {
+            assert!(FeeEstimator::new().with_min_spread(spread).is_err());
+        }
+    }
+
+    #[test]
+    fn test_monte_carlo_is_reproducible() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let deterministic = FeeEstimator::new()
+            .calculate_estimates(&snapshots, None)
+            .unwrap();
+        let monte_carlo = MonteCarlo::new(200, 42).unwrap();
+        let estimator = FeeEstimator::new().with_monte_carlo(monte_carlo);
+        assert_eq!(estimator.monte_carlo(), Some(monte_carlo));
+        let sampled = estimator.calculate_estimates(&snapshots, None).unwrap();
+
+        // The same seed samples the same block arrivals
+        let again = estimator.calculate_estimates(&snapshots, None).unwrap();
+        for (blocks, target) in &sampled.estimates {
+            assert_eq!(target.probabilities, again.estimates[blocks].probabilities);
+        }
+
+        // Sampling block arrivals lands near the expected number of blocks
+        for (blocks, target) in &sampled.estimates {
+            let expected = deterministic.estimates[blocks].probabilities[&OrderedFloat(0.5)];
+            let median = target.probabilities[&OrderedFloat(0.5)];
+            assert!(
+                (median / expected - 1.0).abs() < 0.25,
+                "{blocks} blocks: {median} sampled, {expected} expected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_simulate_explains_estimate() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let estimator = FeeEstimator::new();
+        let simulation = estimator.simulate(&snapshots, 6.0, 0.95).unwrap();
+        assert_eq!(simulation.timestamp, snapshots[11].timestamp);
+        assert!(simulation.expected_blocks > 0);
+        assert!(!simulation.short_term.buckets.is_empty());
+
+        // Every bucket above the run's fee rate was emptied
+        for run in [&simulation.short_term, &simulation.long_term] {
+            let fee_rate = run.fee_rate.unwrap();
+            for bucket in &run.buckets {
+                if bucket.fee_rate > fee_rate + 1e-9 {
+                    assert_eq!(bucket.remaining, 0.0);
+                }
+            }
+        }
+
+        // With a single target and confidence level the blend is the estimate itself
+        let single = FeeEstimator::with_config(
+            vec![0.95],
+            vec![6.0],
+            Duration::minutes(30),
+            Duration::hours(24),
+        )
+        .unwrap();
+        let estimate = single.calculate_estimates(&snapshots, None).unwrap();
+        let fee_rate = simulation.fee_rate.unwrap();
+        assert!((estimate.get_fee_rate(6, 0.95).unwrap() - fee_rate).abs() < 1e-9);
+
+        assert!(estimator.simulate(&snapshots, 2.0, 0.95).is_err());
+        assert!(estimator.simulate(&snapshots, 6.0, 0.0).is_err());
+        assert!(estimator.simulate(&[], 6.0, 0.95).is_err());
+    }
+
+    #[test]
+    fn test_trace_ends_with_the_estimate() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let estimator = FeeEstimator::new();
+        let trace = estimator.trace(&snapshots, None).unwrap();
+        assert_eq!(trace.timestamp, snapshots[11].timestamp);
+        assert_eq!(trace.snapshots, 12);
+        assert_eq!(
+            trace.mempool.values().sum::<f64>(),
+            snapshots[11].total_weight() as f64
+        );
+        assert!(!trace.short_term_inflows.is_empty());
+        assert_eq!(
+            trace.fee_rates.len(),
+            FeeEstimator::DEFAULT_BLOCK_TARGETS.len()
+        );
+
+        // The last stage holds the estimate before rounding, which is exact by default
+        let estimate = estimator.calculate_estimates(&snapshots, None).unwrap();
+        for (row, &target) in trace.block_targets.iter().enumerate() {
+            for (column, &probability) in trace.probabilities.iter().enumerate() {
+                assert_eq!(
+                    estimate.get_fee_rate(target as u32, probability),
+                    Some(trace.fee_rates[row][column])
+                );
+            }
+        }
+        assert_eq!(
+            trace.values(Stage::Monotonicity)[0],
+            ("3 blocks 0.05".to_string(), trace.fee_rates[0][0])
+        );
+        assert_eq!(
+            trace.values(Stage::Bucketing)[0],
+            ("snapshots".to_string(), 12.0)
+        );
+
+        let single = estimator.trace(&snapshots, Some(6.0)).unwrap();
+        assert_eq!(single.block_targets, [6.0]);
+        assert_eq!(single.fee_rates[0], trace.fee_rates[1]);
+        assert!(estimator.trace(&snapshots, Some(2.0)).is_err());
+        assert!(estimator.trace(&[], None).is_err());
+    }
+
+    #[test]
+    fn test_max_history() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..24)
+            .map(|i| {
+                let transactions = (0..200)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 4,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let limit = HistoryLimit::by_count(12).unwrap();
+        let strict = FeeEstimator::new().with_max_history(limit);
+        assert_eq!(strict.max_history(), Some(limit));
+        assert!(matches!(
+            strict.calculate_estimates(&snapshots, None),
+            Err(AugurError::HistoryTooLong(_))
+        ));
+        assert!(matches!(
+            strict.simulate(&snapshots, 6.0, 0.5),
+            Err(AugurError::HistoryTooLong(_))
+        ));
+        assert!(strict.calculate_estimates(&snapshots[12..], None).is_ok());
+
+        // Truncating estimates from the newest snapshots alone
+        let truncating = FeeEstimator::new().with_max_history(limit.truncating());
+        let estimate = truncating.calculate_estimates(&snapshots, None).unwrap();
+        let newest = FeeEstimator::new()
+            .calculate_estimates(&snapshots[12..], None)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(estimate).unwrap(),
+            serde_json::to_value(newest).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_weighting() {
+        let base = Utc::now();
+        let history = |with_values: bool| -> Vec<MempoolSnapshot> {
+            (0..24)
+                .map(|i| {
+                    let transactions = (0..2000)
+                        .map(|j| {
+                            let tx = MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11);
+                            // The higher paying half moves most of the value
+                            let value = if j >= 1000 { 1_000_000 } else { 1_000 };
+                            if with_values {
+                                tx.with_value(value)
+                            } else {
+                                tx
+                            }
+                        })
+                        .collect();
+                    MempoolSnapshot::from_transactions(
+                        transactions,
+                        850000 + i as u32 / 4,
+                        base + Duration::minutes(i as i64 * 5),
+                    )
+                })
+                .collect()
+        };
+
+        let by_weight = FeeEstimator::new();
+        let by_value = FeeEstimator::new().with_weighting(BucketWeighting::Value);
+        assert_eq!(by_value.weighting(), BucketWeighting::Value);
+
+        // Without values there is nothing to weigh by
+        let plain = history(false);
+        assert_eq!(
+            serde_json::to_value(by_value.calculate_estimates(&plain, None).unwrap()).unwrap(),
+            serde_json::to_value(by_weight.calculate_estimates(&plain, None).unwrap()).unwrap()
+        );
+
+        // Crowding the high fee rates with value raises what it takes to confirm
+        let valued = history(true);
+        let weight_estimate = by_weight.calculate_estimates(&valued, None).unwrap();
+        let value_estimate = by_value.calculate_estimates(&valued, None).unwrap();
+        let weight_fee = weight_estimate.get_fee_rate(3, 0.5).unwrap();
+        let value_fee = value_estimate.get_fee_rate(3, 0.5).unwrap();
+        assert!(value_fee > weight_fee, "{value_fee} <= {weight_fee}");
+    }
+
+    #[test]
+    fn test_calculate_by_source() {
+        let base = Utc::now();
+        let history = |source: &str, fee_scale: u64| -> Vec<MempoolSnapshot> {
+            (0..24)
+                .map(|i| {
+                    let transactions = (0..2000)
+                        .map(|j| {
+                            MempoolTransaction::new(
+                                4000 + j * 7,
+                                (1000 + j * 37 + i * 11) * fee_scale,
+                            )
+                        })
+                        .collect();
+                    MempoolSnapshot::from_transactions(
+                        transactions,
+                        850000 + i as u32 / 4,
+                        base + Duration::minutes(i as i64 * 5),
+                    )
+                    .with_source(source)
+                })
+                .collect()
+        };
+        let node_a = history("node-a", 1);
+        let node_b = history("node-b", 3);
+        let mixed: Vec<_> = node_a.iter().chain(&node_b).cloned().collect();
+
+        let estimator = FeeEstimator::new().with_incremental_inflows();
+        let estimates = estimator.calculate_by_source(&mixed, None).unwrap();
+        assert_eq!(
+            estimates.by_source.keys().collect::<Vec<_>>(),
+            ["node-a", "node-b"]
+        );
+
+        // Each source is estimated as if it were alone
+        let alone = FeeEstimator::new()
+            .calculate_estimates(&node_a, None)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&estimates.by_source["node-a"]).unwrap(),
+            serde_json::to_value(&alone).unwrap()
+        );
+
+        // Two sources meet in the middle
+        let rate = |source: &str| estimates.by_source[source].get_fee_rate(3, 0.5).unwrap();
+        let combined = estimates.combined.get_fee_rate(3, 0.5).unwrap();
+        assert!((combined - (rate("node-a") + rate("node-b")) / 2.0).abs() < 1e-9);
+        assert!(rate("node-b") > rate("node-a"));
+    }
+
+    #[test]
+    fn test_confidence_curve() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let estimator = FeeEstimator::new();
+        let grid: Vec<f64> = (1..=19).map(|step| f64::from(step * 5) / 100.0).collect();
+        let curve = estimator.confidence_curve(&snapshots, 6.0, &grid).unwrap();
+        assert_eq!(curve.available_targets(), vec![6]);
+        let target = &curve.estimates[&6];
+        assert_eq!(target.probabilities.len(), grid.len());
+
+        // The estimator's own confidence levels are points on the curve
+        let estimate = estimator.calculate_estimates(&snapshots, None).unwrap();
+        for &probability in FeeEstimator::DEFAULT_PROBABILITIES {
+            let on_curve = target.get_fee_rate(probability).unwrap();
+            assert!((on_curve - estimate.get_fee_rate(6, probability).unwrap()).abs() < 1e-9);
+        }
+
+        // More confidence never costs less
+        let rates: Vec<f64> = target.probabilities.values().copied().collect();
+        assert!(rates.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        assert!(estimator.confidence_curve(&snapshots, 6.0, &[]).is_err());
+        assert!(estimator.confidence_curve(&snapshots, 6.0, &[1.5]).is_err());
+        assert!(estimator.confidence_curve(&snapshots, 2.0, &grid).is_err());
+    }
+
+    #[test]
+    fn test_target_curve() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let estimator = FeeEstimator::new().with_fast_targets();
+        let targets: Vec<f64> = (3..=144).map(f64::from).collect();
+        let curve = estimator.target_curve(&snapshots, 0.8, &targets).unwrap();
+        assert_eq!(curve.available_targets(), (3..=144).collect::<Vec<u32>>());
+
+        // The estimator's own targets are points on the curve
+        let estimate = estimator.calculate_estimates(&snapshots, None).unwrap();
+        for blocks in [3, 6, 12, 144] {
+            let on_curve = curve.get_fee_rate(blocks, 0.8).unwrap();
+            assert!((on_curve - estimate.get_fee_rate(blocks, 0.8).unwrap()).abs() < 1e-9);
+        }
+
+        // Waiting longer never costs more
+        let rates: Vec<f64> = curve
+            .estimates
+            .values()
+            .map(|target| target.get_fee_rate(0.8).unwrap())
+            .collect();
+        assert!(rates.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        assert!(estimator.target_curve(&snapshots, 1.5, &targets).is_err());
+        assert!(estimator.target_curve(&snapshots, 0.8, &[2.5]).is_err());
+        assert!(FeeEstimator::new()
+            .target_curve(&snapshots, 0.8, &[1.0])
+            .is_err());
+    }
+
+    #[test]
+    fn test_target_windows() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                let transactions = (0..2000)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 2,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let near = TargetWindows::new(3.0, 6.0, Duration::minutes(15), Duration::hours(6)).unwrap();
+        let estimator = FeeEstimator::new().with_target_windows(vec![near]).unwrap();
+        assert_eq!(
+            estimator.windows_for(6.0),
+            (Duration::minutes(15), Duration::hours(6))
+        );
+        assert_eq!(
+            estimator.windows_for(9.0),
+            (Duration::minutes(30), Duration::hours(24))
+        );
+
+        // Targets in the range are estimated as if the windows were the estimator's own
+        let estimate = estimator.calculate_estimates(&snapshots, None).unwrap();
+        let near_only = FeeEstimator::with_config(
+            FeeEstimator::DEFAULT_PROBABILITIES.to_vec(),
+            vec![3.0, 6.0],
+            Duration::minutes(15),
+            Duration::hours(6),
+        )
+        .unwrap()
+        .calculate_estimates(&snapshots, None)
+        .unwrap();
+        for blocks in [3, 6] {
+            assert_eq!(
+                estimate.estimates[&blocks].probabilities,
+                near_only.estimates[&blocks].probabilities
+            );
+        }
+
+        // Fee rates still never rise with the target
+        let targets: Vec<_> = estimate.estimates.values().collect();
+        for pair in targets.windows(2) {
+            for (probability, rate) in &pair[1].probabilities {
+                if let Some(shorter) = pair[0].probabilities.get(probability) {
+                    assert!(rate <= shorter);
+                }
+            }
+        }
+
+        let overlapping =
+            TargetWindows::new(6.0, 12.0, Duration::hours(1), Duration::hours(72)).unwrap();
+        assert!(FeeEstimator::new()
+            .with_target_windows(vec![near, overlapping])
+            .is_err());
+
+        // Long windows reach further back than the default 24 hours
+        assert_eq!(estimator.history_window(), Duration::hours(24));
+        let far = TargetWindows::new(24.0, 144.0, Duration::hours(1), Duration::hours(72)).unwrap();
+        let estimator = estimator.with_target_windows(vec![near, far]).unwrap();
+        assert_eq!(estimator.history_window(), Duration::hours(72));
+    }
+
+    #[test]
+    fn test_incremental_inflows_match_full_recalculation() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..24)
+            .map(|i| {
+                let transactions = (0..200)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 4,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let estimate = |estimator: &FeeEstimator, snapshots: &[MempoolSnapshot]| {
+            serde_json::to_value(estimator.calculate_estimates(snapshots, None).unwrap()).unwrap()
+        };
+        let estimator = FeeEstimator::with_config(
+            FeeEstimator::DEFAULT_PROBABILITIES.to_vec(),
+            vec![3.0, 12.0],
+            Duration::minutes(30),
+            Duration::hours(24),
+        )
+        .unwrap();
+        let incremental = estimator.clone().with_incremental_inflows();
+        assert!(incremental.has_incremental_inflows());
+
+        // Estimate after every new snapshot, as the collector does
+        for end in 1..=snapshots.len() {
+            let window = &snapshots[end.saturating_sub(12)..end];
+            assert_eq!(estimate(&incremental, window), estimate(&estimator, window));
+        }
+
+        incremental.clear_inflow_cache();
+        assert_eq!(
+            estimate(&incremental, &snapshots),
+            estimate(&estimator, &snapshots)
+        );
+    }
+
+    #[test]
+    fn test_recompute_after_block() {
+        let base = Utc::now();
+        let snapshots: Vec<_> = (0..24)
+            .map(|i| {
+                let transactions = (0..200)
+                    .map(|j| MempoolTransaction::new(4000 + j * 7, 1000 + j * 37 + i * 11))
+                    .collect();
+                MempoolSnapshot::from_transactions(
+                    transactions,
+                    850000 + i as u32 / 4,
+                    base + Duration::minutes(i as i64 * 5),
+                )
+            })
+            .collect();
+
+        let estimator = FeeEstimator::with_config(
+            FeeEstimator::DEFAULT_PROBABILITIES.to_vec(),
+            vec![3.0, 12.0],
+            Duration::minutes(30),
+            Duration::hours(24),
+        )
+        .unwrap();
+        assert!(matches!(
+            estimator.recompute_after_block(&snapshots, None),
+            Err(AugurError::InvalidConfig(_))
+        ));
+
+        // The long-term window holds every snapshot, so reusing its inflows changes nothing,
+        // whether the newest snapshot starts a block or falls back to a full calculation
+        let incremental = estimator.clone().with_incremental_inflows();
+        for end in 1..=snapshots.len() {
+            let window = &snapshots[..end];
+            let estimate = if end % 4 == 1 {
+                incremental.recompute_after_block(window, None)
+            } else {
+                incremental.calculate_estimates(window, None)
+            };
+            assert_eq!(
+                serde_json::to_value(estimate.unwrap()).unwrap(),
+                serde_json::to_value(estimator.calculate_estimates(window, None).unwrap()).unwrap()
+            );
+        }
+        assert_eq!(
+            serde_json::to_value(
+                incremental
+                    .recompute_after_block(&snapshots, Some(6.0))
+                    .unwrap()
+            )
+            .unwrap(),
+            serde_json::to_value(
+                estimator
+                    .calculate_estimates(&snapshots, Some(6.0))
+                    .unwrap()
+            )
+            .unwrap()
+        );
+    }
 }
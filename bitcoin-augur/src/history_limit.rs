@@ -0,0 +1,224 @@
+use chrono::Duration;
+
+use crate::{
+    error::{AugurError, Result},
+    MempoolSnapshot,
+};
+
+/// Bounds on the snapshot history a single estimate may read.
+///
+/// Estimation time and memory grow with the number of snapshots, so a caller passing months of
+/// history by mistake can tie up a machine for minutes and gigabytes. A limit caps the history
+/// by age, measured back from the newest snapshot, and by count. Set on an estimator with
+/// [`with_max_history`](crate::FeeEstimator::with_max_history); histories over the limit are
+/// rejected with [`AugurError::HistoryTooLong`] unless the limit truncates them.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{HistoryLimit, MempoolSnapshot};
+/// use chrono::{Duration, Utc};
+/// use std::collections::BTreeMap;
+///
+/// let start = Utc::now();
+/// let snapshots: Vec<_> = (0..10)
+///     .map(|i| MempoolSnapshot::new(850_000, start + Duration::minutes(i), BTreeMap::new()))
+///     .collect();
+///
+/// let limit = HistoryLimit::by_count(4).unwrap();
+/// assert!(limit.apply(&snapshots).is_err());
+///
+/// // Truncating keeps the newest snapshots instead
+/// let kept = limit.truncating().apply(&snapshots).unwrap();
+/// assert_eq!(kept.len(), 4);
+/// assert_eq!(kept[0].timestamp, snapshots[6].timestamp);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HistoryLimit {
+    max_age: Option<Duration>,
+    max_snapshots: Option<usize>,
+    truncate: bool,
+}
+
+impl HistoryLimit {
+    /// Limits the history to snapshots at most `max_age` older than the newest one.
+    ///
+    /// # Errors
+    /// If `max_age` is not positive
+    pub fn by_age(max_age: Duration) -> Result<Self> {
+        Self::unbounded().with_max_age(max_age)
+    }
+
+    /// Limits the history to `max_snapshots` snapshots.
+    ///
+    /// # Errors
+    /// If `max_snapshots` is zero
+    pub fn by_count(max_snapshots: usize) -> Result<Self> {
+        Self::unbounded().with_max_snapshots(max_snapshots)
+    }
+
+    /// A limit nothing exceeds, to add bounds to
+    fn unbounded() -> Self {
+        Self {
+            max_age: None,
+            max_snapshots: None,
+            truncate: false,
+        }
+    }
+
+    /// Also limits the history to snapshots at most `max_age` older than the newest one.
+    ///
+    /// # Errors
+    /// If `max_age` is not positive
+    pub fn with_max_age(mut self, max_age: Duration) -> Result<Self> {
+        if max_age <= Duration::zero() {
+            return Err(AugurError::invalid_config(
+                "Maximum history age must be positive",
+            ));
+        }
+        self.max_age = Some(max_age);
+        Ok(self)
+    }
+
+    /// Also limits the history to `max_snapshots` snapshots.
+    ///
+    /// # Errors
+    /// If `max_snapshots` is zero
+    pub fn with_max_snapshots(mut self, max_snapshots: usize) -> Result<Self> {
+        if max_snapshots == 0 {
+            return Err(AugurError::invalid_config(
+                "Maximum history must allow at least one snapshot",
+            ));
+        }
+        self.max_snapshots = Some(max_snapshots);
+        Ok(self)
+    }
+
+    /// Drops the oldest snapshots of a history over the limit rather than rejecting it.
+    pub fn truncating(mut self) -> Self {
+        self.truncate = true;
+        self
+    }
+
+    /// Oldest snapshot age allowed, measured back from the newest snapshot, if limited.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// Most snapshots allowed, if limited.
+    pub fn max_snapshots(&self) -> Option<usize> {
+        self.max_snapshots
+    }
+
+    /// Whether histories over the limit are truncated rather than rejected.
+    pub fn is_truncating(&self) -> bool {
+        self.truncate
+    }
+
+    /// Returns the snapshots within the limit, oldest first.
+    ///
+    /// # Errors
+    /// [`AugurError::HistoryTooLong`] if any snapshot is over the limit and the limit does not
+    /// truncate
+    pub fn apply(&self, snapshots: &[MempoolSnapshot]) -> Result<Vec<MempoolSnapshot>> {
+        let mut ordered: Vec<&MempoolSnapshot> = snapshots.iter().collect();
+        ordered.sort_by_key(|snapshot| snapshot.timestamp);
+
+        let mut first = 0;
+        if let (Some(max_age), Some(newest)) = (self.max_age, ordered.last()) {
+            let oldest_allowed = newest.timestamp - max_age;
+            first = ordered.partition_point(|snapshot| snapshot.timestamp < oldest_allowed);
+        }
+        if let Some(max_snapshots) = self.max_snapshots {
+            first = first.max(ordered.len().saturating_sub(max_snapshots));
+        }
+
+        if first > 0 && !self.truncate {
+            return Err(AugurError::history_too_long(format!(
+                "{count} snapshots exceed the maximum history of {limit}",
+                count = ordered.len(),
+                limit = self.describe(),
+            )));
+        }
+        Ok(ordered[first..]
+            .iter()
+            .map(|&snapshot| snapshot.clone())
+            .collect())
+    }
+
+    /// The bounds in words, for error messages
+    fn describe(&self) -> String {
+        let age = self
+            .max_age
+            .map(|age| format!("{minutes} minutes", minutes = age.num_minutes()));
+        let count = self.max_snapshots.map(|count| format!("{count} snapshots"));
+        match (age, count) {
+            (Some(age), Some(count)) => format!("{age} and {count}"),
+            (Some(bound), None) | (None, Some(bound)) => bound,
+            (None, None) => "no bounds".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::collections::BTreeMap;
+
+    fn snapshots(count: i64) -> Vec<MempoolSnapshot> {
+        let start = Utc::now();
+        (0..count)
+            .rev()
+            .map(|i| MempoolSnapshot::new(850_000, start + Duration::minutes(i), BTreeMap::new()))
+            .collect()
+    }
+
+    #[test]
+    fn test_within_limit_is_kept_in_order() {
+        let history = snapshots(10);
+        let limit = HistoryLimit::by_age(Duration::hours(1))
+            .unwrap()
+            .with_max_snapshots(10)
+            .unwrap();
+
+        let kept = limit.apply(&history).unwrap();
+        assert_eq!(kept.len(), 10);
+        assert!(kept
+            .windows(2)
+            .all(|pair| pair[0].timestamp < pair[1].timestamp));
+    }
+
+    #[test]
+    fn test_oversized_history_is_rejected_or_truncated() {
+        let history = snapshots(10);
+        let newest = history.iter().map(|s| s.timestamp).max().unwrap();
+
+        let by_age = HistoryLimit::by_age(Duration::minutes(3)).unwrap();
+        assert!(matches!(
+            by_age.apply(&history),
+            Err(AugurError::HistoryTooLong(_))
+        ));
+        let kept = by_age.truncating().apply(&history).unwrap();
+        assert_eq!(kept.len(), 4);
+        assert_eq!(kept[0].timestamp, newest - Duration::minutes(3));
+
+        // The tighter bound wins
+        let both = HistoryLimit::by_age(Duration::minutes(5))
+            .unwrap()
+            .with_max_snapshots(2)
+            .unwrap()
+            .truncating();
+        let kept = both.apply(&history).unwrap();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[1].timestamp, newest);
+
+        assert!(HistoryLimit::by_count(1).unwrap().apply(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_limits() {
+        assert!(HistoryLimit::by_count(0).is_err());
+        assert!(HistoryLimit::by_age(Duration::zero()).is_err());
+        assert!(HistoryLimit::by_age(Duration::minutes(-5)).is_err());
+    }
+}
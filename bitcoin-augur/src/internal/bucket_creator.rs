@@ -1,9 +1,13 @@
 use crate::mempool_transaction::MempoolTransaction;
+use chrono::{DateTime, Duration, Utc};
 use std::collections::BTreeMap;
 
 /// Maximum bucket index.
 pub const BUCKET_MAX: i32 = 10000;
 
+/// Buckets per unit of ln(sat/vB): a bucket's index is its fee rate's logarithm times this.
+pub const BUCKET_SCALE: f64 = 100.0;
+
 /// Creates a bucket map from mempool transactions where the key is the bucket index
 /// and the value is the sum of the weights at that fee rate, normalized to a one block duration.
 ///
@@ -16,24 +20,60 @@ pub fn create_fee_rate_buckets(transactions: &[MempoolTransaction]) -> BTreeMap<
         let fee_rate = tx.fee_rate();
         if fee_rate > 0.0 {
             let bucket_index = calculate_bucket_index(fee_rate);
-            *buckets.entry(bucket_index).or_insert(0) += tx.weight;
+            let total = buckets.entry(bucket_index).or_insert(0);
+            // Node-reported weights are untrusted; saturate rather than overflow
+            *total = total.saturating_add(tx.weight);
+        }
+    }
+
+    buckets
+}
+
+/// Creates a bucket map like [`create_fee_rate_buckets`], summing the output values of the
+/// transactions that carry one instead of their weights.
+pub fn create_value_buckets(transactions: &[MempoolTransaction]) -> BTreeMap<i32, u64> {
+    let mut buckets: BTreeMap<i32, u64> = BTreeMap::new();
+
+    for tx in transactions {
+        let Some(value) = tx.value else {
+            continue;
+        };
+        let fee_rate = tx.fee_rate();
+        if fee_rate > 0.0 {
+            let total = buckets.entry(calculate_bucket_index(fee_rate)).or_insert(0);
+            *total = total.saturating_add(value);
         }
     }
 
     buckets
 }
 
+/// Creates a bucket map like [`create_fee_rate_buckets`], over only the transactions that
+/// entered the mempool at least `age` before `now`.
+pub fn create_aged_buckets(
+    transactions: &[MempoolTransaction],
+    now: DateTime<Utc>,
+    age: Duration,
+) -> BTreeMap<i32, u64> {
+    let aged: Vec<MempoolTransaction> = transactions
+        .iter()
+        .filter(|tx| tx.time.is_some_and(|time| now - time >= age))
+        .copied()
+        .collect();
+    create_fee_rate_buckets(&aged)
+}
+
 /// Calculates bucket index using logarithms, providing more precision in the lower fee levels.
 ///
 /// The formula is: min(round(ln(fee_rate) * 100), BUCKET_MAX)
 ///
 /// This matches the Kotlin implementation's logarithmic bucketing.
-fn calculate_bucket_index(fee_rate: f64) -> i32 {
+pub fn calculate_bucket_index(fee_rate: f64) -> i32 {
     if fee_rate <= 0.0 {
         return 0;
     }
 
-    let index = (fee_rate.ln() * 100.0).round() as i32;
+    let index = (fee_rate.ln() * BUCKET_SCALE).round() as i32;
     index.min(BUCKET_MAX)
 }
 
@@ -78,6 +118,21 @@ mod tests {
         assert_eq!(buckets.get(&bucket_4_satvb), Some(&600));
     }
 
+    #[test]
+    fn test_create_aged_buckets() {
+        let now = Utc::now();
+        let transactions = vec![
+            MempoolTransaction::new(400, 1000).with_time(now - Duration::hours(30)),
+            MempoolTransaction::new(400, 1000).with_time(now - Duration::minutes(5)),
+            MempoolTransaction::new(600, 600), // Entry time unknown
+        ];
+
+        let buckets = create_aged_buckets(&transactions, now, Duration::hours(24));
+
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets.get(&calculate_bucket_index(10.0)), Some(&400));
+    }
+
     #[test]
     fn test_empty_transactions() {
         let transactions = vec![];
@@ -85,6 +140,20 @@ mod tests {
         assert!(buckets.is_empty());
     }
 
+    #[test]
+    fn test_bucket_weight_saturates() {
+        let transactions = vec![
+            MempoolTransaction::new(u64::MAX, u64::MAX),
+            MempoolTransaction::new(u64::MAX, u64::MAX),
+        ];
+
+        let buckets = create_fee_rate_buckets(&transactions);
+        assert_eq!(
+            buckets.values().copied().collect::<Vec<_>>(),
+            vec![u64::MAX]
+        );
+    }
+
     #[test]
     fn test_zero_fee_transactions() {
         let transactions = vec![
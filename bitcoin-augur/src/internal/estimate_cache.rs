@@ -0,0 +1,184 @@
+use chrono::{DateTime, Duration, Utc};
+use ndarray::Array1;
+use std::collections::HashMap;
+
+use crate::internal::{InflowCache, SnapshotArray};
+use crate::mempool_snapshot::MempoolSnapshot;
+
+/// Block height and timestamp identifying a snapshot
+type SnapshotKey = (u32, DateTime<Utc>);
+
+/// Work an incremental estimator keeps between estimates.
+///
+/// Consecutive estimates share all but their newest snapshots, so each snapshot is converted to
+/// bucket arrays once, and each block's growth is compared once. The long-term inflows of the
+/// latest estimate are also kept, for recomputing it after the next block's first snapshot.
+#[derive(Debug, Default)]
+pub(crate) struct EstimateCache {
+    /// Growth of each block's snapshot pair
+    pub inflows: InflowCache,
+    /// Converted snapshots by block height and timestamp
+    arrays: HashMap<SnapshotKey, CachedArray>,
+    /// Long-term inflows of each inflow window pair, with the newest snapshot they reach
+    long_term_inflows: HashMap<(Duration, Duration), (SnapshotKey, Array1<f64>)>,
+    /// Number of conversions made with the cache
+    conversions: u64,
+    /// Number of snapshots that had to be converted
+    converted: usize,
+}
+
+/// A converted snapshot and when it was last used
+#[derive(Debug)]
+struct CachedArray {
+    array: SnapshotArray,
+    /// Conversion that last used the snapshot
+    used: u64,
+}
+
+impl EstimateCache {
+    /// Conversions a snapshot may go unused before it is forgotten
+    const MAX_IDLE_CONVERSIONS: u64 = 256;
+
+    /// Converts `snapshots`, in order, reusing the arrays of snapshots already seen.
+    pub fn snapshot_arrays(&mut self, snapshots: &[MempoolSnapshot]) -> Vec<SnapshotArray> {
+        self.conversions += 1;
+        let conversions = self.conversions;
+        self.arrays
+            .retain(|_, cached| conversions - cached.used <= Self::MAX_IDLE_CONVERSIONS);
+
+        snapshots
+            .iter()
+            .map(|snapshot| {
+                let cached = self
+                    .arrays
+                    .entry((snapshot.block_height, snapshot.timestamp))
+                    .or_insert_with(|| {
+                        self.converted += 1;
+                        CachedArray {
+                            array: SnapshotArray::from_snapshot(snapshot),
+                            used: 0,
+                        }
+                    });
+                cached.used = conversions;
+                cached.array.clone()
+            })
+            .collect()
+    }
+
+    /// Long-term inflows over `windows` to reuse for `snapshot_arrays`, if its newest snapshot is
+    /// the first of a new block and the rest end where the latest estimate over `windows` did.
+    pub fn long_term_inflows_after_block(
+        &self,
+        snapshot_arrays: &[SnapshotArray],
+        windows: (Duration, Duration),
+    ) -> Option<Array1<f64>> {
+        let [.., previous, newest] = snapshot_arrays else {
+            return None;
+        };
+        let (key, inflows) = self.long_term_inflows.get(&windows)?;
+        (*key == (previous.block_height, previous.timestamp)
+            && newest.block_height > previous.block_height)
+            .then(|| inflows.clone())
+    }
+
+    /// Keeps the long-term inflows over `windows` of snapshots ending with `newest`.
+    pub fn keep_long_term_inflows(
+        &mut self,
+        newest: &SnapshotArray,
+        windows: (Duration, Duration),
+        inflows: &Array1<f64>,
+    ) {
+        self.long_term_inflows.insert(
+            windows,
+            ((newest.block_height, newest.timestamp), inflows.clone()),
+        );
+    }
+
+    /// Forgets everything kept, for when stored snapshots are replaced.
+    pub fn clear(&mut self) {
+        self.inflows.clear();
+        self.arrays.clear();
+        self.long_term_inflows.clear();
+    }
+
+    /// Number of snapshots that had to be converted so far.
+    #[allow(dead_code)]
+    pub fn converted(&self) -> usize {
+        self.converted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MempoolTransaction;
+
+    fn snapshots(count: u32) -> Vec<MempoolSnapshot> {
+        let base = Utc::now();
+        (0..count)
+            .map(|i| {
+                MempoolSnapshot::from_transactions(
+                    vec![MempoolTransaction::new(400, 1000 + u64::from(i) * 100)],
+                    100 + i / 4,
+                    base + Duration::seconds(i64::from(i) * 30),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshots_are_converted_once() {
+        let snapshots = snapshots(12);
+        let mut cache = EstimateCache::default();
+        for end in 1..=snapshots.len() {
+            let arrays = cache.snapshot_arrays(&snapshots[..end]);
+            assert_eq!(arrays.len(), end);
+            let newest = SnapshotArray::from_snapshot(&snapshots[end - 1]);
+            assert_eq!(arrays[end - 1].buckets, newest.buckets);
+        }
+        assert_eq!(cache.converted(), 12);
+
+        // Snapshots left out for long enough are forgotten
+        for _ in 0..=EstimateCache::MAX_IDLE_CONVERSIONS {
+            cache.snapshot_arrays(&snapshots[8..]);
+        }
+        assert_eq!(cache.arrays.len(), 4);
+        cache.snapshot_arrays(&snapshots);
+        assert_eq!(cache.converted(), 20);
+    }
+
+    #[test]
+    fn test_long_term_inflows_are_reused_after_a_block() {
+        let arrays: Vec<_> = snapshots(6)
+            .iter()
+            .map(SnapshotArray::from_snapshot)
+            .collect();
+        let windows = (Duration::minutes(30), Duration::hours(24));
+        let inflows = Array1::from_elem(4, 250.0);
+        let mut cache = EstimateCache::default();
+        cache.keep_long_term_inflows(&arrays[3], windows, &inflows);
+
+        // Snapshot 4 starts block 101 right after snapshot 3
+        assert_eq!(
+            cache.long_term_inflows_after_block(&arrays[..5], windows),
+            Some(inflows.clone())
+        );
+        assert_eq!(
+            cache.long_term_inflows_after_block(&arrays[..4], windows),
+            None
+        );
+        assert_eq!(
+            cache.long_term_inflows_after_block(&arrays[..6], windows),
+            None
+        );
+        let other = (Duration::hours(1), Duration::hours(24));
+        assert_eq!(
+            cache.long_term_inflows_after_block(&arrays[..5], other),
+            None
+        );
+
+        // Snapshot 5 is in the same block as snapshot 4
+        cache.keep_long_term_inflows(&arrays[4], windows, &inflows);
+        assert_eq!(cache.long_term_inflows_after_block(&arrays, windows), None);
+    }
+}
@@ -1,22 +1,42 @@
-use ndarray::{Array1, Array2};
-use statrs::distribution::{DiscreteCDF, Poisson};
+use ndarray::{s, Array1, Array2, ArrayViewMut1, Zip};
+use statrs::distribution::{ContinuousCDF, DiscreteCDF, Gamma, Poisson};
 
 use crate::internal::BUCKET_MAX;
+use crate::MonteCarlo;
 
 /// Core implementation of the fee estimation algorithm.
 ///
 /// This struct simulates the mining of blocks to predict when transactions
 /// with different fee rates would be confirmed.
+#[derive(Clone)]
 pub(crate) struct FeeCalculator {
     probabilities: Vec<f64>,
     block_targets: Vec<f64>,
     expected_blocks: Array2<f64>,
+    monte_carlo: Option<MonteCarlo>,
+    first_block_deferred: Option<Array1<f64>>,
+}
+
+/// What each stage of [`FeeCalculator::stages`] produced, with rows for block targets and
+/// columns for probabilities.
+pub(crate) struct CalculatorStages {
+    /// Bucket index reached by the short-term simulation
+    pub short_term: Array2<f64>,
+    /// Bucket index reached by the long-term simulation
+    pub long_term: Array2<f64>,
+    /// Blend of both bucket indices
+    pub weighted: Array2<f64>,
+    /// Fee rates in sat/vB after monotonicity is enforced
+    pub fee_rates: Array2<f64>,
 }
 
 impl FeeCalculator {
     /// Block size in weight units (4MB = 4,000,000 WU)
     const BLOCK_SIZE_WEIGHT_UNITS: f64 = 4_000_000.0;
 
+    /// Highest confidence level modelled for fast targets; certainty would wait forever
+    const MAX_FAST_PROBABILITY: f64 = 0.999;
+
     /// Creates a new fee calculator with the given probability and block target settings.
     pub fn new(probabilities: Vec<f64>, block_targets: Vec<f64>) -> Self {
         let expected_blocks = Self::calculate_expected_blocks(&probabilities, &block_targets);
@@ -25,9 +45,24 @@ impl FeeCalculator {
             probabilities,
             block_targets,
             expected_blocks,
+            monte_carlo: None,
+            first_block_deferred: None,
         }
     }
 
+    /// Samples block arrival sequences instead of mining the expected number of blocks.
+    pub fn with_monte_carlo(mut self, monte_carlo: Option<MonteCarlo>) -> Self {
+        self.monte_carlo = monte_carlo;
+        self
+    }
+
+    /// Keeps `deferred` weights, which the next block cannot take, out of the first block mined
+    /// by every simulation. Later blocks mine them like any other weight.
+    pub fn with_first_block_deferred(mut self, deferred: Option<Array1<f64>>) -> Self {
+        self.first_block_deferred = deferred;
+        self
+    }
+
     /// Calculates fee estimates based on mempool snapshot and inflow data.
     ///
     /// # Arguments
@@ -54,29 +89,136 @@ impl FeeCalculator {
             return Array2::from_elem((self.block_targets.len(), self.probabilities.len()), None);
         }
 
+        let monotone_fee_rates = self
+            .stages(mempool_snapshot, short_inflows, long_inflows)
+            .fee_rates;
+        debug_assert!(
+            Self::within_fee_ceiling(
+                &monotone_fee_rates,
+                &[mempool_snapshot, short_inflows, long_inflows]
+            ),
+            "Fee estimates {monotone_fee_rates:?} exceed the highest occupied bucket plus one"
+        );
+
+        // Create final result array with values filtered by maximum threshold
+        self.prepare_result_array(&monotone_fee_rates)
+    }
+
+    /// Runs every stage of [`get_fee_estimates`](Self::get_fee_estimates) up to the monotone fee
+    /// rates, keeping what each stage produced.
+    pub fn stages(
+        &self,
+        mempool_snapshot: &Array1<f64>,
+        short_inflows: &Array1<f64>,
+        long_inflows: &Array1<f64>,
+    ) -> CalculatorStages {
         // Add half of short-term inflows as a buffer to current weights
         let current_weights_with_buffer = mempool_snapshot + short_inflows / 2.0;
 
         // Run simulations for short and long-term intervals
-        let short_term_estimates =
-            self.run_simulations(&current_weights_with_buffer, short_inflows);
-
-        let long_term_estimates = self.run_simulations(&current_weights_with_buffer, long_inflows);
+        let (short_term, long_term) = match &self.monte_carlo {
+            Some(monte_carlo) => (
+                self.run_monte_carlo(monte_carlo, &current_weights_with_buffer, short_inflows),
+                self.run_monte_carlo(monte_carlo, &current_weights_with_buffer, long_inflows),
+            ),
+            None => (
+                self.run_simulations(&current_weights_with_buffer, short_inflows),
+                self.run_simulations(&current_weights_with_buffer, long_inflows),
+            ),
+        };
 
         // Combine estimates with appropriate weighting
-        let weighted_estimates =
-            self.get_weighted_estimates(&short_term_estimates, &long_term_estimates);
+        let weighted = self.get_weighted_estimates(&short_term, &long_term);
 
         // Convert bucket indices to actual fee rates
-        let fee_rates = self.convert_buckets_to_fee_rates(&weighted_estimates);
+        let fee_rates = self.convert_buckets_to_fee_rates(&weighted);
 
         // Ensure fee rates are monotonically decreasing with block targets
-        let monotone_fee_rates = self.enforce_monotonicity(&fee_rates);
+        let fee_rates = self.enforce_monotonicity(&fee_rates);
 
-        // Create final result array with values filtered by maximum threshold
+        CalculatorStages {
+            short_term,
+            long_term,
+            weighted,
+            fee_rates,
+        }
+    }
+
+    /// Calculates fee estimates for targets of one or two blocks from short-term inflows alone.
+    ///
+    /// Exactly the target number of blocks is mined from the current mempool. Short-term inflow
+    /// keeps arriving until they are found, for the time the blocks take at each confidence
+    /// level: the probability quantile of a Gamma distributed wait of `target` block intervals.
+    /// Higher confidence therefore expects slower blocks and more competing transactions.
+    ///
+    /// # Returns
+    /// A 2D array of fee estimates shaped like [`get_fee_estimates`](Self::get_fee_estimates).
+    pub fn get_fast_fee_estimates(
+        &self,
+        mempool_snapshot: &Array1<f64>,
+        short_inflows: &Array1<f64>,
+    ) -> Array2<Option<f64>> {
+        if mempool_snapshot.sum() == 0.0 && short_inflows.sum() == 0.0 {
+            return Array2::from_elem((self.block_targets.len(), self.probabilities.len()), None);
+        }
+
+        let mut bucket_estimates =
+            Array2::zeros((self.block_targets.len(), self.probabilities.len()));
+        for (i, &target) in self.block_targets.iter().enumerate() {
+            let Ok(wait) = Gamma::new(target, 1.0) else {
+                continue;
+            };
+            let blocks = target as usize;
+
+            for (j, &probability) in self.probabilities.iter().enumerate() {
+                let intervals = wait.inverse_cdf(probability.min(Self::MAX_FAST_PROBABILITY));
+                let added_weights_in_one_block = short_inflows * (intervals / target);
+
+                let mut current_weights = mempool_snapshot.clone();
+                for block in 0..blocks {
+                    current_weights += &added_weights_in_one_block;
+                    if block == 0 {
+                        self.mine_first_block_in_place(current_weights.view_mut(), 0);
+                    } else {
+                        current_weights = self.mine_block(&current_weights);
+                    }
+                }
+                bucket_estimates[[i, j]] = self.find_best_index(&current_weights) as f64;
+            }
+        }
+
+        let fee_rates = self.convert_buckets_to_fee_rates(&bucket_estimates);
+        let monotone_fee_rates = self.enforce_monotonicity(&fee_rates);
+        debug_assert!(
+            Self::within_fee_ceiling(&monotone_fee_rates, &[mempool_snapshot, short_inflows]),
+            "Fast fee estimates {monotone_fee_rates:?} exceed the highest occupied bucket plus one"
+        );
         self.prepare_result_array(&monotone_fee_rates)
     }
 
+    /// Whether every fee rate is at most one bucket above the highest fee rate bucket holding
+    /// weight in any of `weights`.
+    ///
+    /// A simulation settles one bucket above the highest bucket it could not fully mine, so no
+    /// estimate can pay more than the most generous transaction it competes with. A rate above
+    /// that ceiling means a bucket index was converted the wrong way.
+    fn within_fee_ceiling(fee_rates: &Array2<f64>, weights: &[&Array1<f64>]) -> bool {
+        // Buckets are stored in reverse order, so the lowest occupied index is the highest bucket
+        let Some(highest_index) = weights
+            .iter()
+            .filter_map(|w| w.iter().position(|&weight| weight > 0.0))
+            .min()
+        else {
+            // Nothing to compete with, every simulation settles at the cheapest bucket
+            return fee_rates.iter().all(|&rate| rate <= 1.0);
+        };
+        let ceiling_bucket = (BUCKET_MAX as usize - highest_index + 1) as f64;
+        let ceiling = (ceiling_bucket / 100.0).exp();
+
+        // Blending short- and long-term buckets may round just above the ceiling
+        fee_rates.iter().all(|&rate| rate <= ceiling * (1.0 + 1e-9))
+    }
+
     /// Runs simulations for all block target and probability combinations.
     fn run_simulations(
         &self,
@@ -89,7 +231,7 @@ impl FeeCalculator {
             let mean_blocks = blocks as usize;
 
             for (prob_idx, _) in self.probabilities.iter().enumerate() {
-                let expected_blocks = self.expected_blocks[[block_idx, prob_idx]] as usize;
+                let expected_blocks = self.expected_blocks(block_idx, prob_idx);
 
                 // Run individual simulation
                 let bucket_index = self.run_simulation(
@@ -106,6 +248,74 @@ impl FeeCalculator {
         result
     }
 
+    /// Runs simulations for all block target and probability combinations by mining sampled
+    /// block arrival sequences.
+    ///
+    /// Every sequence of a target is mined once, adding the inflow of each wait before its
+    /// block. A probability's bucket index is the nearest-rank quantile of the sequences'
+    /// indices, so that share of them confirms a transaction in that bucket. Sequences finding
+    /// no block never confirm; when the quantile falls among them the highest index reached by
+    /// any other sequence is used.
+    fn run_monte_carlo(
+        &self,
+        monte_carlo: &MonteCarlo,
+        initial_weights: &Array1<f64>,
+        added_weights: &Array1<f64>,
+    ) -> Array2<f64> {
+        let mut result = Array2::zeros((self.block_targets.len(), self.probabilities.len()));
+
+        for (block_idx, &target) in self.block_targets.iter().enumerate() {
+            let arrivals = monte_carlo.block_arrivals(target);
+            let mut bucket_indices: Vec<usize> = arrivals
+                .iter()
+                .filter(|waits| !waits.is_empty())
+                .map(|waits| {
+                    let current_weights = self.mine_arrivals(initial_weights, added_weights, waits);
+                    self.find_best_index(&current_weights)
+                })
+                .collect();
+            bucket_indices.sort_unstable();
+
+            for (prob_idx, &probability) in self.probabilities.iter().enumerate() {
+                let rank = ((probability * arrivals.len() as f64).ceil() as usize).max(1);
+                let bucket_index = bucket_indices
+                    .get(rank - 1)
+                    .or(bucket_indices.last())
+                    .copied()
+                    .unwrap_or(0);
+                result[[block_idx, prob_idx]] = bucket_index as f64;
+            }
+        }
+
+        result
+    }
+
+    /// Mines a block after each of `waits`, adding `added_weights` for every block interval
+    /// waited, and returns the weights left in each bucket.
+    fn mine_arrivals(
+        &self,
+        initial_weights: &Array1<f64>,
+        added_weights: &Array1<f64>,
+        waits: &[f64],
+    ) -> Array1<f64> {
+        let mut current_weights = initial_weights.clone();
+        let Some((first, last)) = Self::occupied_span(initial_weights, added_weights) else {
+            return current_weights;
+        };
+
+        let added_weights = added_weights.slice(s![first..=last]);
+        let mut span = current_weights.slice_mut(s![first..=last]);
+        for (block, &wait) in waits.iter().enumerate() {
+            span.scaled_add(wait, &added_weights);
+            if block == 0 {
+                self.mine_first_block_in_place(span.view_mut(), first);
+            } else {
+                Self::mine_block_in_place(span.view_mut());
+            }
+        }
+        current_weights
+    }
+
     /// Simulates mining blocks and returns the bucket index of the lowest fee rate
     /// that would result in the transaction getting mined.
     fn run_simulation(
@@ -119,42 +329,122 @@ impl FeeCalculator {
             return None;
         }
 
+        let current_weights =
+            self.mine_blocks(initial_weights, added_weights, expected_blocks, mean_blocks);
+
+        // Find the index of the last fully mined bucket
+        Some(self.find_best_index(&current_weights))
+    }
+
+    /// Mines `expected_blocks` blocks while `added_weights` arrive over `mean_blocks` block
+    /// intervals, and returns the weights left in each bucket.
+    pub fn mine_blocks(
+        &self,
+        initial_weights: &Array1<f64>,
+        added_weights: &Array1<f64>,
+        expected_blocks: usize,
+        mean_blocks: usize,
+    ) -> Array1<f64> {
+        let mut current_weights = initial_weights.clone();
+        let Some((first, last)) = Self::occupied_span(initial_weights, added_weights) else {
+            return current_weights;
+        };
+
         // Calculate how much of the added weights to use per block
-        let expected_mining_time_factor = mean_blocks as f64 / expected_blocks as f64;
-        let added_weights_in_one_block = added_weights * expected_mining_time_factor;
+        let expected_mining_time_factor = mean_blocks as f64 / expected_blocks.max(1) as f64;
+        let added_weights_in_one_block =
+            &added_weights.slice(s![first..=last]) * expected_mining_time_factor;
 
         // Mine the expected number of blocks
-        let mut current_weights = initial_weights.clone();
-        for _ in 0..expected_blocks {
-            current_weights += &added_weights_in_one_block;
-            current_weights = self.mine_block(&current_weights);
+        let mut span = current_weights.slice_mut(s![first..=last]);
+        for block in 0..expected_blocks {
+            span += &added_weights_in_one_block;
+            if block == 0 {
+                self.mine_first_block_in_place(span.view_mut(), first);
+            } else {
+                Self::mine_block_in_place(span.view_mut());
+            }
         }
+        current_weights
+    }
 
-        // Find the index of the last fully mined bucket
-        Some(self.find_best_index(&current_weights))
+    /// First and last bucket holding weight in either array.
+    ///
+    /// Buckets empty in both arrays stay empty, so only the span between them is mined. A
+    /// mempool spans a few hundred of the buckets.
+    fn occupied_span(
+        initial_weights: &Array1<f64>,
+        added_weights: &Array1<f64>,
+    ) -> Option<(usize, usize)> {
+        let occupied = |weights: &Array1<f64>| {
+            let first = weights.iter().position(|&weight| weight != 0.0)?;
+            let last = weights.iter().rposition(|&weight| weight != 0.0)?;
+            Some((first, last))
+        };
+        match (occupied(initial_weights), occupied(added_weights)) {
+            (Some((a, b)), Some((c, d))) => Some((a.min(c), b.max(d))),
+            (Some(span), None) | (None, Some(span)) => Some(span),
+            (None, None) => None,
+        }
+    }
+
+    /// Number of blocks simulated for every target (rows) and probability (columns).
+    pub fn expected_blocks_matrix(&self) -> &Array2<f64> {
+        &self.expected_blocks
+    }
+
+    /// Number of blocks simulated for the target and probability at the given indices.
+    pub fn expected_blocks(&self, block_idx: usize, prob_idx: usize) -> usize {
+        self.expected_blocks[[block_idx, prob_idx]] as usize
+    }
+
+    /// Weight of the long-term simulation when blending it with the short-term one.
+    ///
+    /// The weight increases quadratically with the block target, reaching 1 at 144 blocks.
+    pub fn long_term_weight(target: f64) -> f64 {
+        1.0 - (1.0 - target / 144.0).powi(2)
     }
 
     /// Mines a block by removing the highest fee rate transactions (lowest indices)
     /// until the block size is reached.
     fn mine_block(&self, current_weights: &Array1<f64>) -> Array1<f64> {
         let mut weights_remaining = current_weights.clone();
+        Self::mine_block_in_place(weights_remaining.view_mut());
+        weights_remaining
+    }
+
+    /// Mines a block like [`mine_block`](Self::mine_block), removing the weights from `weights`.
+    fn mine_block_in_place(mut weights: ArrayViewMut1<f64>) {
         let mut weight_units_remaining = Self::BLOCK_SIZE_WEIGHT_UNITS;
 
-        for i in 0..weights_remaining.len() {
-            let removed_weight = weights_remaining[i].min(weight_units_remaining);
+        for weight in weights.iter_mut() {
+            let removed_weight = weight.min(weight_units_remaining);
             weight_units_remaining -= removed_weight;
-            weights_remaining[i] -= removed_weight;
+            *weight -= removed_weight;
 
             if weight_units_remaining <= 0.0 {
                 break;
             }
         }
+    }
 
-        weights_remaining
+    /// Mines the first block like [`mine_block_in_place`](Self::mine_block_in_place), holding
+    /// back the deferred weights of the buckets `weights` spans from `first` on.
+    fn mine_first_block_in_place(&self, mut weights: ArrayViewMut1<f64>, first: usize) {
+        let Some(deferred) = &self.first_block_deferred else {
+            return Self::mine_block_in_place(weights);
+        };
+        let deferred = deferred.slice(s![first..first + weights.len()]);
+        let held = Zip::from(&weights)
+            .and(&deferred)
+            .map_collect(|&weight, &deferred| weight.min(deferred));
+        weights -= &held;
+        Self::mine_block_in_place(weights.view_mut());
+        weights += &held;
     }
 
     /// Finds the index of the last bucket that is fully mined.
-    fn find_best_index(&self, weights_remaining: &Array1<f64>) -> usize {
+    pub fn find_best_index(&self, weights_remaining: &Array1<f64>) -> usize {
         // Find first non-zero remaining weight
         for (i, &weight) in weights_remaining.iter().enumerate() {
             if weight > 0.0 {
@@ -180,8 +470,7 @@ impl FeeCalculator {
         let mut weighted_estimates = Array2::zeros(short_estimates.dim());
 
         for (i, &target) in self.block_targets.iter().enumerate() {
-            // Weight increases quadratically with block target
-            let weight = 1.0 - (1.0 - target / 144.0).powi(2);
+            let weight = Self::long_term_weight(target);
 
             for j in 0..self.probabilities.len() {
                 weighted_estimates[[i, j]] =
@@ -334,6 +623,67 @@ mod tests {
         assert_eq!(remaining[2], 2_000_000.0); // Partially mined (1M of 3M)
     }
 
+    #[test]
+    fn test_deferred_weight_waits_for_the_second_block() {
+        let mut weights = Array1::zeros(10);
+        weights[2] = 3_000_000.0; // High fee, 2M of it beyond the package limits
+        weights[5] = 4_000_000.0; // Low fee
+        let mut deferred = Array1::zeros(10);
+        deferred[2] = 2_000_000.0;
+        let inflows = Array1::zeros(10);
+
+        let calculator = FeeCalculator::new(vec![0.5], vec![6.0])
+            .with_first_block_deferred(Some(deferred.clone()));
+        let one_block = calculator.mine_blocks(&weights, &inflows, 1, 1);
+        assert_eq!(one_block[2], 2_000_000.0);
+        assert_eq!(one_block[5], 1_000_000.0);
+
+        // The deferred weight goes first once its ancestors are mined
+        let two_blocks = calculator.mine_blocks(&weights, &inflows, 2, 2);
+        assert_eq!(two_blocks[2], 0.0);
+        assert_eq!(two_blocks[5], 0.0);
+
+        // The same with sampled arrivals and the fast model
+        let sampled = calculator.mine_arrivals(&weights, &inflows, &[1.0]);
+        assert_eq!(sampled, one_block);
+        let mut first = weights.clone();
+        calculator.mine_first_block_in_place(first.view_mut(), 0);
+        assert_eq!(first, one_block);
+
+        // Without deferred weight the high fee bucket is mined first
+        let plain = FeeCalculator::new(vec![0.5], vec![6.0]).mine_blocks(&weights, &inflows, 1, 1);
+        assert_eq!(plain[2], 0.0);
+        assert_eq!(plain[5], 3_000_000.0);
+    }
+
+    #[test]
+    fn test_within_fee_ceiling() {
+        let mut weights = Array1::zeros(BUCKET_MAX as usize + 1);
+        // Bucket 300, about 20.09 sat/vB
+        weights[BUCKET_MAX as usize - 300] = 1_000.0;
+        let inflows = Array1::zeros(BUCKET_MAX as usize + 1);
+
+        let one_above = (301.0f64 / 100.0).exp();
+        let rates = Array2::from_elem((1, 2), one_above);
+        assert!(FeeCalculator::within_fee_ceiling(
+            &rates,
+            &[&weights, &inflows]
+        ));
+
+        let two_above = Array2::from_elem((1, 2), (302.0f64 / 100.0).exp());
+        assert!(!FeeCalculator::within_fee_ceiling(
+            &two_above,
+            &[&weights, &inflows]
+        ));
+
+        // Empty weights only allow the cheapest rate
+        assert!(FeeCalculator::within_fee_ceiling(
+            &Array2::from_elem((1, 1), 1.0),
+            &[&inflows]
+        ));
+        assert!(!FeeCalculator::within_fee_ceiling(&rates, &[&inflows]));
+    }
+
     #[test]
     fn test_find_best_index() {
         let calculator = FeeCalculator::new(vec![0.5], vec![6.0]);
@@ -749,4 +1099,32 @@ mod tests {
             }
         }
     }
+    #[test]
+    fn test_fast_fee_estimates() {
+        let calculator = FeeCalculator::new(vec![0.05, 0.5, 0.95], vec![1.0, 2.0]);
+        let index = |bucket: i32| (BUCKET_MAX - bucket) as usize;
+
+        // 6M WU waiting, half of it paying far more, and 2M WU per block of mid fee inflow
+        let mut mempool = Array1::zeros(BUCKET_MAX as usize + 1);
+        mempool[index(500)] = 3_000_000.0;
+        mempool[index(100)] = 3_000_000.0;
+        let mut inflows = Array1::zeros(BUCKET_MAX as usize + 1);
+        inflows[index(300)] = 2_000_000.0;
+
+        let estimates = calculator.get_fast_fee_estimates(&mempool, &inflows);
+        let rate = |i: usize, j: usize| estimates[[i, j]].unwrap();
+
+        // A quick block leaves room below the inflow, a median one does not
+        assert!((rate(0, 0) - (1.01f64).exp()).abs() < 1e-9);
+        assert!((rate(0, 1) - (3.01f64).exp()).abs() < 1e-9);
+        assert!(rate(0, 2) >= rate(0, 1));
+
+        // Two blocks clear the inflow at median speed
+        assert!(rate(1, 1) < rate(0, 1));
+
+        // Nothing waiting and nothing arriving
+        let empty = Array1::zeros(BUCKET_MAX as usize + 1);
+        let estimates = calculator.get_fast_fee_estimates(&empty, &empty);
+        assert!(estimates.iter().all(Option::is_none));
+    }
 }
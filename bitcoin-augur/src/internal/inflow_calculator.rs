@@ -1,5 +1,6 @@
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use ndarray::Array1;
+use std::collections::HashMap;
 
 use crate::internal::{snapshot_array::SnapshotArray, BUCKET_MAX};
 
@@ -19,16 +20,40 @@ impl InflowCalculator {
     /// # Returns
     /// Array of inflow rates by fee rate bucket, normalized to 10 minutes
     pub fn calculate_inflows(snapshots: &[SnapshotArray], timeframe: Duration) -> Array1<f64> {
+        Self::calculate_inflows_with(snapshots, timeframe, None)
+    }
+
+    /// Calculates inflow rates like [`calculate_inflows`](Self::calculate_inflows), reusing the
+    /// bucket growth of snapshot pairs already seen by `cache`.
+    pub fn calculate_inflows_cached(
+        snapshots: &[SnapshotArray],
+        timeframe: Duration,
+        cache: &mut InflowCache,
+    ) -> Array1<f64> {
+        Self::calculate_inflows_with(snapshots, timeframe, Some(cache))
+    }
+
+    fn calculate_inflows_with(
+        snapshots: &[SnapshotArray],
+        timeframe: Duration,
+        mut cache: Option<&mut InflowCache>,
+    ) -> Array1<f64> {
+        if let Some(cache) = cache.as_deref_mut() {
+            cache.begin();
+        }
+
         if snapshots.is_empty() {
             return Array1::zeros(BUCKET_MAX as usize + 1);
         }
 
         // Sort snapshots by timestamp
-        let mut ordered_snapshots = snapshots.to_vec();
+        let mut ordered_snapshots: Vec<&SnapshotArray> = snapshots.iter().collect();
         ordered_snapshots.sort_by_key(|s| s.timestamp);
 
         let end_time = ordered_snapshots.last().unwrap().timestamp;
-        let start_time = end_time - timeframe;
+        let start_time = end_time
+            .checked_sub_signed(timeframe)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
 
         // Filter snapshots within timeframe
         let relevant_snapshots: Vec<_> = ordered_snapshots
@@ -69,14 +94,17 @@ impl InflowCalculator {
             let block_duration = last_snapshot.timestamp - first_snapshot.timestamp;
             total_time_span += block_duration;
 
-            // Calculate positive differences (inflows) between buckets
-            let delta = &last_snapshot.buckets - &first_snapshot.buckets;
-
-            // Only keep positive values (inflows)
-            for i in 0..delta.len() {
-                if delta[i] > 0.0 {
-                    inflows[i] += delta[i];
+            // Only keep positive differences (inflows) between buckets
+            let computed;
+            let growth = match cache.as_deref_mut() {
+                Some(cache) => cache.growth(first_snapshot, last_snapshot),
+                None => {
+                    computed = last_snapshot.inflow_since(first_snapshot);
+                    &computed
                 }
+            };
+            for &(i, growth) in growth {
+                inflows[i] += growth;
             }
         }
 
@@ -92,10 +120,76 @@ impl InflowCalculator {
     }
 }
 
+/// Bucket growth between the first and last snapshot of each block, kept between inflow
+/// calculations.
+///
+/// A new snapshot only extends its own block, so recalculating inflows after it arrives computes
+/// a single new pair per window. Snapshots are identified by block height and timestamp.
+#[derive(Debug, Default)]
+pub(crate) struct InflowCache {
+    /// Pairs by block height and first snapshot time
+    pairs: HashMap<(u32, DateTime<Utc>), CachedPair>,
+    /// Number of calculations made with the cache
+    calculations: u64,
+    /// Number of pairs whose growth had to be computed
+    computed: usize,
+}
+
+/// Growth from a block's first snapshot up to the last one seen
+#[derive(Debug)]
+struct CachedPair {
+    last: Option<DateTime<Utc>>,
+    growth: Vec<(usize, f64)>,
+    /// Calculation that last used the pair
+    used: u64,
+}
+
+impl InflowCache {
+    /// Calculations a pair may go unused before it is forgotten
+    const MAX_IDLE_CALCULATIONS: u64 = 256;
+
+    /// Starts a calculation, forgetting pairs that have long stopped falling in a window.
+    fn begin(&mut self) {
+        self.calculations += 1;
+        let calculations = self.calculations;
+        self.pairs
+            .retain(|_, pair| calculations - pair.used <= Self::MAX_IDLE_CALCULATIONS);
+    }
+
+    /// Growth from `first` to `last`, computed only if the pair is new.
+    fn growth(&mut self, first: &SnapshotArray, last: &SnapshotArray) -> &[(usize, f64)] {
+        let pair = self
+            .pairs
+            .entry((first.block_height, first.timestamp))
+            .or_insert_with(|| CachedPair {
+                last: None,
+                growth: Vec::new(),
+                used: 0,
+            });
+        if pair.last != Some(last.timestamp) {
+            pair.last = Some(last.timestamp);
+            pair.growth = last.inflow_since(first);
+            self.computed += 1;
+        }
+        pair.used = self.calculations;
+        &pair.growth
+    }
+
+    /// Forgets every pair, for when stored snapshots are replaced.
+    pub fn clear(&mut self) {
+        self.pairs.clear();
+    }
+
+    /// Number of pairs whose growth had to be computed so far.
+    #[cfg(test)]
+    pub fn computed(&self) -> usize {
+        self.computed
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
 
     fn create_test_snapshot(
         block_height: u32,
@@ -150,6 +244,72 @@ mod tests {
         assert_eq!(inflows[10], 0.0);
     }
 
+    #[test]
+    fn test_expired_weight_does_not_hide_inflow() {
+        let mut aged = Array1::zeros(BUCKET_MAX as usize + 1);
+        aged[10] = 1000.0;
+        // Half the bucket expires while as much weight arrives, leaving its total unchanged
+        let snapshots = vec![
+            create_test_snapshot(100, 0, vec![(10, 2000.0)]).with_aged(aged),
+            create_test_snapshot(100, 60, vec![(10, 2000.0)]),
+        ];
+
+        let inflows = InflowCalculator::calculate_inflows(&snapshots, Duration::hours(1));
+
+        assert_eq!(inflows[10], 1000.0 * 10.0);
+    }
+
+    #[test]
+    fn test_window_before_earliest_representable_time() {
+        let mut snapshot = create_test_snapshot(100, 0, vec![(10, 1000.0)]);
+        snapshot.timestamp = DateTime::<Utc>::MIN_UTC;
+
+        let inflows =
+            InflowCalculator::calculate_inflows(&[snapshot.clone(), snapshot], Duration::hours(24));
+
+        assert_eq!(inflows.sum(), 0.0);
+    }
+
+    #[test]
+    fn test_cached_inflows_compute_only_new_pairs() {
+        let snapshots: Vec<_> = (0..12)
+            .map(|i| {
+                create_test_snapshot(
+                    100 + i as u32 / 4,
+                    i * 30,
+                    vec![(10, 1000.0 * (i % 4 + 1) as f64), (20, 500.0 + i as f64)],
+                )
+            })
+            .collect();
+
+        let mut cache = InflowCache::default();
+        for end in 2..=snapshots.len() {
+            let before = cache.computed();
+            let cached = InflowCalculator::calculate_inflows_cached(
+                &snapshots[..end],
+                Duration::hours(1),
+                &mut cache,
+            );
+            let uncached =
+                InflowCalculator::calculate_inflows(&snapshots[..end], Duration::hours(1));
+            assert_eq!(cached, uncached);
+            // Only the newest snapshot's block needs computing, if it has a pair yet
+            assert!(cache.computed() - before <= 1);
+        }
+        assert_eq!(cache.computed(), 3 * 3);
+
+        // Pairs outside the window are forgotten once they go unused for long enough
+        for _ in 0..=InflowCache::MAX_IDLE_CALCULATIONS {
+            InflowCalculator::calculate_inflows_cached(
+                &snapshots[8..],
+                Duration::hours(1),
+                &mut cache,
+            );
+        }
+        assert_eq!(cache.pairs.len(), 1);
+        assert_eq!(cache.computed(), 3 * 3);
+    }
+
     // ===== KOTLIN PARITY TESTS =====
     // These tests match InflowCalculatorTest from the Kotlin implementation
 
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use ndarray::Array1;
+use std::collections::BTreeMap;
 
 use crate::internal::BUCKET_MAX;
 use crate::mempool_snapshot::MempoolSnapshot;
@@ -7,12 +8,18 @@ use crate::mempool_snapshot::MempoolSnapshot;
 /// Internal representation of mempool snapshot using ndarray for efficient calculations.
 ///
 /// This mirrors the Kotlin implementation's MempoolSnapshotF64Array, providing
-/// efficient numerical operations for fee estimation calculations.
+/// efficient numerical operations for fee estimation calculations. Buckets are kept sparse, as a
+/// mempool only occupies a small part of the fee rate range, and made dense for simulation.
 #[derive(Debug, Clone)]
 pub(crate) struct SnapshotArray {
     pub timestamp: DateTime<Utc>,
     pub block_height: u32,
-    pub buckets: Array1<f64>,
+    pub buckets: SparseBuckets,
+    /// Weight of the transactions that had waited long enough to count as aged, in the same
+    /// order as `buckets`
+    pub aged: SparseBuckets,
+    /// Weight of the transactions the next block cannot take, in the same order as `buckets`
+    pub deferred: SparseBuckets,
 }
 
 impl SnapshotArray {
@@ -22,31 +29,64 @@ impl SnapshotArray {
         Self {
             timestamp,
             block_height,
-            buckets,
+            buckets: SparseBuckets::from_dense(&buckets),
+            aged: SparseBuckets::default(),
+            deferred: SparseBuckets::default(),
         }
     }
 
+    /// Sets the weight of the aged transactions in each bucket.
+    #[cfg(test)]
+    pub fn with_aged(mut self, aged: Array1<f64>) -> Self {
+        self.aged = SparseBuckets::from_dense(&aged);
+        self
+    }
+
     /// Converts a MempoolSnapshot to SnapshotArray for efficient calculations.
     ///
     /// The buckets are stored in reverse order to allow mining the highest
     /// fee rate transactions first during simulation.
     pub fn from_snapshot(snapshot: &MempoolSnapshot) -> Self {
-        let mut fee_rate_buckets = Array1::zeros(BUCKET_MAX as usize + 1);
+        Self {
+            timestamp: snapshot.timestamp,
+            block_height: snapshot.block_height,
+            buckets: Self::reversed(&snapshot.bucketed_weights),
+            aged: Self::reversed(&snapshot.bucketed_aged_weights),
+            deferred: Self::reversed(&snapshot.bucketed_deferred_weights),
+        }
+    }
+
+    /// Buckets indexed from the highest fee rate down.
+    fn reversed(bucketed_weights: &BTreeMap<i32, u64>) -> SparseBuckets {
+        let mut fee_rate_buckets = BTreeMap::new();
 
-        for (&bucket, &weight) in &snapshot.bucketed_weights {
+        for (&bucket, &weight) in bucketed_weights {
             // Remove buckets that are less than 0 (i.e. fee rates less than 1 sat/vB)
             if bucket >= 0 {
+                // Deserialized snapshots may carry buckets above the cap that
+                // `create_fee_rate_buckets` would have applied, so cap them here too.
+                let bucket = bucket.min(BUCKET_MAX);
                 // Insert in reverse order to mine highest fee rate buckets first
                 let index = (BUCKET_MAX - bucket) as usize;
-                fee_rate_buckets[index] = weight as f64;
+                *fee_rate_buckets.entry(index).or_insert(0.0) += weight as f64;
             }
         }
 
-        Self {
-            timestamp: snapshot.timestamp,
-            block_height: snapshot.block_height,
-            buckets: fee_rate_buckets,
+        SparseBuckets::from_entries(fee_rate_buckets)
+    }
+
+    /// Buckets that received transactions since `earlier`, with how much weight arrived in each.
+    ///
+    /// Snapshots of the same block see no transaction mined, so aged weight that left a bucket
+    /// expired or was evicted. Its departure would hide as much arriving weight from the bucket's
+    /// growth, so it is counted back. Transactions aging in between the snapshots offset
+    /// departures, which keeps the correction conservative.
+    pub fn inflow_since(&self, earlier: &Self) -> Vec<(usize, f64)> {
+        let departed = earlier.aged.growth_since(&self.aged);
+        if departed.is_empty() {
+            return self.buckets.growth_since(&earlier.buckets);
         }
+        self.buckets.plus(&departed).growth_since(&earlier.buckets)
     }
 
     /// Returns the total weight across all buckets.
@@ -56,6 +96,94 @@ impl SnapshotArray {
     }
 }
 
+/// Bucket weights by index, holding only the occupied buckets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SparseBuckets {
+    /// Indices of the occupied buckets, ascending
+    indices: Vec<usize>,
+    /// Weight of each occupied bucket
+    values: Vec<f64>,
+}
+
+impl SparseBuckets {
+    /// Keeps the non-zero buckets of a dense array.
+    pub fn from_dense(dense: &Array1<f64>) -> Self {
+        Self::from_entries(dense.iter().copied().enumerate())
+    }
+
+    /// Collects `(index, weight)` entries given in ascending index order, skipping zeros.
+    fn from_entries(entries: impl IntoIterator<Item = (usize, f64)>) -> Self {
+        let (indices, values) = entries
+            .into_iter()
+            .filter(|&(_, weight)| weight != 0.0)
+            .unzip();
+        Self { indices, values }
+    }
+
+    /// Weight of the bucket at `index`, zero if unoccupied.
+    #[cfg(test)]
+    pub fn get(&self, index: usize) -> f64 {
+        self.indices
+            .binary_search(&index)
+            .map_or(0.0, |position| self.values[position])
+    }
+
+    /// Dense array over every bucket, for simulation.
+    pub fn to_dense(&self) -> Array1<f64> {
+        let mut dense = Array1::zeros(BUCKET_MAX as usize + 1);
+        for (&index, &weight) in self.indices.iter().zip(&self.values) {
+            dense[index] = weight;
+        }
+        dense
+    }
+
+    /// Total weight across all buckets.
+    pub fn sum(&self) -> f64 {
+        self.values.iter().sum()
+    }
+
+    /// These buckets with `(index, weight)` entries, given in ascending index order, added.
+    pub fn plus(&self, entries: &[(usize, f64)]) -> Self {
+        let mut merged: BTreeMap<usize, f64> = self
+            .indices
+            .iter()
+            .copied()
+            .zip(self.values.iter().copied())
+            .collect();
+        for &(index, weight) in entries {
+            *merged.entry(index).or_insert(0.0) += weight;
+        }
+        Self::from_entries(merged)
+    }
+
+    /// Buckets that grew since `earlier`, with how much weight each gained.
+    pub fn growth_since(&self, earlier: &Self) -> Vec<(usize, f64)> {
+        let mut growth = Vec::new();
+        let mut earlier_position = 0;
+
+        for (&index, &weight) in self.indices.iter().zip(&self.values) {
+            while earlier
+                .indices
+                .get(earlier_position)
+                .is_some_and(|&earlier_index| earlier_index < index)
+            {
+                earlier_position += 1;
+            }
+            let earlier_weight = match earlier.indices.get(earlier_position) {
+                Some(&earlier_index) if earlier_index == index => earlier.values[earlier_position],
+                _ => 0.0,
+            };
+
+            let delta = weight - earlier_weight;
+            if delta > 0.0 {
+                growth.push((index, delta));
+            }
+        }
+
+        growth
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,11 +200,11 @@ mod tests {
         let array = SnapshotArray::from_snapshot(&snapshot);
 
         assert_eq!(array.block_height, 850000);
-        assert_eq!(array.buckets.len(), BUCKET_MAX as usize + 1);
+        assert_eq!(array.buckets.to_dense().len(), BUCKET_MAX as usize + 1);
 
         // Check that weights are in reverse order
-        assert_eq!(array.buckets[(BUCKET_MAX - 100) as usize], 1000.0);
-        assert_eq!(array.buckets[(BUCKET_MAX - 200) as usize], 2000.0);
+        assert_eq!(array.buckets.get((BUCKET_MAX - 100) as usize), 1000.0);
+        assert_eq!(array.buckets.get((BUCKET_MAX - 200) as usize), 2000.0);
         assert_eq!(array.total_weight(), 3000.0);
     }
 
@@ -91,7 +219,55 @@ mod tests {
         let array = SnapshotArray::from_snapshot(&snapshot);
 
         // Only positive bucket should be included
-        assert_eq!(array.buckets[(BUCKET_MAX - 50) as usize], 1500.0);
+        assert_eq!(array.buckets.get((BUCKET_MAX - 50) as usize), 1500.0);
         assert_eq!(array.total_weight(), 1500.0);
     }
+
+    #[test]
+    fn test_buckets_above_max_are_capped() {
+        let mut bucketed_weights = BTreeMap::new();
+        bucketed_weights.insert(BUCKET_MAX, 100);
+        bucketed_weights.insert(BUCKET_MAX + 1, 200);
+        bucketed_weights.insert(i32::MAX, 300);
+
+        let snapshot = MempoolSnapshot::new(850000, Utc::now(), bucketed_weights);
+
+        let array = SnapshotArray::from_snapshot(&snapshot);
+
+        assert_eq!(array.buckets.get(0), 600.0);
+        assert_eq!(array.total_weight(), 600.0);
+    }
+
+    #[test]
+    fn test_sparse_buckets_round_trip() {
+        let mut dense = Array1::zeros(BUCKET_MAX as usize + 1);
+        dense[3] = 10.0;
+        dense[4000] = 2.5;
+
+        let sparse = SparseBuckets::from_dense(&dense);
+        assert_eq!(sparse.get(3), 10.0);
+        assert_eq!(sparse.get(5), 0.0);
+        assert_eq!(sparse.sum(), 12.5);
+        assert_eq!(sparse.to_dense(), dense);
+    }
+
+    #[test]
+    fn test_growth_since_matches_dense_delta() {
+        let earlier = SparseBuckets::from_entries([(1, 100.0), (5, 50.0), (9, 30.0)]);
+        let later = SparseBuckets::from_entries([(0, 20.0), (5, 80.0), (7, 10.0), (9, 10.0)]);
+
+        assert_eq!(
+            later.growth_since(&earlier),
+            vec![(0, 20.0), (5, 30.0), (7, 10.0)]
+        );
+
+        let delta = later.to_dense() - earlier.to_dense();
+        let dense_growth: Vec<_> = delta
+            .iter()
+            .enumerate()
+            .filter(|&(_, &d)| d > 0.0)
+            .map(|(index, &d)| (index, d))
+            .collect();
+        assert_eq!(later.growth_since(&earlier), dense_growth);
+    }
 }
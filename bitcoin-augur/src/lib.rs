@@ -7,6 +7,9 @@
 //! - Predictions based on historical mempool data and transaction inflows
 //! - Confidence-based fee rate estimates
 //! - Multiple confirmation targets (from 3 to 144 blocks)
+//! - Pluggable estimation strategies for comparing alternative models
+//! - Weighted ensembles of strategies
+//! - Stage-by-stage traces for finding where two estimates diverge
 //!
 //! # Example
 //! ```no_run
@@ -43,17 +46,46 @@
 pub mod error;
 
 // Data structures
+mod baseline;
+mod ensemble;
 mod fee_estimate;
 mod fee_estimator;
+mod history_limit;
 mod mempool_snapshot;
 mod mempool_transaction;
+mod monte_carlo;
+mod outliers;
+mod package;
+mod rounding;
+mod seasonality;
+mod simulation;
+mod sources;
+mod stages;
+mod strategy;
+mod target_windows;
+mod weighting;
 
 // Internal implementation modules
 pub(crate) mod internal;
 
 // Public exports
+pub use baseline::TopVmbStrategy;
+pub use ensemble::EnsembleStrategy;
 pub use error::{AugurError, Result};
-pub use fee_estimate::{BlockTarget, FeeEstimate, OrderedFloat};
+pub use fee_estimate::{BlockTarget, BlockTargetKey, FeeEstimate, FeeSuggestion, OrderedFloat};
 pub use fee_estimator::FeeEstimator;
+pub use history_limit::HistoryLimit;
+pub use internal::bucket_creator::{BUCKET_MAX, BUCKET_SCALE};
 pub use mempool_snapshot::MempoolSnapshot;
 pub use mempool_transaction::{MempoolTransaction, WU_PER_BYTE};
+pub use monte_carlo::MonteCarlo;
+pub use outliers::{Outlier, OutlierFilter};
+pub use package::{cpfp_child_fee, rbf_replacement_fee, CpfpBump, Package, PackageLimits, RbfBump};
+pub use rounding::RoundingPolicy;
+pub use seasonality::{SeasonalProfile, HOURS_PER_WEEK};
+pub use simulation::{BucketResidual, Simulation, SimulationRun};
+pub use sources::SourceEstimates;
+pub use stages::{EstimateTrace, Stage};
+pub use strategy::EstimationStrategy;
+pub use target_windows::TargetWindows;
+pub use weighting::BucketWeighting;
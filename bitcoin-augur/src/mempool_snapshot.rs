@@ -1,8 +1,9 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
-use crate::mempool_transaction::MempoolTransaction;
+use crate::internal::BUCKET_SCALE;
+use crate::mempool_transaction::{MempoolTransaction, WU_PER_BYTE};
 
 /// Represents a snapshot of the Bitcoin mempool at a specific point in time.
 ///
@@ -37,9 +38,39 @@ pub struct MempoolSnapshot {
     /// The key is the bucket index (calculated logarithmically)
     /// The value is the total weight in that bucket
     pub bucketed_weights: BTreeMap<i32, u64>,
+
+    /// Map of fee rate bucket indices to total output value in satoshis, over the
+    /// transactions whose value is known
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bucketed_values: BTreeMap<i32, u64>,
+
+    /// Map of fee rate bucket indices to the weight of transactions that had been waiting for
+    /// at least [`AGED_AFTER`](Self::AGED_AFTER), over the transactions whose entry time is
+    /// known
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bucketed_aged_weights: BTreeMap<i32, u64>,
+
+    /// Map of fee rate bucket indices to the weight of transactions whose ancestor package
+    /// exceeded the [`PackageLimits`](crate::PackageLimits) the snapshot was taken with. They are
+    /// counted in `bucketed_weights` too, and only kept out of the first simulated block.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bucketed_deferred_weights: BTreeMap<i32, u64>,
+
+    /// Label of the node or collector the snapshot was taken from, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 impl MempoolSnapshot {
+    /// Source label of snapshots that do not carry one.
+    pub const UNLABELED_SOURCE: &'static str = "unlabeled";
+
+    /// How long a transaction waits before it counts as aged.
+    ///
+    /// One still waiting after a day pays too little for the blocks being mined, so its leaving
+    /// the mempool is far more likely expiry, eviction or replacement than confirmation.
+    pub const AGED_AFTER: Duration = Duration::hours(24);
+
     /// Creates a new mempool snapshot.
     pub fn new(
         block_height: u32,
@@ -50,6 +81,10 @@ impl MempoolSnapshot {
             block_height,
             timestamp,
             bucketed_weights,
+            bucketed_values: BTreeMap::new(),
+            bucketed_aged_weights: BTreeMap::new(),
+            bucketed_deferred_weights: BTreeMap::new(),
+            source: None,
         }
     }
 
@@ -69,11 +104,21 @@ impl MempoolSnapshot {
     ) -> Self {
         let bucketed_weights =
             crate::internal::bucket_creator::create_fee_rate_buckets(&transactions);
+        let bucketed_values = crate::internal::bucket_creator::create_value_buckets(&transactions);
+        let bucketed_aged_weights = crate::internal::bucket_creator::create_aged_buckets(
+            &transactions,
+            timestamp,
+            Self::AGED_AFTER,
+        );
 
         Self {
             block_height,
             timestamp,
             bucketed_weights,
+            bucketed_values,
+            bucketed_aged_weights,
+            bucketed_deferred_weights: BTreeMap::new(),
+            source: None,
         }
     }
 
@@ -85,18 +130,170 @@ impl MempoolSnapshot {
             block_height,
             timestamp,
             bucketed_weights: BTreeMap::new(),
+            bucketed_values: BTreeMap::new(),
+            bucketed_aged_weights: BTreeMap::new(),
+            bucketed_deferred_weights: BTreeMap::new(),
+            source: None,
         }
     }
 
+    /// Labels the snapshot with the node or collector it was taken from.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// The snapshot's source label, or [`UNLABELED_SOURCE`](Self::UNLABELED_SOURCE).
+    pub fn source_label(&self) -> &str {
+        self.source.as_deref().unwrap_or(Self::UNLABELED_SOURCE)
+    }
+
     /// Returns the total weight across all buckets.
     pub fn total_weight(&self) -> u64 {
         self.bucketed_weights.values().sum()
     }
 
+    /// Returns the total output value in satoshis across all buckets, over the transactions
+    /// whose value is known.
+    pub fn total_value(&self) -> u64 {
+        self.bucketed_values
+            .values()
+            .fold(0u64, |total, &value| total.saturating_add(value))
+    }
+
     /// Returns the number of fee rate buckets.
     pub fn bucket_count(&self) -> usize {
         self.bucketed_weights.len()
     }
+
+    /// Returns the fee rate in sat/vB of the transactions `vbytes_from_top` virtual bytes deep
+    /// in the mempool, counting from the highest fee rate down as a miner fills a block.
+    ///
+    /// `fee_rate_at_depth(1_000_000)` is roughly what it takes to be in the next block, if no
+    /// more transactions arrive before it. Returns `None` when the mempool holds less than
+    /// `vbytes_from_top`, as any fee rate then makes it that far.
+    ///
+    /// # Example
+    /// ```
+    /// use bitcoin_augur::MempoolSnapshot;
+    /// use chrono::Utc;
+    /// use std::collections::BTreeMap;
+    ///
+    /// // 1 vMB at bucket 300 (about 20 sat/vB), then 1 vMB at bucket 100 (about 2.7 sat/vB)
+    /// let weights = BTreeMap::from([(300, 4_000_000), (100, 4_000_000)]);
+    /// let snapshot = MempoolSnapshot::new(850_000, Utc::now(), weights);
+    ///
+    /// assert!(snapshot.fee_rate_at_depth(500_000).unwrap() > 20.0);
+    /// assert!(snapshot.fee_rate_at_depth(1_500_000).unwrap() < 3.0);
+    /// assert_eq!(snapshot.fee_rate_at_depth(2_000_000), None);
+    /// ```
+    pub fn fee_rate_at_depth(&self, vbytes_from_top: u64) -> Option<f64> {
+        let depth = vbytes_from_top as f64 * WU_PER_BYTE;
+        let mut above = 0.0;
+        for (&bucket, &weight) in self.bucketed_weights.iter().rev() {
+            above += weight as f64;
+            if above > depth {
+                return Some((f64::from(bucket) / BUCKET_SCALE).exp());
+            }
+        }
+        None
+    }
+
+    /// Returns the virtual size of the transactions ahead of one paying `fee_rate` sat/vB: those
+    /// in higher fee rate buckets, which a miner picks first.
+    ///
+    /// The inverse of [`fee_rate_at_depth`](Self::fee_rate_at_depth), up to bucket precision.
+    pub fn vbytes_above(&self, fee_rate: f64) -> u64 {
+        let bucket = crate::internal::bucket_creator::calculate_bucket_index(fee_rate);
+        let weight = self
+            .bucketed_weights
+            .range(bucket + 1..)
+            .fold(0u64, |total, (_, &weight)| total.saturating_add(weight));
+        weight.div_ceil(WU_PER_BYTE as u64)
+    }
+
+    /// Returns the virtual size of each fee rate bucket, highest fee rate first, as
+    /// `(fee_rate, vbytes)` pairs: the fee histogram shape of mempool visualizers and
+    /// Electrum's `mempool.get_fee_histogram`.
+    ///
+    /// Each bucket is listed at its fee rate, e^(bucket / 100) sat/vB, the rate an estimate
+    /// settling on that bucket returns. Transactions in a bucket pay within half a percent of it.
+    ///
+    /// # Example
+    /// ```
+    /// use bitcoin_augur::MempoolSnapshot;
+    /// use chrono::Utc;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let weights = BTreeMap::from([(300, 4_000_000), (100, 2_000_000)]);
+    /// let snapshot = MempoolSnapshot::new(850_000, Utc::now(), weights);
+    ///
+    /// let histogram = snapshot.fee_histogram();
+    /// assert_eq!(histogram.len(), 2);
+    /// assert_eq!(histogram[0], (3f64.exp(), 1_000_000));
+    /// assert_eq!(histogram[1], (1f64.exp(), 500_000));
+    /// ```
+    pub fn fee_histogram(&self) -> Vec<(f64, u64)> {
+        self.bucketed_weights
+            .iter()
+            .rev()
+            .map(|(&bucket, &weight)| {
+                (
+                    (f64::from(bucket) / BUCKET_SCALE).exp(),
+                    weight.div_ceil(WU_PER_BYTE as u64),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the virtual size paying at least each of the `boundaries` fee rates, in sat/vB,
+    /// and less than the next higher boundary, highest boundary first.
+    ///
+    /// Rebuckets [`fee_histogram`](Self::fee_histogram) to ranges of the caller's choosing, up to
+    /// bucket precision: a bucket counts from the boundary whose bucket it is at or above.
+    /// Every boundary is listed, even with nothing paying in its range, and transactions
+    /// paying less than the lowest boundary are left out.
+    pub fn fee_histogram_with_boundaries(&self, boundaries: &[f64]) -> Vec<(f64, u64)> {
+        let mut boundaries = boundaries.to_vec();
+        boundaries.sort_by(|a, b| b.total_cmp(a));
+        boundaries.dedup();
+
+        let mut upper = i32::MAX;
+        boundaries
+            .into_iter()
+            .map(|boundary| {
+                let lower = crate::internal::bucket_creator::calculate_bucket_index(boundary);
+                let weight = if lower < upper {
+                    self.bucketed_weights
+                        .range(lower..upper)
+                        .fold(0u64, |total, (_, &weight)| total.saturating_add(weight))
+                } else {
+                    0
+                };
+                upper = upper.min(lower);
+                (boundary, weight.div_ceil(WU_PER_BYTE as u64))
+            })
+            .collect()
+    }
+
+    /// Removes snapshots taken on a branch of the chain that was later reorganized away.
+    ///
+    /// A snapshot is orphaned when a later snapshot reports a lower block height: the blocks
+    /// above that height were replaced, so mempool growth measured against them is not real
+    /// inflow. `snapshots` must be sorted by timestamp. Returns how many were removed.
+    pub fn remove_orphaned(snapshots: &mut Vec<Self>) -> usize {
+        let mut lowest_later = u32::MAX;
+        let mut orphaned = vec![false; snapshots.len()];
+        for (snapshot, orphaned) in snapshots.iter().zip(&mut orphaned).rev() {
+            *orphaned = snapshot.block_height > lowest_later;
+            lowest_later = lowest_later.min(snapshot.block_height);
+        }
+
+        let before = snapshots.len();
+        let mut orphaned = orphaned.into_iter();
+        snapshots.retain(|_| !orphaned.next().unwrap_or(false));
+        before - snapshots.len()
+    }
 }
 
 #[cfg(test)]
@@ -125,4 +322,86 @@ mod tests {
         assert_eq!(snapshot.total_weight(), 3000);
         assert_eq!(snapshot.bucket_count(), 2);
     }
+
+    #[test]
+    fn test_remove_orphaned() {
+        let start = Utc::now();
+        let heights = [100, 100, 101, 102, 101, 101, 102, 103];
+        let mut snapshots: Vec<_> = heights
+            .iter()
+            .enumerate()
+            .map(|(i, &height)| {
+                MempoolSnapshot::empty(height, start + chrono::Duration::minutes(i as i64))
+            })
+            .collect();
+
+        // The chain dropped back from 102 to 101, orphaning the first 102
+        assert_eq!(MempoolSnapshot::remove_orphaned(&mut snapshots), 1);
+        let kept: Vec<_> = snapshots.iter().map(|s| s.block_height).collect();
+        assert_eq!(kept, vec![100, 100, 101, 101, 101, 102, 103]);
+        assert_eq!(snapshots[3].timestamp, start + chrono::Duration::minutes(4));
+
+        assert_eq!(MempoolSnapshot::remove_orphaned(&mut snapshots), 0);
+    }
+
+    #[test]
+    fn test_fee_rate_at_depth() {
+        let buckets = BTreeMap::from([(200, 400), (100, 400), (0, 400)]);
+        let snapshot = MempoolSnapshot::new(850000, Utc::now(), buckets);
+
+        // 100 vB per bucket, highest fee rate first
+        assert_eq!(snapshot.fee_rate_at_depth(0), Some(2f64.exp()));
+        assert_eq!(snapshot.fee_rate_at_depth(99), Some(2f64.exp()));
+        assert_eq!(snapshot.fee_rate_at_depth(100), Some(1f64.exp()));
+        assert_eq!(snapshot.fee_rate_at_depth(299), Some(1.0));
+        assert_eq!(snapshot.fee_rate_at_depth(300), None);
+        assert_eq!(
+            MempoolSnapshot::empty(850000, Utc::now()).fee_rate_at_depth(0),
+            None
+        );
+
+        assert_eq!(snapshot.vbytes_above(2f64.exp()), 0);
+        assert_eq!(snapshot.vbytes_above(3.0), 100);
+        assert_eq!(snapshot.vbytes_above(1f64.exp()), 100);
+        assert_eq!(snapshot.vbytes_above(1.0), 200);
+        assert_eq!(snapshot.vbytes_above(0.5), 300);
+        assert_eq!(snapshot.vbytes_above(1000.0), 0);
+    }
+
+    #[test]
+    fn test_fee_histogram() {
+        let buckets = BTreeMap::from([(200, 400), (100, 402), (0, 400), (-10, 40)]);
+        let snapshot = MempoolSnapshot::new(850000, Utc::now(), buckets);
+
+        // Highest fee rate first, with partial virtual bytes rounded up
+        assert_eq!(
+            snapshot.fee_histogram(),
+            [
+                (2f64.exp(), 100),
+                (1f64.exp(), 101),
+                (1.0, 100),
+                ((-0.1f64).exp(), 10)
+            ]
+        );
+        assert!(MempoolSnapshot::empty(850000, Utc::now())
+            .fee_histogram()
+            .is_empty());
+
+        // Boundaries in any order, each counting up to the next higher one
+        assert_eq!(
+            snapshot.fee_histogram_with_boundaries(&[1.0, 5.0, 2.0, 5.0, 100.0]),
+            [(100.0, 0), (5.0, 100), (2.0, 101), (1.0, 100)]
+        );
+        // A boundary on a bucket's fee rate includes it
+        assert_eq!(
+            snapshot.fee_histogram_with_boundaries(&[1f64.exp(), 0.5]),
+            [(1f64.exp(), 201), (0.5, 110)]
+        );
+        // Boundaries within one bucket leave nothing to the lower one
+        assert_eq!(
+            snapshot.fee_histogram_with_boundaries(&[2.72, 2.71]),
+            [(2.72, 201), (2.71, 0)]
+        );
+        assert!(snapshot.fee_histogram_with_boundaries(&[]).is_empty());
+    }
 }
@@ -1,9 +1,16 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::package::Package;
+
 /// Represents a transaction in the Bitcoin mempool.
 ///
 /// This struct contains the minimal information needed for fee estimation:
-/// the transaction's weight and the fee amount.
+/// the transaction's weight and the fee amount. The value of its outputs is optional and only
+/// read when estimating with [`BucketWeighting::Value`](crate::BucketWeighting::Value). So is
+/// the time it entered the mempool, which tells transactions likely to expire or be evicted
+/// apart from the rest, and its unconfirmed ancestors, read when applying
+/// [`PackageLimits`](crate::PackageLimits).
 ///
 /// # Example
 /// ```
@@ -12,6 +19,9 @@ use serde::{Deserialize, Serialize};
 /// let transaction = MempoolTransaction {
 ///     weight: 565,  // Transaction weight in weight units
 ///     fee: 1000,    // Fee amount in satoshis
+///     value: None,  // Output value, when known
+///     time: None,   // Mempool entry time, when known
+///     ancestors: None, // Unconfirmed ancestors, when known
 /// };
 ///
 /// // Get fee rate in sat/vB
@@ -25,12 +35,48 @@ pub struct MempoolTransaction {
 
     /// The transaction fee in satoshis
     pub fee: u64,
+
+    /// The total value of the transaction's outputs in satoshis, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u64>,
+
+    /// When the transaction entered the mempool, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<DateTime<Utc>>,
+
+    /// The transaction together with all its unconfirmed ancestors, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ancestors: Option<Package>,
 }
 
 impl MempoolTransaction {
     /// Creates a new mempool transaction.
     pub fn new(weight: u64, fee: u64) -> Self {
-        Self { weight, fee }
+        Self {
+            weight,
+            fee,
+            value: None,
+            time: None,
+            ancestors: None,
+        }
+    }
+
+    /// Sets the total value of the transaction's outputs in satoshis.
+    pub fn with_value(mut self, value: u64) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Sets when the transaction entered the mempool.
+    pub fn with_time(mut self, time: DateTime<Utc>) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Sets the package of the transaction and all its unconfirmed ancestors.
+    pub fn with_ancestors(mut self, ancestors: Package) -> Self {
+        self.ancestors = Some(ancestors);
+        self
     }
 
     /// Calculates the transaction's fee rate in sat/vB.
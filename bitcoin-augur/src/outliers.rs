@@ -0,0 +1,285 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AugurError, Result},
+    MempoolSnapshot,
+};
+
+/// Scales a median absolute deviation to a standard deviation for normal data
+const MAD_TO_STD: f64 = 1.4826;
+
+/// Spread assumed around the neighbours' median, as a fraction of it, however steady they are.
+///
+/// A block clearing part of a small mempool moves its weight by tens of percent; this keeps such
+/// drops from scoring as outliers against a perfectly flat neighbourhood.
+const MIN_RELATIVE_SPREAD: f64 = 0.1;
+
+/// Neighbours needed before a snapshot is judged at all
+const MIN_NEIGHBOURS: usize = 4;
+
+/// Detects snapshots that are wildly inconsistent with the snapshots around them.
+///
+/// Each snapshot's total weight and number of occupied fee buckets are compared with up to
+/// `window` snapshots on either side using a modified z-score: the distance from the
+/// neighbours' median in units of their median absolute deviation. Snapshots keep no
+/// transaction count, so the bucket count stands in for it. A glitch such as an empty mempool
+/// between full ones scores far above the drop caused by a real block.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{MempoolSnapshot, OutlierFilter};
+/// use chrono::{Duration, Utc};
+/// use std::collections::BTreeMap;
+///
+/// let start = Utc::now();
+/// let snapshots: Vec<_> = (0..10)
+///     .map(|i| {
+///         // The sixth snapshot saw an empty mempool
+///         let weights = if i == 5 { BTreeMap::new() } else { BTreeMap::from([(100, 400_000)]) };
+///         MempoolSnapshot::new(850_000, start + Duration::minutes(i), weights)
+///     })
+///     .collect();
+///
+/// let outliers = OutlierFilter::default().detect(&snapshots);
+/// assert_eq!(outliers.len(), 1);
+/// assert_eq!(outliers[0].index, 5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OutlierFilter {
+    /// Modified z-score above which a snapshot is excluded
+    threshold: f64,
+    /// Neighbours compared on either side of a snapshot
+    window: usize,
+}
+
+/// A snapshot judged an outlier, with the evidence against it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Outlier {
+    /// Position of the snapshot in the slice given to [`OutlierFilter::detect`]
+    pub index: usize,
+    pub timestamp: DateTime<Utc>,
+    pub block_height: u32,
+    /// The snapshot's total weight in weight units
+    pub total_weight: u64,
+    /// The neighbours' median total weight
+    pub expected_weight: u64,
+    /// Number of occupied fee buckets in the snapshot
+    pub bucket_count: usize,
+    /// The neighbours' median number of occupied fee buckets
+    pub expected_bucket_count: usize,
+    /// The larger of the weight and bucket count z-scores
+    pub z_score: f64,
+}
+
+impl OutlierFilter {
+    /// Default modified z-score threshold.
+    pub const DEFAULT_THRESHOLD: f64 = 5.0;
+
+    /// Default number of neighbours compared on either side.
+    pub const DEFAULT_WINDOW: usize = 5;
+
+    /// Creates a filter excluding snapshots whose modified z-score exceeds `threshold`.
+    ///
+    /// # Arguments
+    /// * `threshold` - Modified z-score above which a snapshot is an outlier (must be positive)
+    /// * `window` - Neighbours compared on either side (at least 2)
+    pub fn new(threshold: f64, window: usize) -> Result<Self> {
+        if !threshold.is_finite() || threshold <= 0.0 {
+            return Err(AugurError::invalid_config(
+                "Outlier threshold must be a positive number",
+            ));
+        }
+        let minimum_window = MIN_NEIGHBOURS / 2;
+        if window < minimum_window {
+            return Err(AugurError::invalid_config(format!(
+                "Outlier window must be at least {minimum_window} snapshots"
+            )));
+        }
+        Ok(Self { threshold, window })
+    }
+
+    /// The modified z-score above which a snapshot is excluded.
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// The number of neighbours compared on either side of a snapshot.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Finds the outliers among `snapshots`, in chronological order.
+    pub fn detect(&self, snapshots: &[MempoolSnapshot]) -> Vec<Outlier> {
+        let mut order: Vec<usize> = (0..snapshots.len()).collect();
+        order.sort_by_key(|&index| snapshots[index].timestamp);
+
+        let weights: Vec<f64> = order
+            .iter()
+            .map(|&index| snapshots[index].total_weight() as f64)
+            .collect();
+        let bucket_counts: Vec<f64> = order
+            .iter()
+            .map(|&index| snapshots[index].bucket_count() as f64)
+            .collect();
+
+        let mut outliers = Vec::new();
+        for (position, &index) in order.iter().enumerate() {
+            let start = position.saturating_sub(self.window);
+            let end = (position + self.window + 1).min(order.len());
+            if end - start - 1 < MIN_NEIGHBOURS {
+                continue;
+            }
+
+            let (weight_z, expected_weight) = score(&weights, start, position, end);
+            let (bucket_z, expected_buckets) = score(&bucket_counts, start, position, end);
+            let z_score = weight_z.max(bucket_z);
+
+            if z_score > self.threshold {
+                let snapshot = &snapshots[index];
+                outliers.push(Outlier {
+                    index,
+                    timestamp: snapshot.timestamp,
+                    block_height: snapshot.block_height,
+                    total_weight: snapshot.total_weight(),
+                    expected_weight: expected_weight.round() as u64,
+                    bucket_count: snapshot.bucket_count(),
+                    expected_bucket_count: expected_buckets.round() as usize,
+                    z_score,
+                });
+            }
+        }
+
+        outliers
+    }
+
+    /// Returns `snapshots` without their outliers, and the outliers removed.
+    pub fn filter(&self, snapshots: &[MempoolSnapshot]) -> (Vec<MempoolSnapshot>, Vec<Outlier>) {
+        let outliers = self.detect(snapshots);
+        let kept = snapshots
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !outliers.iter().any(|outlier| outlier.index == *index))
+            .map(|(_, snapshot)| snapshot.clone())
+            .collect();
+        (kept, outliers)
+    }
+}
+
+impl Default for OutlierFilter {
+    fn default() -> Self {
+        Self {
+            threshold: Self::DEFAULT_THRESHOLD,
+            window: Self::DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// Absolute modified z-score of `values[position]` against `values[start..end]` without it,
+/// and the neighbours' median
+fn score(values: &[f64], start: usize, position: usize, end: usize) -> (f64, f64) {
+    let neighbours: Vec<f64> = values[start..position]
+        .iter()
+        .chain(&values[position + 1..end])
+        .copied()
+        .collect();
+
+    let expected = median(neighbours.clone());
+    let deviation = median(neighbours.iter().map(|v| (v - expected).abs()).collect());
+    let scale = (MAD_TO_STD * deviation)
+        .max(MIN_RELATIVE_SPREAD * expected)
+        .max(1.0);
+
+    ((values[position] - expected).abs() / scale, expected)
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use std::collections::BTreeMap;
+
+    /// A snapshot per minute with the given total weights, spread over three buckets
+    fn series(weights: &[u64]) -> Vec<MempoolSnapshot> {
+        let start = Utc.with_ymd_and_hms(2025, 1, 20, 0, 0, 0).unwrap();
+        weights
+            .iter()
+            .enumerate()
+            .map(|(i, &weight)| {
+                let buckets = if weight == 0 {
+                    BTreeMap::new()
+                } else {
+                    BTreeMap::from([(100, weight / 3), (200, weight / 3), (300, weight / 3)])
+                };
+                MempoolSnapshot::new(850_000, start + Duration::minutes(i as i64), buckets)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_empty_glitch() {
+        let snapshots = series(&[
+            30_000_000, 30_100_000, 29_900_000, 30_000_000, 0, 30_200_000, 30_000_000, 29_800_000,
+        ]);
+        let outliers = OutlierFilter::default().detect(&snapshots);
+
+        assert_eq!(outliers.len(), 1);
+        let outlier = &outliers[0];
+        assert_eq!(outlier.index, 4);
+        assert_eq!(outlier.total_weight, 0);
+        assert_eq!(outlier.expected_weight, 30_000_000);
+        assert_eq!(outlier.expected_bucket_count, 3);
+        assert!(outlier.z_score > OutlierFilter::DEFAULT_THRESHOLD);
+    }
+
+    #[test]
+    fn test_keeps_block_drops() {
+        // A block clears a third of the mempool, which then refills
+        let snapshots = series(&[
+            12_000_000, 12_100_000, 12_200_000, 12_300_000, 8_300_000, 8_400_000, 8_500_000,
+            8_600_000, 8_700_000,
+        ]);
+        assert!(OutlierFilter::default().detect(&snapshots).is_empty());
+
+        // Including a drop in the newest snapshot, which only has older neighbours
+        let newest_drop = series(&[12_000_000, 12_100_000, 12_200_000, 12_300_000, 8_300_000]);
+        assert!(OutlierFilter::default().detect(&newest_drop).is_empty());
+    }
+
+    #[test]
+    fn test_filter_preserves_order_and_ignores_short_histories() {
+        let mut snapshots = series(&[
+            20_000_000, 20_000_000, 20_000_000, 90_000_000, 20_000_000, 20_000_000, 20_000_000,
+        ]);
+        snapshots.reverse();
+
+        let (kept, outliers) = OutlierFilter::default().filter(&snapshots);
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].index, 3);
+        assert_eq!(kept.len(), 6);
+        assert!(kept.iter().all(|s| s.total_weight() == 19_999_998));
+
+        // Too few neighbours to judge anything
+        let (kept, outliers) = OutlierFilter::default().filter(&snapshots[..4]);
+        assert_eq!(kept.len(), 4);
+        assert!(outliers.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        assert!(OutlierFilter::new(0.0, 5).is_err());
+        assert!(OutlierFilter::new(f64::NAN, 5).is_err());
+        assert!(OutlierFilter::new(3.5, 1).is_err());
+        assert!(OutlierFilter::new(3.5, 2).is_ok());
+    }
+}
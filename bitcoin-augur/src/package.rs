@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::internal::bucket_creator::create_fee_rate_buckets;
+use crate::mempool_transaction::MempoolTransaction;
+
+/// Sizes and fees of unconfirmed transactions that are mined together.
+///
+/// Bitcoin Core reports package data in virtual bytes, so sizes here are in vB rather than
+/// weight units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    /// Number of transactions in the package
+    pub count: u64,
+
+    /// Total size in virtual bytes
+    pub vsize: u64,
+
+    /// Total fee in satoshis
+    pub fee: u64,
+}
+
+impl Package {
+    /// Creates a new package.
+    pub fn new(count: u64, vsize: u64, fee: u64) -> Self {
+        Self { count, vsize, fee }
+    }
+
+    /// Calculates the package's fee rate in sat/vB.
+    ///
+    /// # Returns
+    /// The fee rate in sat/vB, or 0.0 if the package is empty
+    pub fn fee_rate(&self) -> f64 {
+        if self.vsize == 0 {
+            return 0.0;
+        }
+        self.fee as f64 / self.vsize as f64
+    }
+}
+
+/// Ancestor limits block templates respect.
+///
+/// A block template only takes a transaction along with all its unconfirmed ancestors, and
+/// never a package beyond Bitcoin Core's `-limitancestorcount` and `-limitancestorsize`. A
+/// transaction whose ancestor package exceeds them cannot confirm in the next block however much
+/// it pays: some of its ancestors must confirm first, after which it can.
+///
+/// Core enforces the same limits when admitting transactions, so with its defaults of 25
+/// transactions and 101 kvB no mempool transaction ever exceeds them. Limits only take
+/// transactions out of the next block when they are lower than those of the node the mempool
+/// was read from, such as a node relaying with raised `-limitancestorcount` or
+/// `-limitancestorsize` while miners keep the defaults.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{MempoolTransaction, Package, PackageLimits};
+///
+/// let transactions = vec![
+///     // A lone transaction
+///     MempoolTransaction::new(565, 1000),
+///     // The 30th transaction of an unconfirmed chain, admitted by a node with raised limits
+///     MempoolTransaction::new(565, 1000).with_ancestors(Package::new(30, 4_200, 8_400)),
+/// ];
+///
+/// let deferred = PackageLimits::default().deferred_weights(&transactions);
+/// assert_eq!(deferred.values().sum::<u64>(), 565);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageLimits {
+    /// Most transactions in a package, counting the transaction itself
+    pub max_count: u64,
+
+    /// Largest package size in virtual bytes
+    pub max_vsize: u64,
+}
+
+impl Default for PackageLimits {
+    /// Bitcoin Core's default limits, 25 transactions and 101 kvB
+    fn default() -> Self {
+        Self::new(25, 101_000)
+    }
+}
+
+impl PackageLimits {
+    /// Creates package limits.
+    pub fn new(max_count: u64, max_vsize: u64) -> Self {
+        Self {
+            max_count,
+            max_vsize,
+        }
+    }
+
+    /// Whether `package` is larger than a block template takes at once.
+    pub fn exceeded_by(&self, package: &Package) -> bool {
+        package.count > self.max_count || package.vsize > self.max_vsize
+    }
+
+    /// Buckets the weight of the transactions whose ancestor package exceeds the limits, like
+    /// [`MempoolSnapshot::bucketed_weights`](crate::MempoolSnapshot::bucketed_weights).
+    ///
+    /// Stored as a snapshot's
+    /// [`bucketed_deferred_weights`](crate::MempoolSnapshot::bucketed_deferred_weights), the
+    /// estimator keeps this weight out of the first block it simulates. Transactions without
+    /// ancestor data are never deferred.
+    pub fn deferred_weights(&self, transactions: &[MempoolTransaction]) -> BTreeMap<i32, u64> {
+        let deferred: Vec<MempoolTransaction> = transactions
+            .iter()
+            .filter(|tx| {
+                tx.ancestors
+                    .is_some_and(|ancestors| self.exceeded_by(&ancestors))
+            })
+            .copied()
+            .collect();
+        create_fee_rate_buckets(&deferred)
+    }
+}
+
+/// The child fee that lets a stuck transaction and its ancestors confirm together.
+///
+/// Created by [`cpfp_child_fee`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CpfpBump {
+    /// Fee the child must pay, in satoshis
+    pub child_fee: u64,
+
+    /// The stuck transaction's ancestors, the transaction itself and the child
+    pub package: Package,
+}
+
+/// Calculates the fee a child spending `ancestors` must pay to bring the whole package to
+/// `fee_rate`.
+///
+/// The child pays for the package's shortfall and never less than `fee_rate` for its own size,
+/// so it does not hold the package back when the ancestors already pay enough.
+///
+/// # Arguments
+/// * `ancestors` - The stuck transaction together with all its unconfirmed ancestors
+/// * `child_vsize` - Size of the child in virtual bytes
+/// * `fee_rate` - The package fee rate to reach, in sat/vB
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{cpfp_child_fee, Package};
+///
+/// // A 200 vB parent paying 1 sat/vB, bumped to 10 sat/vB by a 100 vB child
+/// let bump = cpfp_child_fee(Package::new(1, 200, 200), 100, 10.0);
+/// assert_eq!(bump.child_fee, 2_800);
+/// assert_eq!(bump.package.fee_rate(), 10.0);
+/// ```
+pub fn cpfp_child_fee(ancestors: Package, child_vsize: u64, fee_rate: f64) -> CpfpBump {
+    let package_vsize = ancestors.vsize + child_vsize;
+    let shortfall = fee_for(fee_rate, package_vsize).saturating_sub(ancestors.fee);
+    let child_fee = shortfall.max(fee_for(fee_rate, child_vsize));
+
+    CpfpBump {
+        child_fee,
+        package: Package::new(
+            ancestors.count + 1,
+            package_vsize,
+            ancestors.fee + child_fee,
+        ),
+    }
+}
+
+/// The fee a replacement must pay to evict a transaction and its descendants.
+///
+/// Created by [`rbf_replacement_fee`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RbfBump {
+    /// Fee the replacement must pay, in satoshis
+    pub fee: u64,
+
+    /// Fee the target fee rate asks for at the replacement's size
+    pub target_fee: u64,
+
+    /// Lowest fee the BIP125 replacement rules accept
+    pub relay_minimum_fee: u64,
+}
+
+/// Calculates the lowest fee a replacement must pay to confirm at `fee_rate` and be relayed.
+///
+/// Following BIP125, a replacement must pay at least the fees of every transaction it evicts
+/// (rule 3), plus the incremental relay fee for its own size (rule 4), at a higher fee rate than
+/// the transaction it replaces (rule 6).
+///
+/// # Arguments
+/// * `original` - The transaction being replaced
+/// * `evicted` - The transaction together with all its descendants, which are evicted with it
+/// * `replacement_vsize` - Size of the replacement in virtual bytes
+/// * `fee_rate` - The fee rate the replacement should pay, in sat/vB
+/// * `incremental_relay_fee` - The node's incremental relay fee, in sat/vB
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{rbf_replacement_fee, Package};
+///
+/// // A 200 vB transaction paying 1 sat/vB with a 100 vB child paying 5 sat/vB
+/// let original = Package::new(1, 200, 200);
+/// let evicted = Package::new(2, 300, 700);
+///
+/// // At 2 sat/vB the estimate asks for less than the evicted fees, so relay rules decide
+/// let bump = rbf_replacement_fee(original, evicted, 200, 2.0, 1.0);
+/// assert_eq!(bump.fee, 900);
+/// assert_eq!(bump.target_fee, 400);
+/// ```
+pub fn rbf_replacement_fee(
+    original: Package,
+    evicted: Package,
+    replacement_vsize: u64,
+    fee_rate: f64,
+    incremental_relay_fee: f64,
+) -> RbfBump {
+    let pays_evicted = evicted.fee + fee_for(incremental_relay_fee, replacement_vsize);
+    let beats_original_rate = (original.fee * replacement_vsize)
+        .checked_div(original.vsize)
+        .map_or(0, |fee| fee + 1);
+    let relay_minimum_fee = pays_evicted.max(beats_original_rate);
+    let target_fee = fee_for(fee_rate, replacement_vsize);
+
+    RbfBump {
+        fee: target_fee.max(relay_minimum_fee),
+        target_fee,
+        relay_minimum_fee,
+    }
+}
+
+/// Fee in whole satoshis, rounded up, for `vsize` virtual bytes at `fee_rate` sat/vB
+fn fee_for(fee_rate: f64, vsize: u64) -> u64 {
+    (fee_rate * vsize as f64).ceil() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_limits_defer_deep_chains() {
+        let limits = PackageLimits::default();
+        let transactions = vec![
+            MempoolTransaction::new(400, 1000),
+            // At the limits
+            MempoolTransaction::new(400, 1000).with_ancestors(Package::new(25, 101_000, 25_000)),
+            // One transaction too many
+            MempoolTransaction::new(400, 1000).with_ancestors(Package::new(26, 3_000, 9_000)),
+            // Too large
+            MempoolTransaction::new(400, 1000).with_ancestors(Package::new(2, 101_100, 9_000)),
+        ];
+
+        // Only the two beyond the limits, in the bucket of their own fee rate
+        let deferred = limits.deferred_weights(&transactions);
+        assert_eq!(deferred.len(), 1);
+        assert_eq!(deferred.values().sum::<u64>(), 800);
+        assert_eq!(
+            deferred.keys().collect::<Vec<_>>(),
+            create_fee_rate_buckets(&transactions)
+                .keys()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_child_covers_shortfall() {
+        // Two ancestors of 150 vB each paying 300 sats in total
+        let ancestors = Package::new(2, 300, 300);
+        let bump = cpfp_child_fee(ancestors, 110, 5.5);
+
+        assert_eq!(bump.child_fee, 1_955);
+        assert_eq!(bump.package, Package::new(3, 410, 2_255));
+        assert!(bump.package.fee_rate() >= 5.5);
+    }
+
+    #[test]
+    fn test_child_pays_own_rate_when_ancestors_suffice() {
+        let ancestors = Package::new(1, 200, 4_000);
+        let bump = cpfp_child_fee(ancestors, 100, 10.0);
+
+        assert_eq!(bump.child_fee, 1_000);
+        assert_eq!(bump.package.fee, 5_000);
+    }
+
+    #[test]
+    fn test_fractional_fees_round_up() {
+        let bump = cpfp_child_fee(Package::new(1, 141, 141), 110, 1.01);
+
+        // 251 vB at 1.01 sat/vB is 253.51 sats
+        assert_eq!(bump.child_fee, 113);
+        assert!(bump.package.fee_rate() >= 1.01);
+    }
+
+    #[test]
+    fn test_replacement_follows_estimate_above_relay_rules() {
+        let original = Package::new(1, 200, 200);
+        let bump = rbf_replacement_fee(original, original, 200, 10.0, 1.0);
+
+        assert_eq!(bump.relay_minimum_fee, 400);
+        assert_eq!(bump.target_fee, 2_000);
+        assert_eq!(bump.fee, 2_000);
+    }
+
+    #[test]
+    fn test_replacement_pays_for_evicted_descendants() {
+        let original = Package::new(1, 200, 200);
+        let evicted = Package::new(3, 500, 3_000);
+        let bump = rbf_replacement_fee(original, evicted, 180, 10.0, 1.0);
+
+        // 3000 evicted plus 180 for the replacement's own relay
+        assert_eq!(bump.relay_minimum_fee, 3_180);
+        assert_eq!(bump.fee, 3_180);
+    }
+
+    #[test]
+    fn test_replacement_beats_original_fee_rate() {
+        // A larger replacement of a high fee rate transaction, with a tiny incremental fee
+        let original = Package::new(1, 100, 2_000);
+        let bump = rbf_replacement_fee(original, original, 300, 1.0, 0.1);
+
+        assert_eq!(bump.relay_minimum_fee, 6_001);
+        assert!(bump.fee as f64 / 300.0 > original.fee_rate());
+    }
+
+    #[test]
+    fn test_fee_rate() {
+        assert_eq!(Package::new(1, 200, 500).fee_rate(), 2.5);
+        assert_eq!(Package::new(0, 0, 0).fee_rate(), 0.0);
+    }
+}
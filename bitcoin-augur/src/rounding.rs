@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How fee rates are quantized before they leave the estimator.
+///
+/// Every policy other than [`RoundingPolicy::Exact`] rounds up, so a rounded estimate never
+/// pays less than the model asked for. Applying a policy to an already rounded rate leaves it
+/// unchanged.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::RoundingPolicy;
+///
+/// assert_eq!(RoundingPolicy::CeilTenth.apply(2.01), 2.1);
+/// assert_eq!(RoundingPolicy::CeilInteger.apply(2.01), 3.0);
+/// assert_eq!(RoundingPolicy::Exact.apply(2.01), 2.01);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingPolicy {
+    /// Fee rates as calculated
+    #[default]
+    Exact,
+    /// Round up to the next 0.1 sat/vB
+    CeilTenth,
+    /// Round up to the next whole sat/vB
+    CeilInteger,
+}
+
+impl RoundingPolicy {
+    /// Tolerance for floating point noise, so that e.g. 2.1 stored as 2.1000000000000001
+    /// is not rounded up to 2.2
+    const EPSILON: f64 = 1e-9;
+
+    /// Applies the policy to a fee rate in sat/vB.
+    pub fn apply(self, fee_rate: f64) -> f64 {
+        let scale = match self {
+            Self::Exact => return fee_rate,
+            Self::CeilTenth => 10.0,
+            Self::CeilInteger => 1.0,
+        };
+        (fee_rate * scale - Self::EPSILON).ceil() / scale
+    }
+}
+
+impl fmt::Display for RoundingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Exact => "exact",
+            Self::CeilTenth => "ceil_tenth",
+            Self::CeilInteger => "ceil_integer",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounds_up() {
+        assert_eq!(RoundingPolicy::CeilTenth.apply(1.01), 1.1);
+        assert_eq!(RoundingPolicy::CeilTenth.apply(1.19), 1.2);
+        assert_eq!(RoundingPolicy::CeilInteger.apply(1.01), 2.0);
+        assert_eq!(RoundingPolicy::CeilInteger.apply(0.3), 1.0);
+        assert_eq!(RoundingPolicy::Exact.apply(1.2345), 1.2345);
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        for policy in [RoundingPolicy::CeilTenth, RoundingPolicy::CeilInteger] {
+            for rate in [0.1, 0.7, 1.0, 2.1, 3.3, 12.9, 57.2, 1000.0] {
+                let once = policy.apply(rate);
+                assert!(once >= rate - 1e-9, "{policy} lowered {rate} to {once}");
+                assert_eq!(policy.apply(once), once, "{policy} is not stable at {rate}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_serde_names() {
+        let policy: RoundingPolicy = serde_json::from_str("\"ceil_tenth\"").unwrap();
+        assert_eq!(policy, RoundingPolicy::CeilTenth);
+        assert_eq!(
+            serde_json::to_string(&RoundingPolicy::CeilInteger).unwrap(),
+            "\"ceil_integer\""
+        );
+        assert_eq!(RoundingPolicy::CeilInteger.to_string(), "ceil_integer");
+    }
+}
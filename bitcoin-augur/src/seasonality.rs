@@ -0,0 +1,236 @@
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+
+use crate::{internal::SnapshotArray, MempoolSnapshot};
+
+/// Number of hour-of-week slots, Monday 00:00 UTC first
+pub const HOURS_PER_WEEK: usize = 7 * 24;
+
+/// Seconds an hour-of-week slot must have been observed before its rate is trusted
+const MIN_COVERAGE_SECS: f64 = 30.0 * 60.0;
+
+/// Mempool inflow by hour of the week, learned from long history.
+///
+/// Inflow is measured the same way as for estimation: the growth of each fee bucket between the
+/// first and last snapshot at a block height, here also split at hour boundaries. Recording
+/// several weeks of snapshots averages each hour of the week across them, so the profile shows
+/// how much busier a weekday afternoon is than a Sunday morning.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{MempoolSnapshot, SeasonalProfile};
+/// use chrono::{Duration, TimeZone, Utc};
+/// use std::collections::BTreeMap;
+///
+/// // Monday 10:00 UTC, with 300,000 WU arriving in half an hour
+/// let start = Utc.with_ymd_and_hms(2025, 1, 20, 10, 0, 0).unwrap();
+/// let snapshots = vec![
+///     MempoolSnapshot::new(850_000, start, BTreeMap::from([(100, 100_000)])),
+///     MempoolSnapshot::new(850_000, start + Duration::minutes(30), BTreeMap::from([(100, 400_000)])),
+/// ];
+///
+/// let profile = SeasonalProfile::from_snapshots(&snapshots);
+/// assert_eq!(profile.hours_covered(), 1);
+/// assert_eq!(profile.inflow_rate(10), Some(100_000.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeasonalProfile {
+    /// Inflow in weight units recorded in each hour of the week
+    inflow: Vec<f64>,
+    /// Seconds observed in each hour of the week
+    seconds: Vec<f64>,
+}
+
+impl SeasonalProfile {
+    /// Creates a profile with nothing recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a profile from `snapshots`.
+    pub fn from_snapshots(snapshots: &[MempoolSnapshot]) -> Self {
+        let mut profile = Self::new();
+        profile.record(snapshots);
+        profile
+    }
+
+    /// Adds the inflow seen in `snapshots` to the profile.
+    ///
+    /// Long histories can be recorded a day at a time; each call must cover a different period.
+    pub fn record(&mut self, snapshots: &[MempoolSnapshot]) {
+        let mut arrays: Vec<SnapshotArray> =
+            snapshots.iter().map(SnapshotArray::from_snapshot).collect();
+        arrays.sort_by_key(|s| s.timestamp);
+
+        let same_period = |a: &SnapshotArray, b: &SnapshotArray| {
+            a.block_height == b.block_height
+                && a.timestamp.timestamp().div_euclid(3600)
+                    == b.timestamp.timestamp().div_euclid(3600)
+        };
+
+        for period in arrays.chunk_by(same_period) {
+            let (Some(first), Some(last)) = (period.first(), period.last()) else {
+                continue;
+            };
+            if period.len() < 2 {
+                continue;
+            }
+
+            let hour = hour_of_week(first.timestamp);
+            let growth = last.inflow_since(first);
+            self.inflow[hour] += growth.iter().map(|&(_, weight)| weight).sum::<f64>();
+            self.seconds[hour] += (last.timestamp - first.timestamp).num_seconds() as f64;
+        }
+    }
+
+    /// Number of hours of the week observed long enough to have a rate.
+    pub fn hours_covered(&self) -> usize {
+        (0..HOURS_PER_WEEK)
+            .filter(|&hour| self.inflow_rate(hour).is_some())
+            .count()
+    }
+
+    /// Average inflow in weight units per 10 minutes during an hour of the week.
+    ///
+    /// # Arguments
+    /// * `hour_of_week` - Hours since Monday 00:00 UTC (0 to 167)
+    ///
+    /// # Returns
+    /// The rate, or `None` if the hour has not been observed for at least half an hour
+    pub fn inflow_rate(&self, hour_of_week: usize) -> Option<f64> {
+        let seconds = *self.seconds.get(hour_of_week)?;
+        if seconds < MIN_COVERAGE_SECS {
+            return None;
+        }
+        Some(self.inflow[hour_of_week] * 600.0 / seconds)
+    }
+
+    /// How much busier the coming `horizon` is expected to be than the past `lookback`.
+    ///
+    /// Compares the profile's average rate over the hours ahead of `now` with its average over
+    /// the hours behind it. Hours without a rate are skipped.
+    ///
+    /// # Returns
+    /// The ratio of the two averages, or 1.0 when either side has no rate
+    pub fn adjustment(&self, now: DateTime<Utc>, lookback: Duration, horizon: Duration) -> f64 {
+        let past = now
+            .checked_sub_signed(lookback)
+            .and_then(|start| self.mean_rate(start, lookback));
+        let future = self.mean_rate(now, horizon);
+
+        match (past, future) {
+            (Some(past), Some(future)) if past > 0.0 => future / past,
+            _ => 1.0,
+        }
+    }
+
+    /// Average rate of the hours of the week touched by `span` from `start`
+    fn mean_rate(&self, start: DateTime<Utc>, span: Duration) -> Option<f64> {
+        let hours = (span.num_seconds() as f64 / 3600.0).ceil().max(1.0) as i64;
+        let rates: Vec<f64> = (0..hours)
+            .filter_map(|i| start.checked_add_signed(Duration::hours(i)))
+            .filter_map(|time| self.inflow_rate(hour_of_week(time)))
+            .collect();
+
+        if rates.is_empty() {
+            return None;
+        }
+        Some(rates.iter().sum::<f64>() / rates.len() as f64)
+    }
+}
+
+impl Default for SeasonalProfile {
+    fn default() -> Self {
+        Self {
+            inflow: vec![0.0; HOURS_PER_WEEK],
+            seconds: vec![0.0; HOURS_PER_WEEK],
+        }
+    }
+}
+
+/// Hours since the start of `time`'s week, Monday 00:00 UTC
+fn hour_of_week(time: DateTime<Utc>) -> usize {
+    time.weekday().num_days_from_monday() as usize * 24 + time.hour() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::BTreeMap;
+
+    /// Two weeks from Monday 00:00 UTC, a block per hour with snapshots on the hour and at half
+    /// past, and weekends receiving a third of the weekday inflow
+    fn quiet_weekends() -> Vec<MempoolSnapshot> {
+        let start = Utc.with_ymd_and_hms(2025, 1, 6, 0, 0, 0).unwrap();
+        (0..2 * HOURS_PER_WEEK as i64)
+            .flat_map(|hour| {
+                let time = start + Duration::hours(hour);
+                let inflow = if time.weekday().num_days_from_monday() >= 5 {
+                    100_000
+                } else {
+                    300_000
+                };
+                let height = 850_000 + hour as u32;
+                [
+                    MempoolSnapshot::new(height, time, BTreeMap::from([(200, 1_000_000)])),
+                    MempoolSnapshot::new(
+                        height,
+                        time + Duration::minutes(30),
+                        BTreeMap::from([(100, inflow), (200, 1_000_000)]),
+                    ),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_profile_tracks_weekly_pattern() {
+        let profile = SeasonalProfile::from_snapshots(&quiet_weekends());
+        assert_eq!(profile.hours_covered(), HOURS_PER_WEEK);
+
+        // Wednesday noon and Sunday noon, in WU per 10 minutes
+        assert_eq!(profile.inflow_rate(2 * 24 + 12), Some(100_000.0));
+        assert_eq!(profile.inflow_rate(6 * 24 + 12), Some(100_000.0 / 3.0));
+        assert_eq!(profile.inflow_rate(HOURS_PER_WEEK), None);
+    }
+
+    #[test]
+    fn test_recording_in_parts_matches_recording_at_once() {
+        let snapshots = quiet_weekends();
+        let mut profile = SeasonalProfile::new();
+        for day in snapshots.chunks(48) {
+            profile.record(day);
+        }
+        assert_eq!(profile, SeasonalProfile::from_snapshots(&snapshots));
+    }
+
+    #[test]
+    fn test_adjustment_expects_quiet_weekend() {
+        let profile = SeasonalProfile::from_snapshots(&quiet_weekends());
+        let day = Duration::hours(24);
+
+        // Saturday 00:00 after a busy Friday
+        let saturday = Utc.with_ymd_and_hms(2025, 1, 25, 0, 0, 0).unwrap();
+        let adjustment = profile.adjustment(saturday, day, day);
+        assert!((adjustment - 1.0 / 3.0).abs() < 1e-9);
+
+        // Monday 00:00 after a quiet Sunday
+        let monday = Utc.with_ymd_and_hms(2025, 1, 27, 0, 0, 0).unwrap();
+        assert!((profile.adjustment(monday, day, day) - 3.0).abs() < 1e-9);
+
+        // Midweek looks like the day before
+        let wednesday = Utc.with_ymd_and_hms(2025, 1, 22, 0, 0, 0).unwrap();
+        assert!((profile.adjustment(wednesday, day, day) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_empty_profile_changes_nothing() {
+        let profile = SeasonalProfile::new();
+        assert_eq!(profile.hours_covered(), 0);
+        let now = Utc.with_ymd_and_hms(2025, 1, 25, 0, 0, 0).unwrap();
+        assert_eq!(
+            profile.adjustment(now, Duration::hours(24), Duration::hours(24)),
+            1.0
+        );
+    }
+}
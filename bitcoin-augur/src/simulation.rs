@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use ndarray::Array1;
+
+use crate::internal::{BUCKET_MAX, BUCKET_SCALE};
+
+/// One mining simulation behind a fee estimate, bucket by bucket.
+///
+/// Returned by [`FeeEstimator::simulate`](crate::FeeEstimator::simulate), it shows why an
+/// estimate lands where it does: the estimate is the lowest fee rate whose bucket, and every
+/// bucket above it, is emptied by the simulated blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    /// Timestamp of the newest snapshot the simulation started from
+    pub timestamp: DateTime<Utc>,
+    /// Block target simulated
+    pub blocks: f64,
+    /// Confidence level simulated
+    pub probability: f64,
+    /// Number of blocks mined at this confidence level
+    pub expected_blocks: usize,
+    /// Weight of the long-term run in the blended fee rate, from 0 to 1
+    pub long_term_weight: f64,
+    /// Run with short-term inflows
+    pub short_term: SimulationRun,
+    /// Run with long-term inflows
+    pub long_term: SimulationRun,
+    /// Blended fee rate in sat/vB, before monotonicity, rounding and capping
+    pub fee_rate: Option<f64>,
+}
+
+/// Weights of the fee buckets before and after one simulated mining run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationRun {
+    /// Lowest fee rate in sat/vB that was fully mined, or `None` if no bucket was
+    pub fee_rate: Option<f64>,
+    /// Buckets holding any weight, highest fee rate first
+    pub buckets: Vec<BucketResidual>,
+    /// Bucket index the fee rate was read from
+    bucket_index: f64,
+}
+
+/// Weight of one fee bucket in a simulated mining run, in weight units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketResidual {
+    /// Fee rate bucket, 100 × ln(sat/vB)
+    pub bucket: i32,
+    /// Lowest fee rate of the bucket in sat/vB
+    pub fee_rate: f64,
+    /// Weight waiting when mining starts, including half the short-term inflow as a buffer
+    pub initial: f64,
+    /// Weight arriving while the blocks are mined
+    pub added: f64,
+    /// Weight left after the blocks are mined
+    pub remaining: f64,
+}
+
+impl SimulationRun {
+    /// Collects the buckets of a run from its reversed-order weight arrays.
+    pub(crate) fn new(
+        initial: &Array1<f64>,
+        added: &Array1<f64>,
+        remaining: &Array1<f64>,
+        bucket_index: usize,
+    ) -> Self {
+        let buckets = initial
+            .iter()
+            .zip(added)
+            .zip(remaining)
+            .enumerate()
+            .filter(|(_, ((&initial, &added), &remaining))| {
+                initial > 0.0 || added > 0.0 || remaining > 0.0
+            })
+            .map(|(index, ((&initial, &added), &remaining))| {
+                let bucket = BUCKET_MAX - index as i32;
+                BucketResidual {
+                    bucket,
+                    fee_rate: bucket_fee_rate(f64::from(bucket)),
+                    initial,
+                    added,
+                    remaining,
+                }
+            })
+            .collect();
+
+        Self {
+            fee_rate: valid_fee_rate(bucket_index as f64),
+            buckets,
+            bucket_index: bucket_index as f64,
+        }
+    }
+
+    /// Bucket index the run's fee rate was read from, for blending runs.
+    pub(crate) fn bucket_index(&self) -> f64 {
+        self.bucket_index
+    }
+}
+
+/// Fee rate in sat/vB at a (possibly fractional) bucket index
+fn bucket_fee_rate(bucket: f64) -> f64 {
+    (bucket / BUCKET_SCALE).exp()
+}
+
+/// Fee rate at a bucket index, or `None` past the highest bucket
+pub(crate) fn valid_fee_rate(bucket: f64) -> Option<f64> {
+    Some(bucket_fee_rate(bucket)).filter(|&rate| rate < bucket_fee_rate(f64::from(BUCKET_MAX)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+
+    #[test]
+    fn test_run_lists_buckets_with_weight() {
+        let mut initial = Array1::zeros(BUCKET_MAX as usize + 1);
+        let mut added = Array1::zeros(BUCKET_MAX as usize + 1);
+        initial[(BUCKET_MAX - 300) as usize] = 1_000.0;
+        added[(BUCKET_MAX - 100) as usize] = 500.0;
+        let mut remaining = Array1::zeros(BUCKET_MAX as usize + 1);
+        remaining[(BUCKET_MAX - 100) as usize] = 200.0;
+
+        let run = SimulationRun::new(&initial, &added, &remaining, 200);
+        assert_eq!(run.buckets.len(), 2);
+        assert_eq!(run.buckets[0].bucket, 300);
+        assert_eq!(run.buckets[0].remaining, 0.0);
+        assert_eq!(run.buckets[1].bucket, 100);
+        assert_eq!(run.buckets[1].added, 500.0);
+        assert_eq!(run.buckets[1].remaining, 200.0);
+        assert!((run.buckets[1].fee_rate - 1f64.exp()).abs() < 1e-9);
+        assert!((run.fee_rate.unwrap() - 2f64.exp()).abs() < 1e-9);
+
+        let unmined = SimulationRun::new(&initial, &added, &initial, BUCKET_MAX as usize + 1);
+        assert_eq!(unmined.fee_rate, None);
+    }
+}
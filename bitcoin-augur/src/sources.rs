@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use crate::{EnsembleStrategy, FeeEstimate, MempoolSnapshot};
+
+/// Estimates made separately from each source's snapshots, and the estimate they agree on.
+///
+/// Nodes with different mempool policies see different mempools, and interleaving their
+/// snapshots would read the differences as inflows. Estimating each source on its own keeps
+/// them apart; comparing a source against [`combined`](Self::combined) shows whether it skews
+/// the result. Made by [`FeeEstimator::calculate_by_source`](crate::FeeEstimator::calculate_by_source).
+#[derive(Debug, Clone)]
+pub struct SourceEstimates {
+    /// Estimate from each source's snapshots alone, keyed by
+    /// [`source_label`](MempoolSnapshot::source_label)
+    pub by_source: BTreeMap<String, FeeEstimate>,
+
+    /// Median across sources of each block target and confidence level, so a single outlying
+    /// source cannot drag it far
+    pub combined: FeeEstimate,
+}
+
+impl SourceEstimates {
+    /// Combines estimates already made for each source.
+    pub fn new(by_source: BTreeMap<String, FeeEstimate>) -> Self {
+        let combined =
+            EnsembleStrategy::combine(by_source.values().map(|estimate| (estimate, 1.0)));
+        Self {
+            by_source,
+            combined,
+        }
+    }
+
+    /// Largest relative difference between `source`'s fee rates and the combined ones, over the
+    /// block targets and confidence levels both estimate.
+    ///
+    /// Returns `None` for an unknown source or when nothing can be compared.
+    pub fn deviation(&self, source: &str) -> Option<f64> {
+        let estimate = self.by_source.get(source)?;
+        estimate
+            .targets()
+            .flat_map(|(blocks, target)| {
+                target
+                    .probabilities
+                    .iter()
+                    .filter_map(move |(probability, &rate)| {
+                        let combined = self.combined.get_target_fee_rate(blocks, probability.0)?;
+                        (combined > 0.0).then(|| (rate - combined).abs() / combined)
+                    })
+            })
+            .reduce(f64::max)
+    }
+}
+
+/// Splits `snapshots` by source label, keeping each source's snapshots in order
+pub(crate) fn group_by_source(
+    snapshots: &[MempoolSnapshot],
+) -> BTreeMap<String, Vec<MempoolSnapshot>> {
+    let mut groups: BTreeMap<String, Vec<MempoolSnapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        groups
+            .entry(snapshot.source_label().to_string())
+            .or_default()
+            .push(snapshot.clone());
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockTarget, OrderedFloat};
+    use chrono::Utc;
+
+    fn estimate(rate: f64) -> FeeEstimate {
+        let target = BlockTarget::new(6, BTreeMap::from([(OrderedFloat(0.5), rate)]));
+        FeeEstimate::new(BTreeMap::from([(6, target)]), Utc::now())
+    }
+
+    #[test]
+    fn test_outlying_source_stands_out() {
+        let estimates = SourceEstimates::new(BTreeMap::from([
+            ("a".to_string(), estimate(10.0)),
+            ("b".to_string(), estimate(11.0)),
+            ("c".to_string(), estimate(30.0)),
+        ]));
+
+        assert_eq!(estimates.combined.get_fee_rate(6, 0.5), Some(11.0));
+        assert_eq!(estimates.deviation("b"), Some(0.0));
+        assert!(estimates.deviation("c").unwrap() > 1.0);
+        assert_eq!(estimates.deviation("d"), None);
+    }
+
+    #[test]
+    fn test_group_by_source() {
+        let now = Utc::now();
+        let snapshots = vec![
+            MempoolSnapshot::empty(100, now).with_source("a"),
+            MempoolSnapshot::empty(100, now),
+            MempoolSnapshot::empty(101, now).with_source("a"),
+        ];
+
+        let groups = group_by_source(&snapshots);
+        assert_eq!(groups["a"].len(), 2);
+        assert_eq!(groups[MempoolSnapshot::UNLABELED_SOURCE].len(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::internal::BUCKET_MAX;
+
+/// A stage of the estimation pipeline, in the order an estimate passes through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Snapshots kept after bounding and filtering, and the newest one's bucket weights
+    Bucketing,
+    /// Short- and long-term inflow rates per bucket
+    Inflows,
+    /// Blocks mined for every target and confidence level
+    ExpectedBlocks,
+    /// Bucket index reached by the short- and long-term simulations
+    Simulation,
+    /// Blend of the two simulations' bucket indices
+    Weighting,
+    /// Fee rates after monotonicity is enforced
+    Monotonicity,
+}
+
+impl Stage {
+    /// Every stage, in pipeline order
+    pub const ALL: [Self; 6] = [
+        Self::Bucketing,
+        Self::Inflows,
+        Self::ExpectedBlocks,
+        Self::Simulation,
+        Self::Weighting,
+        Self::Monotonicity,
+    ];
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Bucketing => "bucketing",
+            Self::Inflows => "inflows",
+            Self::ExpectedBlocks => "expected blocks",
+            Self::Simulation => "simulation",
+            Self::Weighting => "weighting",
+            Self::Monotonicity => "monotonicity",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What every stage of the estimation pipeline produced for one set of snapshots.
+///
+/// Returned by [`FeeEstimator::trace`](crate::FeeEstimator::trace). Two builds, or two
+/// configurations, that disagree on an estimate can trace the same snapshots; the first
+/// [`Stage`] whose [`values`](Self::values) differ is where they part ways. Buckets are keyed by
+/// 100 × ln(sat/vB) and grids have a row per block target and a column per probability.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EstimateTrace {
+    /// Timestamp of the newest snapshot
+    pub timestamp: DateTime<Utc>,
+    /// Block targets, one per grid row
+    pub block_targets: Vec<f64>,
+    /// Confidence levels, one per grid column
+    pub probabilities: Vec<f64>,
+    /// Snapshots left after bounding the history and dropping outliers and orphans
+    pub snapshots: usize,
+    /// Weight in each occupied bucket of the newest snapshot, in weight units
+    pub mempool: BTreeMap<i32, f64>,
+    /// Short-term inflow per block interval of each bucket that received any
+    pub short_term_inflows: BTreeMap<i32, f64>,
+    /// Long-term inflow per block interval of each bucket that received any
+    pub long_term_inflows: BTreeMap<i32, f64>,
+    /// Blocks mined in each simulation
+    pub expected_blocks: Vec<Vec<f64>>,
+    /// Bucket index reached with short-term inflows
+    pub short_term_buckets: Vec<Vec<f64>>,
+    /// Bucket index reached with long-term inflows
+    pub long_term_buckets: Vec<Vec<f64>>,
+    /// Blend of both bucket indices
+    pub weighted_buckets: Vec<Vec<f64>>,
+    /// Fee rates in sat/vB after monotonicity, before rounding and capping
+    pub fee_rates: Vec<Vec<f64>>,
+}
+
+impl EstimateTrace {
+    /// The values `stage` produced, each with a label locating it, such as `bucket 250` or
+    /// `6 blocks 0.95`
+    pub fn values(&self, stage: Stage) -> Vec<(String, f64)> {
+        match stage {
+            Stage::Bucketing => {
+                let mut values = vec![("snapshots".to_string(), self.snapshots as f64)];
+                values.extend(buckets("bucket", &self.mempool));
+                values
+            }
+            Stage::Inflows => buckets("short-term bucket", &self.short_term_inflows)
+                .chain(buckets("long-term bucket", &self.long_term_inflows))
+                .collect(),
+            Stage::ExpectedBlocks => self.grid("", &self.expected_blocks).collect(),
+            Stage::Simulation => self
+                .grid("short-term ", &self.short_term_buckets)
+                .chain(self.grid("long-term ", &self.long_term_buckets))
+                .collect(),
+            Stage::Weighting => self.grid("", &self.weighted_buckets).collect(),
+            Stage::Monotonicity => self.grid("", &self.fee_rates).collect(),
+        }
+    }
+
+    /// Cells of `grid` labelled by their target and probability, after `prefix`
+    fn grid<'a>(
+        &'a self,
+        prefix: &'a str,
+        grid: &'a [Vec<f64>],
+    ) -> impl Iterator<Item = (String, f64)> + 'a {
+        grid.iter()
+            .zip(&self.block_targets)
+            .flat_map(move |(row, target)| {
+                row.iter()
+                    .zip(&self.probabilities)
+                    .map(move |(&value, probability)| {
+                        (format!("{prefix}{target} blocks {probability:.2}"), value)
+                    })
+            })
+    }
+}
+
+/// Entries of a bucket map labelled `<name> <bucket>`
+fn buckets<'a>(
+    name: &'a str,
+    weights: &'a BTreeMap<i32, f64>,
+) -> impl Iterator<Item = (String, f64)> + 'a {
+    weights
+        .iter()
+        .map(move |(bucket, &weight)| (format!("{name} {bucket}"), weight))
+}
+
+/// Nonzero entries of a reversed-order bucket array, keyed by bucket
+pub(crate) fn bucket_map(weights: &Array1<f64>) -> BTreeMap<i32, f64> {
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, &weight)| weight != 0.0)
+        .map(|(index, &weight)| (BUCKET_MAX - index as i32, weight))
+        .collect()
+}
+
+/// Rows of a target by probability array
+pub(crate) fn grid_rows(grid: &Array2<f64>) -> Vec<Vec<f64>> {
+    grid.rows().into_iter().map(|row| row.to_vec()).collect()
+}
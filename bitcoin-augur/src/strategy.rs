@@ -0,0 +1,81 @@
+use chrono::Duration;
+
+use crate::{error::Result, FeeEstimate, FeeEstimator, MempoolSnapshot};
+
+/// An algorithm turning a window of mempool snapshots into fee estimates.
+///
+/// [`FeeEstimator`] is the default strategy. Alternative models implement this trait so they
+/// can be replayed over the same snapshots and compared with it.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{
+///     BlockTarget, EstimationStrategy, FeeEstimate, MempoolSnapshot, OrderedFloat, Result,
+/// };
+/// use std::collections::BTreeMap;
+///
+/// /// Always suggests the same fee rate
+/// struct Constant(f64);
+///
+/// impl EstimationStrategy for Constant {
+///     fn name(&self) -> &str {
+///         "constant"
+///     }
+///
+///     fn estimate(&self, snapshots: &[MempoolSnapshot], num_blocks: Option<f64>) -> Result<FeeEstimate> {
+///         let Some(latest) = snapshots.last() else {
+///             return Ok(FeeEstimate::empty(chrono::Utc::now()));
+///         };
+///         let blocks = num_blocks.map_or(6, |blocks| blocks as u32);
+///         let probabilities = BTreeMap::from([(OrderedFloat(0.5), self.0)]);
+///         let target = BlockTarget::new(blocks, probabilities);
+///         Ok(FeeEstimate::new(BTreeMap::from([(blocks, target)]), latest.timestamp))
+///     }
+/// }
+///
+/// let estimate = Constant(2.0).estimate(&[MempoolSnapshot::empty(850_000, chrono::Utc::now())], None)?;
+/// assert_eq!(estimate.get_fee_rate(6, 0.5), Some(2.0));
+/// # Ok::<(), bitcoin_augur::AugurError>(())
+/// ```
+pub trait EstimationStrategy: Send + Sync {
+    /// Short name identifying the strategy in reports, such as `augur`
+    fn name(&self) -> &str;
+
+    /// Calculates estimates from snapshots ordered oldest first.
+    ///
+    /// `num_blocks` restricts the estimate to one block target, as for
+    /// [`FeeEstimator::calculate_estimates`].
+    fn estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> Result<FeeEstimate>;
+
+    /// How far back from the newest snapshot the strategy reads; older snapshots may be dropped.
+    fn history_window(&self) -> Duration {
+        Duration::hours(24)
+    }
+}
+
+/// The augur mining simulation
+impl EstimationStrategy for FeeEstimator {
+    fn name(&self) -> &str {
+        if self.monte_carlo().is_some() {
+            "augur-monte-carlo"
+        } else {
+            "augur"
+        }
+    }
+
+    fn estimate(
+        &self,
+        snapshots: &[MempoolSnapshot],
+        num_blocks: Option<f64>,
+    ) -> Result<FeeEstimate> {
+        self.calculate_estimates(snapshots, num_blocks)
+    }
+
+    fn history_window(&self) -> Duration {
+        FeeEstimator::history_window(self)
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::Duration;
+
+use crate::error::{AugurError, Result};
+
+/// Short- and long-term inflow windows for a range of block targets.
+///
+/// Near targets react best to recent inflow, while distant ones need a longer history to
+/// average out. Set on an estimator with
+/// [`with_target_windows`](crate::FeeEstimator::with_target_windows); targets outside every
+/// range keep the estimator's own windows.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::TargetWindows;
+/// use chrono::Duration;
+///
+/// let near = TargetWindows::new(3.0, 6.0, Duration::minutes(15), Duration::hours(6)).unwrap();
+/// assert!(near.contains(6.0));
+/// assert!(!near.contains(12.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetWindows {
+    min_target: f64,
+    max_target: f64,
+    short_term: Duration,
+    long_term: Duration,
+}
+
+impl TargetWindows {
+    /// Creates windows for the block targets from `min_target` to `max_target`, inclusive.
+    ///
+    /// # Errors
+    /// If the range is empty or not finite, or either window is not positive
+    pub fn new(
+        min_target: f64,
+        max_target: f64,
+        short_term: Duration,
+        long_term: Duration,
+    ) -> Result<Self> {
+        if !min_target.is_finite() || !max_target.is_finite() || min_target > max_target {
+            return Err(AugurError::invalid_config(
+                "Target window range must run from a lower to a higher block target",
+            ));
+        }
+        if short_term <= Duration::zero() || long_term <= Duration::zero() {
+            return Err(AugurError::invalid_config(
+                "Inflow windows must be positive",
+            ));
+        }
+
+        Ok(Self {
+            min_target,
+            max_target,
+            short_term,
+            long_term,
+        })
+    }
+
+    /// Smallest block target the windows apply to.
+    pub fn min_target(&self) -> f64 {
+        self.min_target
+    }
+
+    /// Largest block target the windows apply to.
+    pub fn max_target(&self) -> f64 {
+        self.max_target
+    }
+
+    /// Window short-term inflows are measured over.
+    pub fn short_term(&self) -> Duration {
+        self.short_term
+    }
+
+    /// Window long-term inflows are measured over.
+    pub fn long_term(&self) -> Duration {
+        self.long_term
+    }
+
+    /// Whether the windows apply to `target`.
+    pub fn contains(&self, target: f64) -> bool {
+        (self.min_target..=self.max_target).contains(&target)
+    }
+
+    /// Whether the target ranges of `self` and `other` share any target.
+    pub(crate) fn overlaps(&self, other: &Self) -> bool {
+        self.min_target <= other.max_target && other.min_target <= self.max_target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_invalid_windows() {
+        let minutes = Duration::minutes(15);
+        let hours = Duration::hours(6);
+        assert!(TargetWindows::new(6.0, 3.0, minutes, hours).is_err());
+        assert!(TargetWindows::new(3.0, f64::INFINITY, minutes, hours).is_err());
+        assert!(TargetWindows::new(3.0, 6.0, Duration::zero(), hours).is_err());
+        assert!(TargetWindows::new(3.0, 6.0, minutes, -hours).is_err());
+        assert!(TargetWindows::new(3.0, 3.0, minutes, hours).is_ok());
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let window = |min, max| {
+            TargetWindows::new(min, max, Duration::minutes(15), Duration::hours(6)).unwrap()
+        };
+        assert!(window(3.0, 6.0).overlaps(&window(6.0, 12.0)));
+        assert!(window(3.0, 144.0).overlaps(&window(6.0, 12.0)));
+        assert!(!window(3.0, 6.0).overlaps(&window(9.0, 12.0)));
+    }
+}
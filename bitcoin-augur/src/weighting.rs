@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::MempoolSnapshot;
+
+/// What a fee rate bucket's share of the mempool is measured in.
+///
+/// Estimates normally weigh each bucket by the block space its transactions take. Weighting by
+/// value instead gives each bucket a share of the snapshot's weight proportional to the output
+/// value it holds, so a bucket of large payments counts for more than a bucket of dust of the
+/// same size. The total is unchanged, so simulated blocks still mine the same amount per block.
+///
+/// Only transactions with a known [`value`](crate::MempoolTransaction::value) contribute to
+/// value weighting; snapshots carrying no values at all keep their weights.
+///
+/// # Example
+/// ```
+/// use bitcoin_augur::{BucketWeighting, MempoolSnapshot, MempoolTransaction};
+/// use chrono::Utc;
+///
+/// let transactions = vec![
+///     MempoolTransaction::new(400, 4000).with_value(9_000_000), // 40 sat/vB
+///     MempoolTransaction::new(400, 400).with_value(1_000_000),  // 4 sat/vB
+/// ];
+/// let snapshot = MempoolSnapshot::from_transactions(transactions, 850_000, Utc::now());
+///
+/// let weighted = BucketWeighting::Value.apply(&snapshot);
+/// assert_eq!(weighted.total_weight(), 800);
+/// assert_eq!(weighted.bucketed_weights.values().max(), Some(&720));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketWeighting {
+    /// Buckets weigh the block space their transactions take
+    #[default]
+    Weight,
+    /// Buckets weigh the output value their transactions carry
+    Value,
+}
+
+impl BucketWeighting {
+    /// Returns `snapshot` with its bucket weights measured as this weighting measures them.
+    pub fn apply(self, snapshot: &MempoolSnapshot) -> MempoolSnapshot {
+        let total_value = snapshot.total_value();
+        if self == Self::Weight || total_value == 0 {
+            return snapshot.clone();
+        }
+
+        let total_weight = u128::from(snapshot.total_weight());
+        let bucketed_weights: BTreeMap<i32, u64> = snapshot
+            .bucketed_values
+            .iter()
+            .map(|(&bucket, &value)| {
+                let share = total_weight * u128::from(value) / u128::from(total_value);
+                (bucket, share as u64)
+            })
+            .filter(|&(_, weight)| weight > 0)
+            .collect();
+        // Aged and deferred weights are block space, which value-weighted buckets no longer
+        // measure
+        MempoolSnapshot {
+            bucketed_weights,
+            bucketed_aged_weights: BTreeMap::new(),
+            bucketed_deferred_weights: BTreeMap::new(),
+            ..snapshot.clone()
+        }
+    }
+}
+
+impl fmt::Display for BucketWeighting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Weight => "weight",
+            Self::Value => "value",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MempoolTransaction;
+    use chrono::Utc;
+
+    #[test]
+    fn test_value_weighting_redistributes_weight() {
+        let transactions = vec![
+            MempoolTransaction::new(4_000, 40_000).with_value(100),
+            MempoolTransaction::new(4_000, 4_000).with_value(300),
+            // Without a value, this weight is shared among the buckets that have one
+            MempoolTransaction::new(8_000, 2_000),
+        ];
+        let snapshot = MempoolSnapshot::from_transactions(transactions, 850_000, Utc::now());
+
+        let weighted = BucketWeighting::Value.apply(&snapshot);
+        assert_eq!(weighted.total_weight(), 16_000);
+        assert_eq!(
+            weighted
+                .bucketed_weights
+                .values()
+                .copied()
+                .collect::<Vec<_>>(),
+            vec![12_000, 4_000]
+        );
+        assert_eq!(weighted.bucketed_values, snapshot.bucketed_values);
+
+        let unchanged = BucketWeighting::Weight.apply(&snapshot);
+        assert_eq!(unchanged.bucketed_weights, snapshot.bucketed_weights);
+    }
+
+    #[test]
+    fn test_snapshot_without_values_keeps_weights() {
+        let transactions = vec![MempoolTransaction::new(4_000, 4_000)];
+        let snapshot = MempoolSnapshot::from_transactions(transactions, 850_000, Utc::now());
+
+        let weighted = BucketWeighting::Value.apply(&snapshot);
+        assert_eq!(weighted.bucketed_weights, snapshot.bucketed_weights);
+    }
+}
@@ -0,0 +1,66 @@
+//! Helpers shared by the library's integration tests
+//!
+//! The trace corpus is read from the regression test crate's `traces/` directory.
+
+use bitcoin_augur::MempoolSnapshot;
+use std::path::{Path, PathBuf};
+
+/// Relative tolerance when comparing fee rates with recorded expectations
+const TOLERANCE: f64 = 0.001;
+
+#[allow(dead_code)]
+pub fn assert_fee_rate_close(expected: f64, actual: f64, message: &str) {
+    let diff = (expected - actual).abs();
+    let relative_diff = if expected != 0.0 {
+        diff / expected.abs()
+    } else {
+        diff
+    };
+
+    assert!(
+        relative_diff < TOLERANCE,
+        "{message}: expected {expected:.2}, got {actual:.2} (diff: {percent:.4}%)",
+        percent = relative_diff * 100.0
+    );
+}
+
+/// Directory of recorded traces shared with the regression tests
+#[allow(dead_code)]
+pub fn trace_corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../bitcoin-augur-regression-tests/traces")
+}
+
+/// Every trace of the corpus by file name, each sorted by time
+#[allow(dead_code)]
+pub fn trace_corpus() -> Vec<(String, Vec<MempoolSnapshot>)> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(trace_corpus_dir())
+        .expect("trace corpus directory")
+        .map(|entry| entry.expect("trace corpus entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            (name, load_trace(&path))
+        })
+        .collect()
+}
+
+/// Reads a trace of one JSON snapshot per line, as written by the server's `--record-trace`
+#[allow(dead_code)]
+pub fn load_trace(path: &Path) -> Vec<MempoolSnapshot> {
+    let content = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read trace {path:?}: {e}"));
+    let mut snapshots: Vec<MempoolSnapshot> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("Invalid snapshot in {path:?}: {e}"))
+        })
+        .collect();
+    snapshots.sort_by_key(|s| s.timestamp);
+    snapshots
+}
@@ -4,29 +4,6 @@ use bitcoin_augur::{FeeEstimator, Result};
 use chrono::{DateTime, Utc};
 use test_utils::TestUtils;
 
-/// Note: These are currently unused but will be needed when we have exact test vectors
-#[allow(dead_code)]
-const TOLERANCE: f64 = 0.001;
-
-#[allow(dead_code)]
-fn assert_fee_rate_close(expected: f64, actual: f64, message: &str) {
-    let diff = (expected - actual).abs();
-    let relative_diff = if expected != 0.0 {
-        diff / expected.abs()
-    } else {
-        diff
-    };
-
-    assert!(
-        relative_diff < TOLERANCE,
-        "{}: expected {:.2}, got {:.2} (diff: {:.4}%)",
-        message,
-        expected,
-        actual,
-        relative_diff * 100.0
-    );
-}
-
 #[test]
 fn kotlin_parity_empty_snapshot_list_returns_null_estimates() -> Result<()> {
     // Matches Kotlin: FeeEstimatorTest."test empty snapshot list returns null estimates"
@@ -371,6 +348,7 @@ fn kotlin_parity_num_blocks_parameter() -> Result<()> {
 }
 
 #[test]
+#[allow(deprecated)]
 fn kotlin_parity_get_nearest_block_target() -> Result<()> {
     // Matches Kotlin: FeeEstimatorTest."test getNearestBlockTarget"
     let custom_targets = vec![3.0, 6.0, 24.0, 144.0];
@@ -438,8 +416,8 @@ fn kotlin_parity_block_target_get_fee_rate() -> Result<()> {
         for probability in &[0.05, 0.20, 0.50, 0.80, 0.95] {
             let fee_rate = block_target.get_fee_rate(*probability);
             // Should either return Some value or None if not available
-            if fee_rate.is_some() {
-                assert!(fee_rate.unwrap() >= 0.0, "Fee rate should be non-negative");
+            if let Some(fee_rate) = fee_rate {
+                assert!(fee_rate >= 0.0, "Fee rate should be non-negative");
             }
         }
 
@@ -476,8 +454,7 @@ fn kotlin_parity_available_targets_and_confidence_levels() -> Result<()> {
     // Test that confidence levels are consistent across all targets
     // (In Rust, probabilities are fixed per estimator, not per estimate)
     for target in estimate.estimates.values() {
-        let mut available_probabilities: Vec<f64> =
-            target.probabilities.keys().map(|k| k.0).collect();
+        let mut available_probabilities: Vec<f64> = target.confidence_levels().collect();
         available_probabilities.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
         // Should have probabilities in ascending order
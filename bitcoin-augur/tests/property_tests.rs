@@ -4,6 +4,9 @@
 //! regardless of the input data, ensuring the implementation behaves
 //! correctly across all edge cases.
 
+// The estimator is configured with whole block targets only
+#![allow(deprecated)]
+
 use bitcoin_augur::{FeeEstimator, MempoolSnapshot, MempoolTransaction};
 use chrono::{Duration, Utc};
 use proptest::prelude::*;
@@ -164,6 +167,33 @@ proptest! {
         }
     }
 
+    /// Test that the 3-block/95% estimate never exceeds the top of the mempool
+    ///
+    /// The simulation settles one bucket above the highest bucket it could not mine, so the
+    /// estimate can be at most one bucket width above the highest occupied bucket.
+    #[test]
+    fn test_fee_ceiling(snapshots in snapshot_sequence_strategy()) {
+        let estimator = FeeEstimator::new();
+        let estimates = estimator.calculate_estimates(&snapshots, None).unwrap();
+
+        let highest_bucket = snapshots
+            .iter()
+            .flat_map(|s| s.bucketed_weights.keys())
+            .filter(|&&bucket| bucket >= 0)
+            .max()
+            .map(|&bucket| bucket.min(MAX_FEE_RATE as i32));
+
+        if let Some(fee_rate) = estimates.get_fee_rate(3, 0.95) {
+            let highest_bucket = highest_bucket.expect("Estimate without occupied buckets");
+            let ceiling = ((highest_bucket + 1) as f64 / 100.0).exp();
+            prop_assert!(
+                fee_rate <= ceiling * (1.0 + 1e-9),
+                "3-block/95% estimate {} exceeds the ceiling {} of bucket {}",
+                fee_rate, ceiling, highest_bucket
+            );
+        }
+    }
+
     /// Test determinism: same input produces same output
     #[test]
     fn test_determinism(snapshots in snapshot_sequence_strategy()) {
@@ -0,0 +1,53 @@
+//! The bundled regression vectors, whose expected values this implementation generated
+//!
+//! They catch changes to the estimates, not divergence from the Kotlin reference.
+
+mod helpers;
+
+use bitcoin_augur::{BlockTargetKey, FeeEstimator, MempoolSnapshot, Result};
+use helpers::assert_fee_rate_close;
+use std::collections::BTreeMap;
+
+const REGRESSION_VECTORS: &str =
+    include_str!("../../bitcoin-augur-regression-tests/vectors/regression_v1.json");
+
+#[test]
+fn bundled_regression_vectors_still_match() -> Result<()> {
+    #[derive(serde::Deserialize)]
+    struct Vector {
+        name: String,
+        snapshots: Vec<MempoolSnapshot>,
+        num_blocks: Option<f64>,
+        expected: BTreeMap<String, BTreeMap<String, Option<f64>>>,
+    }
+    #[derive(serde::Deserialize)]
+    struct VectorFile {
+        vectors: Vec<Vector>,
+    }
+
+    let file: VectorFile = serde_json::from_str(REGRESSION_VECTORS).unwrap();
+    let estimator = FeeEstimator::new();
+    for vector in &file.vectors {
+        let estimate = estimator.calculate_estimates(&vector.snapshots, vector.num_blocks)?;
+        for (blocks, probabilities) in &vector.expected {
+            for (probability, &expected) in probabilities {
+                let target = BlockTargetKey::try_from(blocks.parse::<f64>().unwrap())?;
+                let actual = estimate.get_target_fee_rate(target, probability.parse().unwrap());
+                match (expected, actual) {
+                    (Some(expected), Some(actual)) => assert_fee_rate_close(
+                        expected,
+                        actual,
+                        &format!("{} {blocks} blocks @ {probability}", vector.name),
+                    ),
+                    _ => assert_eq!(
+                        expected, actual,
+                        "{} {blocks} blocks @ {probability}",
+                        vector.name
+                    ),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! Estimates over the recorded trace corpus
+//!
+//! Every trace in the regression tests' `traces/` directory is replayed window by window, so a
+//! scenario recorded with the server's `--record-trace` option is checked as soon as it is added.
+
+mod helpers;
+
+use bitcoin_augur::FeeEstimator;
+use helpers::trace_corpus;
+
+/// Snapshots in each replayed estimation window
+const WINDOW: usize = 12;
+
+#[test]
+fn trace_corpus_estimates_are_ordered() {
+    let corpus = trace_corpus();
+    assert!(!corpus.is_empty(), "The trace corpus is empty");
+
+    let estimator = FeeEstimator::new();
+    for (name, trace) in corpus {
+        for end in (1..=trace.len()).step_by(WINDOW / 2) {
+            let window = &trace[end.saturating_sub(WINDOW)..end];
+            let estimate = estimator.calculate_estimates(window, None).unwrap();
+
+            for target in estimate.estimates.values() {
+                let rates: Vec<f64> = target.probabilities.values().copied().collect();
+                assert!(
+                    rates.iter().all(|rate| rate.is_finite() && *rate >= 1.0),
+                    "{name} at snapshot {end}: invalid fee rates {rates:?}"
+                );
+                assert!(
+                    rates.windows(2).all(|pair| pair[0] <= pair[1]),
+                    "{name} at snapshot {end}: fee rates fall with confidence {rates:?}"
+                );
+            }
+
+            // Waiting longer never costs more
+            for probability in [0.05, 0.5, 0.95] {
+                let rates: Vec<f64> = estimate
+                    .estimates
+                    .keys()
+                    .filter_map(|blocks| estimate.get_fee_rate(*blocks, probability))
+                    .collect();
+                assert!(
+                    rates.windows(2).all(|pair| pair[0] >= pair[1]),
+                    "{name} at snapshot {end}: {probability} fee rates rise with blocks {rates:?}"
+                );
+            }
+        }
+    }
+}
@@ -120,6 +120,7 @@
               cargo build \
                 --release \
                 --target x86_64-unknown-linux-musl \
+                --features bitcoin-augur-server/dashboard \
                 --offline \
                 -j $NIX_BUILD_CORES
               
@@ -0,0 +1,53 @@
+#![no_main]
+
+use std::collections::BTreeMap;
+
+use arbitrary::Arbitrary;
+use bitcoin_augur::{FeeEstimator, MempoolSnapshot};
+use chrono::DateTime;
+use libfuzzer_sys::fuzz_target;
+
+/// A snapshot as it could be read back from disk: any bucket keys, any weights
+#[derive(Debug, Arbitrary)]
+struct FuzzSnapshot {
+    block_height: u32,
+    timestamp_secs: i64,
+    buckets: BTreeMap<i32, u64>,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    snapshots: Vec<FuzzSnapshot>,
+    /// Block target in the range the API accepts; larger targets only cost time
+    num_blocks: Option<u16>,
+}
+
+fuzz_target!(|input: Input| {
+    let snapshots: Vec<MempoolSnapshot> = input
+        .snapshots
+        .into_iter()
+        .take(200)
+        .map(|s| {
+            let timestamp = DateTime::from_timestamp(s.timestamp_secs, 0).unwrap_or_default();
+            MempoolSnapshot::new(s.block_height, timestamp, s.buckets)
+        })
+        .collect();
+
+    let num_blocks = input
+        .num_blocks
+        .map(|blocks| f64::from(blocks % 1000).max(3.0));
+
+    let estimator = FeeEstimator::new();
+    let estimate = estimator
+        .calculate_estimates(&snapshots, num_blocks)
+        .expect("valid block targets never fail");
+
+    for target in estimate.estimates.values() {
+        for &fee_rate in target.probabilities.values() {
+            assert!(
+                fee_rate.is_finite() && fee_rate > 0.0,
+                "invalid fee rate {fee_rate}"
+            );
+        }
+    }
+});
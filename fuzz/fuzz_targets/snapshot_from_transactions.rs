@@ -0,0 +1,32 @@
+#![no_main]
+
+use bitcoin_augur::{MempoolSnapshot, MempoolTransaction, BUCKET_MAX};
+use chrono::DateTime;
+use libfuzzer_sys::fuzz_target;
+
+// Unconstrained weights and fees, exactly as a (possibly misbehaving) node could report them
+fuzz_target!(|input: (Vec<(u64, u64)>, u32, i64)| {
+    let (pairs, block_height, timestamp) = input;
+    let timestamp = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+
+    let transactions: Vec<MempoolTransaction> = pairs
+        .iter()
+        .map(|&(weight, fee)| MempoolTransaction::new(weight, fee))
+        .collect();
+
+    let snapshot = MempoolSnapshot::from_transactions(transactions.clone(), block_height, timestamp);
+
+    assert_eq!(snapshot.block_height, block_height);
+    for &bucket in snapshot.bucketed_weights.keys() {
+        assert!(bucket <= BUCKET_MAX, "bucket {bucket} above cap");
+    }
+
+    // Only transactions paying a positive fee rate are bucketed
+    let any_paying = transactions.iter().any(|tx| tx.fee_rate() > 0.0);
+    assert_eq!(any_paying, !snapshot.bucketed_weights.is_empty());
+
+    // The JSON form round-trips
+    let json = serde_json::to_string(&snapshot).expect("snapshot serializes");
+    let parsed: MempoolSnapshot = serde_json::from_str(&json).expect("snapshot deserializes");
+    assert_eq!(parsed.bucketed_weights, snapshot.bucketed_weights);
+});
@@ -1,12 +1,15 @@
 #![no_main]
 
 use libfuzzer_sys::fuzz_target;
-use bitcoin_augur::MempoolSnapshot;
+use bitcoin_augur::{FeeEstimator, MempoolSnapshot};
 
 fuzz_target!(|data: &[u8]| {
     // Try to parse arbitrary data as JSON snapshot
     if let Ok(s) = std::str::from_utf8(data) {
-        let _ = serde_json::from_str::<MempoolSnapshot>(s);
+        if let Ok(snapshot) = serde_json::from_str::<MempoolSnapshot>(s) {
+            // Whatever deserializes must also be safe to estimate from
+            let _ = FeeEstimator::new().calculate_estimates(&[snapshot], None);
+        }
     }
     
     // Also test with valid JSON structure but fuzzed values
@@ -24,7 +27,7 @@ fuzz_target!(|data: &[u8]| {
             r#"{{
                 "block_height": {},
                 "timestamp": {},
-                "fee_levels": {{}}
+                "bucketed_weights": {{}}
             }}"#,
             block_height,
             timestamp
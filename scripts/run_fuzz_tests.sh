@@ -16,6 +16,8 @@ TARGETS=(
     "snapshot_parsing"
     "api_input_validation"
     "rpc_response_parsing"
+    "snapshot_from_transactions"
+    "estimate_bucket_maps"
 )
 
 # Function to run a single fuzz target